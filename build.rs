@@ -0,0 +1,14 @@
+//! Build script: sets `cfg(hardware)` when the panel drivers can talk to
+//! real hardware, i.e. with the `hardware` feature on an ARM target (the
+//! Raspberry Pi). Elsewhere `rppal` is not a dependency and the display
+//! falls back to the mock backend.
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(hardware)");
+
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let feature = std::env::var_os("CARGO_FEATURE_HARDWARE").is_some();
+    if feature && matches!(arch.as_str(), "arm" | "aarch64") {
+        println!("cargo::rustc-cfg=hardware");
+    }
+}
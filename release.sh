@@ -162,7 +162,7 @@ update_cargo_version() {
 build_release() {
     log_info "Building release binary for ${TARGET}..."
     if [[ "$DRY_RUN" == "true" ]]; then
-        log_info "[DRY-RUN] Would run: cargo build --release --target ${TARGET}"; return
+        log_info "[DRY-RUN] Would run: cargo build --release --target ${TARGET} --features full"; return
     fi
 
     # Source cargo environment if needed
@@ -170,7 +170,7 @@ build_release() {
         source "$HOME/.cargo/env"
     fi
 
-    cargo build --release --target "${TARGET}"
+    cargo build --release --target "${TARGET}" --features full
     log_success "Build completed"
 }
 
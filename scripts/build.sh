@@ -40,8 +40,8 @@ fi
 
 cd "$PROJECT_DIR"
 
-# Build options
-BUILD_OPTS="--release --target $TARGET"
+# Build options; set FEATURES (e.g. FEATURES=hardware,tls) for a smaller binary
+BUILD_OPTS="--release --target $TARGET --features ${FEATURES:-full}"
 
 echo "Building..."
 cargo build $BUILD_OPTS
@@ -0,0 +1,183 @@
+//! Content arbitration.
+//!
+//! The panel normally shows the scheduled source. Other content can
+//! override it for a while: a kiosk message posted by hand, or an alert
+//! from a monitoring system. Each kind has a priority, and the arbiter
+//! keeps one override per priority and decides what the panel shows:
+//!
+//! alert > manual override > scheduled source
+//!
+//! An alert preempts a message, and the message (if it has not expired
+//! meanwhile) returns once the alert is cleared. Without any override the
+//! schedule resumes.
+
+use crate::config::Config;
+use crate::sources::SourceConfig;
+use crate::status::StatusTracker;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Priority of an override, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Kiosk message posted by hand
+    Manual,
+    /// Alert from a monitoring system, held until it is cleared
+    Alert,
+}
+
+/// Content shown instead of the scheduled source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub priority: Priority,
+    /// What is shown, for logs
+    pub label: String,
+    /// Source rendering the content
+    pub source: SourceConfig,
+    /// Local time the override ends by itself (None = until cleared)
+    pub until: Option<DateTime<Local>>,
+}
+
+impl Override {
+    fn expired(&self, now: DateTime<Local>) -> bool {
+        self.until.is_some_and(|until| until <= now)
+    }
+
+    /// Time left until the override ends by itself
+    pub fn remaining(&self) -> Option<Duration> {
+        self.until
+            .map(|until| (until - Local::now()).to_std().unwrap_or_default())
+    }
+
+    /// `config` with the override as its source
+    ///
+    /// Scheduled refreshes skip redrawing the override if the panel
+    /// already shows it.
+    pub fn content_config(&self, config: &Config) -> Config {
+        Config {
+            skip_similar_distance: Some(config.skip_similar_distance.unwrap_or(0)),
+            source: self.source.clone(),
+            ..config.clone()
+        }
+    }
+}
+
+/// Decides between the scheduled source and the active overrides
+#[derive(Debug, Default)]
+pub struct ContentArbiter {
+    overrides: Mutex<BTreeMap<Priority, Override>>,
+    /// Wakes the scheduler so it reverts when a new override expires
+    changed: Notify,
+    /// Receives the priority and expiry of the active override
+    status: Option<Arc<StatusTracker>>,
+}
+
+impl ContentArbiter {
+    /// Report the active override to `status`
+    pub fn with_status(mut self, status: Arc<StatusTracker>) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Set the override for its priority, replacing an earlier one
+    ///
+    /// Returns whether it is the one shown now (no higher priority active).
+    pub fn set(&self, content: Override) -> bool {
+        let label = content.label.clone();
+        let shown = self.modify(content.priority, |_| Some(content));
+        if shown {
+            tracing::info!("Showing {}", label);
+        } else {
+            tracing::info!(
+                "Holding {} back, a higher priority override is shown",
+                label
+            );
+        }
+        shown
+    }
+
+    /// Remove the override of `priority`; returns false if there is none
+    pub fn clear(&self, priority: Priority) -> bool {
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        self.update(&mut overrides);
+        let Some(removed) = overrides.remove(&priority) else {
+            return false;
+        };
+        tracing::info!("Removed {}", removed.label);
+        self.update(&mut overrides);
+        self.changed.notify_waiters();
+        true
+    }
+
+    /// Replace the override of `priority` with `f` of the current one
+    ///
+    /// `f` returning None removes it. Returns whether the new override is
+    /// the one shown now.
+    pub fn modify(
+        &self,
+        priority: Priority,
+        f: impl FnOnce(Option<&Override>) -> Option<Override>,
+    ) -> bool {
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        self.update(&mut overrides);
+        match f(overrides.get(&priority)) {
+            Some(content) => {
+                overrides.insert(priority, content);
+            }
+            None => {
+                if let Some(removed) = overrides.remove(&priority) {
+                    tracing::info!("Removed {}", removed.label);
+                }
+            }
+        }
+        let shown = self
+            .update(&mut overrides)
+            .is_some_and(|o| o.priority == priority);
+        self.changed.notify_waiters();
+        shown
+    }
+
+    /// Override the panel should show, if any
+    pub fn active(&self) -> Option<Override> {
+        let mut overrides = self.overrides.lock().unwrap_or_else(|e| e.into_inner());
+        self.update(&mut overrides)
+    }
+
+    /// `config` with the source of the active override, if any, else the
+    /// scheduled content
+    pub fn content_config<'a>(&self, config: &'a Config) -> Cow<'a, Config> {
+        match self.active() {
+            Some(content) => Cow::Owned(content.content_config(config)),
+            None => config.scheduled_content(),
+        }
+    }
+
+    /// Wait until an override is set or cleared
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    /// Drop expired overrides and report the active one
+    fn update(&self, overrides: &mut BTreeMap<Priority, Override>) -> Option<Override> {
+        let now = Local::now();
+        overrides.retain(|_, content| {
+            let expired = content.expired(now);
+            if expired {
+                tracing::info!("End of {}", content.label);
+            }
+            !expired
+        });
+
+        let active = overrides.values().next_back().cloned();
+        if let Some(status) = &self.status {
+            status.set_override(active.as_ref().map(|o| (o.priority, o.until)));
+        }
+        active
+    }
+}
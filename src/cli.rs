@@ -0,0 +1,177 @@
+//! Client subcommands.
+//!
+//! Subcommands like `epaper-display message` talk to the server already
+//! running on the device through its JSON API, since only the server may
+//! drive the panel. The port and, with `auth.users`, the credentials of
+//! the first admin account are taken from the config file.
+//!
+//! `epaper-display push --host frame.local` sends to another device
+//! instead; its account is given with `--user` and the password in
+//! `EPAPER_PASSWORD`.
+
+use crate::config::Config;
+use crate::secrets;
+use crate::web::auth::Role;
+use anyhow::{Context, bail};
+use reqwest::Method;
+use std::time::Duration;
+
+/// Longest wait for the server; covers a full panel refresh
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Environment variable holding the password for `--user`
+const PASSWORD_VAR: &str = "EPAPER_PASSWORD";
+
+/// Account to sign in to another device with
+pub struct Login {
+    pub username: String,
+    pub password: String,
+}
+
+impl Login {
+    /// `username` with the password from `EPAPER_PASSWORD`
+    pub fn from_env(username: &str) -> anyhow::Result<Self> {
+        let password = std::env::var(PASSWORD_VAR)
+            .with_context(|| format!("Set {} to the password of '{}'", PASSWORD_VAR, username))?;
+        Ok(Self {
+            username: username.to_string(),
+            password,
+        })
+    }
+}
+
+/// Show `text` on the panel of the local server via `POST /api/v1/message`
+pub async fn post_message(
+    config: &Config,
+    port: u16,
+    text: &str,
+    duration_min: Option<u32>,
+) -> anyhow::Result<()> {
+    let body = serde_json::json!({ "text": text, "duration_min": duration_min });
+    let response = send(config, port, Method::POST, "/api/v1/message", Some(body)).await?;
+
+    let until = response["until"].as_str().unwrap_or_default();
+    let until = chrono::DateTime::parse_from_rfc3339(until)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| until.to_string());
+    if response["shown"].as_bool().unwrap_or(true) {
+        println!("Message shown until {}", until);
+    } else {
+        println!(
+            "An alert is shown; the message follows once it is cleared (until {})",
+            until
+        );
+    }
+    Ok(())
+}
+
+/// Remove the message and resume the schedule via `DELETE /api/v1/message`
+pub async fn cancel_message(config: &Config, port: u16) -> anyhow::Result<()> {
+    let response = send(config, port, Method::DELETE, "/api/v1/message", None).await?;
+    println!(
+        "{}",
+        response["message"].as_str().unwrap_or("Schedule resumed")
+    );
+    Ok(())
+}
+
+/// Show the image file at `path` on the panel of `host` via
+/// `POST /api/v1/image`
+///
+/// `host` is a host name or address, with `:port` unless the device
+/// listens on `port`. Without a `login`, the local config's admin
+/// account is used, if any.
+pub async fn push_image(
+    config: &Config,
+    host: &str,
+    port: u16,
+    path: &str,
+    duration_min: Option<u32>,
+    login: Option<Login>,
+) -> anyhow::Result<()> {
+    let image = std::fs::read(path).with_context(|| format!("Cannot read {}", path))?;
+    let host = host.trim().trim_end_matches('/');
+    let base = match host.split_once("://") {
+        Some(_) => host.to_string(),
+        None if host.contains(':') => format!("http://{}", host),
+        None => format!("http://{}:{}", host, port),
+    };
+    let mut url = format!("{}/api/v1/image", base);
+    if let Some(duration_min) = duration_min {
+        url.push_str(&format!("?duration_min={}", duration_min));
+    }
+
+    let request = reqwest::Client::new()
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(image);
+    let request = match login {
+        Some(login) => request.basic_auth(login.username, Some(login.password)),
+        None => sign_in(request, config)?,
+    };
+    let response = finish(request, &url).await?;
+
+    let until = response["until"].as_str().unwrap_or_default();
+    let until = chrono::DateTime::parse_from_rfc3339(until)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| until.to_string());
+    if response["shown"].as_bool().unwrap_or(true) {
+        println!("{} shown on {} until {}", path, host, until);
+    } else {
+        println!(
+            "An alert is shown on {}; the image follows once it is cleared (until {})",
+            host, until
+        );
+    }
+    Ok(())
+}
+
+/// Send a request to the local server and return the JSON response
+async fn send(
+    config: &Config,
+    port: u16,
+    method: Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> anyhow::Result<serde_json::Value> {
+    let url = format!("http://127.0.0.1:{}{}", port, path);
+    let mut request = reqwest::Client::new().request(method, &url);
+    if let Some(body) = body {
+        request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+    }
+    finish(sign_in(request, config)?, &url).await
+}
+
+/// Add the credentials of the config's first admin account, if any
+fn sign_in(
+    request: reqwest::RequestBuilder,
+    config: &Config,
+) -> anyhow::Result<reqwest::RequestBuilder> {
+    let Some(account) = config.auth.users.iter().find(|a| a.role == Role::Admin) else {
+        return Ok(request);
+    };
+    let password = secrets::resolve(&account.password)
+        .with_context(|| format!("Password of '{}' unavailable", account.username))?;
+    Ok(request.basic_auth(account.username.trim(), Some(password)))
+}
+
+/// Send `request` to `url` and return the JSON response
+async fn finish(request: reqwest::RequestBuilder, url: &str) -> anyhow::Result<serde_json::Value> {
+    let response = request
+        .timeout(REQUEST_TIMEOUT)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await
+        .with_context(|| format!("Cannot reach the display server at {}", url))?;
+    let status = response.status();
+    let text = response.text().await?;
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+
+    if !status.is_success() {
+        let message = json["message"].as_str().unwrap_or(&text);
+        bail!("{} ({})", message, status);
+    }
+    Ok(json)
+}
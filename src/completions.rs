@@ -0,0 +1,40 @@
+//! Shell completions.
+//!
+//! `epaper-display completions <shell>` prints a completion script for
+//! bash, zsh, fish, elvish or PowerShell, generated by `clap_complete`
+//! from the command line definition so it stays in step with the options:
+//!
+//! ```bash
+//! epaper-display completions bash > ~/.local/share/bash-completion/completions/epaper-display
+//! epaper-display completions zsh > ~/.zfunc/_epaper-display
+//! epaper-display completions fish > ~/.config/fish/completions/epaper-display.fish
+//! ```
+
+use clap::Command;
+pub use clap_complete::Shell;
+
+/// Completion script of `command` for `shell`
+pub fn generate(shell: Shell, mut command: Command) -> String {
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn completions_cover_subcommands_and_files() {
+        let bash = generate(Shell::Bash, crate::Args::command());
+        assert!(bash.contains("epaper__display,render)"));
+        assert!(bash.contains("--spi-trace)"));
+        let zsh = generate(Shell::Zsh, crate::Args::command());
+        assert!(zsh.starts_with("#compdef epaper-display"));
+        assert!(zsh.contains("powershell"));
+        let fish = generate(Shell::Fish, crate::Args::command());
+        assert!(fish.contains("using_subcommand render\" -l in -d 'Image file to render' -r -F"));
+    }
+}
@@ -2,21 +2,58 @@
 //!
 //! Handles loading, saving, and validating configuration from JSON files.
 
+use crate::display::{It8951Config, PanelModel, Spectra6Config, SpiConfig};
+use crate::image_proc::download::{Checksum, DEFAULT_USER_AGENT, DownloadConfig, is_sha256_hex};
+use crate::image_proc::color::ColorProfile;
+use crate::image_proc::photo::PhotoCaption;
+use crate::image_proc::session::SessionConfig;
+use crate::load_guard::LoadGuardConfig;
+use crate::power_save::PowerSaveConfig;
+use crate::mirror::MirrorConfig;
+use crate::notifications::NotificationsConfig;
+use crate::outputs::OutputsConfig;
+use crate::render::theme::ThemeConfig;
+use crate::scheduler::StartupConfig;
+use crate::secrets;
+use crate::sources::SourceConfig;
+use crate::vacation::VacationConfig;
+use crate::web::auth::AuthConfig;
 use chrono::{Datelike, Timelike};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Default configuration file path
-#[allow(dead_code)]
 pub const DEFAULT_CONFIG_PATH: &str = "/opt/epaper-display/config.json";
 
+/// Configuration file path with `--container`, in the config volume
+pub const CONTAINER_CONFIG_PATH: &str = "/config/config.json";
+
+/// Environment variable naming the configuration file
+pub const CONFIG_ENV: &str = "EPAPER_CONFIG";
+
+/// State directory set with `--state-dir`
+static STATE_DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
 /// Type alias for day-of-week to schedule plan name mapping
 pub type DayAssignments = HashMap<Weekday, String>;
 
 /// Days of the week
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    utoipa::ToSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum Weekday {
     Monday,
@@ -84,6 +121,7 @@ impl Weekday {
 
 /// Configuration errors
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ConfigError {
     #[error("Failed to read config file: {0}")]
     ReadError(#[from] std::io::Error),
@@ -95,8 +133,51 @@ pub enum ConfigError {
     ValidationError(String),
 }
 
+/// Longest `device_name`
+const MAX_DEVICE_NAME_LEN: usize = 40;
+
+/// Field names whose values are masked in exports
+const SECRET_FIELDS: [&str; 4] = ["token", "api_key", "password", "secret"];
+
+/// Maps whose values are all masked in exports, whatever their keys
+/// (login forms name the password field `pass`, `pwd`, ...)
+const SECRET_MAPS: [&str; 1] = ["login_form"];
+
+/// Replace non-empty secret string values anywhere in `value`
+///
+/// `${ENV:...}` / `${FILE:...}` references are kept; they hold no secret.
+fn redact_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    serde_json::Value::String(s) if SECRET_FIELDS.contains(&key.as_str()) => {
+                        redact_string(s)
+                    }
+                    serde_json::Value::Object(fields) if SECRET_MAPS.contains(&key.as_str()) => {
+                        for value in fields.values_mut() {
+                            if let serde_json::Value::String(s) = value {
+                                redact_string(s);
+                            }
+                        }
+                    }
+                    other => redact_secrets(other),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+fn redact_string(s: &mut String) {
+    if !s.is_empty() && !secrets::is_reference(s) {
+        *s = secrets::REDACTED.to_string();
+    }
+}
+
 /// A time-based refresh schedule period
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
 pub struct SchedulePeriod {
     /// Start time in HH:MM format (24-hour)
     pub start_time: String,
@@ -191,7 +272,7 @@ impl SchedulePeriod {
 }
 
 /// A named schedule plan containing multiple time periods
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
 pub struct SchedulePlan {
     /// Name of the schedule plan (e.g., "Weekday", "Weekend")
     pub name: String,
@@ -339,6 +420,120 @@ impl SchedulePlan {
     }
 }
 
+/// Ready-made schedule plans to start a new plan from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulePreset {
+    /// Frequent refreshes during the working day, few at night
+    OfficeHours,
+    /// A new picture every few hours while someone is awake to see it
+    PhotoFrame,
+    /// As few refreshes as still make sense, for battery-powered panels
+    BatterySaver,
+}
+
+impl SchedulePreset {
+    /// All presets, in the order they are offered
+    pub fn all() -> &'static [SchedulePreset] {
+        &[
+            SchedulePreset::OfficeHours,
+            SchedulePreset::PhotoFrame,
+            SchedulePreset::BatterySaver,
+        ]
+    }
+
+    /// Name of a plan created from this preset
+    pub fn title(&self) -> &'static str {
+        match self {
+            SchedulePreset::OfficeHours => "Office Hours",
+            SchedulePreset::PhotoFrame => "Photo Frame",
+            SchedulePreset::BatterySaver => "Battery Saver",
+        }
+    }
+
+    /// One-line summary of the periods
+    pub fn description(&self) -> &'static str {
+        match self {
+            SchedulePreset::OfficeHours => {
+                "Every 15 min from 07:00 to 18:00, hourly until 22:00, every 4 h at night"
+            }
+            SchedulePreset::PhotoFrame => "Every 2 h from 07:00 to 23:00, none at night",
+            SchedulePreset::BatterySaver => "Every 3 h from 06:00 to 22:00, none at night",
+        }
+    }
+
+    /// Periods of the preset, covering the whole day
+    pub fn periods(&self) -> Vec<SchedulePeriod> {
+        match self {
+            SchedulePreset::OfficeHours => vec![
+                SchedulePeriod::new("07:00", "18:00", 15),
+                SchedulePeriod::new("18:00", "22:00", 60),
+                SchedulePeriod::new("22:00", "07:00", 240),
+            ],
+            SchedulePreset::PhotoFrame => vec![
+                SchedulePeriod::new("07:00", "23:00", 120),
+                SchedulePeriod::new("23:00", "07:00", 480),
+            ],
+            SchedulePreset::BatterySaver => vec![
+                SchedulePeriod::new("06:00", "22:00", 180),
+                SchedulePeriod::new("22:00", "06:00", 480),
+            ],
+        }
+    }
+
+    /// A plan with the periods of this preset
+    pub fn plan(&self, name: &str) -> SchedulePlan {
+        SchedulePlan::new(name, self.periods())
+    }
+}
+
+/// Day assignments for every other week (A/B weeks)
+///
+/// In B weeks the days listed here use their own plan; the other days,
+/// and every day of A weeks, follow `day_assignments`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlternateWeeks {
+    /// Which weeks are B weeks: `odd` or `even` ISO week numbers, or a date
+    /// (YYYY-MM-DD) in a B week, alternating from there
+    pub b_weeks: String,
+    /// Plans of the days that differ in B weeks
+    #[serde(default)]
+    pub day_assignments: DayAssignments,
+}
+
+impl AlternateWeeks {
+    /// Validate the week selection; plan names are checked with the plans
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self.b_weeks.as_str() {
+            "odd" | "even" => Ok(()),
+            date if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => Ok(()),
+            other => Err(ConfigError::ValidationError(format!(
+                "alternate_weeks.b_weeks must be odd, even or a date (YYYY-MM-DD), not '{}'",
+                other
+            ))),
+        }
+    }
+
+    /// Whether `date` lies in a B week
+    ///
+    /// ISO week parity repeats a week at the turn of years with 53 weeks;
+    /// counting from a date keeps alternating.
+    pub fn is_b_week(&self, date: chrono::NaiveDate) -> bool {
+        match self.b_weeks.as_str() {
+            "odd" => !date.iso_week().week().is_multiple_of(2),
+            "even" => date.iso_week().week().is_multiple_of(2),
+            anchor => chrono::NaiveDate::parse_from_str(anchor, "%Y-%m-%d")
+                .map(|anchor| {
+                    let monday = |d: chrono::NaiveDate| {
+                        d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64)
+                    };
+                    ((monday(date) - monday(anchor)).num_days() / 7) % 2 == 0
+                })
+                .unwrap_or(false),
+        }
+    }
+}
+
 /// Default schedule plans
 fn default_schedule_plans() -> Vec<SchedulePlan> {
     vec![SchedulePlan::default_plan()]
@@ -356,10 +551,37 @@ fn default_day_assignments() -> DayAssignments {
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Friendly name telling displays apart, e.g. "Kitchen" (empty = host
+    /// name)
+    #[serde(default)]
+    pub device_name: String,
+
     /// URL of the image to display
     #[serde(default)]
     pub image_url: String,
 
+    /// Expected SHA-256 of the image at `image_url` (hex); a different
+    /// image is not displayed
+    #[serde(default)]
+    pub image_sha256: String,
+
+    /// URL of a `.sha256` sidecar file with the expected SHA-256 of the
+    /// image at `image_url`, for images that change
+    #[serde(default)]
+    pub image_sha256_url: String,
+
+    /// Content source (defaults to downloading `image_url`)
+    #[serde(default)]
+    pub source: SourceConfig,
+
+    /// Colors and text scale for rendered sources
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    /// Web UI accounts (none = open UI)
+    #[serde(default)]
+    pub auth: AuthConfig,
+
     /// Legacy: Refresh interval in minutes (for backward compatibility)
     /// Will be migrated to schedule_plans on load
     #[serde(default, skip_serializing)]
@@ -378,6 +600,15 @@ pub struct Config {
     #[serde(default = "default_day_assignments")]
     pub day_assignments: DayAssignments,
 
+    /// Different day assignments for every other week (unset = every week
+    /// the same)
+    #[serde(default)]
+    pub alternate_weeks: Option<AlternateWeeks>,
+
+    /// Vacation mode: one plan for every day and an optional static image
+    #[serde(default)]
+    pub vacation: VacationConfig,
+
     /// Display rotation in degrees (0, 90, 180, 270)
     #[serde(default)]
     pub rotation: u16,
@@ -394,10 +625,52 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub scale_to_fit: bool,
 
+    /// Without `scale_to_fit`, crop around the most detailed part of the
+    /// image (faces, subjects) instead of its center
+    #[serde(default)]
+    pub smart_crop: bool,
+
     /// Apply rotation before mirroring (true) or mirror before rotating (false)
     #[serde(default = "default_true")]
     pub rotate_first: bool,
 
+    /// Rotate and mirror the dithered frame instead of the source image
+    ///
+    /// Cheaper on the Pi Zero, and the last frame can be turned to a new
+    /// orientation without fetching and dithering it again.
+    #[serde(default)]
+    pub rotate_packed: bool,
+
+    /// Color adjustments before dithering, e.g. for color blindness
+    #[serde(default)]
+    pub color_profile: ColorProfile,
+
+    /// Pixels within this many levels of black or white are shown as pure
+    /// black or white instead of being dithered (0 = off)
+    #[serde(default)]
+    pub snap_threshold: u8,
+
+    /// Panel model attached to the Pi (takes effect on restart)
+    #[serde(default)]
+    pub panel: PanelModel,
+
+    /// IT8951 panel settings (takes effect on restart)
+    #[serde(default)]
+    pub it8951: It8951Config,
+
+    /// Spectra 6 panel settings (takes effect on restart)
+    #[serde(default)]
+    pub spectra6: Spectra6Config,
+
+    /// SPI transfer settings (takes effect on restart)
+    #[serde(default)]
+    pub spi: SpiConfig,
+
+    /// Full refreshes after which the panel is powered off and initialized
+    /// again before the next one (0 = only at startup)
+    #[serde(default)]
+    pub reinit_every: u32,
+
     /// Display width in pixels
     #[serde(default = "default_display_width")]
     pub display_width: u32,
@@ -413,6 +686,215 @@ pub struct Config {
     /// Enable verbose logging
     #[serde(default)]
     pub verbose: bool,
+
+    /// Minutes between lightweight source health checks (0 = disabled)
+    ///
+    /// Checks run between refreshes and only update status; they never
+    /// trigger a panel refresh.
+    #[serde(default = "default_health_check_interval_min")]
+    pub health_check_interval_min: u32,
+
+    /// Steps before the first refresh (wait for network and clock)
+    #[serde(default)]
+    pub startup: StartupConfig,
+
+    /// Limits on system load and temperature for scheduled refreshes
+    #[serde(default)]
+    pub load_guard: LoadGuardConfig,
+
+    /// Power off between refreshes, woken by the RTC of a photo frame HAT
+    #[serde(default)]
+    pub power_save: PowerSaveConfig,
+
+    /// Auxiliary GPIO outputs (frontlight, LED strip) switched on a schedule
+    #[serde(default)]
+    pub outputs: OutputsConfig,
+
+    /// Seconds between network probes (0 = disabled)
+    ///
+    /// The default route and DNS resolution of the image source are
+    /// checked between refreshes. When the network comes back after an
+    /// outage, the display is refreshed right away.
+    #[serde(default = "default_network_check_interval_secs")]
+    pub network_check_interval_secs: u32,
+
+    /// Shortest allowed full refresh interval in minutes
+    ///
+    /// Frequent full refreshes wear out e-paper panels; shorter schedule
+    /// intervals are rejected unless `allow_fast_refresh` is set.
+    #[serde(default = "default_min_refresh_interval_min")]
+    pub min_refresh_interval_min: u32,
+
+    /// Allow schedule intervals below `min_refresh_interval_min`
+    #[serde(default)]
+    pub allow_fast_refresh: bool,
+
+    /// Maximum full panel refreshes per day (0 = unlimited)
+    ///
+    /// Once reached, scheduled refreshes are skipped until midnight;
+    /// manual refreshes still run but warn.
+    #[serde(default)]
+    pub max_refreshes_per_day: u32,
+
+    /// Largest changed area, in percent of the panel, shown with a partial
+    /// refresh instead of a full one (0 = always full refresh)
+    ///
+    /// Only used on panels with partial refresh; each new frame is compared
+    /// with the one on the panel.
+    #[serde(default = "default_auto_partial_max_percent")]
+    pub auto_partial_max_percent: u8,
+
+    /// Skip a scheduled refresh if the new image's perceptual hash is at
+    /// most this many bits (of 64) away from the one on the panel
+    /// (None = always refresh; 0 = only practically identical images)
+    ///
+    /// Catches images that differ in a few pixels only, like a dashboard
+    /// whose only change is its "updated at" timestamp. Manual refreshes
+    /// always refresh.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_similar_distance: Option<u8>,
+
+    /// Number of displayed frames kept as PNGs for the history gallery
+    /// (0 = no history)
+    #[serde(default)]
+    pub history_frames: u32,
+
+    /// Minutes a kiosk message stays on the panel unless the request says
+    /// otherwise
+    #[serde(default = "default_message_duration_min")]
+    pub message_duration_min: u32,
+
+    /// Memory in MB an image may need for processing; larger images are
+    /// refused instead of risking an OOM kill (0 = no limit)
+    #[serde(default = "default_max_image_memory_mb")]
+    pub max_image_memory_mb: u32,
+
+    /// Show a notice on the panel when the image URL answers with a web
+    /// page (login or error page) instead of an image
+    #[serde(default)]
+    pub show_html_errors: bool,
+
+    /// Local image shown when the content on the panel has expired and no
+    /// current content can be fetched (empty = a notice)
+    #[serde(default)]
+    pub fallback_image: String,
+
+    /// Caption drawn on downloaded photos from their EXIF/IPTC metadata
+    #[serde(default)]
+    pub photo_caption: PhotoCaption,
+
+    /// Cookie jar and login for image sources behind a login page
+    #[serde(default)]
+    pub session: SessionConfig,
+
+    /// User-Agent sent with image requests (empty = program name and version)
+    #[serde(default)]
+    pub user_agent: String,
+
+    /// Subscription to pushed notifications (ntfy / Gotify)
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// Announce this display over mDNS and list the other displays on the
+    /// network in the web UI (takes effect on restart)
+    #[serde(default = "default_true")]
+    pub peer_discovery: bool,
+
+    /// Mirror group: show the same frames as other displays
+    #[serde(default)]
+    pub mirror: MirrorConfig,
+
+    /// Restart-only settings read from the file while the running ones
+    /// stay in effect (after a reload); saving writes these
+    #[serde(skip)]
+    pub pending_restart: Option<RestartSettings>,
+}
+
+/// Settings set up once at startup (panel driver, web server)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartSettings {
+    pub panel: PanelModel,
+    pub it8951: It8951Config,
+    pub spectra6: Spectra6Config,
+    pub spi: SpiConfig,
+    pub web_port: u16,
+}
+
+impl RestartSettings {
+    fn of(config: &Config) -> Self {
+        Self {
+            panel: config.panel,
+            it8951: config.it8951.clone(),
+            spectra6: config.spectra6.clone(),
+            spi: config.spi.clone(),
+            web_port: config.web_port,
+        }
+    }
+
+    fn apply(&self, config: &mut Config) {
+        config.panel = self.panel;
+        config.it8951 = self.it8951.clone();
+        config.spectra6 = self.spectra6.clone();
+        config.spi = self.spi.clone();
+        config.web_port = self.web_port;
+    }
+}
+
+/// Keep runtime state files in `dir` instead of next to the config
+/// (`--state-dir`); only the first call counts
+pub fn set_state_dir(dir: PathBuf) {
+    let _ = STATE_DIR.set(dir);
+}
+
+/// Directory for runtime state files (crash reports, frame history): the
+/// one set with [`set_state_dir`], or the config's
+pub fn state_dir(config_path: &str) -> PathBuf {
+    if let Some(dir) = STATE_DIR.get() {
+        return dir.clone();
+    }
+    match Path::new(config_path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Configuration file used without `--config`
+///
+/// `EPAPER_CONFIG` if set; otherwise [`DEFAULT_CONFIG_PATH`] (in a
+/// container [`CONTAINER_CONFIG_PATH`]) if it exists, then
+/// `epaper-display/config.json` in the user's config directory
+/// (`$XDG_CONFIG_HOME`, or `~/.config`) if that exists. A development
+/// checkout run as a normal user thus finds its config without flags, and
+/// with no file anywhere the system path is reported as missing.
+pub fn default_config_path(container: bool) -> String {
+    let system = if container {
+        CONTAINER_CONFIG_PATH
+    } else {
+        DEFAULT_CONFIG_PATH
+    };
+    find_config_path(|name| std::env::var_os(name), |path| path.exists(), system)
+}
+
+/// [`default_config_path`] with the environment and file checks passed in
+pub(crate) fn find_config_path(
+    env: impl Fn(&str) -> Option<std::ffi::OsString>,
+    exists: impl Fn(&Path) -> bool,
+    system: &str,
+) -> String {
+    let set = |name| env(name).filter(|value: &std::ffi::OsString| !value.is_empty());
+    if let Some(path) = set(CONFIG_ENV) {
+        return path.to_string_lossy().into_owned();
+    }
+    if exists(Path::new(system)) {
+        return system.to_string();
+    }
+    let user_dir = set("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| set("HOME").map(|home| PathBuf::from(home).join(".config")));
+    match user_dir.map(|dir| dir.join("epaper-display").join("config.json")) {
+        Some(path) if exists(&path) => path.to_string_lossy().into_owned(),
+        _ => system.to_string(),
+    }
 }
 
 fn default_web_port() -> u16 {
@@ -431,23 +913,87 @@ fn default_display_height() -> u32 {
     480
 }
 
+fn default_health_check_interval_min() -> u32 {
+    10
+}
+
+fn default_network_check_interval_secs() -> u32 {
+    60
+}
+
+fn default_message_duration_min() -> u32 {
+    60
+}
+
+fn default_max_image_memory_mb() -> u32 {
+    128
+}
+
+fn default_min_refresh_interval_min() -> u32 {
+    5
+}
+
+fn default_auto_partial_max_percent() -> u8 {
+    25
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
+            device_name: String::new(),
             image_url: String::new(),
+            image_sha256: String::new(),
+            image_sha256_url: String::new(),
+            source: SourceConfig::default(),
+            theme: ThemeConfig::default(),
+            auth: AuthConfig::default(),
             refresh_interval_min: None,
             schedule: None,
             schedule_plans: default_schedule_plans(),
             day_assignments: default_day_assignments(),
+            alternate_weeks: None,
+            vacation: VacationConfig::default(),
             rotation: 0,
             mirror_h: false,
             mirror_v: false,
             scale_to_fit: true,
+            smart_crop: false,
             rotate_first: true,
+            rotate_packed: false,
+            color_profile: ColorProfile::Standard,
+            snap_threshold: 0,
+            panel: PanelModel::default(),
+            it8951: It8951Config::default(),
+            spectra6: Spectra6Config::default(),
+            spi: SpiConfig::default(),
+            reinit_every: 0,
             display_width: default_display_width(),
             display_height: default_display_height(),
             web_port: default_web_port(),
             verbose: false,
+            health_check_interval_min: default_health_check_interval_min(),
+            startup: StartupConfig::default(),
+            load_guard: LoadGuardConfig::default(),
+            power_save: PowerSaveConfig::default(),
+            outputs: OutputsConfig::default(),
+            network_check_interval_secs: default_network_check_interval_secs(),
+            min_refresh_interval_min: default_min_refresh_interval_min(),
+            allow_fast_refresh: false,
+            max_refreshes_per_day: 0,
+            auto_partial_max_percent: default_auto_partial_max_percent(),
+            skip_similar_distance: None,
+            history_frames: 0,
+            message_duration_min: default_message_duration_min(),
+            max_image_memory_mb: default_max_image_memory_mb(),
+            show_html_errors: false,
+            fallback_image: String::new(),
+            photo_caption: PhotoCaption::Off,
+            session: SessionConfig::default(),
+            user_agent: String::new(),
+            notifications: NotificationsConfig::default(),
+            peer_discovery: true,
+            mirror: MirrorConfig::default(),
+            pending_restart: None,
         }
     }
 }
@@ -465,6 +1011,46 @@ impl Config {
         Ok(config)
     }
 
+    /// Settings that differ from `new` and only take effect on restart
+    ///
+    /// The panel driver and the web server are set up once at startup, so
+    /// a reloaded config cannot change them in a running process.
+    pub fn restart_required_changes(&self, new: &Config) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.panel != new.panel {
+            changes.push("panel");
+        }
+        if self.it8951 != new.it8951 {
+            changes.push("it8951");
+        }
+        if self.spectra6 != new.spectra6 {
+            changes.push("spectra6");
+        }
+        if self.spi != new.spi {
+            changes.push("spi");
+        }
+        if self.web_port != new.web_port {
+            changes.push("web_port");
+        }
+        changes
+    }
+
+    /// Keep the restart-only settings of the `running` config
+    ///
+    /// For a reloaded config, so it describes the panel and web server
+    /// actually in use. The values read from the file are kept in
+    /// `pending_restart` and written back on save. Returns the settings
+    /// that wait for a restart.
+    pub fn keep_restart_settings(&mut self, running: &Config) -> Vec<&'static str> {
+        let changes = running.restart_required_changes(self);
+        self.pending_restart = None;
+        if !changes.is_empty() {
+            self.pending_restart = Some(RestartSettings::of(self));
+            RestartSettings::of(running).apply(self);
+        }
+        changes
+    }
+
     /// Load configuration from default path, or return default config if not found
     #[allow(dead_code)]
     pub fn load_or_default() -> Self {
@@ -481,7 +1067,14 @@ impl Config {
     /// for reliability on embedded devices without UPS.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
         let path = path.as_ref();
-        let content = serde_json::to_string_pretty(self)?;
+        let content = match &self.pending_restart {
+            Some(pending) => {
+                let mut file = self.clone();
+                pending.apply(&mut file);
+                serde_json::to_string_pretty(&file)?
+            }
+            None => serde_json::to_string_pretty(self)?,
+        };
 
         // Write to temporary file first
         let tmp_path = path.with_extension("json.tmp");
@@ -497,6 +1090,18 @@ impl Config {
         Ok(())
     }
 
+    /// Configuration as pretty JSON with secret values masked
+    ///
+    /// Used for exports such as the debug bundle. Besides the secret
+    /// fields, resolved secret values embedded elsewhere (e.g. an API key
+    /// in `image_url`) are masked too.
+    pub fn to_redacted_json(&self) -> Result<String, ConfigError> {
+        let mut value = serde_json::to_value(self)?;
+        redact_secrets(&mut value);
+        let json = serde_json::to_string_pretty(&value)?;
+        Ok(secrets::redact(&json).into_owned())
+    }
+
     /// Save configuration to default path
     #[allow(dead_code)]
     pub fn save_default(&self) -> Result<(), ConfigError> {
@@ -508,21 +1113,21 @@ impl Config {
         let mut migrated = false;
 
         // Check if we need to migrate from old single-schedule format
-        if let Some(schedule) = self.schedule.take() {
-            if !schedule.is_empty() {
-                // Check if schedule_plans is default (single Default plan)
-                let is_default_plans = self.schedule_plans.len() == 1
-                    && self.schedule_plans[0].name == "Default"
-                    && self.schedule_plans[0].periods.len() == 1
-                    && self.schedule_plans[0].periods[0].start_time == "00:00"
-                    && self.schedule_plans[0].periods[0].end_time == "00:00"
-                    && self.schedule_plans[0].periods[0].interval_min == 60;
-
-                if is_default_plans {
-                    tracing::info!("Migrating legacy schedule array to schedule_plans");
-                    self.schedule_plans = vec![SchedulePlan::new("Default", schedule)];
-                    migrated = true;
-                }
+        if let Some(schedule) = self.schedule.take()
+            && !schedule.is_empty()
+        {
+            // Check if schedule_plans is default (single Default plan)
+            let is_default_plans = self.schedule_plans.len() == 1
+                && self.schedule_plans[0].name == "Default"
+                && self.schedule_plans[0].periods.len() == 1
+                && self.schedule_plans[0].periods[0].start_time == "00:00"
+                && self.schedule_plans[0].periods[0].end_time == "00:00"
+                && self.schedule_plans[0].periods[0].interval_min == 60;
+
+            if is_default_plans {
+                tracing::info!("Migrating legacy schedule array to schedule_plans");
+                self.schedule_plans = vec![SchedulePlan::new("Default", schedule)];
+                migrated = true;
             }
         }
 
@@ -573,6 +1178,32 @@ impl Config {
             plan.validate()?;
         }
 
+        if self.min_refresh_interval_min < 1 || self.min_refresh_interval_min > 1440 {
+            return Err(ConfigError::ValidationError(
+                "min_refresh_interval_min must be between 1 and 1440".to_string(),
+            ));
+        }
+
+        if !self.allow_fast_refresh {
+            for plan in &self.schedule_plans {
+                if let Some(period) = plan
+                    .periods
+                    .iter()
+                    .find(|p| p.interval_min < self.min_refresh_interval_min)
+                {
+                    return Err(ConfigError::ValidationError(format!(
+                        "Plan '{}' refreshes every {} min ({}-{}), below the minimum of {} min \
+                         that protects the panel (set allow_fast_refresh to override)",
+                        plan.name,
+                        period.interval_min,
+                        period.start_time,
+                        period.end_time,
+                        self.min_refresh_interval_min
+                    )));
+                }
+            }
+        }
+
         // Validate day assignments
         for day in Weekday::all() {
             let plan_name = self.day_assignments.get(day).ok_or_else(|| {
@@ -590,6 +1221,26 @@ impl Config {
                 )));
             }
         }
+        self.vacation.validate()?;
+        let vacation_plan = self.vacation.plan.trim();
+        if !vacation_plan.is_empty() && self.get_plan(vacation_plan).is_none() {
+            return Err(ConfigError::ValidationError(format!(
+                "vacation.plan names non-existent plan '{}'",
+                vacation_plan
+            )));
+        }
+        if let Some(weeks) = &self.alternate_weeks {
+            weeks.validate()?;
+            for (day, plan_name) in &weeks.day_assignments {
+                if self.get_plan(plan_name).is_none() {
+                    return Err(ConfigError::ValidationError(format!(
+                        "{} of B weeks is assigned to non-existent plan '{}'",
+                        day.display_name(),
+                        plan_name
+                    )));
+                }
+            }
+        }
 
         if !matches!(self.rotation, 0 | 90 | 180 | 270) {
             return Err(ConfigError::ValidationError(
@@ -597,6 +1248,56 @@ impl Config {
             ));
         }
 
+        if self.snap_threshold > 127 {
+            return Err(ConfigError::ValidationError(
+                "snap_threshold must be between 0 and 127".to_string(),
+            ));
+        }
+
+        if self.auto_partial_max_percent > 100 {
+            return Err(ConfigError::ValidationError(
+                "auto_partial_max_percent must be between 0 and 100".to_string(),
+            ));
+        }
+
+        let image_sha256 = self.image_sha256.trim();
+        if !image_sha256.is_empty() && !is_sha256_hex(image_sha256) {
+            return Err(ConfigError::ValidationError(
+                "image_sha256 must be 64 hex digits".to_string(),
+            ));
+        }
+        let sidecar = self.image_sha256_url.trim();
+        if !sidecar.is_empty() {
+            if !image_sha256.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "Set either image_sha256 or image_sha256_url, not both".to_string(),
+                ));
+            }
+            if !sidecar.starts_with("http://") && !sidecar.starts_with("https://") {
+                return Err(ConfigError::ValidationError(
+                    "image_sha256_url must start with http:// or https://".to_string(),
+                ));
+            }
+        }
+
+        if self.skip_similar_distance.is_some_and(|d| d > 64) {
+            return Err(ConfigError::ValidationError(
+                "skip_similar_distance must be between 0 and 64".to_string(),
+            ));
+        }
+
+        if self.message_duration_min < 1 || self.message_duration_min > 1440 {
+            return Err(ConfigError::ValidationError(
+                "message_duration_min must be between 1 and 1440".to_string(),
+            ));
+        }
+
+        if self.max_image_memory_mb != 0 && !(16..=4096).contains(&self.max_image_memory_mb) {
+            return Err(ConfigError::ValidationError(
+                "max_image_memory_mb must be 0 (no limit) or between 16 and 4096".to_string(),
+            ));
+        }
+
         if self.web_port == 0 {
             return Err(ConfigError::ValidationError(
                 "web_port must be greater than 0".to_string(),
@@ -615,6 +1316,51 @@ impl Config {
             ));
         }
 
+        let (width, height) = (self.panel.width(), self.panel.height());
+        if (self.display_width, self.display_height) != (width, height) {
+            return Err(ConfigError::ValidationError(format!(
+                "display_width/display_height must be {}x{} for panel '{}'",
+                width,
+                height,
+                self.panel.as_str()
+            )));
+        }
+
+        self.source.validate()?;
+        self.theme.validate()?;
+        self.auth.validate()?;
+        self.it8951.validate()?;
+        self.spectra6.validate()?;
+        self.spi.validate()?;
+        self.startup.validate()?;
+        self.load_guard.validate()?;
+        self.power_save.validate()?;
+        self.outputs.validate()?;
+        self.notifications.validate()?;
+        self.session.validate()?;
+        self.mirror.validate()?;
+
+        if self.device_name.chars().count() > MAX_DEVICE_NAME_LEN
+            || self.device_name.chars().any(char::is_control)
+        {
+            return Err(ConfigError::ValidationError(format!(
+                "device_name must be at most {} characters on one line",
+                MAX_DEVICE_NAME_LEN
+            )));
+        }
+
+        if reqwest::header::HeaderValue::from_str(self.user_agent.trim()).is_err() {
+            return Err(ConfigError::ValidationError(
+                "user_agent must be printable ASCII".to_string(),
+            ));
+        }
+
+        if self.health_check_interval_min > 1440 {
+            return Err(ConfigError::ValidationError(
+                "health_check_interval_min must be between 0 and 1440".to_string(),
+            ));
+        }
+
         Ok(())
     }
 
@@ -623,6 +1369,14 @@ impl Config {
         self.schedule_plans.iter().find(|p| p.name == name)
     }
 
+    /// `base`, or `base` with a number appended if a plan already has that name
+    pub fn unused_plan_name(&self, base: &str) -> String {
+        std::iter::once(base.to_string())
+            .chain((2..).map(|n| format!("{} {}", base, n)))
+            .find(|name| self.get_plan(name).is_none())
+            .unwrap_or_default()
+    }
+
     /// Get the schedule plan for a specific weekday
     pub fn get_plan_for_day(&self, day: Weekday) -> Option<&SchedulePlan> {
         self.day_assignments
@@ -632,9 +1386,31 @@ impl Config {
 
     /// Get the current active schedule plan based on today's day of week
     pub fn get_current_plan(&self) -> Option<&SchedulePlan> {
-        let now = chrono::Local::now();
-        let weekday = Weekday::from_chrono(now.weekday());
-        self.get_plan_for_day(weekday)
+        self.plan_at(chrono::Local::now())
+    }
+
+    /// Get the schedule plan in effect at `time`
+    pub fn plan_at(&self, time: chrono::DateTime<chrono::Local>) -> Option<&SchedulePlan> {
+        if let Some(plan) = self.vacation.active_plan() {
+            return self.get_plan(plan);
+        }
+        let day = Weekday::from_chrono(time.weekday());
+        match self
+            .alternate_weeks
+            .as_ref()
+            .filter(|weeks| weeks.is_b_week(time.date_naive()))
+            .and_then(|weeks| weeks.day_assignments.get(&day))
+        {
+            Some(name) => self.get_plan(name),
+            None => self.get_plan_for_day(day),
+        }
+    }
+
+    /// Whether `time` lies in a B week of `alternate_weeks`
+    pub fn is_b_week(&self, time: chrono::DateTime<chrono::Local>) -> bool {
+        self.alternate_weeks
+            .as_ref()
+            .is_some_and(|weeks| weeks.is_b_week(time.date_naive()))
     }
 
     /// Get the current weekday
@@ -644,14 +1420,41 @@ impl Config {
     }
 
     /// Get the current refresh interval based on day and time
+    ///
+    /// Never below the minimum refresh interval unless fast refresh is
+    /// allowed, even if validation was bypassed.
     pub fn get_current_interval(&self) -> u32 {
-        let now = chrono::Local::now();
-        let current_minutes = now.hour() * 60 + now.minute();
+        self.interval_at(chrono::Local::now())
+    }
+
+    /// Get the refresh interval in effect at `time`, with the same limits
+    pub fn interval_at(&self, time: chrono::DateTime<chrono::Local>) -> u32 {
+        let minutes = time.hour() * 60 + time.minute();
 
-        if let Some(plan) = self.get_current_plan() {
-            plan.get_interval_for_time(current_minutes)
+        let interval = if let Some(plan) = self.plan_at(time) {
+            plan.get_interval_for_time(minutes)
         } else {
             60 // Fallback
+        };
+        interval.max(self.min_interval())
+    }
+
+    /// Whether `refreshes` uses up the daily refresh budget
+    pub fn refresh_budget_exhausted(&self, refreshes: u32) -> bool {
+        self.max_refreshes_per_day > 0 && refreshes >= self.max_refreshes_per_day
+    }
+
+    /// Whether `refreshes` goes over the daily refresh budget
+    pub fn refresh_budget_exceeded(&self, refreshes: u32) -> bool {
+        self.max_refreshes_per_day > 0 && refreshes > self.max_refreshes_per_day
+    }
+
+    /// Shortest full refresh interval the scheduler will use, in minutes
+    pub fn min_interval(&self) -> u32 {
+        if self.allow_fast_refresh {
+            1
+        } else {
+            self.min_refresh_interval_min.max(1)
         }
     }
 
@@ -668,4 +1471,175 @@ impl Config {
     pub fn has_image_url(&self) -> bool {
         !self.image_url.trim().is_empty()
     }
+
+    /// Name of the device: `device_name`, or the host name if unset
+    pub fn device_name(&self) -> String {
+        match self.device_name.trim() {
+            "" => crate::network::hostname(),
+            name => name.to_string(),
+        }
+    }
+
+    /// User-Agent header for source and mirror group requests
+    pub fn user_agent(&self) -> reqwest::header::HeaderValue {
+        let user_agent = self.user_agent.trim();
+        if user_agent.is_empty() {
+            return reqwest::header::HeaderValue::from_static(DEFAULT_USER_AGENT);
+        }
+        reqwest::header::HeaderValue::from_str(user_agent)
+            .unwrap_or_else(|_| reqwest::header::HeaderValue::from_static(DEFAULT_USER_AGENT))
+    }
+
+    /// Memory limit for processing a downloaded image, in bytes
+    pub fn max_image_memory(&self) -> Option<u64> {
+        (self.max_image_memory_mb > 0).then(|| self.max_image_memory_mb as u64 * 1024 * 1024)
+    }
+
+    /// Download settings for source requests: User-Agent, memory limit
+    /// and cookie session
+    pub fn download_config(&self) -> DownloadConfig {
+        let mut download_config = DownloadConfig {
+            max_memory: self.max_image_memory(),
+            session: self.session.enabled().then(|| self.session.clone()),
+            ..DownloadConfig::default()
+        };
+        download_config
+            .headers
+            .insert(reqwest::header::USER_AGENT, self.user_agent());
+        download_config
+    }
+
+    /// Checksum the image at `image_url` must match, if configured
+    pub fn image_checksum(&self) -> Result<Option<Checksum>, secrets::SecretError> {
+        let sidecar = self.image_sha256_url.trim();
+        Ok(if !sidecar.is_empty() {
+            Some(Checksum::Sidecar(secrets::expand(sidecar)?))
+        } else if !self.image_sha256.trim().is_empty() {
+            Some(Checksum::Sha256(self.image_sha256.trim().to_string()))
+        } else {
+            None
+        })
+    }
+
+    /// Size of content before rotation (width/height swapped for 90°/270°)
+    pub fn content_size(&self) -> (u32, u32) {
+        if matches!(self.rotation, 90 | 270) {
+            (self.display_height, self.display_width)
+        } else {
+            (self.display_width, self.display_height)
+        }
+    }
+
+    /// Check if the configured source can fetch content
+    pub fn has_source(&self) -> bool {
+        self.source.is_configured(self)
+    }
+
+    /// The configuration the schedule displays: with the vacation image
+    /// instead of the configured source while vacation mode is on
+    pub fn scheduled_content(&self) -> Cow<'_, Config> {
+        match self.vacation.active_image() {
+            Some(url) => Cow::Owned(Config {
+                image_url: url.to_string(),
+                image_sha256: String::new(),
+                image_sha256_url: String::new(),
+                source: SourceConfig::Url,
+                ..self.clone()
+            }),
+            None => Cow::Borrowed(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    #[test]
+    fn config_is_found_without_flags() {
+        let user = "/home/pi/.config/epaper-display/config.json";
+        let find = |vars: &[(&str, &str)], files: &[&str]| {
+            find_config_path(
+                |name| {
+                    vars.iter()
+                        .find(|(var, _)| *var == name)
+                        .map(|(_, value)| OsString::from(value))
+                },
+                |path| files.iter().any(|file| Path::new(file) == path),
+                DEFAULT_CONFIG_PATH,
+            )
+        };
+
+        // The variable wins, even over an existing system config
+        let env = [("EPAPER_CONFIG", "dev.json"), ("HOME", "/home/pi")];
+        assert_eq!(find(&env, &[DEFAULT_CONFIG_PATH, user]), "dev.json");
+        let home = [("EPAPER_CONFIG", ""), ("HOME", "/home/pi")];
+        assert_eq!(
+            find(&home, &[DEFAULT_CONFIG_PATH, user]),
+            DEFAULT_CONFIG_PATH
+        );
+        assert_eq!(find(&home, &[user]), user);
+        let xdg = [("HOME", "/home/pi"), ("XDG_CONFIG_HOME", "/tmp/xdg")];
+        assert_eq!(
+            find(&xdg, &["/tmp/xdg/epaper-display/config.json", user]),
+            "/tmp/xdg/epaper-display/config.json"
+        );
+        assert_eq!(find(&home, &[]), DEFAULT_CONFIG_PATH);
+    }
+
+    #[test]
+    fn container_mode_reads_the_config_volume() {
+        let nothing = find_config_path(|_| None, |_| false, CONTAINER_CONFIG_PATH);
+        assert_eq!(nothing, CONTAINER_CONFIG_PATH);
+        let env = find_config_path(
+            |name| (name == "EPAPER_CONFIG").then(|| OsString::from("/srv/display.json")),
+            |_| true,
+            CONTAINER_CONFIG_PATH,
+        );
+        assert_eq!(env, "/srv/display.json");
+    }
+
+    #[test]
+    fn device_name_falls_back_to_the_host_name() {
+        assert_eq!(Config::default().device_name(), crate::network::hostname());
+        let named = Config {
+            device_name: "Kitchen".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(named.device_name(), "Kitchen");
+
+        let long = Config {
+            device_name: "x".repeat(41),
+            ..Config::default()
+        };
+        assert!(long.validate().is_err());
+    }
+
+    #[test]
+    fn alternate_weeks_start_on_a_date() {
+        let mut config = Config::default();
+        config
+            .schedule_plans
+            .push(SchedulePreset::PhotoFrame.plan("Visit"));
+        config.alternate_weeks = Some(AlternateWeeks {
+            b_weeks: "2026-03-04".to_string(),
+            day_assignments: [(Weekday::Saturday, "Visit".to_string())].into(),
+        });
+        assert!(config.validate().is_ok());
+
+        config.alternate_weeks.as_mut().unwrap().b_weeks = "weekly".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn fast_refresh_needs_override() {
+        let mut config = Config::default();
+        config.schedule_plans[0].periods[0].interval_min = 1;
+        assert_eq!(config.get_current_interval(), 5);
+
+        config.allow_fast_refresh = true;
+        assert!(config.validate().is_ok());
+        assert_eq!(config.get_current_interval(), 1);
+    }
 }
@@ -0,0 +1,169 @@
+//! Panic handling and crash reports.
+//!
+//! Release builds use `panic = "abort"`, so no destructors run when the
+//! process panics. Without intervention a panic mid-refresh leaves the
+//! panel's PWR rail high indefinitely. The hook installed here powers the
+//! panel down and records what happened before the process aborts.
+
+use serde::{Deserialize, Serialize};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+
+/// File name of the crash report inside the state directory
+pub const CRASH_REPORT_FILE: &str = "crash_report.json";
+
+/// File name the crash report is moved to once it has been reported
+pub const PREVIOUS_CRASH_REPORT_FILE: &str = "crash_report.prev.json";
+
+/// File name of the restart counter inside the state directory
+pub const RESTART_STATE_FILE: &str = "restart_state.json";
+
+/// Details of the last panic, persisted for post-mortem inspection
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CrashReport {
+    /// Local time of the panic (RFC 3339)
+    pub timestamp: String,
+    /// Panic message
+    pub message: String,
+    /// Source location of the panic, if known
+    pub location: Option<String>,
+    /// Name of the panicking thread
+    pub thread: Option<String>,
+    /// Version of the binary that crashed
+    pub version: String,
+}
+
+impl CrashReport {
+    /// Build a report from panic information
+    fn from_panic(info: &PanicHookInfo<'_>) -> Self {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            message,
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            thread: std::thread::current().name().map(str::to_string),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Load the crash report from a state directory, if one exists
+    ///
+    /// The file is moved aside to `crash_report.prev.json`, so the crash
+    /// is reported by the start right after it and not by every later one.
+    pub fn take(state_dir: &Path) -> Option<Self> {
+        let path = state_dir.join(CRASH_REPORT_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        if let Err(e) = std::fs::rename(&path, state_dir.join(PREVIOUS_CRASH_REPORT_FILE)) {
+            tracing::warn!("Failed to move aside {}: {}", path.display(), e);
+        }
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Write the report into the state directory
+    fn save(&self, state_dir: &Path) -> std::io::Result<()> {
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(state_dir.join(CRASH_REPORT_FILE), content)
+    }
+}
+
+/// Install the process-wide panic hook
+///
+/// On panic: print the default panic message, power down the panel
+/// (best effort), write a crash report into `state_dir`, then abort.
+pub fn install_panic_hook(state_dir: PathBuf) {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        // Panel first: a powered panel is the costly failure mode
+        crate::display::emergency_shutdown();
+
+        let report = CrashReport::from_panic(info);
+        if let Err(e) = report.save(&state_dir) {
+            eprintln!(
+                "Failed to write crash report to {}: {}",
+                state_dir.display(),
+                e
+            );
+        }
+
+        std::process::abort();
+    }));
+}
+
+/// Persistent restart bookkeeping
+///
+/// Incremented on every start so that devices stuck in a restart loop
+/// under systemd can be recognised from the status API.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestartState {
+    /// Number of starts recorded since the state file was created
+    pub restart_count: u64,
+    /// Local time of the most recent start (RFC 3339)
+    pub last_start: Option<String>,
+}
+
+impl RestartState {
+    /// Record a start in `state_dir` and return the updated state
+    ///
+    /// Failures to persist are logged but never prevent startup.
+    pub fn record_start(state_dir: &Path) -> Self {
+        let path = state_dir.join(RESTART_STATE_FILE);
+        let mut state: Self = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        state.restart_count += 1;
+        state.last_start = Some(chrono::Local::now().to_rfc3339());
+
+        let result = serde_json::to_string_pretty(&state)
+            .map_err(std::io::Error::other)
+            .and_then(|content| std::fs::write(&path, content));
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to persist restart counter to {}: {}",
+                path.display(),
+                e
+            );
+        }
+
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_report_is_taken_once() {
+        let dir = std::env::temp_dir().join(format!("epaper-crash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let report = CrashReport {
+            timestamp: "2026-10-16T08:00:00+02:00".to_string(),
+            message: "index out of bounds".to_string(),
+            location: Some("src/dither.rs:10:5".to_string()),
+            thread: Some("main".to_string()),
+            version: "1.0.0".to_string(),
+        };
+        report.save(&dir).unwrap();
+
+        let taken = CrashReport::take(&dir).unwrap();
+        assert_eq!(taken.message, report.message);
+        assert!(CrashReport::take(&dir).is_none());
+        assert!(dir.join(PREVIOUS_CRASH_REPORT_FILE).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
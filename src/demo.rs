@@ -0,0 +1,238 @@
+//! Demo mode.
+//!
+//! For showing the display at a meetup or lending it out:
+//! `POST /api/v1/demo` cycles through the panel's test pattern, the
+//! built-in [sample images](crate::sources::sample) and a clock (with the
+//! `renderers` feature), one step every `step_secs`, for `duration_min` minutes. Each step is a manual
+//! override for the [content arbiter](crate::arbiter) that ends with the
+//! demo, so the config is never touched and the scheduled content returns
+//! by itself afterwards. A kiosk message or `DELETE /api/v1/demo` ends the
+//! demo early; alerts are shown over it.
+
+use crate::arbiter::{ContentArbiter, Override, Priority};
+use crate::config::Config;
+use crate::image_proc::ImageProcessor;
+use crate::sources::SourceConfig;
+#[cfg(feature = "renderers")]
+use crate::sources::clock::{ClockSource, ClockStyle};
+use crate::sources::sample::SampleImage;
+use crate::sources::text::{TextAlign, TextSource};
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::AbortHandle;
+
+/// Shortest time per step; a full refresh of the slower panels takes
+/// up to 20 seconds
+pub const MIN_STEP_SECS: u32 = 20;
+
+/// Longest time per step
+pub const MAX_STEP_SECS: u32 = 3600;
+
+/// Longest demo
+pub const MAX_DURATION_MIN: u32 = 480;
+
+/// Labels of demo overrides start with this
+const LABEL: &str = "demo";
+
+/// Task cycling the steps of the running demo
+static RUNNING: Mutex<Option<AbortHandle>> = Mutex::new(None);
+
+/// One screen of the demo
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    /// Title card with the panel model and the end of the demo
+    Intro,
+    /// Test pattern of the panel driver
+    TestPattern,
+    Sample(SampleImage),
+    #[cfg(feature = "renderers")]
+    Clock(ClockStyle),
+}
+
+impl Step {
+    fn all() -> Vec<Step> {
+        let mut steps = vec![Step::Intro, Step::TestPattern];
+        steps.extend(SampleImage::all().iter().copied().map(Step::Sample));
+        #[cfg(feature = "renderers")]
+        steps.extend([
+            Step::Clock(ClockStyle::Digital),
+            Step::Clock(ClockStyle::Analog),
+        ]);
+        steps
+    }
+
+    /// Override showing the step until `until`
+    ///
+    /// The test pattern is drawn by the driver; its override shows the
+    /// color bars in case the panel is redrawn meanwhile.
+    fn content(&self, config: &Config, until: DateTime<Local>) -> Override {
+        let (name, source) = match self {
+            Step::Intro => (
+                "intro".to_string(),
+                SourceConfig::Text(TextSource {
+                    text: format!(
+                        "Demo\n\n{}\n\nuntil {}",
+                        config.panel.spec().model,
+                        until.format("%H:%M")
+                    ),
+                    size: 0,
+                    color: None,
+                    background: None,
+                    align: TextAlign::Center,
+                }),
+            ),
+            Step::TestPattern => (
+                "test pattern".to_string(),
+                SourceConfig::Sample(SampleImage::ColorBars),
+            ),
+            Step::Sample(sample) => (sample.title().to_string(), SourceConfig::Sample(*sample)),
+            #[cfg(feature = "renderers")]
+            Step::Clock(style) => (
+                "clock".to_string(),
+                SourceConfig::Clock(ClockSource {
+                    style: *style,
+                    hour24: true,
+                    show_date: true,
+                }),
+            ),
+        };
+        Override {
+            priority: Priority::Manual,
+            label: format!("{} ({}) until {}", LABEL, name, until.format("%H:%M")),
+            source,
+            until: Some(until),
+        }
+    }
+}
+
+/// Whether `content` belongs to a demo
+fn is_demo(content: &Override) -> bool {
+    content.label.starts_with(LABEL)
+}
+
+/// Runs demos on the panel
+pub struct Demo {
+    config: Arc<RwLock<Config>>,
+    processor: Arc<ImageProcessor>,
+    arbiter: Arc<ContentArbiter>,
+}
+
+impl Demo {
+    pub fn new(
+        config: Arc<RwLock<Config>>,
+        processor: Arc<ImageProcessor>,
+        arbiter: Arc<ContentArbiter>,
+    ) -> Self {
+        Self {
+            config,
+            processor,
+            arbiter,
+        }
+    }
+
+    /// Start a demo of `duration`, replacing a running one
+    ///
+    /// Returns the local time it ends.
+    pub fn start(self, duration: Duration, step: Duration) -> DateTime<Local> {
+        let until = Local::now() + duration;
+        let steps = (duration.as_secs() / step.as_secs().max(1)).max(1) as usize;
+        let task = tokio::spawn(async move {
+            self.run(until, steps, step).await;
+        });
+        let previous = RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        until
+    }
+
+    /// Stop the running demo; returns false if there is none
+    pub fn stop(arbiter: &ContentArbiter) -> bool {
+        let running = RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .is_some_and(|task| {
+                let running = !task.is_finished();
+                task.abort();
+                running
+            });
+        let mut shown = false;
+        arbiter.modify(Priority::Manual, |current| {
+            shown = current.is_some_and(is_demo);
+            current.filter(|o| !is_demo(o)).cloned()
+        });
+        running || shown
+    }
+
+    /// Show `steps` steps in turn, `step` apart, then resume the schedule
+    ///
+    /// Returns the number of steps shown. Ends early if another message
+    /// replaced the demo or its override expired.
+    pub(crate) async fn run(self, until: DateTime<Local>, steps: usize, step: Duration) -> usize {
+        tracing::info!("Starting demo until {}", until.format("%H:%M"));
+        let mut shown_steps = 0;
+        for (index, next) in Step::all().into_iter().cycle().take(steps).enumerate() {
+            if index > 0 {
+                tokio::time::sleep(step).await;
+            }
+
+            let config = self.config.read().await.clone();
+            let content = next.content(&config, until);
+            let label = content.label.clone();
+            let shown = if index == 0 {
+                self.arbiter.set(content.clone())
+            } else {
+                // Another message replaced the demo, or its time is up
+                let mut ended = None;
+                let shown = self
+                    .arbiter
+                    .modify(Priority::Manual, |current| match current {
+                        Some(current) if is_demo(current) => Some(content.clone()),
+                        Some(current) => {
+                            ended = Some("replaced by another message");
+                            Some(current.clone())
+                        }
+                        None => {
+                            ended = Some("time is up");
+                            None
+                        }
+                    });
+                if let Some(reason) = ended {
+                    tracing::info!("Demo ended, {}", reason);
+                    return shown_steps;
+                }
+                shown
+            };
+
+            if shown {
+                tracing::debug!("Showing {}", label);
+                let result = match next {
+                    Step::TestPattern => self.processor.show_test_pattern(&config).await,
+                    _ => {
+                        self.processor
+                            .process_and_display(&content.content_config(&config))
+                            .await
+                    }
+                };
+                match result {
+                    Ok(()) => shown_steps += 1,
+                    Err(e) => tracing::error!("Failed to display {}: {}", label, e),
+                }
+            }
+        }
+
+        // The last step stays up for its time too
+        tokio::time::sleep(step).await;
+        self.arbiter.modify(Priority::Manual, |current| {
+            current.filter(|o| !is_demo(o)).cloned()
+        });
+        tracing::info!("Demo finished");
+        shown_steps
+    }
+}
@@ -0,0 +1,468 @@
+//! Discovery of other displays on the network (mDNS / DNS-SD).
+//!
+//! Every instance answers multicast DNS queries for the
+//! `_epaper-display._tcp.local` service with its host name, web port and
+//! device name, and the web UI asks the network for it to list the other
+//! displays in the home, without a fleet server. Only the part of mDNS
+//! needed for this is implemented. The responder shares port 5353 with
+//! avahi where that runs; avahi keeps answering for the host itself.
+
+use crate::status::StatusTracker;
+use serde::Serialize;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Service type announced by every display
+pub const SERVICE: &str = "_epaper-display._tcp.local";
+
+/// mDNS multicast group and port
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+/// Seconds other hosts may cache the records
+const TTL: u32 = 120;
+
+/// Time answers are collected for after a query
+const BROWSE_TIME: Duration = Duration::from_millis(1500);
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Top bit of the class: "unicast response" in questions, "cache flush" in
+/// answers
+const CLASS_FLAG: u16 = 0x8000;
+/// Header flags of an authoritative response
+const FLAGS_RESPONSE: u16 = 0x8400;
+
+/// Another display found on the network
+#[derive(Debug, Clone, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct Peer {
+    /// Device name (`device_name` or host name)
+    pub name: String,
+    /// mDNS host name, e.g. `kitchen.local`
+    pub host: String,
+    /// IPv4 address
+    pub address: String,
+    /// Web UI port
+    pub port: u16,
+    /// Version of the service
+    pub version: String,
+    /// Web UI
+    pub url: String,
+}
+
+/// What a display announces about itself
+#[derive(Debug, Clone)]
+pub struct Advert {
+    /// Host name, also the service instance name
+    pub host: String,
+    pub name: String,
+    pub version: String,
+    pub port: u16,
+    pub address: Ipv4Addr,
+}
+
+impl Advert {
+    fn instance(&self) -> String {
+        format!("{}.{}", self.host, SERVICE)
+    }
+
+    fn host_name(&self) -> String {
+        format!("{}.local", self.host)
+    }
+}
+
+/// Answers queries for this display
+pub struct Responder {
+    status: Arc<StatusTracker>,
+    port: u16,
+}
+
+impl Responder {
+    /// Responder announcing the web UI on `port`; the device name comes
+    /// from `status`, so renaming applies at once
+    pub fn new(status: Arc<StatusTracker>, port: u16) -> Self {
+        Self { status, port }
+    }
+
+    /// Answer queries until the task is aborted
+    pub async fn run(self) {
+        let socket = match bind_mdns() {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::warn!("Peer discovery unavailable: {}", e);
+                return;
+            }
+        };
+        tracing::info!(
+            "Announcing this display as {} on port {}",
+            SERVICE,
+            self.port
+        );
+
+        let mut buf = [0u8; 1500];
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    tracing::debug!("mDNS receive failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let Some(address) = local_address_towards(from) else {
+                continue;
+            };
+            let advert = Advert {
+                host: crate::network::hostname(),
+                name: self.status.snapshot().device_name,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                port: self.port,
+                address,
+            };
+            // Queries from other ports are one-shot queries that expect
+            // the answer back at their port, with their question and ID
+            let legacy = from.port() != MDNS_PORT;
+            let Some(reply) = answer(&buf[..len], &advert, legacy) else {
+                continue;
+            };
+            let to = if legacy {
+                from
+            } else {
+                SocketAddr::from((MDNS_GROUP, MDNS_PORT))
+            };
+            if let Err(e) = socket.send_to(&reply, to).await {
+                tracing::debug!("mDNS answer to {} failed: {}", to, e);
+            }
+        }
+    }
+}
+
+/// Other displays answering on the network, except the one with host
+/// name `own_host`
+pub async fn browse(own_host: &str) -> Vec<Peer> {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::warn!("Cannot search for other displays: {}", e);
+            return Vec::new();
+        }
+    };
+    if let Err(e) = socket.send_to(&query(), (MDNS_GROUP, MDNS_PORT)).await {
+        tracing::warn!("Cannot search for other displays: {}", e);
+        return Vec::new();
+    }
+
+    let own_host = format!("{}.local", own_host);
+    let mut peers: Vec<Peer> = Vec::new();
+    let mut buf = [0u8; 1500];
+    let deadline = tokio::time::Instant::now() + BROWSE_TIME;
+    while let Ok(Ok((len, from))) =
+        tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await
+    {
+        for peer in parse_answer(&buf[..len], from.ip()) {
+            if !peer.host.eq_ignore_ascii_case(&own_host)
+                && !peers
+                    .iter()
+                    .any(|p| p.host == peer.host && p.port == peer.port)
+            {
+                peers.push(peer);
+            }
+        }
+    }
+    peers.sort_by_key(|peer| peer.name.to_lowercase());
+    peers
+}
+
+/// Query for all displays
+pub fn query() -> Vec<u8> {
+    let mut out = Vec::new();
+    // ID, flags, one question, no records
+    for field in [0, 0, 1, 0, 0, 0] {
+        push_u16(&mut out, field);
+    }
+    push_name(&mut out, SERVICE);
+    push_u16(&mut out, TYPE_PTR);
+    push_u16(&mut out, CLASS_IN);
+    out
+}
+
+/// Answer to `query` announcing `advert`, if it asks for displays
+///
+/// A `legacy` answer repeats the ID and question of the query.
+pub fn answer(query: &[u8], advert: &Advert, legacy: bool) -> Option<Vec<u8>> {
+    let id = read_u16(query, 0)?;
+    let flags = read_u16(query, 2)?;
+    let questions = read_u16(query, 4)?;
+    if flags & 0x8000 != 0 {
+        // A response, not a query
+        return None;
+    }
+
+    let mut pos = 12;
+    let mut asked = None;
+    for _ in 0..questions {
+        let (name, end) = read_name(query, pos)?;
+        let qtype = read_u16(query, end)?;
+        if name.eq_ignore_ascii_case(SERVICE) && matches!(qtype, TYPE_PTR | TYPE_ANY) {
+            asked = Some(qtype);
+        }
+        pos = end + 4;
+    }
+    let asked = asked?;
+
+    let mut out = Vec::new();
+    push_u16(&mut out, if legacy { id } else { 0 });
+    push_u16(&mut out, FLAGS_RESPONSE);
+    push_u16(&mut out, u16::from(legacy));
+    push_u16(&mut out, 1);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 3);
+    if legacy {
+        push_name(&mut out, SERVICE);
+        push_u16(&mut out, asked);
+        push_u16(&mut out, CLASS_IN);
+    }
+
+    let instance = advert.instance();
+    let host = advert.host_name();
+    let mut ptr = Vec::new();
+    push_name(&mut ptr, &instance);
+    push_record(&mut out, SERVICE, TYPE_PTR, CLASS_IN, &ptr);
+
+    let mut srv = Vec::new();
+    for field in [0, 0, advert.port] {
+        push_u16(&mut srv, field);
+    }
+    push_name(&mut srv, &host);
+    push_record(&mut out, &instance, TYPE_SRV, CLASS_IN | CLASS_FLAG, &srv);
+
+    let mut txt = Vec::new();
+    for entry in [
+        format!("name={}", advert.name),
+        format!("version={}", advert.version),
+    ] {
+        let entry = &entry.as_bytes()[..entry.len().min(255)];
+        txt.push(entry.len() as u8);
+        txt.extend_from_slice(entry);
+    }
+    push_record(&mut out, &instance, TYPE_TXT, CLASS_IN | CLASS_FLAG, &txt);
+
+    push_record(
+        &mut out,
+        &host,
+        TYPE_A,
+        CLASS_IN | CLASS_FLAG,
+        &advert.address.octets(),
+    );
+    Some(out)
+}
+
+/// Displays announced in `message`, received from `from`
+///
+/// Without an address record, `from` is taken as the address.
+pub fn parse_answer(message: &[u8], from: IpAddr) -> Vec<Peer> {
+    parse_records(message, from).unwrap_or_default()
+}
+
+fn parse_records(message: &[u8], from: IpAddr) -> Option<Vec<Peer>> {
+    let flags = read_u16(message, 2)?;
+    if flags & 0x8000 == 0 {
+        return Some(Vec::new());
+    }
+    let questions = read_u16(message, 4)?;
+    let records = (6..12)
+        .step_by(2)
+        .map(|offset| read_u16(message, offset).map(usize::from))
+        .sum::<Option<usize>>()?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(message, pos)?.1 + 4;
+    }
+
+    let mut instances = Vec::new();
+    let mut services: HashMap<String, (u16, String)> = HashMap::new();
+    let mut texts: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut addresses: HashMap<String, Ipv4Addr> = HashMap::new();
+    for _ in 0..records {
+        let (name, end) = read_name(message, pos)?;
+        let rtype = read_u16(message, end)?;
+        let len = usize::from(read_u16(message, end + 8)?);
+        let data = end + 10;
+        let rdata = message.get(data..data + len)?;
+        let key = name.to_lowercase();
+        match rtype {
+            TYPE_PTR if name.eq_ignore_ascii_case(SERVICE) => {
+                instances.push(read_name(message, data)?.0.to_lowercase());
+            }
+            TYPE_SRV => {
+                let port = read_u16(message, data + 4)?;
+                let target = read_name(message, data + 6)?.0;
+                services.insert(key, (port, target));
+            }
+            TYPE_TXT => {
+                let mut entries = HashMap::new();
+                let mut rest = rdata;
+                while let Some((&n, tail)) = rest.split_first() {
+                    let entry = tail.get(..usize::from(n))?;
+                    let entry = String::from_utf8_lossy(entry);
+                    if let Some((k, v)) = entry.split_once('=') {
+                        entries.insert(k.to_lowercase(), v.to_string());
+                    }
+                    rest = &tail[usize::from(n)..];
+                }
+                texts.insert(key, entries);
+            }
+            TYPE_A if len == 4 => {
+                addresses.insert(key, Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3]));
+            }
+            _ => {}
+        }
+        pos = data + len;
+    }
+
+    let peers = instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (port, host) = services.get(&instance)?.clone();
+            let address = addresses
+                .get(&host.to_lowercase())
+                .map(|a| IpAddr::V4(*a))
+                .unwrap_or(from);
+            let text = texts.get(&instance);
+            let field = |key: &str| text.and_then(|t| t.get(key)).cloned();
+            Some(Peer {
+                name: field("name").unwrap_or_else(|| host.trim_end_matches(".local").to_string()),
+                version: field("version").unwrap_or_default(),
+                url: format!("http://{}:{}/", address, port),
+                address: address.to_string(),
+                host,
+                port,
+            })
+        })
+        .collect();
+    Some(peers)
+}
+
+/// UDP socket joined to the mDNS group, sharing the port with other
+/// responders
+fn bind_mdns() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Local address the route to `peer` leaves from
+fn local_address_towards(peer: SocketAddr) -> Option<Ipv4Addr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect(peer).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(address) if !address.is_unspecified() => Some(address),
+        _ => None,
+    }
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// `name` as DNS labels, without compression
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        out.push(label.len() as u8);
+        out.extend_from_slice(label);
+    }
+    out.push(0);
+}
+
+fn push_record(out: &mut Vec<u8>, name: &str, rtype: u16, class: u16, data: &[u8]) {
+    push_name(out, name);
+    push_u16(out, rtype);
+    push_u16(out, class);
+    out.extend_from_slice(&TTL.to_be_bytes());
+    push_u16(out, data.len() as u16);
+    out.extend_from_slice(data);
+}
+
+fn read_u16(message: &[u8], pos: usize) -> Option<u16> {
+    let bytes = message.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Name at `pos` and the position after it, following compression
+/// pointers
+fn read_name(message: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the pointers followed, against loops
+    for _ in 0..128 {
+        let len = *message.get(pos)?;
+        match len {
+            0 => {
+                return Some((labels.join("."), end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xC0 == 0xC0 => {
+                let target = usize::from(read_u16(message, pos)? & 0x3FFF);
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            len => {
+                let label = message.get(pos + 1..pos + 1 + usize::from(len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + usize::from(len);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_find_each_other_over_mdns() {
+        let advert = Advert {
+            host: "kitchen".to_string(),
+            name: "Küche".to_string(),
+            version: "1.4.1".to_string(),
+            port: 8888,
+            address: Ipv4Addr::new(192, 168, 1, 20),
+        };
+        let from = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 99));
+
+        for legacy in [false, true] {
+            let reply = answer(&query(), &advert, legacy).unwrap();
+            let peers = parse_answer(&reply, from);
+            assert_eq!(peers.len(), 1);
+            assert_eq!(peers[0].name, "Küche");
+            assert_eq!(peers[0].host, "kitchen.local");
+            assert_eq!(peers[0].url, "http://192.168.1.20:8888/");
+            assert_eq!(peers[0].version, "1.4.1");
+        }
+
+        // Neither other services nor answers are answered
+        let mut other = query();
+        let at = other.windows(8).position(|w| w == b"_epaper-").unwrap();
+        other[at + 1] = b'x';
+        assert!(answer(&other, &advert, false).is_none());
+        let reply = answer(&query(), &advert, false).unwrap();
+        assert!(answer(&reply, &advert, false).is_none());
+        assert!(parse_answer(&query(), from).is_empty());
+        assert!(parse_answer(&reply[..reply.len() - 3], from).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Device access checks.
+//!
+//! The panel needs the SPI bus (`/dev/spidev0.0`) and the GPIO registers
+//! (`/dev/gpiomem`, `/dev/gpiomem0` on the Pi 5). Root can open both; a
+//! normal user needs to be in the `spi` and `gpio` groups, which Raspberry
+//! Pi OS sets up with udev rules. Both devices are opened once at startup,
+//! before the driver, so a missing permission is reported with what to do
+//! about it instead of as an rppal error on the first refresh.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// SPI bus the panel is connected to (SPI0, CE0)
+pub const SPI_DEVICE: &str = "/dev/spidev0.0";
+
+/// GPIO register devices, the first existing one is used
+pub const GPIO_DEVICES: [&str; 2] = ["/dev/gpiomem", "/dev/gpiomem0"];
+
+/// Why a device cannot be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessProblem {
+    /// The device does not exist (SPI disabled, or not a Raspberry Pi)
+    Missing(&'static str),
+    /// The device exists but this user cannot open it
+    Denied(&'static str),
+}
+
+impl fmt::Display for AccessProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessProblem::Missing(SPI_DEVICE) => write!(
+                f,
+                "{} does not exist: enable SPI with `sudo raspi-config nonint do_spi 0` \
+                 (or `dtparam=spi=on` in /boot/firmware/config.txt) and reboot",
+                SPI_DEVICE
+            ),
+            AccessProblem::Missing(device) => write!(
+                f,
+                "{} does not exist: GPIO is only available on a Raspberry Pi",
+                device
+            ),
+            AccessProblem::Denied(device) => {
+                let group = if *device == SPI_DEVICE { "spi" } else { "gpio" };
+                write!(
+                    f,
+                    "Permission denied on {}: add the user to the {} group \
+                     (`sudo usermod -aG spi,gpio $USER`, then log in again), or install \
+                     the udev rules from the README if the group does not exist",
+                    device, group
+                )
+            }
+        }
+    }
+}
+
+impl AccessProblem {
+    /// What to do about the problem when running in a container, where
+    /// devices have to be passed through explicitly
+    pub fn in_container(&self) -> String {
+        match self {
+            AccessProblem::Missing(device) => format!(
+                "{} is not mapped into the container: pass it with `--device {}` \
+                 (`devices:` with Compose)",
+                device, device
+            ),
+            AccessProblem::Denied(device) => format!(
+                "Permission denied on {} in the container: add its group with \
+                 `--group-add` (see `ls -ln {}` on the host) or run as root",
+                device, device
+            ),
+        }
+    }
+}
+
+/// Problems opening the panel's devices, empty if both can be used
+pub fn check() -> Vec<AccessProblem> {
+    let gpio = GPIO_DEVICES
+        .into_iter()
+        .find(|device| Path::new(device).exists())
+        .unwrap_or(GPIO_DEVICES[0]);
+    [SPI_DEVICE, gpio]
+        .into_iter()
+        .filter_map(check_device)
+        .collect()
+}
+
+/// Problem opening `device` for reading and writing, if any
+pub fn check_device(device: &'static str) -> Option<AccessProblem> {
+    match OpenOptions::new().read(true).write(true).open(device) {
+        Ok(_) => None,
+        Err(e) if e.kind() == ErrorKind::NotFound => Some(AccessProblem::Missing(device)),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => Some(AccessProblem::Denied(device)),
+        // Anything else shows up again, with details, when the driver opens it
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inaccessible_panel_devices_come_with_guidance() {
+        assert_eq!(
+            check_device("/nonexistent/spidev9.9"),
+            Some(AccessProblem::Missing("/nonexistent/spidev9.9"))
+        );
+        assert_eq!(check_device("/dev/null"), None);
+
+        assert!(
+            AccessProblem::Missing(SPI_DEVICE)
+                .to_string()
+                .contains("do_spi 0")
+        );
+        let denied = AccessProblem::Denied(SPI_DEVICE).to_string();
+        assert!(denied.contains("spi group") && denied.contains("usermod -aG spi,gpio"));
+        let gpio = AccessProblem::Denied("/dev/gpiomem").to_string();
+        assert!(gpio.contains("gpio group"));
+
+        let hint = AccessProblem::Missing("/dev/spidev0.0").in_container();
+        assert!(hint.contains("--device /dev/spidev0.0"));
+    }
+}
@@ -1,25 +1,33 @@
-//! Waveshare 7.3" E Ink Spectra 6 (EPD7IN3E) display driver.
+//! Waveshare E Ink Spectra 6 display driver (EPD7IN3E and siblings).
 //!
 //! 7-color e-paper display: Black, White, Yellow, Red, Orange, Blue, Green
-//! Resolution: 800 x 480 pixels
+//! Resolution: depends on the panel model (see `panel`)
 //! 4-bit color depth (2 pixels per byte)
+//! No partial refresh: region updates fall back to a full refresh
 //!
-//! Based on official Waveshare Python driver:
+//! Waveshare has shipped batches of the same panel that need slightly
+//! different booster settings. The booster values of the
+//! `spectra6.revisions` entry matching the panel's revision are used
+//! instead of Waveshare's. The revision is configured, or read back from
+//! the controller during init on wiring that connects the panel's data
+//! line to MISO (`spectra6.read_revision`).
+//!
+//! Based on official Waveshare drivers:
 //! https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in3e.py
-
-use super::gpio::{GpioController, GpioError};
-use super::spi::{SpiDisplay, SpiError};
-use std::thread;
+//! https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd4in0e.py
+//!
+//! The 13.3" panel has two controllers, each driving one half of every
+//! row, selected through their own chip select pins. Its init values
+//! follow Waveshare's 13.3" E6 HAT+ C driver.
+
+use super::gpio::{Chip, GpioController, GpioError, pins};
+use super::spi::{SpiConfig, SpiDisplay, SpiError};
+use super::{DisplayBackend, Palette, PanelModel, PixelFormat};
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use thiserror::Error;
 
-/// Display dimensions
-pub const WIDTH: u32 = 800;
-pub const HEIGHT: u32 = 480;
-
-/// Buffer size: 2 pixels per byte (4-bit color)
-pub const BUFFER_SIZE: usize = (WIDTH as usize * HEIGHT as usize) / 2;
-
 /// EPD commands (from official Waveshare driver)
 #[allow(dead_code)]
 mod cmd {
@@ -41,6 +49,16 @@ mod cmd {
     pub const BOOSTER_SOFT_START2: u8 = 0x06;
     pub const BOOSTER_SOFT_START3: u8 = 0x08;
     pub const UNKNOWN_84: u8 = 0x84;
+    pub const REVISION: u8 = 0x70;
+    // Dual-controller (13.3") panels only
+    pub const CCSET: u8 = 0xE0;
+    pub const AN_TM: u8 = 0x74;
+    pub const AGID: u8 = 0x86;
+    pub const BUCK_BOOST_VDDN: u8 = 0xB0;
+    pub const TFT_VCOM_POWER: u8 = 0xB1;
+    pub const EN_BUF: u8 = 0xB6;
+    pub const BOOST_VDDP_EN: u8 = 0xB7;
+    pub const CMD66: u8 = 0xF0;
 }
 
 /// 7-color palette indices
@@ -56,6 +74,17 @@ pub enum Color {
     Green = 6,
 }
 
+/// Colors accepted by the panel, in index order
+pub const PALETTE: [Color; 7] = [
+    Color::Black,
+    Color::White,
+    Color::Yellow,
+    Color::Red,
+    Color::Orange,
+    Color::Blue,
+    Color::Green,
+];
+
 /// Display driver errors
 #[derive(Error, Debug)]
 pub enum DisplayError {
@@ -70,38 +99,231 @@ pub enum DisplayError {
 
     #[error("Invalid buffer size: expected {expected}, got {actual}")]
     InvalidBufferSize { expected: usize, actual: usize },
+
+    #[error("Region {0:?} outside the panel")]
+    InvalidRegion(super::Region),
+
+    #[error("Display thread stopped")]
+    ThreadStopped,
+
+    #[error("Display did not respond within {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Invalid raw command: {0}")]
+    InvalidCommand(String),
+}
+
+/// Booster soft start values of a single-controller panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boosters {
+    /// Booster soft start 1 (0x05)
+    pub soft_start1: [u8; 4],
+    /// Booster soft start 2 (0x06)
+    pub soft_start2: [u8; 4],
+    /// Booster soft start 3 (0x08)
+    pub soft_start3: [u8; 4],
+}
+
+impl Boosters {
+    /// Values of Waveshare's driver for `model`
+    pub fn for_model(model: PanelModel) -> Self {
+        // The 4" panel ends booster soft start 2 differently
+        let booster2_end = match model {
+            PanelModel::Epd4in0e => 0x17,
+            _ => 0x49,
+        };
+        Self {
+            soft_start1: [0x40, 0x1F, 0x1F, 0x2C],
+            soft_start2: [0x6F, 0x1F, 0x17, booster2_end],
+            soft_start3: [0x6F, 0x1F, 0x1F, 0x22],
+        }
+    }
+}
+
+/// Booster values for one panel revision
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RevisionInit {
+    /// Panel revision, 4 hex digits (e.g. `"0a03"`), or `unknown` for
+    /// panels whose revision is neither configured nor read back
+    pub revision: String,
+    /// Booster soft start 1 (0x05); unset keeps Waveshare's values
+    #[serde(default)]
+    pub booster1: Option<[u8; 4]>,
+    /// Booster soft start 2 (0x06); unset keeps Waveshare's values
+    #[serde(default)]
+    pub booster2: Option<[u8; 4]>,
+    /// Booster soft start 3 (0x08); unset keeps Waveshare's values
+    #[serde(default)]
+    pub booster3: Option<[u8; 4]>,
+}
+
+/// Spectra 6 panel settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Spectra6Config {
+    /// Booster values for panel revisions that need other settings than
+    /// Waveshare's drivers use
+    #[serde(default)]
+    pub revisions: Vec<RevisionInit>,
+    /// Revision of the attached panel, 4 hex digits (empty = `unknown`);
+    /// ignored with `read_revision`
+    #[serde(default)]
+    pub revision: String,
+    /// Read the revision back from the controller during init; only for
+    /// wiring that connects the panel's data line to MISO, which the
+    /// Waveshare HATs do not
+    #[serde(default)]
+    pub read_revision: bool,
+}
+
+impl Spectra6Config {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.revision.trim().is_empty() && self.configured_revision().is_none() {
+            return Err(ConfigError::ValidationError(format!(
+                "spectra6.revision '{}' must be 4 hex digits",
+                self.revision
+            )));
+        }
+        let mut seen = Vec::new();
+        for entry in &self.revisions {
+            let revision = entry.revision.trim().to_ascii_lowercase();
+            if revision != "unknown" && parse_revision(&revision).is_none() {
+                return Err(ConfigError::ValidationError(format!(
+                    "spectra6.revisions: revision '{}' must be 4 hex digits or 'unknown'",
+                    entry.revision
+                )));
+            }
+            if seen.contains(&revision) {
+                return Err(ConfigError::ValidationError(format!(
+                    "spectra6.revisions: revision '{}' is listed twice",
+                    entry.revision
+                )));
+            }
+            seen.push(revision);
+        }
+        Ok(())
+    }
+
+    /// Configured `revision`, `None` if unset
+    pub fn configured_revision(&self) -> Option<[u8; 2]> {
+        parse_revision(self.revision.trim())
+    }
+
+    /// Booster values for a `model` panel of `revision` (`None` if
+    /// unknown)
+    pub fn boosters(&self, model: PanelModel, revision: Option<[u8; 2]>) -> Boosters {
+        let wanted = revision.map_or_else(|| "unknown".to_string(), revision_hex);
+        let mut boosters = Boosters::for_model(model);
+        if let Some(entry) = self
+            .revisions
+            .iter()
+            .find(|entry| entry.revision.trim().eq_ignore_ascii_case(&wanted))
+        {
+            boosters.soft_start1 = entry.booster1.unwrap_or(boosters.soft_start1);
+            boosters.soft_start2 = entry.booster2.unwrap_or(boosters.soft_start2);
+            boosters.soft_start3 = entry.booster3.unwrap_or(boosters.soft_start3);
+        }
+        boosters
+    }
 }
 
-/// EPD7IN3E display driver
+/// `revision` as written in `spectra6.revisions`
+fn revision_hex(revision: [u8; 2]) -> String {
+    format!("{:02x}{:02x}", revision[0], revision[1])
+}
+
+/// Revision written as 4 hex digits
+fn parse_revision(text: &str) -> Option<[u8; 2]> {
+    if text.len() != 4 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u16::from_str_radix(text, 16).ok().map(u16::to_be_bytes)
+}
+
+/// Spectra 6 display driver
 pub struct Epd7in3e {
     gpio: GpioController,
     spi: SpiDisplay,
+    model: PanelModel,
+    settings: Spectra6Config,
     initialized: bool,
 }
 
 impl Epd7in3e {
-    /// Create a new display driver instance
-    pub fn new() -> Result<Self, DisplayError> {
-        let gpio = GpioController::new()?;
-        let spi = SpiDisplay::new()?;
+    /// Create a new display driver instance for `model`
+    pub fn new(
+        model: PanelModel,
+        settings: Spectra6Config,
+        spi: &SpiConfig,
+    ) -> Result<Self, DisplayError> {
+        let chip_selects: &[u8] = if model.dual_controller() {
+            &[pins::CS_M, pins::CS_S]
+        } else {
+            &[]
+        };
+        let gpio = GpioController::new(chip_selects)?;
+        let spi = SpiDisplay::new(spi)?;
 
         Ok(Self {
             gpio,
             spi,
+            model,
+            settings,
             initialized: false,
         })
     }
 
     /// Initialize the display hardware
-    /// Based on official Waveshare epd7in3e.py init() sequence
     pub fn init(&mut self) -> Result<(), DisplayError> {
-        tracing::info!("Initializing EPD7IN3E display ({}x{})", WIDTH, HEIGHT);
+        tracing::info!(
+            "Initializing {} display ({}x{})",
+            self.model.as_str(),
+            self.model.width(),
+            self.model.height()
+        );
+
+        if self.model.dual_controller() {
+            self.init_dual()?;
+        } else {
+            self.init_single()?;
+        }
+
+        self.initialized = true;
+        tracing::info!("Display initialized successfully");
+
+        Ok(())
+    }
+
+    /// Init sequence of single-controller panels
+    /// Based on official Waveshare epd7in3e.py / epd4in0e.py init() sequence
+    fn init_single(&mut self) -> Result<(), DisplayError> {
+        // The 4" panel differs in PLL value
+        let pll = match self.model {
+            PanelModel::Epd4in0e => 0x08,
+            _ => 0x03,
+        };
 
         // Power on and reset
         self.gpio.power_on();
         self.gpio.reset();
         self.gpio.wait_busy()?;
-        thread::sleep(Duration::from_millis(30));
+        self.gpio.delay(Duration::from_millis(30));
+
+        // Booster values for the panel's revision
+        let revision = if self.settings.read_revision {
+            let revision = self.read_revision()?;
+            match revision {
+                Some(revision) => tracing::info!("Panel revision {}", revision_hex(revision)),
+                None => tracing::warn!("Panel revision cannot be read, is MISO connected?"),
+            }
+            revision
+        } else {
+            self.settings.configured_revision()
+        };
+        let boosters = self.settings.boosters(self.model, revision);
+        if boosters != Boosters::for_model(self.model) {
+            tracing::info!("Using booster settings from spectra6.revisions");
+        }
 
         // Command header (0xAA)
         self.send_command_data(cmd::CMDH, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])?;
@@ -116,16 +338,16 @@ impl Epd7in3e {
         self.send_command_data(cmd::INPUT_DATA, &[0x00, 0x54, 0x00, 0x44])?;
 
         // Booster soft start 1 (0x05)
-        self.send_command_data(cmd::BOOSTER_SOFT_START1, &[0x40, 0x1F, 0x1F, 0x2C])?;
+        self.send_command_data(cmd::BOOSTER_SOFT_START1, &boosters.soft_start1)?;
 
         // Booster soft start 2 (0x06)
-        self.send_command_data(cmd::BOOSTER_SOFT_START2, &[0x6F, 0x1F, 0x17, 0x49])?;
+        self.send_command_data(cmd::BOOSTER_SOFT_START2, &boosters.soft_start2)?;
 
         // Booster soft start 3 (0x08)
-        self.send_command_data(cmd::BOOSTER_SOFT_START3, &[0x6F, 0x1F, 0x1F, 0x22])?;
+        self.send_command_data(cmd::BOOSTER_SOFT_START3, &boosters.soft_start3)?;
 
         // PLL control (0x30)
-        self.send_command_data(cmd::PLL_CONTROL, &[0x03])?;
+        self.send_command_data(cmd::PLL_CONTROL, &[pll])?;
 
         // VCOM and data interval (0x50)
         self.send_command_data(cmd::VCOM_DATA_INTERVAL, &[0x3F])?;
@@ -133,8 +355,10 @@ impl Epd7in3e {
         // TCON setting (0x60)
         self.send_command_data(cmd::TCON_SETTING, &[0x02, 0x00])?;
 
-        // Resolution setting (0x61) - 800 x 480 = 0x0320 x 0x01E0
-        self.send_command_data(cmd::RESOLUTION_SETTING, &[0x03, 0x20, 0x01, 0xE0])?;
+        // Resolution setting (0x61) - width and height, 16-bit big endian
+        let [w_hi, w_lo] = (self.model.width() as u16).to_be_bytes();
+        let [h_hi, h_lo] = (self.model.height() as u16).to_be_bytes();
+        self.send_command_data(cmd::RESOLUTION_SETTING, &[w_hi, w_lo, h_hi, h_lo])?;
 
         // Unknown command 0x84
         self.send_command_data(cmd::UNKNOWN_84, &[0x01])?;
@@ -146,8 +370,66 @@ impl Epd7in3e {
         self.send_command(cmd::POWER_ON)?;
         self.gpio.wait_busy()?;
 
-        self.initialized = true;
-        tracing::info!("Display initialized successfully");
+        Ok(())
+    }
+
+    /// Revision reported by the controller (0x70), `None` if it cannot be
+    /// read
+    ///
+    /// Clocks two bytes out as data while reading, so it is only done with
+    /// `read_revision`. The Waveshare HATs do not connect the panel's data
+    /// line to MISO; reads there return all zeros or all ones and the
+    /// stray bytes reach a controller in an unknown state.
+    fn read_revision(&mut self) -> Result<Option<[u8; 2]>, DisplayError> {
+        let mut revision = [0; 2];
+        self.spi.write_command(&mut self.gpio, cmd::REVISION)?;
+        self.gpio.dc_high();
+        self.spi.read_raw(&mut revision)?;
+        Ok((revision != [0x00; 2] && revision != [0xFF; 2]).then_some(revision))
+    }
+
+    /// Init sequence of the dual-controller 13.3" panel
+    ///
+    /// Panel settings go to both controllers; power and booster settings
+    /// only to the main one.
+    fn init_dual(&mut self) -> Result<(), DisplayError> {
+        self.gpio.power_on();
+        self.gpio.reset();
+        self.gpio.wait_busy()?;
+
+        let main = [
+            (cmd::AN_TM, &[0xC0, 0x1C, 0x1C, 0xCC, 0xCC, 0xCC, 0x15, 0x15, 0x55][..]),
+        ];
+        let both = [
+            (cmd::CMD66, &[0x49, 0x55, 0x13, 0x5D, 0x05, 0x10][..]),
+            (cmd::PANEL_SETTING, &[0xDF, 0x69][..]),
+            (cmd::VCOM_DATA_INTERVAL, &[0xF7][..]),
+            (cmd::TCON_SETTING, &[0x03, 0x03][..]),
+            (cmd::AGID, &[0x10][..]),
+            (cmd::POWER_SAVING, &[0x22][..]),
+            (cmd::CCSET, &[0x01][..]),
+            // Resolution per controller: 1200 x 800
+            (cmd::RESOLUTION_SETTING, &[0x04, 0xB0, 0x03, 0x20][..]),
+        ];
+        let main_power = [
+            (cmd::POWER_SETTING, &[0x0F, 0x00, 0x28, 0x2C, 0x28, 0x38][..]),
+            (cmd::EN_BUF, &[0x07][..]),
+            (cmd::BOOSTER_SOFT_START2, &[0xE8, 0x28][..]),
+            (cmd::BOOST_VDDP_EN, &[0x01][..]),
+            (cmd::BOOSTER_SOFT_START1, &[0xE8, 0x28][..]),
+            (cmd::BUCK_BOOST_VDDN, &[0x01][..]),
+            (cmd::TFT_VCOM_POWER, &[0x02][..]),
+        ];
+
+        for (command, data) in main {
+            self.send_to(Chip::Main, command, data)?;
+        }
+        for (command, data) in both {
+            self.send_to(Chip::Both, command, data)?;
+        }
+        for (command, data) in main_power {
+            self.send_to(Chip::Main, command, data)?;
+        }
 
         Ok(())
     }
@@ -155,25 +437,25 @@ impl Epd7in3e {
     /// Display image data from buffer
     ///
     /// Buffer should contain packed 4-bit pixel data (2 pixels per byte)
+    /// and match the panel resolution exactly.
     /// Based on official Waveshare display() and TurnOnDisplay() sequence
-    ///
-    /// Note: Buffer size should match the display dimensions (800x480 / 2 = 192000 bytes)
-    /// but we allow flexibility for different configured dimensions as the display
-    /// hardware will handle the data stream.
     pub fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
         if !self.initialized {
             return Err(DisplayError::NotInitialized);
         }
 
-        // Validate buffer size matches expected display size
-        // The hardware expects exactly BUFFER_SIZE bytes for 800x480 display
-        if buffer.len() != BUFFER_SIZE {
+        // The controller expects exactly one full frame
+        let expected = self.model.buffer_size();
+        if buffer.len() != expected {
             tracing::warn!(
                 "Buffer size mismatch: expected {} bytes for {}x{}, got {} bytes",
-                BUFFER_SIZE, WIDTH, HEIGHT, buffer.len()
+                expected,
+                self.model.width(),
+                self.model.height(),
+                buffer.len()
             );
             return Err(DisplayError::InvalidBufferSize {
-                expected: BUFFER_SIZE,
+                expected,
                 actual: buffer.len(),
             });
         }
@@ -181,8 +463,15 @@ impl Epd7in3e {
         tracing::info!("Sending image data to display ({} bytes)", buffer.len());
 
         // Send image data (command 0x10)
-        self.send_command(cmd::DATA_START)?;
-        self.spi.write_data_bulk(&mut self.gpio, buffer)?;
+        if self.model.dual_controller() {
+            // Left half of each row to the main controller, right half to the second
+            let half = (self.model.width() / 4) as usize;
+            self.send_halves(Chip::Main, buffer, 0..half)?;
+            self.send_halves(Chip::Second, buffer, half..2 * half)?;
+        } else {
+            self.send_command(cmd::DATA_START)?;
+            self.spi.write_data_bulk(&mut self.gpio, buffer)?;
+        }
 
         // TurnOnDisplay sequence from official driver
         self.turn_on_display()?;
@@ -217,7 +506,7 @@ impl Epd7in3e {
         }
 
         let pixel = (color as u8) << 4 | (color as u8);
-        let buffer = vec![pixel; BUFFER_SIZE];
+        let buffer = vec![pixel; self.model.buffer_size()];
 
         tracing::info!("Clearing display to {:?}", color);
         self.display(&buffer)
@@ -231,10 +520,11 @@ impl Epd7in3e {
 
         tracing::info!("Displaying test pattern");
 
-        let mut buffer = vec![0u8; BUFFER_SIZE];
-        let stripe_height = HEIGHT / 7;
+        let (width, height) = (self.model.width(), self.model.height());
+        let mut buffer = vec![0u8; self.model.buffer_size()];
+        let stripe_height = height / 7;
 
-        for y in 0..HEIGHT {
+        for y in 0..height {
             let color = match y / stripe_height {
                 0 => Color::Black,
                 1 => Color::White,
@@ -247,8 +537,8 @@ impl Epd7in3e {
 
             let packed = (color << 4) | color;
 
-            for x in (0..WIDTH).step_by(2) {
-                let idx = ((y * WIDTH + x) / 2) as usize;
+            for x in (0..width).step_by(2) {
+                let idx = ((y * width + x) / 2) as usize;
                 buffer[idx] = packed;
             }
         }
@@ -282,13 +572,78 @@ impl Epd7in3e {
 
     /// Send command to display
     fn send_command(&mut self, cmd: u8) -> Result<(), DisplayError> {
-        self.spi.write_command(&mut self.gpio, cmd)?;
-        Ok(())
+        self.send_to(Chip::Both, cmd, &[])
     }
 
     /// Send command with data to display
     fn send_command_data(&mut self, cmd: u8, data: &[u8]) -> Result<(), DisplayError> {
-        self.spi.write_command_data(&mut self.gpio, cmd, data)?;
+        self.send_to(Chip::Both, cmd, data)
+    }
+
+    /// Send command with data to the given controller(s)
+    ///
+    /// Single-controller panels ignore `chip`.
+    fn send_to(&mut self, chip: Chip, cmd: u8, data: &[u8]) -> Result<(), DisplayError> {
+        self.gpio.select(chip);
+        let result = self.spi.write_command_data(&mut self.gpio, cmd, data);
+        self.gpio.deselect();
+        Ok(result?)
+    }
+
+    /// Send the `columns` byte range of every row to one controller
+    fn send_halves(
+        &mut self,
+        chip: Chip,
+        buffer: &[u8],
+        columns: std::ops::Range<usize>,
+    ) -> Result<(), DisplayError> {
+        let row_bytes = (self.model.width() / 2) as usize;
+
+        self.gpio.select(chip);
+        let result = self
+            .spi
+            .write_command(&mut self.gpio, cmd::DATA_START)
+            .and_then(|()| {
+                buffer.chunks(row_bytes).try_for_each(|row| {
+                    self.spi.write_data_bulk(&mut self.gpio, &row[columns.clone()])
+                })
+            });
+        self.gpio.deselect();
+        Ok(result?)
+    }
+}
+
+impl DisplayBackend for Epd7in3e {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        Epd7in3e::init(self)
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::packed4(Palette::Colors(&PALETTE))
+    }
+
+    fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        Epd7in3e::display(self, buffer)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        Epd7in3e::clear(self, color)
+    }
+
+    fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        Epd7in3e::test_pattern(self)
+    }
+
+    fn sleep(&mut self) -> Result<(), DisplayError> {
+        Epd7in3e::sleep(self)
+    }
+
+    fn raw_command(&mut self, command: u16, data: &[u8]) -> Result<(), DisplayError> {
+        let command = u8::try_from(command).map_err(|_| {
+            DisplayError::InvalidCommand(format!("0x{:04X} is not an 8-bit command", command))
+        })?;
+        self.send_command_data(command, data)?;
+        self.gpio.wait_busy()?;
         Ok(())
     }
 }
@@ -301,3 +656,57 @@ impl Drop for Epd7in3e {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panel_revisions_pick_booster_settings() {
+        let settings: Spectra6Config = serde_json::from_str(
+            r#"{ "revisions": [
+                { "revision": "0A03", "booster2": [111, 31, 23, 23] },
+                { "revision": "unknown", "booster1": [64, 31, 31, 40] }
+            ] }"#,
+        )
+        .unwrap();
+        settings.validate().unwrap();
+
+        let waveshare = Boosters::for_model(PanelModel::Epd7in3e);
+        let batch = settings.boosters(PanelModel::Epd7in3e, Some([0x0a, 0x03]));
+        assert_eq!(batch.soft_start2, [0x6F, 0x1F, 0x17, 0x17]);
+        assert_eq!(batch.soft_start1, waveshare.soft_start1);
+        assert_eq!(
+            settings.boosters(PanelModel::Epd7in3e, Some([0x0b, 0x01])),
+            waveshare
+        );
+        assert_eq!(
+            settings.boosters(PanelModel::Epd7in3e, None).soft_start1,
+            [0x40, 0x1F, 0x1F, 0x28]
+        );
+
+        let configured = Spectra6Config {
+            revision: "0A03".to_string(),
+            ..settings.clone()
+        };
+        assert_eq!(configured.configured_revision(), Some([0x0a, 0x03]));
+        assert_eq!(Spectra6Config::default().configured_revision(), None);
+        let invalid = Spectra6Config {
+            revision: "rev3".to_string(),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = settings.clone();
+        invalid.revisions.push(RevisionInit {
+            revision: "a03".to_string(),
+            ..Default::default()
+        });
+        assert!(invalid.validate().is_err());
+        let mut twice = settings;
+        twice.revisions.push(RevisionInit {
+            revision: "0a03".to_string(),
+            ..Default::default()
+        });
+        assert!(twice.validate().is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Waveshare black/white/red (3-color) display driver.
+//!
+//! Covers the 2.13" (V4), 4.2" (V2) and 7.5" (V2) B/W/R panels.
+//! The panels take two 1-bit planes (8 pixels per byte, rows padded to
+//! whole bytes): one for black ink and one for red ink.
+//! No partial refresh: region updates fall back to a full refresh
+//!
+//! Based on official Waveshare C drivers:
+//! https://github.com/waveshare/e-Paper/tree/master/RaspberryPi_JetsonNano/c/lib/e-Paper
+//! (EPD_2in13b_V4.c, EPD_4in2b_V2.c, EPD_7in5b_V2.c)
+
+use super::gpio::GpioController;
+use super::panel::PALETTE_BWR;
+use super::spi::{SpiConfig, SpiDisplay};
+use super::{Color, DisplayBackend, DisplayError, Palette, PanelModel, PixelFormat};
+use std::time::Duration;
+
+/// Controller differences between the supported panels
+struct Variant {
+    /// Command writing the black plane
+    black_cmd: u8,
+    /// Command writing the red plane
+    red_cmd: u8,
+    /// Whether a set bit in the red plane means red ink (else cleared bit)
+    red_bit_set: bool,
+    /// SSD16xx controllers report busy with BUSY high
+    busy_high: bool,
+}
+
+impl Variant {
+    fn of(model: PanelModel) -> Self {
+        match model {
+            PanelModel::Epd2in13bV4 => Self {
+                black_cmd: 0x24,
+                red_cmd: 0x26,
+                red_bit_set: true,
+                busy_high: true,
+            },
+            PanelModel::Epd4in2bV2 => Self {
+                black_cmd: 0x10,
+                red_cmd: 0x13,
+                red_bit_set: false,
+                busy_high: false,
+            },
+            _ => Self {
+                black_cmd: 0x10,
+                red_cmd: 0x13,
+                red_bit_set: true,
+                busy_high: false,
+            },
+        }
+    }
+}
+
+/// Split a packed 4-bit color frame into black and red planes
+///
+/// Black plane: cleared bit = black ink. Colors the panel cannot show
+/// map to red (warm colors) or black (cool colors).
+pub fn to_planes(frame: &[u8], width: u32, height: u32, red_bit_set: bool) -> (Vec<u8>, Vec<u8>) {
+    let row_bytes = width.div_ceil(8) as usize;
+    let mut black = vec![0xFFu8; row_bytes * height as usize];
+    let mut red = vec![if red_bit_set { 0x00 } else { 0xFF }; row_bytes * height as usize];
+
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            let index = y * width as usize + x;
+            let byte = frame.get(index / 2).copied().unwrap_or(0x11);
+            let code = if index.is_multiple_of(2) {
+                byte >> 4
+            } else {
+                byte & 0x0F
+            };
+
+            let (is_black, is_red) = match code {
+                c if c == Color::White as u8 => (false, false),
+                c if c == Color::Red as u8
+                    || c == Color::Orange as u8
+                    || c == Color::Yellow as u8 =>
+                {
+                    (false, true)
+                }
+                _ => (true, false),
+            };
+
+            let bit = 0x80 >> (x % 8);
+            let at = y * row_bytes + x / 8;
+            if is_black {
+                black[at] &= !bit;
+            }
+            if is_red {
+                if red_bit_set {
+                    red[at] |= bit;
+                } else {
+                    red[at] &= !bit;
+                }
+            }
+        }
+    }
+
+    (black, red)
+}
+
+/// B/W/R display driver
+pub struct EpdBwr {
+    gpio: GpioController,
+    spi: SpiDisplay,
+    model: PanelModel,
+    variant: Variant,
+    initialized: bool,
+}
+
+impl EpdBwr {
+    /// Create a new display driver instance for `model`
+    pub fn new(model: PanelModel, spi: &SpiConfig) -> Result<Self, DisplayError> {
+        let gpio = GpioController::new(&[])?;
+        let spi = SpiDisplay::new(spi)?;
+
+        Ok(Self {
+            gpio,
+            spi,
+            model,
+            variant: Variant::of(model),
+            initialized: false,
+        })
+    }
+
+    /// Initialize the display hardware
+    pub fn init(&mut self) -> Result<(), DisplayError> {
+        tracing::info!(
+            "Initializing {} display ({}x{})",
+            self.model.as_str(),
+            self.model.width(),
+            self.model.height()
+        );
+
+        self.gpio.power_on();
+        self.gpio.reset();
+
+        match self.model {
+            PanelModel::Epd2in13bV4 => {
+                let (width, height) = (self.model.width(), self.model.height());
+                let x_end = ((width - 1) >> 3) as u8;
+                let [y_end_hi, y_end_lo] = ((height - 1) as u16).to_be_bytes();
+
+                self.wait_busy()?;
+                self.send_command(0x12)?; // Software reset
+                self.wait_busy()?;
+                self.send_command_data(0x01, &[0xF9, 0x00, 0x00])?; // Driver output control
+                self.send_command_data(0x11, &[0x03])?; // Data entry mode: x+, y+
+                self.send_command_data(0x44, &[0x00, x_end])?; // RAM x window
+                self.send_command_data(0x45, &[0x00, 0x00, y_end_lo, y_end_hi])?; // RAM y window
+                self.send_command_data(0x4E, &[0x00])?; // RAM x counter
+                self.send_command_data(0x4F, &[0x00, 0x00])?; // RAM y counter
+                self.send_command_data(0x3C, &[0x05])?; // Border waveform
+                self.send_command_data(0x18, &[0x80])?; // Internal temperature sensor
+                self.send_command_data(0x21, &[0x80, 0x80])?; // Display update control
+                self.wait_busy()?;
+            }
+            PanelModel::Epd4in2bV2 => {
+                self.send_command(0x04)?; // Power on
+                self.wait_busy()?;
+                self.send_command_data(0x00, &[0x0F])?; // Panel setting
+            }
+            _ => {
+                let [w_hi, w_lo] = (self.model.width() as u16).to_be_bytes();
+                let [h_hi, h_lo] = (self.model.height() as u16).to_be_bytes();
+
+                self.send_command_data(0x01, &[0x07, 0x07, 0x3F, 0x3F])?; // Power setting
+                self.send_command_data(0x06, &[0x17, 0x17, 0x28, 0x17])?; // Booster soft start
+                self.send_command(0x04)?; // Power on
+                self.gpio.delay(Duration::from_millis(100));
+                self.wait_busy()?;
+                self.send_command_data(0x00, &[0x0F])?; // Panel setting
+                self.send_command_data(0x61, &[w_hi, w_lo, h_hi, h_lo])?; // Resolution
+                self.send_command_data(0x15, &[0x00])?; // Dual SPI off
+                self.send_command_data(0x50, &[0x11, 0x07])?; // VCOM and data interval
+                self.send_command_data(0x60, &[0x22])?; // TCON setting
+            }
+        }
+
+        self.initialized = true;
+        tracing::info!("Display initialized successfully");
+
+        Ok(())
+    }
+
+    /// Display a packed 4-bit color frame
+    ///
+    /// Buffer should match the panel resolution exactly; it is split into
+    /// the black and red planes the panel expects.
+    pub fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        if !self.initialized {
+            return Err(DisplayError::NotInitialized);
+        }
+
+        let expected = self.model.buffer_size();
+        if buffer.len() != expected {
+            return Err(DisplayError::InvalidBufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+
+        let (black, red) = to_planes(
+            buffer,
+            self.model.width(),
+            self.model.height(),
+            self.variant.red_bit_set,
+        );
+
+        tracing::info!(
+            "Sending image planes to display ({} bytes each)",
+            black.len()
+        );
+
+        self.send_command_data(self.variant.black_cmd, &black)?;
+        self.send_command_data(self.variant.red_cmd, &red)?;
+
+        // Refresh: SSD16xx master activation, UC81xx display refresh
+        if self.variant.busy_high {
+            self.send_command(0x20)?;
+        } else {
+            self.send_command(0x12)?;
+            self.gpio.delay(Duration::from_millis(100));
+        }
+        tracing::info!("Waiting for display refresh to complete...");
+        self.wait_busy()?;
+
+        tracing::info!("Display refresh complete");
+        Ok(())
+    }
+
+    /// Clear display to a single color
+    pub fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        if !self.initialized {
+            self.init()?;
+        }
+
+        let pixel = (color as u8) << 4 | (color as u8);
+        let buffer = vec![pixel; self.model.buffer_size()];
+
+        tracing::info!("Clearing display to {:?}", color);
+        self.display(&buffer)
+    }
+
+    /// Display test pattern: black, white and red stripes
+    pub fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        if !self.initialized {
+            self.init()?;
+        }
+
+        tracing::info!("Displaying test pattern");
+
+        let (width, height) = (self.model.width(), self.model.height());
+        let row_bytes = (width / 2) as usize;
+        let mut buffer = vec![0u8; self.model.buffer_size()];
+
+        for (y, row) in buffer.chunks_mut(row_bytes).enumerate() {
+            let color = match y as u32 * 3 / height {
+                0 => Color::Black,
+                1 => Color::White,
+                _ => Color::Red,
+            } as u8;
+            row.fill((color << 4) | color);
+        }
+
+        self.display(&buffer)
+    }
+
+    /// Put display into deep sleep mode
+    pub fn sleep(&mut self) -> Result<(), DisplayError> {
+        tracing::info!("Putting display to sleep");
+
+        if self.variant.busy_high {
+            self.send_command_data(0x10, &[0x01])?; // Deep sleep
+        } else {
+            if self.model == PanelModel::Epd4in2bV2 {
+                self.send_command_data(0x50, &[0xF7])?;
+            }
+            self.send_command(0x02)?; // Power off
+            self.wait_busy()?;
+            self.send_command_data(0x07, &[0xA5])?; // Deep sleep
+        }
+
+        self.gpio.power_off();
+        self.initialized = false;
+
+        Ok(())
+    }
+
+    /// Wait until the controller is idle, in its BUSY polarity
+    fn wait_busy(&self) -> Result<(), DisplayError> {
+        if self.variant.busy_high {
+            self.gpio.wait_busy_high()?;
+        } else {
+            self.gpio.wait_busy()?;
+        }
+        Ok(())
+    }
+
+    /// Send command to display
+    fn send_command(&mut self, cmd: u8) -> Result<(), DisplayError> {
+        self.spi.write_command(&mut self.gpio, cmd)?;
+        Ok(())
+    }
+
+    /// Send command with data to display
+    fn send_command_data(&mut self, cmd: u8, data: &[u8]) -> Result<(), DisplayError> {
+        self.spi.write_command_data(&mut self.gpio, cmd, data)?;
+        Ok(())
+    }
+}
+
+impl DisplayBackend for EpdBwr {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        EpdBwr::init(self)
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::packed4(Palette::Colors(&PALETTE_BWR))
+    }
+
+    fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        EpdBwr::display(self, buffer)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        EpdBwr::clear(self, color)
+    }
+
+    fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        EpdBwr::test_pattern(self)
+    }
+
+    fn sleep(&mut self) -> Result<(), DisplayError> {
+        EpdBwr::sleep(self)
+    }
+
+    fn raw_command(&mut self, command: u16, data: &[u8]) -> Result<(), DisplayError> {
+        let command = u8::try_from(command).map_err(|_| {
+            DisplayError::InvalidCommand(format!("0x{:04X} is not an 8-bit command", command))
+        })?;
+        self.send_command_data(command, data)?;
+        self.wait_busy()
+    }
+}
+
+impl Drop for EpdBwr {
+    fn drop(&mut self) {
+        if self.initialized {
+            let _ = self.sleep();
+        }
+    }
+}
@@ -5,8 +5,14 @@
 //! - DC (Data/Command): GPIO 25
 //! - BUSY: GPIO 24
 //! - PWR (Power): GPIO 18
+//! - CS_M / CS_S (chip selects): GPIO 8 / GPIO 7, only on panels whose
+//!   driver controls chip select itself
 
+#[cfg(not(hardware))]
+use super::no_hardware as rppal;
+use super::trace::{self, Event};
 use rppal::gpio::{Gpio, InputPin, Level, OutputPin};
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
 use std::time::Duration;
 use thiserror::Error;
@@ -17,8 +23,24 @@ pub mod pins {
     pub const DC: u8 = 25;    // Data/Command pin
     pub const BUSY: u8 = 24;  // Busy status pin
     pub const PWR: u8 = 18;   // Power control pin
+    pub const CS_M: u8 = 8;   // Main controller chip select (manual CS only)
+    pub const CS_S: u8 = 7;   // Second controller chip select (dual-controller panels)
 }
 
+/// Which controller(s) to address on panels with manual chip select
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    Main,
+    Second,
+    Both,
+}
+
+/// Handle to the PWR pin of the live controller, used by the panic hook
+///
+/// The panic hook cannot rely on `Drop` (release builds abort on panic),
+/// so the controller registers its PWR pin here on creation.
+static EMERGENCY_PWR: Mutex<Option<Weak<Mutex<OutputPin>>>> = Mutex::new(None);
+
 /// GPIO-related errors
 #[derive(Error, Debug)]
 pub enum GpioError {
@@ -33,13 +55,18 @@ pub enum GpioError {
 pub struct GpioController {
     rst: OutputPin,
     dc: OutputPin,
-    pwr: OutputPin,
+    pwr: Arc<Mutex<OutputPin>>,
     busy: InputPin,
+    /// Manually driven chip selects, main controller first
+    cs: Vec<OutputPin>,
 }
 
 impl GpioController {
     /// Initialize GPIO pins for display control
-    pub fn new() -> Result<Self, GpioError> {
+    ///
+    /// The `chip_selects` pins (main controller first) are claimed and
+    /// driven manually; SPI must then run without hardware chip select.
+    pub fn new(chip_selects: &[u8]) -> Result<Self, GpioError> {
         let gpio = Gpio::new()?;
 
         let mut rst = gpio.get(pins::RST)?.into_output();
@@ -60,12 +87,36 @@ impl GpioController {
             pins::PWR
         );
 
-        Ok(Self { rst, dc, pwr, busy })
+        let mut cs = Vec::with_capacity(chip_selects.len());
+        for &pin in chip_selects {
+            let mut pin = gpio.get(pin)?.into_output();
+            pin.set_high();
+            cs.push(pin);
+        }
+        if !chip_selects.is_empty() {
+            tracing::debug!("Manual chip selects: {:?}", chip_selects);
+        }
+
+        trace::record(Event::Open(chip_selects.into()));
+
+        let pwr = Arc::new(Mutex::new(pwr));
+        if let Ok(mut slot) = EMERGENCY_PWR.lock() {
+            *slot = Some(Arc::downgrade(&pwr));
+        }
+
+        Ok(Self {
+            rst,
+            dc,
+            pwr,
+            busy,
+            cs,
+        })
     }
 
     /// Perform hardware reset sequence
     pub fn reset(&mut self) {
         tracing::debug!("Performing hardware reset");
+        trace::record(Event::Reset);
 
         self.rst.set_high();
         thread::sleep(Duration::from_millis(20));
@@ -87,6 +138,7 @@ impl GpioController {
 
     /// Wait for display with custom timeout
     pub fn wait_busy_timeout(&self, timeout: Duration) -> Result<(), GpioError> {
+        trace::record(Event::Busy);
         let start = std::time::Instant::now();
         let poll_interval = Duration::from_millis(100);
 
@@ -109,6 +161,30 @@ impl GpioController {
         Ok(())
     }
 
+    /// Wait while the BUSY pin is HIGH
+    ///
+    /// SSD16xx-based panels signal busy with the opposite polarity.
+    pub fn wait_busy_high(&self) -> Result<(), GpioError> {
+        trace::record(Event::BusyHigh);
+        let timeout = Duration::from_secs(30);
+        let start = std::time::Instant::now();
+
+        while self.busy.read() == Level::High {
+            if start.elapsed() > timeout {
+                return Err(GpioError::BusyTimeout(timeout.as_millis() as u64));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(())
+    }
+
+    /// Pause between steps of a sequence
+    pub fn delay(&self, duration: Duration) {
+        trace::record(Event::Delay(duration));
+        thread::sleep(duration);
+    }
+
     /// Check if display is currently busy
     #[allow(dead_code)]
     pub fn is_busy(&self) -> bool {
@@ -127,25 +203,81 @@ impl GpioController {
         self.dc.set_high();
     }
 
+    /// Select controller(s) (CS low)
+    ///
+    /// No-op without manual chip selects, where SPI drives CE0.
+    pub fn select(&mut self, chip: Chip) {
+        if !self.cs.is_empty() {
+            trace::record(Event::Select(chip));
+        }
+        for (i, pin) in self.cs.iter_mut().enumerate() {
+            let selected = match chip {
+                Chip::Main => i == 0,
+                Chip::Second => i == 1,
+                Chip::Both => true,
+            };
+            if selected {
+                pin.set_low();
+            }
+        }
+    }
+
+    /// Deselect all controllers (CS high)
+    pub fn deselect(&mut self) {
+        if !self.cs.is_empty() {
+            trace::record(Event::Deselect);
+        }
+        for pin in &mut self.cs {
+            pin.set_high();
+        }
+    }
+
     /// Enable display power
     pub fn power_on(&mut self) {
         tracing::debug!("Display power ON");
-        self.pwr.set_high();
+        trace::record(Event::Power(true));
+        self.set_power(Level::High);
         thread::sleep(Duration::from_millis(10));
     }
 
     /// Disable display power
     pub fn power_off(&mut self) {
         tracing::debug!("Display power OFF");
-        self.pwr.set_low();
+        trace::record(Event::Power(false));
+        self.set_power(Level::Low);
     }
+
+    /// Drive the PWR pin, tolerating a poisoned lock
+    fn set_power(&mut self, level: Level) {
+        let mut pwr = self.pwr.lock().unwrap_or_else(|e| e.into_inner());
+        pwr.write(level);
+    }
+}
+
+/// Pull the PWR pin low without going through the owning controller
+///
+/// Best-effort and non-blocking: intended for the panic hook, where the
+/// controller may be mid-operation. Returns true if the pin was driven low.
+pub fn emergency_power_off() -> bool {
+    let Ok(slot) = EMERGENCY_PWR.try_lock() else {
+        return false;
+    };
+    let Some(pwr) = slot.as_ref().and_then(Weak::upgrade) else {
+        return false;
+    };
+    let mut pin = match pwr.try_lock() {
+        Ok(pin) => pin,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return false,
+    };
+    pin.set_low();
+    true
 }
 
 impl Drop for GpioController {
     fn drop(&mut self) {
         // Ensure power is off when controller is dropped
-        self.pwr.set_low();
+        self.set_power(Level::Low);
         tracing::debug!("GPIO controller dropped, power disabled");
     }
 }
-
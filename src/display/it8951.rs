@@ -0,0 +1,490 @@
+//! IT8951 controller driver (Waveshare 6", 9.7" and 10.3" HAT panels).
+//!
+//! The IT8951 drives large parallel-interface panels and talks to the Pi
+//! over SPI with a framed protocol: every transfer starts with a preamble
+//! word (command, write or read) and the controller signals readiness on
+//! HRDY (the BUSY pin). Chip select is driven manually on GPIO 8.
+//!
+//! Frames hold 4-bit grey levels (0 = black, 15 = white) and are loaded
+//! into the controller's image buffer as-is, then refreshed with one of
+//! its waveform modes. Monochrome modes get frames with black and white
+//! only.
+//! Regions can be refreshed on their own.
+//!
+//! Based on the official Waveshare IT8951 C driver:
+//! https://github.com/waveshare/IT8951-ePaper
+
+use super::gpio::{Chip, GpioController, pins};
+use super::spi::{SpiConfig, SpiDisplay};
+use super::{Color, DisplayBackend, DisplayError, Palette, PanelModel, PixelFormat, Region};
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Transfer preambles
+mod preamble {
+    pub const COMMAND: [u8; 2] = [0x60, 0x00];
+    pub const WRITE: [u8; 2] = [0x00, 0x00];
+    pub const READ: [u8; 2] = [0x10, 0x00];
+}
+
+/// IT8951 commands
+mod cmd {
+    pub const SYS_RUN: u16 = 0x0001;
+    pub const SLEEP: u16 = 0x0003;
+    pub const REG_RD: u16 = 0x0010;
+    pub const REG_WR: u16 = 0x0011;
+    pub const LD_IMG_AREA: u16 = 0x0021;
+    pub const LD_IMG_END: u16 = 0x0022;
+    pub const DPY_AREA: u16 = 0x0034;
+    pub const VCOM: u16 = 0x0039;
+    pub const GET_DEV_INFO: u16 = 0x0302;
+}
+
+/// IT8951 registers
+mod reg {
+    /// Packed write enable
+    pub const I80CPCR: u16 = 0x0004;
+    /// Image buffer target address (low word; high word at +2)
+    pub const LISAR: u16 = 0x0208;
+    /// Waveform (LUT) engine status, 0 when idle
+    pub const LUTAFSR: u16 = 0x1224;
+}
+
+/// Waveform used to refresh the panel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshMode {
+    /// 16 grey levels, flashing full update
+    #[default]
+    Gc16,
+    /// 16 grey levels, less flashing, more ghosting
+    Gl16,
+    /// Fast monochrome update without flashing
+    Du,
+    /// Fastest monochrome update, most ghosting
+    A2,
+}
+
+/// IT8951 panel settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct It8951Config {
+    /// Panel VCOM in mV as printed on the FPC cable (e.g. 1530 for -1.53 V);
+    /// unset keeps the controller's value
+    #[serde(default)]
+    pub vcom_mv: Option<u16>,
+    /// Waveform used for refreshes
+    #[serde(default)]
+    pub mode: RefreshMode,
+}
+
+impl It8951Config {
+    /// Grey levels frames may use with the configured mode
+    pub fn palette(&self) -> Palette {
+        match self.mode {
+            RefreshMode::Gc16 | RefreshMode::Gl16 => Palette::Grey(16),
+            RefreshMode::Du | RefreshMode::A2 => Palette::Grey(2),
+        }
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(vcom) = self.vcom_mv
+            && !(200..=5000).contains(&vcom)
+        {
+            return Err(ConfigError::ValidationError(
+                "it8951.vcom_mv must be between 200 and 5000".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Panel information reported by the controller
+struct DeviceInfo {
+    width: u16,
+    height: u16,
+    buffer_addr: u32,
+    lut_version: String,
+}
+
+/// IT8951 display driver
+pub struct It8951 {
+    gpio: GpioController,
+    spi: SpiDisplay,
+    model: PanelModel,
+    settings: It8951Config,
+    info: Option<DeviceInfo>,
+}
+
+impl It8951 {
+    /// Create a new display driver instance for `model`
+    pub fn new(
+        model: PanelModel,
+        settings: It8951Config,
+        spi: &SpiConfig,
+    ) -> Result<Self, DisplayError> {
+        let gpio = GpioController::new(&[pins::CS_M])?;
+        let spi = SpiDisplay::new(spi)?;
+
+        Ok(Self {
+            gpio,
+            spi,
+            model,
+            settings,
+            info: None,
+        })
+    }
+
+    /// Initialize the controller and read the panel information
+    pub fn init(&mut self) -> Result<(), DisplayError> {
+        tracing::info!(
+            "Initializing {} display ({}x{})",
+            self.model.as_str(),
+            self.model.width(),
+            self.model.height()
+        );
+
+        self.gpio.power_on();
+        self.gpio.reset();
+        self.gpio.wait_busy()?;
+
+        self.write_command(cmd::SYS_RUN)?;
+
+        self.write_command(cmd::GET_DEV_INFO)?;
+        let words = self.read_data(20)?;
+        let text = |words: &[u16]| {
+            words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .take_while(|&b| b != 0)
+                .map(char::from)
+                .collect::<String>()
+        };
+        let info = DeviceInfo {
+            width: words[0],
+            height: words[1],
+            buffer_addr: words[2] as u32 | (words[3] as u32) << 16,
+            lut_version: text(&words[12..20]),
+        };
+        tracing::info!(
+            "IT8951: panel {}x{}, firmware {}, LUT {}",
+            info.width,
+            info.height,
+            text(&words[4..12]),
+            info.lut_version
+        );
+        if (info.width as u32, info.height as u32) != (self.model.width(), self.model.height()) {
+            tracing::warn!(
+                "IT8951 reports {}x{}, but panel '{}' is {}x{}",
+                info.width,
+                info.height,
+                self.model.as_str(),
+                self.model.width(),
+                self.model.height()
+            );
+        }
+
+        // Packed pixel writes
+        self.write_register(reg::I80CPCR, 0x0001)?;
+
+        if let Some(vcom) = self.settings.vcom_mv {
+            self.write_args(cmd::VCOM, &[0x0001, vcom])?;
+        }
+
+        self.info = Some(info);
+        tracing::info!("Display initialized successfully");
+        Ok(())
+    }
+
+    /// Display a full packed 4-bit frame
+    pub fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        let region = Region {
+            x: 0,
+            y: 0,
+            width: self.model.width(),
+            height: self.model.height(),
+        };
+        self.display_region(buffer, region)
+    }
+
+    /// Load `region` of a full frame into the controller and refresh it
+    fn display_region(&mut self, buffer: &[u8], region: Region) -> Result<(), DisplayError> {
+        let Some(info) = &self.info else {
+            return Err(DisplayError::NotInitialized);
+        };
+        let buffer_addr = info.buffer_addr;
+        let mode = self.mode_number();
+
+        let expected = self.model.buffer_size();
+        if buffer.len() != expected {
+            return Err(DisplayError::InvalidBufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+
+        let (width, height) = (self.model.width(), self.model.height());
+        // 4bpp loads are word based: 4 pixels
+        let x = region.x & !3;
+        let right = ((region.x + region.width + 3) & !3).min(width);
+        if right <= x || region.y + region.height > height {
+            return Err(DisplayError::InvalidRegion(region));
+        }
+        let area = Region {
+            x,
+            width: right - x,
+            ..region
+        };
+
+        let pixels = pack_area(buffer, width, area);
+        tracing::info!("Sending image data to display ({} bytes)", pixels.len());
+
+        self.wait_display_ready()?;
+        self.write_register(reg::LISAR + 2, (buffer_addr >> 16) as u16)?;
+        self.write_register(reg::LISAR, buffer_addr as u16)?;
+        // Little endian, 4bpp (2), no rotation
+        self.write_args(
+            cmd::LD_IMG_AREA,
+            &[
+                2 << 4,
+                area.x as u16,
+                area.y as u16,
+                area.width as u16,
+                area.height as u16,
+            ],
+        )?;
+        self.write_data(&pixels)?;
+        self.write_command(cmd::LD_IMG_END)?;
+
+        self.write_args(
+            cmd::DPY_AREA,
+            &[
+                area.x as u16,
+                area.y as u16,
+                area.width as u16,
+                area.height as u16,
+                mode,
+            ],
+        )?;
+        tracing::info!("Waiting for display refresh to complete...");
+        self.wait_display_ready()?;
+
+        tracing::info!("Display refresh complete");
+        Ok(())
+    }
+
+    /// Waveform number of the configured mode
+    ///
+    /// A2 is numbered differently on 6" panels (LUT M641).
+    fn mode_number(&self) -> u16 {
+        match self.settings.mode {
+            RefreshMode::Du => 1,
+            RefreshMode::Gc16 => 2,
+            RefreshMode::Gl16 => 3,
+            RefreshMode::A2 => {
+                let m641 = self
+                    .info
+                    .as_ref()
+                    .is_some_and(|info| info.lut_version.starts_with("M641"));
+                if m641 { 4 } else { 6 }
+            }
+        }
+    }
+
+    /// Clear display to a single color
+    pub fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        if self.info.is_none() {
+            self.init()?;
+        }
+
+        let level = self.settings.palette().code(color);
+        let buffer = vec![level << 4 | level; self.model.buffer_size()];
+
+        tracing::info!("Clearing display to {:?}", color);
+        self.display(&buffer)
+    }
+
+    /// Display test pattern: 16 grey stripes from black to white
+    pub fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        if self.info.is_none() {
+            self.init()?;
+        }
+
+        tracing::info!("Displaying test pattern");
+
+        let (width, height) = (self.model.width(), self.model.height());
+        let row_bytes = (width / 2) as usize;
+        let mut buffer = vec![0u8; self.model.buffer_size()];
+
+        for (y, row) in buffer.chunks_mut(row_bytes).enumerate() {
+            let level = (y as u32 * 16 / height) as u8;
+            row.fill((level << 4) | level);
+        }
+
+        self.display(&buffer)
+    }
+
+    /// Put the controller to sleep
+    pub fn sleep(&mut self) -> Result<(), DisplayError> {
+        tracing::info!("Putting display to sleep");
+
+        self.write_command(cmd::SLEEP)?;
+
+        self.gpio.power_off();
+        self.info = None;
+
+        Ok(())
+    }
+
+    /// Wait until the waveform engine is idle
+    fn wait_display_ready(&mut self) -> Result<(), DisplayError> {
+        let start = std::time::Instant::now();
+        while self.read_register(reg::LUTAFSR)? != 0 {
+            if start.elapsed() > Duration::from_secs(30) {
+                return Err(super::gpio::GpioError::BusyTimeout(30_000).into());
+            }
+            self.gpio.delay(Duration::from_millis(10));
+        }
+        Ok(())
+    }
+
+    /// Run one framed transfer with chip select held low
+    fn transfer(
+        &mut self,
+        preamble: [u8; 2],
+        body: impl FnOnce(&mut Self) -> Result<(), DisplayError>,
+    ) -> Result<(), DisplayError> {
+        self.gpio.wait_busy()?;
+        self.gpio.select(Chip::Main);
+        let result = (|| {
+            self.spi.write_raw(&preamble)?;
+            self.gpio.wait_busy()?;
+            body(self)
+        })();
+        self.gpio.deselect();
+        result
+    }
+
+    fn write_command(&mut self, command: u16) -> Result<(), DisplayError> {
+        self.transfer(preamble::COMMAND, |this| {
+            Ok(this.spi.write_raw(&command.to_be_bytes())?)
+        })
+    }
+
+    /// Write big-endian data words
+    fn write_data(&mut self, data: &[u8]) -> Result<(), DisplayError> {
+        self.transfer(preamble::WRITE, |this| Ok(this.spi.write_raw(data)?))
+    }
+
+    fn write_args(&mut self, command: u16, args: &[u16]) -> Result<(), DisplayError> {
+        self.write_command(command)?;
+        for arg in args {
+            self.write_data(&arg.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read_data(&mut self, words: usize) -> Result<Vec<u16>, DisplayError> {
+        let mut bytes = vec![0u8; words * 2];
+        self.transfer(preamble::READ, |this| {
+            // The first word is a dummy
+            let mut dummy = [0u8; 2];
+            this.spi.read_raw(&mut dummy)?;
+            this.gpio.wait_busy()?;
+            Ok(this.spi.read_raw(&mut bytes)?)
+        })?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|w| u16::from_be_bytes([w[0], w[1]]))
+            .collect())
+    }
+
+    fn write_register(&mut self, address: u16, value: u16) -> Result<(), DisplayError> {
+        self.write_args(cmd::REG_WR, &[address, value])
+    }
+
+    fn read_register(&mut self, address: u16) -> Result<u16, DisplayError> {
+        self.write_args(cmd::REG_RD, &[address])?;
+        Ok(self.read_data(1)?[0])
+    }
+}
+
+/// Pack `area` of a frame into IT8951 4bpp words
+///
+/// Frames hold the first pixel of a byte in the high nibble; in a
+/// little-endian IT8951 word the first pixel is the lowest nibble, and
+/// words go out most significant byte first.
+fn pack_area(frame: &[u8], width: u32, area: Region) -> Vec<u8> {
+    let row_bytes = (width / 2) as usize;
+    let mut out = Vec::with_capacity((area.width / 2 * area.height) as usize);
+
+    for y in area.y..area.y + area.height {
+        let start = y as usize * row_bytes + (area.x / 2) as usize;
+        let row = &frame[start..start + (area.width / 2) as usize];
+        for pair in row.chunks_exact(2) {
+            for &byte in pair.iter().rev() {
+                out.push(byte.rotate_left(4));
+            }
+        }
+    }
+
+    out
+}
+
+impl DisplayBackend for It8951 {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        It8951::init(self)
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::packed4(self.settings.palette())
+    }
+
+    fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        It8951::display(self, buffer)
+    }
+
+    fn supports_partial(&self) -> bool {
+        true
+    }
+
+    fn display_partial(&mut self, buffer: &[u8], region: Region) -> Result<(), DisplayError> {
+        self.display_region(buffer, region)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        It8951::clear(self, color)
+    }
+
+    fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        It8951::test_pattern(self)
+    }
+
+    fn sleep(&mut self) -> Result<(), DisplayError> {
+        It8951::sleep(self)
+    }
+
+    /// Data is sent as big-endian 16-bit words after the command
+    fn raw_command(&mut self, command: u16, data: &[u8]) -> Result<(), DisplayError> {
+        if !data.len().is_multiple_of(2) {
+            return Err(DisplayError::InvalidCommand(
+                "IT8951 data must be whole 16-bit words".to_string(),
+            ));
+        }
+        self.write_command(command)?;
+        if !data.is_empty() {
+            self.write_data(data)?;
+        }
+        self.gpio.wait_busy()?;
+        Ok(())
+    }
+}
+
+impl Drop for It8951 {
+    fn drop(&mut self) {
+        if self.info.is_some() {
+            let _ = self.sleep();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! In-memory display backend.
+//!
+//! Stands in for the panel when running without hardware (development
+//! machines, CI). Frames are validated and recorded instead of being
+//! sent over SPI.
+
+use super::{Color, DisplayBackend, DisplayError, PanelModel, PanelSpec, PixelFormat, Region};
+use std::sync::{Arc, Mutex};
+
+/// Frames recorded by the mock display
+#[derive(Debug, Default)]
+pub struct MockState {
+    /// Number of frames sent to the display
+    pub frames_displayed: u64,
+    /// Last frame sent to the display (packed 4-bit pixels)
+    pub last_frame: Option<Vec<u8>>,
+    /// Number of partial refreshes
+    pub partial_updates: u64,
+    /// Region of the last partial refresh
+    pub last_region: Option<Region>,
+    /// Whether the display is currently initialized
+    pub initialized: bool,
+    /// Number of times the display was initialized
+    pub inits: u64,
+    /// Raw commands sent through the debug API, with their data
+    pub raw_commands: Vec<(u16, Vec<u8>)>,
+}
+
+/// Mock panel: the layout of `model`, with partial refresh
+pub fn spec(model: PanelModel) -> PanelSpec {
+    PanelSpec {
+        model: "Mock display",
+        partial_refresh: true,
+        ..model.spec()
+    }
+}
+
+/// Shared handle to the mock display's recorded state
+pub type MockHandle = Arc<Mutex<MockState>>;
+
+/// Mock display backend
+pub struct MockDisplay {
+    state: MockHandle,
+    model: PanelModel,
+}
+
+impl MockDisplay {
+    /// Create a mock display of `model` recording into `state`
+    pub fn new(state: MockHandle, model: PanelModel) -> Self {
+        Self { state, model }
+    }
+
+    /// Reject frames not matching the panel resolution
+    fn check_size(&self, buffer: &[u8]) -> Result<(), DisplayError> {
+        let expected = self.model.buffer_size();
+        if buffer.len() != expected {
+            return Err(DisplayError::InvalidBufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl DisplayBackend for MockDisplay {
+    fn init(&mut self) -> Result<(), DisplayError> {
+        tracing::info!(
+            "Initializing mock display ({}x{})",
+            self.model.width(),
+            self.model.height()
+        );
+        let mut state = self.state();
+        state.initialized = true;
+        state.inits += 1;
+        Ok(())
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        PixelFormat::packed4(self.model.spec().palette)
+    }
+
+    fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        self.check_size(buffer)?;
+        let mut state = self.state();
+
+        if !state.initialized {
+            return Err(DisplayError::NotInitialized);
+        }
+
+        state.frames_displayed += 1;
+        state.last_frame = Some(buffer.to_vec());
+        tracing::info!("Mock display received frame #{}", state.frames_displayed);
+        Ok(())
+    }
+
+    fn supports_partial(&self) -> bool {
+        true
+    }
+
+    fn display_partial(&mut self, buffer: &[u8], region: Region) -> Result<(), DisplayError> {
+        self.check_size(buffer)?;
+        let mut state = self.state();
+
+        if !state.initialized {
+            return Err(DisplayError::NotInitialized);
+        }
+
+        let (width, height) = (self.model.width(), self.model.height());
+        if region.x + region.width > width || region.y + region.height > height {
+            return Err(DisplayError::InvalidRegion(region));
+        }
+
+        // Only the region is copied onto the retained frame
+        let frame = state
+            .last_frame
+            .get_or_insert_with(|| vec![0x11; buffer.len()]);
+        let row_bytes = (width / 2) as usize;
+        for y in region.y..region.y + region.height {
+            let start = y as usize * row_bytes + (region.x / 2) as usize;
+            let end = start + (region.width / 2) as usize;
+            frame[start..end].copy_from_slice(&buffer[start..end]);
+        }
+
+        state.partial_updates += 1;
+        state.last_region = Some(region);
+        tracing::info!(
+            "Mock display partial refresh #{} of {:?}",
+            state.partial_updates,
+            region
+        );
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), DisplayError> {
+        let code = self.model.spec().palette.code(color);
+        let pixel = code << 4 | code;
+        self.display(&vec![pixel; self.model.buffer_size()])
+    }
+
+    fn test_pattern(&mut self) -> Result<(), DisplayError> {
+        self.clear(Color::White)
+    }
+
+    fn sleep(&mut self) -> Result<(), DisplayError> {
+        self.state().initialized = false;
+        Ok(())
+    }
+
+    fn raw_command(&mut self, command: u16, data: &[u8]) -> Result<(), DisplayError> {
+        tracing::info!("Mock display received raw command 0x{:02X}", command);
+        self.state().raw_commands.push((command, data.to_vec()));
+        Ok(())
+    }
+}
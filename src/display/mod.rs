@@ -1,118 +1,395 @@
 //! Display module for e-paper display control.
 //!
-//! This module provides the interface to the Waveshare 7.3" E Ink Spectra 6
-//! (EPD7IN3E) display connected via SPI.
+//! This module provides the interface to the Waveshare E Ink Spectra 6
+//! displays (4", 7.3" and 13.3"), black/white/red displays (2.13", 4.2"
+//! and 7.5") and IT8951-driven greyscale displays (6", 9.7" and 10.3")
+//! connected via SPI, plus an in-memory mock backend for running without
+//! hardware.
+//!
+//! The active backend runs on its own display thread; `DisplayController`
+//! is the async handle that sends it commands.
+//!
+//! Drivers implement [`DisplayBackend`] and are picked at runtime from the
+//! configured [`PanelModel`], so one binary drives every panel and tests
+//! swap in the mock. Adding a panel takes a `PanelModel` variant with its
+//! [`PanelSpec`] and family, the driver, and a line in the worker's
+//! `Backend::create`; nothing outside this module names a driver.
+//!
+//! Dimensions and palette come from the model's [`PanelSpec`] rather than
+//! the driver, so they are known before the panel is initialized. The
+//! power, reset and busy sequencing stays in each driver: the families
+//! wait on BUSY in opposite polarities, the 13.3" panel selects one of two
+//! controllers per command, and IT8951 polls a register instead of BUSY.
 
+pub mod access;
 pub mod epd7in3e;
+pub mod epd_bwr;
 pub mod gpio;
+pub mod it8951;
+pub mod mock;
+#[cfg(not(hardware))]
+pub(crate) mod no_hardware;
+pub mod panel;
 pub mod spi;
+pub mod trace;
+mod worker;
 
 // Re-export main types
-pub use epd7in3e::{Color, DisplayError, Epd7in3e};
+pub use epd_bwr::EpdBwr;
+pub use epd7in3e::{Color, DisplayError, Epd7in3e, Spectra6Config};
+pub use it8951::{It8951, It8951Config};
+pub use mock::{MockDisplay, MockHandle};
+pub use panel::{PanelFamily, PanelModel};
+pub use spi::SpiConfig;
+
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak, mpsc};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use worker::{Backend, BackendKind, Command, Reply};
+
+/// Rectangle in panel pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Widen to even x/width so the region covers whole packed bytes
+    pub fn align_to_bytes(self) -> Self {
+        let x = self.x & !1;
+        let right = (self.x + self.width + 1) & !1;
+        Self {
+            x,
+            width: right - x,
+            ..self
+        }
+    }
+
+    /// Whether `other` lies entirely inside this region
+    pub fn contains(self, other: Region) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+}
+
+/// Pixel values a panel accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    /// Indices of these display colors
+    Colors(&'static [Color]),
+    /// This many evenly spaced grey levels, stored as 4-bit values
+    /// (0 = black, 15 = white)
+    Grey(u8),
+}
+
+impl Palette {
+    /// Grey level shown for each display color (0 = black, 15 = white)
+    const COLOR_LEVELS: [u8; 7] = [0, 15, 13, 4, 9, 2, 9];
+
+    /// 4-bit pixel value showing `color` (its grey level on grey panels)
+    pub fn code(self, color: Color) -> u8 {
+        match self {
+            Palette::Colors(_) => color as u8,
+            Palette::Grey(_) => Self::COLOR_LEVELS[color as usize],
+        }
+    }
+}
+
+/// Frame format a backend accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    /// Values pixels may take
+    pub palette: Palette,
+    /// Bits per pixel in the packed frame (1, 2, 4 or 8), first pixel in
+    /// the most significant bits; must hold the largest palette index
+    pub bits_per_pixel: u8,
+}
+
+impl PixelFormat {
+    /// Packed 4-bit pixels, 2 per byte
+    pub const fn packed4(palette: Palette) -> Self {
+        Self {
+            palette,
+            bits_per_pixel: 4,
+        }
+    }
+
+    /// Size of a packed frame of `width` x `height` pixels
+    pub fn buffer_size(&self, width: u32, height: u32) -> usize {
+        (width as usize * height as usize * self.bits_per_pixel as usize).div_ceil(8)
+    }
+}
+
+/// Fixed characteristics of a panel
+#[derive(Debug, Clone, Copy)]
+pub struct PanelSpec {
+    /// Human-readable panel name
+    pub model: &'static str,
+    /// Native width in pixels
+    pub width: u32,
+    /// Native height in pixels
+    pub height: u32,
+    /// Colors or grey levels the panel can show
+    pub palette: Palette,
+    /// Whether regions can be refreshed without a full refresh
+    pub partial_refresh: bool,
+    /// Typical duration of a full refresh (datasheet)
+    pub refresh_secs: u32,
+}
+
+/// Operations provided by every display backend
+pub trait DisplayBackend: Send {
+    /// Initialize the display
+    fn init(&mut self) -> Result<(), DisplayError>;
+
+    /// Frame format `display` expects; the pipeline dithers to it
+    fn pixel_format(&self) -> PixelFormat;
+
+    /// Display a packed frame buffer in the backend's pixel format
+    fn display(&mut self, buffer: &[u8]) -> Result<(), DisplayError>;
+
+    /// Whether the panel can refresh a region without a full refresh
+    fn supports_partial(&self) -> bool {
+        false
+    }
+
+    /// Refresh only `region` of the panel from a full packed frame buffer
+    ///
+    /// Backends without partial refresh fall back to a full refresh.
+    fn display_partial(&mut self, buffer: &[u8], region: Region) -> Result<(), DisplayError> {
+        let _ = region;
+        self.display(buffer)
+    }
+
+    /// Clear display to a single color
+    fn clear(&mut self, color: Color) -> Result<(), DisplayError>;
+
+    /// Display a test pattern
+    fn test_pattern(&mut self) -> Result<(), DisplayError>;
+
+    /// Put display into deep sleep
+    fn sleep(&mut self) -> Result<(), DisplayError>;
+
+    /// Send a controller command with its data bytes as given, then wait
+    /// until the controller is idle (debug API)
+    fn raw_command(&mut self, command: u16, data: &[u8]) -> Result<(), DisplayError>;
+}
+
+/// Slot holding the active backend (None while uninitialized/asleep)
+///
+/// Only the display thread uses the backend; the slot is shared so the
+/// panic hook can reach it.
+type BackendSlot = std::sync::Mutex<Option<Box<dyn DisplayBackend>>>;
 
-use std::sync::Arc;
-use tokio::sync::Mutex;
+/// Display slot of the controller, registered for the panic hook
+static EMERGENCY_DISPLAY: OnceCell<Weak<BackendSlot>> = OnceCell::new();
 
-/// Thread-safe display controller wrapper
+/// Longest wait for the display thread to answer a command
+///
+/// Well above the slowest full refresh; a command that times out keeps
+/// running, later commands queue behind it.
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Handle to the display thread
+///
+/// Clones share the thread; it stops once the last clone is dropped.
+#[derive(Clone)]
 pub struct DisplayController {
-    display: Arc<Mutex<Option<Epd7in3e>>>,
+    commands: mpsc::Sender<Command>,
+    backend: Backend,
+    /// Duration of the last full refresh in milliseconds (0 = none yet)
+    last_refresh_ms: Arc<AtomicU64>,
 }
 
 impl DisplayController {
-    /// Create a new display controller (uninitialized)
-    pub fn new() -> Self {
+    /// Create a new display controller for `model` (uninitialized)
+    pub fn new(model: PanelModel) -> Self {
+        Self::with_kind(BackendKind::Hardware, model)
+    }
+
+    /// Create a controller backed by the in-memory mock display
+    ///
+    /// The mock takes on the resolution of `model`. Returns the handle
+    /// through which recorded frames can be inspected.
+    pub fn mock(model: PanelModel) -> (Self, MockHandle) {
+        let handle = MockHandle::default();
+        (
+            Self::with_kind(BackendKind::Mock(Arc::clone(&handle)), model),
+            handle,
+        )
+    }
+
+    fn with_kind(kind: BackendKind, model: PanelModel) -> Self {
+        let slot = Arc::new(std::sync::Mutex::new(None));
+        let _ = EMERGENCY_DISPLAY.set(Arc::downgrade(&slot));
+        let last_refresh_ms = Arc::new(AtomicU64::new(0));
+
+        let (commands, receiver) = mpsc::channel();
+        worker::spawn(receiver, slot, Arc::clone(&last_refresh_ms));
+
         Self {
-            display: Arc::new(Mutex::new(None)),
+            commands,
+            backend: Backend {
+                kind,
+                model,
+                it8951: It8951Config::default(),
+                spectra6: Spectra6Config::default(),
+                spi: SpiConfig::default(),
+            },
+            last_refresh_ms,
         }
     }
 
-    /// Initialize the display hardware
-    pub async fn init(&self) -> Result<(), DisplayError> {
-        let mut display_guard = self.display.lock().await;
+    /// Use `settings` for IT8951 panels
+    pub fn with_it8951(mut self, settings: It8951Config) -> Self {
+        self.backend.it8951 = settings;
+        self
+    }
+
+    /// Use `settings` for Spectra 6 panels
+    pub fn with_spectra6(mut self, settings: Spectra6Config) -> Self {
+        self.backend.spectra6 = settings;
+        self
+    }
+
+    /// Use `settings` for SPI transfers
+    pub fn with_spi(mut self, settings: SpiConfig) -> Self {
+        self.backend.spi = settings;
+        self
+    }
 
-        if display_guard.is_some() {
-            tracing::debug!("Display already initialized");
-            return Ok(());
+    /// Characteristics of the configured panel (available before init)
+    pub fn spec(&self) -> PanelSpec {
+        let model = self.backend.model;
+        let spec = model.configured_spec(&self.backend.it8951);
+        match self.backend.kind {
+            BackendKind::Hardware => spec,
+            BackendKind::Mock(_) => PanelSpec {
+                palette: spec.palette,
+                ..mock::spec(model)
+            },
         }
+    }
 
-        // Create and initialize display
-        let mut epd = Epd7in3e::new()?;
-        epd.init()?;
+    /// Measured duration of the last full refresh
+    pub fn last_refresh_ms(&self) -> Option<u64> {
+        match self.last_refresh_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(ms),
+        }
+    }
 
-        *display_guard = Some(epd);
-        Ok(())
+    /// Send a command to the display thread and wait for its reply
+    async fn call<T>(&self, command: impl FnOnce(Reply<T>) -> Command) -> Result<T, DisplayError> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| DisplayError::ThreadStopped)?;
+
+        match tokio::time::timeout(COMMAND_TIMEOUT, response).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(DisplayError::ThreadStopped),
+            Err(_) => Err(DisplayError::Timeout(COMMAND_TIMEOUT)),
+        }
+    }
+
+    /// Initialize the display hardware
+    pub async fn init(&self) -> Result<(), DisplayError> {
+        let backend = self.backend.clone();
+        self.call(|reply| Command::Init(backend, reply)).await
     }
 
     /// Display image buffer
+    ///
+    /// The buffer is copied for the display thread.
     pub async fn display(&self, buffer: &[u8]) -> Result<(), DisplayError> {
-        let mut display_guard = self.display.lock().await;
+        let buffer = buffer.to_vec();
+        self.call(|reply| Command::Display(buffer, reply)).await
+    }
+
+    /// Frame format of the backend, initializing it if needed
+    pub async fn pixel_format(&self) -> Result<PixelFormat, DisplayError> {
+        self.init().await?;
+        self.call(Command::PixelFormat).await
+    }
 
-        let display = display_guard
-            .as_mut()
-            .ok_or(DisplayError::NotInitialized)?;
+    /// Refresh a region of the display from a full frame buffer
+    pub async fn display_partial(&self, buffer: &[u8], region: Region) -> Result<(), DisplayError> {
+        let buffer = buffer.to_vec();
+        self.call(|reply| Command::DisplayPartial(buffer, region, reply))
+            .await
+    }
 
-        display.display(buffer)
+    /// Whether the initialized backend supports partial refresh
+    pub async fn supports_partial(&self) -> bool {
+        self.call(Command::SupportsPartial).await.unwrap_or(false)
     }
 
     /// Clear display to white
     pub async fn clear(&self) -> Result<(), DisplayError> {
-        let mut display_guard = self.display.lock().await;
-
-        let display = display_guard
-            .as_mut()
-            .ok_or(DisplayError::NotInitialized)?;
-
-        display.clear(Color::White)
+        self.call(Command::Clear).await
     }
 
     /// Show test pattern
     pub async fn test_pattern(&self) -> Result<(), DisplayError> {
-        let mut display_guard = self.display.lock().await;
-
         // Initialize if needed
-        if display_guard.is_none() {
-            drop(display_guard);
-            self.init().await?;
-            display_guard = self.display.lock().await;
-        }
-
-        let display = display_guard
-            .as_mut()
-            .ok_or(DisplayError::NotInitialized)?;
-
-        display.test_pattern()
+        self.init().await?;
+        self.call(Command::TestPattern).await
     }
 
     /// Put display to sleep
     pub async fn sleep(&self) -> Result<(), DisplayError> {
-        let mut display_guard = self.display.lock().await;
-
-        if let Some(display) = display_guard.as_mut() {
-            display.sleep()?;
-            *display_guard = None;
-        }
+        self.call(Command::Sleep).await
+    }
 
-        Ok(())
+    /// Send a raw controller command, initializing the display if needed
+    ///
+    /// Nothing checks what the command does to the panel state.
+    pub async fn raw_command(&self, command: u16, data: &[u8]) -> Result<(), DisplayError> {
+        self.init().await?;
+        let data = data.to_vec();
+        self.call(|reply| Command::Raw(command, data, reply)).await
     }
 
     /// Check if display is initialized
     #[allow(dead_code)]
     pub async fn is_initialized(&self) -> bool {
-        self.display.lock().await.is_some()
+        self.call(Command::IsInitialized).await.unwrap_or(false)
     }
 }
 
-impl Default for DisplayController {
-    fn default() -> Self {
-        Self::new()
+/// Best-effort power down of the panel from a panic context
+///
+/// If the display is idle, runs the regular sleep sequence (power off
+/// command, deep sleep, PWR low). If a panic struck mid-refresh the
+/// display lock is still held, so only the PWR pin is pulled low.
+pub fn emergency_shutdown() {
+    let idle = EMERGENCY_DISPLAY
+        .get()
+        .and_then(Weak::upgrade)
+        .and_then(|display| {
+            let mut guard = display.try_lock().ok()?;
+            if let Some(epd) = guard.as_mut() {
+                let _ = epd.sleep();
+            }
+            *guard = None;
+            Some(())
+        });
+
+    if idle.is_none() && gpio::emergency_power_off() {
+        eprintln!("Display busy during panic, PWR pin forced low");
     }
 }
 
-impl Clone for DisplayController {
-    fn clone(&self) -> Self {
-        Self {
-            display: Arc::clone(&self.display),
-        }
+impl Default for DisplayController {
+    fn default() -> Self {
+        Self::new(PanelModel::default())
     }
 }
-
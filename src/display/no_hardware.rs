@@ -0,0 +1,112 @@
+//! Stand-ins for the `rppal` GPIO and SPI types.
+//!
+//! Used in builds without hardware support (the `hardware` feature off,
+//! or a non-ARM target) so the panel drivers still compile and type-check
+//! on any machine. Opening GPIO or SPI fails, so the pins and the bus
+//! are never created and their methods do nothing.
+
+// Only the failing constructors are ever called
+#![allow(dead_code)]
+
+use std::fmt;
+
+/// Error returned when opening GPIO or SPI
+#[derive(Debug)]
+pub struct Unsupported;
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("built without hardware support (`hardware` feature, ARM targets only)")
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+pub mod gpio {
+    pub use super::Unsupported as Error;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Level {
+        Low,
+        High,
+    }
+
+    pub struct Gpio;
+
+    impl Gpio {
+        pub fn new() -> Result<Self, Error> {
+            Err(Error)
+        }
+
+        pub fn get(&self, _pin: u8) -> Result<Pin, Error> {
+            Err(Error)
+        }
+    }
+
+    pub struct Pin;
+
+    impl Pin {
+        pub fn into_output(self) -> OutputPin {
+            OutputPin
+        }
+
+        pub fn into_input_pulldown(self) -> InputPin {
+            InputPin
+        }
+    }
+
+    pub struct OutputPin;
+
+    impl OutputPin {
+        pub fn set_high(&mut self) {}
+
+        pub fn set_low(&mut self) {}
+
+        pub fn write(&mut self, _level: Level) {}
+    }
+
+    pub struct InputPin;
+
+    impl InputPin {
+        pub fn read(&self) -> Level {
+            Level::High
+        }
+    }
+}
+
+pub mod spi {
+    pub use super::Unsupported as Error;
+
+    pub enum Bus {
+        Spi0,
+    }
+
+    pub enum SlaveSelect {
+        Ss0,
+    }
+
+    pub enum Mode {
+        Mode0,
+    }
+
+    pub struct Spi;
+
+    impl Spi {
+        pub fn new(
+            _bus: Bus,
+            _slave: SlaveSelect,
+            _clock: u32,
+            _mode: Mode,
+        ) -> Result<Self, Error> {
+            Err(Error)
+        }
+
+        pub fn write(&mut self, _buffer: &[u8]) -> Result<usize, Error> {
+            Err(Error)
+        }
+
+        pub fn read(&mut self, _buffer: &mut [u8]) -> Result<usize, Error> {
+            Err(Error)
+        }
+    }
+}
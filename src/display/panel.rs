@@ -0,0 +1,178 @@
+//! Supported panel models.
+//!
+//! Three families are supported:
+//! - Waveshare E Ink Spectra 6 (E6) panels, sharing the 6-color palette
+//!   and packed 4-bit frame format; they differ in resolution and a few
+//!   init values.
+//! - Waveshare black/white/red (B) panels, which take one 1-bit plane for
+//!   black and one for red.
+//! - Greyscale panels behind an IT8951 controller (Waveshare HATs).
+//!
+//! The pipeline always produces packed 4-bit frames (color indices, or
+//! grey levels on greyscale panels) at the panel resolution; drivers
+//! convert them to the panel's own format.
+
+use super::epd7in3e::PALETTE;
+use super::{Color, It8951Config, Palette, PanelSpec};
+use serde::{Deserialize, Serialize};
+
+/// Colors of black/white/red panels, in index order
+pub const PALETTE_BWR: [Color; 3] = [Color::Black, Color::White, Color::Red];
+
+/// Panel model, selected with `panel` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanelModel {
+    /// Waveshare 4" E6, 400 x 600
+    Epd4in0e,
+    /// Waveshare 7.3" E6, 800 x 480
+    #[default]
+    Epd7in3e,
+    /// Waveshare 13.3" E6, 1200 x 1600, driven by two controllers
+    Epd13in3e,
+    /// Waveshare 2.13" B/W/R (V4), 122 x 250
+    Epd2in13bV4,
+    /// Waveshare 4.2" B/W/R (V2), 400 x 300
+    Epd4in2bV2,
+    /// Waveshare 7.5" B/W/R (V2), 800 x 480
+    Epd7in5bV2,
+    /// 6" panel on the IT8951 HAT, 800 x 600
+    #[serde(rename = "it8951_6in")]
+    It8951Epd6in,
+    /// 9.7" panel on the IT8951 HAT, 1200 x 825
+    #[serde(rename = "it8951_9in7")]
+    It8951Epd9in7,
+    /// 10.3" panel on the IT8951 HAT, 1872 x 1404
+    #[serde(rename = "it8951_10in3")]
+    It8951Epd10in3,
+}
+
+/// Driver family of a panel model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelFamily {
+    /// 7-color Spectra 6, packed 4-bit frames
+    Spectra6,
+    /// Black/white/red, one 1-bit plane per ink
+    BlackWhiteRed,
+    /// IT8951 controller, 16 grey levels
+    It8951,
+}
+
+impl PanelModel {
+    /// Config value of the model
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PanelModel::Epd4in0e => "epd4in0e",
+            PanelModel::Epd7in3e => "epd7in3e",
+            PanelModel::Epd13in3e => "epd13in3e",
+            PanelModel::Epd2in13bV4 => "epd2in13b_v4",
+            PanelModel::Epd4in2bV2 => "epd4in2b_v2",
+            PanelModel::Epd7in5bV2 => "epd7in5b_v2",
+            PanelModel::It8951Epd6in => "it8951_6in",
+            PanelModel::It8951Epd9in7 => "it8951_9in7",
+            PanelModel::It8951Epd10in3 => "it8951_10in3",
+        }
+    }
+
+    /// Driver family of the panel
+    pub fn family(self) -> PanelFamily {
+        match self {
+            PanelModel::Epd4in0e | PanelModel::Epd7in3e | PanelModel::Epd13in3e => {
+                PanelFamily::Spectra6
+            }
+            PanelModel::Epd2in13bV4 | PanelModel::Epd4in2bV2 | PanelModel::Epd7in5bV2 => {
+                PanelFamily::BlackWhiteRed
+            }
+            PanelModel::It8951Epd6in | PanelModel::It8951Epd9in7 | PanelModel::It8951Epd10in3 => {
+                PanelFamily::It8951
+            }
+        }
+    }
+
+    /// Fixed characteristics of the panel
+    pub fn spec(self) -> PanelSpec {
+        let (model, width, height, refresh_secs) = match self {
+            PanelModel::Epd4in0e => ("Waveshare 4\" E Ink Spectra 6 (EPD4IN0E)", 400, 600, 12),
+            PanelModel::Epd7in3e => ("Waveshare 7.3\" E Ink Spectra 6 (EPD7IN3E)", 800, 480, 12),
+            PanelModel::Epd13in3e => (
+                "Waveshare 13.3\" E Ink Spectra 6 (EPD13IN3E)",
+                1200,
+                1600,
+                19,
+            ),
+            PanelModel::Epd2in13bV4 => ("Waveshare 2.13\" B/W/R (EPD2IN13B V4)", 122, 250, 15),
+            PanelModel::Epd4in2bV2 => ("Waveshare 4.2\" B/W/R (EPD4IN2B V2)", 400, 300, 15),
+            PanelModel::Epd7in5bV2 => ("Waveshare 7.5\" B/W/R (EPD7IN5B V2)", 800, 480, 16),
+            PanelModel::It8951Epd6in => ("Waveshare 6\" e-Paper HAT (IT8951)", 800, 600, 1),
+            PanelModel::It8951Epd9in7 => ("Waveshare 9.7\" e-Paper HAT (IT8951)", 1200, 825, 1),
+            PanelModel::It8951Epd10in3 => ("Waveshare 10.3\" e-Paper HAT (IT8951)", 1872, 1404, 1),
+        };
+        let palette = match self.family() {
+            PanelFamily::Spectra6 => Palette::Colors(&PALETTE),
+            PanelFamily::BlackWhiteRed => Palette::Colors(&PALETTE_BWR),
+            PanelFamily::It8951 => Palette::Grey(16),
+        };
+        PanelSpec {
+            model,
+            width,
+            height,
+            palette,
+            partial_refresh: self.family() == PanelFamily::It8951,
+            refresh_secs,
+        }
+    }
+
+    /// Characteristics of the panel as driven with `it8951` (grey levels
+    /// of IT8951 panels)
+    pub fn configured_spec(self, it8951: &It8951Config) -> PanelSpec {
+        let mut spec = self.spec();
+        if self.family() == PanelFamily::It8951 {
+            spec.palette = it8951.palette();
+        }
+        spec
+    }
+
+    /// Native width in pixels
+    pub fn width(self) -> u32 {
+        self.spec().width
+    }
+
+    /// Native height in pixels
+    pub fn height(self) -> u32 {
+        self.spec().height
+    }
+
+    /// Size of a packed frame: 2 pixels per byte (4-bit color)
+    pub fn buffer_size(self) -> usize {
+        (self.width() as usize * self.height() as usize) / 2
+    }
+
+    /// Whether the panel has a second controller on its own chip select
+    pub fn dual_controller(self) -> bool {
+        self == PanelModel::Epd13in3e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::it8951::RefreshMode;
+
+    #[test]
+    fn configured_spec_follows_the_it8951_mode() {
+        let monochrome = It8951Config {
+            mode: RefreshMode::A2,
+            ..Default::default()
+        };
+        assert_eq!(
+            PanelModel::It8951Epd6in
+                .configured_spec(&monochrome)
+                .palette,
+            Palette::Grey(2)
+        );
+        assert_eq!(
+            PanelModel::Epd7in3e.configured_spec(&monochrome).palette,
+            Palette::Colors(&PALETTE)
+        );
+    }
+}
@@ -2,18 +2,77 @@
 //!
 //! Provides SPI interface for sending commands and data to the display.
 //! Uses SPI0 with CE0 (Chip Enable 0) at 4 MHz.
+//!
+//! Bulk data goes out in transfers as large as the spidev driver accepts
+//! (its `bufsiz` module parameter, 4096 bytes by default). Raising it, e.g.
+//! `spidev.bufsiz=65536` on the kernel command line, cuts the per-transfer
+//! overhead of sending a whole frame.
 
 use super::gpio::GpioController;
+use super::trace::{self, Event};
+use crate::config::ConfigError;
+#[cfg(not(hardware))]
+use super::no_hardware as rppal;
 use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// SPI configuration
 pub mod config {
     /// SPI clock speed in Hz (4 MHz)
     pub const CLOCK_SPEED: u32 = 4_000_000;
+
+    /// Transfer size every spidev accepts (its default `bufsiz`)
+    pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+    /// Largest transfer size accepted in the config
+    pub const MAX_CHUNK_SIZE: u32 = 1 << 20;
+
+    /// spidev's transfer size limit
+    pub const BUFSIZ_PATH: &str = "/sys/module/spidev/parameters/bufsiz";
+}
+
+/// SPI transfer settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SpiConfig {
+    /// Bytes per bulk transfer; 0 uses the spidev `bufsiz` limit
+    #[serde(default)]
+    pub chunk_size: u32,
+}
+
+impl SpiConfig {
+    /// Transfer size to start with
+    pub fn chunk_size(&self) -> usize {
+        match self.chunk_size {
+            0 => kernel_bufsiz().unwrap_or(config::DEFAULT_CHUNK_SIZE),
+            size => size as usize,
+        }
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.chunk_size != 0 && !(64..=config::MAX_CHUNK_SIZE).contains(&self.chunk_size) {
+            return Err(ConfigError::ValidationError(format!(
+                "spi.chunk_size must be 0 (auto) or between 64 and {}",
+                config::MAX_CHUNK_SIZE
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Transfer size limit of the spidev driver, if it can be read
+fn kernel_bufsiz() -> Option<usize> {
+    std::fs::read_to_string(config::BUFSIZ_PATH)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&size| size > 0)
 }
 
 /// SPI-related errors
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum SpiError {
     #[error("SPI initialization failed: {0}")]
@@ -21,18 +80,23 @@ pub enum SpiError {
 
     #[error("SPI write failed: {0}")]
     WriteError(String),
+
+    #[error("SPI read failed: {0}")]
+    ReadError(String),
 }
 
 /// SPI display interface
 pub struct SpiDisplay {
     spi: Spi,
+    /// Bytes per bulk transfer
+    chunk_size: usize,
 }
 
 impl SpiDisplay {
     /// Initialize SPI for display communication
     ///
     /// Uses SPI0, CE0, Mode 0 (CPOL=0, CPHA=0), 4 MHz clock
-    pub fn new() -> Result<Self, SpiError> {
+    pub fn new(settings: &SpiConfig) -> Result<Self, SpiError> {
         let spi = Spi::new(
             Bus::Spi0,
             SlaveSelect::Ss0,
@@ -40,18 +104,21 @@ impl SpiDisplay {
             Mode::Mode0,
         )?;
 
+        let chunk_size = settings.chunk_size();
         tracing::debug!(
-            "SPI initialized: Bus=SPI0, SS=CE0, Speed={}Hz, Mode=0",
-            config::CLOCK_SPEED
+            "SPI initialized: Bus=SPI0, SS=CE0, Speed={}Hz, Mode=0, {} bytes per transfer",
+            config::CLOCK_SPEED,
+            chunk_size
         );
 
-        Ok(Self { spi })
+        Ok(Self { spi, chunk_size })
     }
 
     /// Send a command byte to the display
     ///
     /// Sets DC pin LOW before sending (command mode)
     pub fn write_command(&mut self, gpio: &mut GpioController, cmd: u8) -> Result<(), SpiError> {
+        trace::record(Event::Command(cmd));
         gpio.dc_low();
         self.spi
             .write(&[cmd])
@@ -64,6 +131,7 @@ impl SpiDisplay {
     /// Sets DC pin HIGH before sending (data mode)
     #[allow(dead_code)]
     pub fn write_data(&mut self, gpio: &mut GpioController, data: u8) -> Result<(), SpiError> {
+        trace::record(Event::Data([data][..].into()));
         gpio.dc_high();
         self.spi
             .write(&[data])
@@ -80,20 +148,67 @@ impl SpiDisplay {
         gpio: &mut GpioController,
         data: &[u8],
     ) -> Result<(), SpiError> {
+        trace::record(Event::Data(data.into()));
         gpio.dc_high();
+        self.write_chunked(data)
+    }
 
-        // Write in chunks to avoid potential buffer issues
-        const CHUNK_SIZE: usize = 4096;
+    /// Write bytes without touching the DC pin
+    ///
+    /// For controllers with a framed protocol (IT8951) instead of a DC line.
+    pub fn write_raw(&mut self, data: &[u8]) -> Result<(), SpiError> {
+        trace::record(Event::Write(data.into()));
+        self.write_chunked(data)
+    }
 
-        for chunk in data.chunks(CHUNK_SIZE) {
-            self.spi
-                .write(chunk)
-                .map_err(|e| SpiError::WriteError(e.to_string()))?;
+    /// Write `data` in transfers of up to `chunk_size` bytes
+    ///
+    /// If the kernel rejects a transfer larger than the default size, the
+    /// rest (and all later writes) fall back to the default size; nothing
+    /// is sent by a rejected transfer. A short transfer continues after
+    /// the last byte sent.
+    ///
+    /// Vectored transfers (several segments per ioctl) would not help:
+    /// spidev caps the whole message at `bufsiz`, the same as one chunk.
+    fn write_chunked(&mut self, data: &[u8]) -> Result<(), SpiError> {
+        let mut rest = data;
+
+        while !rest.is_empty() {
+            let len = rest.len().min(self.chunk_size);
+            match self.spi.write(&rest[..len]) {
+                Ok(0) => {
+                    return Err(SpiError::WriteError(format!(
+                        "transfer sent nothing, {} of {} bytes left",
+                        rest.len(),
+                        data.len()
+                    )));
+                }
+                Ok(written) => rest = &rest[written.min(len)..],
+                Err(e) if len > config::DEFAULT_CHUNK_SIZE => {
+                    tracing::warn!(
+                        "SPI transfer of {} bytes rejected ({}), falling back to {} bytes",
+                        len,
+                        e,
+                        config::DEFAULT_CHUNK_SIZE
+                    );
+                    self.chunk_size = config::DEFAULT_CHUNK_SIZE;
+                }
+                Err(e) => return Err(SpiError::WriteError(e.to_string())),
+            }
         }
 
         Ok(())
     }
 
+    /// Read bytes into `buffer`
+    pub fn read_raw(&mut self, buffer: &mut [u8]) -> Result<(), SpiError> {
+        self.spi
+            .read(buffer)
+            .map_err(|e| SpiError::ReadError(e.to_string()))?;
+        trace::record(Event::Read(buffer[..].into()));
+        Ok(())
+    }
+
     /// Send command followed by data bytes
     pub fn write_command_data(
         &mut self,
@@ -108,4 +223,3 @@ impl SpiDisplay {
         Ok(())
     }
 }
-
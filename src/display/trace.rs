@@ -0,0 +1,356 @@
+//! SPI transaction traces.
+//!
+//! With `--spi-trace FILE`, everything the driver does on the wire is
+//! written to a text file, one step per line: opening the GPIO pins,
+//! power, reset, waits for BUSY, delays, chip select, and every command,
+//! data and raw transfer with its bytes in hex. Recording starts with the
+//! process and stops after the first full refresh, so the trace holds the
+//! init sequence and one frame, ready to be compared with what the
+//! Waveshare reference driver sends.
+//!
+//! `epaper-display replay-trace FILE` sends a recorded (or hand-edited)
+//! trace to the panel again, step by step.
+//!
+//! ```text
+//! open
+//! power on
+//! reset
+//! busy
+//! command 01
+//! data 07 07 3f 00
+//! ```
+
+use super::DisplayError;
+use super::gpio::{Chip, GpioController};
+use super::spi::{SpiConfig, SpiDisplay};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Bytes per `data`, `write` or `read` line; longer transfers continue on
+/// the next lines
+const BYTES_PER_LINE: usize = 32;
+
+/// Set while a trace is recorded, so untraced transfers skip the lock
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// File of the trace being recorded
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+/// Trace errors
+#[derive(Error, Debug)]
+pub enum TraceError {
+    #[error("Cannot access trace file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+
+    #[error("A trace opens the GPIO pins once, in its first step")]
+    Open,
+
+    #[error(transparent)]
+    Display(#[from] DisplayError),
+}
+
+/// One step on the wire
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// GPIO pins claimed, with the manually driven chip selects
+    Open(Cow<'a, [u8]>),
+    /// Panel power switched on or off
+    Power(bool),
+    /// Hardware reset pulse
+    Reset,
+    /// Wait while BUSY is low
+    Busy,
+    /// Wait while BUSY is high (SSD16xx panels)
+    BusyHigh,
+    Delay(Duration),
+    Select(Chip),
+    Deselect,
+    /// Command byte (DC low)
+    Command(u8),
+    /// Data bytes (DC high)
+    Data(Cow<'a, [u8]>),
+    /// Bytes written without touching DC (IT8951)
+    Write(Cow<'a, [u8]>),
+    /// Bytes read back
+    Read(Cow<'a, [u8]>),
+}
+
+struct Recorder {
+    path: String,
+    out: BufWriter<File>,
+}
+
+/// Record the driver's transfers to `path` until the first full refresh
+pub fn start(path: &str, panel: &str) -> std::io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "# SPI trace, {} panel, {}",
+        panel,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )?;
+    out.flush()?;
+    *RECORDER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Recorder {
+        path: path.to_string(),
+        out,
+    });
+    RECORDING.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Add `event` to the trace being recorded, if any
+pub fn record(event: Event<'_>) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut recorder = RECORDER.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(recorder) = recorder.as_mut() else {
+        return;
+    };
+    // Flushed at once, so a trace of an init that hangs is complete
+    let written = write_event(&mut recorder.out, &event).and_then(|()| recorder.out.flush());
+    if let Err(e) = written {
+        tracing::warn!("Cannot write SPI trace {}: {}", recorder.path, e);
+    }
+}
+
+/// Stop recording, after the first full refresh
+pub fn finish() {
+    if !RECORDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    if let Some(recorder) = RECORDER.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        tracing::info!("SPI trace written to {}", recorder.path);
+    }
+}
+
+fn write_event(out: &mut impl Write, event: &Event<'_>) -> std::io::Result<()> {
+    let bytes = |out: &mut dyn Write, name: &str, bytes: &[u8]| {
+        for line in bytes.chunks(BYTES_PER_LINE) {
+            write!(out, "{}", name)?;
+            for byte in line {
+                write!(out, " {:02x}", byte)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    };
+    match event {
+        Event::Open(chip_selects) => {
+            write!(out, "open")?;
+            for pin in chip_selects.iter() {
+                write!(out, " {}", pin)?;
+            }
+            writeln!(out)
+        }
+        Event::Power(on) => writeln!(out, "power {}", if *on { "on" } else { "off" }),
+        Event::Reset => writeln!(out, "reset"),
+        Event::Busy => writeln!(out, "busy"),
+        Event::BusyHigh => writeln!(out, "busy_high"),
+        Event::Delay(delay) => writeln!(out, "delay {}", delay.as_millis()),
+        Event::Select(chip) => writeln!(out, "select {}", chip_name(*chip)),
+        Event::Deselect => writeln!(out, "deselect"),
+        Event::Command(command) => writeln!(out, "command {:02x}", command),
+        Event::Data(data) => bytes(out, "data", data),
+        Event::Write(data) => bytes(out, "write", data),
+        Event::Read(data) => bytes(out, "read", data),
+    }
+}
+
+fn chip_name(chip: Chip) -> &'static str {
+    match chip {
+        Chip::Main => "main",
+        Chip::Second => "second",
+        Chip::Both => "both",
+    }
+}
+
+/// Steps of the trace in `path`
+///
+/// Consecutive `data`, `write` or `read` lines are joined into one step.
+/// Empty lines and `#` comments are skipped.
+pub fn load(path: &str) -> Result<Vec<Event<'static>>, TraceError> {
+    let text = std::fs::read_to_string(path)?;
+    let mut events: Vec<Event<'static>> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let parse_error = |message: String| TraceError::Parse {
+            line: index + 1,
+            message,
+        };
+        let mut words = line.split_whitespace();
+        let Some(step) = words.next().filter(|step| !step.starts_with('#')) else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+        let hex = || {
+            args.iter()
+                .map(|arg| u8::from_str_radix(arg, 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| parse_error(format!("invalid hex in `{}`", line.trim())))
+        };
+
+        let event = match (step, args.as_slice()) {
+            ("open", _) => Event::Open(
+                args.iter()
+                    .map(|arg| arg.parse())
+                    .collect::<Result<Vec<u8>, _>>()
+                    .map_err(|_| parse_error(format!("invalid pin in `{}`", line.trim())))?
+                    .into(),
+            ),
+            ("power", ["on"]) => Event::Power(true),
+            ("power", ["off"]) => Event::Power(false),
+            ("reset", []) => Event::Reset,
+            ("busy", []) => Event::Busy,
+            ("busy_high", []) => Event::BusyHigh,
+            ("delay", [ms]) => Event::Delay(Duration::from_millis(
+                ms.parse()
+                    .map_err(|_| parse_error(format!("invalid delay `{}`", ms)))?,
+            )),
+            ("select", ["main"]) => Event::Select(Chip::Main),
+            ("select", ["second"]) => Event::Select(Chip::Second),
+            ("select", ["both"]) => Event::Select(Chip::Both),
+            ("deselect", []) => Event::Deselect,
+            ("command", [_]) => Event::Command(hex()?[0]),
+            ("data", _) => Event::Data(hex()?.into()),
+            ("write", _) => Event::Write(hex()?.into()),
+            ("read", _) => Event::Read(hex()?.into()),
+            _ => return Err(parse_error(format!("unknown step `{}`", line.trim()))),
+        };
+
+        match (events.last_mut(), event) {
+            (Some(Event::Data(prev)), Event::Data(more))
+            | (Some(Event::Write(prev)), Event::Write(more))
+            | (Some(Event::Read(prev)), Event::Read(more)) => {
+                prev.to_mut().extend_from_slice(&more)
+            }
+            (_, event) => events.push(event),
+        }
+    }
+    Ok(events)
+}
+
+/// Result of a replay
+#[derive(Debug, Default)]
+pub struct ReplaySummary {
+    /// Steps replayed
+    pub steps: usize,
+    /// Bytes sent (commands, data and raw writes)
+    pub bytes_sent: usize,
+    /// Reads that returned other bytes than recorded
+    pub read_mismatches: usize,
+}
+
+/// Send `events` to the panel, step by step
+///
+/// The first step must be `open`. Reads are compared with the recorded
+/// bytes; differences are logged and counted, not treated as errors.
+pub fn replay(events: &[Event<'_>], spi: &SpiConfig) -> Result<ReplaySummary, TraceError> {
+    let mut summary = ReplaySummary::default();
+    let (mut gpio, mut spi) = match events.first() {
+        Some(Event::Open(chip_selects)) => (
+            GpioController::new(chip_selects).map_err(DisplayError::from)?,
+            SpiDisplay::new(spi).map_err(DisplayError::from)?,
+        ),
+        _ => return Err(TraceError::Open),
+    };
+
+    for (step, event) in events.iter().enumerate().skip(1) {
+        match event {
+            Event::Open(_) => return Err(TraceError::Open),
+            Event::Power(true) => gpio.power_on(),
+            Event::Power(false) => gpio.power_off(),
+            Event::Reset => gpio.reset(),
+            Event::Busy => gpio.wait_busy().map_err(DisplayError::from)?,
+            Event::BusyHigh => gpio.wait_busy_high().map_err(DisplayError::from)?,
+            Event::Delay(delay) => std::thread::sleep(*delay),
+            Event::Select(chip) => gpio.select(*chip),
+            Event::Deselect => gpio.deselect(),
+            Event::Command(command) => {
+                spi.write_command(&mut gpio, *command)
+                    .map_err(DisplayError::from)?;
+                summary.bytes_sent += 1;
+            }
+            Event::Data(data) => {
+                spi.write_data_bulk(&mut gpio, data)
+                    .map_err(DisplayError::from)?;
+                summary.bytes_sent += data.len();
+            }
+            Event::Write(data) => {
+                spi.write_raw(data).map_err(DisplayError::from)?;
+                summary.bytes_sent += data.len();
+            }
+            Event::Read(expected) => {
+                // Spectra 6 controllers answer with DC high; IT8951 ignores DC
+                gpio.dc_high();
+                let mut actual = vec![0; expected.len()];
+                spi.read_raw(&mut actual).map_err(DisplayError::from)?;
+                if actual != **expected {
+                    tracing::warn!(
+                        "Step {}: read {:02x?}, recorded {:02x?}",
+                        step + 1,
+                        actual,
+                        expected
+                    );
+                    summary.read_mismatches += 1;
+                }
+            }
+        }
+    }
+    summary.steps = events.len();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spi_traces_are_read_step_by_step() {
+        let path = std::env::temp_dir().join(format!("epaper-trace-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(
+            path,
+            "# SPI trace, 13in3e panel\n\
+             open 8 7\n\
+             power on\n\
+             reset\n\
+             busy\n\
+             delay 30\n\
+             select both\n\
+             command 10\n\
+             data 11 22\n\
+             data 33\n\
+             deselect\n",
+        )
+        .unwrap();
+        let events = load(path).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::Open(vec![8, 7].into()),
+                Event::Power(true),
+                Event::Reset,
+                Event::Busy,
+                Event::Delay(Duration::from_millis(30)),
+                Event::Select(Chip::Both),
+                Event::Command(0x10),
+                Event::Data(vec![0x11, 0x22, 0x33].into()),
+                Event::Deselect,
+            ]
+        );
+
+        std::fs::write(path, "open\ncommand 1g\n").unwrap();
+        let error = load(path).unwrap_err().to_string();
+        assert!(error.starts_with("Line 2:"), "{}", error);
+        std::fs::remove_file(path).unwrap();
+    }
+}
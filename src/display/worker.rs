@@ -0,0 +1,193 @@
+//! Display thread.
+//!
+//! The panel backend, and with it the GPIO and SPI handles, lives on a
+//! dedicated OS thread. [`DisplayController`](super::DisplayController)
+//! sends it commands over a channel and awaits the replies, so blocking
+//! driver code (busy waits, multi-second refreshes) never runs on the
+//! async runtime. Commands run one at a time, in order; a caller that
+//! stops waiting does not interrupt the command it sent.
+
+use super::{
+    BackendSlot, Color, DisplayBackend, DisplayError, Epd7in3e, EpdBwr, It8951, It8951Config,
+    MockDisplay, MockHandle, PanelFamily, PanelModel, PixelFormat, Region, Spectra6Config,
+    SpiConfig,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, PoisonError};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// Reply channel of a command
+pub type Reply<T> = oneshot::Sender<Result<T, DisplayError>>;
+
+/// Request to the display thread
+pub enum Command {
+    /// Create and initialize the backend unless already done
+    Init(Backend, Reply<()>),
+    /// Frame format of the backend
+    PixelFormat(Reply<PixelFormat>),
+    /// Full refresh with a frame
+    Display(Vec<u8>, Reply<()>),
+    /// Refresh a region from a full frame
+    DisplayPartial(Vec<u8>, Region, Reply<()>),
+    /// Whether the backend supports partial refresh
+    SupportsPartial(Reply<bool>),
+    /// Clear to white
+    Clear(Reply<()>),
+    /// Show the test pattern
+    TestPattern(Reply<()>),
+    /// Put the panel to sleep and drop the backend
+    Sleep(Reply<()>),
+    /// Whether a backend is initialized
+    IsInitialized(Reply<bool>),
+    /// Send a raw controller command with its data
+    Raw(u16, Vec<u8>, Reply<()>),
+}
+
+/// Which backend the controller creates on init
+#[derive(Clone)]
+pub enum BackendKind {
+    /// Waveshare panel over SPI/GPIO
+    Hardware,
+    /// In-memory mock recording frames into the shared handle
+    Mock(MockHandle),
+}
+
+/// Everything needed to create the backend
+#[derive(Clone)]
+pub struct Backend {
+    pub kind: BackendKind,
+    pub model: PanelModel,
+    pub it8951: It8951Config,
+    pub spectra6: Spectra6Config,
+    pub spi: SpiConfig,
+}
+
+impl Backend {
+    /// Create the configured backend
+    fn create(&self) -> Result<Box<dyn DisplayBackend>, DisplayError> {
+        Ok(match &self.kind {
+            BackendKind::Hardware => match self.model.family() {
+                PanelFamily::Spectra6 => {
+                    Box::new(Epd7in3e::new(self.model, self.spectra6.clone(), &self.spi)?)
+                }
+                PanelFamily::BlackWhiteRed => Box::new(EpdBwr::new(self.model, &self.spi)?),
+                PanelFamily::It8951 => {
+                    Box::new(It8951::new(self.model, self.it8951.clone(), &self.spi)?)
+                }
+            },
+            BackendKind::Mock(handle) => Box::new(MockDisplay::new(Arc::clone(handle), self.model)),
+        })
+    }
+}
+
+/// Start the display thread
+///
+/// The thread runs until every sender of `commands` is dropped.
+pub fn spawn(commands: Receiver<Command>, slot: Arc<BackendSlot>, last_refresh_ms: Arc<AtomicU64>) {
+    std::thread::Builder::new()
+        .name("display".to_string())
+        .spawn(move || {
+            for command in commands {
+                run(command, &slot, &last_refresh_ms);
+            }
+            tracing::debug!("Display thread stopped");
+        })
+        .expect("Failed to start display thread");
+}
+
+/// Execute one command and send its reply
+///
+/// Replies to callers that stopped waiting are dropped.
+fn run(command: Command, slot: &BackendSlot, last_refresh_ms: &AtomicU64) {
+    let mut guard = slot.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // Run a full refresh, recording how long it took
+    let timed = |refresh: &mut dyn FnMut() -> Result<(), DisplayError>| {
+        let started = Instant::now();
+        let result = refresh();
+        super::trace::finish();
+        result?;
+        let elapsed = started.elapsed().as_millis().max(1) as u64;
+        last_refresh_ms.store(elapsed, Ordering::Relaxed);
+        Ok(())
+    };
+
+    match command {
+        Command::Init(backend, reply) => {
+            let result = if guard.is_some() {
+                tracing::debug!("Display already initialized");
+                Ok(())
+            } else {
+                backend.create().and_then(|mut display| {
+                    display.init()?;
+                    *guard = Some(display);
+                    Ok(())
+                })
+            };
+            let _ = reply.send(result);
+        }
+        Command::PixelFormat(reply) => {
+            let result = guard
+                .as_ref()
+                .map(|display| display.pixel_format())
+                .ok_or(DisplayError::NotInitialized);
+            let _ = reply.send(result);
+        }
+        Command::Display(buffer, reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => timed(&mut || display.display(&buffer)),
+                None => Err(DisplayError::NotInitialized),
+            };
+            let _ = reply.send(result);
+        }
+        Command::DisplayPartial(buffer, region, reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => display.display_partial(&buffer, region),
+                None => Err(DisplayError::NotInitialized),
+            };
+            let _ = reply.send(result);
+        }
+        Command::SupportsPartial(reply) => {
+            let supported = guard
+                .as_ref()
+                .is_some_and(|display| display.supports_partial());
+            let _ = reply.send(Ok(supported));
+        }
+        Command::Clear(reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => timed(&mut || display.clear(Color::White)),
+                None => Err(DisplayError::NotInitialized),
+            };
+            let _ = reply.send(result);
+        }
+        Command::TestPattern(reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => timed(&mut || display.test_pattern()),
+                None => Err(DisplayError::NotInitialized),
+            };
+            let _ = reply.send(result);
+        }
+        Command::Sleep(reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => display.sleep(),
+                None => Ok(()),
+            };
+            if result.is_ok() {
+                *guard = None;
+            }
+            let _ = reply.send(result);
+        }
+        Command::IsInitialized(reply) => {
+            let _ = reply.send(Ok(guard.is_some()));
+        }
+        Command::Raw(command, data, reply) => {
+            let result = match guard.as_mut() {
+                Some(display) => display.raw_command(command, &data),
+                None => Err(DisplayError::NotInitialized),
+            };
+            let _ = reply.send(result);
+        }
+    }
+}
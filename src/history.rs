@@ -0,0 +1,180 @@
+//! History of displayed frames.
+//!
+//! With `history_frames` set, every frame shown with a full refresh is
+//! kept as a PNG in the `history` directory next to the config file, so
+//! the web UI can show what the panel displayed over the last days. The
+//! oldest files are removed once more than `history_frames` are stored.
+
+use chrono::{Local, NaiveDateTime};
+use std::path::{Path, PathBuf};
+
+/// Directory inside the state directory holding the frames
+pub const HISTORY_DIR: &str = "history";
+
+/// File name timestamp format; sorts chronologically
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Most frames in one timelapse; longer ranges are sampled evenly
+#[cfg(feature = "timelapse")]
+pub const MAX_TIMELAPSE_FRAMES: usize = 300;
+
+/// A stored frame
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// File name inside the history directory
+    pub name: String,
+    /// Local time the frame was displayed
+    pub displayed_at: NaiveDateTime,
+}
+
+/// Frames kept on disk
+#[derive(Debug, Clone)]
+pub struct FrameHistory {
+    dir: PathBuf,
+}
+
+impl FrameHistory {
+    /// History stored below `state_dir`
+    pub fn new(state_dir: &Path) -> Self {
+        Self {
+            dir: state_dir.join(HISTORY_DIR),
+        }
+    }
+
+    /// Store a frame, keeping at most `keep` frames
+    pub fn record(&self, png: &[u8], keep: usize) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let stamp = Local::now().format(TIMESTAMP_FORMAT).to_string();
+        // Two frames within a second (manual refresh right after a
+        // scheduled one) get a counter instead of overwriting each other
+        let mut name = format!("frame-{}.png", stamp);
+        let mut n = 1;
+        while self.dir.join(&name).exists() {
+            name = format!("frame-{}-{}.png", stamp, n);
+            n += 1;
+        }
+        std::fs::write(self.dir.join(&name), png)?;
+
+        let entries = self.list();
+        for old in entries.iter().skip(keep) {
+            if let Err(e) = std::fs::remove_file(self.dir.join(&old.name)) {
+                tracing::warn!("Failed to remove old frame {}: {}", old.name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stored frames, newest first
+    pub fn list(&self) -> Vec<HistoryEntry> {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut entries: Vec<HistoryEntry> = dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let displayed_at = parse_name(&name)?;
+                Some(HistoryEntry { name, displayed_at })
+            })
+            .collect();
+        // Within a second, suffixed names (`-1`, `-2`, ...) are the newer ones
+        entries.sort_by(|a, b| {
+            (b.displayed_at, b.name.len(), &b.name).cmp(&(a.displayed_at, a.name.len(), &a.name))
+        });
+        entries
+    }
+
+    /// Stored frames displayed between `from` and `to` (inclusive), oldest
+    /// first
+    #[cfg(feature = "timelapse")]
+    pub fn range(
+        &self,
+        from: Option<chrono::NaiveDate>,
+        to: Option<chrono::NaiveDate>,
+    ) -> Vec<HistoryEntry> {
+        let mut entries = self.list();
+        entries.reverse();
+        entries.retain(|entry| {
+            let day = entry.displayed_at.date();
+            from.is_none_or(|from| day >= from) && to.is_none_or(|to| day <= to)
+        });
+        entries
+    }
+
+    /// Animated GIF of the frames displayed between `from` and `to`
+    ///
+    /// Frames are scaled to at most `max_width` pixels wide (nearest
+    /// neighbour, so the panel palette stays exact and no quantization is
+    /// needed) and shown for `delay_ms` each. Returns None if no frames were
+    /// displayed in the range.
+    #[cfg(feature = "timelapse")]
+    pub fn timelapse(
+        &self,
+        from: Option<chrono::NaiveDate>,
+        to: Option<chrono::NaiveDate>,
+        max_width: u32,
+        delay_ms: u32,
+    ) -> Result<Option<Vec<u8>>, image::ImageError> {
+        use image::codecs::gif::{GifEncoder, Repeat};
+        use image::imageops::FilterType;
+        use image::{Delay, Frame};
+
+        let entries = self.range(from, to);
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let step = entries.len().div_ceil(MAX_TIMELAPSE_FRAMES);
+
+        let mut gif = Vec::new();
+        let mut encoder = GifEncoder::new_with_speed(&mut gif, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+
+        // All frames take the size of the first one (the orientation may
+        // have changed within the range)
+        let mut size = None;
+        for entry in entries.iter().step_by(step) {
+            let Some(img) = self
+                .read(&entry.name)
+                .and_then(|png| image::load_from_memory(&png).ok())
+            else {
+                tracing::warn!("Skipping unreadable frame {}", entry.name);
+                continue;
+            };
+            let (width, height) = *size.get_or_insert_with(|| {
+                let width = img.width().min(max_width);
+                (width, (img.height() * width / img.width()).max(1))
+            });
+            let img = if (img.width(), img.height()) == (width, height) {
+                img
+            } else {
+                img.resize_exact(width, height, FilterType::Nearest)
+            };
+
+            encoder.encode_frame(Frame::from_parts(img.to_rgba8(), 0, 0, delay))?;
+        }
+        drop(encoder);
+
+        Ok(size.map(|_| gif))
+    }
+
+    /// PNG of a stored frame
+    ///
+    /// Only names of the form `list` returns are accepted.
+    pub fn read(&self, name: &str) -> Option<Vec<u8>> {
+        parse_name(name)?;
+        std::fs::read(self.dir.join(name)).ok()
+    }
+}
+
+/// Display time encoded in a frame file name
+fn parse_name(name: &str) -> Option<NaiveDateTime> {
+    let stem = name.strip_prefix("frame-")?.strip_suffix(".png")?;
+    if !stem.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return None;
+    }
+    let stamp = stem.get(..15)?;
+    NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()
+}
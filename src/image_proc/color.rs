@@ -0,0 +1,147 @@
+//! Color adjustments applied before dithering.
+//!
+//! The color profile changes colors of the transformed image so they come
+//! out better on the panel for the people looking at it. With the
+//! `colorblind` profile, greens are turned into blue so red/green
+//! content (charts, traffic lights, status dots) becomes red/blue, which
+//! stays distinct with red-green color blindness (protanopia,
+//! deuteranopia). Yellows, reds and blues keep their hue; brightness and
+//! saturation are kept for every pixel.
+
+use crate::display::{Color, Palette, PixelFormat};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+/// Hue map of the `colorblind` profile, in degrees: (from, to) points,
+/// linear in between
+///
+/// Greens and cyans (120-240°) become blue, so the dithering needs no
+/// green ink for them; yellow-greens are spread over the hues in between,
+/// and everything from blue through purple and red to yellow stays.
+const COLORBLIND_HUES: [(i32, i32); 5] = [(0, 0), (60, 60), (120, 240), (240, 240), (360, 360)];
+
+/// Panel colors the `colorblind` profile dithers with
+const WITHOUT_GREEN: [Color; 6] = [
+    Color::Black,
+    Color::White,
+    Color::Yellow,
+    Color::Red,
+    Color::Orange,
+    Color::Blue,
+];
+
+/// How colors are adjusted before dithering
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorProfile {
+    /// Colors as they are
+    #[default]
+    Standard,
+    /// Greens shown as blue, for red-green color blindness
+    Colorblind,
+}
+
+/// Adjust `img` for `profile` before it is dithered to `format`
+///
+/// Returns the format to dither with: the colorblind profile leaves out
+/// the green ink, so error diffusion does not bring green dots back. The
+/// frame is still in the panel's `format`. Panels without blue, and grey
+/// panels, are left alone.
+pub fn apply_profile(
+    img: &mut RgbImage,
+    profile: ColorProfile,
+    format: PixelFormat,
+) -> PixelFormat {
+    match (profile, format.palette) {
+        (ColorProfile::Colorblind, Palette::Colors(colors)) if colors.contains(&Color::Blue) => {
+            tracing::debug!("Remapping greens to blue (colorblind profile)");
+            for pixel in img.pixels_mut() {
+                pixel.0 = remap_hue(pixel.0, &COLORBLIND_HUES);
+            }
+            if WITHOUT_GREEN.iter().all(|color| colors.contains(color)) {
+                return PixelFormat {
+                    palette: Palette::Colors(&WITHOUT_GREEN),
+                    ..format
+                };
+            }
+            format
+        }
+        _ => format,
+    }
+}
+
+/// `rgb` with its hue moved along `map`, same brightness and saturation
+fn remap_hue(rgb: [u8; 3], map: &[(i32, i32)]) -> [u8; 3] {
+    let [r, g, b] = rgb.map(i32::from);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    if chroma == 0 {
+        return rgb;
+    }
+
+    // Hue in degrees, from the sector of the largest channel
+    let hue = if max == r {
+        (60 * (g - b) / chroma).rem_euclid(360)
+    } else if max == g {
+        120 + 60 * (b - r) / chroma
+    } else {
+        240 + 60 * (r - g) / chroma
+    };
+    let hue = map
+        .windows(2)
+        .find(|pair| hue <= pair[1].0)
+        .map(|pair| {
+            let ((from_a, to_a), (from_b, to_b)) = (pair[0], pair[1]);
+            to_a + (hue - from_a) * (to_b - to_a) / (from_b - from_a)
+        })
+        .unwrap_or(hue)
+        % 360;
+
+    // Back to RGB with the same largest and smallest channel
+    let rising = |offset: i32| min + chroma * offset / 60;
+    let falling = |offset: i32| max - chroma * offset / 60;
+    let (r, g, b) = match hue / 60 {
+        0 => (max, rising(hue), min),
+        1 => (falling(hue - 60), max, min),
+        2 => (min, max, rising(hue - 120)),
+        3 => (min, falling(hue - 180), max),
+        4 => (rising(hue - 240), min, max),
+        _ => (max, min, falling(hue - 300)),
+    };
+    [r as u8, g as u8, b as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PanelModel;
+    use crate::image_proc::dither_image;
+
+    #[test]
+    fn colorblind_profile_shows_greens_as_blue() {
+        // Red and green bars of a chart on white
+        let chart = image::RgbImage::from_fn(40, 20, |x, _| {
+            image::Rgb(match x / 10 {
+                0 => [220, 30, 30],
+                1 => [40, 180, 60],
+                _ => [255, 255, 255],
+            })
+        });
+        let format = PixelFormat::packed4(PanelModel::Epd7in3e.spec().palette);
+        let count = |profile, color: Color| {
+            let mut img = chart.clone();
+            let dither_format = apply_profile(&mut img, profile, format);
+            let frame = dither_image(&img, dither_format, 0);
+            frame
+                .iter()
+                .map(|&b| (b >> 4 == color as u8) as usize + (b & 0x0F == color as u8) as usize)
+                .sum::<usize>()
+        };
+
+        assert!(count(ColorProfile::Standard, Color::Green) > 100);
+        assert_eq!(count(ColorProfile::Colorblind, Color::Green), 0);
+        assert!(count(ColorProfile::Colorblind, Color::Blue) > 100);
+        assert!(count(ColorProfile::Colorblind, Color::Red) > 100);
+    }
+}
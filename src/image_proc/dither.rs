@@ -1,14 +1,23 @@
-//! Floyd-Steinberg dithering for 7-color e-paper display.
+//! Floyd-Steinberg dithering for color e-paper displays.
 //!
-//! Converts RGB images to the 7-color palette of the EPD7IN3E display
-//! using error diffusion dithering.
+//! Converts RGB images to the colors the panel can show (the 7-color
+//! palette of the EPD7IN3E display, or a subset such as black/white/red)
+//! using error diffusion dithering. Greyscale panels get grey levels
+//! instead, dithered on luminance. Palette and bit depth come from the
+//! active display backend's [`PixelFormat`].
 //!
 //! This implementation uses a memory-optimized row-by-row approach that only
-//! keeps 2 rows in memory at a time, reducing memory usage from ~4.4MB to ~19KB
-//! for an 800x480 image. This is critical for the Pi Zero W's limited RAM.
+//! keeps one row of error in memory, reducing memory usage from ~4.4MB to
+//! ~5KB for an 800x480 image. This is critical for the Pi Zero W's limited
+//! RAM. Dithering is also a large part of a refresh on its single core, so
+//! the inner loop has no divisions (the CPU has no divide instruction) and
+//! keeps the error for neighbouring pixels in locals.
 
-use crate::display::Color;
-use image::RgbImage;
+use crate::display::{Color, Palette, PixelFormat};
+use image::{Rgb, RgbImage};
+#[cfg(feature = "renderers")]
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
 /// RGB values for each display color (using i16 for error diffusion arithmetic)
 const PALETTE: [(i16, i16, i16); 7] = [
@@ -21,136 +30,398 @@ const PALETTE: [(i16, i16, i16); 7] = [
     (0, 255, 0),     // Green
 ];
 
-/// Find the nearest palette color using Euclidean distance in RGB space
-/// Uses i32 internally for distance calculation to avoid overflow
-#[inline]
-fn find_nearest_color(r: i16, g: i16, b: i16) -> usize {
-    PALETTE
-        .iter()
-        .enumerate()
-        .min_by_key(|(_, (pr, pg, pb))| {
-            let dr = (r - pr) as i32;
-            let dg = (g - pg) as i32;
-            let db = (b - pb) as i32;
-            dr * dr + dg * dg + db * db
-        })
-        .map(|(i, _)| i)
-        .unwrap_or(0)
+/// How an image is turned into panel colors
+#[cfg(feature = "renderers")]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DitherMode {
+    /// Floyd-Steinberg error diffusion, for photos and gradients
+    #[default]
+    FloydSteinberg,
+    /// Nearest panel color for every pixel, for crisp text and lines
+    None,
 }
 
-/// Calculate buffer size for given dimensions (2 pixels per byte)
-pub fn calculate_buffer_size(width: u32, height: u32) -> usize {
-    (width as usize * height as usize) / 2
+/// Pixels mapped straight to a palette color instead of being dithered
+#[derive(Debug, Clone, Copy)]
+enum Snap {
+    /// Those within this many levels of black or white (0 = none)
+    Near(u8),
+    /// All of them, to the nearest color
+    #[cfg_attr(not(feature = "renderers"), allow(dead_code))]
+    All,
 }
 
-/// Apply Floyd-Steinberg dithering to an RGB image
+/// Palette subset prepared for the nearest-color search
 ///
-/// Returns a buffer of packed 4-bit pixel data (2 pixels per byte)
-/// suitable for sending to the EPD7IN3E display.
+/// Holds the RGB values of the panel's colors next to their indices, so
+/// the per-pixel search runs over a small local array.
+struct Nearest {
+    entries: Vec<([i32; 3], u8)>,
+}
+
+impl Nearest {
+    fn new(colors: &[Color]) -> Self {
+        let mut entries: Vec<([i32; 3], u8)> = colors
+            .iter()
+            .map(|&c| {
+                let (r, g, b) = PALETTE[c as usize];
+                ([r as i32, g as i32, b as i32], c as u8)
+            })
+            .collect();
+        if entries.is_empty() {
+            let (r, g, b) = PALETTE[0];
+            entries.push(([r as i32, g as i32, b as i32], 0));
+        }
+        Self { entries }
+    }
+
+    /// Nearest entry using Euclidean distance in RGB space (first on ties)
+    #[inline(always)]
+    fn find(&self, rgb: [i32; 3]) -> &([i32; 3], u8) {
+        let mut best = &self.entries[0];
+        let mut best_dist = i32::MAX;
+        for entry in &self.entries {
+            let dr = rgb[0] - entry.0[0];
+            let dg = rgb[1] - entry.0[1];
+            let db = rgb[2] - entry.0[2];
+            let dist = dr * dr + dg * dg + db * db;
+            if dist < best_dist {
+                best = entry;
+                best_dist = dist;
+            }
+        }
+        best
+    }
+}
+
+/// Writes pixel values into (part of) a packed frame, first pixel in the
+/// most significant bits of each byte
+struct Packer<'a> {
+    buffer: &'a mut [u8],
+    bits: usize,
+    next: usize,
+}
+
+impl<'a> Packer<'a> {
+    fn new(buffer: &'a mut [u8], bits: usize) -> Self {
+        Self {
+            buffer,
+            bits,
+            next: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, code: u8) {
+        let bit = self.next * self.bits;
+        let shift = 8 - self.bits - bit % 8;
+        self.buffer[bit / 8] |= (code & max_code(self.bits) as u8) << shift;
+        self.next += 1;
+    }
+}
+
+/// Largest value a pixel of `bits` bits can hold
+fn max_code(bits: usize) -> u16 {
+    (1 << bits) - 1
+}
+
+/// Rows dithered before a band starts, to build up the error the rows
+/// above would have passed down
+const LEAD_IN_ROWS: usize = 16;
+
+/// Apply Floyd-Steinberg dithering to an RGB image
 ///
-/// This implementation uses a memory-optimized row-by-row approach:
-/// - Only keeps 2 rows of error accumulation in memory at a time
-/// - Uses i16 instead of i32 (error range is -255 to +255, fits in i16)
-/// - Memory usage: ~19KB for 2 rows vs ~4.4MB for full image buffer
+/// Dithers to the palette of `format` and packs the result at its bit
+/// depth, ready to be sent to the display backend that reported it.
+/// Pixels within `snap` of black or white (in every channel) are shown as
+/// pure black or white without dithering: they neither take nor pass on
+/// error, so text stays sharp while photos in the same frame are dithered
+/// as usual. `snap` = 0 snaps nothing.
 ///
 /// The image dimensions should match the expected target dimensions.
-pub fn dither_image(img: &RgbImage) -> Vec<u8> {
+/// With the parallel pipeline (`--parallel`) the image is split into one
+/// band of rows per core, see [`dither_in_bands`].
+pub fn dither_image(img: &RgbImage, format: PixelFormat, snap: u8) -> Vec<u8> {
+    dither_in_bands(img, format, snap, super::parallelism())
+}
+
+/// Dither in `bands` horizontal bands of rows, in parallel when built with
+/// the `parallel` feature
+///
+/// Each band starts [`LEAD_IN_ROWS`] rows early so it picks up error much
+/// like the rows above would pass down; the lead-in rows are not written.
+/// The result is close to, but not exactly, that of a single band, which
+/// is what `bands` = 1 gives.
+pub fn dither_in_bands(img: &RgbImage, format: PixelFormat, snap: u8, bands: usize) -> Vec<u8> {
+    quantize(img, format, Snap::Near(snap), bands)
+}
+
+/// Map every pixel to the nearest color of `format`, without dithering
+///
+/// Packed like [`dither_image`]. Flat areas in colors the panel does not
+/// have come out as one panel color instead of a dither pattern.
+#[cfg(feature = "renderers")]
+pub fn nearest_colors(img: &RgbImage, format: PixelFormat) -> Vec<u8> {
+    quantize(img, format, Snap::All, super::parallelism())
+}
+
+fn quantize(img: &RgbImage, format: PixelFormat, snap: Snap, bands: usize) -> Vec<u8> {
     let (width, height) = img.dimensions();
-    let width_usize = width as usize;
-    let height_usize = height as usize;
+    let bits = format.bits_per_pixel as usize;
+    let mut buffer = vec![0u8; format.buffer_size(width, height)];
 
-    tracing::info!(
-        "Applying Floyd-Steinberg dithering ({}x{}) - memory optimized",
-        width,
-        height
-    );
+    // Bands of a multiple of 8 rows start on a byte boundary
+    let band_rows = (height as usize)
+        .div_ceil(bands.max(1))
+        .next_multiple_of(8)
+        .max(8);
+    let band_bytes = (band_rows * width as usize * bits).div_ceil(8).max(1);
+    let dither_band = |(i, chunk): (usize, &mut [u8])| {
+        let start = i * band_rows;
+        let rows = start..(start + band_rows).min(height as usize);
+        let mut out = Packer::new(chunk, bits);
+        match format.palette {
+            Palette::Colors(colors) => dither_colors(img, colors, snap, rows, &mut out),
+            Palette::Grey(levels) => dither_grey(img, levels, snap, rows, &mut out),
+        }
+    };
 
-    // Only need 2 rows at a time: current and next
-    // Using i16 instead of i32 (error range is -255 to +255, fits in i16)
-    // Memory: 2 * width * 6 bytes = ~9.6KB for 800px width
-    let mut curr_row: Vec<(i16, i16, i16)> = vec![(0, 0, 0); width_usize];
-    let mut next_row: Vec<(i16, i16, i16)> = vec![(0, 0, 0); width_usize];
-
-    // Output buffer (packed 4-bit pixels)
-    let buffer_size = calculate_buffer_size(width, height);
-    let mut result = vec![0u8; buffer_size];
-
-    for y in 0..height_usize {
-        // Load current row pixels and add accumulated error from previous row
-        for x in 0..width_usize {
-            let p = img.get_pixel(x as u32, y as u32);
-            curr_row[x].0 += p[0] as i16;
-            curr_row[x].1 += p[1] as i16;
-            curr_row[x].2 += p[2] as i16;
+    match format.palette {
+        _ if matches!(snap, Snap::All) => {
+            tracing::info!("Mapping to the nearest panel colors ({}x{})", width, height)
         }
+        Palette::Colors(_) => tracing::info!(
+            "Applying Floyd-Steinberg dithering ({}x{}) - memory optimized",
+            width,
+            height
+        ),
+        Palette::Grey(levels) => tracing::info!(
+            "Applying Floyd-Steinberg greyscale dithering ({}x{}, {} levels)",
+            width,
+            height,
+            grey_steps(levels, bits) + 1
+        ),
+    }
+    if bands > 1 {
+        tracing::debug!("Dithering in {} bands of {} rows", bands, band_rows);
+    }
+    if let Snap::Near(snap @ 1..) = snap {
+        tracing::debug!("Snapping pixels within {} of black or white", snap);
+    }
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        buffer
+            .par_chunks_mut(band_bytes)
+            .enumerate()
+            .for_each(dither_band);
+    }
+    #[cfg(not(feature = "parallel"))]
+    buffer
+        .chunks_mut(band_bytes)
+        .enumerate()
+        .for_each(dither_band);
 
-        for x in 0..width_usize {
-            let (r, g, b) = curr_row[x];
-
-            // Clamp values to valid range
-            let r = r.clamp(0, 255);
-            let g = g.clamp(0, 255);
-            let b = b.clamp(0, 255);
-
-            // Find nearest palette color
-            let color_idx = find_nearest_color(r, g, b);
-            let (pr, pg, pb) = PALETTE[color_idx];
-
-            // Calculate quantization error
-            let err_r = r - pr;
-            let err_g = g - pg;
-            let err_b = b - pb;
-
-            // Distribute error to neighboring pixels (Floyd-Steinberg pattern)
-            // Right: 7/16
-            if x + 1 < width_usize {
-                curr_row[x + 1].0 += err_r * 7 / 16;
-                curr_row[x + 1].1 += err_g * 7 / 16;
-                curr_row[x + 1].2 += err_b * 7 / 16;
-            }
+    tracing::debug!("Dithering complete, output size: {} bytes", buffer.len());
+    buffer
+}
 
-            if y + 1 < height_usize {
-                // Bottom-left: 3/16
-                if x > 0 {
-                    next_row[x - 1].0 += err_r * 3 / 16;
-                    next_row[x - 1].1 += err_g * 3 / 16;
-                    next_row[x - 1].2 += err_b * 3 / 16;
-                }
+/// Dither `rows` to the panel's `colors`, writing their indices
+fn dither_colors(
+    img: &RgbImage,
+    colors: &[Color],
+    snap: Snap,
+    rows: Range<usize>,
+    out: &mut Packer,
+) {
+    let nearest = Nearest::new(colors);
+    let black = colors.contains(&Color::Black).then_some(Color::Black as u8);
+    let white = colors.contains(&Color::White).then_some(Color::White as u8);
+    diffuse(
+        img,
+        rows,
+        out,
+        |p| match snap {
+            Snap::Near(snap) => snapped(p, snap, black, white),
+            Snap::All => Some(nearest.find([p[0] as i32, p[1] as i32, p[2] as i32]).1),
+        },
+        |p| [p[0] as i32, p[1] as i32, p[2] as i32],
+        |rgb| {
+            let (color, index) = *nearest.find(rgb);
+            let err = [rgb[0] - color[0], rgb[1] - color[1], rgb[2] - color[2]];
+            (index, err)
+        },
+    );
+}
+
+/// Dither `rows` to `levels` evenly spaced grey levels
+///
+/// Values are scaled to the full pixel range (0 = black, all bits set =
+/// white) whatever the number of levels. Same error diffusion as the color
+/// path, on a single luminance channel.
+fn dither_grey(img: &RgbImage, levels: u8, snap: Snap, rows: Range<usize>, out: &mut Packer) {
+    let max_code = max_code(out.bits) as i32;
+    let steps = grey_steps(levels, out.bits);
+
+    // Pixel value and quantization error for every clamped input value,
+    // computed once so the inner loop has no divisions
+    let levels: Vec<(u8, i32)> = (0..=255)
+        .map(|value: i32| {
+            let step = (value * steps + 127) / 255;
+            let err = value - step * 255 / steps;
+            ((step * max_code / steps) as u8, err)
+        })
+        .collect();
 
-                // Bottom: 5/16
-                next_row[x].0 += err_r * 5 / 16;
-                next_row[x].1 += err_g * 5 / 16;
-                next_row[x].2 += err_b * 5 / 16;
+    let white = Some(max_code as u8);
+    diffuse(
+        img,
+        rows,
+        out,
+        |p| match snap {
+            Snap::Near(snap) => snapped(p, snap, Some(0), white),
+            Snap::All => Some(levels[luma(p[0], p[1], p[2]) as usize].0),
+        },
+        |p| [luma(p[0], p[1], p[2])],
+        |[value]| {
+            let (code, err) = levels[value as usize];
+            (code, [err])
+        },
+    );
+}
+
+/// Pixel value for a pixel within `snap` of black or white in every
+/// channel, if the panel has that color
+#[inline(always)]
+fn snapped(pixel: &[u8], snap: u8, black: Option<u8>, white: Option<u8>) -> Option<u8> {
+    if snap == 0 {
+        None
+    } else if pixel.iter().all(|&v| v <= snap) {
+        black
+    } else if pixel.iter().all(|&v| v >= 255 - snap) {
+        white
+    } else {
+        None
+    }
+}
 
-                // Bottom-right: 1/16
-                if x + 1 < width_usize {
-                    next_row[x + 1].0 += err_r / 16;
-                    next_row[x + 1].1 += err_g / 16;
-                    next_row[x + 1].2 += err_b / 16;
+/// Steps between the grey levels used: `levels` - 1, within what `bits`
+/// bits can hold
+fn grey_steps(levels: u8, bits: usize) -> i32 {
+    (levels as i32).clamp(2, max_code(bits) as i32 + 1) - 1
+}
+
+/// Floyd-Steinberg error diffusion over `N` channels
+///
+/// `snap` gives the pixel value for pixels that are not dithered: they
+/// drop the error diffused to them and pass none on. `load` turns other
+/// pixels into channel values; `quantize` gets them with the diffused
+/// error added and clamped to 0..=255 and returns the pixel value to write
+/// with the quantization error per channel. Rows from
+/// [`LEAD_IN_ROWS`] above `rows` are diffused too, without writing them.
+///
+/// The error for the row below lives in a single row buffer (i16 is
+/// enough, the error range is -255 to +255; arithmetic is done in i32,
+/// the native width): once pixel `x` is done, entry
+/// `x - 1` has been read for this row and received all its error for the
+/// next one, so it is overwritten in place. Error still being collected
+/// for the neighbours is kept in locals.
+#[inline(always)]
+fn diffuse<const N: usize>(
+    img: &RgbImage,
+    rows: Range<usize>,
+    out: &mut Packer,
+    snap: impl Fn(&[u8]) -> Option<u8>,
+    load: impl Fn(&[u8]) -> [i32; N],
+    quantize: impl Fn([i32; N]) -> (u8, [i32; N]),
+) {
+    let width = img.width() as usize;
+    if width == 0 || rows.is_empty() {
+        return;
+    }
+    let mut below: Vec<[i16; N]> = vec![[0; N]; width];
+
+    let first = rows.start.saturating_sub(LEAD_IN_ROWS);
+    let pixels = &img.as_raw()[first * width * 3..rows.end * width * 3];
+    for (y, pixels) in (first..).zip(pixels.chunks_exact(width * 3)) {
+        let lead_in = y < rows.start;
+        // 7/16 of the error of the pixel to the left
+        let mut right = [0i32; N];
+        // Error collected so far for the pixels below-left and below
+        let mut below_left = [0i32; N];
+        let mut below_here = [0i32; N];
+
+        for (x, pixel) in pixels.chunks_exact(3).enumerate() {
+            let (code, err) = match snap(pixel) {
+                Some(code) => (code, [0; N]),
+                None => {
+                    let mut value = load(pixel);
+                    let above = below[x];
+                    for c in 0..N {
+                        value[c] = (value[c] + above[c] as i32 + right[c]).clamp(0, 255);
+                    }
+                    quantize(value)
                 }
+            };
+            if !lead_in {
+                out.push(code);
             }
 
-            // Pack two 4-bit pixels into one byte
-            let byte_idx = (y * width_usize + x) / 2;
-            if x % 2 == 0 {
-                result[byte_idx] = (color_idx as u8) << 4;
-            } else {
-                result[byte_idx] |= color_idx as u8;
+            // 7/16 right, 3/16 below-left, 5/16 below, 1/16 below-right;
+            // the divisions by 16 compile to shifts
+            let mut done = [0i16; N];
+            for c in 0..N {
+                let err = err[c];
+                right[c] = err * 7 / 16;
+                done[c] = (below_left[c] + err * 3 / 16) as i16;
+                below_left[c] = below_here[c] + err * 5 / 16;
+                below_here[c] = err / 16;
+            }
+            if x > 0 {
+                below[x - 1] = done;
             }
         }
-
-        // Swap rows: next becomes current, current is cleared for next iteration
-        std::mem::swap(&mut curr_row, &mut next_row);
-        // Clear the row that will accumulate errors for the row after next
-        next_row.iter_mut().for_each(|p| *p = (0, 0, 0));
+        below[width - 1] = below_left.map(|e| e as i16);
     }
+}
 
-    tracing::debug!("Dithering complete, output size: {} bytes", result.len());
-    result
+/// ITU-R BT.601 luma, `(299 R + 587 G + 114 B) / 1000`
+///
+/// The division is done as a multiplication by 2^32 / 1000 (rounded up),
+/// which gives the same result for every possible sum.
+#[inline(always)]
+fn luma(r: u8, g: u8, b: u8) -> i32 {
+    let sum = r as u64 * 299 + g as u64 * 587 + b as u64 * 114;
+    ((sum * 4_294_968) >> 32) as i32
+}
+
+/// Expand a packed frame back to RGB (for previews and debugging)
+pub fn unpack_to_rgb(buffer: &[u8], width: u32, height: u32, format: PixelFormat) -> RgbImage {
+    let bits = format.bits_per_pixel as usize;
+    let max_code = ((1u16 << bits) - 1) as u8;
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let bit = (y * width + x) as usize * bits;
+        let byte = buffer.get(bit / 8).copied().unwrap_or(0xFF);
+        let code = (byte >> (8 - bits - bit % 8)) & max_code;
+        match format.palette {
+            Palette::Colors(_) => {
+                let (r, g, b) = PALETTE.get(code as usize).copied().unwrap_or(PALETTE[1]);
+                Rgb([r as u8, g as u8, b as u8])
+            }
+            Palette::Grey(_) => Rgb([(code as u16 * 255 / max_code as u16) as u8; 3]),
+        }
+    })
+}
+
+/// RGB value the dithering assumes for a display color
+pub fn color_rgb(color: Color) -> [u8; 3] {
+    let (r, g, b) = PALETTE[color as usize];
+    [r as u8, g as u8, b as u8]
 }
 
 /// Get color name for debugging
-#[allow(dead_code)]
 pub fn color_name(color: Color) -> &'static str {
     match color {
         Color::Black => "Black",
@@ -163,3 +434,109 @@ pub fn color_name(color: Color) -> &'static str {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::PanelModel;
+
+    #[test]
+    fn dithering_in_bands_stays_close_to_one_pass() {
+        let img = image::RgbImage::from_fn(200, 100, |x, y| {
+            image::Rgb([(x + y) as u8, (x * 255 / 200) as u8, (y * 255 / 100) as u8])
+        });
+        let format = PixelFormat::packed4(PanelModel::Epd7in3e.spec().palette);
+        let whole = dither_image(&img, format, 0);
+        assert_eq!(dither_in_bands(&img, format, 0, 1), whole);
+
+        // 4 bands of 32 rows: the first has nothing above it and comes out
+        // the same, the others may differ a little at their top
+        let banded = dither_in_bands(&img, format, 0, 4);
+        assert_eq!(banded.len(), whole.len());
+        let first_band = 32 * 200 / 2;
+        assert_eq!(banded[..first_band], whole[..first_band]);
+
+        let count = |frame: &[u8], color: u8| {
+            frame
+                .iter()
+                .map(|&b| (b >> 4 == color) as usize + (b & 0x0F == color) as usize)
+                .sum::<usize>()
+        };
+        for color in 0..7 {
+            let (a, b) = (count(&whole, color), count(&banded, color));
+            assert!(
+                a.abs_diff(b) <= 200,
+                "color {}: {} vs {} pixels",
+                color,
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn snapping_keeps_text_sharp_next_to_photos() {
+        // Off-white page with dark grey text strokes on the left, a grey
+        // photo on the right
+        let img = image::RgbImage::from_fn(64, 32, |x, y| {
+            image::Rgb(match x {
+                0..32 if y % 8 < 2 => [30, 30, 30],
+                0..32 => [235, 235, 235],
+                _ => [128, 128, 128],
+            })
+        });
+        let format = PixelFormat::packed4(PanelModel::Epd7in3e.spec().palette);
+        let codes = |snap| {
+            let frame = dither_image(&img, format, snap);
+            frame
+                .iter()
+                .flat_map(|&b| [b >> 4, b & 0x0F])
+                .collect::<Vec<u8>>()
+        };
+        // Text rows are black, the rest of the page white
+        let text_matches = |codes: &[u8]| {
+            (0..32).all(|y| {
+                let color = if y % 8 < 2 {
+                    Color::Black
+                } else {
+                    Color::White
+                };
+                codes[y * 64..y * 64 + 32]
+                    .iter()
+                    .all(|&code| code == color as u8)
+            })
+        };
+
+        assert!(!text_matches(&codes(0)));
+        let snapped = codes(48);
+        assert!(text_matches(&snapped));
+        // The photo is still dithered
+        let photo: Vec<u8> = (0..32 * 64)
+            .filter(|i| i % 64 >= 32)
+            .map(|i| snapped[i])
+            .collect();
+        assert!(photo.contains(&(Color::Black as u8)));
+        assert!(photo.contains(&(Color::White as u8)));
+    }
+
+    #[test]
+    fn frame_layout_comes_from_the_pixel_format() {
+        let img = image::RgbImage::from_pixel(8, 2, image::Rgb([255, 0, 0]));
+
+        // Monochrome waveforms only get black and white
+        let mono = crate::display::It8951Config {
+            mode: crate::display::it8951::RefreshMode::Du,
+            ..Default::default()
+        };
+        let format = PixelFormat::packed4(mono.palette());
+        let frame = dither_image(&img, format, 0);
+        assert_eq!(frame.len(), 8);
+        assert!(frame.iter().all(|&b| [0, 15].contains(&(b >> 4))));
+
+        // 1 bit per pixel packs 8 pixels per byte
+        let format = PixelFormat {
+            bits_per_pixel: 1,
+            ..format
+        };
+        assert_eq!(dither_image(&img, format, 0).len(), 2);
+    }
+}
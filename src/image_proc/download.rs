@@ -5,11 +5,43 @@
 //! Uses a shared HTTP client to avoid connection pool leaks and reduce
 //! memory overhead from creating new clients for each download.
 
-use image::DynamicImage;
+use super::photo::PhotoInfo;
+use super::session::SessionConfig;
+use chrono::{DateTime, Local};
+use image::{DynamicImage, ImageDecoder};
 use once_cell::sync::Lazy;
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
 use std::time::Duration;
 use thiserror::Error;
 
+/// User-Agent sent unless `user_agent` is configured
+pub const DEFAULT_USER_AGENT: &str = concat!("rpizerow-epaper-display/", env!("CARGO_PKG_VERSION"));
+
+/// Response header with the time the content expires (RFC 3339 or HTTP
+/// date)
+pub const CONTENT_EXPIRES_HEADER: &str = "x-content-expires";
+
+/// Headers whose values are logged as is; the others are masked, since
+/// sources and servers put credentials in headers of any name
+const LOGGED_HEADERS: [&str; 15] = [
+    "accept",
+    "accept-encoding",
+    "age",
+    "cache-control",
+    "content-encoding",
+    "content-length",
+    "content-type",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "server",
+    "transfer-encoding",
+    "user-agent",
+    CONTENT_EXPIRES_HEADER,
+];
+
 /// Shared HTTP client for all downloads
 ///
 /// This prevents connection pool leaks and reduces memory overhead
@@ -23,6 +55,7 @@ static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
         .timeout(Duration::from_secs(30))
         .pool_max_idle_per_host(1) // Minimize idle connections for Pi Zero W
         .pool_idle_timeout(Duration::from_secs(30))
+        .user_agent(DEFAULT_USER_AGENT)
         .build()
         .expect("Failed to create HTTP client")
 });
@@ -39,11 +72,48 @@ pub enum DownloadError {
     #[error("Image decode failed: {0}")]
     DecodeError(#[from] image::ImageError),
 
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
     #[error("Empty URL")]
     EmptyUrl,
 
     #[error("Download timeout")]
     Timeout,
+
+    #[error("Checksum mismatch: expected SHA-256 {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error(
+        "Image needs about {required_mb} MB to process, more than the {limit_mb} MB allowed by max_image_memory_mb"
+    )]
+    TooLarge { required_mb: u64, limit_mb: u64 },
+
+    #[error(
+        "Source returned an HTML page{} instead of an image - check the URL and authentication",
+        title.as_ref().map(|t| format!(" (\"{}\")", t)).unwrap_or_default()
+    )]
+    HtmlPage { title: Option<String> },
+
+    #[error("Login failed: {0}")]
+    Login(String),
+}
+
+/// Expected SHA-256 of a downloaded image
+#[derive(Debug, Clone, PartialEq)]
+pub enum Checksum {
+    /// Hex digest
+    Sha256(String),
+    /// URL of a sidecar file in `sha256sum` format ("<hex digest>  <name>")
+    Sidecar(String),
+}
+
+/// Body of a successful download
+#[derive(Debug)]
+pub struct Body {
+    pub bytes: bytes::Bytes,
+    /// Time the content expires, from an `X-Content-Expires` header
+    pub expires: Option<DateTime<Local>>,
 }
 
 /// Download configuration
@@ -56,6 +126,16 @@ pub struct DownloadConfig {
     /// Maximum image dimensions
     pub max_width: u32,
     pub max_height: u32,
+    /// Extra request headers (e.g., authorization for API sources)
+    pub headers: HeaderMap,
+    /// Refuse the image unless its SHA-256 matches
+    pub checksum: Option<Checksum>,
+    /// Refuse images whose processing would need more memory (bytes)
+    pub max_memory: Option<u64>,
+    /// Cookie jar and login to use, if enabled
+    pub session: Option<SessionConfig>,
+    /// Read the date taken and description of photos (captions)
+    pub metadata: bool,
 }
 
 impl Default for DownloadConfig {
@@ -65,13 +145,29 @@ impl Default for DownloadConfig {
             retry_delay: Duration::from_secs(2),
             max_width: 4096,
             max_height: 4096,
+            headers: HeaderMap::new(),
+            checksum: None,
+            max_memory: None,
+            session: None,
+            metadata: false,
         }
     }
 }
 
+/// Downloaded image and what the response and the file say about it
+#[derive(Debug)]
+pub struct Downloaded {
+    pub image: DynamicImage,
+    /// Time the content expires, from an `X-Content-Expires` header
+    pub expires: Option<DateTime<Local>>,
+    /// Date taken and description, if asked for with `metadata`
+    pub info: PhotoInfo,
+}
+
 /// Download an image from a URL using the shared HTTP client
+#[allow(dead_code)]
 pub async fn download_image(url: &str) -> Result<DynamicImage, DownloadError> {
-    download_image_with_config(url, &DownloadConfig::default()).await
+    Ok(download_image_with_config(url, &DownloadConfig::default()).await?.image)
 }
 
 /// Download an image from a URL with custom configuration
@@ -80,7 +176,7 @@ pub async fn download_image(url: &str) -> Result<DynamicImage, DownloadError> {
 pub async fn download_image_with_config(
     url: &str,
     config: &DownloadConfig,
-) -> Result<DynamicImage, DownloadError> {
+) -> Result<Downloaded, DownloadError> {
     let url = url.trim();
     if url.is_empty() {
         return Err(DownloadError::EmptyUrl);
@@ -88,16 +184,28 @@ pub async fn download_image_with_config(
 
     tracing::info!("Downloading image from: {}", url);
 
-    let bytes: bytes::Bytes = download_with_retry(&HTTP_CLIENT, url, config).await?;
-
-    tracing::debug!("Downloaded {} bytes, decoding image...", bytes.len());
-
-    // Decode image with size limits
-    let reader = image::ImageReader::new(std::io::Cursor::new(bytes))
-        .with_guessed_format()
-        .map_err(|e| DownloadError::DecodeError(image::ImageError::IoError(e)))?;
-
-    let img = reader.decode()?;
+    // Truncated or damaged image data gets one more download; if that is
+    // no better, the part that decoded is shown with a warning
+    let body = fetch_verified(url, config).await?;
+    let mut expires = body.expires;
+    let (decoded, mut info) = decode(body.bytes, config)?;
+    let img = match decoded {
+        Decoded::Complete(img) => img,
+        Decoded::Partial(_, e) => {
+            tracing::warn!("Image data incomplete ({}), downloading again", e);
+            let body = fetch_verified(url, config).await?;
+            expires = body.expires;
+            let (decoded, retried) = decode(body.bytes, config)?;
+            info = retried;
+            match decoded {
+                Decoded::Complete(img) => img,
+                Decoded::Partial(img, e) => {
+                    tracing::warn!("Image still incomplete ({}), showing what decoded", e);
+                    mark_incomplete(img)
+                }
+            }
+        }
+    };
 
     // Check dimensions
     let (width, height) = (img.width(), img.height());
@@ -113,7 +221,411 @@ pub async fn download_image_with_config(
         );
     }
 
-    Ok(img)
+    Ok(Downloaded {
+        image: img,
+        expires,
+        info,
+    })
+}
+
+/// Download a body that is not decoded as an image (bundles), with the
+/// same retries, login and checksum as images
+pub async fn download_bytes(url: &str, config: &DownloadConfig) -> Result<Body, DownloadError> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(DownloadError::EmptyUrl);
+    }
+    tracing::info!("Downloading {}", url);
+    fetch_verified(url, config).await
+}
+
+/// Decode image data that did not come straight from a download
+///
+/// Damaged data cannot be fetched again here; the part that decoded is
+/// shown with a warning.
+pub fn decode_image(
+    bytes: bytes::Bytes,
+    config: &DownloadConfig,
+) -> Result<DynamicImage, DownloadError> {
+    Ok(match decode(bytes, config)?.0 {
+        Decoded::Complete(img) => img,
+        Decoded::Partial(img, e) => {
+            tracing::warn!("Image incomplete ({}), showing what decoded", e);
+            mark_incomplete(img)
+        }
+    })
+}
+
+/// Download the image body and check its checksum, if configured
+async fn fetch_verified(url: &str, config: &DownloadConfig) -> Result<Body, DownloadError> {
+    let body = fetch_signed_in(url, config).await?;
+
+    tracing::debug!("Downloaded {} bytes, decoding image...", body.bytes.len());
+
+    if let Some(checksum) = &config.checksum {
+        verify_checksum(&body.bytes, checksum, config).await?;
+    }
+    Ok(body)
+}
+
+/// Download the image body, logging in first if the source asks for it
+///
+/// A 401/403 or an HTML page (the login form) means the session cookies
+/// are missing or expired.
+async fn fetch_signed_in(url: &str, config: &DownloadConfig) -> Result<Body, DownloadError> {
+    let result = download_with_retry(&HTTP_CLIENT, url, config).await;
+    let Some(session) = config.session.as_ref().filter(|s| s.has_login()) else {
+        return result;
+    };
+    let signed_out = match &result {
+        Ok(body) => is_html(&body.bytes),
+        Err(DownloadError::HttpError { status }) => matches!(status, 401 | 403),
+        Err(_) => false,
+    };
+    if !signed_out {
+        return result;
+    }
+
+    tracing::info!("Not signed in to {}, logging in", url);
+    session.login(config.headers.get(reqwest::header::USER_AGENT)).await?;
+    download_with_retry(&HTTP_CLIENT, url, config).await
+}
+
+/// Result of decoding an image
+enum Decoded {
+    Complete(DynamicImage),
+    /// The pixel data ended early or is damaged: the pixels decoded before
+    /// the error, the rest white
+    Partial(DynamicImage, image::ImageError),
+}
+
+/// Decode image data with size limits, and read the photo metadata if
+/// asked for
+///
+/// Data that is not an image at all (or too large) is an error; errors in
+/// the pixel data after a valid header give a partial image.
+fn decode(
+    bytes: bytes::Bytes,
+    config: &DownloadConfig,
+) -> Result<(Decoded, PhotoInfo), DownloadError> {
+    if is_html(&bytes) {
+        let title = html_title(&bytes);
+        tracing::error!("Image URL returned an HTML page (title: {:?})", title);
+        return Err(DownloadError::HtmlPage { title });
+    }
+
+    let body_len = bytes.len() as u64;
+    let mut reader = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| DownloadError::DecodeError(image::ImageError::IoError(e)))?;
+
+    // The header gives the decoded size; check it before allocating
+    let mut limits = image::Limits::default();
+    if let Some(max_memory) = config.max_memory {
+        limits.max_alloc = Some(max_memory);
+    }
+    let max_alloc = limits.max_alloc;
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder()?;
+    let info = if config.metadata {
+        PhotoInfo::read(&mut decoder)
+    } else {
+        PhotoInfo::default()
+    };
+    let total_bytes = decoder.total_bytes();
+    if let Some(max_memory) = config.max_memory {
+        let required = estimate_memory(body_len, total_bytes);
+        if required > max_memory {
+            return Err(too_large(required, max_memory));
+        }
+    }
+    if max_alloc.is_some_and(|max| total_bytes > max) {
+        return Err(DownloadError::DecodeError(image::ImageError::Limits(
+            image::error::LimitError::from_kind(image::error::LimitErrorKind::InsufficientMemory),
+        )));
+    }
+
+    let (width, height) = decoder.dimensions();
+    let color = decoder.color_type();
+    // Pixels the decoder does not get to stay white
+    let mut buffer = vec![0xFF; total_bytes as usize];
+    let result = decoder.read_image(&mut buffer);
+    let img = from_pixels(width, height, color, buffer).ok_or_else(|| {
+        DownloadError::InvalidResponse(format!("Unsupported pixel format {:?}", color))
+    });
+    match result {
+        Ok(()) => Ok((Decoded::Complete(img?), info)),
+        // Without a way to keep the pixels, a damaged image is an error
+        Err(e) => match img {
+            Ok(img) => Ok((Decoded::Partial(img, e), info)),
+            Err(_) => Err(e.into()),
+        },
+    }
+}
+
+/// Whether a body looks like an HTML page (login form, error page)
+/// rather than image data
+fn is_html(body: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&body[..body.len().min(1024)]).to_ascii_lowercase();
+    let head = head.trim_start_matches(['\u{feff}', ' ', '\t', '\r', '\n']);
+    head.starts_with('<')
+        && ["<!doctype html", "<html", "<head", "<body"]
+            .iter()
+            .any(|tag| head.contains(tag))
+}
+
+/// Text of the `<title>` of an HTML page, cut to 80 characters
+fn html_title(body: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(&body[..body.len().min(4096)]);
+    let lower = head.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = head[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then(|| title.chars().take(80).collect())
+}
+
+/// Image from the raw output of a decoder (native-endian samples)
+fn from_pixels(
+    width: u32,
+    height: u32,
+    color: image::ColorType,
+    buffer: Vec<u8>,
+) -> Option<DynamicImage> {
+    use image::{ColorType, ImageBuffer};
+
+    let wide = |buffer: Vec<u8>| -> Vec<u16> {
+        buffer
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect()
+    };
+    Some(match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, buffer)?),
+        ColorType::La8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, buffer)?),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, buffer)?),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, buffer)?),
+        ColorType::L16 => {
+            DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, wide(buffer))?)
+        }
+        ColorType::La16 => {
+            DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, wide(buffer))?)
+        }
+        ColorType::Rgb16 => {
+            DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, wide(buffer))?)
+        }
+        ColorType::Rgba16 => {
+            DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, wide(buffer))?)
+        }
+        _ => return None,
+    })
+}
+
+/// Draw a black "Image incomplete" bar across the bottom of `img`, where
+/// the missing part of a truncated image is
+fn mark_incomplete(img: DynamicImage) -> DynamicImage {
+    let mut img = img.into_rgb8();
+    let (width, height) = img.dimensions();
+    let bar = (height / 12).max(12).min(height);
+    let top = height.saturating_sub(bar);
+    for y in top..height {
+        for x in 0..width {
+            img.put_pixel(x, y, image::Rgb([0, 0, 0]));
+        }
+    }
+    let size = bar * 2 / 3;
+    crate::render::text::draw_text(
+        &mut img,
+        (bar / 2) as i32,
+        (top + (bar - size) / 2) as i32,
+        "Image incomplete",
+        size,
+        image::Rgb([255, 255, 255]),
+    );
+    DynamicImage::ImageRgb8(img)
+}
+
+/// Peak memory for processing a downloaded image, in bytes
+///
+/// The encoded body and the decoded image stay alive while the
+/// transform makes a full-size working copy (rotation, mirroring); the
+/// scaled and dithered buffers are small next to these.
+pub fn estimate_memory(body_len: u64, decoded_len: u64) -> u64 {
+    body_len + decoded_len * 2
+}
+
+fn too_large(required: u64, max_memory: u64) -> DownloadError {
+    const MB: u64 = 1024 * 1024;
+    DownloadError::TooLarge {
+        required_mb: required.div_ceil(MB),
+        limit_mb: max_memory / MB,
+    }
+}
+
+/// Check the SHA-256 of downloaded bytes, fetching a sidecar if needed
+async fn verify_checksum(
+    bytes: &[u8],
+    checksum: &Checksum,
+    config: &DownloadConfig,
+) -> Result<(), DownloadError> {
+    let expected = match checksum {
+        Checksum::Sha256(hex) => hex.trim().to_lowercase(),
+        Checksum::Sidecar(url) => {
+            let sidecar = DownloadConfig {
+                checksum: None,
+                ..config.clone()
+            };
+            let body = download_with_retry(&HTTP_CLIENT, url.trim(), &sidecar).await?;
+            String::from_utf8_lossy(&body.bytes)
+                .split_whitespace()
+                .next()
+                .filter(|hex| is_sha256_hex(hex))
+                .ok_or_else(|| {
+                    DownloadError::InvalidResponse(format!("No SHA-256 digest in {}", url))
+                })?
+                .to_lowercase()
+        }
+    };
+
+    let actual = sha256_hex(bytes);
+    if actual != expected {
+        tracing::error!("Refusing image: SHA-256 {} does not match {}", actual, expected);
+        return Err(DownloadError::ChecksumMismatch { expected, actual });
+    }
+    tracing::debug!("SHA-256 verified");
+    Ok(())
+}
+
+/// SHA-256 of `bytes` as lowercase hex
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Whether `value` is a SHA-256 digest in hex
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Fetch and parse a JSON document (for API-based sources)
+///
+/// Uses the same retry logic and shared client as image downloads, with
+/// the User-Agent and memory limit of `config` and the source's `headers`
+/// on top; no cookies.
+#[cfg(feature = "renderers")]
+pub async fn fetch_json<T: serde::de::DeserializeOwned>(
+    url: &str,
+    headers: &HeaderMap,
+    config: &DownloadConfig,
+) -> Result<T, DownloadError> {
+    let mut all_headers = config.headers.clone();
+    all_headers.extend(headers.clone());
+    let config = DownloadConfig {
+        headers: all_headers,
+        max_memory: config.max_memory,
+        ..DownloadConfig::default()
+    };
+
+    tracing::debug!("Fetching JSON from: {}", url);
+    let body = download_with_retry(&HTTP_CLIENT, url.trim(), &config).await?;
+
+    serde_json::from_slice(&body.bytes).map_err(|e| DownloadError::InvalidResponse(e.to_string()))
+}
+
+/// Send a request with an arbitrary method and body, returning the text body
+///
+/// For protocols such as CalDAV that use extension methods (`REPORT`).
+/// Single attempt, no retries; the headers of `config` go first.
+#[cfg(feature = "renderers")]
+pub async fn fetch_text(
+    method: reqwest::Method,
+    url: &str,
+    headers: &HeaderMap,
+    body: String,
+    config: &DownloadConfig,
+) -> Result<String, DownloadError> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(DownloadError::EmptyUrl);
+    }
+
+    tracing::debug!("{} {}", method, url);
+    let response = HTTP_CLIENT
+        .request(method, url)
+        .headers(config.headers.clone())
+        .headers(headers.clone())
+        .body(body)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        tracing::warn!("HTTP error: {} for {}", status, url);
+        return Err(DownloadError::HttpError {
+            status: status.as_u16(),
+        });
+    }
+
+    Ok(response.text().await?)
+}
+
+/// Check that a URL is reachable without downloading the image body
+///
+/// Sends a HEAD request, falling back to GET for servers that do not
+/// implement HEAD. Only the status line is inspected, no retries.
+/// Returns the HTTP status code on success.
+pub async fn check_source(
+    url: &str,
+    headers: &HeaderMap,
+    session: Option<&SessionConfig>,
+) -> Result<u16, DownloadError> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(DownloadError::EmptyUrl);
+    }
+
+    let send = |method: reqwest::Method| async move {
+        match session {
+            Some(session) => session.send(method, url, headers).await,
+            None => {
+                log_request(&method, url, headers);
+                let response = HTTP_CLIENT
+                    .request(method, url)
+                    .headers(headers.clone())
+                    .send()
+                    .await?;
+                log_response(&response);
+                Ok(response)
+            }
+        }
+    };
+    let mut response = send(reqwest::Method::HEAD).await?;
+
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+    ) {
+        tracing::debug!("HEAD not supported by {}, falling back to GET", url);
+        response = send(reqwest::Method::GET).await?;
+    }
+
+    let status = response.status();
+    let html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim_start().to_ascii_lowercase().starts_with("text/html"));
+    if status.is_success() && html {
+        Err(DownloadError::HtmlPage { title: None })
+    } else if status.is_success() {
+        Ok(status.as_u16())
+    } else {
+        Err(DownloadError::HttpError {
+            status: status.as_u16(),
+        })
+    }
 }
 
 /// Download with retry logic
@@ -121,7 +633,7 @@ async fn download_with_retry(
     client: &reqwest::Client,
     url: &str,
     config: &DownloadConfig,
-) -> Result<bytes::Bytes, DownloadError> {
+) -> Result<Body, DownloadError> {
     let mut last_error = None;
 
     for attempt in 0..config.max_retries {
@@ -131,16 +643,38 @@ async fn download_with_retry(
             tokio::time::sleep(delay).await;
         }
 
-        match client.get(url).send().await {
+        let response = match &config.session {
+            Some(session) => session.send(reqwest::Method::GET, url, &config.headers).await,
+            None => {
+                log_request(&reqwest::Method::GET, url, &config.headers);
+                client
+                    .get(url)
+                    .headers(config.headers.clone())
+                    .send()
+                    .await
+                    .inspect(log_response)
+                    .map_err(DownloadError::from)
+            }
+        };
+        match response {
             Ok(response) => {
                 let status = response.status();
 
                 if status.is_success() {
-                    match response.bytes().await {
-                        Ok(bytes) => return Ok(bytes),
+                    // Refuse oversized bodies before reading them
+                    if let (Some(max_memory), Some(len)) =
+                        (config.max_memory, response.content_length())
+                        && len > max_memory
+                    {
+                        return Err(too_large(len, max_memory));
+                    }
+                    let expires = content_expires(response.headers());
+                    match read_body(response, config.max_memory).await {
+                        Ok(bytes) => return Ok(Body { bytes, expires }),
+                        Err(e @ DownloadError::TooLarge { .. }) => return Err(e),
                         Err(e) => {
                             tracing::warn!("Failed to read response body: {}", e);
-                            last_error = Some(DownloadError::RequestError(e));
+                            last_error = Some(e);
                         }
                     }
                 } else {
@@ -152,7 +686,7 @@ async fn download_with_retry(
             }
             Err(e) => {
                 tracing::warn!("Request failed: {} for {}", e, url);
-                last_error = Some(DownloadError::RequestError(e));
+                last_error = Some(e);
             }
         }
     }
@@ -160,3 +694,89 @@ async fn download_with_retry(
     Err(last_error.unwrap_or(DownloadError::Timeout))
 }
 
+/// Read a response body, giving up as soon as it outgrows `max_memory`
+///
+/// Chunked responses have no Content-Length to check up front.
+async fn read_body(
+    mut response: reqwest::Response,
+    max_memory: Option<u64>,
+) -> Result<bytes::Bytes, DownloadError> {
+    let Some(max_memory) = max_memory else {
+        return Ok(response.bytes().await?);
+    };
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let len = (body.len() + chunk.len()) as u64;
+        if len > max_memory {
+            return Err(too_large(len, max_memory));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.into())
+}
+
+/// Time in an `X-Content-Expires` header, if there is a valid one
+fn content_expires(headers: &HeaderMap) -> Option<DateTime<Local>> {
+    let value = headers.get(CONTENT_EXPIRES_HEADER)?.to_str().ok()?.trim();
+    let time = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .inspect_err(|e| {
+            tracing::warn!("Ignoring {} '{}': {}", CONTENT_EXPIRES_HEADER, value, e);
+        })
+        .ok()?;
+    Some(time.with_timezone(&Local))
+}
+
+/// Log the method, URL and headers of a request (debug level)
+pub(super) fn log_request(method: &reqwest::Method, url: &str, headers: &HeaderMap) {
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        tracing::debug!("> {} {} [{}]", method, url, describe_headers(headers));
+    }
+}
+
+/// Log the status line and headers of a response (debug level)
+pub(super) fn log_response(response: &reqwest::Response) {
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        tracing::debug!(
+            "< {:?} {} from {} [{}]",
+            response.version(),
+            response.status(),
+            response.url(),
+            describe_headers(response.headers())
+        );
+    }
+}
+
+/// Headers as "name: value" pairs, credentials masked
+fn describe_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if LOGGED_HEADERS.contains(&name.as_str()) {
+                value.to_str().unwrap_or("<binary>")
+            } else {
+                crate::secrets::REDACTED
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn only_common_header_values_are_logged() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("image/png"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer abc"));
+        headers.insert("x-api-key", HeaderValue::from_static("k3y"));
+        assert_eq!(
+            describe_headers(&headers),
+            "content-type: image/png, authorization: <redacted>, x-api-key: <redacted>"
+        );
+    }
+}
@@ -2,16 +2,33 @@
 //!
 //! Provides image download, transformation, and dithering for the e-paper display.
 
+pub mod color;
 pub mod dither;
 pub mod download;
+pub mod packed;
+pub mod phash;
+pub mod photo;
+pub mod session;
 pub mod transform;
 
 pub use dither::dither_image;
-pub use download::{download_image, DownloadError};
-pub use transform::{transform_image, Rotation, TransformOptions};
+pub use download::{check_source, DownloadError};
+pub use packed::{changed_region, transform_packed};
+pub use transform::{transform_image, transform_region, Rotation, TransformOptions};
 
 use crate::config::Config;
-use crate::display::DisplayController;
+use crate::display::gpio::GpioError;
+use crate::display::{DisplayController, DisplayError, PixelFormat, Region};
+use crate::history::FrameHistory;
+use crate::memory;
+use crate::mirror::{self, MirrorRole};
+use crate::render::Theme;
+use phash::Fingerprint;
+use crate::sources::{self, Presentation, SourceConfig, SourceError};
+use crate::sources::text::{TextAlign, TextSource};
+use crate::status::{ErrorCategory, RefreshStage, StatusTracker};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
 /// Image processing errors
@@ -25,83 +42,822 @@ pub enum ProcessingError {
 
     #[error("No image URL configured")]
     NoImageUrl,
+
+    #[error("Source error: {0}")]
+    Source(SourceError),
+}
+
+impl ProcessingError {
+    /// Step of the update the error belongs to
+    pub fn stage(&self) -> RefreshStage {
+        match self {
+            ProcessingError::Display(_) => RefreshStage::Displaying,
+            ProcessingError::Download(_)
+            | ProcessingError::NoImageUrl
+            | ProcessingError::Source(_) => RefreshStage::Fetching,
+        }
+    }
+
+    /// Cause of the error, for the error counters
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ProcessingError::Download(e) => download_category(e),
+            ProcessingError::NoImageUrl | ProcessingError::Source(_) => ErrorCategory::Source,
+            ProcessingError::Display(e) => match e {
+                DisplayError::Spi(_) => ErrorCategory::Spi,
+                DisplayError::Gpio(GpioError::BusyTimeout(_)) | DisplayError::Timeout(_) => {
+                    ErrorCategory::BusyTimeout
+                }
+                DisplayError::Gpio(_) => ErrorCategory::Gpio,
+                _ => ErrorCategory::Display,
+            },
+        }
+    }
+}
+
+/// Cause of a failed download
+fn download_category(error: &DownloadError) -> ErrorCategory {
+    match error {
+        DownloadError::HttpError { status } => ErrorCategory::of_http_status(*status),
+        DownloadError::RequestError(e) => match e.status() {
+            Some(status) => ErrorCategory::of_http_status(status.as_u16()),
+            None => request_category(e),
+        },
+        DownloadError::DecodeError(_) => ErrorCategory::Decode,
+        DownloadError::Timeout => ErrorCategory::Connection,
+        _ => ErrorCategory::Source,
+    }
+}
+
+/// Cause of a request that got no response
+///
+/// reqwest only tells connection errors apart from timeouts; the DNS and
+/// TLS errors underneath are recognized by their messages.
+fn request_category(error: &reqwest::Error) -> ErrorCategory {
+    let mut causes = String::new();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        causes.push_str(&cause.to_string().to_lowercase());
+        causes.push('\n');
+        source = cause.source();
+    }
+    if causes.contains("dns error") || causes.contains("failed to lookup address") {
+        ErrorCategory::Dns
+    } else if ["tls", "certificate", "handshake"]
+        .iter()
+        .any(|word| causes.contains(word))
+    {
+        ErrorCategory::Tls
+    } else {
+        ErrorCategory::Connection
+    }
+}
+
+impl From<SourceError> for ProcessingError {
+    fn from(e: SourceError) -> Self {
+        match e {
+            SourceError::Download(e) => ProcessingError::Download(e),
+            other => ProcessingError::Source(other),
+        }
+    }
+}
+
+/// Whether the pipeline may use all cores (`--parallel`)
+#[cfg(feature = "parallel")]
+static PARALLEL: AtomicBool = AtomicBool::new(false);
+
+/// Let dithering use all cores; for multi-core Pis (`--parallel`)
+#[cfg(feature = "parallel")]
+pub fn set_parallel(enabled: bool) {
+    PARALLEL.store(enabled, Ordering::Relaxed);
+}
+
+/// Number of parts to split CPU-bound pipeline work into
+fn parallelism() -> usize {
+    #[cfg(feature = "parallel")]
+    if PARALLEL.load(Ordering::Relaxed) {
+        return rayon::current_num_threads();
+    }
+    1
+}
+
+/// Transform options from the display settings
+fn transform_options(config: &Config) -> TransformOptions {
+    TransformOptions {
+        rotation: Rotation::from(config.rotation),
+        mirror_h: config.mirror_h,
+        mirror_v: config.mirror_v,
+        scale_to_fit: config.scale_to_fit,
+        smart_crop: config.smart_crop,
+        rotate_first: config.rotate_first,
+        target_width: config.display_width,
+        target_height: config.display_height,
+    }
+}
+
+/// Transform options for the image itself
+///
+/// With `rotate_packed` the image is only scaled, to the content size;
+/// rotation and mirroring are applied to the dithered frame instead.
+fn image_options(config: &Config) -> TransformOptions {
+    let options = transform_options(config);
+    if !config.rotate_packed {
+        return options;
+    }
+
+    let (target_width, target_height) = config.content_size();
+    TransformOptions {
+        rotation: Rotation::None,
+        mirror_h: false,
+        mirror_v: false,
+        target_width,
+        target_height,
+        ..options
+    }
+}
+
+/// Dither a transformed image to a frame in `format`, with the color
+/// profile applied first
+fn dither_frame(config: &Config, mut rgb_image: image::RgbImage, format: PixelFormat) -> Frame {
+    let dither_format = color::apply_profile(&mut rgb_image, config.color_profile, format);
+    let buffer = dither_image(&rgb_image, dither_format, config.snap_threshold);
+    let (width, height) = rgb_image.dimensions();
+    Frame {
+        width,
+        height,
+        format,
+        buffer,
+    }
+}
+
+/// `img` as a refresh would show it on a panel taking `format`, for
+/// checking content offline (`render` subcommand)
+///
+/// Runs the same transformations, color profile and dithering as a
+/// refresh, and turns the frame like the one sent to the panel, so the
+/// result matches `/preview.png`.
+pub fn render_preview(
+    config: &Config,
+    img: image::DynamicImage,
+    format: PixelFormat,
+) -> image::RgbImage {
+    let content = dither_frame(config, transform_image(img, &image_options(config)), format);
+    let (buffer, width, height) = if config.rotate_packed {
+        transform_packed(
+            &content.buffer,
+            content.width,
+            content.height,
+            format,
+            &transform_options(config),
+        )
+    } else {
+        (content.buffer, content.width, content.height)
+    };
+    dither::unpack_to_rgb(&buffer, width, height, format)
+}
+
+/// Notice shown instead of an HTML page (`show_html_errors`)
+fn html_notice(config: &Config, error: &DownloadError) -> image::DynamicImage {
+    let title = match error {
+        DownloadError::HtmlPage { title: Some(title) } => format!("\n\"{}\"", title),
+        _ => String::new(),
+    };
+    let notice = TextSource {
+        text: format!(
+            "The image URL returned a web page{}\n\nCheck the URL and authentication",
+            title
+        ),
+        size: 0,
+        color: None,
+        background: None,
+        align: TextAlign::Center,
+    };
+    let (width, height) = config.content_size();
+    notice.render(width, height, &Theme::from_config(&config.theme))
+}
+
+/// Image shown instead of expired content: `fallback_image`, or a notice
+/// if there is none or it cannot be read
+fn fallback_image(config: &Config) -> image::DynamicImage {
+    let path = config.fallback_image.trim();
+    if !path.is_empty() {
+        match image::open(path) {
+            Ok(img) => return img,
+            Err(e) => tracing::warn!("Cannot read fallback image {}: {}", path, e),
+        }
+    }
+    let notice = TextSource {
+        text: "No current content".to_string(),
+        size: 0,
+        color: None,
+        background: None,
+        align: TextAlign::Center,
+    };
+    let (width, height) = config.content_size();
+    notice.render(width, height, &Theme::from_config(&config.theme))
+}
+
+/// A dithered frame as sent to the panel
+struct Frame {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    buffer: Vec<u8>,
 }
 
 /// Image processor that handles the full pipeline
 pub struct ImageProcessor {
     display: DisplayController,
+    /// Last frame shown, kept for debugging (~192KB for 800x480)
+    last_frame: Mutex<Option<Frame>>,
+    /// Whether the panel still shows `last_frame` (not cleared since)
+    frame_on_panel: AtomicBool,
+    /// Full refreshes since the panel was last initialized again
+    /// (`reinit_every`)
+    full_refreshes: AtomicU32,
+    /// Last frame before rotation/mirroring, kept with `rotate_packed`
+    /// so the orientation can be changed without dithering again
+    last_content: Mutex<Option<Frame>>,
+    /// Perceptual fingerprint of the image behind `last_frame`
+    last_fingerprint: Mutex<Option<Fingerprint>>,
+    /// How the last fetched content asked to be presented
+    presentation: Mutex<Presentation>,
+    /// Where displayed frames are kept with `history_frames`
+    history: Option<FrameHistory>,
+    /// Receives the stage of running updates
+    status: Option<Arc<StatusTracker>>,
+}
+
+/// Clears the reported refresh stage when an update ends, however it ends
+struct StageGuard<'a>(&'a ImageProcessor);
+
+impl Drop for StageGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set_stage(None);
+    }
 }
 
 impl ImageProcessor {
     /// Create a new image processor
     pub fn new(display: DisplayController) -> Self {
-        Self { display }
+        Self {
+            display,
+            last_frame: Mutex::new(None),
+            frame_on_panel: AtomicBool::new(false),
+            full_refreshes: AtomicU32::new(0),
+            last_content: Mutex::new(None),
+            last_fingerprint: Mutex::new(None),
+            presentation: Mutex::new(Presentation::default()),
+            history: None,
+            status: None,
+        }
+    }
+
+    /// Report the stage of running updates to `status`
+    pub fn with_status(mut self, status: Arc<StatusTracker>) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    fn set_stage(&self, stage: Option<RefreshStage>) {
+        if let Some(status) = &self.status {
+            status.set_refresh_stage(stage);
+        }
+    }
+
+    /// Report an update starting at `stage`
+    fn begin(&self, stage: RefreshStage) -> StageGuard<'_> {
+        self.set_stage(Some(stage));
+        StageGuard(self)
+    }
+
+    /// Keep displayed frames in `history` (see `history_frames`)
+    pub fn with_history(mut self, history: FrameHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Frame history, if configured
+    pub fn history(&self) -> Option<&FrameHistory> {
+        self.history.as_ref()
+    }
+
+    /// How the last fetched content asked to be presented
+    pub fn presentation(&self) -> Presentation {
+        self.presentation
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Whether the content on the panel is past its `valid_until`
+    fn content_expired(&self) -> bool {
+        self.presentation()
+            .valid_until
+            .is_some_and(|until| until <= chrono::Local::now())
+    }
+
+    /// Record how the fetched content asks to be presented
+    ///
+    /// The same slide shown again keeps the dwell timer it started with.
+    fn set_presentation(&self, mut presentation: Presentation) {
+        let mut current = self.presentation.lock().unwrap_or_else(|e| e.into_inner());
+        if presentation.slide.is_some() && presentation.slide == current.slide {
+            presentation.next_slide = current.next_slide;
+        }
+        *current = presentation;
+    }
+
+    /// Display controller driven by this pipeline
+    pub fn display(&self) -> &DisplayController {
+        &self.display
+    }
+
+    /// Last frame sent to the panel, encoded as PNG
+    pub fn last_frame_png(&self) -> Option<Vec<u8>> {
+        self.encode_last_frame(None)
+    }
+
+    /// Last frame sent to the panel, scaled down to at most `max_width`
+    /// pixels wide and encoded as PNG
+    pub fn last_frame_thumbnail(&self, max_width: u32) -> Option<Vec<u8>> {
+        self.encode_last_frame(Some(max_width))
+    }
+
+    fn encode_last_frame(&self, max_width: Option<u32>) -> Option<Vec<u8>> {
+        let img = {
+            let guard = self.last_frame.lock().unwrap_or_else(|e| e.into_inner());
+            let frame = guard.as_ref()?;
+            dither::unpack_to_rgb(&frame.buffer, frame.width, frame.height, frame.format)
+        };
+        let mut img = image::DynamicImage::ImageRgb8(img);
+        if let Some(max_width) = max_width
+            && img.width() > max_width
+        {
+            let height = (img.height() * max_width / img.width()).max(1);
+            img = img.resize_exact(max_width, height, image::imageops::FilterType::Triangle);
+        }
+
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| tracing::warn!("Failed to encode frame: {}", e))
+            .ok()?;
+        Some(png)
     }
 
-    /// Process and display an image from URL
+    /// Remember the frame just sent to the panel
+    fn store_frame(&self, frame: Frame) {
+        *self.last_frame.lock().unwrap_or_else(|e| e.into_inner()) = Some(frame);
+        self.frame_on_panel.store(true, Ordering::Relaxed);
+    }
+
+    /// Add the last frame to the history
+    fn record_history(&self, config: &Config) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        if config.history_frames == 0 {
+            return;
+        }
+        let Some(png) = self.last_frame_png() else {
+            return;
+        };
+        if let Err(e) = history.record(&png, config.history_frames as usize) {
+            tracing::warn!("Failed to store frame in history: {}", e);
+        }
+    }
+
+    /// Region to refresh for `frame` if a partial refresh is enough
+    ///
+    /// Compares the frame with the one on the panel; the changed area,
+    /// widened to whole bytes, must be within `auto_partial_max_percent`
+    /// of the panel.
+    async fn auto_partial_region(&self, config: &Config, frame: &Frame) -> Option<Region> {
+        if config.auto_partial_max_percent == 0
+            || !self.frame_on_panel.load(Ordering::Relaxed)
+            || !self.display.supports_partial().await
+        {
+            return None;
+        }
+
+        let region = {
+            let guard = self.last_frame.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = guard.as_ref().filter(|previous| {
+                (previous.width, previous.height, previous.format)
+                    == (frame.width, frame.height, frame.format)
+            })?;
+            changed_region(
+                &previous.buffer,
+                &frame.buffer,
+                frame.width,
+                frame.height,
+                frame.format,
+            )?
+            .align_to_bytes()
+        };
+
+        let changed = region.width as u64 * region.height as u64 * 100;
+        let limit =
+            frame.width as u64 * frame.height as u64 * config.auto_partial_max_percent as u64;
+        (changed <= limit).then_some(region)
+    }
+
+    /// Whether `frame` differs from the one on the panel only inside `region`
+    fn changed_only_within(&self, frame: &Frame, region: Region) -> bool {
+        let guard = self.last_frame.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(previous) = guard.as_ref().filter(|previous| {
+            (previous.width, previous.height, previous.format)
+                == (frame.width, frame.height, frame.format)
+        }) else {
+            return false;
+        };
+        changed_region(
+            &previous.buffer,
+            &frame.buffer,
+            frame.width,
+            frame.height,
+            frame.format,
+        )
+        .is_none_or(|changed| region.contains(changed))
+    }
+
+    /// Rotate and mirror a frame dithered in content orientation
+    ///
+    /// Keeps the unrotated frame for [`reorient`](Self::reorient) and
+    /// returns the frame in panel orientation. Without `rotate_packed` the
+    /// frame is already in panel orientation and returned as is.
+    fn orient_frame(&self, config: &Config, content: Frame) -> Frame {
+        if !config.rotate_packed {
+            *self.last_content.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            return content;
+        }
+
+        let format = content.format;
+        let (buffer, width, height) = transform_packed(
+            &content.buffer,
+            content.width,
+            content.height,
+            format,
+            &transform_options(config),
+        );
+        *self.last_content.lock().unwrap_or_else(|e| e.into_inner()) = Some(content);
+        Frame {
+            width,
+            height,
+            format,
+            buffer,
+        }
+    }
+
+    /// Process and display an image from the configured source
     ///
     /// Full pipeline:
-    /// 1. Download image from URL
+    /// 1. Fetch image from the source (download from URL by default)
     /// 2. Apply transformations (rotate, mirror, scale)
-    /// 3. Dither to 7-color palette
-    /// 4. Send to display
+    /// 3. Dither to the panel's palette
+    /// 4. Send to display (partial refresh if only a small area changed)
     ///
     /// Memory optimization: Explicitly drops intermediate buffers to free
     /// memory before the next allocation. This reduces peak memory usage
     /// on the Pi Zero W's constrained RAM.
     pub async fn process_and_display(&self, config: &Config) -> Result<(), ProcessingError> {
-        if !config.has_image_url() {
+        self.process(config, false).await.map(|_| ())
+    }
+
+    /// Like [`process_and_display`](Self::process_and_display), but leave
+    /// the panel alone if the image looks like the one it shows
+    ///
+    /// With `skip_similar_distance` set, the perceptual fingerprint of the
+    /// transformed image is compared with that of the frame on the panel.
+    /// Returns false if the refresh was skipped.
+    pub async fn display_if_changed(&self, config: &Config) -> Result<bool, ProcessingError> {
+        self.process(config, true).await
+    }
+
+    async fn process(&self, config: &Config, skip_similar: bool) -> Result<bool, ProcessingError> {
+        let result = self.run_pipeline(config, skip_similar).await;
+        // All buffers of the refresh are freed by now
+        memory::release();
+        if let Err(e) = &result
+            && let Some(status) = &self.status
+        {
+            status.record_error(e.stage(), e.category(), &e.to_string());
+        }
+        result
+    }
+
+    async fn run_pipeline(&self, config: &Config, skip_similar: bool) -> Result<bool, ProcessingError> {
+        if config.source == SourceConfig::Url && !config.has_image_url() {
             return Err(ProcessingError::NoImageUrl);
         }
 
-        tracing::info!("Starting image processing pipeline");
+        tracing::info!(
+            "Starting image processing pipeline ({})",
+            config.source.kind_name()
+        );
+        let _stage = self.begin(RefreshStage::Fetching);
 
-        // Download image (~1.5MB for 800x480 RGBA)
-        let img = download_image(&config.image_url).await?;
+        // Fetch image (~1.5MB for 800x480 RGBA)
+        // A login or error page instead of the image can be shown as a
+        // notice, and expired content that cannot be replaced gives way to
+        // the fallback image; the refresh still fails so the error reaches
+        // the status
+        let (img, presentation, failed) = match sources::fetch_image(config).await {
+            Err(SourceError::Download(e @ DownloadError::HtmlPage { .. }))
+                if config.show_html_errors =>
+            {
+                (html_notice(config, &e), Presentation::default(), Some(e.into()))
+            }
+            Err(e) if self.content_expired() => {
+                tracing::warn!(
+                    "Content on the panel expired and cannot be replaced ({}), showing the fallback image",
+                    e
+                );
+                (fallback_image(config), Presentation::default(), Some(e.into()))
+            }
+            result => {
+                let (img, presentation) = result?;
+                (img, presentation, None)
+            }
+        };
+        self.set_presentation(presentation);
+        self.set_stage(Some(RefreshStage::Processing));
 
         // Apply transformations with configurable dimensions and transform order
         // `img` is consumed here, freeing the original ~1.5MB DynamicImage
-        let options = TransformOptions {
-            rotation: Rotation::from(config.rotation),
-            mirror_h: config.mirror_h,
-            mirror_v: config.mirror_v,
-            scale_to_fit: config.scale_to_fit,
-            rotate_first: config.rotate_first,
-            target_width: config.display_width,
-            target_height: config.display_height,
-        };
+        let options = image_options(config);
         let rgb_image = transform_image(img, &options);
         // Note: `img` is now moved into transform_image and freed
 
-        // Dither to 7-color palette (~192KB output for 800x480)
-        // The dither function uses row-by-row processing (~19KB working memory)
-        let buffer = dither_image(&rgb_image);
+        let fingerprint = Fingerprint::of(&rgb_image);
+        if skip_similar
+            && let Some(max_distance) = config.skip_similar_distance
+            && let Some(distance) = self.distance_to_panel(&fingerprint)
+            && distance <= max_distance as u32
+        {
+            tracing::info!(
+                "Image looks unchanged (hash distance {}), skipping refresh",
+                distance
+            );
+            if let Some(e) = failed {
+                return Err(e);
+            }
+            return Ok(false);
+        }
 
-        // Explicitly drop rgb_image (~1.15MB) before display operation
-        // This ensures we have freed as much memory as possible before
+        // Dither to the format the driver expects (~192KB output for 800x480)
+        // The dither function uses row-by-row processing (~5KB working memory)
+        // Asking for the format initializes the display if needed
+        let format = self.display.pixel_format().await?;
+        // rgb_image (~1.15MB) is freed as soon as it is dithered, before
         // the display operation which may also need buffers
-        drop(rgb_image);
+        let frame = dither_frame(config, rgb_image, format);
 
-        // Ensure display is initialized
-        self.display.init().await?;
+        // Turn the dithered frame to the panel orientation (`rotate_packed`)
+        let frame = self.orient_frame(config, frame);
 
-        // Send to display - only `buffer` (~192KB) is in memory now
-        self.display.display(&buffer).await?;
+        // Send to display - only the frame (~192KB) is in memory now
+        self.set_stage(Some(RefreshStage::Displaying));
+        // Followers of a mirror group get the frame first, then all panels
+        // start refreshing together
+        if config.mirror.role == MirrorRole::Leader {
+            let show_at = mirror::push(
+                &config.mirror,
+                &config.user_agent(),
+                self.display.spec().model,
+                frame.width,
+                frame.height,
+                &frame.buffer,
+            )
+            .await;
+            tokio::time::sleep(mirror::wait_until(show_at.timestamp_millis())).await;
+        }
+        self.send_frame(config, frame).await?;
+        *self.last_fingerprint.lock().unwrap_or_else(|e| e.into_inner()) = Some(fingerprint);
+        self.record_history(config);
 
+        if let Some(e) = failed {
+            return Err(e);
+        }
         tracing::info!("Image processing complete");
+        Ok(true)
+    }
+
+    /// Show a frame pushed by the leader of the mirror group
+    ///
+    /// The frame is already in panel orientation and format; small
+    /// changes get a partial refresh as with frames rendered here.
+    pub async fn show_mirrored_frame(
+        &self,
+        config: &Config,
+        width: u32,
+        height: u32,
+        buffer: Vec<u8>,
+    ) -> Result<(), ProcessingError> {
+        let result = self.display_mirrored(config, width, height, buffer).await;
+        if let Err(e) = &result
+            && let Some(status) = &self.status
+        {
+            status.record_error(e.stage(), e.category(), &e.to_string());
+        }
+        result
+    }
+
+    async fn display_mirrored(
+        &self,
+        config: &Config,
+        width: u32,
+        height: u32,
+        buffer: Vec<u8>,
+    ) -> Result<(), ProcessingError> {
+        let _stage = self.begin(RefreshStage::Displaying);
+        let format = self.display.pixel_format().await?;
+        *self.last_content.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.send_frame(
+            config,
+            Frame {
+                width,
+                height,
+                format,
+                buffer,
+            },
+        )
+        .await?;
+        // The image behind it is not known here
+        *self.last_fingerprint.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.record_history(config);
+        Ok(())
+    }
+
+    /// Send `frame` to the panel and keep it as the last frame
+    ///
+    /// Small changes against the frame on the panel get a partial refresh.
+    async fn send_frame(&self, config: &Config, frame: Frame) -> Result<(), ProcessingError> {
+        match self.auto_partial_region(config, &frame).await {
+            Some(region) => {
+                tracing::info!("Only {:?} changed, using a partial refresh", region);
+                self.display.display_partial(&frame.buffer, region).await?;
+            }
+            None => self.full_refresh(config, &frame.buffer).await?,
+        }
+        self.store_frame(frame);
+        Ok(())
+    }
+
+    /// Full refresh with `buffer`, power-cycling and initializing the
+    /// panel first every `reinit_every` full refreshes
+    ///
+    /// Spectra 6 panels that are only initialized once drift in tint over
+    /// weeks of refreshes; a fresh init brings the colors back.
+    async fn full_refresh(&self, config: &Config, buffer: &[u8]) -> Result<(), ProcessingError> {
+        if config.reinit_every > 0
+            && self.full_refreshes.load(Ordering::Relaxed) >= config.reinit_every
+        {
+            tracing::info!(
+                "{} full refreshes since the last init, power-cycling the panel",
+                config.reinit_every
+            );
+            self.display.sleep().await?;
+            self.display.init().await?;
+            self.full_refreshes.store(0, Ordering::Relaxed);
+        }
+        self.display.display(buffer).await?;
+        self.full_refreshes.fetch_add(1, Ordering::Relaxed);
+        self.count_full_refresh(config);
         Ok(())
     }
 
+    /// Count a full refresh of the panel against the daily budget
+    ///
+    /// Only scheduled refreshes stop at the budget; manual ones, messages
+    /// and pushes still run and are warned about here once over it.
+    fn count_full_refresh(&self, config: &Config) {
+        let Some(status) = &self.status else {
+            return;
+        };
+        status.record_panel_refresh();
+        let refreshes = status.refreshes_today();
+        if config.refresh_budget_exceeded(refreshes) {
+            tracing::warn!(
+                "Refresh over the daily refresh budget ({}/{})",
+                refreshes,
+                config.max_refreshes_per_day
+            );
+        }
+    }
+
+    /// Distance between an image and the one on the panel, if known
+    fn distance_to_panel(&self, fingerprint: &Fingerprint) -> Option<u32> {
+        if !self.frame_on_panel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let guard = self.last_fingerprint.lock().unwrap_or_else(|e| e.into_inner());
+        Some(guard.as_ref()?.distance(fingerprint))
+    }
+
+    /// Redraw only the source's partial-update region
+    ///
+    /// Renders the full frame but refreshes just the region the source
+    /// reports as changing (e.g., the clock's time). Returns false without
+    /// touching the panel if the source has no such region, the display
+    /// cannot do partial refreshes, or the panel does not show the rest of
+    /// the frame already (after a clear, or when content outside the
+    /// region changed); the regular full refresh redraws it instead.
+    pub async fn refresh_partial(&self, config: &Config) -> Result<bool, ProcessingError> {
+        let (width, height) = config.content_size();
+        let Some(region) = config.source.partial_region(width, height) else {
+            return Ok(false);
+        };
+        if !self.frame_on_panel.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        self.display.init().await?;
+        if !self.display.supports_partial().await {
+            return Ok(false);
+        }
+
+        let (img, _) = sources::fetch_image(config).await?;
+        let options = transform_options(config);
+        // Source region in panel coordinates, however the frame is turned
+        let region = transform_region(region, img.width(), img.height(), &options)
+            .map(|r| r.align_to_bytes())
+            .unwrap_or(crate::display::Region {
+                x: 0,
+                y: 0,
+                width: config.display_width,
+                height: config.display_height,
+            });
+
+        let rgb_image = transform_image(img, &image_options(config));
+        let format = self.display.pixel_format().await?;
+        let frame = self.orient_frame(config, dither_frame(config, rgb_image, format));
+        if !self.changed_only_within(&frame, region) {
+            tracing::debug!(
+                "Content outside {:?} changed, partial refresh not enough",
+                region
+            );
+            return Ok(false);
+        }
+
+        tracing::debug!("Partial refresh of {:?}", region);
+        self.display.display_partial(&frame.buffer, region).await?;
+        self.store_frame(frame);
+        Ok(true)
+    }
+
+    /// Show the last frame again in the configured orientation
+    ///
+    /// Only possible with `rotate_packed`: the kept unrotated frame is
+    /// rotated and mirrored again, without fetching or dithering. Returns
+    /// false without touching the panel if there is no such frame or it no
+    /// longer fits the panel (e.g. after turning by 90°).
+    pub async fn reorient(&self, config: &Config) -> Result<bool, ProcessingError> {
+        if !config.rotate_packed {
+            return Ok(false);
+        }
+
+        let format = self.display.pixel_format().await?;
+        let content = {
+            let guard = self.last_content.lock().unwrap_or_else(|e| e.into_inner());
+            match guard.as_ref() {
+                Some(frame)
+                    if frame.format == format
+                        && (frame.width, frame.height) == config.content_size() =>
+                {
+                    Frame {
+                        buffer: frame.buffer.clone(),
+                        ..*frame
+                    }
+                }
+                _ => return Ok(false),
+            }
+        };
+
+        tracing::info!("Showing last frame in the new orientation");
+        let _stage = self.begin(RefreshStage::Displaying);
+        let frame = self.orient_frame(config, content);
+        self.full_refresh(config, &frame.buffer).await?;
+        self.store_frame(frame);
+        Ok(true)
+    }
+
     /// Show test pattern on display
-    pub async fn show_test_pattern(&self) -> Result<(), ProcessingError> {
+    pub async fn show_test_pattern(&self, config: &Config) -> Result<(), ProcessingError> {
+        self.frame_on_panel.store(false, Ordering::Relaxed);
+        let _stage = self.begin(RefreshStage::Displaying);
         self.display.test_pattern().await?;
+        self.count_full_refresh(config);
         Ok(())
     }
 
     /// Clear display
-    pub async fn clear_display(&self) -> Result<(), ProcessingError> {
+    pub async fn clear_display(&self, config: &Config) -> Result<(), ProcessingError> {
+        self.frame_on_panel.store(false, Ordering::Relaxed);
+        let _stage = self.begin(RefreshStage::Displaying);
         self.display.init().await?;
         self.display.clear().await?;
+        self.count_full_refresh(config);
         Ok(())
     }
 
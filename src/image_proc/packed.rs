@@ -0,0 +1,215 @@
+//! Operations on packed frames.
+//!
+//! With `rotate_packed` the image is dithered in content orientation and
+//! the packed frame is turned afterwards by moving pixel values around.
+//! This is much cheaper than rotating the RGB image, and lets a new
+//! mounting orientation be applied to the last frame without fetching
+//! and dithering it again.
+//!
+//! Comparing a frame with the one on the panel gives the changed area,
+//! used to pick a partial refresh for small changes.
+
+use super::transform::{Rotation, TransformOptions};
+use crate::display::{PixelFormat, Region};
+
+/// Rotate and mirror a packed `width` x `height` frame
+///
+/// Follows the same rotation/mirroring order as
+/// [`transform_image`](super::transform_image). Returns the new frame and
+/// its dimensions (swapped for 90°/270°).
+pub fn transform_packed(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    options: &TransformOptions,
+) -> (Vec<u8>, u32, u32) {
+    let mut frame = (buffer.to_vec(), width, height);
+
+    if options.rotate_first {
+        frame = rotate(frame, format, options.rotation);
+        frame = mirror(frame, format, options.mirror_h, options.mirror_v);
+    } else {
+        frame = mirror(frame, format, options.mirror_h, options.mirror_v);
+        frame = rotate(frame, format, options.rotation);
+    }
+
+    frame
+}
+
+/// Bounding box of the pixels that differ between two packed frames
+///
+/// Both frames are `width` x `height` in `format`. Returns None if they
+/// are identical.
+pub fn changed_region(
+    old: &[u8],
+    new: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+) -> Option<Region> {
+    if old == new {
+        return None;
+    }
+
+    let bits = format.bits_per_pixel as usize;
+    let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+
+    for y in 0..height {
+        let row = y as usize * width as usize;
+        let differs = |x: u32| {
+            let index = row + x as usize;
+            pixel(old, index, bits) != pixel(new, index, bits)
+        };
+
+        let Some(first) = (0..width).find(|&x| differs(x)) else {
+            continue;
+        };
+        let last = (first..width).rev().find(|&x| differs(x)).unwrap_or(first);
+
+        left = left.min(first);
+        right = right.max(last + 1);
+        top = top.min(y);
+        bottom = y + 1;
+    }
+
+    (right > left).then_some(Region {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Rotate a packed frame clockwise, like `rotate90`
+fn rotate(
+    (buffer, width, height): (Vec<u8>, u32, u32),
+    format: PixelFormat,
+    rotation: Rotation,
+) -> (Vec<u8>, u32, u32) {
+    match rotation {
+        Rotation::None => (buffer, width, height),
+        Rotation::Rotate90 => (
+            remap(&buffer, width, format, height, width, |x, y| {
+                (y, height - 1 - x)
+            }),
+            height,
+            width,
+        ),
+        Rotation::Rotate180 => (
+            remap(&buffer, width, format, width, height, |x, y| {
+                (width - 1 - x, height - 1 - y)
+            }),
+            width,
+            height,
+        ),
+        Rotation::Rotate270 => (
+            remap(&buffer, width, format, height, width, |x, y| {
+                (width - 1 - y, x)
+            }),
+            height,
+            width,
+        ),
+    }
+}
+
+/// Mirror a packed frame
+fn mirror(
+    (buffer, width, height): (Vec<u8>, u32, u32),
+    format: PixelFormat,
+    mirror_h: bool,
+    mirror_v: bool,
+) -> (Vec<u8>, u32, u32) {
+    if !mirror_h && !mirror_v {
+        return (buffer, width, height);
+    }
+
+    let buffer = remap(&buffer, width, format, width, height, |x, y| {
+        (
+            if mirror_h { width - 1 - x } else { x },
+            if mirror_v { height - 1 - y } else { y },
+        )
+    });
+    (buffer, width, height)
+}
+
+/// Build an `out_width` x `out_height` frame, taking each pixel from the
+/// source pixel `source(x, y)` returns
+fn remap(
+    buffer: &[u8],
+    width: u32,
+    format: PixelFormat,
+    out_width: u32,
+    out_height: u32,
+    source: impl Fn(u32, u32) -> (u32, u32),
+) -> Vec<u8> {
+    let bits = format.bits_per_pixel as usize;
+    let mut out = vec![0u8; format.buffer_size(out_width, out_height)];
+
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let (sx, sy) = source(x, y);
+            let value = pixel(buffer, sy as usize * width as usize + sx as usize, bits);
+            let to = (y as usize * out_width as usize + x as usize) * bits;
+            out[to / 8] |= value << (8 - bits - to % 8);
+        }
+    }
+
+    out
+}
+
+/// Value of pixel `index` in a frame of `bits`-bit pixels
+#[inline]
+fn pixel(buffer: &[u8], index: usize, bits: usize) -> u8 {
+    let bit = index * bits;
+    let mask = ((1u16 << bits) - 1) as u8;
+    (buffer[bit / 8] >> (8 - bits - bit % 8)) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Palette;
+
+    /// 4 bits per pixel, codes 0..=6
+    fn packed4() -> PixelFormat {
+        PixelFormat::packed4(Palette::Colors(&crate::display::epd7in3e::PALETTE))
+    }
+
+    #[test]
+    fn packed_rotation_moves_pixel_codes() {
+        // 3x2 frame, codes 0..=5 in reading order
+        let frame = [0x01, 0x23, 0x45];
+        let turn = |options: TransformOptions| transform_packed(&frame, 3, 2, packed4(), &options);
+
+        let rotated = turn(TransformOptions {
+            rotation: Rotation::Rotate90,
+            ..Default::default()
+        });
+        assert_eq!(rotated, (vec![0x30, 0x41, 0x52], 2, 3));
+        let mirrored = turn(TransformOptions {
+            mirror_h: true,
+            ..Default::default()
+        });
+        assert_eq!(mirrored, (vec![0x21, 0x05, 0x43], 3, 2));
+    }
+
+    #[test]
+    fn changed_region_bounds_the_differing_pixels() {
+        // 4x4 frame, pixel (2, 2) changed
+        let old = [0u8; 8];
+        let mut new = old;
+        new[5] = 0x30;
+
+        assert_eq!(changed_region(&old, &old, 4, 4, packed4()), None);
+        assert_eq!(
+            changed_region(&old, &new, 4, 4, packed4()),
+            Some(Region {
+                x: 2,
+                y: 2,
+                width: 1,
+                height: 1,
+            })
+        );
+    }
+}
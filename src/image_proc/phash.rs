@@ -0,0 +1,155 @@
+//! Perceptual fingerprint of transformed images.
+//!
+//! The image is reduced to 32x32 and grey, transformed with a DCT, and the
+//! 8x8 lowest frequencies (without the DC term) are compared with their
+//! median. Images that look alike get hashes a few bits apart, even when
+//! small details differ (a clock digit, a "last updated" line), which is
+//! what makes it useful for skipping refreshes that would change next to
+//! nothing.
+//!
+//! The hash only captures structure in brightness, so a screen turning
+//! from green to red would hash the same. The fingerprint therefore also
+//! keeps the average color of a 4x4 grid of tiles.
+
+use image::RgbImage;
+use image::imageops::{self, FilterType};
+
+/// Side of the reduced image
+const SIZE: usize = 32;
+
+/// Side of the low-frequency block the hash is built from
+const LOW: usize = 8;
+
+/// Tiles per side of the color grid
+const TILES: usize = 4;
+
+/// Largest change of a tile's average color (per channel) that still
+/// counts as the same image
+const TILE_TOLERANCE: u8 = 24;
+
+/// Perceptual hash and coarse colors of an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    hash: u64,
+    tiles: [[u8; 3]; TILES * TILES],
+}
+
+impl Fingerprint {
+    pub fn of(img: &RgbImage) -> Self {
+        let small = imageops::resize(img, SIZE as u32, SIZE as u32, FilterType::Triangle);
+
+        let mut sums = [[0u32; 3]; TILES * TILES];
+        let mut grey = [[0f32; SIZE]; SIZE];
+        for (x, y, pixel) in small.enumerate_pixels() {
+            let [r, g, b] = pixel.0;
+            grey[y as usize][x as usize] = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+
+            let tile = (y as usize * TILES / SIZE) * TILES + x as usize * TILES / SIZE;
+            for (sum, value) in sums[tile].iter_mut().zip(pixel.0) {
+                *sum += value as u32;
+            }
+        }
+        let per_tile = (SIZE * SIZE / (TILES * TILES)) as u32;
+        let tiles = sums.map(|sum| sum.map(|s| (s / per_tile) as u8));
+
+        Self {
+            hash: dct_hash(&grey),
+            tiles,
+        }
+    }
+
+    /// Number of differing hash bits, or 64 if the colors changed noticeably
+    pub fn distance(&self, other: &Fingerprint) -> u32 {
+        let colors_changed = self.tiles.iter().zip(&other.tiles).any(|(a, b)| {
+            a.iter()
+                .zip(b)
+                .any(|(x, y)| x.abs_diff(*y) > TILE_TOLERANCE)
+        });
+        if colors_changed {
+            64
+        } else {
+            (self.hash ^ other.hash).count_ones()
+        }
+    }
+}
+
+/// 64-bit DCT hash of a grey 32x32 image
+fn dct_hash(grey: &[[f32; SIZE]; SIZE]) -> u64 {
+    // Separable 2D DCT-II, only the low-frequency rows/columns are needed
+    let basis: Vec<[f32; SIZE]> = (0..LOW)
+        .map(|u| {
+            std::array::from_fn(|x| {
+                ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / (2 * SIZE) as f32).cos()
+            })
+        })
+        .collect();
+    let rows: Vec<[f32; LOW]> = grey
+        .iter()
+        .map(|row| std::array::from_fn(|u| dot(&basis[u], row)))
+        .collect();
+    let mut coefficients = [0f32; LOW * LOW];
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let column: [f32; SIZE] = std::array::from_fn(|y| rows[y][u]);
+            coefficients[v * LOW + u] = dot(&basis[v], &column);
+        }
+    }
+
+    // The DC term only says how bright the image is overall
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, c)| **c > median)
+        .fold(0u64, |hash, (i, _)| hash | 1 << i)
+}
+
+fn dot(a: &[f32; SIZE], b: &[f32; SIZE]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    /// Diagonal gradient with a dark disc, like a photo with a subject
+    fn scene() -> RgbImage {
+        RgbImage::from_fn(200, 120, |x, y| {
+            let (dx, dy) = (x as i32 - 70, y as i32 - 60);
+            if dx * dx + dy * dy < 30 * 30 {
+                Rgb([30, 30, 60])
+            } else {
+                let v = (x + y) as u8;
+                Rgb([v, v, 200])
+            }
+        })
+    }
+
+    #[test]
+    fn small_details_keep_the_distance_small() {
+        let before = Fingerprint::of(&scene());
+        let mut after = scene();
+        for x in 150..156 {
+            for y in 10..16 {
+                after.put_pixel(x, y, Rgb([0, 0, 0]));
+            }
+        }
+        assert!(before.distance(&Fingerprint::of(&after)) <= 4);
+        assert_eq!(before.distance(&before), 0);
+
+        let flipped = imageops::flip_horizontal(&scene());
+        assert!(before.distance(&Fingerprint::of(&flipped)) > 10);
+    }
+
+    #[test]
+    fn color_changes_count_as_different() {
+        let red = Fingerprint::of(&RgbImage::from_pixel(64, 64, Rgb([200, 30, 30])));
+        let green = Fingerprint::of(&RgbImage::from_pixel(64, 64, Rgb([30, 200, 30])));
+        assert_eq!(red.distance(&green), 64);
+    }
+}
@@ -0,0 +1,276 @@
+//! Photo captions from EXIF and IPTC metadata.
+//!
+//! For a photo frame, downloaded photos can get a small caption with the
+//! date they were taken and their description, drawn in a corner of the
+//! photo. Only these few fields are read: the date from EXIF
+//! `DateTimeOriginal` (else IPTC `Date Created`, else EXIF `DateTime`) and
+//! the description from IPTC `Caption-Abstract` (else EXIF
+//! `ImageDescription`).
+
+use super::transform::fit_image;
+use crate::render::text::text_width;
+use crate::render::{Theme, fit_text};
+use chrono::NaiveDateTime;
+use image::{DynamicImage, ImageDecoder};
+use serde::{Deserialize, Serialize};
+
+/// EXIF tags read
+const TAG_IMAGE_DESCRIPTION: u16 = 0x010e;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// Photoshop image resource holding the IPTC records
+const IPTC_RESOURCE: u16 = 0x0404;
+
+/// Longest caption drawn, in characters
+const MAX_CAPTION_CHARS: usize = 120;
+
+/// What the caption of a downloaded photo shows
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoCaption {
+    /// No caption
+    #[default]
+    Off,
+    /// Date taken
+    Date,
+    /// Description
+    Description,
+    /// Description and date taken
+    Both,
+}
+
+/// Metadata of a photo shown in its caption
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhotoInfo {
+    /// Time the photo was taken, in the camera's time zone
+    pub taken: Option<NaiveDateTime>,
+    /// Description or caption
+    pub description: Option<String>,
+}
+
+impl PhotoCaption {
+    /// Caption text for `info`, or None if there is nothing to show
+    pub fn text(self, info: &PhotoInfo) -> Option<String> {
+        let date = info
+            .taken
+            .map(|taken| taken.format("%-d %B %Y").to_string());
+        let parts: Vec<String> = match self {
+            PhotoCaption::Off => return None,
+            PhotoCaption::Date => date.into_iter().collect(),
+            PhotoCaption::Description => info.description.clone().into_iter().collect(),
+            PhotoCaption::Both => info.description.clone().into_iter().chain(date).collect(),
+        };
+        let text: String = parts.join(" · ").chars().take(MAX_CAPTION_CHARS).collect();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+impl PhotoInfo {
+    /// Read the metadata of the image `decoder` is about to decode
+    ///
+    /// Missing or damaged metadata gives empty fields.
+    pub fn read(decoder: &mut impl ImageDecoder) -> Self {
+        let exif = decoder.exif_metadata().ok().flatten();
+        let iptc = decoder.iptc_metadata().ok().flatten();
+        let exif = exif.as_deref().map(Exif::parse).unwrap_or_default();
+        let iptc = iptc.as_deref().map(Iptc::parse).unwrap_or_default();
+
+        let exif_time = |value: Option<String>| {
+            value.and_then(|value| {
+                NaiveDateTime::parse_from_str(value.trim(), "%Y:%m:%d %H:%M:%S").ok()
+            })
+        };
+        let taken = exif_time(exif.date_time_original)
+            .or(iptc.date_created)
+            .or_else(|| exif_time(exif.date_time));
+        PhotoInfo {
+            taken,
+            description: iptc.caption.or(exif.image_description),
+        }
+    }
+}
+
+/// `image` fitted to `width` x `height` with `caption` in the bottom
+/// right corner of the photo
+pub fn with_caption(
+    image: DynamicImage,
+    caption: &str,
+    width: u32,
+    height: u32,
+    scale_to_fit: bool,
+    smart_crop: bool,
+    theme: &Theme,
+) -> DynamicImage {
+    let image = fit_image(image, width, height, scale_to_fit, smart_crop);
+    let (photo_width, photo_height) = (image.width().min(width), image.height().min(height));
+    let left = (width - photo_width) / 2;
+    let top = (height - photo_height) / 2;
+    let mut canvas = theme.canvas(width, height);
+    canvas.draw_image(left as i32, top as i32, &image);
+    drop(image);
+
+    let size = theme.unit(height);
+    let pad = size / 3;
+    let line = fit_text(caption, size, photo_width.saturating_sub(4 * pad));
+    let box_width = text_width(&line, size) + 2 * pad;
+    let box_height = size + 2 * pad;
+    let x = (left + photo_width).saturating_sub(box_width + pad);
+    let y = (top + photo_height).saturating_sub(box_height + pad);
+    canvas.fill_rect(x as i32, y as i32, box_width, box_height, theme.background);
+    canvas.text((x + pad) as i32, (y + pad) as i32, &line, size, theme.text);
+    canvas.into_image()
+}
+
+/// Fields read from an EXIF block (a TIFF header and IFDs)
+#[derive(Debug, Default)]
+struct Exif {
+    image_description: Option<String>,
+    date_time: Option<String>,
+    date_time_original: Option<String>,
+}
+
+impl Exif {
+    fn parse(data: &[u8]) -> Self {
+        let mut exif = Exif::default();
+        let big_endian = match data.get(..2) {
+            Some(b"MM") => true,
+            Some(b"II") => false,
+            _ => return exif,
+        };
+        let tiff = Tiff { data, big_endian };
+        let Some(ifd0) = tiff.u32(4) else {
+            return exif;
+        };
+        let mut exif_ifd = None;
+        tiff.entries(ifd0 as usize, |tag, offset| match tag {
+            TAG_IMAGE_DESCRIPTION => exif.image_description = tiff.ascii(offset),
+            TAG_DATE_TIME => exif.date_time = tiff.ascii(offset),
+            TAG_EXIF_IFD => exif_ifd = tiff.u32(offset + 8),
+            _ => {}
+        });
+        if let Some(ifd) = exif_ifd {
+            tiff.entries(ifd as usize, |tag, offset| {
+                if tag == TAG_DATE_TIME_ORIGINAL {
+                    exif.date_time_original = tiff.ascii(offset);
+                }
+            });
+        }
+        exif
+    }
+}
+
+/// TIFF structure of an EXIF block
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// Call `entry` with the tag and offset of each entry of the IFD at
+    /// `offset`
+    fn entries(&self, offset: usize, mut entry: impl FnMut(u16, usize)) {
+        let Some(count) = self.u16(offset) else {
+            return;
+        };
+        for index in 0..count as usize {
+            let at = offset + 2 + index * 12;
+            match self.u16(at) {
+                Some(tag) => entry(tag, at),
+                None => return,
+            }
+        }
+    }
+
+    /// Value of the ASCII entry at `entry`, trimmed; None if empty
+    fn ascii(&self, entry: usize) -> Option<String> {
+        // Type 2 = ASCII; up to 4 bytes are stored in the entry itself
+        if self.u16(entry + 2)? != 2 {
+            return None;
+        }
+        let count = self.u32(entry + 4)? as usize;
+        let start = if count <= 4 {
+            entry + 8
+        } else {
+            self.u32(entry + 8)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+/// Fields read from IPTC records in Photoshop image resources
+#[derive(Debug, Default)]
+struct Iptc {
+    caption: Option<String>,
+    date_created: Option<NaiveDateTime>,
+}
+
+impl Iptc {
+    fn parse(data: &[u8]) -> Self {
+        let mut iptc = Iptc::default();
+        let Some(records) = photoshop_resource(data, IPTC_RESOURCE) else {
+            return iptc;
+        };
+        let mut at = 0;
+        // Tag marker, record, dataset, length (no extended lengths)
+        while let Some(&[0x1c, record, dataset, high, low]) = records.get(at..at + 5) {
+            let len = u16::from_be_bytes([high, low]) as usize;
+            let Some(value) = records.get(at + 5..at + 5 + len) else {
+                break;
+            };
+            let text = String::from_utf8_lossy(value).trim().to_string();
+            match (record, dataset) {
+                (2, 120) if !text.is_empty() => iptc.caption = Some(text),
+                (2, 55) => {
+                    iptc.date_created = chrono::NaiveDate::parse_from_str(&text, "%Y%m%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0));
+                }
+                _ => {}
+            }
+            at += 5 + len;
+        }
+        iptc
+    }
+}
+
+/// Data of the Photoshop image resource `id` in `data`
+fn photoshop_resource(data: &[u8], id: u16) -> Option<&[u8]> {
+    let mut at = 0;
+    while data.get(at..at + 4)? == b"8BIM" {
+        let resource = u16::from_be_bytes(data.get(at + 4..at + 6)?.try_into().ok()?);
+        // Pascal string name, padded to an even length
+        let name_len = *data.get(at + 6)? as usize;
+        let size_at = at + 6 + (name_len + 2) / 2 * 2;
+        let size = u32::from_be_bytes(data.get(size_at..size_at + 4)?.try_into().ok()?) as usize;
+        let start = size_at + 4;
+        let value = data.get(start..start.checked_add(size)?)?;
+        if resource == id {
+            return Some(value);
+        }
+        at = start + size.div_ceil(2) * 2;
+    }
+    None
+}
@@ -0,0 +1,348 @@
+//! Cookie sessions for image sources behind a login.
+//!
+//! With `session.cookies` on, cookies set by image sources are kept in a
+//! jar (`cookie_store`, behind the HTTP client's cookie provider) and sent
+//! back with later requests, including those set on redirects along the
+//! way (the usual SSO dance of redirects to the identity provider and
+//! back).
+//!
+//! With `session.login_url` set, a source answering 401/403 or with an
+//! HTML page (a login form) gets `session.login_form` posted to that URL
+//! once, and the download is tried again with the cookies the login set.
+//! The jar can be saved to `session.cookie_file` so a restart does not
+//! need a new login.
+
+use super::download::{DEFAULT_USER_AGENT, DownloadError, log_request, log_response};
+use crate::config::ConfigError;
+use crate::secrets;
+use cookie_store::{CookieStore, RawCookie};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Method, Response, Url};
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Public suffix list of the system (Debian's `publicsuffix` package)
+const PUBLIC_SUFFIX_FILE: &str = "/usr/share/publicsuffix/public_suffix_list.dat";
+
+/// Cookies of all sources, shared by every refresh
+static JAR: Lazy<Arc<Jar>> = Lazy::new(Arc::default);
+
+/// Client for session requests, keeping cookies in the jar
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .pool_max_idle_per_host(1)
+        .pool_idle_timeout(Duration::from_secs(30))
+        .user_agent(DEFAULT_USER_AGENT)
+        .cookie_provider(Arc::clone(&JAR))
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+/// Suffixes like `co.uk` that cookies must not be set for, if the system
+/// has the list
+static PUBLIC_SUFFIXES: Lazy<Option<publicsuffix::List>> = Lazy::new(|| {
+    let list = std::fs::read(PUBLIC_SUFFIX_FILE)
+        .map_err(|e| e.to_string())
+        .and_then(|list| publicsuffix::List::from_bytes(&list).map_err(|e| e.to_string()));
+    list.inspect_err(|e| {
+        tracing::debug!("No public suffix list from {}: {}", PUBLIC_SUFFIX_FILE, e);
+    })
+    .ok()
+});
+
+/// Cookie and login settings for image sources
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionConfig {
+    /// Keep cookies set by image sources and send them back
+    #[serde(default)]
+    pub cookies: bool,
+    /// File the cookies are saved to (empty = kept in memory only)
+    #[serde(default)]
+    pub cookie_file: String,
+    /// URL the login form is posted to when a source asks for a login
+    /// (empty = no login; setting it turns the cookie jar on)
+    #[serde(default)]
+    pub login_url: String,
+    /// Fields of the login form; values may be `${ENV:...}` / `${FILE:...}`
+    #[serde(default)]
+    pub login_form: BTreeMap<String, String>,
+}
+
+impl SessionConfig {
+    /// Whether the cookie jar is used
+    pub fn enabled(&self) -> bool {
+        self.cookies || self.has_login()
+    }
+
+    /// Whether a login request is configured
+    pub fn has_login(&self) -> bool {
+        !self.login_url.trim().is_empty()
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = self.login_url.trim();
+        if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "session.login_url must start with http:// or https://".to_string(),
+            ));
+        }
+        if !self.has_login() && !self.login_form.is_empty() {
+            return Err(ConfigError::ValidationError(
+                "session.login_form needs session.login_url".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Send a request with the jar's cookies, following redirects
+    pub async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response, DownloadError> {
+        self.send_with_form(method, url, headers, None).await
+    }
+
+    /// Post the login form, keeping the cookies the server sets
+    pub async fn login(&self, user_agent: Option<&HeaderValue>) -> Result<(), DownloadError> {
+        let url = secrets::expand(self.login_url.trim())
+            .map_err(|e| DownloadError::Login(e.to_string()))?;
+        let form = self
+            .resolved_form()
+            .map_err(|e| DownloadError::Login(e.to_string()))?;
+
+        tracing::info!("Logging in at {}", url);
+        let mut headers = HeaderMap::new();
+        if let Some(user_agent) = user_agent {
+            headers.insert(USER_AGENT, user_agent.clone());
+        }
+        let response = self
+            .send_with_form(Method::POST, &url, &headers, Some(form))
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError::Login(format!("{} answered {}", url, status)));
+        }
+        Ok(())
+    }
+
+    /// Fields of the login form with references resolved
+    ///
+    /// Every value counts as a secret and is masked from then on, whatever
+    /// the field is called.
+    fn resolved_form(&self) -> Result<Vec<(String, String)>, secrets::SecretError> {
+        self.login_form
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), secrets::resolve(value)?)))
+            .collect()
+    }
+
+    async fn send_with_form(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &HeaderMap,
+        form: Option<Vec<(String, String)>>,
+    ) -> Result<Response, DownloadError> {
+        let file = self.cookie_file.trim();
+        JAR.open((!file.is_empty()).then(|| PathBuf::from(file)));
+
+        let mut request = CLIENT.request(method, url).headers(headers.clone());
+        if let Some(form) = &form {
+            request = request.form(form);
+        }
+        let request = request.build()?;
+        log_request(request.method(), request.url().as_str(), request.headers());
+        let response = CLIENT.execute(request).await?;
+        log_response(&response);
+        Ok(response)
+    }
+}
+
+/// Cookies received so far, optionally backed by a file
+#[derive(Debug, Default)]
+struct Jar {
+    store: CookieStoreMutex,
+    file: Mutex<Option<PathBuf>>,
+}
+
+impl Jar {
+    /// Switch to the cookies saved in `file` (none = start empty)
+    fn open(&self, file: Option<PathBuf>) {
+        let mut current = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if *current == file {
+            return;
+        }
+        let mut store = file.as_deref().map(load).unwrap_or_default();
+        if let Some(list) = PUBLIC_SUFFIXES.as_ref() {
+            store = store.with_suffix_list(list.clone());
+        }
+        *self.store.lock().unwrap_or_else(|e| e.into_inner()) = store;
+        *current = file;
+    }
+
+    /// Write the cookies to the jar's file, if it has one
+    fn save(&self) {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(file) = file.as_deref() else {
+            return;
+        };
+        let store = self.store.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = save(file, &store) {
+            tracing::warn!("Failed to save cookies to {}: {}", file.display(), e);
+        }
+    }
+}
+
+impl reqwest::cookie::CookieStore for Jar {
+    fn set_cookies(&self, headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut accepted = headers.filter(|header| registrable(header, url)).peekable();
+        if accepted.peek().is_none() {
+            return;
+        }
+        self.store.set_cookies(&mut accepted, url);
+        self.save();
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.store.cookies(url)
+    }
+}
+
+/// Whether the Domain attribute of a `Set-Cookie` header, if any, names
+/// more than a top-level domain like `com` or `local`
+///
+/// Suffixes with a dot (`co.uk`) are refused by the public suffix list.
+fn registrable(header: &HeaderValue, url: &Url) -> bool {
+    let Some(cookie) = header.to_str().ok().and_then(|h| RawCookie::parse(h).ok()) else {
+        return true;
+    };
+    let Some(domain) = cookie.domain().map(|d| d.trim_start_matches('.')) else {
+        return true;
+    };
+    if domain.contains('.')
+        || url
+            .host_str()
+            .is_some_and(|h| h.eq_ignore_ascii_case(domain))
+    {
+        return true;
+    }
+    tracing::debug!(
+        "Ignoring cookie {} for top-level domain {}",
+        cookie.name(),
+        domain
+    );
+    false
+}
+
+/// Cookies saved in `file`; a missing or unreadable file gives none
+fn load(file: &Path) -> CookieStore {
+    let content = match std::fs::read(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return CookieStore::default(),
+        Err(e) => {
+            tracing::warn!("Failed to read cookies from {}: {}", file.display(), e);
+            return CookieStore::default();
+        }
+    };
+    cookie_store::serde::json::load(content.as_slice()).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring unreadable cookie file {}: {}", file.display(), e);
+        CookieStore::default()
+    })
+}
+
+/// Write cookies atomically, readable by the owner only
+///
+/// Session cookies are kept too: a login is usually one.
+fn save(file: &Path, store: &CookieStore) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let tmp = file.with_extension("tmp");
+    let mut out = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(store, &mut out)
+        .map_err(std::io::Error::other)?;
+    out.flush()?;
+    drop(out);
+    std::fs::rename(&tmp, file).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_form_values_are_masked() {
+        let session = SessionConfig {
+            login_url: "https://sso.local/login".to_string(),
+            login_form: [("user", "grandma"), ("pwd", "hunter2-login")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let form = session.resolved_form().unwrap();
+        assert_eq!(form[0], ("pwd".to_string(), "hunter2-login".to_string()));
+        assert_eq!(
+            secrets::redact("login failed for grandma with hunter2-login"),
+            "login failed for <redacted> with <redacted>"
+        );
+    }
+
+    #[test]
+    fn cookies_for_top_level_domains_and_public_suffixes_are_refused() {
+        use reqwest::cookie::CookieStore as _;
+
+        let list = "// ===BEGIN ICANN DOMAINS===\nuk\nco.uk\n".parse().unwrap();
+        let jar = Jar {
+            store: CookieStoreMutex::new(CookieStore::default().with_suffix_list(list)),
+            ..Default::default()
+        };
+        let url = Url::parse("https://shop.example.co.uk/login").unwrap();
+        let headers = [
+            "tld=1; Domain=uk",
+            "suffix=2; Domain=.co.uk",
+            "site=3; Domain=example.co.uk",
+            "host=4",
+        ]
+        .map(HeaderValue::from_static);
+        jar.set_cookies(&mut headers.iter(), &url);
+
+        let sibling = Url::parse("https://www.example.co.uk/").unwrap();
+        assert_eq!(jar.cookies(&sibling).unwrap(), "site=3");
+        let foreign = Url::parse("https://evil.co.uk/").unwrap();
+        assert_eq!(jar.cookies(&foreign), None);
+
+        // No dot is refused even without a list, unless it is the host
+        let jar = Jar::default();
+        let headers = ["tld=1; Domain=local", "nas=2; Domain=nas"].map(HeaderValue::from_static);
+        jar.set_cookies(&mut headers.iter(), &Url::parse("http://nas/").unwrap());
+        jar.set_cookies(
+            &mut headers.iter(),
+            &Url::parse("http://frame.local/").unwrap(),
+        );
+        assert_eq!(
+            jar.cookies(&Url::parse("http://other.local/").unwrap()),
+            None
+        );
+        assert_eq!(
+            jar.cookies(&Url::parse("http://nas/").unwrap()).unwrap(),
+            "nas=2"
+        );
+    }
+}
@@ -2,7 +2,15 @@
 //!
 //! Provides scaling, rotation, and mirroring for display preparation.
 
-use image::{DynamicImage, GenericImageView, RgbImage};
+use crate::display::Region;
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use std::cmp::Reverse;
+
+/// Size of the thumbnail the crop window of `smart_crop` is chosen on
+const SALIENCY_SIZE: u32 = 200;
+
+/// Detail score of a skin-toned pixel, on top of its edges (0..=510)
+const SKIN_BONUS: u32 = 96;
 
 /// Rotation angle in degrees
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,6 +43,9 @@ pub struct TransformOptions {
     pub mirror_v: bool,
     /// Scale to fit display dimensions
     pub scale_to_fit: bool,
+    /// When filling, crop around the most detailed part instead of the
+    /// center
+    pub smart_crop: bool,
     /// Apply rotation before mirroring (true) or mirror before rotating (false)
     pub rotate_first: bool,
     /// Target display width
@@ -50,6 +61,7 @@ impl Default for TransformOptions {
             mirror_h: false,
             mirror_v: false,
             scale_to_fit: true,
+            smart_crop: false,
             rotate_first: true,
             target_width: 800,
             target_height: 480,
@@ -81,12 +93,113 @@ pub fn transform_image(img: DynamicImage, options: &TransformOptions) -> RgbImag
     let scaled = if options.scale_to_fit {
         scale_to_fit(img, target_width, target_height)
     } else {
-        scale_to_fill(img, target_width, target_height)
+        scale_to_fill(img, target_width, target_height, options.smart_crop)
     };
 
     scaled.into_rgb8()
 }
 
+/// Scale an image into a `width` x `height` box without rotating
+///
+/// Letterboxes (`scale_to_fit`) or center-crops like [`transform_image`].
+pub fn fit_image(
+    img: DynamicImage,
+    width: u32,
+    height: u32,
+    scale_to_fit: bool,
+    smart_crop: bool,
+) -> RgbImage {
+    if img.width() == width && img.height() == height {
+        return img.into_rgb8();
+    }
+
+    let scaled = if scale_to_fit {
+        self::scale_to_fit(img, width, height)
+    } else {
+        scale_to_fill(img, width, height, smart_crop)
+    };
+    scaled.into_rgb8()
+}
+
+/// Map a region of the source image to display coordinates
+///
+/// Follows the same rotation/mirroring order as [`transform_image`].
+/// Returns None when the image would be scaled, since the region then
+/// no longer maps onto whole pixels.
+pub fn transform_region(
+    region: Region,
+    src_width: u32,
+    src_height: u32,
+    options: &TransformOptions,
+) -> Option<Region> {
+    let mut rect = (region, src_width, src_height);
+
+    if options.rotate_first {
+        rect = rotate_region(rect, options.rotation);
+        rect = mirror_region(rect, options.mirror_h, options.mirror_v);
+    } else {
+        rect = mirror_region(rect, options.mirror_h, options.mirror_v);
+        rect = rotate_region(rect, options.rotation);
+    }
+
+    let (region, width, height) = rect;
+    (width == options.target_width && height == options.target_height).then_some(region)
+}
+
+/// Rotate a region within a `width` x `height` image (clockwise, like `rotate90`)
+fn rotate_region(
+    (r, width, height): (Region, u32, u32),
+    rotation: Rotation,
+) -> (Region, u32, u32) {
+    match rotation {
+        Rotation::None => (r, width, height),
+        Rotation::Rotate90 => (
+            Region {
+                x: height - (r.y + r.height),
+                y: r.x,
+                width: r.height,
+                height: r.width,
+            },
+            height,
+            width,
+        ),
+        Rotation::Rotate180 => (
+            Region {
+                x: width - (r.x + r.width),
+                y: height - (r.y + r.height),
+                ..r
+            },
+            width,
+            height,
+        ),
+        Rotation::Rotate270 => (
+            Region {
+                x: r.y,
+                y: width - (r.x + r.width),
+                width: r.height,
+                height: r.width,
+            },
+            height,
+            width,
+        ),
+    }
+}
+
+/// Mirror a region within a `width` x `height` image
+fn mirror_region(
+    (mut r, width, height): (Region, u32, u32),
+    mirror_h: bool,
+    mirror_v: bool,
+) -> (Region, u32, u32) {
+    if mirror_h {
+        r.x = width - (r.x + r.width);
+    }
+    if mirror_v {
+        r.y = height - (r.y + r.height);
+    }
+    (r, width, height)
+}
+
 /// Apply rotation to image
 fn apply_rotation(img: DynamicImage, rotation: Rotation) -> DynamicImage {
     match rotation {
@@ -145,7 +258,15 @@ fn scale_to_fit(img: DynamicImage, max_width: u32, max_height: u32) -> DynamicIm
 }
 
 /// Scale image to fill dimensions (crop overflow)
-fn scale_to_fill(img: DynamicImage, target_width: u32, target_height: u32) -> DynamicImage {
+///
+/// The overflow is cropped evenly from both sides, or with `smart_crop`
+/// so that the most detailed part of the image stays.
+fn scale_to_fill(
+    img: DynamicImage,
+    target_width: u32,
+    target_height: u32,
+    smart_crop: bool,
+) -> DynamicImage {
     let (src_width, src_height) = img.dimensions();
 
     // Calculate scale factor to fill bounds
@@ -169,10 +290,112 @@ fn scale_to_fill(img: DynamicImage, target_width: u32, target_height: u32) -> Dy
     // Resize the image
     let resized = img.resize_exact(new_width, new_height, image::imageops::FilterType::Triangle);
 
-    // Crop to target size (center crop)
-    let crop_x = (new_width - target_width) / 2;
-    let crop_y = (new_height - target_height) / 2;
+    // Crop to target size
+    let (crop_x, crop_y) = if !smart_crop {
+        (
+            (new_width - target_width) / 2,
+            (new_height - target_height) / 2,
+        )
+    } else if new_height > target_height {
+        (0, subject_offset(&resized, target_height, true))
+    } else {
+        (subject_offset(&resized, target_width, false), 0)
+    };
 
     resized.crop_imm(crop_x, crop_y, target_width, target_height)
 }
 
+/// Start of the crop window of `window` pixels along the height
+/// (`vertical`) or width of `img` that holds the most detail
+///
+/// Detail is the contrast to neighbouring pixels, with extra weight for
+/// skin tones so faces are kept, summed per row or column of a small
+/// thumbnail. Windows with equal detail (a plain image) prefer the center.
+fn subject_offset(img: &DynamicImage, window: u32, vertical: bool) -> u32 {
+    let length = if vertical { img.height() } else { img.width() };
+    if window >= length {
+        return 0;
+    }
+
+    let thumb = img.thumbnail(SALIENCY_SIZE, SALIENCY_SIZE).into_rgb8();
+    let luma = |x: u32, y: u32| {
+        let [r, g, b] = thumb.get_pixel(x, y).0;
+        (r as i32 * 299 + g as i32 * 587 + b as i32 * 114) / 1000
+    };
+    let (width, height) = thumb.dimensions();
+    let mut profile = vec![0u64; if vertical { height } else { width } as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let mut score = 0;
+            if x + 1 < width {
+                score += luma(x, y).abs_diff(luma(x + 1, y));
+            }
+            if y + 1 < height {
+                score += luma(x, y).abs_diff(luma(x, y + 1));
+            }
+            if is_skin(*thumb.get_pixel(x, y)) {
+                score += SKIN_BONUS;
+            }
+            profile[if vertical { y } else { x } as usize] += score as u64;
+        }
+    }
+
+    // Sums over windows from running totals
+    let scale = profile.len() as f64 / length as f64;
+    let span = ((window as f64 * scale).round() as usize).clamp(1, profile.len());
+    let totals: Vec<u64> = std::iter::once(0)
+        .chain(profile.iter().scan(0, |total, score| {
+            *total += score;
+            Some(*total)
+        }))
+        .collect();
+    let center = (profile.len() - span) / 2;
+    let best = (0..=profile.len() - span)
+        .max_by_key(|&start| {
+            (
+                totals[start + span] - totals[start],
+                Reverse(start.abs_diff(center)),
+            )
+        })
+        .unwrap_or(center);
+
+    let offset = ((best as f64 / scale).round() as u32).min(length - window);
+    tracing::debug!("Smart crop at {} of {} ({} kept)", offset, length, window);
+    offset
+}
+
+/// Whether a pixel looks like skin (a common RGB rule of thumb)
+fn is_skin(pixel: Rgb<u8>) -> bool {
+    let [r, g, b] = pixel.0.map(i32::from);
+    r > 95 && g > 40 && b > 20 && r > g && r > b && r - g.min(b) > 15 && (r - g).abs() > 15
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_crop_keeps_the_subject() {
+        // Portrait photo, plain white but for a detailed top quarter
+        let img = image::RgbImage::from_fn(200, 400, |x, y| {
+            let dark = y < 100 && (x / 10 + y / 10) % 2 == 0;
+            image::Rgb(if dark { [0, 0, 0] } else { [255, 255, 255] })
+        });
+        let crop = |smart_crop| {
+            let options = TransformOptions {
+                scale_to_fit: false,
+                smart_crop,
+                target_width: 400,
+                target_height: 240,
+                ..Default::default()
+            };
+            let out = transform_image(DynamicImage::ImageRgb8(img.clone()), &options);
+            assert_eq!(out.dimensions(), (400, 240));
+            out.pixels().filter(|p| p.0[0] < 128).count()
+        };
+
+        // The center crop only shows the white part
+        assert_eq!(crop(false), 0);
+        assert!(crop(true) > 400 * 200 / 2 / 2);
+    }
+}
@@ -0,0 +1,174 @@
+//! Deferring refreshes while the Pi is busy.
+//!
+//! Dithering and the panel update keep the single core of a Pi Zero busy
+//! for a while. During an `apt upgrade` or another heavy job that makes
+//! both the refresh and the web UI crawl, and drives up the SoC
+//! temperature. With a load or temperature limit set, a scheduled refresh
+//! is put off in short steps while the system is above it, up to a limit
+//! after which it runs anyway.
+
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 1-minute load average, first field of this file
+const LOADAVG_PATH: &str = "/proc/loadavg";
+
+/// SoC temperature in millidegrees Celsius
+const TEMPERATURE_PATH: &str = "/sys/class/thermal/thermal_zone0/temp";
+
+/// Limits above which scheduled refreshes are deferred
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoadGuardConfig {
+    /// Defer while the 1-minute load average is above this (unset = off)
+    #[serde(default)]
+    pub max_load: Option<f32>,
+
+    /// Defer while the CPU temperature in °C is above this (unset = off)
+    #[serde(default)]
+    pub max_temp_c: Option<f32>,
+
+    /// Seconds between checks while deferring
+    #[serde(default = "default_retry_secs")]
+    pub retry_secs: u32,
+
+    /// Longest deferral in minutes; the refresh then runs anyway
+    #[serde(default = "default_max_defer_min")]
+    pub max_defer_min: u32,
+}
+
+fn default_retry_secs() -> u32 {
+    30
+}
+
+fn default_max_defer_min() -> u32 {
+    10
+}
+
+impl Default for LoadGuardConfig {
+    fn default() -> Self {
+        Self {
+            max_load: None,
+            max_temp_c: None,
+            retry_secs: default_retry_secs(),
+            max_defer_min: default_max_defer_min(),
+        }
+    }
+}
+
+impl LoadGuardConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self
+            .max_load
+            .is_some_and(|load| !(load > 0.0 && load <= 64.0))
+        {
+            return Err(ConfigError::ValidationError(
+                "load_guard.max_load must be between 0 and 64".to_string(),
+            ));
+        }
+        if self
+            .max_temp_c
+            .is_some_and(|temp| !(30.0..=100.0).contains(&temp))
+        {
+            return Err(ConfigError::ValidationError(
+                "load_guard.max_temp_c must be between 30 and 100".to_string(),
+            ));
+        }
+        if !(5..=600).contains(&self.retry_secs) {
+            return Err(ConfigError::ValidationError(
+                "load_guard.retry_secs must be between 5 and 600".to_string(),
+            ));
+        }
+        if self.max_defer_min > 120 {
+            return Err(ConfigError::ValidationError(
+                "load_guard.max_defer_min must be between 0 and 120".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether any limit is set
+    pub fn enabled(&self) -> bool {
+        self.max_load.is_some() || self.max_temp_c.is_some()
+    }
+
+    /// Time between checks while deferring
+    pub fn retry(&self) -> Duration {
+        Duration::from_secs(self.retry_secs as u64)
+    }
+
+    /// Longest deferral
+    pub fn max_defer(&self) -> Duration {
+        Duration::from_secs(self.max_defer_min as u64 * 60)
+    }
+
+    /// Why a refresh should wait right now, if it should
+    pub fn busy_reason(&self) -> Option<String> {
+        let load = self.max_load.and_then(|_| load_average());
+        let temp = self.max_temp_c.and_then(|_| cpu_temperature());
+        self.reason_for(load, temp)
+    }
+
+    /// Why a refresh should wait with these readings, if it should
+    pub fn reason_for(&self, load: Option<f32>, temp: Option<f32>) -> Option<String> {
+        let load = self
+            .max_load
+            .zip(load)
+            .filter(|(max, load)| load > max)
+            .map(|(max, load)| format!("load {:.2} above {:.2}", load, max));
+        let temp = self
+            .max_temp_c
+            .zip(temp)
+            .filter(|(max, temp)| temp > max)
+            .map(|(max, temp)| format!("CPU at {:.1} °C, above {:.1} °C", temp, max));
+        match (load, temp) {
+            (Some(load), Some(temp)) => Some(format!("{}, {}", load, temp)),
+            (load, temp) => load.or(temp),
+        }
+    }
+}
+
+/// 1-minute load average, if the system reports one
+fn load_average() -> Option<f32> {
+    let loadavg = std::fs::read_to_string(LOADAVG_PATH).ok()?;
+    loadavg.split_whitespace().next()?.parse().ok()
+}
+
+/// CPU temperature in °C, if the system reports one
+fn cpu_temperature() -> Option<f32> {
+    let millidegrees: f32 = std::fs::read_to_string(TEMPERATURE_PATH)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(millidegrees / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_guard_names_the_exceeded_limit() {
+        let guard = LoadGuardConfig {
+            max_load: Some(2.0),
+            max_temp_c: Some(70.0),
+            ..Default::default()
+        };
+
+        assert_eq!(guard.reason_for(Some(1.5), Some(60.0)), None);
+        assert_eq!(
+            guard.reason_for(Some(3.25), Some(60.0)).as_deref(),
+            Some("load 3.25 above 2.00")
+        );
+        assert!(
+            guard
+                .reason_for(Some(1.0), Some(75.0))
+                .unwrap()
+                .contains("75.0 °C")
+        );
+        // Sensors that cannot be read do not hold refreshes back
+        assert_eq!(guard.reason_for(None, None), None);
+    }
+}
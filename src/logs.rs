@@ -0,0 +1,262 @@
+//! In-memory log buffer and log output.
+//!
+//! Keeps the most recent log lines so they can be retrieved over HTTP
+//! (debug bundle, `GET /api/v1/logs`) without shell access to the
+//! journal, and passes new lines on to clients following the log. The
+//! buffer has its own filter: it keeps "info" lines by default, while
+//! stdout (and with it the journal on the SD card) only gets warnings and
+//! errors.
+//!
+//! Both the buffer and stdout mask resolved secrets (see `secrets`).
+//!
+//! Filter directives can be added at runtime (`PUT /api/v1/logs/filter`),
+//! e.g. `display=debug` while troubleshooting the panel. They apply to
+//! both outputs until changed again or the service restarts.
+
+use crate::secrets;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::reload;
+
+/// Number of lines kept
+pub const CAPACITY: usize = 500;
+
+/// New lines queued per follower; a follower that falls further behind
+/// skips lines
+const FOLLOW_QUEUE: usize = 100;
+
+/// Target of this crate's events
+const CRATE: &str = env!("CARGO_CRATE_NAME");
+
+/// Directives added at runtime to the filter of every output
+static DIRECTIVES: Mutex<String> = Mutex::new(String::new());
+
+/// Outputs whose filter follows [`set_directives`]
+static FILTERS: Mutex<Vec<RuntimeFilter>> = Mutex::new(Vec::new());
+
+/// Filter directives that could not be applied
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("Invalid log filter \"{directives}\": {reason}")]
+    Invalid { directives: String, reason: String },
+}
+
+/// Filter of one output
+struct RuntimeFilter {
+    /// Directives the output started with
+    base: String,
+    /// Replaces the filter; false once the output is gone
+    reload: Box<dyn Fn(EnvFilter) -> bool + Send>,
+}
+
+/// One recorded log event
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct LogLine {
+    /// Local time of the event (RFC 3339)
+    pub timestamp: String,
+    /// Level name ("ERROR", "WARN", ...)
+    pub level: String,
+    /// Message followed by any extra fields as `key=value`
+    pub message: String,
+}
+
+impl std::fmt::Display for LogLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:>5} {}", self.timestamp, self.level, self.message)
+    }
+}
+
+static BUFFER: Lazy<Mutex<VecDeque<LogLine>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+/// New lines, for followers
+static LIVE: Lazy<broadcast::Sender<LogLine>> = Lazy::new(|| broadcast::channel(FOLLOW_QUEUE).0);
+
+/// Recorded lines, oldest first
+pub fn recent() -> Vec<LogLine> {
+    BUFFER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Recorded lines, oldest first, and a receiver for every line after them
+pub fn follow() -> (Vec<LogLine>, broadcast::Receiver<LogLine>) {
+    // Lines are recorded under the lock, so none is missed or repeated
+    let buffer = BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+    (buffer.iter().cloned().collect(), LIVE.subscribe())
+}
+
+/// Filter for an output logging `level` (or what `RUST_LOG` sets), plus
+/// the directives set at runtime
+pub fn runtime_filter<S: Subscriber>(level: &str) -> reload::Layer<EnvFilter, S> {
+    let base = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|env| EnvFilter::try_new(env).is_ok())
+        .unwrap_or_else(|| format!("{}={}", CRATE, level));
+
+    let directives = DIRECTIVES.lock().unwrap_or_else(|e| e.into_inner());
+    let filter = build_filter(&base, &directives).unwrap_or_else(|_| EnvFilter::new(&base));
+    let (layer, handle) = reload::Layer::new(filter);
+    FILTERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(RuntimeFilter {
+            base,
+            reload: Box::new(move |filter| handle.reload(filter).is_ok()),
+        });
+    layer
+}
+
+/// Directives currently added at runtime, comma-separated
+pub fn directives() -> String {
+    DIRECTIVES.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Add `directives` (e.g. `display=debug,reqwest=info`) to the filter of
+/// every output, replacing the ones set before
+///
+/// A bare module name like `display` also names the module of this
+/// crate. Empty directives restore the filters of the start.
+pub fn set_directives(directives: &str) -> Result<(), FilterError> {
+    let directives = directives
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut current = DIRECTIVES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut filters = FILTERS.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Nothing is changed unless every output accepts the directives
+    let invalid = |e: tracing_subscriber::filter::ParseError| FilterError::Invalid {
+        directives: directives.clone(),
+        reason: e.to_string(),
+    };
+    build_filter("", &directives).map_err(invalid)?;
+    let mut built = filters
+        .iter()
+        .map(|filter| build_filter(&filter.base, &directives).map_err(invalid))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+    // Outputs that are gone (a dropped subscriber) are forgotten
+    filters.retain(|filter| built.next().is_some_and(|new| (filter.reload)(new)));
+
+    *current = directives;
+    Ok(())
+}
+
+/// Filter of `base` and the runtime `directives`
+fn build_filter(
+    base: &str,
+    directives: &str,
+) -> Result<EnvFilter, tracing_subscriber::filter::ParseError> {
+    let mut all: Vec<String> = base
+        .split(',')
+        .filter(|d| !d.is_empty())
+        .map(String::from)
+        .collect();
+    for directive in directives.split(',').filter(|d| !d.is_empty()) {
+        all.push(directive.to_string());
+        let target = directive.split(['[', '=']).next().unwrap_or_default();
+        let module = !target.is_empty()
+            && !target.contains("::")
+            && target != CRATE
+            && target.parse::<LevelFilter>().is_err();
+        if module {
+            all.push(format!("{}::{}", CRATE, directive));
+        }
+    }
+    EnvFilter::builder().parse(all.join(","))
+}
+
+/// Tracing layer that records events into the buffer
+pub struct BufferLayer;
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let line = LogLine {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            level: event.metadata().level().to_string(),
+            message: secrets::redact(&(visitor.message + &visitor.fields)).into_owned(),
+        };
+
+        let mut buffer = BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+        if buffer.len() == CAPACITY {
+            buffer.pop_front();
+        }
+        // No followers is not an error
+        let _ = LIVE.send(line.clone());
+        buffer.push_back(line);
+    }
+}
+
+/// Stdout writer for the fmt layer that masks secrets
+pub struct RedactedStdout;
+
+impl<'a> MakeWriter<'a> for RedactedStdout {
+    type Writer = RedactedWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactedWriter(std::io::stdout())
+    }
+}
+
+/// Writer returned by [`RedactedStdout`]
+///
+/// The fmt layer writes each event in one call, so secrets are never
+/// split across writes.
+pub struct RedactedWriter(std::io::Stdout);
+
+impl std::io::Write for RedactedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(secrets::redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Collects the message and remaining fields of an event
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
@@ -6,18 +6,46 @@
 //! - Provides a web interface for configuration
 //! - Runs as a systemd service with graceful shutdown
 
+mod arbiter;
+mod cli;
+mod completions;
 mod config;
+mod crash;
+mod demo;
+mod discovery;
 mod display;
+mod history;
 mod image_proc;
+mod load_guard;
+mod logs;
+mod memory;
+mod message;
+mod mirror;
+mod network;
+mod notifications;
+mod outputs;
+mod power_save;
+mod render;
+mod schedule;
 mod scheduler;
+mod secrets;
+mod sources;
+mod status;
+mod time_sync;
+mod vacation;
+#[cfg(test)]
+mod testing;
 mod web;
 
-use clap::Parser;
+use anyhow::Context;
+use clap::{CommandFactory, Parser, Subcommand, ValueHint};
 use config::Config;
-use display::DisplayController;
+use display::{DisplayController, PixelFormat};
 use scheduler::Scheduler;
-use tokio::sync::broadcast;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use status::StatusTracker;
+use std::sync::Arc;
+use tokio::sync::{RwLock, broadcast};
+use tracing_subscriber::{Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
 /// Command line arguments
 #[derive(Parser, Debug)]
@@ -25,9 +53,19 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 #[command(about = "E-Paper Display Server for Raspberry Pi Zero W")]
 #[command(version)]
 struct Args {
-    /// Configuration file path
-    #[arg(short, long, default_value = "/opt/epaper-display/config.json")]
-    config: String,
+    /// Configuration file path (default: $EPAPER_CONFIG, /opt/epaper-display/config.json or
+    /// with --container /config/config.json, then ~/.config/epaper-display/config.json)
+    #[arg(short, long, global = true, value_hint = ValueHint::FilePath)]
+    config: Option<String>,
+
+    /// Run in a container: config from the /config volume, panel devices
+    /// only if passed through, otherwise the mock display
+    #[arg(long, global = true)]
+    container: bool,
+
+    /// Directory for crash reports and frame history (default: the config's)
+    #[arg(long = "state-dir", value_name = "DIR", value_hint = ValueHint::DirPath)]
+    state_dir: Option<std::path::PathBuf>,
 
     /// Web server port (overrides config, default: 8888)
     #[arg(long = "http-port")]
@@ -44,28 +82,222 @@ struct Args {
     /// Clear display and exit
     #[arg(long)]
     clear: bool,
+
+    /// Use an in-memory mock display instead of the panel (no hardware needed)
+    #[arg(long = "mock-display")]
+    mock_display: bool,
+
+    /// Accept raw panel controller commands on `POST /api/v1/debug/epd`
+    #[arg(long = "debug-epd")]
+    debug_epd: bool,
+
+    /// Write the panel's SPI and GPIO traffic up to the first full refresh to FILE
+    #[arg(long = "spi-trace", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    spi_trace: Option<String>,
+
+    /// Use all cores: multi-threaded runtime and parallel dithering (Pi Zero 2 W, Pi 3/4/5)
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    parallel: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-/// Using current_thread runtime for single-core Pi Zero W
-/// This reduces memory overhead and avoids thread synchronization costs
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> anyhow::Result<()> {
+impl Args {
+    /// Configuration file: `--config`, or the one looked up for this
+    /// environment
+    fn config_path(&self) -> String {
+        self.config
+            .clone()
+            .unwrap_or_else(|| config::default_config_path(self.container))
+    }
+}
+
+/// Subcommands; all but `replay-trace`, `render` and `completions` are
+/// sent to a running server, this device's unless `push` names another
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show a message instead of the scheduled content for a while
+    Message {
+        /// Text to show
+        #[arg(required_unless_present = "cancel")]
+        text: Option<String>,
+
+        /// Minutes until the schedule resumes (default: message_duration_min)
+        #[arg(long = "duration", conflicts_with = "cancel")]
+        duration_min: Option<u32>,
+
+        /// Remove the current message and resume the schedule
+        #[arg(long)]
+        cancel: bool,
+    },
+
+    /// Show an image file on a display for a while, like a message
+    Push {
+        /// Display to send to, as host or host:port (default: this device)
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Image file to show
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: String,
+
+        /// Minutes until the schedule resumes (default: message_duration_min)
+        #[arg(long = "duration")]
+        duration_min: Option<u32>,
+
+        /// Account on the display; the password is read from EPAPER_PASSWORD
+        #[arg(long)]
+        user: Option<String>,
+    },
+
+    /// Send an SPI trace recorded with --spi-trace to the panel again (stop the service first)
+    ReplayTrace {
+        /// Trace file
+        #[arg(value_hint = ValueHint::FilePath)]
+        trace: String,
+    },
+
+    /// Transform and dither an image as the display would, and save the preview
+    Render {
+        /// Image file to render
+        #[arg(long = "in", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        input: String,
+
+        /// Preview image to write (format from the extension, e.g. .png)
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String,
+    },
+
+    /// Print a completion script for bash, zsh, fish, elvish or PowerShell
+    Completions {
+        /// Shell to complete in
+        shell: completions::Shell,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
+    // Printed before logging starts, so no log line ends up in the script
+    if let Some(Command::Completions { shell }) = args.command {
+        print!("{}", completions::generate(shell, Args::command()));
+        return Ok(());
+    }
+
+    // Keep the allocator from spreading freed memory over many arenas
+    memory::tune();
+
+    runtime(&args)?.block_on(run(args))
+}
+
+/// Using current_thread runtime for single-core Pi Zero W
+/// This reduces memory overhead and avoids thread synchronization costs.
+/// Multi-core Pis can opt into the multi-threaded runtime with `--parallel`.
+fn runtime(args: &Args) -> std::io::Result<tokio::runtime::Runtime> {
+    #[cfg(feature = "parallel")]
+    if args.parallel {
+        image_proc::set_parallel(true);
+        return tokio::runtime::Builder::new_multi_thread().enable_all().build();
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = args;
+    tokio::runtime::Builder::new_current_thread().enable_all().build()
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
     // Initialize logging
     init_logging(args.verbose);
 
+    if let Some(command) = &args.command {
+        return run_command(&args, command).await;
+    }
+
     tracing::info!("Starting E-Paper Display Server");
+    let config_path = args.config_path();
+    if args.container {
+        tracing::info!("Container mode, config {}", config_path);
+    }
+    #[cfg(feature = "parallel")]
+    if args.parallel {
+        tracing::info!("Parallel pipeline on {} threads", rayon::current_num_threads());
+    }
+
+    // Record this start and pick up the report of any previous crash
+    if let Some(dir) = &args.state_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create state directory {}", dir.display()))?;
+        config::set_state_dir(dir.clone());
+    }
+    let state_dir = config::state_dir(&config_path);
+    let restart = crash::RestartState::record_start(&state_dir);
+    let last_crash = crash::CrashReport::take(&state_dir);
+    if let Some(report) = &last_crash {
+        tracing::warn!(
+            "Previous crash at {}: {} (start #{})",
+            report.timestamp,
+            report.message,
+            restart.restart_count
+        );
+    }
+    let status = Arc::new(StatusTracker::new(&restart, last_crash).with_state_dir(&state_dir));
+
+    // Power the panel down and leave a crash report if anything panics
+    crash::install_panic_hook(state_dir);
 
     // Load configuration
-    let config = Config::load(&args.config).unwrap_or_else(|e| {
-        tracing::warn!("Failed to load config from {}: {}", args.config, e);
+    let config = Config::load(&config_path).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config from {}: {}", config_path, e);
         tracing::info!("Using default configuration");
         Config::default()
     });
 
-    // Initialize display controller
-    let display = DisplayController::new();
+    if let Some(path) = &args.spi_trace {
+        display::trace::start(path, config.panel.as_str())
+            .map_err(|e| anyhow::anyhow!("Cannot create SPI trace {}: {}", path, e))?;
+        tracing::info!(
+            "Recording SPI trace to {} until the first full refresh",
+            path
+        );
+    }
+
+    // Initialize display controller; builds without hardware support
+    // (non-ARM targets, `hardware` feature off) can only use the mock
+    if !cfg!(hardware) && !args.mock_display {
+        tracing::warn!("Built without hardware support, falling back to the mock display");
+    }
+    let panel_access = if args.mock_display || !cfg!(hardware) {
+        Vec::new()
+    } else {
+        display::access::check()
+    };
+    if !panel_access.is_empty() {
+        // One-shot commands are about the panel, so they fail instead
+        if args.test || args.clear {
+            anyhow::bail!(access_report(&panel_access, args.container));
+        }
+        for line in access_report(&panel_access, args.container).lines() {
+            tracing::error!("{}", line.trim());
+        }
+        tracing::warn!(
+            "Panel not accessible, falling back to the mock display: the web interface \
+             and previews work, the panel is not driven"
+        );
+    } else if args.container && cfg!(hardware) && !args.mock_display {
+        tracing::info!("Panel devices mapped into the container");
+    }
+    let mock = args.mock_display || !cfg!(hardware) || !panel_access.is_empty();
+    status.set_mock_display(mock);
+    let display = if mock {
+        tracing::warn!("Using mock display backend, the panel will not be driven");
+        DisplayController::mock(config.panel).0
+    } else {
+        DisplayController::new(config.panel)
+            .with_it8951(config.it8951.clone())
+            .with_spectra6(config.spectra6.clone())
+            .with_spi(config.spi.clone())
+    };
 
     // Handle one-shot commands
     if args.test {
@@ -89,10 +321,46 @@ async fn main() -> anyhow::Result<()> {
 
     // Create web server
     let port = args.http_port.unwrap_or(config.web_port);
-    let web_server = web::WebServer::new(config, display, args.config.clone());
+    if args.debug_epd {
+        tracing::warn!("Raw panel commands enabled (--debug-epd), do not leave this on");
+    }
+    let web_server = web::WebServer::new(config, display, config_path.clone(), Arc::clone(&status))
+        .with_debug_epd(args.debug_epd);
+
+    // Subscribe to pushed notifications (idle unless configured)
+    #[cfg(feature = "notifications")]
+    let notifications_handle = tokio::spawn(
+        notifications::Subscriber::new(
+            web_server.config(),
+            web_server.processor(),
+            web_server.arbiter(),
+        )
+        .run(),
+    );
+    #[cfg(not(feature = "notifications"))]
+    if web_server.config().read().await.notifications.provider.is_some() {
+        tracing::warn!("Built without the notifications feature, ignoring notifications.provider");
+    }
+
+    // Answer other displays looking for this one
+    let discovery_handle = web_server
+        .config()
+        .read()
+        .await
+        .peer_discovery
+        .then(|| tokio::spawn(discovery::Responder::new(Arc::clone(&status), port).run()));
+
+    // Reload the config file on SIGHUP
+    let reload_handle = tokio::spawn(reload_on_sighup(
+        web_server.config(),
+        Arc::clone(&status),
+        config_path,
+    ));
 
     // Create scheduler
-    let scheduler = Scheduler::new(web_server.config(), web_server.processor());
+    let scheduler = Scheduler::new(web_server.config(), web_server.processor(), status)
+        .with_arbiter(web_server.arbiter())
+        .with_outputs(web_server.outputs());
 
     // Spawn scheduler task
     let scheduler_shutdown = shutdown_tx.subscribe();
@@ -114,6 +382,12 @@ async fn main() -> anyhow::Result<()> {
 
     // Send shutdown to all tasks
     let _ = shutdown_tx.send(());
+    reload_handle.abort();
+    if let Some(handle) = discovery_handle {
+        handle.abort();
+    }
+    #[cfg(feature = "notifications")]
+    notifications_handle.abort();
 
     // Wait for tasks to complete with timeout
     tokio::select! {
@@ -137,19 +411,166 @@ async fn main() -> anyhow::Result<()> {
 /// Initialize tracing/logging
 ///
 /// Default level is "warn" to minimize SD card wear from log writes.
-/// Use --verbose flag for "debug" level during development/troubleshooting.
+/// The in-memory buffer behind `/logs` costs no writes and keeps "info"
+/// lines as well. Use --verbose flag for "debug" level during
+/// development/troubleshooting. RUST_LOG, if set, applies to both, and
+/// both follow the directives set with `PUT /api/v1/logs/filter`.
 fn init_logging(verbose: bool) {
-    let level = if verbose { "debug" } else { "warn" };
-
-    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| format!("rpizerow_epaper_display={}", level).into());
+    let (level, buffer_level) = if verbose { ("debug", "debug") } else { ("warn", "info") };
 
     tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_subscriber::fmt::layer().with_target(false))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_writer(logs::RedactedStdout)
+                .with_filter(logs::runtime_filter(level)),
+        )
+        .with(logs::BufferLayer.with_filter(logs::runtime_filter(buffer_level)))
         .init();
 }
 
+/// Run a command against the server already running on this device
+async fn run_command(args: &Args, command: &Command) -> anyhow::Result<()> {
+    let config_path = args.config_path();
+    let config = Config::load(&config_path).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config from {}: {}", config_path, e);
+        Config::default()
+    });
+    let port = args.http_port.unwrap_or(config.web_port);
+
+    match command {
+        Command::Message { cancel: true, .. } => cli::cancel_message(&config, port).await,
+        Command::Message {
+            text, duration_min, ..
+        } => {
+            let text = text.as_deref().unwrap_or_default();
+            cli::post_message(&config, port, text, *duration_min).await
+        }
+        Command::Push {
+            host,
+            file,
+            duration_min,
+            user,
+        } => {
+            let login = user.as_deref().map(cli::Login::from_env).transpose()?;
+            cli::push_image(&config, host, port, file, *duration_min, login).await
+        }
+        Command::ReplayTrace { trace } => replay_trace(&config, trace, args.container),
+        Command::Render { input, out } => render(&config, input, out),
+        Command::Completions { .. } => unreachable!("completions are printed in main"),
+    }
+}
+
+/// Render the image at `input` for the configured panel into `out`
+///
+/// Runs the refresh pipeline without a panel, so content can be checked
+/// before it is deployed.
+fn render(config: &Config, input: &str, out: &str) -> anyhow::Result<()> {
+    let bytes = std::fs::read(input).with_context(|| format!("Cannot read {}", input))?;
+    let download_config = image_proc::download::DownloadConfig {
+        max_memory: config.max_image_memory(),
+        ..Default::default()
+    };
+    let img = image_proc::download::decode_image(bytes.into(), &download_config)?;
+
+    // Spec of the configured panel, as the server would drive it
+    let spec = config.panel.configured_spec(&config.it8951);
+    let preview = image_proc::render_preview(config, img, PixelFormat::packed4(spec.palette));
+    preview
+        .save(out)
+        .with_context(|| format!("Cannot write {}", out))?;
+    println!(
+        "Rendered {} for the {} ({}x{}) to {}",
+        input,
+        spec.model,
+        preview.width(),
+        preview.height(),
+        out
+    );
+    Ok(())
+}
+
+/// Send the steps of the SPI trace at `path` to the panel
+fn replay_trace(config: &Config, path: &str, container: bool) -> anyhow::Result<()> {
+    let events = display::trace::load(path)?;
+    if cfg!(hardware) {
+        let problems = display::access::check();
+        if !problems.is_empty() {
+            anyhow::bail!(access_report(&problems, container));
+        }
+    }
+    println!("Replaying {} steps of {}", events.len(), path);
+    let summary = display::trace::replay(&events, &config.spi)?;
+    println!(
+        "Sent {} bytes in {} steps",
+        summary.bytes_sent, summary.steps
+    );
+    if summary.read_mismatches > 0 {
+        println!(
+            "{} reads returned other bytes than recorded (see the log)",
+            summary.read_mismatches
+        );
+    }
+    Ok(())
+}
+
+/// Error message listing why the panel's devices cannot be opened
+fn access_report(problems: &[display::access::AccessProblem], container: bool) -> String {
+    let mut report = String::from("Cannot access the panel");
+    for problem in problems {
+        report.push_str("\n  ");
+        if container {
+            report.push_str(&problem.in_container());
+        } else {
+            report.push_str(&problem.to_string());
+        }
+    }
+    report
+}
+
+/// Reload the config file into the shared config on every SIGHUP
+///
+/// Follows the convention of other daemons, so `systemctl reload` or
+/// configuration management can apply an edited file without a restart.
+/// An unreadable or invalid file is logged and the current config kept.
+async fn reload_on_sighup(config: Arc<RwLock<Config>>, status: Arc<StatusTracker>, path: String) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sighup = signal(SignalKind::hangup()).expect("Failed to setup SIGHUP handler");
+
+    while sighup.recv().await.is_some() {
+        tracing::info!("Received SIGHUP, reloading {}", path);
+        if let Err(e) = reload_config(&config, &status, &path).await {
+            tracing::error!("Failed to reload config, keeping the current one: {}", e);
+        }
+    }
+}
+
+/// Replace the shared config with the contents of `path`
+///
+/// The shared config is left untouched if the file cannot be loaded.
+async fn reload_config(
+    config: &RwLock<Config>,
+    status: &StatusTracker,
+    path: &str,
+) -> Result<(), config::ConfigError> {
+    let mut new = Config::load(path)?;
+
+    let mut config = config.write().await;
+    let restart = new.keep_restart_settings(&config);
+    if !restart.is_empty() {
+        tracing::warn!(
+            "Changed settings take effect after a restart: {}",
+            restart.join(", ")
+        );
+    }
+    *config = new;
+    status.set_device_name(config.device_name());
+    status.set_restart_required(&restart);
+    tracing::info!("Config reloaded");
+    Ok(())
+}
+
 /// Wait for shutdown signals (SIGTERM, SIGINT)
 async fn wait_for_shutdown() {
     use tokio::signal::unix::{signal, SignalKind};
@@ -166,3 +587,39 @@ async fn wait_for_shutdown() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reload_replaces_shared_config() {
+        let path = std::env::temp_dir().join(format!("epaper-reload-{}.json", std::process::id()));
+        let path_str = path.display().to_string();
+        let config = RwLock::new(Config::default());
+        let status = StatusTracker::default();
+
+        let edited = Config {
+            rotation: 180,
+            web_port: 9999,
+            device_name: "Hallway".to_string(),
+            ..Config::default()
+        };
+        edited.save(&path).unwrap();
+        reload_config(&config, &status, &path_str).await.unwrap();
+        assert_eq!(config.read().await.rotation, 180);
+        assert_eq!(status.snapshot().device_name, "Hallway");
+
+        // The web server keeps its port until a restart, the file keeps the new one
+        assert_eq!(config.read().await.web_port, Config::default().web_port);
+        assert_eq!(status.snapshot().restart_required, ["web_port"]);
+        config.read().await.save(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap().web_port, 9999);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(reload_config(&config, &status, &path_str).await.is_err());
+        assert_eq!(config.read().await.rotation, 180);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
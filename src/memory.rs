@@ -0,0 +1,55 @@
+//! Returning freed memory to the OS.
+//!
+//! A refresh allocates a few large buffers (download, decoded image,
+//! working copies) and frees them again. glibc keeps most of that freed
+//! memory in its heaps for reuse, so a long-running server settles at a
+//! resident size well above what it needs between refreshes. After each
+//! refresh the heaps are trimmed, and at startup the number of malloc
+//! arenas (one per thread by default, up to 8 per core) is capped, since
+//! each arena holds on to memory of its own.
+//!
+//! Other C libraries (musl) return memory by themselves; there this is a
+//! no-op.
+
+/// Malloc arenas allowed; the display thread and the blocking pool (DNS)
+/// share them with the runtime thread
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+const MAX_ARENAS: libc::c_int = 2;
+
+/// Cap the number of malloc arenas; call before spawning threads
+pub fn tune() {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    // SAFETY: mallopt only changes allocator settings
+    unsafe {
+        libc::mallopt(libc::M_ARENA_MAX, MAX_ARENAS);
+    }
+}
+
+/// Give memory freed by a refresh back to the OS
+pub fn release() {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        let before = resident_kb();
+        // SAFETY: malloc_trim only releases free heap memory
+        unsafe {
+            libc::malloc_trim(0);
+        }
+        if let (Some(before), Some(after)) = (before, resident_kb()) {
+            tracing::debug!(
+                "Released memory, resident size {} KB -> {} KB",
+                before,
+                after
+            );
+        }
+    }
+}
+
+/// Resident set size of the process in KB
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn resident_kb() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size.max(0) as u64 / 1024)
+}
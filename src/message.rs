@@ -0,0 +1,51 @@
+//! Kiosk messages.
+//!
+//! A message posted with `epaper-display message "Back at 3pm"` or
+//! `POST /api/v1/message` replaces the scheduled content for a while
+//! (`message_duration_min` unless the request says otherwise). It is a
+//! manual override for the [content arbiter](crate::arbiter): the
+//! scheduler leaves it on the panel until it expires and then shows the
+//! scheduled content again, and alerts take precedence over it. The expiry
+//! is reported in the status, and `DELETE /api/v1/message` ends the
+//! message early.
+//!
+//! An image pushed with `epaper-display push` or `POST /api/v1/image` is
+//! shown the same way.
+
+use crate::arbiter::{Override, Priority};
+use crate::sources::SourceConfig;
+use crate::sources::pushed::PushedImage;
+use crate::sources::text::{TextAlign, TextSource};
+use chrono::Local;
+use std::time::Duration;
+
+/// Longest message accepted, in characters
+pub const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Manual override showing `text` for `duration`
+pub fn new(text: &str, duration: Duration) -> Override {
+    let until = Local::now() + duration;
+    Override {
+        priority: Priority::Manual,
+        label: format!("kiosk message until {}", until.format("%H:%M")),
+        source: SourceConfig::Text(TextSource {
+            text: text.trim().to_string(),
+            size: 0,
+            color: None,
+            background: None,
+            align: TextAlign::Center,
+        }),
+        until: Some(until),
+    }
+}
+
+/// Manual override showing a pushed `image` for `duration`
+pub fn image(image: PushedImage, duration: Duration) -> Override {
+    let until = Local::now() + duration;
+    Override {
+        priority: Priority::Manual,
+        label: format!("pushed image until {}", until.format("%H:%M")),
+        source: SourceConfig::Pushed(image),
+        until: Some(until),
+    }
+}
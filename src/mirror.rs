@@ -0,0 +1,560 @@
+//! Mirror groups: several panels showing the same content.
+//!
+//! One display is the leader: it fetches, renders and dithers the content
+//! as usual and pushes the finished frame to its followers over HTTP.
+//! Followers skip their own schedule and show what they are sent, as is:
+//! they need the same panel model, size and mounting.
+//!
+//! A push has two steps, so a flaky Wi-Fi link costs retries rather than
+//! the frame:
+//!
+//! 1. The frame is uploaded in [`CHUNK_SIZE`] chunks
+//!    (`PUT /api/v1/mirror/chunks/{sha256}/{index}`), each with its own
+//!    SHA-256. The follower keeps the chunks that arrived intact, and
+//!    `GET /api/v1/mirror/chunks/{sha256}` tells the leader which are
+//!    missing, so a broken upload resumes instead of starting over. A frame
+//!    the follower already has is not sent again.
+//! 2. `POST /api/v1/mirror/frame` names the uploaded frame and a start time
+//!    a few seconds ahead, so the leader and all followers start their
+//!    refresh together. Small frames can also be sent whole in its body.
+
+use crate::config::ConfigError;
+use crate::image_proc::download::{DEFAULT_USER_AGENT, sha256_hex};
+use crate::secrets;
+use bytes::Bytes;
+use chrono::{DateTime, Local, TimeZone};
+use once_cell::sync::Lazy;
+use reqwest::header::{HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinSet;
+
+/// Time between the upload and the refresh of all panels, for the
+/// followers to receive the start time
+pub const PUSH_LEAD: Duration = Duration::from_secs(5);
+
+/// Timeout of each request to a follower
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bytes per uploaded chunk; a 7.3" frame (192 KB) takes 12
+pub const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Times the leader asks a follower for missing chunks and sends them
+/// before giving up on it for this frame
+const UPLOAD_ROUNDS: u32 = 5;
+
+/// Pause before another round, times the round number
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Frames a follower keeps, complete or not; older ones are dropped
+const MAX_UPLOADS: usize = 2;
+
+/// Longest a follower waits for the start time; a time further ahead
+/// means the clocks disagree
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// Largest frame accepted from a leader
+pub const MAX_FRAME_BYTES: usize = 8 * 1024 * 1024;
+
+/// Path followers receive frames at
+pub const FRAME_PATH: &str = "/api/v1/mirror/frame";
+
+/// Path followers receive chunks at, followed by `/{sha256}/{index}`
+pub const CHUNKS_PATH: &str = "/api/v1/mirror/chunks";
+
+/// Headers describing a pushed frame
+pub const HEADER_WIDTH: &str = "x-frame-width";
+pub const HEADER_HEIGHT: &str = "x-frame-height";
+pub const HEADER_MODEL: &str = "x-panel-model";
+/// Start of the refresh, Unix time in milliseconds
+pub const HEADER_SHOW_AT: &str = "x-show-at";
+/// SHA-256 of an uploaded frame, instead of sending it in the body
+pub const HEADER_FRAME_SHA256: &str = "x-frame-sha256";
+
+/// Headers of an uploaded chunk
+pub const HEADER_FRAME_SIZE: &str = "x-frame-size";
+pub const HEADER_CHUNK_SHA256: &str = "x-chunk-sha256";
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+/// Part a display plays in a mirror group
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorRole {
+    /// Not in a group
+    #[default]
+    Off,
+    /// Renders the content and pushes it to `followers`
+    Leader,
+    /// Shows the frames pushed by the leader instead of its own schedule
+    Follower,
+}
+
+/// Mirror group settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    #[serde(default)]
+    pub role: MirrorRole,
+
+    /// Web UI addresses of the followers, e.g. `http://hallway.local:8888`
+    /// (leader only)
+    #[serde(default)]
+    pub followers: Vec<String>,
+
+    /// Shared secret of the group; the leader sends it, followers only
+    /// accept frames carrying it. May be a `${ENV:...}` / `${FILE:...}`
+    /// reference
+    #[serde(default)]
+    pub token: String,
+}
+
+impl MirrorConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.role == MirrorRole::Off {
+            return Ok(());
+        }
+        if self.token.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "mirror.token is required for a mirror group".to_string(),
+            ));
+        }
+        if self.role == MirrorRole::Leader {
+            if self.followers.is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "mirror.followers must list at least one follower".to_string(),
+                ));
+            }
+            for follower in &self.followers {
+                let valid = reqwest::Url::parse(follower)
+                    .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+                if !valid {
+                    return Err(ConfigError::ValidationError(format!(
+                        "mirror.followers: '{}' is not an http(s) URL",
+                        follower
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether this display shows the leader's frames instead of its own
+    pub fn is_follower(&self) -> bool {
+        self.role == MirrorRole::Follower
+    }
+
+    /// Whether `authorization` (the header value) carries the group token
+    pub fn authorizes(&self, authorization: Option<&str>) -> bool {
+        let token = match secrets::resolve(&self.token) {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::warn!("Cannot check the mirror group token: {}", e);
+                return false;
+            }
+        };
+        !token.is_empty()
+            && authorization
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|sent| {
+                    secrets::constant_time_eq(sent.trim().as_bytes(), token.as_bytes())
+                })
+    }
+}
+
+/// Send a frame to the followers
+///
+/// Uploads to all followers at once, then tells those that got the whole
+/// frame to show it [`PUSH_LEAD`] from now and returns that time. Followers
+/// that cannot be reached are logged and left behind; if none got the
+/// frame, or there is no token, the start time is now. Requests carry the
+/// configured `user_agent`.
+pub async fn push(
+    config: &MirrorConfig,
+    user_agent: &HeaderValue,
+    model: &str,
+    width: u32,
+    height: u32,
+    frame: &[u8],
+) -> DateTime<Local> {
+    let token = match secrets::resolve(&config.token) {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!("Frame not sent to the mirror group: {}", e);
+            return Local::now();
+        }
+    };
+    let frame = Bytes::copy_from_slice(frame);
+    let sha256 = sha256_hex(&frame);
+
+    let mut uploads = JoinSet::new();
+    for follower in &config.followers {
+        let follower = Follower {
+            base: follower.trim_end_matches('/').to_string(),
+            token: token.clone(),
+            user_agent: user_agent.clone(),
+        };
+        let (frame, sha256) = (frame.clone(), sha256.clone());
+        uploads.spawn(async move {
+            let result = follower.upload(&sha256, &frame).await;
+            (follower, result)
+        });
+    }
+    let mut ready = Vec::new();
+    while let Some(done) = uploads.join_next().await {
+        match done {
+            Ok((follower, Ok(()))) => ready.push(follower),
+            Ok((follower, Err(e))) => {
+                tracing::warn!("Failed to send frame to {}: {}", follower.base, e)
+            }
+            Err(e) => tracing::warn!("Frame upload aborted: {}", e),
+        }
+    }
+    if ready.is_empty() {
+        return Local::now();
+    }
+
+    let show_at = Local::now() + PUSH_LEAD;
+    let mut commits = JoinSet::new();
+    for follower in ready {
+        let request = CLIENT
+            .post(format!("{}{}", follower.base, FRAME_PATH))
+            .bearer_auth(&follower.token)
+            .header(USER_AGENT, &follower.user_agent)
+            .header(HEADER_WIDTH, width)
+            .header(HEADER_HEIGHT, height)
+            .header(HEADER_MODEL, model)
+            .header(HEADER_SHOW_AT, show_at.timestamp_millis())
+            .header(HEADER_FRAME_SHA256, &sha256);
+        commits.spawn(async move {
+            // Repeating it is harmless, and pointless once the panels refresh
+            let mut result = send(request.try_clone().expect("no streamed body")).await;
+            while result.is_err() && Local::now() < show_at {
+                tokio::time::sleep(RETRY_DELAY).await;
+                result = send(request.try_clone().expect("no streamed body")).await;
+            }
+            (follower.base, result)
+        });
+    }
+    while let Some(done) = commits.join_next().await {
+        match done {
+            Ok((base, Ok(_))) => tracing::debug!("Frame sent to {}", base),
+            Ok((base, Err(e))) => tracing::warn!("Failed to send frame to {}: {}", base, e),
+            Err(e) => tracing::warn!("Frame push aborted: {}", e),
+        }
+    }
+    show_at
+}
+
+/// Follower as seen from the leader
+struct Follower {
+    /// Web UI address without trailing slash
+    base: String,
+    token: String,
+    user_agent: HeaderValue,
+}
+
+impl Follower {
+    /// Upload `frame` in chunks, resuming until the follower has them all
+    async fn upload(&self, sha256: &str, frame: &[u8]) -> Result<(), String> {
+        let url = format!("{}{}/{}", self.base, CHUNKS_PATH, sha256);
+        let mut error = String::new();
+        // The last round only checks what the round before sent
+        for round in 0..=UPLOAD_ROUNDS {
+            if round > 0 {
+                tokio::time::sleep(RETRY_DELAY * round).await;
+            }
+            let received = match self.received(&url, frame.len()).await {
+                Ok(received) => received,
+                Err(e) => {
+                    error = e;
+                    continue;
+                }
+            };
+            let missing: Vec<usize> = (0..chunk_count(frame.len()))
+                .filter(|index| !received.contains(index))
+                .collect();
+            if missing.is_empty() {
+                return Ok(());
+            }
+            if round == UPLOAD_ROUNDS {
+                break;
+            }
+            if round > 0 {
+                tracing::debug!("Resending {} chunks to {}", missing.len(), self.base);
+            }
+            error = format!("{} chunks missing", missing.len());
+            for index in missing {
+                let chunk = &frame[index * CHUNK_SIZE..frame.len().min((index + 1) * CHUNK_SIZE)];
+                let request = CLIENT
+                    .put(format!("{}/{}", url, index))
+                    .bearer_auth(&self.token)
+                    .header(USER_AGENT, &self.user_agent)
+                    .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                    .header(HEADER_FRAME_SIZE, frame.len())
+                    .header(HEADER_CHUNK_SHA256, sha256_hex(chunk))
+                    .body(Bytes::copy_from_slice(chunk));
+                if let Err(e) = send(request).await {
+                    // Wait for the link to come back before going on
+                    error = format!("chunk {}: {}", index, e);
+                    break;
+                }
+            }
+        }
+        Err(error)
+    }
+
+    /// Chunks of the upload the follower has
+    async fn received(&self, url: &str, size: usize) -> Result<Vec<usize>, String> {
+        let response = CLIENT
+            .get(url)
+            .bearer_auth(&self.token)
+            .header(USER_AGENT, &self.user_agent)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let body = check(response).await?;
+        let upload: UploadState = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        // A different frame under the same name is replaced chunk by chunk
+        Ok(if upload.size == size {
+            upload.received
+        } else {
+            Vec::new()
+        })
+    }
+}
+
+/// Send `request`, failing on an error status
+async fn send(request: reqwest::RequestBuilder) -> Result<String, String> {
+    check(request.send().await.map_err(|e| e.to_string())?).await
+}
+
+/// Body of `response`, or its status and body as error
+async fn check(response: reqwest::Response) -> Result<String, String> {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status.is_success() {
+        Ok(body)
+    } else {
+        Err(format!("{} {}", status, body.trim()))
+    }
+}
+
+/// Number of chunks of a frame of `size` bytes
+fn chunk_count(size: usize) -> usize {
+    size.div_ceil(CHUNK_SIZE)
+}
+
+/// Failure to store or assemble an uploaded frame
+#[derive(Error, Debug, PartialEq)]
+pub enum UploadError {
+    #[error("Frame {0} was not uploaded")]
+    Unknown(String),
+
+    #[error("Frame name must be its SHA-256 in hex")]
+    InvalidName,
+
+    #[error("Frame of {0} bytes is empty or too large")]
+    InvalidSize(usize),
+
+    #[error("Frame of {size} bytes has no chunk {index}")]
+    NoSuchChunk { size: usize, index: usize },
+
+    #[error("Chunk {index} must have {expected} bytes, got {got}")]
+    ChunkSize {
+        index: usize,
+        expected: usize,
+        got: usize,
+    },
+
+    #[error("Chunk {0} does not match its checksum, send it again")]
+    ChunkChecksum(usize),
+
+    #[error("Frame is missing {0} chunks")]
+    Incomplete(usize),
+
+    #[error("Assembled frame does not match its checksum, upload it again")]
+    FrameChecksum,
+}
+
+/// Chunks received of an uploaded frame
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UploadState {
+    /// Frame size in bytes
+    pub size: usize,
+    /// Bytes per chunk; the last chunk may be shorter
+    pub chunk_size: usize,
+    /// Indexes of the chunks received intact, ascending
+    pub received: Vec<usize>,
+}
+
+/// Frame being uploaded to a follower
+struct Upload {
+    sha256: String,
+    size: usize,
+    chunks: Vec<Option<Bytes>>,
+    /// Start time of the last request to show it, to ignore repeats
+    shown_at: Option<i64>,
+}
+
+/// Frames uploaded to a follower, newest last
+#[derive(Default)]
+pub struct Uploads {
+    uploads: Mutex<VecDeque<Upload>>,
+}
+
+impl Uploads {
+    /// Chunks received of the frame `sha256`
+    pub fn state(&self, sha256: &str) -> Option<UploadState> {
+        let uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        let upload = uploads.iter().find(|u| u.sha256 == sha256)?;
+        Some(UploadState {
+            size: upload.size,
+            chunk_size: CHUNK_SIZE,
+            received: (0..upload.chunks.len())
+                .filter(|&i| upload.chunks[i].is_some())
+                .collect(),
+        })
+    }
+
+    /// Store chunk `index` of the frame `sha256` of `size` bytes, if it
+    /// matches `checksum` (SHA-256 in hex)
+    pub fn put(
+        &self,
+        sha256: &str,
+        size: usize,
+        index: usize,
+        checksum: &str,
+        chunk: Bytes,
+    ) -> Result<(), UploadError> {
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(UploadError::InvalidName);
+        }
+        if size == 0 || size > MAX_FRAME_BYTES {
+            return Err(UploadError::InvalidSize(size));
+        }
+        if index >= chunk_count(size) {
+            return Err(UploadError::NoSuchChunk { size, index });
+        }
+        let expected = (size - index * CHUNK_SIZE).min(CHUNK_SIZE);
+        if chunk.len() != expected {
+            return Err(UploadError::ChunkSize {
+                index,
+                expected,
+                got: chunk.len(),
+            });
+        }
+        if !sha256_hex(&chunk).eq_ignore_ascii_case(checksum.trim()) {
+            return Err(UploadError::ChunkChecksum(index));
+        }
+
+        let sha256 = sha256.to_ascii_lowercase();
+        let mut uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        uploads.retain(|u| u.sha256 != sha256 || u.size == size);
+        if !uploads.iter().any(|u| u.sha256 == sha256) {
+            if uploads.len() >= MAX_UPLOADS {
+                uploads.pop_front();
+            }
+            uploads.push_back(Upload {
+                sha256: sha256.clone(),
+                size,
+                chunks: vec![None; chunk_count(size)],
+                shown_at: None,
+            });
+        }
+        let upload = uploads
+            .iter_mut()
+            .find(|u| u.sha256 == sha256)
+            .expect("added above");
+        upload.chunks[index] = Some(chunk);
+        Ok(())
+    }
+
+    /// The complete frame `sha256`, to be shown at `show_at`
+    ///
+    /// `None` if it was already asked for with the same start time, i.e.
+    /// the leader repeated a request whose answer got lost.
+    pub fn take(&self, sha256: &str, show_at: i64) -> Result<Option<Vec<u8>>, UploadError> {
+        let sha256 = sha256.to_ascii_lowercase();
+        let mut uploads = self.uploads.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(position) = uploads.iter().position(|u| u.sha256 == sha256) else {
+            return Err(UploadError::Unknown(sha256));
+        };
+        let upload = &mut uploads[position];
+        let missing = upload.chunks.iter().filter(|c| c.is_none()).count();
+        if missing > 0 {
+            return Err(UploadError::Incomplete(missing));
+        }
+        if upload.shown_at == Some(show_at) {
+            return Ok(None);
+        }
+        let frame: Vec<u8> = upload.chunks.iter().flatten().flatten().copied().collect();
+        if sha256_hex(&frame) != sha256 {
+            uploads.remove(position);
+            return Err(UploadError::FrameChecksum);
+        }
+        upload.shown_at = Some(show_at);
+        Ok(Some(frame))
+    }
+}
+
+/// Time to wait for the start time `show_at` (Unix milliseconds)
+///
+/// A time past or too far ahead (clocks out of sync) means no waiting.
+pub fn wait_until(show_at: i64) -> Duration {
+    let Some(show_at) = Local.timestamp_millis_opt(show_at).single() else {
+        return Duration::ZERO;
+    };
+    let wait = (show_at - Local::now()).to_std().unwrap_or_default();
+    if wait > MAX_WAIT {
+        tracing::warn!(
+            "Frame start time is {:?} ahead, showing it now; is the clock synchronized?",
+            wait
+        );
+        return Duration::ZERO;
+    }
+    wait
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_group_token_is_authorized() {
+        let follower = |token: &str| MirrorConfig {
+            role: MirrorRole::Follower,
+            followers: Vec::new(),
+            token: token.to_string(),
+        };
+
+        let config = follower("group-secret");
+        for (authorization, authorized) in [
+            (Some("Bearer group-secret"), true),
+            (Some("Bearer group-secret "), true),
+            (Some("Bearer group-secreT"), false),
+            (Some("Bearer group-secret-2"), false),
+            (Some("Basic group-secret"), false),
+            (None, false),
+        ] {
+            assert_eq!(
+                config.authorizes(authorization),
+                authorized,
+                "{:?}",
+                authorization
+            );
+        }
+        assert!(!follower("").authorizes(Some("Bearer ")));
+    }
+}
@@ -0,0 +1,70 @@
+//! Network availability probe.
+//!
+//! Wi-Fi on the Pi Zero W drops out now and then. The scheduler probes the
+//! network between refreshes and refreshes right away once it comes back,
+//! instead of showing stale content for the rest of a long interval.
+
+use std::time::Duration;
+
+/// Kernel routing table
+const ROUTE_TABLE: &str = "/proc/net/route";
+
+/// Host name of the device, as set by the OS
+const HOSTNAME: &str = "/proc/sys/kernel/hostname";
+
+/// Longest wait for a DNS answer
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether the device has a default route and can resolve `host`
+pub async fn is_online(host: &str) -> bool {
+    if !has_default_route() {
+        tracing::debug!("No default route");
+        return false;
+    }
+
+    match tokio::time::timeout(DNS_TIMEOUT, tokio::net::lookup_host((host, 80))).await {
+        Ok(Ok(mut addrs)) => addrs.next().is_some(),
+        Ok(Err(e)) => {
+            tracing::debug!("Cannot resolve {}: {}", host, e);
+            false
+        }
+        Err(_) => {
+            tracing::debug!("Resolving {} timed out", host);
+            false
+        }
+    }
+}
+
+/// Host name of a URL, for probing
+pub fn host_of(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.trim_matches(['[', ']']).to_string())
+}
+
+/// Host name of the device ("localhost" where it cannot be read)
+pub fn hostname() -> String {
+    std::fs::read_to_string(HOSTNAME)
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Whether the routing table has an active default route
+///
+/// Assumes there is one where the table cannot be read (not Linux).
+fn has_default_route() -> bool {
+    let Ok(table) = std::fs::read_to_string(ROUTE_TABLE) else {
+        return true;
+    };
+
+    // Columns: Iface Destination Gateway Flags ...; RTF_UP = 0x1
+    table.lines().skip(1).any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        fields.len() > 3
+            && fields[1] == "00000000"
+            && u16::from_str_radix(fields[3], 16).is_ok_and(|flags| flags & 0x1 != 0)
+    })
+}
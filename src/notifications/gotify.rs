@@ -0,0 +1,165 @@
+//! Gotify subscription via its WebSocket message stream (`/stream`).
+//!
+//! Only the small part of WebSocket (RFC 6455) the stream needs is
+//! implemented: reading text frames and answering pings.
+
+use super::subscriber::{IDLE_TIMEOUT, Notification, NotificationError, STREAM_CLIENT};
+use base64::Engine;
+use reqwest::StatusCode;
+use reqwest::header::{CONNECTION, UPGRADE};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Largest message accepted; Gotify messages are small
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Message pushed by Gotify
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Subscribe to the stream of the server at `url` with a client token
+pub async fn subscribe<F, Fut>(url: &str, token: &str, show: F) -> Result<(), NotificationError>
+where
+    F: Fn(Notification) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let response = STREAM_CLIENT
+        .get(format!("{}/stream", url))
+        .header(CONNECTION, "Upgrade")
+        .header(UPGRADE, "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", handshake_key())
+        .header("X-Gotify-Key", token)
+        .send()
+        .await?;
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        return Err(NotificationError::Http(response.status().as_u16()));
+    }
+    let mut stream = response.upgrade().await?;
+    tracing::info!("Subscribed to Gotify stream at {}", url);
+
+    let mut message = Vec::new();
+    loop {
+        let (fin, opcode, payload) = tokio::time::timeout(IDLE_TIMEOUT, read_frame(&mut stream))
+            .await
+            .map_err(|_| NotificationError::Idle(IDLE_TIMEOUT.as_secs()))??;
+
+        match opcode {
+            OP_PING => write_frame(&mut stream, OP_PONG, &payload).await?,
+            OP_PONG => {}
+            OP_CLOSE => {
+                let _ = write_frame(&mut stream, OP_CLOSE, &[]).await;
+                return Ok(());
+            }
+            OP_TEXT | OP_CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if message.len() > MAX_MESSAGE_BYTES {
+                    return Err(NotificationError::Protocol("message too large".to_string()));
+                }
+                if fin {
+                    if let Some(notification) = parse_message(&message) {
+                        show(notification).await;
+                    }
+                    message.clear();
+                }
+            }
+            other => {
+                return Err(NotificationError::Protocol(format!(
+                    "unexpected frame type {:#x}",
+                    other
+                )));
+            }
+        }
+    }
+}
+
+fn parse_message(data: &[u8]) -> Option<Notification> {
+    let message: Message = serde_json::from_slice(data)
+        .inspect_err(|e| tracing::warn!("Ignoring unreadable Gotify message: {}", e))
+        .ok()?;
+    Some(Notification {
+        title: message.title,
+        message: message.message,
+    })
+}
+
+/// `Sec-WebSocket-Key`: 16 bytes that only need to differ per connection
+fn handshake_key() -> String {
+    base64::engine::general_purpose::STANDARD.encode(nonce())
+}
+
+/// Bytes for the handshake key and frame masks; these guard against
+/// confused proxies, not attackers, so the clock is random enough
+fn nonce() -> [u8; 16] {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_le_bytes()
+}
+
+/// Read one frame: (final fragment, opcode, unmasked payload)
+async fn read_frame<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> Result<(bool, u8, Vec<u8>), NotificationError> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).await?;
+    let fin = head[0] & 0x80 != 0;
+    let opcode = head[0] & 0x0f;
+    let masked = head[1] & 0x80 != 0;
+
+    let len = match head[1] & 0x7f {
+        126 => stream.read_u16().await? as u64,
+        127 => stream.read_u64().await?,
+        len => len as u64,
+    };
+    if len > MAX_MESSAGE_BYTES as u64 {
+        return Err(NotificationError::Protocol("frame too large".to_string()));
+    }
+
+    let mut mask = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload).await?;
+    if masked {
+        payload
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, b)| *b ^= mask[i % 4]);
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Write one final, masked frame (clients must mask) of a control payload
+async fn write_frame<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    opcode: u8,
+    payload: &[u8],
+) -> Result<(), NotificationError> {
+    // Control frames carry at most 125 bytes
+    let payload = &payload[..payload.len().min(125)];
+    let nonce = nonce();
+    let mask = [nonce[0], nonce[1], nonce[2], nonce[3]];
+
+    let mut frame = Vec::with_capacity(6 + payload.len());
+    frame.push(0x80 | opcode);
+    frame.push(0x80 | payload.len() as u8);
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    stream.write_all(&frame).await?;
+    stream.flush().await?;
+    Ok(())
+}
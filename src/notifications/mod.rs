@@ -0,0 +1,93 @@
+//! Pushed notifications.
+//!
+//! With `notifications.provider` set, a background task subscribes to an
+//! ntfy topic (server-sent events) or to the message stream of a Gotify
+//! server (WebSocket) and shows every incoming notification on the panel
+//! for `notifications.duration_min` minutes. A notification is a manual
+//! override for the [content arbiter](crate::arbiter), like a kiosk
+//! message: it replaces an earlier message or notification, waits behind
+//! alerts, and the scheduled content returns once it expires.
+//!
+//! The subscriber is left out of builds without the `notifications`
+//! feature; the settings are kept so the config file stays the same.
+
+#[cfg(feature = "notifications")]
+mod gotify;
+#[cfg(feature = "notifications")]
+mod ntfy;
+#[cfg(feature = "notifications")]
+mod subscriber;
+
+#[cfg(feature = "notifications")]
+pub use subscriber::Subscriber;
+
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
+
+/// Push service to subscribe to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PushProvider {
+    /// ntfy topic (ntfy.sh or self-hosted)
+    Ntfy,
+    /// Gotify server
+    Gotify,
+}
+
+/// Pushed notification settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NotificationsConfig {
+    /// Service to subscribe to (none = off)
+    #[serde(default)]
+    pub provider: Option<PushProvider>,
+    /// ntfy topic URL (e.g., "https://ntfy.sh/mytopic") or Gotify server URL
+    #[serde(default)]
+    pub url: String,
+    /// ntfy access token or Gotify client token (optional for ntfy)
+    #[serde(default)]
+    pub token: String,
+    /// Minutes a notification stays on the panel
+    #[serde(default = "default_duration_min")]
+    pub duration_min: u32,
+}
+
+fn default_duration_min() -> u32 {
+    10
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            url: String::new(),
+            token: String::new(),
+            duration_min: default_duration_min(),
+        }
+    }
+}
+
+impl NotificationsConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.duration_min < 1 || self.duration_min > 1440 {
+            return Err(ConfigError::ValidationError(
+                "notifications.duration_min must be between 1 and 1440".to_string(),
+            ));
+        }
+        let Some(provider) = self.provider else {
+            return Ok(());
+        };
+        let url = self.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "notifications.url must start with http:// or https://".to_string(),
+            ));
+        }
+        if provider == PushProvider::Gotify && self.token.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "notifications.token is required for Gotify (a client token)".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! ntfy subscription via server-sent events (`<topic URL>/sse`).
+
+use super::subscriber::{IDLE_TIMEOUT, Notification, NotificationError, STREAM_CLIENT};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde::Deserialize;
+
+/// Event sent by ntfy (`open`, `keepalive`, `message`, ...)
+#[derive(Debug, Deserialize)]
+struct Event {
+    event: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Subscribe to the topic at `url` and pass each message to `show`
+pub async fn subscribe<F, Fut>(url: &str, token: &str, show: F) -> Result<(), NotificationError>
+where
+    F: Fn(Notification) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut request = STREAM_CLIENT
+        .get(format!("{}/sse", url))
+        .header(ACCEPT, "text/event-stream");
+    if !token.is_empty() {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let mut response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(NotificationError::Http(response.status().as_u16()));
+    }
+    tracing::info!("Subscribed to ntfy topic {}", url);
+
+    let mut buffer = Vec::new();
+    loop {
+        let chunk = tokio::time::timeout(IDLE_TIMEOUT, response.chunk())
+            .await
+            .map_err(|_| NotificationError::Idle(IDLE_TIMEOUT.as_secs()))??;
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(notification) = parse_line(line.trim_end()) {
+                show(notification).await;
+            }
+        }
+    }
+}
+
+/// Notification in an SSE `data:` line, if it carries a message
+fn parse_line(line: &str) -> Option<Notification> {
+    let data = line.strip_prefix("data:")?.trim_start();
+    let event: Event = serde_json::from_str(data)
+        .inspect_err(|e| tracing::warn!("Ignoring unreadable ntfy event: {}", e))
+        .ok()?;
+    (event.event == "message").then_some(Notification {
+        title: event.title,
+        message: event.message,
+    })
+}
@@ -0,0 +1,189 @@
+//! Subscription to the configured push service.
+
+use super::{NotificationsConfig, PushProvider, gotify, ntfy};
+use crate::arbiter::{ContentArbiter, Override};
+use crate::config::Config;
+use crate::image_proc::ImageProcessor;
+use crate::message::{self, MAX_MESSAGE_CHARS};
+use crate::secrets::{self, SecretError};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// Notification subscription errors
+#[derive(Error, Debug)]
+pub enum NotificationError {
+    #[error("HTTP request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("HTTP error: {0}")]
+    Http(u16),
+
+    #[error("Connection failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid stream: {0}")]
+    Protocol(String),
+
+    #[error("No data for {0} seconds")]
+    Idle(u64),
+
+    #[error("{0}")]
+    Secret(#[from] SecretError),
+}
+
+/// A pushed notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub message: String,
+}
+
+impl Notification {
+    /// Manual override showing the notification for `duration`
+    fn into_override(self, duration: Duration) -> Override {
+        let title = self.title.trim();
+        let body = self.message.trim();
+        let text = match (title.is_empty(), body.is_empty()) {
+            (false, false) => format!("{}\n\n{}", title, body),
+            (false, true) => title.to_string(),
+            _ => body.to_string(),
+        };
+        let text: String = text.chars().take(MAX_MESSAGE_CHARS).collect();
+
+        let mut content = message::new(&text, duration);
+        if let Some(until) = content.until {
+            content.label = format!("notification until {}", until.format("%H:%M"));
+        }
+        content
+    }
+}
+
+/// Client for the subscription streams: no overall timeout, the streams
+/// stay open; silence is detected per read instead
+pub(super) static STREAM_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .pool_max_idle_per_host(0)
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+/// Longest silence before the connection is considered dead; both
+/// services send keepalives more often
+pub(super) const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Subscribes to the configured push service and shows notifications
+pub struct Subscriber {
+    config: Arc<RwLock<Config>>,
+    processor: Arc<ImageProcessor>,
+    arbiter: Arc<ContentArbiter>,
+}
+
+impl Subscriber {
+    /// First delay before reconnecting (doubled per failed attempt)
+    const RETRY_MIN: Duration = Duration::from_secs(5);
+
+    /// Longest delay before reconnecting
+    const RETRY_MAX: Duration = Duration::from_secs(300);
+
+    /// Time between checks for changed settings
+    const SETTINGS_POLL: Duration = Duration::from_secs(30);
+
+    pub fn new(
+        config: Arc<RwLock<Config>>,
+        processor: Arc<ImageProcessor>,
+        arbiter: Arc<ContentArbiter>,
+    ) -> Self {
+        Self {
+            config,
+            processor,
+            arbiter,
+        }
+    }
+
+    /// Stay subscribed, reconnecting after errors and settings changes
+    pub async fn run(self) {
+        let mut retry = Self::RETRY_MIN;
+        loop {
+            let settings = self.config.read().await.notifications.clone();
+            let Some(provider) = settings.provider else {
+                self.settings_changed(&settings).await;
+                continue;
+            };
+
+            let started = Instant::now();
+            let result = tokio::select! {
+                result = self.subscribe(provider, &settings) => result,
+                _ = self.settings_changed(&settings) => {
+                    tracing::info!("Notification settings changed, reconnecting");
+                    retry = Self::RETRY_MIN;
+                    continue;
+                }
+            };
+
+            // A connection that held for a while was not a failed attempt
+            if started.elapsed() > Self::RETRY_MAX {
+                retry = Self::RETRY_MIN;
+            }
+            match result {
+                Ok(()) => tracing::warn!(
+                    "Notification stream closed, reconnecting in {}s",
+                    retry.as_secs()
+                ),
+                Err(e) => tracing::warn!(
+                    "Notification stream failed: {}, reconnecting in {}s",
+                    e,
+                    retry.as_secs()
+                ),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(retry) => {}
+                _ = self.settings_changed(&settings) => {}
+            }
+            retry = (retry * 2).min(Self::RETRY_MAX);
+        }
+    }
+
+    /// Connect and show notifications until the stream ends
+    async fn subscribe(
+        &self,
+        provider: PushProvider,
+        settings: &NotificationsConfig,
+    ) -> Result<(), NotificationError> {
+        let url = secrets::expand(settings.url.trim())?;
+        let url = url.trim_end_matches('/');
+        let token = secrets::resolve(&settings.token)?;
+        let duration = Duration::from_secs(settings.duration_min as u64 * 60);
+        let show = |notification| self.show(notification, duration);
+
+        match provider {
+            PushProvider::Ntfy => ntfy::subscribe(url, &token, show).await,
+            PushProvider::Gotify => gotify::subscribe(url, &token, show).await,
+        }
+    }
+
+    /// Put a notification on the panel
+    async fn show(&self, notification: Notification, duration: Duration) {
+        let content = notification.into_override(duration);
+        let config = content.content_config(&*self.config.read().await);
+        if !self.arbiter.set(content) {
+            return;
+        }
+        if let Err(e) = self.processor.process_and_display(&config).await {
+            tracing::error!("Failed to display notification: {}", e);
+        }
+    }
+
+    /// Resolve once the settings differ from `settings`
+    async fn settings_changed(&self, settings: &NotificationsConfig) {
+        loop {
+            tokio::time::sleep(Self::SETTINGS_POLL).await;
+            if self.config.read().await.notifications != *settings {
+                return;
+            }
+        }
+    }
+}
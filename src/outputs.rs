@@ -0,0 +1,435 @@
+//! Auxiliary GPIO outputs switched on a schedule.
+//!
+//! Besides the panel, a frame can drive a few more loads through spare
+//! GPIO pins, usually through a MOSFET or relay: a frontlight LED strip
+//! that lights the panel in the evening, a fan, a status LED. Each output
+//! is on during its `schedule` periods, given as `HH:MM` or as `sunrise`
+//! and `sunset` at the configured location, and can be switched through
+//! the API. A switch through the API holds until the schedule itself
+//! changes state, so a light turned off early comes on again the next
+//! evening.
+//!
+//! The scheduler brings the outputs in line with the schedule at startup
+//! and at every minute boundary.
+
+use crate::config::{ConfigError, SchedulePeriod};
+use crate::display::gpio::pins;
+#[cfg(not(hardware))]
+use crate::display::no_hardware as rppal;
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike, Utc};
+use rppal::gpio::{Gpio, Level, OutputPin};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Pins the panel uses: the display pins and SPI0 (MOSI, SCLK, MISO)
+const RESERVED_PINS: [u8; 9] = [
+    pins::RST,
+    pins::DC,
+    pins::BUSY,
+    pins::PWR,
+    pins::CS_M,
+    pins::CS_S,
+    10,
+    11,
+    9,
+];
+
+/// Highest GPIO number on the 40-pin header (BCM numbering)
+const MAX_PIN: u8 = 27;
+
+/// Auxiliary outputs and the location for `sunrise` and `sunset`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct OutputsConfig {
+    /// Latitude in degrees, north positive (needed for sunrise/sunset)
+    #[serde(default)]
+    pub latitude: Option<f64>,
+
+    /// Longitude in degrees, east positive (needed for sunrise/sunset)
+    #[serde(default)]
+    pub longitude: Option<f64>,
+
+    /// Outputs switched by the schedule
+    #[serde(default)]
+    pub pins: Vec<GpioOutput>,
+}
+
+/// A GPIO pin switched on and off
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GpioOutput {
+    /// Name used in the API and the log
+    pub name: String,
+
+    /// GPIO number (BCM numbering)
+    pub pin: u8,
+
+    /// The load is on while the pin is low
+    #[serde(default)]
+    pub active_low: bool,
+
+    /// Periods the output is on (empty = only switched through the API)
+    #[serde(default)]
+    pub schedule: Vec<OutputPeriod>,
+}
+
+/// Period an output is on; it spans midnight if it ends before it starts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutputPeriod {
+    /// Switch-on time: `HH:MM`, `sunrise` or `sunset`
+    pub on: String,
+
+    /// Switch-off time: `HH:MM`, `sunrise` or `sunset`
+    pub off: String,
+}
+
+/// Time of day an output is switched at
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SwitchTime {
+    /// Minutes since midnight
+    At(u32),
+    Sunrise,
+    Sunset,
+}
+
+impl SwitchTime {
+    fn parse(value: &str) -> Result<Self, ConfigError> {
+        match value.trim() {
+            "sunrise" => Ok(SwitchTime::Sunrise),
+            "sunset" => Ok(SwitchTime::Sunset),
+            time => SchedulePeriod::parse_time(time).map(SwitchTime::At),
+        }
+    }
+
+    /// Minutes since midnight, given sunrise and sunset of the day (None
+    /// while the sun does not rise or set)
+    fn minutes(self, sun: Option<(u32, u32)>) -> Option<u32> {
+        match self {
+            SwitchTime::At(minutes) => Some(minutes),
+            SwitchTime::Sunrise => sun.map(|(rise, _)| rise),
+            SwitchTime::Sunset => sun.map(|(_, set)| set),
+        }
+    }
+}
+
+impl OutputsConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::ValidationError(message));
+        if self
+            .latitude
+            .is_some_and(|lat| !(-90.0..=90.0).contains(&lat))
+        {
+            return invalid("outputs.latitude must be between -90 and 90".to_string());
+        }
+        if self
+            .longitude
+            .is_some_and(|lon| !(-180.0..=180.0).contains(&lon))
+        {
+            return invalid("outputs.longitude must be between -180 and 180".to_string());
+        }
+
+        for (i, output) in self.pins.iter().enumerate() {
+            if output.name.trim().is_empty() {
+                return invalid("outputs.pins: every output needs a name".to_string());
+            }
+            if output.pin > MAX_PIN {
+                return invalid(format!(
+                    "Output '{}': GPIO {} is not on the header (0-{})",
+                    output.name, output.pin, MAX_PIN
+                ));
+            }
+            if RESERVED_PINS.contains(&output.pin) {
+                return invalid(format!(
+                    "Output '{}': GPIO {} is used by the panel",
+                    output.name, output.pin
+                ));
+            }
+            if let Some(other) = self.pins[..i]
+                .iter()
+                .find(|other| other.name == output.name || other.pin == output.pin)
+            {
+                return invalid(format!(
+                    "Outputs '{}' and '{}' share a name or pin",
+                    other.name, output.name
+                ));
+            }
+            for period in &output.schedule {
+                let on = SwitchTime::parse(&period.on)?;
+                let off = SwitchTime::parse(&period.off)?;
+                let at_sun = [on, off].iter().any(|t| !matches!(t, SwitchTime::At(_)));
+                if at_sun && self.location().is_none() {
+                    return invalid(format!(
+                        "Output '{}': sunrise and sunset need outputs.latitude and outputs.longitude",
+                        output.name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Output called `name`
+    pub fn get(&self, name: &str) -> Option<&GpioOutput> {
+        self.pins.iter().find(|output| output.name == name)
+    }
+
+    /// Whether the schedule has `output` on at `now`
+    pub fn scheduled_on(&self, output: &GpioOutput, now: DateTime<Local>) -> bool {
+        let sun = self
+            .location()
+            .and_then(|(lat, lon)| sun_times(now.date_naive(), lat, lon));
+        let minute = now.hour() * 60 + now.minute();
+        output.schedule.iter().any(|period| {
+            let (Ok(on), Ok(off)) = (
+                SwitchTime::parse(&period.on),
+                SwitchTime::parse(&period.off),
+            ) else {
+                return false;
+            };
+            match (on.minutes(sun), off.minutes(sun)) {
+                (Some(on), Some(off)) if off <= on => minute >= on || minute < off,
+                (Some(on), Some(off)) => minute >= on && minute < off,
+                _ => false,
+            }
+        })
+    }
+
+    fn location(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+}
+
+/// Local sunrise and sunset on `date` in minutes since midnight, or None
+/// during polar day or night
+///
+/// Uses the sunrise equation with the usual correction for refraction and
+/// the size of the sun, good to a minute or two.
+fn sun_times(date: NaiveDate, latitude: f64, longitude: f64) -> Option<(u32, u32)> {
+    // Days since the J2000 epoch (2000-01-01 12:00 UTC)
+    let julian_day = date.num_days_from_ce() as f64 - 730_120.5 + 2_451_545.0;
+    let days = (julian_day - 2_451_545.0 + 0.0008).ceil();
+
+    let mean_solar_noon = days - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_solar_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic = (anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        2_451_545.0 + mean_solar_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic).sin();
+
+    let declination = (ecliptic.sin() * 23.4397_f64.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = ((-0.833_f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+
+    let local_minutes = |julian: f64| {
+        let secs = ((julian - 2_440_587.5) * 86_400.0).round() as i64;
+        let time = Utc.timestamp_opt(secs, 0).single()?.with_timezone(&Local);
+        Some(time.hour() * 60 + time.minute())
+    };
+    Some((
+        local_minutes(transit - hour_angle)?,
+        local_minutes(transit + hour_angle)?,
+    ))
+}
+
+/// State of an output
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputStatus {
+    pub name: String,
+    pub pin: u8,
+    pub on: bool,
+    /// Switched through the API, holding until the schedule changes state
+    pub manual: bool,
+}
+
+/// Switches the configured outputs
+#[derive(Default)]
+pub struct Outputs {
+    states: Mutex<HashMap<String, PinState>>,
+}
+
+/// Pin of an output and what it was set to
+struct PinState {
+    pin_number: u8,
+    /// Opened on first use; None if the GPIO could not be opened
+    pin: Option<OutputPin>,
+    on: bool,
+    /// Scheduled state when switched through the API
+    manual_at: Option<bool>,
+}
+
+impl Outputs {
+    /// Switch every output to its scheduled state, unless switched through
+    /// the API since the schedule last changed state
+    ///
+    /// Outputs no longer configured are released.
+    pub fn apply(&self, config: &OutputsConfig, now: DateTime<Local>) {
+        let mut states = self.states.lock().unwrap();
+        states.retain(|name, state| {
+            config
+                .get(name)
+                .is_some_and(|output| output.pin == state.pin_number)
+        });
+
+        for output in &config.pins {
+            let scheduled = config.scheduled_on(output, now);
+            match states.get_mut(&output.name) {
+                Some(state) => {
+                    if state.manual_at.is_some_and(|at| at != scheduled) {
+                        state.manual_at = None;
+                    }
+                    if state.manual_at.is_none() && state.on != scheduled {
+                        state.switch(output, scheduled);
+                    }
+                }
+                None => {
+                    let mut state = PinState {
+                        pin_number: output.pin,
+                        pin: open(output.pin),
+                        on: !scheduled,
+                        manual_at: None,
+                    };
+                    state.switch(output, scheduled);
+                    states.insert(output.name.clone(), state);
+                }
+            }
+        }
+    }
+
+    /// Switch the output `name` on or off until the schedule changes state
+    ///
+    /// Returns None if there is no such output.
+    pub fn switch(
+        &self,
+        config: &OutputsConfig,
+        name: &str,
+        on: bool,
+        now: DateTime<Local>,
+    ) -> Option<OutputStatus> {
+        let output = config.get(name)?;
+        self.apply(config, now);
+        let mut states = self.states.lock().unwrap();
+        let state = states.get_mut(name)?;
+        state.manual_at = Some(config.scheduled_on(output, now));
+        if state.on != on {
+            state.switch(output, on);
+        }
+        Some(state.status(output))
+    }
+
+    /// State of the configured outputs
+    pub fn statuses(&self, config: &OutputsConfig) -> Vec<OutputStatus> {
+        let states = self.states.lock().unwrap();
+        config
+            .pins
+            .iter()
+            .map(|output| match states.get(&output.name) {
+                Some(state) => state.status(output),
+                None => OutputStatus {
+                    name: output.name.clone(),
+                    pin: output.pin,
+                    on: false,
+                    manual: false,
+                },
+            })
+            .collect()
+    }
+}
+
+impl PinState {
+    fn switch(&mut self, output: &GpioOutput, on: bool) {
+        self.on = on;
+        tracing::info!(
+            "Output '{}' (GPIO {}) {}",
+            output.name,
+            output.pin,
+            if on { "on" } else { "off" }
+        );
+        if let Some(pin) = &mut self.pin {
+            pin.write(if on != output.active_low {
+                Level::High
+            } else {
+                Level::Low
+            });
+        }
+    }
+
+    fn status(&self, output: &GpioOutput) -> OutputStatus {
+        OutputStatus {
+            name: output.name.clone(),
+            pin: output.pin,
+            on: self.on,
+            manual: self.manual_at.is_some(),
+        }
+    }
+}
+
+/// Open GPIO `pin` as an output; the state is still tracked if that fails
+fn open(pin: u8) -> Option<OutputPin> {
+    match Gpio::new().and_then(|gpio| gpio.get(pin)) {
+        Ok(pin) => Some(pin.into_output()),
+        Err(e) => {
+            tracing::warn!("Cannot open GPIO {} for an output: {}", pin, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outputs_follow_the_sun_until_switched() {
+        let at = |day: u32, hour: u32| {
+            Utc.with_ymd_and_hms(2024, 6, day, hour, 0, 0)
+                .unwrap()
+                .with_timezone(&Local)
+        };
+        let mut config = OutputsConfig {
+            latitude: None,
+            longitude: None,
+            pins: vec![GpioOutput {
+                name: "frontlight".to_string(),
+                pin: 23,
+                active_low: false,
+                schedule: vec![OutputPeriod {
+                    on: "sunset".to_string(),
+                    off: "sunrise".to_string(),
+                }],
+            }],
+        };
+        assert!(config.validate().is_err());
+        config.latitude = Some(51.5);
+        config.longitude = Some(0.0);
+        config.validate().unwrap();
+
+        let outputs = Outputs::default();
+        let on = |outputs: &Outputs| outputs.statuses(&config)[0].on;
+        outputs.apply(&config, at(21, 12));
+        assert!(!on(&outputs));
+        outputs.apply(&config, at(21, 23));
+        assert!(on(&outputs));
+
+        // Switched off for the night, back on the next evening
+        let status = outputs.switch(&config, "frontlight", false, at(21, 23));
+        assert!(status.is_some_and(|status| !status.on && status.manual));
+        outputs.apply(&config, at(22, 1));
+        assert!(!on(&outputs));
+        outputs.apply(&config, at(22, 12));
+        outputs.apply(&config, at(22, 23));
+        assert!(on(&outputs));
+        assert!(!outputs.statuses(&config)[0].manual);
+        assert!(outputs.switch(&config, "fan", true, at(22, 23)).is_none());
+
+        config.pins[0].pin = pins::BUSY;
+        assert!(config.validate().is_err());
+    }
+}
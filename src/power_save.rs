@@ -0,0 +1,169 @@
+//! Powering off between refreshes, woken by the RTC.
+//!
+//! E-paper keeps its image without power, so a battery-run photo frame
+//! only needs the Pi while it refreshes. Photo frame HATs carry a PCF8523
+//! or DS3231 real-time clock whose alarm output switches the Pi's power
+//! back on. With power save on, the scheduler sets that alarm to the next
+//! refresh after each scheduled refresh, less the time the Pi needs to
+//! boot, and powers the Pi off.
+//!
+//! The alarm is set through the kernel's RTC driver (`dtoverlay=i2c-rtc,
+//! pcf8523` or `ds3231` in `config.txt`), which also sets the system clock
+//! from the RTC at boot.
+
+use crate::config::ConfigError;
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Power save errors
+#[derive(Error, Debug)]
+pub enum PowerSaveError {
+    #[error("Cannot set the RTC alarm in {path}: {source}")]
+    Alarm {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("Power-off command failed: {0}")]
+    PowerOff(String),
+}
+
+/// Power save settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PowerSaveConfig {
+    /// Power off between refreshes and wake up with the RTC alarm
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Sysfs directory of the RTC whose alarm wakes the Pi
+    #[serde(default = "default_rtc_path")]
+    pub rtc_path: String,
+
+    /// Seconds from power-on to the first refresh; the alarm goes off
+    /// this much before the refresh is due
+    #[serde(default = "default_boot_secs")]
+    pub boot_secs: u32,
+
+    /// Shortest time in minutes until the next refresh worth powering off
+    /// for; the Pi stays on for shorter intervals
+    #[serde(default = "default_min_off_min")]
+    pub min_off_min: u32,
+
+    /// Command that powers the Pi off
+    #[serde(default = "default_power_off_command")]
+    pub power_off_command: Vec<String>,
+}
+
+fn default_rtc_path() -> String {
+    "/sys/class/rtc/rtc0".to_string()
+}
+
+fn default_boot_secs() -> u32 {
+    90
+}
+
+fn default_min_off_min() -> u32 {
+    15
+}
+
+fn default_power_off_command() -> Vec<String> {
+    vec!["systemctl".to_string(), "poweroff".to_string()]
+}
+
+impl Default for PowerSaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rtc_path: default_rtc_path(),
+            boot_secs: default_boot_secs(),
+            min_off_min: default_min_off_min(),
+            power_off_command: default_power_off_command(),
+        }
+    }
+}
+
+impl PowerSaveConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.rtc_path.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "power_save.rtc_path must not be empty".to_string(),
+            ));
+        }
+        if !(10..=900).contains(&self.boot_secs) {
+            return Err(ConfigError::ValidationError(
+                "power_save.boot_secs must be between 10 and 900".to_string(),
+            ));
+        }
+        if !(5..=1440).contains(&self.min_off_min) {
+            return Err(ConfigError::ValidationError(
+                "power_save.min_off_min must be between 5 and 1440".to_string(),
+            ));
+        }
+        if self
+            .power_off_command
+            .first()
+            .is_none_or(|c| c.trim().is_empty())
+        {
+            return Err(ConfigError::ValidationError(
+                "power_save.power_off_command must name a command".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time the RTC wakes the Pi for a refresh due in `interval`, if it is
+    /// worth powering off until then
+    pub fn wake_time(&self, now: DateTime<Local>, interval: Duration) -> Option<DateTime<Local>> {
+        if !self.enabled || interval < Duration::from_secs(self.min_off_min as u64 * 60) {
+            return None;
+        }
+        let interval = TimeDelta::from_std(interval).ok()?;
+        Some(now + interval - TimeDelta::seconds(self.boot_secs as i64))
+    }
+
+    /// Set the RTC alarm to `wake` and power the Pi off
+    ///
+    /// The service is stopped by the power-off like on any shutdown. If
+    /// the alarm cannot be set, the Pi stays on.
+    pub fn power_off_until(&self, wake: DateTime<Local>) -> Result<(), PowerSaveError> {
+        self.set_alarm(wake)?;
+        tracing::info!(
+            "Powering off until {} (RTC alarm)",
+            wake.format("%Y-%m-%d %H:%M:%S")
+        );
+
+        let (program, args) = self
+            .power_off_command
+            .split_first()
+            .ok_or_else(|| PowerSaveError::PowerOff("no command".to_string()))?;
+        let status = std::process::Command::new(program)
+            .args(args)
+            .status()
+            .map_err(|e| PowerSaveError::PowerOff(format!("{}: {}", program, e)))?;
+        if !status.success() {
+            return Err(PowerSaveError::PowerOff(format!("{}: {}", program, status)));
+        }
+        Ok(())
+    }
+
+    /// Program the wake alarm of the RTC
+    ///
+    /// A pending alarm has to be cleared before a new one is accepted.
+    fn set_alarm(&self, wake: DateTime<Local>) -> Result<(), PowerSaveError> {
+        let path = format!("{}/wakealarm", self.rtc_path.trim().trim_end_matches('/'));
+        let write = |value: String| {
+            std::fs::write(&path, value).map_err(|source| PowerSaveError::Alarm {
+                path: path.clone(),
+                source,
+            })
+        };
+        write("0".to_string())?;
+        write(wake.timestamp().to_string())
+    }
+}
@@ -0,0 +1,204 @@
+//! Right-to-left text support.
+//!
+//! Text is stored in logical order; the panel needs it in visual order.
+//! Lines containing Hebrew or Arabic are run through the Unicode
+//! bidirectional algorithm, brackets in right-to-left runs are mirrored,
+//! and Arabic letters are replaced by their joined presentation forms so
+//! fonts without shaping support still draw connected script.
+
+use std::borrow::Cow;
+use unicode_bidi::BidiInfo;
+
+/// Reorder one line of text for display, left to right
+pub(super) fn visual_order(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_rtl_script) {
+        return Cow::Borrowed(text);
+    }
+
+    let shaped = shape_arabic(text);
+    let bidi = BidiInfo::new(&shaped, None);
+    let mut visual = String::with_capacity(shaped.len());
+
+    for para in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(para, para.range.clone());
+        for run in runs {
+            let part = &shaped[run.clone()];
+            if levels[run.start].is_rtl() {
+                visual.extend(part.chars().rev().map(mirror));
+            } else {
+                visual.push_str(part);
+            }
+        }
+    }
+
+    // Paragraph separators are kept by the algorithm; lines have none
+    visual.retain(|c| c != '\n' && c != '\r');
+    Cow::Owned(visual)
+}
+
+/// Hebrew, Arabic and related scripts (incl. presentation forms)
+fn is_rtl_script(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+    )
+}
+
+/// Mirrored counterpart of a bracket drawn in a right-to-left run
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        c => c,
+    }
+}
+
+/// How an Arabic letter connects to its neighbours
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Connects on both sides (isolated, final, initial, medial forms)
+    Dual,
+    /// Connects to the preceding letter only (isolated, final forms)
+    Right,
+}
+
+/// Joining type and isolated presentation form of an Arabic letter
+///
+/// The other forms follow the isolated one: final, then initial and
+/// medial for dual-joining letters.
+fn arabic_forms(c: char) -> Option<(Joining, u32)> {
+    use Joining::*;
+
+    Some(match c {
+        '\u{0621}' => (Right, 0xFE80),
+        '\u{0622}' => (Right, 0xFE81),
+        '\u{0623}' => (Right, 0xFE83),
+        '\u{0624}' => (Right, 0xFE85),
+        '\u{0625}' => (Right, 0xFE87),
+        '\u{0626}' => (Dual, 0xFE89),
+        '\u{0627}' => (Right, 0xFE8D),
+        '\u{0628}' => (Dual, 0xFE8F),
+        '\u{0629}' => (Right, 0xFE93),
+        '\u{062A}'..='\u{062E}' => (Dual, 0xFE95 + (c as u32 - 0x062A) * 4),
+        '\u{062F}'..='\u{0632}' => (Right, 0xFEA9 + (c as u32 - 0x062F) * 2),
+        '\u{0633}'..='\u{063A}' => (Dual, 0xFEB1 + (c as u32 - 0x0633) * 4),
+        '\u{0641}'..='\u{0647}' => (Dual, 0xFED1 + (c as u32 - 0x0641) * 4),
+        '\u{0648}' => (Right, 0xFEED),
+        '\u{0649}' => (Right, 0xFEEF),
+        '\u{064A}' => (Dual, 0xFEF1),
+        // Persian and Urdu letters
+        '\u{067E}' => (Dual, 0xFB56),
+        '\u{0686}' => (Dual, 0xFB7A),
+        '\u{0698}' => (Right, 0xFB8A),
+        '\u{06A9}' => (Dual, 0xFB8E),
+        '\u{06AF}' => (Dual, 0xFB92),
+        '\u{06CC}' => (Dual, 0xFBFC),
+        _ => return None,
+    })
+}
+
+/// Whether `c` lets the following letter connect to it
+fn joins_forward(c: char) -> bool {
+    c == '\u{0640}' || matches!(arabic_forms(c), Some((Joining::Dual, _)))
+}
+
+/// Whether `c` connects to the preceding letter
+fn joins_backward(c: char) -> bool {
+    c == '\u{0640}' || arabic_forms(c).is_some_and(|(_, base)| base != 0xFE80)
+}
+
+/// Isolated form of the lam-alef ligature for an alef variant
+fn lam_alef(alef: char) -> Option<u32> {
+    match alef {
+        '\u{0622}' => Some(0xFEF5),
+        '\u{0623}' => Some(0xFEF7),
+        '\u{0625}' => Some(0xFEF9),
+        '\u{0627}' => Some(0xFEFB),
+        _ => None,
+    }
+}
+
+/// Replace Arabic letters by their contextual presentation forms
+fn shape_arabic(text: &str) -> String {
+    // Vowel marks are drawn separately and do not break joining
+    let letters: Vec<char> = text
+        .chars()
+        .filter(|c| !matches!(c, '\u{064B}'..='\u{065F}' | '\u{0670}'))
+        .collect();
+    let mut shaped = String::with_capacity(text.len());
+
+    let mut i = 0;
+    while i < letters.len() {
+        let c = letters[i];
+        let prev = i.checked_sub(1).map(|p| letters[p]);
+        let connect_prev = prev.is_some_and(joins_forward);
+
+        // Lam followed by alef becomes one ligature glyph
+        if c == '\u{0644}'
+            && let Some(ligature) = letters.get(i + 1).and_then(|&a| lam_alef(a))
+        {
+            let form = ligature + connect_prev as u32;
+            shaped.extend(char::from_u32(form));
+            i += 2;
+            continue;
+        }
+
+        let Some((joining, isolated)) = arabic_forms(c) else {
+            shaped.push(c);
+            i += 1;
+            continue;
+        };
+
+        let connect_next =
+            joining == Joining::Dual && letters.get(i + 1).copied().is_some_and(joins_backward);
+        let offset = match (connect_prev && isolated != 0xFE80, connect_next) {
+            (true, true) => 3,
+            (false, true) => 2,
+            (true, false) => 1,
+            (false, false) => 0,
+        };
+        shaped.extend(char::from_u32(isolated + offset));
+        i += 1;
+    }
+
+    shaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_to_right_text_is_kept() {
+        assert!(matches!(visual_order("Hello (world)"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn hebrew_is_reversed_with_mirrored_brackets() {
+        assert_eq!(visual_order("שלום"), "םולש");
+        assert_eq!(visual_order("(שלום)"), "(םולש)");
+        // Numbers after Hebrew join its run and keep their digit order
+        assert_eq!(visual_order("Hi שלום 42"), "Hi 42 םולש");
+    }
+
+    #[test]
+    fn arabic_letters_are_joined() {
+        // Beh, then lam-alef: initial beh connects to the final ligature
+        assert_eq!(shape_arabic("\u{0628}\u{0644}\u{0627}"), "\u{FE91}\u{FEFC}");
+        // Alef does not connect forward, so the beh after it is isolated
+        assert_eq!(shape_arabic("\u{0627}\u{0628}"), "\u{FE8D}\u{FE8F}");
+        // Vowel marks are dropped without breaking the join
+        assert_eq!(shape_arabic("\u{0628}\u{064E}\u{0628}"), "\u{FE91}\u{FE90}");
+    }
+}
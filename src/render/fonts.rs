@@ -0,0 +1,54 @@
+//! Fonts loaded from disk.
+//!
+//! TTF/OTF files listed in the theme are tried before the built-in bitmap
+//! fonts, either for a different typeface or for scripts the bitmaps lack
+//! (CJK, Hebrew, Arabic, ...). Files are read once and kept in memory
+//! until the configured list changes.
+
+use ab_glyph::FontArc;
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+
+/// Currently loaded fonts and the paths they came from
+#[derive(Default)]
+struct Loaded {
+    paths: Vec<String>,
+    fonts: Vec<FontArc>,
+}
+
+static LOADED: Lazy<RwLock<Loaded>> = Lazy::new(|| RwLock::new(Loaded::default()));
+
+/// Load the configured font files, replacing the previous set
+///
+/// Does nothing when the list is unchanged. Files that cannot be read or
+/// parsed are skipped with a warning; text then falls back to the
+/// built-in fonts.
+pub fn load(paths: &[String]) {
+    if LOADED.read().unwrap_or_else(|e| e.into_inner()).paths == paths {
+        return;
+    }
+
+    let fonts = paths
+        .iter()
+        .filter_map(|path| {
+            let data = std::fs::read(path)
+                .map_err(|e| tracing::warn!("Cannot read font {}: {}", path, e))
+                .ok()?;
+            let font = FontArc::try_from_vec(data)
+                .map_err(|e| tracing::warn!("Invalid font {}: {}", path, e))
+                .ok()?;
+            tracing::info!("Loaded font {}", path);
+            Some(font)
+        })
+        .collect();
+
+    *LOADED.write().unwrap_or_else(|e| e.into_inner()) = Loaded {
+        paths: paths.to_vec(),
+        fonts,
+    };
+}
+
+/// Run `f` with the loaded fonts, in configured order
+pub(super) fn with_fonts<R>(f: impl FnOnce(&[FontArc]) -> R) -> R {
+    f(&LOADED.read().unwrap_or_else(|e| e.into_inner()).fonts)
+}
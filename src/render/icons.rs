@@ -0,0 +1,558 @@
+//! Embedded icon set.
+//!
+//! Small pixel-art icons (weather, battery, Wi-Fi, arrows) bundled in the
+//! binary, so renderers need no icon files or downloads. Icons are looked
+//! up by name and scaled by pixel repetition like the bitmap fonts.
+
+#[cfg(feature = "renderers")]
+use super::{Theme, colors};
+use image::{Rgb, RgbImage};
+
+/// Icon grid size in pixels
+const GRID: u32 = 16;
+
+/// A bundled icon
+///
+/// Each row has one character per pixel: `.` transparent, `K` text color,
+/// `W` background color, `Y`/`O`/`R`/`G`/`B` palette colors (text color
+/// with the mono icon style).
+pub struct Icon {
+    pub name: &'static str,
+    rows: [&'static str; GRID as usize],
+}
+
+/// All bundled icons
+pub static ICONS: &[Icon] = &[
+    Icon {
+        name: "sun",
+        rows: [
+            "................",
+            ".......OO.......",
+            ".......OO.......",
+            "..OO........OO..",
+            "..OOO.YYYY.OOO..",
+            ".....YYYYYY.....",
+            "....YYYYYYYY....",
+            "OO..YYYYYYYY..OO",
+            "OO..YYYYYYYY..OO",
+            "....YYYYYYYY....",
+            ".....YYYYYY.....",
+            "..OOO.YYYY.OOO..",
+            "..OO........OO..",
+            ".......OO.......",
+            ".......OO.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "moon",
+        rows: [
+            "................",
+            ".....YYYY.......",
+            "...YYYYY........",
+            "..YYYYY.........",
+            ".YYYYY..........",
+            ".YYYY...........",
+            "YYYYY...........",
+            "YYYYY...........",
+            "YYYYY...........",
+            "YYYYYY..........",
+            ".YYYYYY.......Y.",
+            ".YYYYYYYY...YYY.",
+            "..YYYYYYYYYYYYY.",
+            "...YYYYYYYYYYY..",
+            ".....YYYYYYY....",
+            "................",
+        ],
+    },
+    Icon {
+        name: "partly_cloudy",
+        rows: [
+            "..O..O..........",
+            "...YYY..........",
+            ".OYYYYYO........",
+            "..YYYYY.........",
+            ".OYYYKKKK.......",
+            "...YKWWWWK......",
+            "..OKWWWWWWKKK...",
+            "..KKWWWWWWWWWK..",
+            ".KWWWWWWWWWWWWK.",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            ".KKKKKKKKKKKKKK.",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "cloud",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            "......KKKK......",
+            ".....KWWWWK.....",
+            "....KWWWWWWKKK..",
+            "..KKKWWWWWWWWWK.",
+            ".KWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            ".KKKKKKKKKKKKKK.",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "fog",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "..KKKKKKKKKKKK..",
+            "................",
+            "KKKKKKKKKKKKKK..",
+            "................",
+            "..KKKKKKKKKKKKKK",
+            "................",
+            "KKKKKKKKKKKKKK..",
+            "................",
+            "..KKKKKKKKKKKK..",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "rain",
+        rows: [
+            "......KKKK......",
+            ".....KWWWWK.....",
+            "....KWWWWWWKKK..",
+            "..KKKWWWWWWWWWK.",
+            ".KWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            ".KKKKKKKKKKKKKK.",
+            "................",
+            "...B....B....B..",
+            "..B....B....B...",
+            "................",
+            "....B....B....B.",
+            "...B....B....B..",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "snow",
+        rows: [
+            "......KKKK......",
+            ".....KWWWWK.....",
+            "....KWWWWWWKKK..",
+            "..KKKWWWWWWWWWK.",
+            ".KWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            ".KKKKKKKKKKKKKK.",
+            "................",
+            "..B.....B.....B.",
+            ".BBB...BBB...BBB",
+            "..B.....B.....B.",
+            "................",
+            ".....B.....B....",
+            "....BBB...BBB...",
+            ".....B.....B....",
+        ],
+    },
+    Icon {
+        name: "thunderstorm",
+        rows: [
+            "......KKKK......",
+            ".....KWWWWK.....",
+            "....KWWWWWWKKK..",
+            "..KKKWWWWWWWWWK.",
+            ".KWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            "KWWWWWWWWWWWWWWK",
+            ".KKKKKKKKKKKKKK.",
+            ".......OO.......",
+            "......OO........",
+            ".....OOOOO......",
+            ".......OO.......",
+            "......OO........",
+            ".....O..........",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "wind",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "..........KKK...",
+            ".........K...K..",
+            ".............K..",
+            "KKKKKKKKKKKKKK..",
+            "................",
+            "KKKKKKKKKKKKKK..",
+            "..............K.",
+            "..............K.",
+            "..........K..K..",
+            "...........KK...",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "battery_full",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            ".KKKKKKKKKKKKK..",
+            ".K...........K..",
+            ".K.GGGGGGGGG.KK.",
+            ".K.GGGGGGGGG.KK.",
+            ".K.GGGGGGGGG.KK.",
+            ".K.GGGGGGGGG.KK.",
+            ".K...........K..",
+            ".KKKKKKKKKKKKK..",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "battery_half",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            ".KKKKKKKKKKKKK..",
+            ".K...........K..",
+            ".K.OOOOO.....KK.",
+            ".K.OOOOO.....KK.",
+            ".K.OOOOO.....KK.",
+            ".K.OOOOO.....KK.",
+            ".K...........K..",
+            ".KKKKKKKKKKKKK..",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "battery_low",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            ".KKKKKKKKKKKKK..",
+            ".K...........K..",
+            ".K.RR........KK.",
+            ".K.RR........KK.",
+            ".K.RR........KK.",
+            ".K.RR........KK.",
+            ".K...........K..",
+            ".KKKKKKKKKKKKK..",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "battery_empty",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            ".KKKKKKKKKKKKK..",
+            ".K...........K..",
+            ".K...........KK.",
+            ".K...........KK.",
+            ".K...........KK.",
+            ".K...........KK.",
+            ".K...........K..",
+            ".KKKKKKKKKKKKK..",
+            "................",
+            "................",
+            "................",
+            "................",
+        ],
+    },
+    Icon {
+        name: "wifi",
+        rows: [
+            "................",
+            "................",
+            ".....KKKKKK.....",
+            "...KKKKKKKKKK...",
+            ".KKK........KKK.",
+            "KK............KK",
+            "....KKKKKKKK....",
+            "..KKK......KKK..",
+            ".KK..........KK.",
+            "......KKKK......",
+            ".....KKKKKK.....",
+            "....KK....KK....",
+            "................",
+            ".......KK.......",
+            ".......KK.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "wifi_medium",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "....KKKKKKKK....",
+            "..KKK......KKK..",
+            ".KK..........KK.",
+            "......KKKK......",
+            ".....KKKKKK.....",
+            "....KK....KK....",
+            "................",
+            ".......KK.......",
+            ".......KK.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "wifi_weak",
+        rows: [
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "................",
+            "......KKKK......",
+            ".....KKKKKK.....",
+            "....KK....KK....",
+            "................",
+            ".......KK.......",
+            ".......KK.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "wifi_off",
+        rows: [
+            "................",
+            ".RR.............",
+            "..RR.KKKKKK.....",
+            "...RRKKKKKKKK...",
+            ".KKKRR......KKK.",
+            "KK...RR.......KK",
+            "....KKRRKKKK....",
+            "..KKK..RR..KKK..",
+            ".KK.....RR...KK.",
+            "......KKKRR.....",
+            ".....KKKKKRR....",
+            "....KK....KRR...",
+            "............RR..",
+            ".......KK....RR.",
+            ".......KK.....RR",
+            "................",
+        ],
+    },
+    Icon {
+        name: "arrow_up",
+        rows: [
+            "................",
+            ".......KK.......",
+            "......KKKK......",
+            ".....KKKKKK.....",
+            "....KKKKKKKK....",
+            "...KKKKKKKKKK...",
+            "..KKKKKKKKKKKK..",
+            ".KKKKKKKKKKKKKK.",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "arrow_down",
+        rows: [
+            "................",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            "......KKKK......",
+            ".KKKKKKKKKKKKKK.",
+            "..KKKKKKKKKKKK..",
+            "...KKKKKKKKKK...",
+            "....KKKKKKKK....",
+            ".....KKKKKK.....",
+            "......KKKK......",
+            ".......KK.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "arrow_left",
+        rows: [
+            "................",
+            ".......K........",
+            "......KK........",
+            ".....KKK........",
+            "....KKKK........",
+            "...KKKKK........",
+            "..KKKKKKKKKKKKK.",
+            ".KKKKKKKKKKKKKK.",
+            ".KKKKKKKKKKKKKK.",
+            "..KKKKKKKKKKKKK.",
+            "...KKKKK........",
+            "....KKKK........",
+            ".....KKK........",
+            "......KK........",
+            ".......K........",
+            "................",
+        ],
+    },
+    Icon {
+        name: "arrow_right",
+        rows: [
+            "................",
+            "........K.......",
+            "........KK......",
+            "........KKK.....",
+            "........KKKK....",
+            "........KKKKK...",
+            ".KKKKKKKKKKKKK..",
+            ".KKKKKKKKKKKKKK.",
+            ".KKKKKKKKKKKKKK.",
+            ".KKKKKKKKKKKKK..",
+            "........KKKKK...",
+            "........KKKK....",
+            "........KKK.....",
+            "........KK......",
+            "........K.......",
+            "................",
+        ],
+    },
+    Icon {
+        name: "warning",
+        rows: [
+            "................",
+            ".......OO.......",
+            "......OOOO......",
+            "......OOOO......",
+            ".....OOKKOO.....",
+            ".....OOKKOO.....",
+            "....OOOKKOOO....",
+            "....OOOKKOOO....",
+            "...OOOOKKOOOO...",
+            "...OOOOOOOOOO...",
+            "..OOOOOKKOOOOO..",
+            "..OOOOOKKOOOOO..",
+            ".OOOOOOOOOOOOOO.",
+            ".OOOOOOOOOOOOOO.",
+            "................",
+            "................",
+        ],
+    },
+];
+
+/// Look up an icon by name
+pub fn get(name: &str) -> Option<&'static Icon> {
+    ICONS.iter().find(|icon| icon.name == name)
+}
+
+/// Theme color for an icon pixel code (None = transparent)
+#[cfg(feature = "renderers")]
+pub(super) fn theme_color(code: u8, theme: &Theme) -> Option<Rgb<u8>> {
+    match code {
+        b'K' => Some(theme.text),
+        b'W' => Some(theme.background),
+        b'Y' => Some(theme.icon(colors::YELLOW)),
+        b'O' => Some(theme.icon(colors::ORANGE)),
+        b'R' => Some(theme.icon(colors::RED)),
+        b'G' => Some(theme.icon(colors::GREEN)),
+        b'B' => Some(theme.icon(colors::BLUE)),
+        _ => None,
+    }
+}
+
+/// Draw `icon` scaled to `size` x `size` with its top-left corner at (x, y)
+///
+/// `paint` maps each pixel code to a color, None leaves it transparent.
+pub(super) fn draw_icon(
+    img: &mut RgbImage,
+    x: i32,
+    y: i32,
+    icon: &Icon,
+    size: u32,
+    paint: impl Fn(u8) -> Option<Rgb<u8>>,
+) {
+    for py in 0..size {
+        let row = icon.rows[(py * GRID / size) as usize].as_bytes();
+        for px in 0..size {
+            let (tx, ty) = (x + px as i32, y + py as i32);
+            if tx < 0 || ty < 0 || tx as u32 >= img.width() || ty as u32 >= img.height() {
+                continue;
+            }
+            if let Some(color) = paint(row[(px * GRID / size) as usize]) {
+                img.put_pixel(tx as u32, ty as u32, color);
+            }
+        }
+    }
+}
+
+/// Bundled icon drawn in place of an emoji or arrow character
+pub(super) fn for_emoji(c: char) -> Option<&'static Icon> {
+    let name = match c {
+        '☀' | '🌞' => "sun",
+        '🌙' | '🌛' | '🌜' => "moon",
+        '⛅' | '🌤' | '🌥' => "partly_cloudy",
+        '☁' => "cloud",
+        '🌫' => "fog",
+        '🌧' | '🌦' | '☔' | '☂' => "rain",
+        '❄' | '🌨' | '☃' | '⛄' => "snow",
+        '⛈' | '🌩' | '⚡' => "thunderstorm",
+        '💨' | '🌬' => "wind",
+        '🔋' => "battery_full",
+        '🪫' => "battery_low",
+        '📶' | '🛜' => "wifi",
+        '↑' | '⬆' => "arrow_up",
+        '↓' | '⬇' => "arrow_down",
+        '←' | '⬅' => "arrow_left",
+        '→' | '➡' => "arrow_right",
+        '⚠' => "warning",
+        _ => return None,
+    };
+    get(name)
+}
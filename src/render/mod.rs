@@ -0,0 +1,271 @@
+//! Built-in renderers.
+//!
+//! Sources that show data rather than a downloaded picture (printer
+//! status, departure boards, ...) draw their layout onto a [`Canvas`]
+//! which then goes through the normal transform and dither pipeline.
+//! Drawing in exact palette colors keeps text free of dither noise.
+
+mod bidi;
+pub mod fonts;
+pub mod icons;
+pub mod text;
+pub mod theme;
+
+#[cfg(feature = "renderers")]
+pub use icons::Icon;
+pub use theme::Theme;
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// Panel palette colors as RGB (these dither to themselves)
+#[allow(dead_code)]
+pub mod colors {
+    use image::Rgb;
+
+    pub const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+    pub const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+    pub const YELLOW: Rgb<u8> = Rgb([255, 255, 0]);
+    pub const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    pub const ORANGE: Rgb<u8> = Rgb([255, 128, 0]);
+    pub const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
+    pub const GREEN: Rgb<u8> = Rgb([0, 255, 0]);
+
+    /// Look up a palette color by name ("black", "red", ...)
+    pub fn from_name(name: &str) -> Option<Rgb<u8>> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "black" => Some(BLACK),
+            "white" => Some(WHITE),
+            "yellow" => Some(YELLOW),
+            "red" => Some(RED),
+            "orange" => Some(ORANGE),
+            "blue" => Some(BLUE),
+            "green" => Some(GREEN),
+            _ => None,
+        }
+    }
+}
+
+/// Drawing surface for built-in renderers
+pub struct Canvas {
+    img: RgbImage,
+}
+
+impl Canvas {
+    /// Create a canvas filled with `background`
+    pub fn new(width: u32, height: u32, background: Rgb<u8>) -> Self {
+        Self {
+            img: RgbImage::from_pixel(width, height, background),
+        }
+    }
+
+    /// Canvas width in pixels
+    pub fn width(&self) -> u32 {
+        self.img.width()
+    }
+
+    /// Canvas height in pixels
+    pub fn height(&self) -> u32 {
+        self.img.height()
+    }
+
+    /// Fill a rectangle, clipped to the canvas
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Rgb<u8>) {
+        let x0 = x.max(0) as u32;
+        let y0 = y.max(0) as u32;
+        let x1 = ((x + width as i32).max(0) as u32).min(self.width());
+        let y1 = ((y + height as i32).max(0) as u32).min(self.height());
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.img.put_pixel(px, py, color);
+            }
+        }
+    }
+
+    /// Draw a rectangle outline of the given thickness
+    pub fn stroke_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        thickness: u32,
+        color: Rgb<u8>,
+    ) {
+        let t = thickness.min(width / 2).min(height / 2);
+        self.fill_rect(x, y, width, t, color);
+        self.fill_rect(x, y + (height - t) as i32, width, t, color);
+        self.fill_rect(x, y, t, height, color);
+        self.fill_rect(x + (width - t) as i32, y, t, height, color);
+    }
+
+    /// Draw text with its top-left corner at (x, y); returns the drawn width
+    pub fn text(&mut self, x: i32, y: i32, text: &str, size: u32, color: Rgb<u8>) -> u32 {
+        text::draw_text(&mut self.img, x, y, text, size, color)
+    }
+
+    /// Draw text horizontally centered on `center_x`
+    pub fn text_centered(&mut self, center_x: i32, y: i32, text: &str, size: u32, color: Rgb<u8>) {
+        let width = text::text_width(text, size) as i32;
+        self.text(center_x - width / 2, y, text, size, color);
+    }
+
+    /// Draw text right-aligned to `right_x`
+    pub fn text_right(&mut self, right_x: i32, y: i32, text: &str, size: u32, color: Rgb<u8>) {
+        let width = text::text_width(text, size) as i32;
+        self.text(right_x - width, y, text, size, color);
+    }
+
+    /// Draw a horizontal progress bar, `fraction` in 0.0..=1.0
+    #[cfg(feature = "renderers")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn progress_bar(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        fraction: f32,
+        fill: Rgb<u8>,
+        border: Rgb<u8>,
+    ) {
+        let border_px = (height / 8).max(1);
+        self.stroke_rect(x, y, width, height, border_px, border);
+
+        let inner = width.saturating_sub(border_px * 2);
+        let filled = (inner as f32 * fraction.clamp(0.0, 1.0)).round() as u32;
+        self.fill_rect(
+            x + border_px as i32,
+            y + border_px as i32,
+            filled,
+            height.saturating_sub(border_px * 2),
+            fill,
+        );
+    }
+
+    /// Fill a triangle given its three corners, clipped to the canvas
+    #[cfg(feature = "renderers")]
+    pub fn fill_triangle(&mut self, points: [(i32, i32); 3], color: Rgb<u8>) {
+        let [(ax, ay), (bx, by), (cx, cy)] = points;
+        let edge = |x0: i32, y0: i32, x1: i32, y1: i32, px: i32, py: i32| {
+            (x1 - x0) as i64 * (py - y0) as i64 - (y1 - y0) as i64 * (px - x0) as i64
+        };
+
+        let x0 = ax.min(bx).min(cx).max(0);
+        let y0 = ay.min(by).min(cy).max(0);
+        let x1 = ax.max(bx).max(cx).min(self.width() as i32 - 1);
+        let y1 = ay.max(by).max(cy).min(self.height() as i32 - 1);
+
+        for py in y0..=y1 {
+            for px in x0..=x1 {
+                let w0 = edge(bx, by, cx, cy, px, py);
+                let w1 = edge(cx, cy, ax, ay, px, py);
+                let w2 = edge(ax, ay, bx, by, px, py);
+                // Inside if all edge functions agree in sign (either winding)
+                if (w0 >= 0 && w1 >= 0 && w2 >= 0) || (w0 <= 0 && w1 <= 0 && w2 <= 0) {
+                    self.img.put_pixel(px as u32, py as u32, color);
+                }
+            }
+        }
+    }
+
+    /// Fill a circle centered on (cx, cy)
+    #[cfg(feature = "renderers")]
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: u32, color: Rgb<u8>) {
+        let r = radius as i32;
+        let r2 = (r as i64) * (r as i64);
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let (px, py) = (cx + dx, cy + dy);
+                if (dx as i64 * dx as i64 + dy as i64 * dy as i64) <= r2
+                    && px >= 0
+                    && py >= 0
+                    && (px as u32) < self.width()
+                    && (py as u32) < self.height()
+                {
+                    self.img.put_pixel(px as u32, py as u32, color);
+                }
+            }
+        }
+    }
+
+    /// Draw a line of the given thickness with rounded ends
+    #[cfg(feature = "renderers")]
+    pub fn line(&mut self, from: (i32, i32), to: (i32, i32), thickness: u32, color: Rgb<u8>) {
+        let (dx, dy) = ((to.0 - from.0) as f32, (to.1 - from.1) as f32);
+        let steps = dx.abs().max(dy.abs()).max(1.0) as i32;
+        let radius = thickness / 2;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let x = from.0 + (dx * t).round() as i32;
+            let y = from.1 + (dy * t).round() as i32;
+            self.fill_circle(x, y, radius, color);
+        }
+    }
+
+    /// Draw a bundled icon in theme colors, `size` pixels square
+    #[cfg(feature = "renderers")]
+    pub fn icon(&mut self, x: i32, y: i32, icon: &Icon, size: u32, theme: &Theme) {
+        icons::draw_icon(&mut self.img, x, y, icon, size, |code| {
+            icons::theme_color(code, theme)
+        });
+    }
+
+    /// Copy an image onto the canvas with its top-left corner at (x, y)
+    pub fn draw_image(&mut self, x: i32, y: i32, img: &RgbImage) {
+        image::imageops::replace(&mut self.img, img, x as i64, y as i64);
+    }
+
+    /// Finish drawing and hand the canvas to the image pipeline
+    pub fn into_image(self) -> DynamicImage {
+        DynamicImage::ImageRgb8(self.img)
+    }
+}
+
+/// Break `text` into lines no wider than `max_width` pixels
+///
+/// Wraps at whitespace and keeps explicit line breaks; single words
+/// that are too long are shortened with [`fit_text`].
+pub fn wrap_text(text: &str, size: u32, max_width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+
+            if text::text_width(&candidate, size) <= max_width {
+                line = candidate;
+            } else {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line = fit_text(word, size, max_width);
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Shorten `text` with "..." so it fits within `max_width` pixels
+pub fn fit_text(text: &str, size: u32, max_width: u32) -> String {
+    if text::text_width(text, size) <= max_width {
+        return text.to_string();
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().collect::<String>() + "...";
+        if text::text_width(&candidate, size) <= max_width {
+            return candidate;
+        }
+    }
+    String::new()
+}
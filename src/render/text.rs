@@ -0,0 +1,348 @@
+//! Bitmap text rendering.
+//!
+//! Uses the ISO-8859 monospace fonts bundled with embedded-graphics, so
+//! no font files are needed on the device. Each character is drawn from
+//! the first font that has it: fonts loaded from disk, then the bitmap
+//! charsets (Latin-1, Latin-ext, Cyrillic, Greek); common emoji are drawn
+//! as bundled icons. Sizes above the largest
+//! bitmap font are produced by integer pixel scaling, which keeps glyph
+//! edges crisp after dithering.
+
+use super::icons::{self, Icon};
+use super::{bidi, colors, fonts};
+use ab_glyph::{Font, FontArc, GlyphId, PxScale, ScaleFont, point};
+use embedded_graphics::{
+    mono_font::{self, MonoFont, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use image::{Rgb, RgbImage};
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// The bitmap font sizes of one charset, smallest first
+macro_rules! charset {
+    ($set:ident) => {
+        [
+            &mono_font::$set::FONT_6X10,
+            &mono_font::$set::FONT_6X13,
+            &mono_font::$set::FONT_9X15,
+            &mono_font::$set::FONT_9X18,
+            &mono_font::$set::FONT_10X20,
+        ]
+    };
+}
+
+/// Charsets tried in order for each character (all share the same sizes)
+const CHARSETS: [[&MonoFont<'static>; 5]; 12] = [
+    charset!(iso_8859_1),
+    charset!(iso_8859_15),
+    charset!(iso_8859_2),
+    charset!(iso_8859_3),
+    charset!(iso_8859_4),
+    charset!(iso_8859_9),
+    charset!(iso_8859_10),
+    charset!(iso_8859_13),
+    charset!(iso_8859_14),
+    charset!(iso_8859_16),
+    charset!(iso_8859_5),
+    charset!(iso_8859_7),
+];
+
+/// Bitmap fonts by line height, smallest first
+const FONTS: [&MonoFont<'static>; 5] = CHARSETS[0];
+
+/// Character no charset contains, used to find the replacement glyph
+const MISSING: char = '\u{FFFF}';
+
+/// Pick the font size index and integer scale that best match a line height
+fn font_for_size(size: u32) -> (usize, u32) {
+    let largest = FONTS.len() - 1;
+    let largest_height = FONTS[largest].character_size.height;
+
+    if size >= largest_height {
+        return (largest, size / largest_height);
+    }
+
+    let index = FONTS
+        .iter()
+        .rposition(|f| f.character_size.height <= size)
+        .unwrap_or(0);
+    (index, 1)
+}
+
+/// One shaped character
+enum Glyph {
+    /// Character drawn from the bitmap charset at this index
+    Char(char, usize),
+    /// Glyph from the loaded font at this index
+    Outline(usize, GlyphId),
+    /// Emoji drawn as an icon two cells wide
+    Icon(&'static Icon),
+}
+
+/// Index of the first charset containing `c`
+fn charset_for(c: char) -> Option<usize> {
+    if c.is_ascii() {
+        return Some(0);
+    }
+    CHARSETS.iter().position(|set| {
+        let mapping = set[0].glyph_mapping;
+        mapping.index(c) != mapping.index(MISSING)
+    })
+}
+
+/// ASCII stand-ins for typographic characters missing from all charsets
+fn substitute(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => "'",
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => "\"",
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+        '\u{2026}' => "...",
+        '\u{2022}' | '\u{2027}' | '\u{2219}' => "\u{B7}",
+        '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' => " ",
+        '\u{2039}' => "<",
+        '\u{203A}' => ">",
+        '\u{2122}' => "TM",
+        '\u{2103}' => "\u{B0}C",
+        '\u{2109}' => "\u{B0}F",
+        _ => return None,
+    })
+}
+
+/// Invisible characters that only affect emoji or script shaping
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{200B}'..='\u{200F}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FEFF}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+    ) || is_combining_mark(c)
+}
+
+/// Turn text into drawable glyphs
+///
+/// Composes accents (NFC) so "e" + combining acute uses the precomposed
+/// glyph, puts right-to-left runs into visual order, drops joiners,
+/// variation selectors and leftover combining marks, and falls back to
+/// ASCII look-alikes or '?' for uncovered characters. Loaded fonts are
+/// tried before the bitmap charsets.
+fn shape(text: &str, fonts: &[FontArc]) -> Vec<Glyph> {
+    let mut glyphs = Vec::with_capacity(text.len());
+
+    let composed: String = text.nfc().collect();
+    for c in bidi::visual_order(&composed).chars() {
+        if is_invisible(c) {
+            continue;
+        }
+
+        let outline = fonts.iter().enumerate().find_map(|(i, font)| {
+            let id = font.glyph_id(c);
+            (id.0 != 0).then_some(Glyph::Outline(i, id))
+        });
+        if let Some(glyph) = outline {
+            glyphs.push(glyph);
+        } else if let Some(set) = charset_for(c) {
+            glyphs.push(Glyph::Char(c, set));
+        } else if let Some(icon) = icons::for_emoji(c) {
+            glyphs.push(Glyph::Icon(icon));
+        } else if let Some(text) = substitute(c) {
+            glyphs.extend(
+                text.chars()
+                    .map(|c| Glyph::Char(c, charset_for(c).unwrap_or(0))),
+            );
+        } else {
+            glyphs.push(Glyph::Char('?', 0));
+        }
+    }
+
+    glyphs
+}
+
+/// Glyphs of one line with their x offsets, and the line width
+struct Line {
+    glyphs: Vec<(i32, Glyph)>,
+    width: u32,
+}
+
+/// Shape `text` and place its glyphs for line height `size`
+fn layout(text: &str, size: u32, fonts: &[FontArc]) -> Line {
+    let (index, scale) = font_for_size(size);
+    let bitmap = FONTS[index];
+    let spacing = bitmap.character_spacing * scale;
+    let advance = (bitmap.character_size.width * scale + spacing) as f32;
+    let px = PxScale::from(size as f32);
+
+    let mut glyphs = Vec::new();
+    let mut pen = 0.0f32;
+    let mut trailing = 0;
+    let mut prev: Option<(usize, GlyphId)> = None;
+
+    for glyph in shape(text, fonts) {
+        let width = match glyph {
+            Glyph::Char(..) => advance,
+            Glyph::Icon(_) => advance * 2.0,
+            Glyph::Outline(font, id) => {
+                let scaled = fonts[font].as_scaled(px);
+                if let Some((prev_font, prev_id)) = prev
+                    && prev_font == font
+                {
+                    pen += scaled.kern(prev_id, id);
+                }
+                scaled.h_advance(id)
+            }
+        };
+        prev = match glyph {
+            Glyph::Outline(font, id) => Some((font, id)),
+            _ => None,
+        };
+        trailing = if prev.is_some() { 0 } else { spacing };
+
+        glyphs.push((pen.round() as i32, glyph));
+        pen += width;
+    }
+
+    Line {
+        glyphs,
+        width: (pen.round() as u32).saturating_sub(trailing),
+    }
+}
+
+/// Width in pixels of `text` rendered at line height `size`
+pub fn text_width(text: &str, size: u32) -> u32 {
+    fonts::with_fonts(|loaded| layout(text, size, loaded).width)
+}
+
+/// Draw `text` with its top-left corner at (x, y); returns the drawn width
+pub fn draw_text(img: &mut RgbImage, x: i32, y: i32, text: &str, size: u32, color: Rgb<u8>) -> u32 {
+    fonts::with_fonts(|loaded| {
+        let (index, scale) = font_for_size(size);
+        let bitmap = FONTS[index];
+        let text_color = Rgb888::new(color[0], color[1], color[2]);
+        let line = layout(text, size, loaded);
+
+        for (offset, glyph) in line.glyphs {
+            let left = x + offset;
+            match glyph {
+                Glyph::Char(c, set) => {
+                    let mut target = ScaledTarget {
+                        img: &mut *img,
+                        origin: Point::new(left, y),
+                        scale: scale as i32,
+                    };
+                    let style = MonoTextStyle::new(CHARSETS[set][index], text_color);
+                    let mut buf = [0u8; 4];
+                    // ScaledTarget is infallible
+                    let _ = Text::with_baseline(
+                        c.encode_utf8(&mut buf),
+                        Point::zero(),
+                        style,
+                        Baseline::Top,
+                    )
+                    .draw(&mut target);
+                }
+                Glyph::Outline(font, id) => {
+                    draw_outline(img, &loaded[font], id, left, y, size, color);
+                }
+                Glyph::Icon(icon) => {
+                    let box_width =
+                        (bitmap.character_size.width * 2 + bitmap.character_spacing) * scale;
+                    let box_height = bitmap.character_size.height * scale;
+                    let icon_size = box_width.min(box_height);
+                    icons::draw_icon(
+                        img,
+                        left + (box_width - icon_size) as i32 / 2,
+                        y + (box_height - icon_size) as i32 / 2,
+                        icon,
+                        icon_size,
+                        |code| match code {
+                            b'K' => Some(color),
+                            b'Y' => Some(colors::YELLOW),
+                            b'O' => Some(colors::ORANGE),
+                            b'R' => Some(colors::RED),
+                            b'G' => Some(colors::GREEN),
+                            b'B' => Some(colors::BLUE),
+                            _ => None,
+                        },
+                    );
+                }
+            }
+        }
+
+        line.width
+    })
+}
+
+/// Draw one outline glyph with its line box top at `y`
+///
+/// Pixels are set where coverage is at least half, without anti-aliasing,
+/// so text stays in the exact palette color.
+fn draw_outline(
+    img: &mut RgbImage,
+    font: &FontArc,
+    id: GlyphId,
+    x: i32,
+    y: i32,
+    size: u32,
+    color: Rgb<u8>,
+) {
+    let px = PxScale::from(size as f32);
+    let baseline = y as f32 + font.as_scaled(px).ascent();
+    let glyph = id.with_scale_and_position(px, point(x as f32, baseline));
+    let Some(outline) = font.outline_glyph(glyph) else {
+        return;
+    };
+
+    let bounds = outline.px_bounds();
+    let (width, height) = (img.width() as i32, img.height() as i32);
+    outline.draw(|gx, gy, coverage| {
+        let px = bounds.min.x as i32 + gx as i32;
+        let py = bounds.min.y as i32 + gy as i32;
+        if coverage >= 0.5 && px >= 0 && py >= 0 && px < width && py < height {
+            img.put_pixel(px as u32, py as u32, color);
+        }
+    });
+}
+
+/// Draw target that scales every pixel into a `scale`×`scale` block
+struct ScaledTarget<'a> {
+    img: &'a mut RgbImage,
+    origin: Point,
+    scale: i32,
+}
+
+impl OriginDimensions for ScaledTarget<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.img.width(), self.img.height())
+    }
+}
+
+impl DrawTarget for ScaledTarget<'_> {
+    type Color = Rgb888;
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = (self.img.width() as i32, self.img.height() as i32);
+
+        for Pixel(point, color) in pixels {
+            let rgb = Rgb([color.r(), color.g(), color.b()]);
+            let base_x = self.origin.x + point.x * self.scale;
+            let base_y = self.origin.y + point.y * self.scale;
+
+            for dy in 0..self.scale {
+                for dx in 0..self.scale {
+                    let (px, py) = (base_x + dx, base_y + dy);
+                    if px >= 0 && py >= 0 && px < width && py < height {
+                        self.img.put_pixel(px as u32, py as u32, rgb);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
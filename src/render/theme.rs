@@ -0,0 +1,300 @@
+//! Themes for the built-in renderers.
+//!
+//! A theme picks the palette colors, text scale, borders and icon style
+//! used by every rendered source, so widgets on one screen look alike.
+//! Start from a preset and override individual colors by name.
+
+use super::{Canvas, colors};
+use crate::config::ConfigError;
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+
+/// Built-in theme presets
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemePreset {
+    /// Black/white with colored accents (default)
+    #[default]
+    Colorful,
+    /// Black on white only
+    Mono,
+    /// Light text on black
+    Dark,
+}
+
+/// How icons and other status glyphs are colored
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IconStyle {
+    /// Semantic palette colors (green up, red down, ...)
+    #[default]
+    Color,
+    /// Text color only
+    Mono,
+}
+
+/// Per-role color overrides (palette color names)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ThemeColors {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_background: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_accent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positive: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl ThemeColors {
+    fn entries(&self) -> [&Option<String>; 10] {
+        [
+            &self.background,
+            &self.text,
+            &self.header_background,
+            &self.header_text,
+            &self.header_accent,
+            &self.accent,
+            &self.highlight,
+            &self.positive,
+            &self.negative,
+            &self.warning,
+        ]
+    }
+}
+
+/// Theme settings from the config file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ThemeConfig {
+    /// Preset the theme starts from
+    #[serde(default)]
+    pub preset: ThemePreset,
+    /// Text size in percent of the default
+    #[serde(default = "default_text_scale")]
+    pub text_scale: u32,
+    /// Draw row separators and frames (default from preset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub borders: Option<bool>,
+    /// Icon coloring (default from preset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_style: Option<IconStyle>,
+    /// Color overrides
+    #[serde(default)]
+    pub colors: ThemeColors,
+    /// TTF/OTF font files tried before the built-in fonts, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fonts: Vec<String>,
+}
+
+fn default_text_scale() -> u32 {
+    100
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            preset: ThemePreset::default(),
+            text_scale: default_text_scale(),
+            borders: None,
+            icon_style: None,
+            colors: ThemeColors::default(),
+            fonts: Vec::new(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(50..=200).contains(&self.text_scale) {
+            return Err(ConfigError::ValidationError(
+                "Theme text_scale must be between 50 and 200".to_string(),
+            ));
+        }
+
+        for name in self.colors.entries().into_iter().flatten() {
+            if colors::from_name(name).is_none() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Unknown theme color '{}' (use black, white, yellow, red, orange, blue or green)",
+                    name
+                )));
+            }
+        }
+
+        if self.fonts.iter().any(|path| path.trim().is_empty()) {
+            return Err(ConfigError::ValidationError(
+                "Theme font paths must not be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolved theme used while drawing
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub background: Rgb<u8>,
+    pub text: Rgb<u8>,
+    pub header_background: Rgb<u8>,
+    pub header_text: Rgb<u8>,
+    /// Status text in the header bar
+    pub header_accent: Rgb<u8>,
+    /// Frames, progress bars, secondary text
+    pub accent: Rgb<u8>,
+    /// Emphasized values (big numbers, "Today!")
+    pub highlight: Rgb<u8>,
+    /// Good news (price up, target temperature reached)
+    pub positive: Rgb<u8>,
+    /// Bad news (price down, overdue, delayed)
+    pub negative: Rgb<u8>,
+    /// Needs attention soon (due today)
+    pub warning: Rgb<u8>,
+    pub text_scale: u32,
+    pub borders: bool,
+    pub icon_style: IconStyle,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::from_config(&ThemeConfig::default())
+    }
+}
+
+impl Theme {
+    /// Smallest base text unit, whatever the height and text scale
+    pub const MIN_UNIT: u32 = 8;
+
+    /// Resolve a theme from its settings
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        use colors::*;
+
+        let mut theme = match config.preset {
+            ThemePreset::Colorful => Self::preset(
+                [
+                    WHITE, BLACK, BLACK, WHITE, YELLOW, BLUE, RED, GREEN, RED, ORANGE,
+                ],
+                true,
+                IconStyle::Color,
+            ),
+            ThemePreset::Mono => Self::preset(
+                [
+                    WHITE, BLACK, BLACK, WHITE, WHITE, BLACK, BLACK, BLACK, BLACK, BLACK,
+                ],
+                true,
+                IconStyle::Mono,
+            ),
+            ThemePreset::Dark => Self::preset(
+                [
+                    BLACK, WHITE, BLUE, WHITE, YELLOW, YELLOW, RED, GREEN, RED, ORANGE,
+                ],
+                false,
+                IconStyle::Color,
+            ),
+        };
+
+        let overrides = &config.colors;
+        let slots = [
+            (&overrides.background, &mut theme.background),
+            (&overrides.text, &mut theme.text),
+            (&overrides.header_background, &mut theme.header_background),
+            (&overrides.header_text, &mut theme.header_text),
+            (&overrides.header_accent, &mut theme.header_accent),
+            (&overrides.accent, &mut theme.accent),
+            (&overrides.highlight, &mut theme.highlight),
+            (&overrides.positive, &mut theme.positive),
+            (&overrides.negative, &mut theme.negative),
+            (&overrides.warning, &mut theme.warning),
+        ];
+        for (name, slot) in slots {
+            if let Some(color) = name.as_deref().and_then(from_name) {
+                *slot = color;
+            }
+        }
+
+        theme.text_scale = config.text_scale;
+        theme.borders = config.borders.unwrap_or(theme.borders);
+        theme.icon_style = config.icon_style.unwrap_or(theme.icon_style);
+        theme
+    }
+
+    fn preset(c: [Rgb<u8>; 10], borders: bool, icon_style: IconStyle) -> Self {
+        Self {
+            background: c[0],
+            text: c[1],
+            header_background: c[2],
+            header_text: c[3],
+            header_accent: c[4],
+            accent: c[5],
+            highlight: c[6],
+            positive: c[7],
+            negative: c[8],
+            warning: c[9],
+            text_scale: default_text_scale(),
+            borders,
+            icon_style,
+        }
+    }
+
+    /// Base text unit for a layout of the given height
+    pub fn unit(&self, height: u32) -> u32 {
+        (height / 24 * self.text_scale / 100).max(Self::MIN_UNIT)
+    }
+
+    /// Color for an icon or glyph that would be `color` in color style
+    #[cfg(feature = "renderers")]
+    pub fn icon(&self, color: Rgb<u8>) -> Rgb<u8> {
+        match self.icon_style {
+            IconStyle::Color => color,
+            IconStyle::Mono => self.text,
+        }
+    }
+
+    /// Blank canvas in the background color
+    pub fn canvas(&self, width: u32, height: u32) -> Canvas {
+        Canvas::new(width, height, self.background)
+    }
+
+    /// Draw the header bar with a title and optional status on the right
+    ///
+    /// Returns the bar height.
+    #[cfg(feature = "renderers")]
+    pub fn header(&self, canvas: &mut Canvas, unit: u32, title: &str, status: &str) -> u32 {
+        let width = canvas.width();
+        let margin = unit as i32;
+        let bar = unit * 3;
+
+        canvas.fill_rect(0, 0, width, bar, self.header_background);
+        canvas.text(margin, unit as i32 / 2, title, unit * 2, self.header_text);
+        if !status.is_empty() {
+            canvas.text_right(
+                width as i32 - margin,
+                unit as i32 / 2,
+                status,
+                unit * 2,
+                self.header_accent,
+            );
+        }
+        bar
+    }
+
+    /// Thin separator line below a row (only when borders are enabled)
+    #[cfg(any(feature = "renderers", feature = "alerts"))]
+    pub fn separator(&self, canvas: &mut Canvas, x: i32, y: i32, width: u32) {
+        if self.borders {
+            canvas.fill_rect(x, y, width, 1, self.text);
+        }
+    }
+}
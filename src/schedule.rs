@@ -0,0 +1,250 @@
+//! Refresh times the schedule produces.
+//!
+//! The scheduler refreshes, then waits the interval of the period in
+//! effect at that moment. Replaying this over a time range shows what a
+//! set of plans does, e.g. around plan changes at midnight, without
+//! waiting for it. Backoff after failures, deferrals, overrides and an
+//! unsynchronized clock are not part of the replay.
+
+use crate::config::Config;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// Longest range replayed
+pub const MAX_RANGE_DAYS: i64 = 31;
+
+/// Most refreshes listed for one range
+pub const MAX_REFRESHES: usize = 5000;
+
+/// A refresh the schedule would run
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRefresh {
+    pub at: DateTime<Local>,
+    /// Plan in effect (none for weekdays without a plan)
+    pub plan: Option<String>,
+    /// Minutes until the next refresh
+    pub interval_min: u32,
+    /// Left out because the daily budget (`max_refreshes_per_day`) is used up
+    pub skipped: bool,
+}
+
+/// Refreshes from `from` (a refresh) up to and including `to`
+///
+/// Each day starts with an unused refresh budget. Returns the refreshes
+/// and whether the list was cut at [`MAX_REFRESHES`].
+pub fn replay(
+    config: &Config,
+    from: DateTime<Local>,
+    to: DateTime<Local>,
+) -> (Vec<PlannedRefresh>, bool) {
+    let mut refreshes = Vec::new();
+    let mut at = from;
+    let mut day = from.date_naive();
+    let mut today = 0;
+
+    while at <= to {
+        if refreshes.len() == MAX_REFRESHES {
+            return (refreshes, true);
+        }
+        if at.date_naive() != day {
+            day = at.date_naive();
+            today = 0;
+        }
+        let skipped = config.refresh_budget_exhausted(today);
+        if !skipped {
+            today += 1;
+        }
+
+        let interval_min = config.interval_at(at);
+        refreshes.push(PlannedRefresh {
+            at,
+            plan: config.plan_at(at).map(|plan| plan.name.clone()),
+            interval_min,
+            skipped,
+        });
+        at += Duration::minutes(interval_min as i64);
+    }
+    (refreshes, false)
+}
+
+/// Parse a local time given as RFC 3339, `YYYY-MM-DDTHH:MM` or a date
+/// (midnight)
+pub fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN))
+        })
+        .ok()?;
+    // The earlier of the two times when the clock is set back
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// Refreshes as an iCalendar feed, one minute-long event per refresh
+///
+/// Skipped refreshes are kept as cancelled events, so a calendar shows
+/// where the daily budget runs out.
+pub fn to_ics(refreshes: &[PlannedRefresh]) -> String {
+    let stamp = ics_time(Utc::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//rpizerow-epaper-display//{}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:E-paper refreshes".to_string(),
+    ];
+    for refresh in refreshes {
+        let start = ics_time(refresh.at.with_timezone(&Utc));
+        let summary = match (&refresh.plan, refresh.skipped) {
+            (_, true) => "Refresh skipped (daily budget used up)".to_string(),
+            (Some(plan), false) => format!("Refresh ({})", plan),
+            (None, false) => "Refresh".to_string(),
+        };
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:refresh-{}@rpizerow-epaper-display", start),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART:{}", start),
+            "DURATION:PT1M".to_string(),
+            format!("SUMMARY:{}", ics_escape(&summary)),
+            format!(
+                "DESCRIPTION:Next refresh {} min later",
+                refresh.interval_min
+            ),
+            "TRANSP:TRANSPARENT".to_string(),
+        ]);
+        if refresh.skipped {
+            lines.push("STATUS:CANCELLED".to_string());
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| ics_fold(line))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// UTC time in iCalendar form (`20260302T200000Z`)
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape text for an iCalendar value
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Line with CRLF ending, folded after 75 octets (RFC 5545, 3.1)
+fn ics_fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 4);
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{SchedulePeriod, SchedulePlan, Weekday};
+
+    fn office() -> Config {
+        Config {
+            schedule_plans: vec![SchedulePlan::new(
+                "Office",
+                vec![
+                    SchedulePeriod::new("06:00", "22:00", 60),
+                    SchedulePeriod::new("22:00", "06:00", 240),
+                ],
+            )],
+            day_assignments: Weekday::all()
+                .iter()
+                .map(|day| (*day, "Office".to_string()))
+                .collect(),
+            ..Config::default()
+        }
+    }
+
+    fn at(value: &str) -> DateTime<Local> {
+        parse_time(value).unwrap()
+    }
+
+    #[test]
+    fn replay_waits_the_interval_in_effect_at_each_refresh() {
+        let (refreshes, truncated) =
+            replay(&office(), at("2026-03-02T20:00"), at("2026-03-03T08:00"));
+        assert!(!truncated);
+        let times: Vec<String> = refreshes
+            .iter()
+            .map(|refresh| refresh.at.format("%H:%M").to_string())
+            .collect();
+        assert_eq!(
+            times,
+            [
+                "20:00", "21:00", "22:00", "02:00", "06:00", "07:00", "08:00"
+            ]
+        );
+        assert_eq!(refreshes[2].interval_min, 240);
+        assert_eq!(refreshes[0].plan.as_deref(), Some("Office"));
+    }
+
+    #[test]
+    fn replay_skips_refreshes_over_the_daily_budget() {
+        let config = Config {
+            max_refreshes_per_day: 2,
+            ..office()
+        };
+        let (refreshes, _) = replay(&config, at("2026-03-02T20:00"), at("2026-03-03T07:00"));
+        let skipped: Vec<bool> = refreshes.iter().map(|refresh| refresh.skipped).collect();
+        // The budget starts over at midnight
+        assert_eq!(skipped, [false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn times_parse_with_and_without_a_clock_time() {
+        assert_eq!(
+            at("2026-03-02").naive_local(),
+            at("2026-03-02T00:00").naive_local()
+        );
+        assert_eq!(at("2026-03-02 20:15"), at("2026-03-02T20:15"));
+        assert_eq!(
+            at("2026-03-02T20:15:00Z").with_timezone(&Utc),
+            Utc.with_ymd_and_hms(2026, 3, 2, 20, 15, 0).unwrap()
+        );
+        assert!(parse_time("tomorrow").is_none());
+    }
+
+    #[test]
+    fn calendar_lines_are_folded() {
+        let (refreshes, _) = replay(&office(), at("2026-03-02T20:00"), at("2026-03-02T21:00"));
+        let ics = to_ics(&refreshes);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("SUMMARY:Refresh (Office)").count(), 2);
+
+        let folded = ics_fold(&"x".repeat(100));
+        assert_eq!(
+            folded,
+            format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(25))
+        );
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+    }
+}
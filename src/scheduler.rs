@@ -2,14 +2,99 @@
 //!
 //! Manages automatic refresh of the display at configurable intervals.
 //! Includes failure tracking and exponential backoff for resilience.
-
-use crate::config::Config;
-use crate::image_proc::ImageProcessor;
-use std::sync::atomic::{AtomicU32, Ordering};
+//!
+//! Before the first refresh a startup sequence waits for the network and
+//! a synchronized clock, so a slowly connecting Wi-Fi does not cost a
+//! failed first refresh (and the backoff that follows).
+//!
+//! Scheduled refreshes can be put off while the system is busy (see
+//! [`load_guard`](crate::load_guard)).
+//!
+//! Auxiliary GPIO outputs are switched at minute boundaries while
+//! waiting (see [`outputs`](crate::outputs)).
+
+use crate::config::{Config, ConfigError};
+use crate::image_proc::{check_source, DownloadError, ImageProcessor, ProcessingError};
+use crate::arbiter::{ContentArbiter, Priority};
+use crate::network;
+use crate::outputs::Outputs;
+use crate::time_sync;
+use crate::status::StatusTracker;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{broadcast, RwLock};
 
+/// Steps run before the first refresh
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StartupConfig {
+    /// Seconds to wait before anything else
+    #[serde(default)]
+    pub delay_secs: u32,
+
+    /// Wait until the image source's host can be reached
+    #[serde(default = "default_true")]
+    pub wait_for_network: bool,
+
+    /// Longest wait for the network in seconds
+    #[serde(default = "default_wait_secs")]
+    pub network_timeout_secs: u32,
+
+    /// Wait until the system clock is synchronized
+    #[serde(default = "default_true")]
+    pub wait_for_clock: bool,
+
+    /// Longest wait for the clock in seconds
+    #[serde(default = "default_wait_secs")]
+    pub clock_timeout_secs: u32,
+
+    /// Refresh right after the startup sequence (off = leave the panel as
+    /// it is until the first scheduled refresh)
+    #[serde(default = "default_true")]
+    pub refresh_on_start: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_wait_secs() -> u32 {
+    120
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            delay_secs: 0,
+            wait_for_network: true,
+            network_timeout_secs: default_wait_secs(),
+            wait_for_clock: true,
+            clock_timeout_secs: default_wait_secs(),
+            refresh_on_start: true,
+        }
+    }
+}
+
+impl StartupConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (name, secs) in [
+            ("delay_secs", self.delay_secs),
+            ("network_timeout_secs", self.network_timeout_secs),
+            ("clock_timeout_secs", self.clock_timeout_secs),
+        ] {
+            if secs > 3600 {
+                return Err(ConfigError::ValidationError(format!(
+                    "startup.{} must be between 0 and 3600",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Scheduler for periodic display refresh
 ///
 /// Tracks consecutive failures and applies exponential backoff
@@ -17,8 +102,17 @@ use tokio::sync::{broadcast, RwLock};
 pub struct Scheduler {
     config: Arc<RwLock<Config>>,
     processor: Arc<ImageProcessor>,
+    status: Arc<StatusTracker>,
+    /// Decides whether an override is shown instead of the scheduled source
+    arbiter: Arc<ContentArbiter>,
+    /// Auxiliary GPIO outputs switched on their schedule
+    outputs: Arc<Outputs>,
     /// Counter for consecutive failures
     consecutive_failures: AtomicU32,
+    /// Set once the display turned out not to support partial refresh
+    partial_unsupported: AtomicBool,
+    /// Set while the last network probe failed
+    network_down: AtomicBool,
 }
 
 impl Scheduler {
@@ -31,15 +125,39 @@ impl Scheduler {
     /// Maximum backoff duration (1 hour)
     const MAX_BACKOFF_SECS: u64 = 3600;
 
+    /// Time between checks while waiting for the network or the clock
+    const STARTUP_POLL: Duration = Duration::from_secs(5);
+
     /// Create a new scheduler
-    pub fn new(config: Arc<RwLock<Config>>, processor: Arc<ImageProcessor>) -> Self {
+    pub fn new(
+        config: Arc<RwLock<Config>>,
+        processor: Arc<ImageProcessor>,
+        status: Arc<StatusTracker>,
+    ) -> Self {
         Self {
             config,
             processor,
+            status,
+            arbiter: Arc::new(ContentArbiter::default()),
+            outputs: Arc::default(),
             consecutive_failures: AtomicU32::new(0),
+            partial_unsupported: AtomicBool::new(false),
+            network_down: AtomicBool::new(false),
         }
     }
 
+    /// Show the overrides of `arbiter` instead of the scheduled source
+    pub fn with_arbiter(mut self, arbiter: Arc<ContentArbiter>) -> Self {
+        self.arbiter = arbiter;
+        self
+    }
+
+    /// Switch `outputs` on their schedule
+    pub fn with_outputs(mut self, outputs: Arc<Outputs>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
     /// Run the scheduler loop
     ///
     /// Periodically refreshes the display based on the configured interval.
@@ -47,54 +165,417 @@ impl Scheduler {
     /// Applies exponential backoff after repeated failures.
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
         tracing::info!("Scheduler started");
+        self.switch_outputs().await;
 
-        // Initial delay before first refresh (wait for system to stabilize)
-        tokio::select! {
-            _ = tokio::time::sleep(Duration::from_secs(10)) => {}
-            _ = shutdown.recv() => {
+        if !self.start_up(&mut shutdown).await {
+            tracing::info!("Scheduler shutdown before initial refresh");
+            return;
+        }
+
+        // Initial refresh
+        if self.config.read().await.startup.refresh_on_start {
+            if !self.wait_while_busy(&mut shutdown).await {
                 tracing::info!("Scheduler shutdown before initial refresh");
                 return;
             }
+            self.refresh_display().await;
+        } else {
+            tracing::info!("Skipping the refresh at startup, waiting for the schedule");
         }
 
-        // Initial refresh
-        self.refresh_display().await;
-
         loop {
             // Get current interval from config based on day and time, with backoff applied
-            let interval = {
+            let interval = if !time_sync::is_synchronized() {
+                self.status.set_clock_synchronized(false);
+                tracing::warn!(
+                    "System clock not synchronized, ignoring the schedule and refreshing every {:?}",
+                    time_sync::UNSYNCED_INTERVAL
+                );
+                self.get_effective_interval(time_sync::UNSYNCED_INTERVAL)
+            } else {
+                self.status.set_clock_synchronized(true);
                 let config = self.config.read().await;
                 let current_interval = config.get_current_interval();
                 let base_interval = Duration::from_secs(current_interval as u64 * 60);
 
-                if let Some(plan) = config.get_current_plan() {
-                    if let Some(period) = config.get_current_period() {
-                        tracing::debug!(
-                            "Active plan: '{}' ({}) - period {} - {} (every {} min)",
-                            plan.name,
-                            crate::config::Config::get_current_weekday().display_name(),
-                            period.start_time,
-                            period.end_time,
-                            period.interval_min
+                if let Some(plan) = config.get_current_plan()
+                    && let Some(period) = config.get_current_period()
+                {
+                    tracing::debug!(
+                        "Active plan: '{}' ({}) - period {} - {} (every {} min)",
+                        plan.name,
+                        crate::config::Config::get_current_weekday().display_name(),
+                        period.start_time,
+                        period.end_time,
+                        period.interval_min
+                    );
+
+                    if period.interval_min < current_interval {
+                        tracing::warn!(
+                            "Interval of {} min is below the minimum, refreshing every {} min",
+                            period.interval_min,
+                            current_interval
                         );
                     }
                 }
 
+                let base_interval = self.content_interval(&config, base_interval);
                 self.get_effective_interval(base_interval)
             };
 
             tracing::debug!("Next refresh in {:?}", interval);
+            self.status.record_next_refresh(interval);
+            self.power_off(interval).await;
+
+            let deadline = tokio::time::Instant::now() + interval;
+            if !self.wait_until(deadline, &mut shutdown).await
+                || !self.wait_while_busy(&mut shutdown).await
+            {
+                tracing::info!("Scheduler shutting down");
+                break;
+            }
+
+            self.refresh_display().await;
+        }
+    }
+
+    /// Power the Pi off until shortly before the next refresh, due in
+    /// `interval` (`power_save`)
+    ///
+    /// The Pi stays on while an override is shown and on mirror group
+    /// followers, which wait for pushed frames. If powering off fails, the
+    /// refresh is waited for as usual.
+    pub(crate) async fn power_off(&self, interval: Duration) {
+        let (power_save, follower) = {
+            let config = self.config.read().await;
+            (config.power_save.clone(), config.mirror.is_follower())
+        };
+        let Some(wake) = power_save.wake_time(chrono::Local::now(), interval) else {
+            return;
+        };
+        if follower || self.arbiter.active().is_some() {
+            tracing::debug!("Staying on for the mirror group or an override");
+            return;
+        }
+        if let Err(e) = power_save.power_off_until(wake) {
+            tracing::warn!("Staying on until the next refresh: {}", e);
+        }
+    }
 
+    /// Run the startup sequence: delay, wait for network, wait for clock
+    ///
+    /// Waits that time out are logged and the sequence continues.
+    /// Returns false if shutdown was requested meanwhile.
+    pub(crate) async fn start_up(&self, shutdown: &mut broadcast::Receiver<()>) -> bool {
+        let (startup, host) = {
+            let config = self.config.read().await;
+            let host = config
+                .source
+                .health_request(&config)
+                .and_then(|request| network::host_of(&request.url));
+            (config.startup.clone(), host)
+        };
+
+        if startup.delay_secs > 0 {
+            let delay = Duration::from_secs(startup.delay_secs as u64);
+            tracing::info!("Waiting {:?} before the first refresh", delay);
+            self.status.record_next_refresh(delay);
             tokio::select! {
-                _ = tokio::time::sleep(interval) => {
-                    self.refresh_display().await;
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.recv() => return false,
+            }
+        }
+
+        // Sources rendered on the device need no network
+        if startup.wait_for_network
+            && let Some(host) = host
+        {
+            let timeout = Duration::from_secs(startup.network_timeout_secs as u64);
+            let Some(online) = self
+                .wait_for("network", timeout, shutdown, || network::is_online(&host))
+                .await
+            else {
+                return false;
+            };
+            // Refresh as soon as it is back (see `check_network`)
+            self.network_down.store(!online, Ordering::Relaxed);
+        }
+
+        // Without an RTC the clock may still be far off until NTP has run
+        if startup.wait_for_clock {
+            let timeout = Duration::from_secs(startup.clock_timeout_secs as u64);
+            let Some(synchronized) = self
+                .wait_for("synchronized clock", timeout, shutdown, || async {
+                    time_sync::is_synchronized()
+                })
+                .await
+            else {
+                return false;
+            };
+            self.status.set_clock_synchronized(synchronized);
+        }
+
+        true
+    }
+
+    /// Poll `ready` until it holds or `timeout` passes
+    ///
+    /// Returns whether it held, or None if shutdown was requested.
+    async fn wait_for<F, Fut>(
+        &self,
+        what: &str,
+        timeout: Duration,
+        shutdown: &mut broadcast::Receiver<()>,
+        ready: F,
+    ) -> Option<bool>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        let deadline = tokio::time::Instant::now() + timeout;
+        self.status.record_next_refresh(timeout);
+
+        loop {
+            if ready().await {
+                return Some(true);
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                tracing::warn!("No {} after {:?}, continuing anyway", what, timeout);
+                return Some(false);
+            }
+            tracing::info!("Waiting for {}", what);
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline.min(now + Self::STARTUP_POLL)) => {}
+                _ = shutdown.recv() => return None,
+            }
+        }
+    }
+
+    /// Wait for the refresh deadline, running source health checks meanwhile
+    ///
+    /// Sources with a partial-update region (the clock) also get that
+    /// region redrawn at every minute boundary, and GPIO outputs are
+    /// switched there as their schedule says. The network is probed
+    /// regularly; the wait ends early when it comes back after an outage.
+    /// It also ends when an override expires, so the scheduled content
+    /// returns right away.
+    /// Returns false if shutdown was requested before the deadline.
+    async fn wait_until(
+        &self,
+        deadline: tokio::time::Instant,
+        shutdown: &mut broadcast::Receiver<()>,
+    ) -> bool {
+        let mut timers = CheckTimers::default();
+        loop {
+            let now = tokio::time::Instant::now();
+            let deadline = match self.arbiter.active().and_then(|o| o.remaining()) {
+                Some(remaining) if now + remaining < deadline => {
+                    self.status.record_next_refresh(remaining);
+                    now + remaining
                 }
-                _ = shutdown.recv() => {
-                    tracing::info!("Scheduler shutting down");
-                    break;
+                _ => deadline,
+            };
+            {
+                let config = self.config.read().await;
+                let (width, height) = config.content_size();
+                let minutes = config.health_check_interval_min as u64;
+                let seconds = config.network_check_interval_secs as u64;
+                let partial = config.source.partial_region(width, height).is_some()
+                    && !self.partial_unsupported.load(Ordering::Relaxed);
+                timers.arm(
+                    Check::SourceHealth,
+                    (minutes > 0).then(|| Duration::from_secs(minutes * 60)),
+                );
+                timers.arm(Check::PartialRefresh, partial.then(until_next_minute));
+                timers.arm(
+                    Check::Outputs,
+                    (!config.outputs.pins.is_empty()).then(until_next_minute),
+                );
+                timers.arm(
+                    Check::NetworkProbe,
+                    (seconds > 0).then(|| Duration::from_secs(seconds)),
+                );
+            }
+            let next = timers.next(deadline);
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(next.map_or(deadline, |(_, at)| at)) => {
+                    let Some((check, _)) = next else {
+                        return true;
+                    };
+                    timers.fired(check);
+                    match check {
+                        Check::SourceHealth => self.check_source_health().await,
+                        Check::PartialRefresh => self.refresh_partial().await,
+                        Check::Outputs => self.switch_outputs().await,
+                        Check::NetworkProbe => {
+                            if self.check_network().await {
+                                return true;
+                            }
+                        }
+                    }
                 }
+                _ = self.arbiter.changed() => {}
+                _ = shutdown.recv() => return false,
+            }
+        }
+    }
+
+    /// Bring the auxiliary outputs in line with their schedule
+    pub(crate) async fn switch_outputs(&self) {
+        let config = self.config.read().await;
+        self.outputs.apply(&config.outputs, chrono::Local::now());
+    }
+
+    /// Put off a refresh while load or temperature are above the limits
+    ///
+    /// Checks every `load_guard.retry_secs` and gives up waiting after
+    /// `load_guard.max_defer_min`. Alerts are never held back.
+    /// Returns false if shutdown was requested meanwhile.
+    async fn wait_while_busy(&self, shutdown: &mut broadcast::Receiver<()>) -> bool {
+        let guard = self.config.read().await.load_guard.clone();
+        if !guard.enabled() {
+            return true;
+        }
+
+        let deadline = tokio::time::Instant::now() + guard.max_defer();
+        loop {
+            if self.arbiter.active().is_some_and(|o| o.priority == Priority::Alert) {
+                return true;
+            }
+            let Some(reason) = guard.busy_reason() else {
+                return true;
+            };
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                tracing::warn!(
+                    "System still busy after {:?} ({}), refreshing anyway",
+                    guard.max_defer(),
+                    reason
+                );
+                return true;
+            }
+            let wait = guard.retry().min(deadline - now);
+            tracing::info!("System busy ({}), deferring refresh by {:?}", reason, wait);
+            self.status.record_next_refresh(wait);
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.arbiter.changed() => {}
+                _ = shutdown.recv() => return false,
+            }
+        }
+    }
+
+    /// Redraw the source's partial-update region
+    pub(crate) async fn refresh_partial(&self) {
+        // The region belongs to the scheduled content, not the override
+        if self.arbiter.active().is_some() {
+            return;
+        }
+        let config = self.config.read().await;
+        if config.mirror.is_follower() {
+            return;
+        }
+
+        match self.processor.refresh_partial(&config).await {
+            Ok(true) => tracing::debug!("Partial refresh completed"),
+            Ok(false) if !self.processor.display().supports_partial().await => {
+                tracing::info!(
+                    "Display does not support partial refresh, updating on the regular schedule only"
+                );
+                self.partial_unsupported.store(true, Ordering::Relaxed);
+            }
+            // Cleared, failed or changed elsewhere: the regular schedule
+            // redraws the whole frame, within its own interval and backoff
+            Ok(false) => tracing::debug!(
+                "Panel content not known to match, waiting for the next full refresh"
+            ),
+            Err(e) => tracing::warn!("Partial refresh failed: {}", e),
+        }
+    }
+
+    /// Probe the network the image source is reached over
+    ///
+    /// Returns true if the network just came back after an outage. Sources
+    /// rendered on the device are not probed.
+    pub(crate) async fn check_network(&self) -> bool {
+        let host = {
+            let config = self.config.read().await;
+            config
+                .source
+                .health_request(&config)
+                .and_then(|request| network::host_of(&request.url))
+        };
+        let Some(host) = host else {
+            return false;
+        };
+
+        let online = network::is_online(&host).await;
+        match (self.network_down.swap(!online, Ordering::Relaxed), online) {
+            (false, false) => {
+                tracing::warn!("Network unavailable, refreshing as soon as it is back");
+                false
+            }
+            (true, true) => {
+                tracing::info!("Network is back, refreshing now");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Check the image source without refreshing the panel
+    async fn check_source_health(&self) {
+        let (request, session) = {
+            let config = self.config.read().await;
+            match config.source.health_request(&config) {
+                Some(request) => (request, config.session.enabled().then(|| config.session.clone())),
+                None => return,
             }
+        };
+
+        match check_source(&request.url, &request.headers, session.as_ref()).await {
+            Ok(status) => {
+                tracing::debug!("Source health check OK ({})", status);
+                self.status.record_source_health(Some(status), None);
+            }
+            Err(e) => {
+                tracing::warn!("Source health check failed: {}", e);
+                self.status
+                    .record_source_health(http_status_of(&e), Some(e.to_string()));
+            }
+        }
+    }
+
+    /// Time until the next refresh, for the content on the panel
+    ///
+    /// Slides (bundle manifests, playlists) advance on their own dwell
+    /// timers while the schedule's `interval` keeps refreshing them. A
+    /// refresh that would come less than the minimum interval before the
+    /// next slide waits for it. Content that expires earlier is refreshed
+    /// then.
+    pub(crate) fn content_interval(&self, config: &Config, mut interval: Duration) -> Duration {
+        let presentation = self.processor.presentation();
+        let now = chrono::Local::now();
+        // Past times: the timer already ran, back to the schedule
+        let left = |time: Option<chrono::DateTime<chrono::Local>>| {
+            time.and_then(|time| (time - now).to_std().ok())
+        };
+        let min_interval = Duration::from_secs(config.min_interval() as u64 * 60);
+        if let Some(left) = left(presentation.next_slide)
+            && left < interval + min_interval
+        {
+            tracing::debug!("Next slide due in {:?}", left);
+            interval = left;
         }
+        if let Some(left) = left(presentation.valid_until)
+            && left < interval
+        {
+            tracing::debug!("Content expires in {:?}", left);
+            interval = left;
+        }
+        interval
     }
 
     /// Calculate effective interval with backoff applied
@@ -128,18 +609,39 @@ impl Scheduler {
     }
 
     /// Perform a display refresh with failure tracking
-    async fn refresh_display(&self) {
-        let config = self.config.read().await;
+    pub(crate) async fn refresh_display(&self) {
+        let shared = self.config.read().await;
+        if shared.mirror.is_follower() {
+            tracing::debug!("Mirror group follower, the leader sends the content");
+            return;
+        }
+        let config = self.arbiter.content_config(&shared);
 
-        if !config.has_image_url() {
-            tracing::debug!("No image URL configured, skipping refresh");
+        if !config.has_source() {
+            tracing::debug!("No image source configured, skipping refresh");
+            return;
+        }
+
+        let refreshes = self.status.refreshes_today();
+        if config.refresh_budget_exhausted(refreshes) {
+            tracing::warn!(
+                "Daily refresh budget used up ({}/{}), skipping scheduled refresh",
+                refreshes,
+                config.max_refreshes_per_day
+            );
             return;
         }
 
         tracing::info!("Scheduled refresh starting...");
 
-        match self.processor.process_and_display(&config).await {
-            Ok(_) => {
+        let result = self.processor.display_if_changed(&config).await;
+        self.record_refresh_source_health(&result);
+
+        match result {
+            Ok(false) => {
+                self.consecutive_failures.store(0, Ordering::Relaxed);
+            }
+            Ok(true) => {
                 let prev_failures = self.consecutive_failures.swap(0, Ordering::Relaxed);
                 if prev_failures > 0 {
                     tracing::info!(
@@ -161,6 +663,90 @@ impl Scheduler {
             }
         }
     }
+
+    /// A refresh doubles as a source check: update health from its outcome
+    fn record_refresh_source_health<T>(&self, result: &Result<T, ProcessingError>) {
+        match result {
+            Ok(_) => self.status.record_source_health(None, None),
+            Err(ProcessingError::Download(e)) => self
+                .status
+                .record_source_health(http_status_of(e), Some(e.to_string())),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Time until just past the next wall-clock minute
+fn until_next_minute() -> Duration {
+    use chrono::Timelike;
+
+    let now = chrono::Local::now();
+    let into_minute = Duration::new(now.second() as u64, now.nanosecond() % 1_000_000_000);
+    // Small margin so the render sees the new minute
+    Duration::from_secs(60).saturating_sub(into_minute) + Duration::from_millis(200)
+}
+
+/// Periodic checks run while waiting for the next refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Check {
+    /// Source health check (`health_check_interval_min`)
+    SourceHealth,
+    /// Minute tick for the partial refresh region
+    PartialRefresh,
+    /// Minute tick for the GPIO outputs
+    Outputs,
+    /// Network probe (`network_check_interval_secs`)
+    NetworkProbe,
+}
+
+/// Due times of the periodic checks of one wait
+///
+/// Times are absolute and only the check that ran is re-armed, so frequent
+/// checks (the network probe, minute ticks) do not put off the others.
+#[derive(Debug, Default)]
+struct CheckTimers {
+    due: [Option<tokio::time::Instant>; 4],
+}
+
+impl CheckTimers {
+    /// Schedule `check` `every` from now unless already scheduled; None
+    /// turns it off
+    fn arm(&mut self, check: Check, every: Option<Duration>) {
+        let due = &mut self.due[check as usize];
+        match every {
+            None => *due = None,
+            Some(every) if due.is_none() => *due = Some(tokio::time::Instant::now() + every),
+            Some(_) => {}
+        }
+    }
+
+    /// Check due first, if before `deadline`
+    fn next(&self, deadline: tokio::time::Instant) -> Option<(Check, tokio::time::Instant)> {
+        [
+            Check::SourceHealth,
+            Check::PartialRefresh,
+            Check::Outputs,
+            Check::NetworkProbe,
+        ]
+        .into_iter()
+        .filter_map(|check| Some((check, self.due[check as usize]?)))
+        .filter(|(_, at)| *at < deadline)
+        .min_by_key(|(_, at)| *at)
+    }
+
+    /// `check` ran; it is scheduled again by the next `arm`
+    fn fired(&mut self, check: Check) {
+        self.due[check as usize] = None;
+    }
+}
+
+/// HTTP status carried by a download error, if any
+fn http_status_of(error: &DownloadError) -> Option<u16> {
+    match error {
+        DownloadError::HttpError { status } => Some(*status),
+        DownloadError::RequestError(e) => e.status().map(|s| s.as_u16()),
+        _ => None,
+    }
 }
 
 /// Scheduler with manual trigger support
@@ -176,9 +762,10 @@ impl SchedulerWithTrigger {
     pub fn new(
         config: Arc<RwLock<Config>>,
         processor: Arc<ImageProcessor>,
+        status: Arc<StatusTracker>,
     ) -> (Self, tokio::sync::mpsc::Sender<()>) {
         let (trigger_tx, trigger_rx) = tokio::sync::mpsc::channel(1);
-        let inner = Scheduler::new(config, processor);
+        let inner = Scheduler::new(config, processor, status);
         (Self { inner, trigger_rx }, trigger_tx)
     }
 
@@ -188,14 +775,14 @@ impl SchedulerWithTrigger {
     pub async fn run(mut self, mut shutdown: broadcast::Receiver<()>) {
         tracing::info!("Scheduler with trigger started");
 
-        // Initial delay
-        tokio::select! {
-            _ = tokio::time::sleep(Duration::from_secs(10)) => {}
-            _ = shutdown.recv() => return,
+        if !self.inner.start_up(&mut shutdown).await {
+            return;
         }
 
         // Initial refresh
-        self.inner.refresh_display().await;
+        if self.inner.config.read().await.startup.refresh_on_start {
+            self.inner.refresh_display().await;
+        }
 
         loop {
             // Get effective interval based on day and time (with backoff applied)
@@ -223,3 +810,33 @@ impl SchedulerWithTrigger {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn health_check_runs_while_the_network_is_probed() {
+        let start = tokio::time::Instant::now();
+        let deadline = start + Duration::from_secs(3600);
+        let mut timers = CheckTimers::default();
+        let mut health_checks = Vec::new();
+        let mut probes = 0;
+        loop {
+            timers.arm(Check::SourceHealth, Some(Duration::from_secs(600)));
+            timers.arm(Check::NetworkProbe, Some(Duration::from_secs(60)));
+            let Some((check, at)) = timers.next(deadline) else {
+                break;
+            };
+            tokio::time::sleep_until(at).await;
+            timers.fired(check);
+            match check {
+                Check::SourceHealth => health_checks.push((at - start).as_secs()),
+                _ => probes += 1,
+            }
+        }
+
+        assert_eq!(health_checks, [600, 1200, 1800, 2400, 3000]);
+        assert_eq!(probes, 59);
+    }
+}
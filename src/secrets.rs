@@ -0,0 +1,206 @@
+//! Secret references in the configuration.
+//!
+//! API keys, tokens and passwords can be kept out of config.json by
+//! referencing them instead of storing the value:
+//!
+//! - `${ENV:NAME}`: environment variable `NAME` (e.g. from a systemd
+//!   `EnvironmentFile=`)
+//! - `${FILE:/path}`: contents of a file not accessible to other users
+//!   (e.g. root-owned with mode 0600 or 0640)
+//!
+//! References are resolved each time a source builds a request, so the
+//! config file (and every export of it) only ever holds the reference.
+//! Resolved values are remembered and masked wherever text leaves the
+//! process: logs, status messages and the debug bundle.
+
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+use thiserror::Error;
+
+/// Replacement for masked values
+pub const REDACTED: &str = "<redacted>";
+
+/// Shorter values are not masked; they would match ordinary text
+const MIN_MASK_LEN: usize = 4;
+
+/// Secret resolution errors
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("environment variable {0} referenced by the config is not set")]
+    MissingEnv(String),
+
+    #[error("cannot read secret file {path}: {source}")]
+    File {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("secret file {0} is accessible to other users (chmod o-rwx)")]
+    Permissions(String),
+
+    #[error("unsupported secret reference '${{{0}}}' (use ENV: or FILE:)")]
+    Unsupported(String),
+}
+
+/// Resolved values seen so far
+static KNOWN: Lazy<RwLock<BTreeSet<String>>> = Lazy::new(|| RwLock::new(BTreeSet::new()));
+
+/// Whether `value` consists of a single secret reference
+pub fn is_reference(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with("${") && value.ends_with('}') && value[2..].find('}') == Some(value.len() - 3)
+}
+
+/// Substitute every `${ENV:...}` / `${FILE:...}` reference in `value`
+///
+/// Text outside references is kept as is, so a reference can be embedded
+/// in a URL (`https://host/img?key=${ENV:KEY}`). Substituted values are
+/// masked from then on.
+pub fn expand(value: &str) -> Result<String, SecretError> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+
+        let reference = &rest[start + 2..start + len];
+        let secret = lookup(reference)?;
+        remember(&secret);
+        out.push_str(&secret);
+
+        rest = &rest[start + len + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolve a field that is secret as a whole (token, API key, password)
+///
+/// Plain values are accepted too; they are masked like referenced ones.
+pub fn resolve(value: &str) -> Result<String, SecretError> {
+    let secret = expand(value.trim())?;
+    remember(&secret);
+    Ok(secret)
+}
+
+/// Mask every known secret value in `text`
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let known = KNOWN.read().unwrap_or_else(|e| e.into_inner());
+    let mut text = Cow::Borrowed(text);
+
+    // Longest first, so a secret containing another is masked whole
+    let mut secrets: Vec<&String> = known.iter().collect();
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+
+    for secret in secrets {
+        if text.contains(secret.as_str()) {
+            text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+        }
+    }
+    text
+}
+
+/// Compare a secret without exiting early on the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn remember(secret: &str) {
+    if secret.len() < MIN_MASK_LEN {
+        return;
+    }
+    if KNOWN
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(secret)
+    {
+        return;
+    }
+    KNOWN
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(secret.to_string());
+}
+
+fn lookup(reference: &str) -> Result<String, SecretError> {
+    if let Some(name) = reference.strip_prefix("ENV:") {
+        return std::env::var(name.trim()).map_err(|_| SecretError::MissingEnv(name.to_string()));
+    }
+
+    if let Some(path) = reference.strip_prefix("FILE:") {
+        return read_file(path.trim());
+    }
+
+    Err(SecretError::Unsupported(reference.to_string()))
+}
+
+/// Read a secret file, refusing files other users can access
+fn read_file(path: &str) -> Result<String, SecretError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let file_error = |source| SecretError::File {
+        path: path.to_string(),
+        source,
+    };
+
+    let mode = std::fs::metadata(path)
+        .map_err(file_error)?
+        .permissions()
+        .mode();
+    if mode & 0o007 != 0 {
+        return Err(SecretError::Permissions(path.to_string()));
+    }
+
+    let content = std::fs::read_to_string(path).map_err(file_error)?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_expanded_and_masked() {
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var("EPAPER_TEST_SECRET_KEY", "s3cr3t-key") };
+        let url = expand("https://host/img?key=${ENV:EPAPER_TEST_SECRET_KEY}&w=800").unwrap();
+        assert_eq!(url, "https://host/img?key=s3cr3t-key&w=800");
+        assert_eq!(
+            redact(&format!("GET {} failed", url)),
+            "GET https://host/img?key=<redacted>&w=800 failed"
+        );
+        assert!(matches!(redact("nothing to hide"), Cow::Borrowed(_)));
+
+        // Too short to mask without hiding ordinary text
+        assert_eq!(resolve("abc").unwrap(), "abc");
+        assert_eq!(redact("abc"), "abc");
+    }
+
+    #[test]
+    fn unknown_references_are_errors() {
+        assert!(matches!(
+            expand("${ENV:EPAPER_TEST_SECRET_UNSET}"),
+            Err(SecretError::MissingEnv(name)) if name == "EPAPER_TEST_SECRET_UNSET"
+        ));
+        assert!(matches!(
+            expand("${VAULT:key}"),
+            Err(SecretError::Unsupported(reference)) if reference == "VAULT:key"
+        ));
+        assert_eq!(expand("${unterminated").unwrap(), "${unterminated");
+    }
+
+    #[test]
+    fn only_whole_values_are_references() {
+        assert!(is_reference("${ENV:TOKEN}"));
+        assert!(is_reference(" ${FILE:/etc/token} "));
+        assert!(!is_reference("Bearer ${ENV:TOKEN}"));
+        assert!(!is_reference("${ENV:A}${ENV:B}"));
+        assert!(!is_reference("plain"));
+    }
+}
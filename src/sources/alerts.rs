@@ -0,0 +1,241 @@
+//! Firing alert list.
+//!
+//! Not configurable as a source: alerts pushed to `POST /api/v1/alerts`
+//! (Prometheus Alertmanager webhooks) are collected here and shown as an
+//! alert override until all of them are resolved.
+
+use super::SourceConfig;
+use crate::arbiter::{Override, Priority};
+use crate::render::{Theme, fit_text, text::text_width};
+use chrono::{DateTime, Local};
+use image::{DynamicImage, Rgb};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// Alertmanager webhook payload (version 4), as far as it is used
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AlertmanagerWebhook {
+    /// Alerts of the notified group, firing and resolved
+    pub alerts: Vec<AlertmanagerAlert>,
+}
+
+/// One alert of an Alertmanager webhook
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertmanagerAlert {
+    /// "firing" or "resolved"
+    pub status: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    /// RFC 3339 time the alert started firing
+    #[serde(default)]
+    pub starts_at: Option<String>,
+    #[serde(default)]
+    pub fingerprint: String,
+}
+
+impl AlertmanagerAlert {
+    /// Fingerprint, or the labels for senders that leave it out
+    fn key(&self) -> String {
+        if !self.fingerprint.is_empty() {
+            return self.fingerprint.clone();
+        }
+        self.labels
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// A firing alert
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiringAlert {
+    /// Identity of the alert across webhooks (Alertmanager fingerprint)
+    pub key: String,
+    /// Alert name (`alertname` label)
+    pub name: String,
+    /// `severity` label, if set
+    pub severity: Option<String>,
+    /// Summary or description annotation
+    pub summary: String,
+    /// Local time the alert started firing
+    pub since: Option<DateTime<Local>>,
+}
+
+impl FiringAlert {
+    /// Sort rank of the severity, most severe first
+    fn rank(&self) -> u8 {
+        match self.severity.as_deref() {
+            Some("critical" | "page" | "error") => 0,
+            Some("warning") => 1,
+            _ => 2,
+        }
+    }
+
+    fn color(&self, theme: &Theme) -> Rgb<u8> {
+        match self.rank() {
+            0 => theme.negative,
+            1 => theme.warning,
+            _ => theme.accent,
+        }
+    }
+}
+
+/// Alerts firing right now, most severe and oldest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertList {
+    alerts: Vec<FiringAlert>,
+}
+
+impl AlertList {
+    /// Apply the firing and resolved alerts of a webhook
+    pub fn apply(&mut self, webhook: &AlertmanagerWebhook) {
+        for alert in &webhook.alerts {
+            if alert.status != "firing" {
+                self.resolve(&alert.key());
+                continue;
+            }
+            let annotation = |name: &str| alert.annotations.get(name).map(|s| s.trim());
+            self.fire(FiringAlert {
+                key: alert.key(),
+                name: alert
+                    .labels
+                    .get("alertname")
+                    .cloned()
+                    .unwrap_or_else(|| "Alert".to_string()),
+                severity: alert.labels.get("severity").map(|s| s.to_lowercase()),
+                summary: annotation("summary")
+                    .or_else(|| annotation("description"))
+                    .unwrap_or_default()
+                    .to_string(),
+                // Alertmanager sends the zero time when it is unknown
+                since: alert
+                    .starts_at
+                    .as_deref()
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .filter(|t| t.timestamp() > 0)
+                    .map(|t| t.with_timezone(&Local)),
+            });
+        }
+    }
+
+    /// Alert override showing the list until the last alert is resolved
+    pub fn into_override(self) -> Override {
+        let label = match self.alerts.len() {
+            1 => "1 firing alert".to_string(),
+            n => format!("{} firing alerts", n),
+        };
+        Override {
+            priority: Priority::Alert,
+            label,
+            source: SourceConfig::Alerts(self),
+            until: None,
+        }
+    }
+
+    /// Add or update a firing alert
+    pub fn fire(&mut self, alert: FiringAlert) {
+        self.resolve(&alert.key);
+        self.alerts.push(alert);
+        self.alerts.sort_by_key(|alert| (alert.rank(), alert.since));
+    }
+
+    /// Remove a resolved alert
+    pub fn resolve(&mut self, key: &str) {
+        self.alerts.retain(|alert| alert.key != key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.alerts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alerts.is_empty()
+    }
+
+    /// Render the list below a red header bar
+    pub fn render(&self, width: u32, height: u32, theme: &Theme) -> DynamicImage {
+        let mut canvas = theme.canvas(width, height);
+        let unit = theme.unit(height);
+        let margin = unit as i32;
+        let right = width as i32 - margin;
+
+        let bar = unit * 3;
+        let top = bar + unit;
+        let row_height = ((height.saturating_sub(top)) / self.alerts.len().max(1) as u32)
+            .clamp(unit * 3, unit * 5);
+        let rows = ((height.saturating_sub(top)) / row_height) as usize;
+        let shown = self.alerts.len().min(rows);
+
+        let title = match self.alerts.len() {
+            1 => "1 alert firing".to_string(),
+            n => format!("{} alerts firing", n),
+        };
+        canvas.fill_rect(0, 0, width, bar, theme.negative);
+        canvas.text(margin, unit as i32 / 2, &title, unit * 2, theme.background);
+        if shown < self.alerts.len() {
+            let more = format!("+{} more", self.alerts.len() - shown);
+            canvas.text_right(right, unit as i32 / 2, &more, unit * 2, theme.background);
+        }
+
+        let name_size = (row_height * 2 / 5).max(10);
+        let summary_size = (row_height * 3 / 10).max(10);
+        let text_x = margin + unit as i32;
+        for (i, alert) in self.alerts.iter().take(shown).enumerate() {
+            let y = (top + row_height * i as u32) as i32;
+            let color = alert.color(theme);
+
+            // Severity stripe on the left
+            canvas.fill_rect(
+                margin,
+                y,
+                (unit / 2).max(2),
+                row_height.saturating_sub(unit / 2),
+                color,
+            );
+
+            let mut details = alert.severity.clone().unwrap_or_default();
+            if let Some(since) = alert.since {
+                let since = if since.date_naive() == Local::now().date_naive() {
+                    since.format("since %H:%M")
+                } else {
+                    since.format("since %d.%m. %H:%M")
+                };
+                details = [details, since.to_string()]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" · ");
+            }
+            let details_width = text_width(&details, summary_size);
+            canvas.text_right(right, y, &details, summary_size, color);
+
+            let name_width = ((right - text_x).max(0) as u32).saturating_sub(details_width + unit);
+            let name = fit_text(&alert.name, name_size, name_width);
+            canvas.text(text_x, y, &name, name_size, theme.text);
+
+            let summary_width = (right - text_x).max(0) as u32;
+            let summary = fit_text(&alert.summary, summary_size, summary_width);
+            canvas.text(
+                text_x,
+                y + name_size as i32 + unit as i32 / 4,
+                &summary,
+                summary_size,
+                theme.text,
+            );
+
+            theme.separator(
+                &mut canvas,
+                margin,
+                y + row_height as i32 - 1,
+                width.saturating_sub(unit * 2),
+            );
+        }
+
+        canvas.into_image()
+    }
+}
@@ -0,0 +1,532 @@
+//! Content bundle source.
+//!
+//! Downloads a zip or tar archive (optionally gzipped) holding an image and
+//! a `manifest.json`, so whoever produces the content also decides how it
+//! is presented, without touching the device config:
+//!
+//! ```json
+//! {"image": "menu.png", "caption": "Lunch menu", "valid_until": "2026-10-17T14:00:00+02:00", "dwell_secs": 900}
+//! ```
+//!
+//! All fields are optional. Without `image` the first PNG or JPEG in the
+//! archive is shown; a URL that serves a plain image is shown as is. An
+//! `X-Content-Expires` response header counts like `valid_until`.
+
+use super::{Presentation, SourceError};
+use crate::config::{Config, ConfigError};
+use crate::image_proc::download;
+use crate::image_proc::transform::fit_image;
+use crate::render::{Theme, fit_text};
+use crate::secrets;
+use bytes::Bytes;
+use chrono::{DateTime, Local};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Name of the manifest in the archive, in any directory
+const MANIFEST: &str = "manifest.json";
+
+/// Largest unpacked file
+const MAX_FILE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Most bytes unpacked from one archive without `max_image_memory_mb`
+const MAX_UNPACKED_BYTES: usize = 128 * 1024 * 1024;
+
+/// Longest caption drawn, in characters
+const MAX_CAPTION_CHARS: usize = 200;
+
+/// File of an archive, unpacked only when read
+struct Entry<'a> {
+    /// Path in the archive
+    name: String,
+    /// Zip compression method (0 = stored, 8 = deflated)
+    method: u16,
+    /// Content as stored in the archive
+    raw: &'a [u8],
+}
+
+impl Entry<'_> {
+    /// Unpacked content, counted against `budget`
+    fn read(&self, budget: &mut usize) -> Result<Vec<u8>, BundleError> {
+        let limit = MAX_FILE_BYTES.min(*budget);
+        let too_large = || BundleError::Archive(format!("{} is too large", self.name));
+        let content = match self.method {
+            0 if self.raw.len() > limit => return Err(too_large()),
+            0 => self.raw.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(self.raw, limit).map_err(
+                |e| match e.status {
+                    miniz_oxide::inflate::TINFLStatus::HasMoreOutput => too_large(),
+                    _ => BundleError::Archive(format!("{}: {}", self.name, e)),
+                },
+            )?,
+            method => {
+                return Err(BundleError::Archive(format!(
+                    "{} uses an unsupported compression method ({})",
+                    self.name, method
+                )));
+            }
+        };
+        *budget -= content.len();
+        Ok(content)
+    }
+}
+
+/// Bundle errors
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("Invalid bundle: {0}")]
+    Archive(String),
+
+    #[error("Bundle has no image{}", .0.as_ref().map(|name| format!(" named '{}'", name)).unwrap_or_default())]
+    NoImage(Option<String>),
+
+    #[error("Invalid bundle manifest: {0}")]
+    Manifest(String),
+
+    #[error("Bundle content expired at {0}")]
+    Expired(String),
+}
+
+/// Content bundle settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleSource {
+    /// URL of the archive (`.zip`, `.tar` or `.tar.gz`)
+    pub url: String,
+}
+
+/// Presentation settings of a bundle
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    /// Path or file name of the image in the archive
+    #[serde(default)]
+    image: Option<String>,
+    /// Line shown in a bar below the image
+    #[serde(default)]
+    caption: Option<String>,
+    /// RFC 3339 time after which the content must not be shown
+    #[serde(default)]
+    valid_until: Option<String>,
+    /// Seconds the content stays up before the next refresh
+    #[serde(default)]
+    dwell_secs: Option<u64>,
+}
+
+impl BundleSource {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = self.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Bundle url must start with http:// or https://".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Download and unpack the bundle
+    ///
+    /// With a caption the image is fitted to `width` x `height` and the
+    /// caption drawn below it; otherwise it keeps its own size. A dwell
+    /// time is at least `min_refresh_interval_min` long.
+    pub async fn fetch(
+        &self,
+        config: &Config,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<(DynamicImage, Presentation), SourceError> {
+        let download_config = config.download_config();
+
+        let url = secrets::expand(self.url.trim())?;
+        let body = download::download_bytes(&url, &download_config).await?;
+        // Archives can claim far more than they hold; only the manifest
+        // and the image are unpacked, together within the memory limit
+        let mut budget = config.max_image_memory().map_or(MAX_UNPACKED_BYTES, |max| {
+            max.min(MAX_UNPACKED_BYTES as u64) as usize
+        });
+        // Entries of a gzipped tar borrow the unpacked tar
+        let tar;
+        let entries = if body.bytes.starts_with(&[0x1f, 0x8b]) {
+            tar = gunzip(&body.bytes, &mut budget)?;
+            Some(untar(&tar)?)
+        } else {
+            unpack(&body.bytes)?
+        };
+        let Some(files) = entries else {
+            let image = download::decode_image(body.bytes, &download_config)?;
+            let presentation = Presentation {
+                valid_until: body.expires,
+                ..Presentation::default()
+            };
+            return Ok((image, presentation));
+        };
+
+        let manifest = match files
+            .iter()
+            .find(|entry| base_name(&entry.name) == MANIFEST)
+        {
+            Some(entry) => serde_json::from_slice::<Manifest>(&entry.read(&mut budget)?)
+                .map_err(|e| BundleError::Manifest(e.to_string()))?,
+            None => Manifest::default(),
+        };
+        let valid_until = manifest
+            .valid_until
+            .as_deref()
+            .map(|time| {
+                DateTime::parse_from_rfc3339(time.trim())
+                    .map(|time| time.with_timezone(&Local))
+                    .map_err(|e| BundleError::Manifest(format!("valid_until '{}': {}", time, e)))
+            })
+            .transpose()?
+            .into_iter()
+            .chain(body.expires)
+            .min();
+        if let Some(until) = valid_until
+            && until <= Local::now()
+        {
+            return Err(BundleError::Expired(until.format("%Y-%m-%d %H:%M").to_string()).into());
+        }
+
+        let image = match &manifest.image {
+            Some(wanted) => files
+                .iter()
+                .find(|entry| entry.name == *wanted || base_name(&entry.name) == wanted),
+            None => files.iter().find(|entry| is_image_name(&entry.name)),
+        };
+        let Some(image) = image else {
+            return Err(BundleError::NoImage(manifest.image).into());
+        };
+        tracing::debug!("Showing {} of the bundle", image.name);
+        let data = image.read(&mut budget)?;
+        let image = download::decode_image(Bytes::from(data), &download_config)?;
+
+        let image = match manifest.caption.as_deref().map(str::trim) {
+            Some(caption) if !caption.is_empty() => {
+                let caption: String = caption.chars().take(MAX_CAPTION_CHARS).collect();
+                with_caption(
+                    image,
+                    &caption,
+                    width,
+                    height,
+                    config.scale_to_fit,
+                    config.smart_crop,
+                    theme,
+                )
+            }
+            _ => image,
+        };
+        let dwell = manifest
+            .dwell_secs
+            .map(|secs| Duration::from_secs(secs.max(config.min_interval() as u64 * 60)));
+        let presentation = Presentation {
+            dwell,
+            next_slide: dwell.map(|dwell| Local::now() + dwell),
+            slide: Some(download::sha256_hex(&body.bytes)),
+            valid_until,
+        };
+        Ok((image, presentation))
+    }
+}
+
+/// `image` fitted above a caption bar at `width` x `height`
+fn with_caption(
+    image: DynamicImage,
+    caption: &str,
+    width: u32,
+    height: u32,
+    scale_to_fit: bool,
+    smart_crop: bool,
+    theme: &Theme,
+) -> DynamicImage {
+    let size = theme.unit(height);
+    let bar = (size * 2).min(height / 3);
+    let top = height - bar;
+
+    let mut canvas = theme.canvas(width, height);
+    let image = fit_image(image, width, top, scale_to_fit, smart_crop);
+    canvas.draw_image(
+        ((width - image.width().min(width)) / 2) as i32,
+        ((top - image.height().min(top)) / 2) as i32,
+        &image,
+    );
+    canvas.fill_rect(0, top as i32, width, bar, theme.header_background);
+    let line = fit_text(caption, size, width.saturating_sub(size));
+    canvas.text(
+        (size / 2) as i32,
+        (top + (bar - size) / 2) as i32,
+        &line,
+        size,
+        theme.header_text,
+    );
+    canvas.into_image()
+}
+
+/// Files of a zip or tar archive, or None if `data` is neither
+///
+/// Gzipped tar archives are unpacked by the caller first.
+fn unpack(data: &[u8]) -> Result<Option<Vec<Entry<'_>>>, BundleError> {
+    if data.starts_with(b"PK\x03\x04") {
+        return unzip(data).map(Some);
+    }
+    if data.len() >= 512 && &data[257..262] == b"ustar" {
+        return untar(data).map(Some);
+    }
+    Ok(None)
+}
+
+/// Files of a zip archive (stored or deflated entries), from its central
+/// directory
+fn unzip(data: &[u8]) -> Result<Vec<Entry<'_>>, BundleError> {
+    let invalid = || BundleError::Archive("damaged zip archive".to_string());
+    let offset = |at: usize, add: usize| at.checked_add(add).ok_or_else(invalid);
+    let u16_at = |at: usize| -> Result<usize, BundleError> {
+        let bytes = data.get(at..offset(at, 2)?).ok_or_else(invalid)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let u32_at = |at: usize| -> Result<usize, BundleError> {
+        let bytes = data.get(at..offset(at, 4)?).ok_or_else(invalid)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+
+    // End of central directory, followed by a comment of up to 64 KiB
+    let search_from = data.len().saturating_sub(22 + 0xFFFF);
+    let end = (search_from..data.len().saturating_sub(21))
+        .rev()
+        .find(|&at| data[at..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(invalid)?;
+    let entries = u16_at(end + 10)?;
+    let mut at = u32_at(end + 16)?;
+
+    let mut files = Vec::new();
+    for _ in 0..entries {
+        if u32_at(at)? != 0x0201_4b50 {
+            return Err(invalid());
+        }
+        let method = u16_at(offset(at, 10)?)? as u16;
+        let compressed = u32_at(offset(at, 20)?)?;
+        let name_len = u16_at(offset(at, 28)?)?;
+        let name_start = offset(at, 46)?;
+        let name_end = offset(name_start, name_len)?;
+        let next = offset(
+            name_end,
+            u16_at(offset(at, 30)?)? + u16_at(offset(at, 32)?)?,
+        )?;
+        let name = data.get(name_start..name_end).ok_or_else(invalid)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let local = u32_at(offset(at, 42)?)?;
+        at = next;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        let start = offset(
+            offset(local, 30)?,
+            u16_at(offset(local, 26)?)? + u16_at(offset(local, 28)?)?,
+        )?;
+        let raw = data
+            .get(start..offset(start, compressed)?)
+            .ok_or_else(invalid)?;
+        files.push(Entry { name, method, raw });
+    }
+    Ok(files)
+}
+
+/// Content of a gzip file, counted against `budget`
+fn gunzip(data: &[u8], budget: &mut usize) -> Result<Vec<u8>, BundleError> {
+    let invalid = || BundleError::Archive("damaged gzip data".to_string());
+    let flags = *data.get(3).ok_or_else(invalid)?;
+    let mut at = 10;
+    // FEXTRA, FNAME, FCOMMENT, FHCRC
+    if flags & 0x04 != 0 {
+        let len = data.get(at..at + 2).ok_or_else(invalid)?;
+        at += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let rest = data.get(at..).ok_or_else(invalid)?;
+            at += rest.iter().position(|&b| b == 0).ok_or_else(invalid)? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        at += 2;
+    }
+    let deflated = data.get(at..).ok_or_else(invalid)?;
+    let tar = Entry {
+        name: "gzip data".to_string(),
+        method: 8,
+        raw: deflated,
+    };
+    tar.read(budget)
+}
+
+/// Regular files of a tar archive
+fn untar(data: &[u8]) -> Result<Vec<Entry<'_>>, BundleError> {
+    let field = |header: &[u8], range: std::ops::Range<usize>| {
+        let raw = &header[range];
+        let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+        String::from_utf8_lossy(&raw[..end]).into_owned()
+    };
+    let damaged = || BundleError::Archive("damaged tar header".to_string());
+
+    let mut files = Vec::new();
+    let mut at = 0usize;
+    while let Some(header) = data.get(at..at + 512) {
+        // Two zero blocks end the archive
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size =
+            usize::from_str_radix(field(header, 124..136).trim(), 8).map_err(|_| damaged())?;
+        let mut name = field(header, 0..100);
+        let prefix = field(header, 345..500);
+        if &header[257..262] == b"ustar" && !prefix.is_empty() {
+            name = format!("{}/{}", prefix, name);
+        }
+        let start = at + 512;
+        // Whatever the entry type, its content must be in the archive
+        if size > data.len() - start {
+            return Err(BundleError::Archive(format!("{} is cut off", name)));
+        }
+        let next = size
+            .div_ceil(512)
+            .checked_mul(512)
+            .and_then(|padded| start.checked_add(padded))
+            .filter(|&next| next > at)
+            .ok_or_else(damaged)?;
+        at = next;
+
+        // Regular files only; links, directories and extended headers are skipped
+        if !matches!(header[156], b'0' | 0) {
+            continue;
+        }
+        files.push(Entry {
+            name,
+            method: 0,
+            raw: &data[start..start + size],
+        });
+    }
+    Ok(files)
+}
+
+/// File name without directories
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Whether `path` names an image the decoder reads
+///
+/// Hidden files are left out: macOS adds `._<name>` metadata files to
+/// archives it creates.
+fn is_image_name(path: &str) -> bool {
+    let name = base_name(path).to_ascii_lowercase();
+    !name.starts_with('.')
+        && [".png", ".jpg", ".jpeg"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tar header block for `name` of `kind` claiming `size` bytes (octal)
+    fn tar_header(name: &str, kind: u8, size: &str) -> Vec<u8> {
+        let mut header = vec![0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..124 + size.len()].copy_from_slice(size.as_bytes());
+        header[156] = kind;
+        header[257..262].copy_from_slice(b"ustar");
+        header
+    }
+
+    /// Zip whose `count` directory entries all point at one deflated file
+    /// of `size` zero bytes
+    fn zip_bomb(count: u16, size: usize) -> Vec<u8> {
+        let deflated = miniz_oxide::deflate::compress_to_vec(&vec![0; size], 9);
+        let mut zip = Vec::new();
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&[0; 22]);
+        zip.extend_from_slice(&1u16.to_le_bytes()); // name length
+        zip.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        zip.push(b'z');
+        zip.extend_from_slice(&deflated);
+
+        let central_at = zip.len() as u32;
+        for i in 0..count {
+            let name = format!("{:05}.png", i);
+            let mut entry = vec![0u8; 46];
+            entry[..4].copy_from_slice(&0x0201_4b50u32.to_le_bytes());
+            entry[10..12].copy_from_slice(&8u16.to_le_bytes());
+            entry[20..24].copy_from_slice(&(deflated.len() as u32).to_le_bytes());
+            entry[24..28].copy_from_slice(&(size as u32).to_le_bytes());
+            entry[28..30].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&entry);
+            zip.extend_from_slice(name.as_bytes());
+        }
+        let central_size = zip.len() as u32 - central_at;
+
+        zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&count.to_le_bytes());
+        zip.extend_from_slice(&count.to_le_bytes());
+        zip.extend_from_slice(&central_size.to_le_bytes());
+        zip.extend_from_slice(&central_at.to_le_bytes());
+        zip.extend_from_slice(&[0; 2]);
+        zip
+    }
+
+    #[test]
+    fn tar_entries_must_fit_in_the_archive() {
+        let mut tar = tar_header("image.png", b'0', "00000000004");
+        tar.extend_from_slice(b"\x89PNG");
+        tar.resize(1024, 0);
+        tar.extend_from_slice(&[0; 1024]);
+        let files = untar(&tar).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            (files[0].name.as_str(), files[0].raw),
+            ("image.png", &b"\x89PNG"[..])
+        );
+
+        // A skipped directory claiming more than the archive holds
+        let mut tar = tar_header("photos/", b'5', "77777777777");
+        tar.extend_from_slice(&tar_header("image.png", b'0', "00000000004"));
+        tar.extend_from_slice(&[0; 1024]);
+        assert!(matches!(untar(&tar), Err(BundleError::Archive(_))));
+    }
+
+    #[test]
+    fn zip_entries_are_unpacked_on_demand_within_the_budget() {
+        let zip = zip_bomb(1000, 4 * 1024 * 1024);
+        assert!(zip.len() < 100 * 1024);
+
+        // Listing the directory inflates nothing
+        let files = unzip(&zip).unwrap();
+        assert_eq!(files.len(), 1000);
+
+        let mut budget = 10 * 1024 * 1024;
+        assert_eq!(files[0].read(&mut budget).unwrap().len(), 4 * 1024 * 1024);
+        assert_eq!(files[1].read(&mut budget).unwrap().len(), 4 * 1024 * 1024);
+        assert!(matches!(
+            files[2].read(&mut budget),
+            Err(BundleError::Archive(message)) if message.contains("too large")
+        ));
+    }
+
+    #[test]
+    fn zip_offsets_outside_the_archive_are_errors() {
+        let mut zip = zip_bomb(1, 1024);
+        // Central directory offset near usize::MAX on 32-bit targets
+        let offset = zip.len() - 6;
+        zip[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(unzip(&zip), Err(BundleError::Archive(_))));
+
+        // Local header offset past the end
+        let mut zip = zip_bomb(1, 1024);
+        let central = zip.len() - 22 - 46 - 9;
+        zip[central + 42..central + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(unzip(&zip), Err(BundleError::Archive(_))));
+    }
+}
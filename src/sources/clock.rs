@@ -0,0 +1,188 @@
+//! Clock face source.
+//!
+//! Renders a large digital or an analog clock. The scheduler redraws the
+//! time region every minute through the partial refresh API on panels
+//! that support it; the regular schedule still does full refreshes.
+
+use crate::display::Region;
+use crate::render::{Canvas, Theme};
+use chrono::{DateTime, Local, Timelike};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Clock face style
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockStyle {
+    /// Large HH:MM digits
+    #[default]
+    Digital,
+    /// Dial with hour and minute hands
+    Analog,
+}
+
+/// Clock source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClockSource {
+    /// Face style
+    #[serde(default)]
+    pub style: ClockStyle,
+    /// Use 24-hour time for the digital face
+    #[serde(default = "default_true")]
+    pub hour24: bool,
+    /// Show the date below the time
+    #[serde(default = "default_true")]
+    pub show_date: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ClockSource {
+    /// Area redrawn by the per-minute partial refresh (content coordinates)
+    ///
+    /// Covers the time and date, everything else on the face is static.
+    pub fn time_region(&self, width: u32, height: u32) -> Region {
+        match self.style {
+            ClockStyle::Digital => {
+                let unit = (height / 24).max(8);
+                Region {
+                    x: 0,
+                    y: unit * 5,
+                    width,
+                    height: (unit * 16).min(height.saturating_sub(unit * 5)),
+                }
+            }
+            ClockStyle::Analog => {
+                let (cx, cy, radius) = dial_geometry(width, height);
+                Region {
+                    x: (cx - radius) as u32,
+                    y: (cy - radius) as u32,
+                    width: radius as u32 * 2,
+                    height: radius as u32 * 2,
+                }
+            }
+        }
+    }
+
+    /// Render the clock face for the current time
+    ///
+    /// Ignores the theme's text scale so the time always stays inside
+    /// [`time_region`](Self::time_region).
+    pub fn render(&self, width: u32, height: u32, theme: &Theme) -> DynamicImage {
+        let now = Local::now();
+        let mut canvas = theme.canvas(width, height);
+
+        match self.style {
+            ClockStyle::Digital => self.draw_digital(&mut canvas, &now, theme),
+            ClockStyle::Analog => self.draw_analog(&mut canvas, &now, theme),
+        }
+
+        canvas.into_image()
+    }
+
+    fn draw_digital(&self, canvas: &mut Canvas, now: &DateTime<Local>, theme: &Theme) {
+        let (width, height) = (canvas.width(), canvas.height());
+        let unit = (height / 24).max(8);
+        let center = width as i32 / 2;
+
+        let time = if self.hour24 {
+            now.format("%H:%M").to_string()
+        } else {
+            now.format("%-I:%M").to_string()
+        };
+
+        // Largest integer scale of the 10x20 font that fits the width
+        let size =
+            (width.saturating_sub(unit * 2) / time.len() as u32 / 10 * 20).clamp(20, unit * 10);
+        canvas.text_centered(center, (unit * 6) as i32, &time, size, theme.text);
+
+        if !self.hour24 {
+            let suffix = now.format("%p").to_string();
+            canvas.text_right(
+                width as i32 - unit as i32,
+                (unit * 6) as i32,
+                &suffix,
+                unit * 2,
+                theme.highlight,
+            );
+        }
+
+        if self.show_date {
+            canvas.text_centered(
+                center,
+                (unit * 17) as i32,
+                &now.format("%A, %d %B").to_string(),
+                unit * 2,
+                theme.accent,
+            );
+        }
+    }
+
+    fn draw_analog(&self, canvas: &mut Canvas, now: &DateTime<Local>, theme: &Theme) {
+        let (cx, cy, radius) = dial_geometry(canvas.width(), canvas.height());
+        let r = radius as f32;
+        let point = |angle: f32, length: f32| {
+            (
+                cx + (angle.sin() * length).round() as i32,
+                cy - (angle.cos() * length).round() as i32,
+            )
+        };
+
+        // Dial
+        canvas.fill_circle(cx, cy, radius as u32, theme.text);
+        canvas.fill_circle(cx, cy, (r * 0.96) as u32, theme.background);
+
+        // Hour ticks, quarters highlighted
+        for hour in 0..12 {
+            let angle = hour as f32 * PI / 6.0;
+            let (inner, color, thickness) = if hour % 3 == 0 {
+                (0.78, theme.highlight, (r / 25.0) as u32)
+            } else {
+                (0.85, theme.text, (r / 40.0) as u32)
+            };
+            canvas.line(
+                point(angle, r * inner),
+                point(angle, r * 0.92),
+                thickness.max(2),
+                color,
+            );
+        }
+
+        if self.show_date {
+            let unit = (radius as u32 / 8).max(8);
+            canvas.text_centered(
+                cx,
+                cy + (r * 0.4) as i32,
+                &now.format("%a %d").to_string(),
+                unit,
+                theme.accent,
+            );
+        }
+
+        // Hands
+        let minutes = now.minute() as f32;
+        let hours = (now.hour() % 12) as f32 + minutes / 60.0;
+        canvas.line(
+            (cx, cy),
+            point(hours * PI / 6.0, r * 0.5),
+            (r / 16.0).max(3.0) as u32,
+            theme.text,
+        );
+        canvas.line(
+            (cx, cy),
+            point(minutes * PI / 30.0, r * 0.8),
+            (r / 24.0).max(2.0) as u32,
+            theme.text,
+        );
+        canvas.fill_circle(cx, cy, (r / 20.0).max(3.0) as u32, theme.highlight);
+    }
+}
+
+/// Center and radius of the analog dial
+fn dial_geometry(width: u32, height: u32) -> (i32, i32, i32) {
+    let radius = (width.min(height) / 2) as i32 - (height / 24).max(8) as i32;
+    (width as i32 / 2, height as i32 / 2, radius.max(1))
+}
@@ -0,0 +1,311 @@
+//! Countdown card source.
+//!
+//! Renders "X days until <event>" cards from a configured list of dates,
+//! showing the next card on each refresh. Needs no external service.
+
+use super::SourceError;
+use crate::config::ConfigError;
+use crate::render::{Theme, fit_text};
+use chrono::{Datelike, Local, NaiveDate};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A dated event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CountdownEvent {
+    /// Event name (e.g., "Summer holidays")
+    pub name: String,
+    /// Event date as YYYY-MM-DD
+    pub date: String,
+    /// Repeat every year on the same day (birthdays, anniversaries)
+    #[serde(default)]
+    pub yearly: bool,
+}
+
+/// Countdown source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CountdownSource {
+    /// Events to cycle through
+    pub events: Vec<CountdownEvent>,
+}
+
+/// Index of the next card to show
+static NEXT_CARD: AtomicUsize = AtomicUsize::new(0);
+
+impl CountdownEvent {
+    fn parsed_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.date.trim(), "%Y-%m-%d").ok()
+    }
+
+    /// Next occurrence on or after `today` (None once a one-off event is past)
+    fn next_occurrence(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let date = self.parsed_date()?;
+        if !self.yearly || date >= today {
+            return (date >= today).then_some(date);
+        }
+
+        // Feb 29 falls back to Feb 28 in non-leap years
+        let in_year = |year: i32| {
+            date.with_year(year)
+                .or_else(|| NaiveDate::from_ymd_opt(year, date.month(), 28))
+        };
+        in_year(today.year())
+            .filter(|d| *d >= today)
+            .or_else(|| in_year(today.year() + 1))
+    }
+}
+
+impl CountdownSource {
+    /// Whether any events are configured
+    pub fn is_configured(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.events.is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Countdown source needs at least one event".to_string(),
+            ));
+        }
+
+        for event in &self.events {
+            if event.name.trim().is_empty() {
+                return Err(ConfigError::ValidationError(
+                    "Countdown events need a name".to_string(),
+                ));
+            }
+            if event.parsed_date().is_none() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid date '{}' for event '{}' (expected YYYY-MM-DD)",
+                    event.date, event.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the next upcoming event card
+    pub async fn render(
+        &self,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let today = Local::now().date_naive();
+        let turn = NEXT_CARD.fetch_add(1, Ordering::Relaxed);
+        let Some(card) = self.card(today, turn) else {
+            return Err(SourceError::NotConfigured(
+                "all countdown events are in the past".to_string(),
+            ));
+        };
+
+        Ok(render_card(&card, width, height, theme))
+    }
+
+    /// Card for the `turn`th refresh, cycling through upcoming events
+    ///
+    /// Past one-off events drop out of the rotation; None if all are past.
+    fn card(&self, today: NaiveDate, turn: usize) -> Option<Card<'_>> {
+        let upcoming: Vec<(&CountdownEvent, NaiveDate)> = self
+            .events
+            .iter()
+            .filter_map(|e| e.next_occurrence(today).map(|d| (e, d)))
+            .collect();
+        if upcoming.is_empty() {
+            return None;
+        }
+
+        let index = turn % upcoming.len();
+        let (event, date) = upcoming[index];
+        Some(Card {
+            event,
+            date,
+            days: (date - today).num_days(),
+            index,
+            count: upcoming.len(),
+        })
+    }
+}
+
+/// One event as shown on a card
+#[derive(Debug)]
+struct Card<'a> {
+    event: &'a CountdownEvent,
+    date: NaiveDate,
+    /// Days from today (0 = today)
+    days: i64,
+    /// Position in the rotation
+    index: usize,
+    count: usize,
+}
+
+/// Draw a countdown card
+fn render_card(card: &Card, width: u32, height: u32, theme: &Theme) -> DynamicImage {
+    let Card {
+        event,
+        date,
+        days,
+        index,
+        count,
+    } = *card;
+    let mut canvas = theme.canvas(width, height);
+    // Fixed grid: the card is laid out in 24 rows regardless of text scale
+    let unit = (height / 24).max(8);
+    let center = width as i32 / 2;
+    let max_width = width.saturating_sub(unit * 4);
+
+    // Accent frame
+    if theme.borders {
+        canvas.stroke_rect(0, 0, width, height, unit / 2, theme.accent);
+    }
+
+    let name = fit_text(event.name.trim(), unit * 3, max_width);
+
+    if days == 0 {
+        canvas.text_centered(
+            center,
+            (unit * 5) as i32,
+            "Today!",
+            unit * 8,
+            theme.highlight,
+        );
+        canvas.text_centered(center, (unit * 15) as i32, &name, unit * 3, theme.text);
+    } else {
+        let number = days.to_string();
+        let label = if days == 1 { "day until" } else { "days until" };
+
+        canvas.text_centered(
+            center,
+            (unit * 3) as i32,
+            &number,
+            unit * 10,
+            theme.highlight,
+        );
+        canvas.text_centered(center, (unit * 14) as i32, label, unit * 2, theme.text);
+        canvas.text_centered(center, (unit * 17) as i32, &name, unit * 3, theme.text);
+    }
+
+    canvas.text_centered(
+        center,
+        (unit * 21) as i32,
+        &date.format("%A, %d %B %Y").to_string(),
+        unit,
+        theme.accent,
+    );
+
+    // Position in the rotation
+    if count > 1 {
+        canvas.text_right(
+            width as i32 - unit as i32,
+            (unit * 22) as i32,
+            &format!("{}/{}", index + 1, count),
+            unit,
+            theme.text,
+        );
+    }
+
+    canvas.into_image()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, date: &str, yearly: bool) -> CountdownEvent {
+        CountdownEvent {
+            name: name.to_string(),
+            date: date.to_string(),
+            yearly,
+        }
+    }
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn occurrences_count_from_today() {
+        let today = day(2026, 10, 16);
+        for (event, expected) in [
+            (event("Today", "2026-10-16", false), Some(day(2026, 10, 16))),
+            (
+                event("Holidays", "2026-12-21", false),
+                Some(day(2026, 12, 21)),
+            ),
+            (event("Past", "2026-10-15", false), None),
+            (
+                event("Birthday", "1990-10-17", true),
+                Some(day(2026, 10, 17)),
+            ),
+            (
+                event("Anniversary", "2015-03-01", true),
+                Some(day(2027, 3, 1)),
+            ),
+            (
+                event("Leap day", "2024-02-29", true),
+                Some(day(2027, 2, 28)),
+            ),
+            (event("Unparsable", "16.10.2026", false), None),
+        ] {
+            assert_eq!(event.next_occurrence(today), expected, "{}", event.name);
+        }
+    }
+
+    #[test]
+    fn cards_cycle_through_upcoming_events() {
+        let source = CountdownSource {
+            events: vec![
+                event("Past", "2026-01-01", false),
+                event("Today", "2026-10-16", false),
+                event("Holidays", "2026-12-21", false),
+            ],
+        };
+        let today = day(2026, 10, 16);
+
+        let cards: Vec<(&str, i64, usize, usize)> = (0..3)
+            .map(|turn| {
+                let card = source.card(today, turn).unwrap();
+                (card.event.name.as_str(), card.days, card.index, card.count)
+            })
+            .collect();
+        assert_eq!(
+            cards,
+            [
+                ("Today", 0, 0, 2),
+                ("Holidays", 66, 1, 2),
+                ("Today", 0, 0, 2)
+            ]
+        );
+
+        let past = CountdownSource {
+            events: vec![event("Past", "2026-01-01", false)],
+        };
+        assert!(past.card(today, 0).is_none());
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        for (events, valid) in [
+            (vec![event("Holidays", "2026-12-21", false)], true),
+            (vec![], false),
+            (vec![event(" ", "2026-12-21", false)], false),
+            (vec![event("Holidays", "2026-13-01", false)], false),
+        ] {
+            let source = CountdownSource { events };
+            assert_eq!(source.validate().is_ok(), valid, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn small_cards_render() {
+        let source = CountdownSource {
+            events: vec![event("Holidays", "2026-12-21", false)],
+        };
+        let card = source.card(day(2026, 10, 16), 0).unwrap();
+        let img = render_card(&card, 20, 20, &Theme::default());
+        assert_eq!((img.width(), img.height()), (20, 20));
+    }
+}
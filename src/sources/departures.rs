@@ -0,0 +1,477 @@
+//! Public transport departures board source.
+//!
+//! Queries a HAFAS REST API (the `hafas-rest-api` format served by
+//! transport.rest instances such as `https://v6.db.transport.rest`) for
+//! the configured stops and renders the next departures as a board.
+
+use super::{ImageRequest, SourceError};
+use crate::config::ConfigError;
+use crate::image_proc::download::{DownloadConfig, fetch_json};
+use crate::render::{Theme, colors, fit_text};
+use chrono::{DateTime, FixedOffset, Local};
+use image::{DynamicImage, Rgb};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+/// A stop to query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransitStop {
+    /// Stop id as used by the API (e.g., IBNR/EVA number "8011160")
+    pub id: String,
+    /// Optional display name (defaults to the name reported by the API)
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Departures source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeparturesSource {
+    /// Base URL of the HAFAS REST API
+    pub base_url: String,
+    /// Stops to show departures for
+    pub stops: Vec<TransitStop>,
+    /// Number of departures to show
+    #[serde(default = "default_results")]
+    pub results: usize,
+    /// Look-ahead window in minutes
+    #[serde(default = "default_duration_min")]
+    pub duration_min: u32,
+}
+
+fn default_results() -> usize {
+    8
+}
+
+fn default_duration_min() -> u32 {
+    60
+}
+
+/// One departure, merged across stops
+#[derive(Debug, Clone)]
+struct DepartureRow {
+    when: DateTime<FixedOffset>,
+    delay_min: Option<i64>,
+    line: String,
+    product: String,
+    direction: String,
+    stop: String,
+    platform: Option<String>,
+    cancelled: bool,
+}
+
+impl DeparturesSource {
+    fn base_url(&self) -> &str {
+        self.base_url.trim().trim_end_matches('/')
+    }
+
+    fn departures_url(&self, stop: &TransitStop) -> String {
+        format!(
+            "{}/stops/{}/departures?duration={}&results={}&remarks=false",
+            self.base_url(),
+            stop.id.trim(),
+            self.duration_min,
+            self.results
+        )
+    }
+
+    /// Whether enough is configured to query departures
+    pub fn is_configured(&self) -> bool {
+        !self.base_url().is_empty() && !self.stops.is_empty()
+    }
+
+    /// Lightweight request used by source health checks
+    pub fn health_request(&self) -> Option<ImageRequest> {
+        self.stops.first().map(|stop| ImageRequest {
+            url: format!("{}/stops/{}", self.base_url(), stop.id.trim()),
+            headers: HeaderMap::new(),
+        })
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = self.base_url();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Departures base_url must start with http:// or https://".to_string(),
+            ));
+        }
+
+        if self.stops.is_empty() || self.stops.iter().any(|s| s.id.trim().is_empty()) {
+            return Err(ConfigError::ValidationError(
+                "Departures source needs at least one stop with an id".to_string(),
+            ));
+        }
+
+        if self.results == 0 || self.results > 30 {
+            return Err(ConfigError::ValidationError(
+                "Departures results must be between 1 and 30".to_string(),
+            ));
+        }
+
+        if self.duration_min == 0 || self.duration_min > 1440 {
+            return Err(ConfigError::ValidationError(
+                "Departures duration_min must be between 1 and 1440".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Query all stops and render the departures board
+    pub async fn render(
+        &self,
+        download: &DownloadConfig,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let mut rows = Vec::new();
+
+        for stop in &self.stops {
+            let response: DeparturesResponse =
+                fetch_json(&self.departures_url(stop), &HeaderMap::new(), download).await?;
+
+            rows.extend(
+                response
+                    .into_departures()
+                    .into_iter()
+                    .filter_map(|dep| departure_row(dep, stop)),
+            );
+        }
+
+        rows.sort_by_key(|r| r.when);
+        rows.truncate(self.results);
+
+        Ok(render_board(
+            &rows,
+            self.stops.len() > 1,
+            width,
+            height,
+            theme,
+        ))
+    }
+}
+
+/// Board row for a departure from `stop`, skipping ones without a time
+fn departure_row(dep: HafasDeparture, stop: &TransitStop) -> Option<DepartureRow> {
+    let when = dep
+        .when
+        .as_deref()
+        .or(dep.planned_when.as_deref())
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())?;
+
+    Some(DepartureRow {
+        when,
+        delay_min: dep.delay.map(|d| d / 60),
+        line: dep
+            .line
+            .as_ref()
+            .and_then(|l| l.name.clone())
+            .unwrap_or_default(),
+        product: dep.line.and_then(|l| l.product).unwrap_or_default(),
+        direction: dep.direction.unwrap_or_default(),
+        stop: stop
+            .name
+            .clone()
+            .or_else(|| dep.stop.and_then(|s| s.name))
+            .unwrap_or_else(|| stop.id.clone()),
+        platform: dep.platform,
+        cancelled: dep.cancelled.unwrap_or(false),
+    })
+}
+
+/// Badge color for a product type
+fn product_color(product: &str, theme: &Theme) -> Rgb<u8> {
+    let color = match product {
+        "suburban" => colors::GREEN,
+        "subway" => colors::BLUE,
+        "tram" => colors::RED,
+        "bus" => colors::ORANGE,
+        "ferry" => colors::BLUE,
+        _ => theme.text,
+    };
+    theme.icon(color)
+}
+
+/// Draw the departures board
+fn render_board(
+    rows: &[DepartureRow],
+    show_stop: bool,
+    width: u32,
+    height: u32,
+    theme: &Theme,
+) -> DynamicImage {
+    let mut canvas = theme.canvas(width, height);
+    let unit = theme.unit(height);
+    let margin = unit as i32;
+    let now = Local::now();
+
+    theme.header(
+        &mut canvas,
+        unit,
+        "Departures",
+        &now.format("%H:%M").to_string(),
+    );
+
+    if rows.is_empty() {
+        canvas.text_centered(
+            width as i32 / 2,
+            (height / 2) as i32,
+            "No departures",
+            unit * 2,
+            theme.text,
+        );
+        return canvas.into_image();
+    }
+
+    let top = unit * 4;
+    let row_height = (height.saturating_sub(top) / rows.len() as u32).min(unit * 3);
+    let text_size = (row_height * 2 / 3).max(10);
+    let time_col = margin;
+    let line_col = time_col + (unit * 6) as i32;
+    let badge_width = unit * 5;
+    let dir_col = line_col + (badge_width + unit) as i32;
+    let right = width as i32 - margin;
+    let minutes_width = unit * 7;
+
+    for (i, row) in rows.iter().enumerate() {
+        let y = (top + row_height * i as u32) as i32;
+        let text_y = y + (row_height.saturating_sub(text_size) / 2) as i32;
+
+        // Departure time, delay in red
+        let time = row.when.format("%H:%M").to_string();
+        let time_color = match row.delay_min {
+            Some(d) if d > 0 => theme.negative,
+            _ => theme.text,
+        };
+        canvas.text(time_col, text_y, &time, text_size, time_color);
+
+        // Line badge
+        canvas.fill_rect(
+            line_col,
+            y + 2,
+            badge_width,
+            row_height.saturating_sub(4),
+            product_color(&row.product, theme),
+        );
+        let line = fit_text(&row.line, text_size, badge_width);
+        canvas.text_centered(
+            line_col + badge_width as i32 / 2,
+            text_y,
+            &line,
+            text_size,
+            theme.background,
+        );
+
+        // Direction (and stop when showing several)
+        let mut direction = row.direction.clone();
+        if show_stop {
+            direction = format!("{} ({})", direction, row.stop);
+        }
+        if let Some(platform) = &row.platform {
+            direction = format!("{} [{}]", direction, platform);
+        }
+        let dir_width = ((right - dir_col).max(0) as u32).saturating_sub(minutes_width);
+        let direction = fit_text(&direction, text_size, dir_width);
+        canvas.text(dir_col, text_y, &direction, text_size, theme.text);
+
+        // Countdown or cancellation
+        let (label, color) = if row.cancelled {
+            ("cancelled".to_string(), theme.negative)
+        } else {
+            let minutes = (row.when.with_timezone(&Local) - now).num_minutes().max(0);
+            let label = match row.delay_min {
+                Some(d) if d > 0 => format!("{} min +{}", minutes, d),
+                _ => format!("{} min", minutes),
+            };
+            (label, theme.text)
+        };
+        canvas.text_right(right, text_y, &label, text_size, color);
+
+        theme.separator(
+            &mut canvas,
+            margin,
+            y + row_height as i32 - 1,
+            width.saturating_sub(unit * 2),
+        );
+    }
+
+    canvas.into_image()
+}
+
+// HAFAS REST API payloads (only the fields we use)
+
+/// v6 wraps the list in an object, older versions return a bare array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DeparturesResponse {
+    Wrapped { departures: Vec<HafasDeparture> },
+    Bare(Vec<HafasDeparture>),
+}
+
+impl DeparturesResponse {
+    fn into_departures(self) -> Vec<HafasDeparture> {
+        match self {
+            DeparturesResponse::Wrapped { departures } => departures,
+            DeparturesResponse::Bare(departures) => departures,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HafasDeparture {
+    when: Option<String>,
+    planned_when: Option<String>,
+    /// Delay in seconds
+    delay: Option<i64>,
+    direction: Option<String>,
+    line: Option<HafasLine>,
+    stop: Option<HafasStop>,
+    platform: Option<String>,
+    cancelled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct HafasLine {
+    name: Option<String>,
+    product: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HafasStop {
+    name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: &str, name: Option<&str>) -> TransitStop {
+        TransitStop {
+            id: id.to_string(),
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        let valid = DeparturesSource {
+            base_url: "https://v6.db.transport.rest/".to_string(),
+            stops: vec![stop("8011160", None)],
+            results: default_results(),
+            duration_min: default_duration_min(),
+        };
+        assert!(valid.validate().is_ok());
+        assert_eq!(
+            valid.departures_url(&valid.stops[0]),
+            "https://v6.db.transport.rest/stops/8011160/departures?duration=60&results=8&remarks=false"
+        );
+
+        let mut invalid = vec![
+            DeparturesSource {
+                base_url: "v6.db.transport.rest".to_string(),
+                ..valid.clone()
+            },
+            DeparturesSource {
+                stops: Vec::new(),
+                ..valid.clone()
+            },
+            DeparturesSource {
+                stops: vec![stop(" ", None)],
+                ..valid.clone()
+            },
+        ];
+        for (results, duration_min) in [(0, 60), (31, 60), (8, 0), (8, 1441)] {
+            invalid.push(DeparturesSource {
+                results,
+                duration_min,
+                ..valid.clone()
+            });
+        }
+        for source in invalid {
+            assert!(source.validate().is_err(), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn both_response_shapes_give_rows() {
+        let departure = r#"{
+            "when": "2026-03-04T12:05:00+01:00",
+            "plannedWhen": "2026-03-04T12:03:00+01:00",
+            "delay": 120,
+            "direction": "Spandau",
+            "line": {"name": "S3", "product": "suburban"},
+            "stop": {"name": "Berlin Hbf"},
+            "platform": "15"
+        }"#;
+        for body in [
+            format!(r#"{{"departures": [{}]}}"#, departure),
+            format!("[{}]", departure),
+        ] {
+            let response: DeparturesResponse = serde_json::from_str(&body).unwrap();
+            let departures = response.into_departures();
+            assert_eq!(departures.len(), 1, "{}", body);
+        }
+    }
+
+    #[test]
+    fn rows_fall_back_on_planned_time_and_stop_names() {
+        let parse = |json: &str| serde_json::from_str::<HafasDeparture>(json).unwrap();
+
+        // Live time, delay in whole minutes, configured stop name first
+        let dep = parse(
+            r#"{"when": "2026-03-04T12:05:00+01:00", "delay": 150,
+                "line": {"name": "S3", "product": "suburban"},
+                "stop": {"name": "Berlin Hbf"}}"#,
+        );
+        let row = departure_row(dep, &stop("8011160", Some("Home"))).unwrap();
+        assert_eq!(row.when.to_rfc3339(), "2026-03-04T12:05:00+01:00");
+        assert_eq!(row.delay_min, Some(2));
+        assert_eq!(
+            (row.line.as_str(), row.product.as_str()),
+            ("S3", "suburban")
+        );
+        assert_eq!(row.stop, "Home");
+        assert!(!row.cancelled);
+
+        // Cancelled trips only have a planned time
+        let dep = parse(
+            r#"{"when": null, "plannedWhen": "2026-03-04T12:10:00+01:00",
+                "cancelled": true, "stop": {"name": "Berlin Hbf"}}"#,
+        );
+        let row = departure_row(dep, &stop("8011160", None)).unwrap();
+        assert_eq!(row.when.to_rfc3339(), "2026-03-04T12:10:00+01:00");
+        assert_eq!(row.stop, "Berlin Hbf");
+        assert!(row.cancelled);
+
+        // Without any name the stop id is shown; without a time, no row
+        let row = departure_row(
+            parse(r#"{"when": "2026-03-04T12:10:00+01:00"}"#),
+            &stop("8011160", None),
+        )
+        .unwrap();
+        assert_eq!(row.stop, "8011160");
+        assert!(departure_row(parse(r#"{"when": "soon"}"#), &stop("1", None)).is_none());
+    }
+
+    #[test]
+    fn line_badges_follow_the_icon_style() {
+        let theme = Theme::default();
+        for (product, color) in [
+            ("suburban", colors::GREEN),
+            ("subway", colors::BLUE),
+            ("tram", colors::RED),
+            ("bus", colors::ORANGE),
+            ("regional", theme.text),
+        ] {
+            assert_eq!(product_color(product, &theme), color, "{}", product);
+        }
+
+        let mono = Theme {
+            icon_style: crate::render::theme::IconStyle::Mono,
+            ..Theme::default()
+        };
+        assert_eq!(product_color("tram", &mono), mono.text);
+    }
+}
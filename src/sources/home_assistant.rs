@@ -0,0 +1,155 @@
+//! Home Assistant camera snapshot source.
+//!
+//! Fetches the current frame of a camera entity through Home Assistant's
+//! `camera_proxy` endpoint, authenticating with a long-lived access token.
+
+use super::{ImageRequest, SourceError};
+use crate::config::ConfigError;
+use crate::secrets;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+/// Home Assistant camera source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HomeAssistantSource {
+    /// Base URL of the Home Assistant instance (e.g., "http://homeassistant.local:8123")
+    pub base_url: String,
+    /// Long-lived access token (Profile → Security in the HA UI)
+    pub token: String,
+    /// Camera entity id (e.g., "camera.front_door")
+    pub entity_id: String,
+}
+
+impl HomeAssistantSource {
+    /// Snapshot URL for the configured entity
+    pub fn snapshot_url(&self) -> String {
+        format!(
+            "{}/api/camera_proxy/{}",
+            self.base_url.trim().trim_end_matches('/'),
+            self.entity_id.trim()
+        )
+    }
+
+    /// Build the authenticated snapshot request
+    pub fn image_request(&self) -> Result<ImageRequest, SourceError> {
+        if self.base_url.trim().is_empty() || self.entity_id.trim().is_empty() {
+            return Err(SourceError::NotConfigured(
+                "Home Assistant base_url and entity_id are required".to_string(),
+            ));
+        }
+
+        let token = secrets::resolve(&self.token)?;
+        let mut headers = HeaderMap::new();
+        let auth = HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|_| {
+            SourceError::NotConfigured(
+                "Home Assistant token contains invalid characters".to_string(),
+            )
+        })?;
+        headers.insert(AUTHORIZATION, auth);
+
+        Ok(ImageRequest {
+            url: self.snapshot_url(),
+            headers,
+        })
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = self.base_url.trim();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Home Assistant base_url must start with http:// or https://".to_string(),
+            ));
+        }
+
+        if !self.entity_id.trim().starts_with("camera.") {
+            return Err(ConfigError::ValidationError(format!(
+                "Home Assistant entity '{}' is not a camera entity",
+                self.entity_id
+            )));
+        }
+
+        if self.token.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Home Assistant token is required".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::SecretError;
+
+    fn source(base_url: &str, token: &str, entity_id: &str) -> HomeAssistantSource {
+        HomeAssistantSource {
+            base_url: base_url.to_string(),
+            token: token.to_string(),
+            entity_id: entity_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn snapshot_request_carries_the_resolved_token() {
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var("EPAPER_TEST_HA_TOKEN", "ha-long-lived-token") };
+        let ha = source(
+            " http://homeassistant.local:8123/ ",
+            "${ENV:EPAPER_TEST_HA_TOKEN}",
+            " camera.front_door",
+        );
+        let request = ha.image_request().unwrap();
+        assert_eq!(
+            request.url,
+            "http://homeassistant.local:8123/api/camera_proxy/camera.front_door"
+        );
+        assert_eq!(request.headers[AUTHORIZATION], "Bearer ha-long-lived-token");
+    }
+
+    #[test]
+    fn incomplete_requests_are_errors() {
+        let missing_entity = source("http://ha.local:8123", "token", " ");
+        assert!(matches!(
+            missing_entity.image_request(),
+            Err(SourceError::NotConfigured(_))
+        ));
+
+        let missing_env = source(
+            "http://ha.local:8123",
+            "${ENV:EPAPER_TEST_HA_TOKEN_UNSET}",
+            "camera.door",
+        );
+        assert!(matches!(
+            missing_env.image_request(),
+            Err(SourceError::Secret(SecretError::MissingEnv(_)))
+        ));
+
+        let bad_token = source("http://ha.local:8123", "line\nbreak", "camera.door");
+        assert!(matches!(
+            bad_token.image_request(),
+            Err(SourceError::NotConfigured(_))
+        ));
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        for (ha, valid) in [
+            (source("http://ha.local:8123", "token", "camera.door"), true),
+            (
+                source("https://ha.example.org", "token", "camera.door"),
+                true,
+            ),
+            (source("ha.local:8123", "token", "camera.door"), false),
+            (
+                source("http://ha.local:8123", "token", "sensor.door"),
+                false,
+            ),
+            (source("http://ha.local:8123", " ", "camera.door"), false),
+        ] {
+            assert_eq!(ha.validate().is_ok(), valid, "{:?}", ha);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Layout compositor.
+//!
+//! Divides the screen into rectangular zones, each bound to its own
+//! source (image, clock, text, ...). Zones are fetched/rendered at their
+//! own size and composited into one frame before dithering.
+//!
+//! Zones may set their own `refresh_min`; until that has elapsed the
+//! cached image is reused, so slow-changing zones are not re-downloaded
+//! on every panel refresh.
+//!
+//! Zones may also set their own `dither` mode (e.g. none for a text zone,
+//! Floyd-Steinberg for a photo). Such zones are turned into panel colors
+//! while compositing, so dithering the whole frame leaves them as they
+//! are.
+
+use super::{SourceConfig, SourceError, fetch_source_image};
+use crate::config::{Config, ConfigError};
+use crate::display::PixelFormat;
+use crate::image_proc::dither::{self, DitherMode};
+use crate::image_proc::transform::fit_image;
+use crate::render::{Canvas, Theme, fit_text, icons};
+use image::{DynamicImage, RgbImage};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A rectangle of the screen bound to a source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Zone {
+    /// Left edge in pixels (content coordinates, before rotation)
+    pub x: u32,
+    /// Top edge in pixels
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Source drawn into the zone
+    pub source: SourceConfig,
+    /// Draw a border around the zone in the theme's text color
+    #[serde(default)]
+    pub border: bool,
+    /// Minutes a fetched image stays valid (None = fetch on every refresh)
+    #[serde(default)]
+    pub refresh_min: Option<u32>,
+    /// Dither mode of the zone (None = dithered with the whole frame)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dither: Option<DitherMode>,
+}
+
+/// Smallest zone edge, in base text units (renderers lay out a header
+/// and a few rows of that size)
+const MIN_ZONE_UNITS: u32 = 6;
+
+/// Last image fetched for a zone
+struct CachedZone {
+    /// Zone settings the image was fetched with (a config change invalidates it)
+    zone: Zone,
+    fetched_at: Instant,
+    image: RgbImage,
+}
+
+impl CachedZone {
+    /// Whether the image was fetched for this zone at this size
+    fn matches(&self, zone: &Zone, width: u32, height: u32) -> bool {
+        self.zone == *zone && self.image.dimensions() == (width, height)
+    }
+
+    /// Whether the image is still within the zone's refresh interval
+    fn is_fresh(&self) -> bool {
+        self.zone
+            .refresh_min
+            .is_some_and(|min| self.fetched_at.elapsed() < Duration::from_secs(min as u64 * 60))
+    }
+}
+
+/// Zone images by zone index
+static ZONE_CACHE: Lazy<Mutex<Vec<Option<CachedZone>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Layout source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LayoutSource {
+    /// Zones, drawn in order (later zones overlap earlier ones)
+    pub zones: Vec<Zone>,
+}
+
+impl LayoutSource {
+    /// Whether any zone is configured
+    pub fn is_configured(&self) -> bool {
+        !self.zones.is_empty()
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.zones.is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Layout needs at least one zone".to_string(),
+            ));
+        }
+
+        for (i, zone) in self.zones.iter().enumerate() {
+            let min_size = Theme::MIN_UNIT * MIN_ZONE_UNITS;
+            if zone.width < min_size || zone.height < min_size {
+                return Err(ConfigError::ValidationError(format!(
+                    "Layout zone {} is smaller than {}x{} pixels",
+                    i + 1,
+                    min_size,
+                    min_size
+                )));
+            }
+            if matches!(zone.source, SourceConfig::Layout(_)) {
+                return Err(ConfigError::ValidationError(
+                    "Layouts cannot be nested".to_string(),
+                ));
+            }
+            zone.source.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch stale zones and composite all zones into one frame
+    ///
+    /// A failing zone keeps its last image if there is one, otherwise it
+    /// is drawn as a placeholder so the rest of the dashboard still updates.
+    pub async fn compose(
+        &self,
+        config: &Config,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let mut canvas = theme.canvas(width, height);
+
+        let mut cache = std::mem::take(&mut *ZONE_CACHE.lock().unwrap_or_else(|e| e.into_inner()));
+        cache.resize_with(self.zones.len(), || None);
+
+        for (zone, cached) in self.zones.iter().zip(cache.iter_mut()) {
+            // Clip to the canvas
+            if zone.x >= width || zone.y >= height {
+                tracing::warn!("Layout zone at {},{} is off screen", zone.x, zone.y);
+                continue;
+            }
+            let zone_width = zone.width.min(width - zone.x);
+            let zone_height = zone.height.min(height - zone.y);
+
+            let usable = cached
+                .as_ref()
+                .filter(|c| c.matches(zone, zone_width, zone_height));
+            if let Some(cached) = usable.filter(|c| c.is_fresh()) {
+                tracing::debug!("Layout zone ({}) cached", zone.source.kind_name());
+                draw_zone(&mut canvas, zone, &cached.image, config);
+            } else {
+                match fetch_source_image(&zone.source, config, zone_width, zone_height).await {
+                    Ok(img) => {
+                        let img = fit_image(
+                            img,
+                            zone_width,
+                            zone_height,
+                            config.scale_to_fit,
+                            config.smart_crop,
+                        );
+                        draw_zone(&mut canvas, zone, &img, config);
+                        *cached = Some(CachedZone {
+                            zone: zone.clone(),
+                            fetched_at: Instant::now(),
+                            image: img,
+                        });
+                    }
+                    Err(e) => {
+                        tracing::warn!("Layout zone ({}) failed: {}", zone.source.kind_name(), e);
+                        match usable {
+                            Some(stale) => draw_zone(&mut canvas, zone, &stale.image, config),
+                            None => draw_placeholder(
+                                &mut canvas,
+                                theme,
+                                zone,
+                                zone_width,
+                                zone_height,
+                                zone.source.kind_name(),
+                            ),
+                        }
+                    }
+                }
+            }
+
+            if zone.border {
+                canvas.stroke_rect(
+                    zone.x as i32,
+                    zone.y as i32,
+                    zone_width,
+                    zone_height,
+                    2,
+                    theme.text,
+                );
+            }
+        }
+
+        *ZONE_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = cache;
+        Ok(canvas.into_image())
+    }
+}
+
+/// Draw the image of `zone`, turned into panel colors if it has its own
+/// dither mode
+fn draw_zone(canvas: &mut Canvas, zone: &Zone, img: &RgbImage, config: &Config) {
+    let Some(mode) = zone.dither else {
+        canvas.draw_image(zone.x as i32, zone.y as i32, img);
+        return;
+    };
+    let format = PixelFormat::packed4(config.panel.spec().palette);
+    let buffer = match mode {
+        DitherMode::FloydSteinberg => dither::dither_image(img, format, config.snap_threshold),
+        DitherMode::None => dither::nearest_colors(img, format),
+    };
+    let img = dither::unpack_to_rgb(&buffer, img.width(), img.height(), format);
+    canvas.draw_image(zone.x as i32, zone.y as i32, &img);
+}
+
+/// Mark a zone whose source could not be fetched
+fn draw_placeholder(
+    canvas: &mut Canvas,
+    theme: &Theme,
+    zone: &Zone,
+    width: u32,
+    height: u32,
+    name: &str,
+) {
+    canvas.fill_rect(
+        zone.x as i32,
+        zone.y as i32,
+        width,
+        height,
+        theme.background,
+    );
+    canvas.stroke_rect(
+        zone.x as i32,
+        zone.y as i32,
+        width,
+        height,
+        2,
+        theme.negative,
+    );
+
+    let size = (height / 6).clamp(10, 20);
+    let label = fit_text(
+        &format!("{} unavailable", name),
+        size,
+        width.saturating_sub(8),
+    );
+    let center = (zone.x + width / 2) as i32;
+
+    // Warning icon above the label when the zone is tall enough
+    let icon = icons::get("warning").filter(|_| height >= size * 5);
+    let icon_size = if icon.is_some() { size * 2 } else { 0 };
+    let gap = if icon.is_some() { size / 2 } else { 0 };
+    let top = zone.y + height.saturating_sub(icon_size + gap + size) / 2;
+    if let Some(icon) = icon {
+        canvas.icon(
+            center - icon_size as i32 / 2,
+            top as i32,
+            icon,
+            icon_size,
+            theme,
+        );
+    }
+
+    canvas.text_centered(
+        center,
+        (top + icon_size + gap) as i32,
+        &label,
+        size,
+        theme.negative,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::clock::{ClockSource, ClockStyle};
+    use crate::sources::text::TextSource;
+
+    fn zone(width: u32, height: u32) -> Zone {
+        Zone {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            source: SourceConfig::Text(TextSource {
+                text: "Hello".to_string(),
+                size: 0,
+                color: None,
+                background: None,
+                align: Default::default(),
+            }),
+            border: false,
+            refresh_min: None,
+            dither: None,
+        }
+    }
+
+    #[test]
+    fn zones_need_room_for_a_few_text_rows() {
+        let min = Theme::MIN_UNIT * MIN_ZONE_UNITS;
+        for (width, height, valid) in [
+            (min, min, true),
+            (400, 240, true),
+            (min - 1, 240, false),
+            (400, min - 1, false),
+            (400, 0, false),
+        ] {
+            let layout = LayoutSource {
+                zones: vec![zone(width, height)],
+            };
+            assert_eq!(layout.validate().is_ok(), valid, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn smallest_zone_renders_without_overflow() {
+        let min = Theme::MIN_UNIT * MIN_ZONE_UNITS;
+        let theme = Theme {
+            text_scale: 200,
+            ..Theme::default()
+        };
+        for style in [ClockStyle::Digital, ClockStyle::Analog] {
+            let clock = ClockSource {
+                style,
+                hour24: false,
+                show_date: true,
+            };
+            let img = clock.render(min, min, &theme);
+            assert_eq!((img.width(), img.height()), (min, min));
+            let region = clock.time_region(min, min);
+            assert!(region.y + region.height <= min);
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Content sources.
+//!
+//! A source produces the image shown on the panel. The default source
+//! downloads `image_url`; other sources fetch from specific services.
+//! Sources are configured via the `source` object in the config file.
+
+#[cfg(feature = "alerts")]
+pub mod alerts;
+pub mod bundle;
+#[cfg(feature = "renderers")]
+pub mod clock;
+#[cfg(feature = "renderers")]
+pub mod countdown;
+#[cfg(feature = "renderers")]
+pub mod departures;
+pub mod home_assistant;
+#[cfg(feature = "renderers")]
+pub mod layout;
+pub mod playlist;
+#[cfg(feature = "renderers")]
+pub mod printer;
+pub mod pushed;
+pub mod sample;
+#[cfg(feature = "renderers")]
+pub mod tasks;
+pub mod text;
+#[cfg(feature = "renderers")]
+pub mod ticker;
+
+use crate::config::{Config, ConfigError};
+use crate::display::Region;
+use crate::image_proc::DownloadError;
+use crate::image_proc::download::{DownloadConfig, download_image_with_config};
+use crate::image_proc::photo::{self, PhotoCaption};
+use crate::render::{Theme, fonts};
+use crate::secrets::{self, SecretError};
+#[cfg(feature = "alerts")]
+use alerts::AlertList;
+use bundle::{BundleError, BundleSource};
+use chrono::{DateTime, Local};
+#[cfg(feature = "renderers")]
+use clock::ClockSource;
+#[cfg(feature = "renderers")]
+use countdown::CountdownSource;
+#[cfg(feature = "renderers")]
+use departures::DeparturesSource;
+use home_assistant::HomeAssistantSource;
+use image::DynamicImage;
+#[cfg(feature = "renderers")]
+use layout::LayoutSource;
+use playlist::{PlaylistError, PlaylistSource};
+#[cfg(feature = "renderers")]
+use printer::PrinterSource;
+use pushed::PushedImage;
+use reqwest::header::{HeaderMap, USER_AGENT};
+use sample::SampleImage;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+#[cfg(feature = "renderers")]
+use tasks::TasksSource;
+use text::TextSource;
+use thiserror::Error;
+#[cfg(feature = "renderers")]
+use ticker::TickerSource;
+
+/// Source errors
+#[derive(Error, Debug)]
+pub enum SourceError {
+    #[error("{0}")]
+    Download(#[from] DownloadError),
+
+    #[error("Source not configured: {0}")]
+    NotConfigured(String),
+
+    #[error("{0}")]
+    Secret(#[from] SecretError),
+
+    #[error("{0}")]
+    Bundle(#[from] BundleError),
+
+    #[error("{0}")]
+    Playlist(#[from] PlaylistError),
+
+    #[error("Content expired at {0}")]
+    Expired(String),
+}
+
+/// Configured content source
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceConfig {
+    /// Download the image from `image_url`
+    #[default]
+    Url,
+    /// Download an image from its own URL (for layout zones)
+    Image { url: String },
+    /// Zip or tar archive with an image and a manifest for its presentation
+    Bundle(BundleSource),
+    /// Slides of a JSON playlist on a server, rotated by the clock
+    Playlist(PlaylistSource),
+    /// Snapshot of a Home Assistant camera entity
+    HomeAssistant(HomeAssistantSource),
+    /// Rendered 3D printer status (OctoPrint / Moonraker)
+    #[cfg(feature = "renderers")]
+    Printer(PrinterSource),
+    /// Rendered public transport departures board (HAFAS REST)
+    #[cfg(feature = "renderers")]
+    Departures(DeparturesSource),
+    /// Rendered stock/crypto quote board
+    #[cfg(feature = "renderers")]
+    Ticker(TickerSource),
+    /// Rendered Todoist / CalDAV task checklist
+    #[cfg(feature = "renderers")]
+    Tasks(TasksSource),
+    /// Rendered "days until" event cards
+    #[cfg(feature = "renderers")]
+    Countdown(CountdownSource),
+    /// Rendered clock face, time updated every minute by partial refresh
+    #[cfg(feature = "renderers")]
+    Clock(ClockSource),
+    /// Rendered static text
+    Text(TextSource),
+    /// Screen divided into zones, each showing its own source
+    #[cfg(feature = "renderers")]
+    Layout(LayoutSource),
+    /// Firing alerts pushed via `POST /api/v1/alerts` (never configured)
+    #[cfg(feature = "alerts")]
+    #[serde(skip)]
+    Alerts(AlertList),
+    /// Built-in sample image shown by the demo mode (never configured)
+    #[serde(skip)]
+    Sample(SampleImage),
+    /// Image pushed via `POST /api/v1/image` (never configured)
+    #[serde(skip)]
+    Pushed(PushedImage),
+}
+
+/// How the content asks to be presented, from a bundle manifest or
+/// playlist
+///
+/// A slide stays up for its dwell time, independent of the schedule's
+/// interval, which keeps refreshing it meanwhile.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Presentation {
+    /// Time the slide stays up
+    pub dwell: Option<Duration>,
+    /// Time the next slide is due
+    pub next_slide: Option<DateTime<Local>>,
+    /// Identity of the slide; a refresh that shows the same slide again
+    /// keeps its dwell timer running
+    pub slide: Option<String>,
+    /// Time it is outdated
+    pub valid_until: Option<DateTime<Local>>,
+}
+
+/// HTTP request for a source's image or API endpoint
+#[derive(Debug, Clone)]
+pub struct ImageRequest {
+    pub url: String,
+    pub headers: HeaderMap,
+}
+
+impl SourceConfig {
+    /// Short name of the source type for status display
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            SourceConfig::Url => "Image URL",
+            SourceConfig::Image { .. } => "Image",
+            SourceConfig::Bundle(_) => "Content bundle",
+            SourceConfig::Playlist(_) => "Remote playlist",
+            SourceConfig::HomeAssistant(_) => "Home Assistant camera",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Printer(_) => "3D printer status",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Departures(_) => "Departures board",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Ticker(_) => "Stock/crypto ticker",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Tasks(_) => "Task list",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Countdown(_) => "Countdown cards",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Clock(_) => "Clock",
+            SourceConfig::Text(_) => "Text",
+            #[cfg(feature = "renderers")]
+            SourceConfig::Layout(_) => "Layout",
+            #[cfg(feature = "alerts")]
+            SourceConfig::Alerts(_) => "Alerts",
+            SourceConfig::Sample(_) => "Sample image",
+            SourceConfig::Pushed(_) => "Pushed image",
+        }
+    }
+
+    /// Check whether the source has everything it needs to fetch
+    pub fn is_configured(&self, config: &Config) -> bool {
+        match self {
+            SourceConfig::Url | SourceConfig::Image { .. } | SourceConfig::HomeAssistant(_) => {
+                self.image_request(config).is_ok()
+            }
+            SourceConfig::Bundle(bundle) => !bundle.url.trim().is_empty(),
+            SourceConfig::Playlist(playlist) => !playlist.url.trim().is_empty(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Printer(printer) => printer.is_configured(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Departures(departures) => departures.is_configured(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Ticker(ticker) => ticker.is_configured(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Tasks(tasks) => tasks.is_configured(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Countdown(countdown) => countdown.is_configured(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Clock(_) => true,
+            #[cfg(feature = "renderers")]
+            SourceConfig::Layout(layout) => layout.is_configured(),
+            #[cfg(feature = "alerts")]
+            SourceConfig::Alerts(_) => true,
+            SourceConfig::Text(_) | SourceConfig::Sample(_) | SourceConfig::Pushed(_) => true,
+        }
+    }
+
+    /// Request used by the periodic source health check
+    pub fn health_request(&self, config: &Config) -> Option<ImageRequest> {
+        let mut request = match self {
+            SourceConfig::Url | SourceConfig::Image { .. } | SourceConfig::HomeAssistant(_) => {
+                self.image_request(config).ok()
+            }
+            SourceConfig::Bundle(BundleSource { url })
+            | SourceConfig::Playlist(PlaylistSource { url }) => {
+                secrets::expand(url.trim()).ok().map(|url| ImageRequest {
+                    url,
+                    headers: HeaderMap::new(),
+                })
+            }
+            #[cfg(feature = "renderers")]
+            SourceConfig::Printer(printer) => printer.health_request().ok(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Departures(departures) => departures.health_request(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Ticker(ticker) => ticker.health_request(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Tasks(tasks) => tasks.health_request().ok(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Countdown(_) => None,
+            #[cfg(feature = "renderers")]
+            SourceConfig::Clock(_) => None,
+            #[cfg(feature = "alerts")]
+            SourceConfig::Alerts(_) => None,
+            SourceConfig::Text(_) | SourceConfig::Sample(_) | SourceConfig::Pushed(_) => None,
+            // First zone that can be checked
+            #[cfg(feature = "renderers")]
+            SourceConfig::Layout(layout) => layout
+                .zones
+                .iter()
+                .find_map(|zone| zone.source.health_request(config)),
+        }?;
+        request.headers.insert(USER_AGENT, config.user_agent());
+        Some(request)
+    }
+
+    /// Build the HTTP request that fetches this source's image
+    ///
+    /// Only meaningful for image sources; rendered sources have none.
+    fn image_request(&self, config: &Config) -> Result<ImageRequest, SourceError> {
+        let mut request = match self {
+            SourceConfig::Url => {
+                if !config.has_image_url() {
+                    return Err(SourceError::NotConfigured("no image URL".to_string()));
+                }
+                ImageRequest {
+                    url: secrets::expand(config.image_url.trim())?,
+                    headers: HeaderMap::new(),
+                }
+            }
+            SourceConfig::Image { url } => {
+                if url.trim().is_empty() {
+                    return Err(SourceError::NotConfigured("no image URL".to_string()));
+                }
+                ImageRequest {
+                    url: secrets::expand(url.trim())?,
+                    headers: HeaderMap::new(),
+                }
+            }
+            SourceConfig::HomeAssistant(ha) => ha.image_request()?,
+            _ => {
+                return Err(SourceError::NotConfigured(
+                    "rendered sources have no image URL".to_string(),
+                ));
+            }
+        };
+        request.headers.insert(USER_AGENT, config.user_agent());
+        Ok(request)
+    }
+
+    /// Region redrawn by per-minute partial refreshes, if the source has one
+    ///
+    /// `width`/`height` are the content size; the region is in the same
+    /// coordinates.
+    #[cfg_attr(not(feature = "renderers"), allow(unused_variables))]
+    pub fn partial_region(&self, width: u32, height: u32) -> Option<Region> {
+        #[cfg(feature = "renderers")]
+        if let SourceConfig::Clock(clock) = self {
+            return Some(clock.time_region(width, height));
+        }
+        None
+    }
+
+    /// Validate source settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            SourceConfig::Url => Ok(()),
+            SourceConfig::Image { url } => {
+                let url = url.trim();
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(ConfigError::ValidationError(
+                        "Image url must start with http:// or https://".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            SourceConfig::HomeAssistant(ha) => ha.validate(),
+            SourceConfig::Bundle(bundle) => bundle.validate(),
+            SourceConfig::Playlist(playlist) => playlist.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Printer(printer) => printer.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Departures(departures) => departures.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Ticker(ticker) => ticker.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Tasks(tasks) => tasks.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Countdown(countdown) => countdown.validate(),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Clock(_) => Ok(()),
+            #[cfg(feature = "renderers")]
+            SourceConfig::Layout(layout) => layout.validate(),
+            #[cfg(feature = "alerts")]
+            SourceConfig::Alerts(_) => Ok(()),
+            SourceConfig::Sample(_) | SourceConfig::Pushed(_) => Ok(()),
+            SourceConfig::Text(text) => text.validate(),
+        }
+    }
+}
+
+/// Fetch the image for the configured source, and how it asks to be
+/// presented
+///
+/// Rendered sources draw at the content size (display size before
+/// rotation) so the layout ends up upright on the mounted panel. Content
+/// that has already expired is an error.
+pub async fn fetch_image(config: &Config) -> Result<(DynamicImage, Presentation), SourceError> {
+    let (width, height) = config.content_size();
+    let (image, presentation) = fetch_source(&config.source, config, width, height).await?;
+    if let Some(until) = presentation.valid_until
+        && until <= Local::now()
+    {
+        return Err(SourceError::Expired(
+            until.format("%Y-%m-%d %H:%M").to_string(),
+        ));
+    }
+    Ok((image, presentation))
+}
+
+/// Fetch the image for `source`, rendering at `width` x `height`
+///
+/// Downloaded images keep their own size; the caller scales them. Zones
+/// show the image; the schedule is the layout's.
+#[cfg(feature = "renderers")]
+pub(crate) async fn fetch_source_image(
+    source: &SourceConfig,
+    config: &Config,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, SourceError> {
+    Ok(fetch_source(source, config, width, height).await?.0)
+}
+
+/// Fetch the image for `source` and how it asks to be presented
+async fn fetch_source(
+    source: &SourceConfig,
+    config: &Config,
+    width: u32,
+    height: u32,
+) -> Result<(DynamicImage, Presentation), SourceError> {
+    fonts::load(&config.theme.fonts);
+    let theme = Theme::from_config(&config.theme);
+    #[cfg(feature = "renderers")]
+    let download = config.download_config();
+    let rendered = |image| Ok((image, Presentation::default()));
+    let request = match source {
+        #[cfg(feature = "renderers")]
+        SourceConfig::Printer(printer) => {
+            return rendered(printer.render(&download, width, height, &theme).await?);
+        }
+        #[cfg(feature = "renderers")]
+        SourceConfig::Departures(departures) => {
+            return rendered(departures.render(&download, width, height, &theme).await?);
+        }
+        #[cfg(feature = "renderers")]
+        SourceConfig::Ticker(ticker) => {
+            return rendered(ticker.render(&download, width, height, &theme).await?);
+        }
+        #[cfg(feature = "renderers")]
+        SourceConfig::Tasks(tasks) => {
+            return rendered(tasks.render(&download, width, height, &theme).await?);
+        }
+        #[cfg(feature = "renderers")]
+        SourceConfig::Countdown(countdown) => {
+            return rendered(countdown.render(width, height, &theme).await?);
+        }
+        #[cfg(feature = "renderers")]
+        SourceConfig::Clock(clock) => return rendered(clock.render(width, height, &theme)),
+        SourceConfig::Text(text) => return rendered(text.render(width, height, &theme)),
+        #[cfg(feature = "alerts")]
+        SourceConfig::Alerts(alerts) => return rendered(alerts.render(width, height, &theme)),
+        SourceConfig::Sample(sample) => return rendered(sample.render(width, height)),
+        SourceConfig::Pushed(image) => return rendered(image.decode(config)?),
+        SourceConfig::Bundle(bundle) => return bundle.fetch(config, width, height, &theme).await,
+        SourceConfig::Playlist(playlist) => {
+            return playlist.fetch(config, width, height, &theme).await;
+        }
+        // Boxed: zones recurse back into this function
+        #[cfg(feature = "renderers")]
+        SourceConfig::Layout(layout) => {
+            return rendered(Box::pin(layout.compose(config, width, height, &theme)).await?);
+        }
+        source => source.image_request(config)?,
+    };
+
+    let download_config = DownloadConfig {
+        headers: request.headers,
+        checksum: match source {
+            SourceConfig::Url => config.image_checksum()?,
+            _ => None,
+        },
+        max_memory: config.max_image_memory(),
+        session: config.session.enabled().then(|| config.session.clone()),
+        metadata: config.photo_caption != PhotoCaption::Off,
+        ..DownloadConfig::default()
+    };
+
+    let downloaded = download_image_with_config(&request.url, &download_config).await?;
+    let image = match config.photo_caption.text(&downloaded.info) {
+        Some(caption) => photo::with_caption(
+            downloaded.image,
+            &caption,
+            width,
+            height,
+            config.scale_to_fit,
+            config.smart_crop,
+            &theme,
+        ),
+        None => downloaded.image,
+    };
+    let presentation = Presentation {
+        valid_until: downloaded.expires,
+        ..Presentation::default()
+    };
+    Ok((image, presentation))
+}
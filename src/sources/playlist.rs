@@ -0,0 +1,191 @@
+//! Remote playlist source.
+//!
+//! Fetches a JSON playlist from a server on every refresh and shows the
+//! slide that is due, so the rotation of many displays is curated in one
+//! place instead of in each device's config:
+//!
+//! ```json
+//! {"duration_secs": 600, "slides": [
+//!     {"url": "menu.zip", "duration_secs": 900, "valid_until": "2026-10-17T14:00:00+02:00"},
+//!     {"url": "https://example.com/weather.png"}
+//! ]}
+//! ```
+//!
+//! Slides are shown in order, each for its duration, and the rotation
+//! follows the clock: every display with the same playlist shows the same
+//! slide at the same time, also after a restart. Slides outside their
+//! `valid_from`/`valid_until` range are left out. A slide URL may be
+//! relative to the playlist and point at an image or a
+//! [content bundle](super::bundle).
+
+use super::bundle::BundleSource;
+use super::{Presentation, SourceError};
+use crate::config::{Config, ConfigError};
+use crate::image_proc::download;
+use crate::render::Theme;
+use crate::secrets;
+use chrono::{DateTime, Local, TimeDelta};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Time a slide is shown unless the playlist says otherwise
+const DEFAULT_DURATION_SECS: u64 = 600;
+
+/// Playlist errors
+#[derive(Error, Debug)]
+pub enum PlaylistError {
+    #[error("Invalid playlist: {0}")]
+    Invalid(String),
+
+    #[error("Playlist has no current slides")]
+    Empty,
+}
+
+/// Remote playlist settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistSource {
+    /// URL of the JSON playlist
+    pub url: String,
+}
+
+/// Playlist document
+#[derive(Debug, Deserialize)]
+struct Playlist {
+    /// Seconds each slide is shown unless it has its own duration
+    #[serde(default)]
+    duration_secs: Option<u64>,
+    slides: Vec<Slide>,
+}
+
+/// Entry of the playlist
+#[derive(Debug, Deserialize)]
+struct Slide {
+    /// Image or bundle URL, absolute or relative to the playlist
+    url: String,
+    /// Seconds the slide is shown
+    #[serde(default)]
+    duration_secs: Option<u64>,
+    /// RFC 3339 time the slide is shown from
+    #[serde(default)]
+    valid_from: Option<String>,
+    /// RFC 3339 time after which the slide is left out
+    #[serde(default)]
+    valid_until: Option<String>,
+}
+
+/// Slide that is due
+#[derive(Debug)]
+struct Due {
+    /// Position in the playlist
+    index: usize,
+    /// Time its turn started
+    start: DateTime<Local>,
+    /// Time its turn ends
+    end: DateTime<Local>,
+}
+
+impl PlaylistSource {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = self.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Playlist url must start with http:// or https://".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Download the playlist and fetch the slide that is due
+    ///
+    /// The slide's dwell timer ends with its turn. Turns are at least
+    /// `min_refresh_interval_min` long.
+    pub async fn fetch(
+        &self,
+        config: &Config,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<(DynamicImage, Presentation), SourceError> {
+        let download_config = config.download_config();
+
+        let url = secrets::expand(self.url.trim())?;
+        let body = download::download_bytes(&url, &download_config).await?;
+        let playlist: Playlist = serde_json::from_slice(&body.bytes)
+            .map_err(|e| PlaylistError::Invalid(e.to_string()))?;
+
+        let min_secs = config.min_interval() as u64 * 60;
+        let now = Local::now();
+        let due = playlist.due(now, min_secs)?.ok_or(PlaylistError::Empty)?;
+        let slide = &playlist.slides[due.index];
+        let slide_url = reqwest::Url::parse(&url)
+            .and_then(|base| base.join(slide.url.trim()))
+            .map_err(|e| PlaylistError::Invalid(format!("slide url '{}': {}", slide.url, e)))?;
+        tracing::info!(
+            "Showing slide {} of {} until {}",
+            due.index + 1,
+            playlist.slides.len(),
+            due.end.format("%H:%M:%S")
+        );
+
+        let bundle = BundleSource {
+            url: slide_url.to_string(),
+        };
+        let (image, shown) = bundle.fetch(config, width, height, theme).await?;
+        let presentation = Presentation {
+            dwell: (due.end - due.start).to_std().ok(),
+            next_slide: Some(due.end),
+            slide: Some(format!("{} {}", slide_url, due.start.timestamp())),
+            valid_until: shown.valid_until,
+        };
+        Ok((image, presentation))
+    }
+}
+
+impl Playlist {
+    /// Slide whose turn it is at `now`, or None if no slide is current
+    ///
+    /// The turns of the current slides repeat back to back since the Unix
+    /// epoch, each at least `min_secs` long.
+    fn due(&self, now: DateTime<Local>, min_secs: u64) -> Result<Option<Due>, PlaylistError> {
+        let default_secs = self.duration_secs.unwrap_or(DEFAULT_DURATION_SECS);
+        let mut turns = Vec::new();
+        for (index, slide) in self.slides.iter().enumerate() {
+            let after = |time: &Option<String>| -> Result<Option<bool>, PlaylistError> {
+                time.as_deref()
+                    .map(|time| {
+                        DateTime::parse_from_rfc3339(time.trim())
+                            .map(|time| now >= time)
+                            .map_err(|e| PlaylistError::Invalid(format!("time '{}': {}", time, e)))
+                    })
+                    .transpose()
+            };
+            if after(&slide.valid_from)? == Some(false) || after(&slide.valid_until)? == Some(true)
+            {
+                continue;
+            }
+            let secs = slide
+                .duration_secs
+                .unwrap_or(default_secs)
+                .max(min_secs)
+                .max(1);
+            turns.push((index, secs));
+        }
+
+        let cycle: u64 = turns.iter().map(|(_, secs)| secs).sum();
+        if cycle == 0 {
+            return Ok(None);
+        }
+        let mut offset = now.timestamp().max(0) as u64 % cycle;
+        for (index, secs) in turns {
+            if offset < secs {
+                let start = now - TimeDelta::seconds(offset as i64);
+                let end = start + TimeDelta::seconds(secs as i64);
+                return Ok(Some(Due { index, start, end }));
+            }
+            offset -= secs;
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,482 @@
+//! 3D printer status source (OctoPrint / Moonraker).
+//!
+//! Queries the printer's REST API and renders job progress, remaining
+//! time and temperatures as a full-screen status layout.
+
+use super::{ImageRequest, SourceError};
+use crate::config::ConfigError;
+use crate::image_proc::download::{DownloadConfig, fetch_json};
+use crate::render::{Theme, fit_text};
+use crate::secrets;
+use image::DynamicImage;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+/// Printer API flavour
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrinterApi {
+    /// OctoPrint REST API
+    Octoprint,
+    /// Moonraker (Klipper) API
+    Moonraker,
+}
+
+/// Printer source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrinterSource {
+    /// Which API the printer host speaks
+    pub api: PrinterApi,
+    /// Base URL of the printer host (e.g., "http://octopi.local")
+    pub base_url: String,
+    /// API key (required for OctoPrint, optional for Moonraker)
+    #[serde(default)]
+    pub api_key: String,
+}
+
+/// Heater reading
+#[derive(Debug, Clone)]
+struct Temperature {
+    name: &'static str,
+    actual: f64,
+    target: f64,
+}
+
+/// Normalized printer state shared by both APIs
+#[derive(Debug, Clone)]
+struct PrinterStatus {
+    state: String,
+    file: Option<String>,
+    /// Completion in 0.0..=1.0, if a job is loaded
+    progress: Option<f64>,
+    elapsed_secs: Option<f64>,
+    remaining_secs: Option<f64>,
+    temperatures: Vec<Temperature>,
+}
+
+impl PrinterSource {
+    fn base_url(&self) -> &str {
+        self.base_url.trim().trim_end_matches('/')
+    }
+
+    fn headers(&self) -> Result<HeaderMap, SourceError> {
+        let mut headers = HeaderMap::new();
+        let key = secrets::resolve(&self.api_key)?;
+        if !key.is_empty()
+            && let Ok(value) = HeaderValue::from_str(&key)
+        {
+            headers.insert("X-Api-Key", value);
+        }
+        Ok(headers)
+    }
+
+    /// Whether enough is configured to query the printer
+    pub fn is_configured(&self) -> bool {
+        !self.base_url().is_empty()
+    }
+
+    /// Lightweight request used by source health checks
+    pub fn health_request(&self) -> Result<ImageRequest, SourceError> {
+        let path = match self.api {
+            PrinterApi::Octoprint => "/api/version",
+            PrinterApi::Moonraker => "/server/info",
+        };
+        Ok(ImageRequest {
+            url: format!("{}{}", self.base_url(), path),
+            headers: self.headers()?,
+        })
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = self.base_url();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Printer base_url must start with http:// or https://".to_string(),
+            ));
+        }
+
+        if self.api == PrinterApi::Octoprint && self.api_key.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "OctoPrint requires an api_key".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Query the printer and render the status layout
+    pub async fn render(
+        &self,
+        download: &DownloadConfig,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let status = match self.api {
+            PrinterApi::Octoprint => self.fetch_octoprint(download).await?,
+            PrinterApi::Moonraker => self.fetch_moonraker(download).await?,
+        };
+
+        Ok(render_status(self.api, &status, width, height, theme))
+    }
+
+    async fn fetch_octoprint(
+        &self,
+        download: &DownloadConfig,
+    ) -> Result<PrinterStatus, SourceError> {
+        let headers = self.headers()?;
+        let job: OctoJob =
+            fetch_json(&format!("{}/api/job", self.base_url()), &headers, download).await?;
+
+        let mut status = PrinterStatus {
+            state: job.state,
+            file: job.job.file.name,
+            progress: job.progress.completion.map(|c| c / 100.0),
+            elapsed_secs: job.progress.print_time,
+            remaining_secs: job.progress.print_time_left,
+            temperatures: Vec::new(),
+        };
+
+        // OctoPrint answers 409 while the printer is disconnected
+        let url = format!("{}/api/printer", self.base_url());
+        match fetch_json::<OctoPrinter>(&url, &headers, download).await {
+            Ok(printer) => {
+                let temps = printer.temperature;
+                for (name, reading) in [("Nozzle", temps.tool0), ("Bed", temps.bed)] {
+                    if let Some(r) = reading {
+                        status.temperatures.push(Temperature {
+                            name,
+                            actual: r.actual.unwrap_or(0.0),
+                            target: r.target.unwrap_or(0.0),
+                        });
+                    }
+                }
+            }
+            Err(e) => tracing::debug!("OctoPrint temperatures unavailable: {}", e),
+        }
+
+        Ok(status)
+    }
+
+    async fn fetch_moonraker(
+        &self,
+        download: &DownloadConfig,
+    ) -> Result<PrinterStatus, SourceError> {
+        let url = format!(
+            "{}/printer/objects/query?print_stats&display_status&extruder&heater_bed",
+            self.base_url()
+        );
+        let response: MoonrakerResponse = fetch_json(&url, &self.headers()?, download).await?;
+        Ok(moonraker_status(response.result.status))
+    }
+}
+
+/// Normalize Moonraker's printer objects, estimating the remaining time
+/// from progress and elapsed time
+fn moonraker_status(objects: MoonrakerObjects) -> PrinterStatus {
+    let progress = objects.display_status.progress;
+    let elapsed = objects.print_stats.print_duration;
+    let remaining = match (progress, elapsed) {
+        (Some(p), Some(e)) if p > 0.0 => Some(e / p - e),
+        _ => None,
+    };
+
+    let mut temperatures = Vec::new();
+    for (name, heater) in [("Nozzle", objects.extruder), ("Bed", objects.heater_bed)] {
+        if let Some(h) = heater {
+            temperatures.push(Temperature {
+                name,
+                actual: h.temperature,
+                target: h.target,
+            });
+        }
+    }
+
+    PrinterStatus {
+        state: objects.print_stats.state,
+        file: objects.print_stats.filename.filter(|f| !f.is_empty()),
+        progress,
+        elapsed_secs: elapsed,
+        remaining_secs: remaining,
+        temperatures,
+    }
+}
+
+/// Format seconds as "H:MM"
+fn format_duration(secs: f64) -> String {
+    let total_min = (secs.max(0.0) / 60.0).round() as u64;
+    format!("{}:{:02}", total_min / 60, total_min % 60)
+}
+
+/// Draw the printer status layout
+fn render_status(
+    api: PrinterApi,
+    status: &PrinterStatus,
+    width: u32,
+    height: u32,
+    theme: &Theme,
+) -> DynamicImage {
+    let mut canvas = theme.canvas(width, height);
+    let unit = theme.unit(height);
+    let margin = unit as i32;
+    let center = width as i32 / 2;
+
+    // Header bar
+    let title = match api {
+        PrinterApi::Octoprint => "OctoPrint",
+        PrinterApi::Moonraker => "Klipper",
+    };
+    theme.header(&mut canvas, unit, title, &capitalize(&status.state));
+
+    // File name
+    let mut y = (unit * 4) as i32;
+    let file = status.file.as_deref().unwrap_or("No job loaded");
+    let file = fit_text(file, unit, width.saturating_sub(unit * 2));
+    canvas.text_centered(center, y, &file, unit, theme.text);
+    y += (unit * 2) as i32;
+
+    // Progress
+    if let Some(progress) = status.progress {
+        let percent = format!("{:.0}%", progress * 100.0);
+        canvas.text_centered(center, y, &percent, unit * 5, theme.text);
+        y += (unit * 6) as i32;
+
+        canvas.progress_bar(
+            margin,
+            y,
+            width.saturating_sub(unit * 2),
+            unit * 2,
+            progress as f32,
+            theme.accent,
+            theme.text,
+        );
+        y += (unit * 3) as i32;
+    } else {
+        y += (unit * 9) as i32;
+    }
+
+    // Times
+    let mut times = Vec::new();
+    if let Some(elapsed) = status.elapsed_secs {
+        times.push(format!("Elapsed {}", format_duration(elapsed)));
+    }
+    if let Some(remaining) = status.remaining_secs {
+        times.push(format!("Left {}", format_duration(remaining)));
+        let eta = chrono::Local::now() + chrono::Duration::seconds(remaining as i64);
+        times.push(format!("ETA {}", eta.format("%H:%M")));
+    }
+    if !times.is_empty() {
+        canvas.text_centered(center, y, &times.join("  "), unit * 3 / 2, theme.text);
+    }
+    y += (unit * 3) as i32;
+
+    // Temperatures: red while heating, green once at target
+    let column = width as i32 / status.temperatures.len().max(1) as i32;
+    for (i, temp) in status.temperatures.iter().enumerate() {
+        let color = if temp.target <= 0.0 {
+            theme.text
+        } else if temp.actual + 2.0 < temp.target {
+            theme.negative
+        } else {
+            theme.positive
+        };
+        let label = format!("{} {:.0}/{:.0}\u{b0}C", temp.name, temp.actual, temp.target);
+        canvas.text_centered(
+            column * i as i32 + column / 2,
+            y,
+            &label,
+            unit * 3 / 2,
+            color,
+        );
+    }
+
+    canvas.into_image()
+}
+
+/// Uppercase the first letter ("printing" → "Printing")
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// OctoPrint API payloads (only the fields we use)
+
+#[derive(Deserialize)]
+struct OctoJob {
+    job: OctoJobInfo,
+    progress: OctoProgress,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct OctoJobInfo {
+    file: OctoFile,
+}
+
+#[derive(Deserialize)]
+struct OctoFile {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OctoProgress {
+    completion: Option<f64>,
+    print_time: Option<f64>,
+    print_time_left: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct OctoPrinter {
+    temperature: OctoTemperatures,
+}
+
+#[derive(Deserialize)]
+struct OctoTemperatures {
+    tool0: Option<OctoReading>,
+    bed: Option<OctoReading>,
+}
+
+#[derive(Deserialize)]
+struct OctoReading {
+    actual: Option<f64>,
+    target: Option<f64>,
+}
+
+// Moonraker API payloads
+
+#[derive(Deserialize)]
+struct MoonrakerResponse {
+    result: MoonrakerResult,
+}
+
+#[derive(Deserialize)]
+struct MoonrakerResult {
+    status: MoonrakerObjects,
+}
+
+#[derive(Deserialize)]
+struct MoonrakerObjects {
+    print_stats: MoonrakerPrintStats,
+    #[serde(default)]
+    display_status: MoonrakerDisplayStatus,
+    extruder: Option<MoonrakerHeater>,
+    heater_bed: Option<MoonrakerHeater>,
+}
+
+#[derive(Deserialize)]
+struct MoonrakerPrintStats {
+    state: String,
+    filename: Option<String>,
+    print_duration: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct MoonrakerDisplayStatus {
+    progress: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct MoonrakerHeater {
+    temperature: f64,
+    target: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_shown_as_hours_and_minutes() {
+        for (secs, expected) in [
+            (0.0, "0:00"),
+            (29.0, "0:00"),
+            (90.0, "0:02"),
+            (3600.0, "1:00"),
+            (7.0 * 3600.0 + 5.0 * 60.0, "7:05"),
+            (-60.0, "0:00"),
+        ] {
+            assert_eq!(format_duration(secs), expected, "{} s", secs);
+        }
+    }
+
+    #[test]
+    fn settings_are_validated_per_api() {
+        let source = |api, base_url: &str, api_key: &str| PrinterSource {
+            api,
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+        };
+        for (source, valid) in [
+            (
+                source(PrinterApi::Moonraker, "http://voron.local/", ""),
+                true,
+            ),
+            (source(PrinterApi::Octoprint, "https://octopi", "k"), true),
+            (source(PrinterApi::Octoprint, "https://octopi", " "), false),
+            (source(PrinterApi::Moonraker, "voron.local", ""), false),
+            (source(PrinterApi::Moonraker, "", ""), false),
+        ] {
+            assert_eq!(source.validate().is_ok(), valid, "{:?}", source);
+        }
+
+        let health = source(PrinterApi::Moonraker, " http://voron.local/ ", "")
+            .health_request()
+            .unwrap();
+        assert_eq!(health.url, "http://voron.local/server/info");
+    }
+
+    #[test]
+    fn moonraker_remaining_time_is_estimated_from_progress() {
+        let objects: MoonrakerObjects = serde_json::from_str(
+            r#"{
+                "print_stats": {"state": "printing", "filename": "benchy.gcode", "print_duration": 600},
+                "display_status": {"progress": 0.25},
+                "extruder": {"temperature": 210.4, "target": 215},
+                "heater_bed": null
+            }"#,
+        )
+        .unwrap();
+        let status = moonraker_status(objects);
+        assert_eq!(status.file.as_deref(), Some("benchy.gcode"));
+        assert_eq!(status.remaining_secs, Some(1800.0));
+        assert_eq!(status.temperatures.len(), 1);
+        assert_eq!(status.temperatures[0].name, "Nozzle");
+
+        // Idle: no job, nothing to estimate
+        let objects: MoonrakerObjects = serde_json::from_str(
+            r#"{"print_stats": {"state": "standby", "filename": "", "print_duration": 0}}"#,
+        )
+        .unwrap();
+        let status = moonraker_status(objects);
+        assert_eq!((status.file, status.remaining_secs), (None, None));
+    }
+
+    #[test]
+    fn progress_bar_shows_only_with_a_job() {
+        let theme = Theme::default();
+        let mut status = PrinterStatus {
+            state: "printing".to_string(),
+            file: Some("benchy.gcode".to_string()),
+            progress: Some(0.5),
+            elapsed_secs: Some(600.0),
+            remaining_secs: Some(600.0),
+            temperatures: Vec::new(),
+        };
+        let has_bar = |status: &PrinterStatus| {
+            let img = render_status(PrinterApi::Octoprint, status, 800, 480, &theme).to_rgb8();
+            assert_eq!((img.width(), img.height()), (800, 480));
+            assert_eq!(*img.get_pixel(0, 0), theme.header_background);
+            img.pixels().any(|&pixel| pixel == theme.accent)
+        };
+        assert!(has_bar(&status));
+
+        status.progress = None;
+        assert!(!has_bar(&status));
+        assert_eq!(capitalize(&status.state), "Printing");
+    }
+}
@@ -0,0 +1,66 @@
+//! Images pushed over HTTP.
+//!
+//! `POST /api/v1/image` (or `epaper-display push` from another machine)
+//! sends an image file to the display, which shows it like a
+//! [kiosk message](crate::message) for a while. The file is kept encoded
+//! and decoded at each refresh with the limits of downloaded images, so
+//! a large photo does not stay in memory as pixels. Never configured.
+
+use super::SourceError;
+use crate::config::Config;
+use crate::image_proc::DownloadError;
+use crate::image_proc::download::{self, DownloadConfig};
+use bytes::Bytes;
+use image::DynamicImage;
+
+/// Largest image file accepted
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Encoded image file pushed to the display
+#[derive(Clone, PartialEq)]
+pub struct PushedImage {
+    data: Bytes,
+    /// Size of the image as pushed, in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::fmt::Debug for PushedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PushedImage({}x{}, {} bytes)",
+            self.width,
+            self.height,
+            self.data.len()
+        )
+    }
+}
+
+impl PushedImage {
+    /// Keep `data` after checking that it decodes as an image within the
+    /// limits of `config`
+    pub fn new(data: Bytes, config: &Config) -> Result<Self, DownloadError> {
+        let img = download::decode_image(data.clone(), &decode_config(config))?;
+        Ok(Self {
+            data,
+            width: img.width(),
+            height: img.height(),
+        })
+    }
+
+    /// Decode the image for a refresh
+    pub fn decode(&self, config: &Config) -> Result<DynamicImage, SourceError> {
+        Ok(download::decode_image(
+            self.data.clone(),
+            &decode_config(config),
+        )?)
+    }
+}
+
+fn decode_config(config: &Config) -> DownloadConfig {
+    DownloadConfig {
+        max_memory: config.max_image_memory(),
+        ..DownloadConfig::default()
+    }
+}
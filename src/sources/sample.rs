@@ -0,0 +1,155 @@
+//! Built-in sample images.
+//!
+//! Drawn rather than bundled as files, so they fit any panel size. They
+//! show off what the panel and the dithering can do and are only used by
+//! the [demo mode](crate::demo), never configured.
+
+use crate::render::{Canvas, colors, fit_text};
+use image::{DynamicImage, Rgb, RgbImage};
+
+/// Sample image to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleImage {
+    /// A bar of each palette color, labeled
+    ColorBars,
+    /// Rainbow and grey ramps, rendered by dithering
+    Gradients,
+    /// Checkerboards from 1 to 8 pixels, for sharpness and ghosting
+    Detail,
+}
+
+/// Palette colors of the color bars
+const BARS: [(&str, Rgb<u8>); 7] = [
+    ("Black", colors::BLACK),
+    ("White", colors::WHITE),
+    ("Red", colors::RED),
+    ("Yellow", colors::YELLOW),
+    ("Orange", colors::ORANGE),
+    ("Blue", colors::BLUE),
+    ("Green", colors::GREEN),
+];
+
+impl SampleImage {
+    /// All samples, in the order the demo shows them
+    pub fn all() -> &'static [SampleImage] {
+        &[
+            SampleImage::ColorBars,
+            SampleImage::Gradients,
+            SampleImage::Detail,
+        ]
+    }
+
+    /// Name for logs
+    pub fn title(&self) -> &'static str {
+        match self {
+            SampleImage::ColorBars => "color bars",
+            SampleImage::Gradients => "gradients",
+            SampleImage::Detail => "detail pattern",
+        }
+    }
+
+    /// Draw the sample at `width` x `height`
+    pub fn render(&self, width: u32, height: u32) -> DynamicImage {
+        match self {
+            SampleImage::ColorBars => color_bars(width, height),
+            SampleImage::Gradients => gradients(width, height),
+            SampleImage::Detail => detail(width, height),
+        }
+    }
+}
+
+fn color_bars(width: u32, height: u32) -> DynamicImage {
+    let mut canvas = Canvas::new(width, height, colors::WHITE);
+    let label_size = (height / 16).clamp(12, 40);
+    let label_top = height.saturating_sub(label_size * 2);
+    let bar_width = width / BARS.len() as u32;
+
+    for (i, (name, color)) in BARS.iter().enumerate() {
+        let x = i as u32 * bar_width;
+        // The last bar takes the rounding remainder
+        let w = if i == BARS.len() - 1 {
+            width - x
+        } else {
+            bar_width
+        };
+        canvas.fill_rect(x as i32, 0, w, label_top, *color);
+        let label = fit_text(name, label_size, w.saturating_sub(4));
+        canvas.text_centered(
+            (x + w / 2) as i32,
+            (label_top + label_size / 2) as i32,
+            &label,
+            label_size,
+            colors::BLACK,
+        );
+    }
+    canvas.stroke_rect(0, 0, width, label_top, 1, colors::BLACK);
+    canvas.into_image()
+}
+
+fn gradients(width: u32, height: u32) -> DynamicImage {
+    let rainbow_height = height * 2 / 3;
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        let t = x as f32 / width.saturating_sub(1).max(1) as f32;
+        if y < rainbow_height {
+            // Fully saturated at the top, fading to white at the bottom
+            let fade = y as f32 / rainbow_height as f32;
+            let [r, g, b] = hue(t * 300.0);
+            let mix = |c: u8| (c as f32 + (255.0 - c as f32) * fade) as u8;
+            Rgb([mix(r), mix(g), mix(b)])
+        } else {
+            let level = (t * 255.0) as u8;
+            Rgb([level, level, level])
+        }
+    });
+    DynamicImage::ImageRgb8(img)
+}
+
+/// Fully saturated color of `degrees` on the color wheel
+fn hue(degrees: f32) -> [u8; 3] {
+    let sector = (degrees / 60.0).rem_euclid(6.0);
+    let rising = ((sector.fract()) * 255.0) as u8;
+    let falling = 255 - rising;
+    match sector as u32 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+fn detail(width: u32, height: u32) -> DynamicImage {
+    let mut canvas = Canvas::new(width, height, colors::WHITE);
+    let label_size = (height / 20).clamp(12, 32);
+    let cell_sizes = [1, 2, 4, 8];
+    let tile_width = width / cell_sizes.len() as u32;
+    let tile_top = label_size * 2;
+    let tile_height = height.saturating_sub(tile_top);
+
+    for (i, cell) in cell_sizes.into_iter().enumerate() {
+        let x0 = i as u32 * tile_width;
+        let label = format!("{} px", cell);
+        canvas.text_centered(
+            (x0 + tile_width / 2) as i32,
+            (label_size / 2) as i32,
+            &label,
+            label_size,
+            colors::BLACK,
+        );
+        for y in (0..tile_height).step_by(cell as usize) {
+            for x in (0..tile_width).step_by(cell as usize) {
+                if (x / cell + y / cell) % 2 == 0 {
+                    canvas.fill_rect(
+                        (x0 + x) as i32,
+                        (tile_top + y) as i32,
+                        cell,
+                        cell,
+                        colors::BLACK,
+                    );
+                }
+            }
+        }
+    }
+    canvas.into_image()
+}
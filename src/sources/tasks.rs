@@ -0,0 +1,618 @@
+//! Task list source (Todoist / CalDAV).
+//!
+//! Fetches open tasks from Todoist or from a CalDAV VTODO collection
+//! (Nextcloud Tasks, Radicale, Baïkal, ...) and renders them as a
+//! checklist, soonest due first.
+
+use super::{ImageRequest, SourceError};
+use crate::config::ConfigError;
+use crate::image_proc::DownloadError;
+use crate::image_proc::download::{DownloadConfig, fetch_json, fetch_text};
+use crate::render::{Theme, colors, fit_text};
+use crate::secrets;
+use base64::Engine;
+use chrono::{Local, NaiveDate};
+use image::{DynamicImage, Rgb};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+/// Task service
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskProvider {
+    /// Todoist API (token from Settings → Integrations → Developer)
+    Todoist,
+    /// CalDAV calendar collection containing VTODO items
+    Caldav,
+}
+
+/// Task list source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TasksSource {
+    /// Which service to query
+    pub provider: TaskProvider,
+    /// CalDAV collection URL, or Todoist API base URL (empty for the public API)
+    #[serde(default)]
+    pub url: String,
+    /// Todoist API token
+    #[serde(default)]
+    pub token: String,
+    /// CalDAV username
+    #[serde(default)]
+    pub username: String,
+    /// CalDAV password (app password recommended)
+    #[serde(default)]
+    pub password: String,
+    /// Todoist filter query (e.g., "today | overdue")
+    #[serde(default)]
+    pub filter: String,
+    /// Heading shown above the list
+    #[serde(default = "default_title")]
+    pub title: String,
+    /// Maximum number of tasks to show
+    #[serde(default = "default_max_tasks")]
+    pub max_tasks: usize,
+}
+
+fn default_title() -> String {
+    "Tasks".to_string()
+}
+
+fn default_max_tasks() -> usize {
+    10
+}
+
+const TODOIST_API: &str = "https://api.todoist.com/api/v1";
+
+/// CalDAV calendar-query for all VTODO components
+const CALDAV_TODO_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+/// Task priority, normalized across providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Priority {
+    High,
+    Medium,
+    Low,
+    None,
+}
+
+impl Priority {
+    fn color(self, theme: &Theme) -> Rgb<u8> {
+        let color = match self {
+            Priority::High => colors::RED,
+            Priority::Medium => colors::ORANGE,
+            Priority::Low => colors::BLUE,
+            Priority::None => theme.text,
+        };
+        theme.icon(color)
+    }
+}
+
+/// One open task
+#[derive(Debug, Clone)]
+struct Task {
+    title: String,
+    due: Option<NaiveDate>,
+    priority: Priority,
+}
+
+impl TasksSource {
+    fn todoist_base_url(&self) -> &str {
+        let base = self.url.trim().trim_end_matches('/');
+        if base.is_empty() { TODOIST_API } else { base }
+    }
+
+    fn headers(&self) -> Result<HeaderMap, SourceError> {
+        let credentials = match self.provider {
+            TaskProvider::Todoist => format!("Bearer {}", secrets::resolve(&self.token)?),
+            TaskProvider::Caldav => {
+                let pair = format!("{}:{}", self.username, secrets::resolve(&self.password)?);
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(pair)
+                )
+            }
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&credentials) {
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+
+    /// Whether enough is configured to fetch tasks
+    pub fn is_configured(&self) -> bool {
+        match self.provider {
+            TaskProvider::Todoist => !self.token.trim().is_empty(),
+            TaskProvider::Caldav => !self.url.trim().is_empty(),
+        }
+    }
+
+    /// Lightweight request used by source health checks
+    pub fn health_request(&self) -> Result<ImageRequest, SourceError> {
+        let url = match self.provider {
+            TaskProvider::Todoist => format!("{}/projects?limit=1", self.todoist_base_url()),
+            TaskProvider::Caldav => self.url.trim().to_string(),
+        };
+        Ok(ImageRequest {
+            url,
+            headers: self.headers()?,
+        })
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = match self.provider {
+            TaskProvider::Todoist => self.todoist_base_url(),
+            TaskProvider::Caldav => self.url.trim(),
+        };
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Tasks url must start with http:// or https://".to_string(),
+            ));
+        }
+
+        if self.provider == TaskProvider::Todoist && self.token.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Todoist requires a token".to_string(),
+            ));
+        }
+
+        if self.max_tasks == 0 || self.max_tasks > 30 {
+            return Err(ConfigError::ValidationError(
+                "Tasks max_tasks must be between 1 and 30".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fetch open tasks and render the checklist
+    pub async fn render(
+        &self,
+        download: &DownloadConfig,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let mut tasks = match self.provider {
+            TaskProvider::Todoist => self.fetch_todoist(download).await?,
+            TaskProvider::Caldav => self.fetch_caldav(download).await?,
+        };
+
+        // Soonest due first (undated last), then by priority
+        tasks.sort_by_key(|t| (t.due.is_none(), t.due, t.priority));
+        let total = tasks.len();
+        tasks.truncate(self.max_tasks);
+
+        Ok(render_checklist(
+            &self.title,
+            &tasks,
+            total,
+            width,
+            height,
+            theme,
+        ))
+    }
+
+    async fn fetch_todoist(&self, download: &DownloadConfig) -> Result<Vec<Task>, SourceError> {
+        let filter = self.filter.trim();
+        let url = if filter.is_empty() {
+            format!("{}/tasks?limit=200", self.todoist_base_url())
+        } else {
+            let mut url = reqwest::Url::parse(&format!("{}/tasks/filter", self.todoist_base_url()))
+                .map_err(|e| SourceError::NotConfigured(format!("invalid Todoist URL: {}", e)))?;
+            url.query_pairs_mut()
+                .append_pair("query", filter)
+                .append_pair("limit", "200");
+            url.to_string()
+        };
+
+        let response: TodoistResponse = fetch_json(&url, &self.headers()?, download).await?;
+
+        Ok(response
+            .into_tasks()
+            .into_iter()
+            .filter(|t| !t.checked.unwrap_or(false))
+            .map(|t| Task {
+                title: t.content,
+                due: t.due.and_then(|d| parse_date(&d.date)),
+                // Todoist: 4 = urgent ... 1 = normal
+                priority: match t.priority {
+                    4 => Priority::High,
+                    3 => Priority::Medium,
+                    2 => Priority::Low,
+                    _ => Priority::None,
+                },
+            })
+            .collect())
+    }
+
+    async fn fetch_caldav(&self, download: &DownloadConfig) -> Result<Vec<Task>, SourceError> {
+        let mut headers = self.headers()?;
+        headers.insert("Depth", HeaderValue::from_static("1"));
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/xml; charset=utf-8"),
+        );
+
+        let method = reqwest::Method::from_bytes(b"REPORT")
+            .map_err(|e| DownloadError::InvalidResponse(e.to_string()))?;
+        let body = fetch_text(
+            method,
+            &self.url,
+            &headers,
+            CALDAV_TODO_QUERY.to_string(),
+            download,
+        )
+        .await?;
+
+        Ok(parse_vtodos(&xml_unescape(&body)))
+    }
+}
+
+/// Parse the date part of "YYYY-MM-DD", "YYYYMMDD" or a datetime starting with either
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    value
+        .get(..10)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .or_else(|| {
+            value
+                .get(..8)
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        })
+}
+
+/// Undo the XML escaping of calendar-data in a multistatus response
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Undo iCalendar TEXT escaping
+fn ics_unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// Extract open VTODO items from iCalendar text
+///
+/// The text may contain several calendars (one per multistatus
+/// response); only properties directly inside BEGIN:VTODO and END:VTODO
+/// matter, not those of nested components such as VALARM.
+fn parse_vtodos(text: &str) -> Vec<Task> {
+    // Unfold continuation lines (RFC 5545 §3.1)
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.trim_end_matches('\r');
+        if let Some(rest) = raw.strip_prefix([' ', '\t'])
+            && let Some(last) = lines.last_mut()
+        {
+            last.push_str(rest);
+        } else {
+            lines.push(raw.to_string());
+        }
+    }
+
+    let mut tasks = Vec::new();
+    let mut current: Option<(Task, bool)> = None;
+    // Components open inside the current VTODO
+    let mut nested = 0usize;
+
+    for line in &lines {
+        let line = line.trim_start();
+        if line == "BEGIN:VTODO" {
+            let task = Task {
+                title: String::new(),
+                due: None,
+                priority: Priority::None,
+            };
+            current = Some((task, false));
+            nested = 0;
+            continue;
+        }
+        if line == "END:VTODO" {
+            if let Some((task, done)) = current.take()
+                && !done
+                && !task.title.is_empty()
+            {
+                tasks.push(task);
+            }
+            continue;
+        }
+
+        let Some((task, done)) = current.as_mut() else {
+            continue;
+        };
+        if line.starts_with("BEGIN:") {
+            nested += 1;
+            continue;
+        }
+        if line.starts_with("END:") {
+            nested = nested.saturating_sub(1);
+            continue;
+        }
+        if nested > 0 {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Strip parameters such as "DUE;VALUE=DATE"
+        let name = key.split(';').next().unwrap_or(key);
+
+        match name {
+            "SUMMARY" => task.title = ics_unescape(value),
+            "DUE" => task.due = parse_date(value),
+            "STATUS" => *done |= matches!(value.trim(), "COMPLETED" | "CANCELLED"),
+            "COMPLETED" => *done = true,
+            // iCalendar: 1 = highest ... 9 = lowest, 0 = undefined
+            "PRIORITY" => {
+                task.priority = match value.trim().parse::<u8>().unwrap_or(0) {
+                    1..=4 => Priority::High,
+                    5 => Priority::Medium,
+                    6..=9 => Priority::Low,
+                    _ => Priority::None,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tasks
+}
+
+/// Draw the checklist
+fn render_checklist(
+    title: &str,
+    tasks: &[Task],
+    total: usize,
+    width: u32,
+    height: u32,
+    theme: &Theme,
+) -> DynamicImage {
+    let mut canvas = theme.canvas(width, height);
+    let unit = theme.unit(height);
+    let margin = unit as i32;
+    let right = width as i32 - margin;
+    let today = Local::now().date_naive();
+
+    let count = if total > tasks.len() {
+        format!("{} of {} open", tasks.len(), total)
+    } else {
+        format!("{} open", total)
+    };
+    theme.header(&mut canvas, unit, title, &count);
+
+    if tasks.is_empty() {
+        canvas.text_centered(
+            width as i32 / 2,
+            (height / 2) as i32,
+            "All done!",
+            unit * 3,
+            theme.positive,
+        );
+        return canvas.into_image();
+    }
+
+    let top = unit * 4;
+    let row_height = (height.saturating_sub(top) / tasks.len() as u32).min(unit * 3);
+    let text_size = (row_height * 2 / 3).max(10);
+    let box_size = text_size;
+    let title_col = margin + (box_size + unit) as i32;
+    let due_width = unit * 7;
+
+    for (i, task) in tasks.iter().enumerate() {
+        let y = (top + row_height * i as u32) as i32;
+        let text_y = y + (row_height.saturating_sub(text_size) / 2) as i32;
+
+        // Checkbox, outlined in the priority color
+        canvas.stroke_rect(
+            margin,
+            text_y,
+            box_size,
+            box_size,
+            (box_size / 8).max(2),
+            task.priority.color(theme),
+        );
+
+        let title_width = ((right - title_col).max(0) as u32).saturating_sub(due_width);
+        let text = fit_text(&task.title, text_size, title_width);
+        canvas.text(title_col, text_y, &text, text_size, theme.text);
+
+        // Due date: highlighted when overdue or due today
+        if let Some(due) = task.due {
+            let (label, color) = match (due - today).num_days() {
+                d if d < 0 => (due.format("%d.%m.").to_string(), theme.negative),
+                0 => ("Today".to_string(), theme.warning),
+                1 => ("Tomorrow".to_string(), theme.text),
+                _ => (due.format("%a %d.%m.").to_string(), theme.text),
+            };
+            canvas.text_right(right, text_y, &label, text_size, color);
+        }
+    }
+
+    canvas.into_image()
+}
+
+// Todoist API payloads (only the fields we use)
+
+/// API v1 returns a paginated object, the older REST v2 a bare array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TodoistResponse {
+    Paginated { results: Vec<TodoistTask> },
+    Bare(Vec<TodoistTask>),
+}
+
+impl TodoistResponse {
+    fn into_tasks(self) -> Vec<TodoistTask> {
+        match self {
+            TodoistResponse::Paginated { results } => results,
+            TodoistResponse::Bare(tasks) => tasks,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TodoistTask {
+    content: String,
+    #[serde(default)]
+    priority: u8,
+    due: Option<TodoistDue>,
+    checked: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct TodoistDue {
+    date: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(provider: TaskProvider, url: &str, token: &str) -> TasksSource {
+        TasksSource {
+            provider,
+            url: url.to_string(),
+            token: token.to_string(),
+            username: "anna".to_string(),
+            password: "secret".to_string(),
+            filter: String::new(),
+            title: default_title(),
+            max_tasks: default_max_tasks(),
+        }
+    }
+
+    #[test]
+    fn dates_accept_both_iso_forms() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 9);
+        for value in [
+            "2026-03-09",
+            "20260309",
+            "20260309T170000Z",
+            "2026-03-09T17:00:00",
+        ] {
+            assert_eq!(parse_date(value), date, "{}", value);
+        }
+        assert_eq!(parse_date("next week"), None);
+        assert_eq!(parse_date(""), None);
+    }
+
+    #[test]
+    fn escapes_are_undone() {
+        assert_eq!(
+            xml_unescape("&lt;d:href&gt;&amp;lt;&quot;a&apos;&#13;"),
+            "<d:href>&lt;\"a'\r"
+        );
+        assert_eq!(
+            ics_unescape("Milk\\, eggs\\; bread\\nand \\\\ more"),
+            "Milk, eggs; bread and \\ more"
+        );
+    }
+
+    #[test]
+    fn open_vtodos_are_parsed() {
+        let text = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VTODO\r\n\
+            SUMMARY:Renew pass\r\n \
+             port\r\n\
+            DUE;VALUE=DATE:20260310\r\n\
+            PRIORITY:1\r\n\
+            BEGIN:VALARM\r\n\
+            ACTION:DISPLAY\r\n\
+            SUMMARY:Reminder\r\n\
+            END:VALARM\r\n\
+            END:VTODO\r\n\
+            BEGIN:VTODO\r\n\
+            SUMMARY:Water plants\r\n\
+            STATUS:COMPLETED\r\n\
+            END:VTODO\r\n\
+            BEGIN:VTODO\r\n\
+            SUMMARY:Call Bob\r\n\
+            PRIORITY:5\r\n\
+            END:VTODO\r\n\
+            BEGIN:VTODO\r\n\
+            SUMMARY:Tidy up\r\n\
+            DUE:20260312T090000Z\r\n\
+            PRIORITY:9\r\n\
+            END:VTODO\r\n\
+            END:VCALENDAR\r\n";
+
+        let tasks = parse_vtodos(text);
+        let summary: Vec<_> = tasks
+            .iter()
+            .map(|t| (t.title.as_str(), t.due.map(|d| d.to_string()), t.priority))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "Renew passport",
+                    Some("2026-03-10".to_string()),
+                    Priority::High
+                ),
+                ("Call Bob", None, Priority::Medium),
+                ("Tidy up", Some("2026-03-12".to_string()), Priority::Low),
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_are_validated_per_provider() {
+        let caldav = "https://cloud.example.org/remote.php/dav/calendars/anna/tasks/";
+        for (source, valid) in [
+            (source(TaskProvider::Todoist, "", "token"), true),
+            (source(TaskProvider::Todoist, "", " "), false),
+            (source(TaskProvider::Caldav, caldav, ""), true),
+            (source(TaskProvider::Caldav, "", ""), false),
+            (source(TaskProvider::Caldav, "cloud.example.org", ""), false),
+            (
+                TasksSource {
+                    max_tasks: 0,
+                    ..source(TaskProvider::Caldav, caldav, "")
+                },
+                false,
+            ),
+        ] {
+            assert_eq!(source.validate().is_ok(), valid, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn health_checks_authenticate_per_provider() {
+        let todoist = source(TaskProvider::Todoist, "", "token");
+        let request = todoist.health_request().unwrap();
+        assert_eq!(
+            request.url,
+            "https://api.todoist.com/api/v1/projects?limit=1"
+        );
+        assert_eq!(request.headers[AUTHORIZATION], "Bearer token");
+
+        let caldav = source(
+            TaskProvider::Caldav,
+            " http://localhost:5232/anna/tasks/ ",
+            "",
+        );
+        let request = caldav.health_request().unwrap();
+        assert_eq!(request.url, "http://localhost:5232/anna/tasks/");
+        assert_eq!(request.headers[AUTHORIZATION], "Basic YW5uYTpzZWNyZXQ=");
+    }
+}
@@ -0,0 +1,109 @@
+//! Static text source.
+//!
+//! Renders a configured message, word-wrapped and centered vertically.
+//! Mostly useful as a layout zone (headings, notes, labels).
+
+use crate::config::ConfigError;
+use crate::render::{Canvas, Theme, colors, text, wrap_text};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// Horizontal text alignment
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// Text source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextSource {
+    /// Text to show (use "\n" for line breaks)
+    pub text: String,
+    /// Line height in pixels (0 = largest size that fits)
+    #[serde(default)]
+    pub size: u32,
+    /// Palette color name for the text (default from theme)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Palette color name for the background (default from theme)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    /// Horizontal alignment
+    #[serde(default)]
+    pub align: TextAlign,
+}
+
+/// Sizes tried when fitting text automatically, largest first
+const AUTO_SIZES: [u32; 10] = [160, 120, 80, 60, 40, 30, 20, 18, 15, 13];
+
+impl TextSource {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for name in [&self.color, &self.background].into_iter().flatten() {
+            if colors::from_name(name).is_none() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Unknown color '{}' (use black, white, yellow, red, orange, blue or green)",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Render the text
+    pub fn render(&self, width: u32, height: u32, theme: &Theme) -> DynamicImage {
+        let foreground = self
+            .color
+            .as_deref()
+            .and_then(colors::from_name)
+            .unwrap_or(theme.text);
+        let background = self
+            .background
+            .as_deref()
+            .and_then(colors::from_name)
+            .unwrap_or(theme.background);
+        let mut canvas = Canvas::new(width, height, background);
+
+        let margin = (height.min(width) / 20).max(2);
+        let max_width = width.saturating_sub(margin * 2);
+        let max_height = height.saturating_sub(margin * 2);
+
+        let (size, lines) = if self.size > 0 {
+            (self.size, wrap_text(&self.text, self.size, max_width))
+        } else {
+            AUTO_SIZES
+                .iter()
+                .map(|&size| (size, wrap_text(&self.text, size, max_width)))
+                .find(|(size, lines)| {
+                    lines.len() as u32 * size <= max_height
+                        && lines
+                            .iter()
+                            .all(|l| text::text_width(l, *size) <= max_width)
+                })
+                .unwrap_or_else(|| (10, wrap_text(&self.text, 10, max_width)))
+        };
+
+        let block_height = lines.len() as u32 * size;
+        let mut y = (height.saturating_sub(block_height) / 2) as i32;
+        for line in &lines {
+            match self.align {
+                TextAlign::Left => {
+                    canvas.text(margin as i32, y, line, size, foreground);
+                }
+                TextAlign::Center => {
+                    canvas.text_centered(width as i32 / 2, y, line, size, foreground)
+                }
+                TextAlign::Right => {
+                    canvas.text_right((width - margin) as i32, y, line, size, foreground)
+                }
+            }
+            y += size as i32;
+        }
+
+        canvas.into_image()
+    }
+}
@@ -0,0 +1,442 @@
+//! Stock/crypto ticker source.
+//!
+//! Fetches quotes for a list of symbols and renders a board with the
+//! price and day change per symbol, arrows colored green (up) or red
+//! (down). Crypto quotes come from CoinGecko, stock quotes from Finnhub.
+
+use super::{ImageRequest, SourceError};
+use crate::config::ConfigError;
+use crate::image_proc::download::{DownloadConfig, fetch_json};
+use crate::render::{Theme, fit_text};
+use crate::secrets;
+use image::DynamicImage;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Quote API provider
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteProvider {
+    /// CoinGecko simple price API (symbols are coin ids, e.g. "bitcoin")
+    Coingecko,
+    /// Finnhub quote API (symbols are tickers, e.g. "AAPL")
+    Finnhub,
+}
+
+impl QuoteProvider {
+    fn default_base_url(self) -> &'static str {
+        match self {
+            QuoteProvider::Coingecko => "https://api.coingecko.com/api/v3",
+            QuoteProvider::Finnhub => "https://finnhub.io/api/v1",
+        }
+    }
+}
+
+/// Ticker source settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TickerSource {
+    /// Which quote API to use
+    pub provider: QuoteProvider,
+    /// API base URL (empty for the provider's public endpoint)
+    #[serde(default)]
+    pub base_url: String,
+    /// API key (required for Finnhub, optional demo key for CoinGecko)
+    #[serde(default)]
+    pub api_key: String,
+    /// Symbols to show, in display order
+    pub symbols: Vec<String>,
+    /// Quote currency for CoinGecko (e.g., "usd", "eur")
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_currency() -> String {
+    "usd".to_string()
+}
+
+/// Maximum number of symbols that fit on the board
+const MAX_SYMBOLS: usize = 12;
+
+/// Quote for one symbol
+#[derive(Debug, Clone)]
+struct Quote {
+    symbol: String,
+    price: Option<f64>,
+    /// Day change in percent
+    change_pct: Option<f64>,
+}
+
+impl TickerSource {
+    fn base_url(&self) -> &str {
+        let base = self.base_url.trim().trim_end_matches('/');
+        if base.is_empty() {
+            self.provider.default_base_url()
+        } else {
+            base
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap, SourceError> {
+        let mut headers = HeaderMap::new();
+        let key = secrets::resolve(&self.api_key)?;
+        if !key.is_empty()
+            && let Ok(value) = HeaderValue::from_str(&key)
+        {
+            let name = match self.provider {
+                QuoteProvider::Coingecko => "x-cg-demo-api-key",
+                QuoteProvider::Finnhub => "X-Finnhub-Token",
+            };
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// Whether enough is configured to fetch quotes
+    pub fn is_configured(&self) -> bool {
+        !self.symbols.is_empty()
+    }
+
+    /// Lightweight request used by source health checks
+    pub fn health_request(&self) -> Option<ImageRequest> {
+        let url = match self.provider {
+            QuoteProvider::Coingecko => format!("{}/ping", self.base_url()),
+            QuoteProvider::Finnhub => format!(
+                "{}/quote?symbol={}",
+                self.base_url(),
+                self.symbols.first()?.trim()
+            ),
+        };
+        Some(ImageRequest {
+            url,
+            headers: self.headers().ok()?,
+        })
+    }
+
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let base_url = self.base_url();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "Ticker base_url must start with http:// or https://".to_string(),
+            ));
+        }
+
+        if self.symbols.is_empty() || self.symbols.len() > MAX_SYMBOLS {
+            return Err(ConfigError::ValidationError(format!(
+                "Ticker needs between 1 and {} symbols",
+                MAX_SYMBOLS
+            )));
+        }
+
+        if self.symbols.iter().any(|s| s.trim().is_empty()) {
+            return Err(ConfigError::ValidationError(
+                "Ticker symbols must not be empty".to_string(),
+            ));
+        }
+
+        if self.provider == QuoteProvider::Finnhub && self.api_key.trim().is_empty() {
+            return Err(ConfigError::ValidationError(
+                "Finnhub requires an api_key".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fetch quotes and render the ticker board
+    pub async fn render(
+        &self,
+        download: &DownloadConfig,
+        width: u32,
+        height: u32,
+        theme: &Theme,
+    ) -> Result<DynamicImage, SourceError> {
+        let quotes = match self.provider {
+            QuoteProvider::Coingecko => self.fetch_coingecko(download).await?,
+            QuoteProvider::Finnhub => self.fetch_finnhub(download).await?,
+        };
+
+        let title = match self.provider {
+            QuoteProvider::Coingecko => format!("Crypto ({})", self.currency.to_uppercase()),
+            QuoteProvider::Finnhub => "Stocks".to_string(),
+        };
+
+        Ok(render_board(&title, &quotes, width, height, theme))
+    }
+
+    async fn fetch_coingecko(&self, download: &DownloadConfig) -> Result<Vec<Quote>, SourceError> {
+        let currency = self.currency.trim().to_lowercase();
+        let ids: Vec<&str> = self.symbols.iter().map(|s| s.trim()).collect();
+        let url = format!(
+            "{}/simple/price?ids={}&vs_currencies={}&include_24hr_change=true",
+            self.base_url(),
+            ids.join(","),
+            currency
+        );
+
+        let prices: CoingeckoPrices = fetch_json(&url, &self.headers()?, download).await?;
+        Ok(coingecko_quotes(&ids, &prices, &currency))
+    }
+
+    async fn fetch_finnhub(&self, download: &DownloadConfig) -> Result<Vec<Quote>, SourceError> {
+        let headers = self.headers()?;
+        let mut quotes = Vec::with_capacity(self.symbols.len());
+
+        for symbol in &self.symbols {
+            let symbol = symbol.trim();
+            let url = format!("{}/quote?symbol={}", self.base_url(), symbol);
+            let quote: FinnhubQuote = fetch_json(&url, &headers, download).await?;
+            quotes.push(finnhub_quote(symbol, quote));
+        }
+
+        Ok(quotes)
+    }
+}
+
+/// Quotes for `ids` in display order, empty where CoinGecko has no price
+fn coingecko_quotes(ids: &[&str], prices: &CoingeckoPrices, currency: &str) -> Vec<Quote> {
+    let change_key = format!("{}_24h_change", currency);
+    ids.iter()
+        .map(|id| {
+            let entry = prices.get(*id);
+            Quote {
+                symbol: id.to_string(),
+                price: entry.and_then(|e| e.get(currency).copied().flatten()),
+                change_pct: entry.and_then(|e| e.get(&change_key).copied().flatten()),
+            }
+        })
+        .collect()
+}
+
+/// Quote for `symbol`; Finnhub answers unknown symbols with all-zero quotes
+fn finnhub_quote(symbol: &str, quote: FinnhubQuote) -> Quote {
+    let known = quote.c.is_some_and(|c| c != 0.0);
+    Quote {
+        symbol: symbol.to_string(),
+        price: quote.c.filter(|_| known),
+        change_pct: quote.dp.filter(|_| known),
+    }
+}
+
+/// Format a price with precision suited to its magnitude
+fn format_price(price: f64) -> String {
+    if price >= 1000.0 {
+        format!("{:.0}", price)
+    } else if price >= 1.0 {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.4}", price)
+    }
+}
+
+/// Draw the ticker board
+fn render_board(
+    title: &str,
+    quotes: &[Quote],
+    width: u32,
+    height: u32,
+    theme: &Theme,
+) -> DynamicImage {
+    let mut canvas = theme.canvas(width, height);
+    let unit = theme.unit(height);
+    let margin = unit as i32;
+    let right = width as i32 - margin;
+
+    theme.header(
+        &mut canvas,
+        unit,
+        title,
+        &chrono::Local::now().format("%H:%M").to_string(),
+    );
+
+    if quotes.is_empty() {
+        return canvas.into_image();
+    }
+
+    let top = unit * 4;
+    let row_height = (height.saturating_sub(top) / quotes.len() as u32).min(unit * 4);
+    let text_size = (row_height / 2).max(10);
+    let change_col = right - (unit * 8) as i32;
+    let price_col = change_col - unit as i32 * 2;
+
+    for (i, quote) in quotes.iter().enumerate() {
+        let y = (top + row_height * i as u32) as i32;
+        let text_y = y + (row_height.saturating_sub(text_size) / 2) as i32;
+
+        let symbol_width = (price_col - margin).max(0) as u32 / 2;
+        let symbol = fit_text(&quote.symbol.to_uppercase(), text_size, symbol_width);
+        canvas.text(margin, text_y, &symbol, text_size, theme.text);
+
+        let price = quote
+            .price
+            .map(format_price)
+            .unwrap_or_else(|| "--".to_string());
+        canvas.text_right(price_col, text_y, &price, text_size, theme.text);
+
+        if let Some(change) = quote.change_pct {
+            let color = if change >= 0.0 {
+                theme.positive
+            } else {
+                theme.negative
+            };
+
+            // Arrow pointing up or down, sized to the text
+            let half = (text_size / 2) as i32;
+            let ax = change_col + half;
+            let (tip, base) = if change >= 0.0 {
+                (text_y, text_y + text_size as i32)
+            } else {
+                (text_y + text_size as i32, text_y)
+            };
+            canvas.fill_triangle(
+                [(ax, tip), (ax - half, base), (ax + half, base)],
+                theme.icon(color),
+            );
+
+            canvas.text_right(right, text_y, &format!("{:+.2}%", change), text_size, color);
+        }
+
+        theme.separator(
+            &mut canvas,
+            margin,
+            y + row_height as i32 - 1,
+            width.saturating_sub(unit * 2),
+        );
+    }
+
+    canvas.into_image()
+}
+
+// CoinGecko API payload: coin id → {"usd": price, "usd_24h_change": percent}
+
+type CoingeckoPrices = HashMap<String, HashMap<String, Option<f64>>>;
+
+// Finnhub API payload (only the fields we use)
+
+#[derive(Deserialize)]
+struct FinnhubQuote {
+    /// Current price
+    c: Option<f64>,
+    /// Change in percent
+    dp: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(provider: QuoteProvider, api_key: &str, symbols: &[&str]) -> TickerSource {
+        TickerSource {
+            provider,
+            base_url: String::new(),
+            api_key: api_key.to_string(),
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            currency: default_currency(),
+        }
+    }
+
+    #[test]
+    fn prices_keep_significant_digits() {
+        for (price, expected) in [
+            (64250.7, "64251"),
+            (1000.0, "1000"),
+            (189.456, "189.46"),
+            (1.0, "1.00"),
+            (0.123456, "0.1235"),
+            (0.0, "0.0000"),
+        ] {
+            assert_eq!(format_price(price), expected, "{}", price);
+        }
+    }
+
+    #[test]
+    fn settings_are_validated_per_provider() {
+        let too_many = vec!["btc"; MAX_SYMBOLS + 1];
+        for (source, valid) in [
+            (source(QuoteProvider::Coingecko, "", &["bitcoin"]), true),
+            (source(QuoteProvider::Finnhub, "key", &["AAPL"]), true),
+            (source(QuoteProvider::Finnhub, "", &["AAPL"]), false),
+            (source(QuoteProvider::Coingecko, "", &[]), false),
+            (source(QuoteProvider::Coingecko, "", &too_many), false),
+            (
+                source(QuoteProvider::Coingecko, "", &["bitcoin", " "]),
+                false,
+            ),
+            (
+                TickerSource {
+                    base_url: "localhost:8080".to_string(),
+                    ..source(QuoteProvider::Coingecko, "", &["bitcoin"])
+                },
+                false,
+            ),
+        ] {
+            assert_eq!(source.validate().is_ok(), valid, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn health_checks_use_the_provider_endpoint() {
+        let coingecko = source(QuoteProvider::Coingecko, "demo", &["bitcoin"]);
+        let request = coingecko.health_request().unwrap();
+        assert_eq!(request.url, "https://api.coingecko.com/api/v3/ping");
+        assert_eq!(request.headers["x-cg-demo-api-key"], "demo");
+
+        let finnhub = TickerSource {
+            base_url: "http://localhost:8080/".to_string(),
+            ..source(QuoteProvider::Finnhub, "key", &[" AAPL"])
+        };
+        let request = finnhub.health_request().unwrap();
+        assert_eq!(request.url, "http://localhost:8080/quote?symbol=AAPL");
+        assert_eq!(request.headers["X-Finnhub-Token"], "key");
+    }
+
+    #[test]
+    fn quotes_keep_order_and_drop_unknown_symbols() {
+        let prices: CoingeckoPrices = serde_json::from_str(
+            r#"{
+                "ethereum": {"eur": 3012.5, "eur_24h_change": -1.25},
+                "bitcoin": {"eur": 61000.0, "eur_24h_change": null}
+            }"#,
+        )
+        .unwrap();
+        let quotes = coingecko_quotes(&["bitcoin", "ethereum", "dogecorn"], &prices, "eur");
+        let symbols: Vec<&str> = quotes.iter().map(|q| q.symbol.as_str()).collect();
+        assert_eq!(symbols, ["bitcoin", "ethereum", "dogecorn"]);
+        assert_eq!(
+            (quotes[0].price, quotes[0].change_pct),
+            (Some(61000.0), None)
+        );
+        assert_eq!(
+            (quotes[1].price, quotes[1].change_pct),
+            (Some(3012.5), Some(-1.25))
+        );
+        assert_eq!((quotes[2].price, quotes[2].change_pct), (None, None));
+
+        let finnhub = |json: &str| finnhub_quote("AAPL", serde_json::from_str(json).unwrap());
+        let known = finnhub(r#"{"c": 189.5, "dp": 0.8}"#);
+        assert_eq!((known.price, known.change_pct), (Some(189.5), Some(0.8)));
+        let unknown = finnhub(r#"{"c": 0, "dp": null}"#);
+        assert_eq!((unknown.price, unknown.change_pct), (None, None));
+    }
+
+    #[test]
+    fn changes_are_colored_by_direction() {
+        let theme = Theme::default();
+        let colors_of = |change_pct| {
+            let quote = Quote {
+                symbol: "btc".to_string(),
+                price: Some(1.0),
+                change_pct,
+            };
+            let img = render_board("Crypto", &[quote], 400, 240, &theme).to_rgb8();
+            (
+                img.pixels().any(|&p| p == theme.positive),
+                img.pixels().any(|&p| p == theme.negative),
+            )
+        };
+        assert_eq!(colors_of(Some(2.5)), (true, false));
+        assert_eq!(colors_of(Some(-2.5)), (false, true));
+        assert_eq!(colors_of(None), (false, false));
+    }
+}
@@ -0,0 +1,473 @@
+//! Runtime status tracking.
+//!
+//! Collects device health information shared between the scheduler,
+//! the image pipeline and the web server, and exposes it as a
+//! serializable snapshot for the `/api/v1/status` endpoint.
+
+use crate::arbiter::Priority;
+use crate::crash::{CrashReport, RestartState};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Serializable view of the current device status
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct StatusSnapshot {
+    /// Name of the device (`device_name` or the host name)
+    pub device_name: String,
+    /// Host name of the device
+    pub hostname: String,
+    /// Application version
+    pub version: &'static str,
+    /// Local time the process started (RFC 3339)
+    pub started_at: String,
+    /// Seconds since the process started
+    pub uptime_secs: u64,
+    /// Number of starts recorded in the state directory
+    pub restart_count: u64,
+    /// Last recorded crash, if any
+    pub last_crash: Option<CrashReport>,
+    /// Result of the most recent image source check, if any
+    pub source_health: Option<SourceHealth>,
+    /// Full panel refreshes since local midnight
+    pub refreshes_today: u32,
+    /// Local time of the last full panel refresh (RFC 3339)
+    pub last_refresh_at: Option<String>,
+    /// Local time the scheduler will refresh next (RFC 3339)
+    pub next_refresh_at: Option<String>,
+    /// Whether the system clock looks synchronized; while it does not,
+    /// the schedule is ignored
+    pub clock_synchronized: bool,
+    /// Whether frames go to the in-memory mock display instead of the
+    /// panel (`--mock-display`, or no access to the panel's devices)
+    pub mock_display: bool,
+    /// Settings changed in the config file that only take effect after a
+    /// restart; until then the running values stay in effect
+    pub restart_required: Vec<String>,
+    /// Whether the panel is being updated right now
+    pub refreshing: bool,
+    /// Step of the running update, if any
+    pub refresh_stage: Option<RefreshStage>,
+    /// Most recent failed update since start, if any; compare its time
+    /// with `last_refresh_at` to see whether a later one succeeded
+    pub last_error: Option<LastError>,
+    /// Kind of content shown instead of the scheduled source, if any
+    pub override_priority: Option<Priority>,
+    /// Local time the override ends by itself and the schedule resumes
+    /// (RFC 3339); None for alerts, which last until they are cleared
+    pub override_until: Option<String>,
+    /// Seconds until the schedule resumes
+    pub override_remaining_secs: Option<u64>,
+}
+
+/// Step of a running panel update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshStage {
+    /// Downloading or rendering the image
+    Fetching,
+    /// Transforming and dithering
+    Processing,
+    /// Sending the frame and waiting for the panel
+    Displaying,
+}
+
+/// Cause of a failed panel update, for the error counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorCategory {
+    /// Host name could not be resolved
+    Dns,
+    /// TLS handshake or certificate problem
+    Tls,
+    /// Connection refused, reset or timed out
+    Connection,
+    /// Source answered with a 4xx status
+    Http4xx,
+    /// Source answered with a 5xx status
+    Http5xx,
+    /// Response was not a decodable image
+    Decode,
+    /// Other source problem (checksum, login page, missing settings, ...)
+    Source,
+    /// SPI transfer to the panel failed
+    Spi,
+    /// GPIO setup failed
+    Gpio,
+    /// Panel did not release its busy line in time
+    BusyTimeout,
+    /// Other display problem
+    Display,
+}
+
+impl ErrorCategory {
+    #[cfg(feature = "metrics")]
+    pub const ALL: [ErrorCategory; 11] = [
+        ErrorCategory::Dns,
+        ErrorCategory::Tls,
+        ErrorCategory::Connection,
+        ErrorCategory::Http4xx,
+        ErrorCategory::Http5xx,
+        ErrorCategory::Decode,
+        ErrorCategory::Source,
+        ErrorCategory::Spi,
+        ErrorCategory::Gpio,
+        ErrorCategory::BusyTimeout,
+        ErrorCategory::Display,
+    ];
+
+    /// Label value in the metrics
+    #[cfg(feature = "metrics")]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Dns => "dns",
+            ErrorCategory::Tls => "tls",
+            ErrorCategory::Connection => "connection",
+            ErrorCategory::Http4xx => "http_4xx",
+            ErrorCategory::Http5xx => "http_5xx",
+            ErrorCategory::Decode => "decode",
+            ErrorCategory::Source => "source",
+            ErrorCategory::Spi => "spi",
+            ErrorCategory::Gpio => "gpio",
+            ErrorCategory::BusyTimeout => "busy_timeout",
+            ErrorCategory::Display => "display",
+        }
+    }
+
+    /// Category of an HTTP error status
+    pub fn of_http_status(status: u16) -> Self {
+        if status >= 500 {
+            ErrorCategory::Http5xx
+        } else {
+            ErrorCategory::Http4xx
+        }
+    }
+}
+
+/// A failed panel update
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct LastError {
+    /// Error description
+    pub message: String,
+    /// Step the update failed at
+    pub stage: RefreshStage,
+    /// Local time of the failure (RFC 3339)
+    pub at: String,
+}
+
+/// Reachability of the configured image source
+#[derive(Debug, Clone, Serialize, utoipa::ToSchema)]
+pub struct SourceHealth {
+    /// Whether the last check succeeded
+    pub healthy: bool,
+    /// Local time of the last check (RFC 3339)
+    pub checked_at: String,
+    /// HTTP status returned by the source, if a response was received
+    pub http_status: Option<u16>,
+    /// Error description for failed checks
+    pub error: Option<String>,
+}
+
+/// File name of the daily refresh counter inside the state directory
+pub const REFRESH_COUNT_FILE: &str = "refresh_count.json";
+
+/// Refresh counter as persisted, so a restart keeps the day's count
+#[derive(Debug, Serialize, Deserialize)]
+struct RefreshCount {
+    /// Day counted (`YYYY-MM-DD`)
+    date: String,
+    count: u32,
+}
+
+impl RefreshCount {
+    /// Count stored in `path`, if it is from today
+    fn load_today(path: &Path) -> Option<u32> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let stored: Self = serde_json::from_str(&content).ok()?;
+        let date = NaiveDate::parse_from_str(&stored.date, "%Y-%m-%d").ok()?;
+        Some(today_count((date, stored.count)))
+    }
+
+    fn save(path: &Path, (date, count): (NaiveDate, u32)) {
+        let stored = Self {
+            date: date.format("%Y-%m-%d").to_string(),
+            count,
+        };
+        let result = serde_json::to_string(&stored)
+            .map_err(std::io::Error::other)
+            .and_then(|content| std::fs::write(path, content));
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to persist refresh counter to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Mutable status fields
+#[derive(Debug)]
+struct StatusState {
+    device_name: String,
+    restart_count: u64,
+    last_crash: Option<CrashReport>,
+    source_health: Option<SourceHealth>,
+    /// Day the refresh counter belongs to and its count
+    refreshes: (NaiveDate, u32),
+    last_refresh_at: Option<String>,
+    next_refresh_at: Option<String>,
+    refresh_stage: Option<RefreshStage>,
+    last_error: Option<LastError>,
+    /// Failed updates since start, by cause
+    error_counts: BTreeMap<ErrorCategory, u64>,
+    clock_synchronized: bool,
+    mock_display: bool,
+    restart_required: Vec<String>,
+    /// Priority of the active override and when it ends
+    active_override: Option<(Priority, Option<DateTime<Local>>)>,
+}
+
+/// Shared runtime status
+///
+/// Uses a std lock: updates are tiny and never held across an await.
+#[derive(Debug)]
+pub struct StatusTracker {
+    started: Instant,
+    started_at: String,
+    state: RwLock<StatusState>,
+    /// File the refresh counter is kept in across restarts
+    refresh_file: Option<PathBuf>,
+}
+
+impl StatusTracker {
+    /// Create a tracker seeded with persisted restart and crash information
+    pub fn new(restart: &RestartState, last_crash: Option<CrashReport>) -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            state: RwLock::new(StatusState {
+                device_name: crate::network::hostname(),
+                restart_count: restart.restart_count,
+                last_crash,
+                source_health: None,
+                refreshes: (chrono::Local::now().date_naive(), 0),
+                last_refresh_at: None,
+                next_refresh_at: None,
+                refresh_stage: None,
+                last_error: None,
+                error_counts: BTreeMap::new(),
+                clock_synchronized: crate::time_sync::is_synchronized(),
+                mock_display: false,
+                restart_required: Vec::new(),
+                active_override: None,
+            }),
+            refresh_file: None,
+        }
+    }
+
+    /// Keep the refresh counter in `state_dir`, continuing today's count
+    /// from before a restart
+    pub fn with_state_dir(mut self, state_dir: &Path) -> Self {
+        let path = state_dir.join(REFRESH_COUNT_FILE);
+        if let Some(count) = RefreshCount::load_today(&path) {
+            let state = self.state.get_mut().unwrap_or_else(|e| e.into_inner());
+            state.refreshes = (chrono::Local::now().date_naive(), count);
+        }
+        self.refresh_file = Some(path);
+        self
+    }
+
+    /// Take a consistent snapshot of the current status
+    pub fn snapshot(&self) -> StatusSnapshot {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let now = Local::now();
+        let active_override = state
+            .active_override
+            .filter(|(_, until)| until.is_none_or(|until| until > now));
+        let override_until = active_override.and_then(|(_, until)| until);
+
+        StatusSnapshot {
+            device_name: state.device_name.clone(),
+            hostname: crate::network::hostname(),
+            version: env!("CARGO_PKG_VERSION"),
+            started_at: self.started_at.clone(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            restart_count: state.restart_count,
+            last_crash: state.last_crash.clone(),
+            source_health: state.source_health.clone(),
+            refreshes_today: today_count(state.refreshes),
+            last_refresh_at: state.last_refresh_at.clone(),
+            next_refresh_at: state.next_refresh_at.clone(),
+            clock_synchronized: state.clock_synchronized,
+            mock_display: state.mock_display,
+            restart_required: state.restart_required.clone(),
+            refreshing: state.refresh_stage.is_some(),
+            refresh_stage: state.refresh_stage,
+            last_error: state.last_error.clone(),
+            override_priority: active_override.map(|(priority, _)| priority),
+            override_until: override_until.map(|until| until.to_rfc3339()),
+            override_remaining_secs: override_until
+                .map(|until| (until - now).num_seconds().max(0) as u64),
+        }
+    }
+
+    /// Record the name of the device, after the config changed
+    pub fn set_device_name(&self, name: String) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .device_name = name;
+    }
+
+    /// Record the settings a reloaded config changes only on restart
+    pub fn set_restart_required(&self, settings: &[&str]) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .restart_required = settings.iter().map(|s| s.to_string()).collect();
+    }
+
+    /// Count a full refresh of the panel
+    pub fn record_panel_refresh(&self) {
+        let refreshes = {
+            let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+            let today = chrono::Local::now().date_naive();
+            state.refreshes = (today, today_count(state.refreshes) + 1);
+            state.last_refresh_at = Some(chrono::Local::now().to_rfc3339());
+            state.refreshes
+        };
+        if let Some(path) = &self.refresh_file {
+            RefreshCount::save(path, refreshes);
+        }
+    }
+
+    /// Record whether the system clock looks synchronized
+    pub fn set_clock_synchronized(&self, synchronized: bool) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clock_synchronized = synchronized;
+    }
+
+    /// Record that frames go to the mock display instead of the panel
+    pub fn set_mock_display(&self, mock: bool) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .mock_display = mock;
+    }
+
+    /// Record the step of the running panel update (None when done)
+    pub fn set_refresh_stage(&self, stage: Option<RefreshStage>) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .refresh_stage = stage;
+    }
+
+    /// Record a failed panel update and count it under `category`
+    pub fn record_error(&self, stage: RefreshStage, category: ErrorCategory, message: &str) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        *state.error_counts.entry(category).or_default() += 1;
+        state.last_error = Some(LastError {
+            message: crate::secrets::redact(message).into_owned(),
+            stage,
+            at: chrono::Local::now().to_rfc3339(),
+        });
+    }
+
+    /// Record the active override and when it ends (None when there is none)
+    pub fn set_override(&self, active: Option<(Priority, Option<DateTime<Local>>)>) {
+        self.state
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .active_override = active;
+    }
+
+    /// Record when the scheduler refreshes next
+    pub fn record_next_refresh(&self, after: Duration) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.next_refresh_at = chrono::Duration::from_std(after)
+            .ok()
+            .map(|after| (chrono::Local::now() + after).to_rfc3339());
+    }
+
+    /// Failed updates since start for every category, including unseen ones
+    #[cfg(feature = "metrics")]
+    pub fn error_counts(&self) -> Vec<(ErrorCategory, u64)> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        ErrorCategory::ALL
+            .iter()
+            .map(|category| {
+                let count = state.error_counts.get(category).copied().unwrap_or(0);
+                (*category, count)
+            })
+            .collect()
+    }
+
+    /// Full panel refreshes since local midnight
+    pub fn refreshes_today(&self) -> u32 {
+        today_count(
+            self.state
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .refreshes,
+        )
+    }
+
+    /// Record the outcome of a source check or download
+    pub fn record_source_health(&self, http_status: Option<u16>, error: Option<String>) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.source_health = Some(SourceHealth {
+            healthy: error.is_none(),
+            checked_at: chrono::Local::now().to_rfc3339(),
+            http_status,
+            error: error.map(|e| crate::secrets::redact(&e).into_owned()),
+        });
+    }
+}
+
+/// Counter value, or 0 once the day it was counted on has passed
+fn today_count((day, count): (NaiveDate, u32)) -> u32 {
+    if day == chrono::Local::now().date_naive() {
+        count
+    } else {
+        0
+    }
+}
+
+impl Default for StatusTracker {
+    fn default() -> Self {
+        Self::new(&RestartState::default(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_count_survives_a_restart_on_the_same_day() {
+        let dir = std::env::temp_dir().join(format!("epaper-refreshes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let status = StatusTracker::default().with_state_dir(&dir);
+        status.record_panel_refresh();
+        status.record_panel_refresh();
+        assert_eq!(status.refreshes_today(), 2);
+
+        let restarted = StatusTracker::default().with_state_dir(&dir);
+        assert_eq!(restarted.refreshes_today(), 2);
+
+        // A count from another day starts over
+        let yesterday = Local::now().date_naive().pred_opt().unwrap();
+        RefreshCount::save(&dir.join(REFRESH_COUNT_FILE), (yesterday, 7));
+        let next_day = StatusTracker::default().with_state_dir(&dir);
+        assert_eq!(next_day.refreshes_today(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,2887 @@
+//! End-to-end test harness.
+//!
+//! Spins up an Axum server on a loopback port serving fixture images and
+//! wires the scheduler and image pipeline to the mock display backend, so
+//! the full refresh path (download → transform → dither → display) can be
+//! exercised in CI without hardware.
+
+use crate::arbiter::ContentArbiter;
+use crate::config::Config;
+use crate::display::{DisplayController, MockHandle, PanelModel};
+use crate::image_proc::ImageProcessor;
+use crate::image_proc::download::sha256_hex;
+use crate::scheduler::Scheduler;
+use crate::status::StatusTracker;
+use axum::{
+    Form, Router,
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+    routing::{MethodRouter, get, post},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// Encode a solid-color PNG fixture
+pub fn fixture_png(width: u32, height: u32, rgb: [u8; 3]) -> Vec<u8> {
+    let img = image::RgbImage::from_pixel(width, height, image::Rgb(rgb));
+    let mut bytes = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Png,
+    )
+    .expect("Failed to encode fixture PNG");
+    bytes
+}
+
+/// PNG header claiming `width` x `height` RGB pixels, without pixel data
+pub fn oversized_png(width: u32, height: u32) -> Vec<u8> {
+    fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32fast::hash(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+    chunk(
+        &mut png,
+        b"IDAT",
+        &[0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+    );
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Solid-color JPEG with an EXIF block holding `description` and the
+/// date taken `taken` ("YYYY:MM:DD HH:MM:SS")
+pub fn fixture_photo_jpeg(rgb: [u8; 3], description: &str, taken: &str) -> Vec<u8> {
+    fn entry(tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+        tiff.extend_from_slice(&tag.to_le_bytes());
+        tiff.extend_from_slice(&kind.to_le_bytes());
+        tiff.extend_from_slice(&count.to_le_bytes());
+        tiff.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let description = format!("{}\0", description);
+    let taken = format!("{}\0", taken);
+    // IFD0 (description, Exif IFD pointer), its string, then the Exif IFD
+    let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+    let exif_ifd = 38 + description.len() as u32;
+    tiff.extend_from_slice(&2u16.to_le_bytes());
+    entry(&mut tiff, 0x010e, 2, description.len() as u32, 38);
+    entry(&mut tiff, 0x8769, 4, 1, exif_ifd);
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff.extend_from_slice(description.as_bytes());
+    tiff.extend_from_slice(&1u16.to_le_bytes());
+    entry(&mut tiff, 0x9003, 2, taken.len() as u32, exif_ifd + 18);
+    tiff.extend_from_slice(&0u32.to_le_bytes());
+    tiff.extend_from_slice(taken.as_bytes());
+
+    let img = image::RgbImage::from_pixel(400, 240, image::Rgb(rgb));
+    let mut jpeg = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut jpeg),
+        image::ImageFormat::Jpeg,
+    )
+    .expect("Failed to encode fixture JPEG");
+    // APP1 segment right after the start of image marker
+    let mut app1 = vec![0xff, 0xe1];
+    app1.extend_from_slice(&(2 + 6 + tiff.len() as u16).to_be_bytes());
+    app1.extend_from_slice(b"Exif\0\0");
+    app1.extend_from_slice(&tiff);
+    jpeg.splice(2..2, app1);
+    jpeg
+}
+
+/// Tar archive of `files`, gzipped
+pub fn fixture_tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut tar = Vec::new();
+    for (name, data) in files {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        // Checksum of the header with the checksum field as spaces
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        tar.extend_from_slice(&header);
+        tar.extend_from_slice(data);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+    tar.resize(tar.len() + 1024, 0);
+
+    let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    gz.extend(miniz_oxide::deflate::compress_to_vec(&tar, 6));
+    gz.extend_from_slice(&crc32fast::hash(&tar).to_le_bytes());
+    gz.extend_from_slice(&(tar.len() as u32).to_le_bytes());
+    gz
+}
+
+/// HTTP server serving fixture images
+///
+/// Routes are grouped by the feature they exercise, see [`image_routes`],
+/// [`check_routes`], [`session_routes`], [`content_routes`] and
+/// [`notification_routes`].
+pub struct FixtureServer {
+    base_url: String,
+    handle: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    /// Start the server on an ephemeral loopback port
+    pub async fn start() -> Self {
+        let red = fixture_png(400, 240, [255, 0, 0]);
+        let router = Router::new()
+            .merge(image_routes(&red))
+            .merge(check_routes(&red))
+            .merge(session_routes(&red))
+            .merge(content_routes(&red))
+            .merge(notification_routes());
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind fixture server");
+        let addr = listener
+            .local_addr()
+            .expect("Fixture server has no address");
+
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+
+        Self {
+            base_url: format!("http://{}", addr),
+            handle,
+        }
+    }
+
+    /// Absolute URL for a fixture path
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// GET handler answering with `body`
+fn fixture(content_type: &'static str, body: Vec<u8>) -> MethodRouter {
+    get(move || {
+        let body = body.clone();
+        async move { ([(header::CONTENT_TYPE, content_type)], body) }
+    })
+}
+
+/// Images going through the whole pipeline
+///
+/// - `/red.png`: 400x240 solid red PNG
+/// - `/red-dot.png`: the same with a 20x20 white square in the top left
+/// - `/white.png`: 400x240 solid white PNG
+/// - `/grey.png`: 400x240 solid mid-grey PNG
+/// - `/slow.png`: `/red.png` after a one second delay
+fn image_routes(red: &[u8]) -> Router {
+    let red_dot = {
+        let mut img = image::RgbImage::from_pixel(400, 240, image::Rgb([255, 0, 0]));
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            if x < 20 && y < 20 {
+                *pixel = image::Rgb([255, 255, 255]);
+            }
+        }
+        let mut bytes = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .expect("Failed to encode fixture PNG");
+        bytes
+    };
+    let slow = red.to_vec();
+
+    Router::new()
+        .route("/red.png", fixture("image/png", red.to_vec()))
+        .route("/red-dot.png", fixture("image/png", red_dot))
+        .route(
+            "/white.png",
+            fixture("image/png", fixture_png(400, 240, [255, 255, 255])),
+        )
+        .route(
+            "/grey.png",
+            fixture("image/png", fixture_png(400, 240, [128, 128, 128])),
+        )
+        .route(
+            "/slow.png",
+            get(move || {
+                let slow = slow.clone();
+                async move {
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    ([(header::CONTENT_TYPE, "image/png")], slow)
+                }
+            }),
+        )
+}
+
+/// Downloads the pipeline has to check before showing them
+///
+/// - `/huge.png`: header of a 20000x20000 PNG
+/// - `/endless.png`: 4 MB of zeros, chunked without a Content-Length
+/// - `/truncated.png`: first half of a 400x240 PNG, red on top, noise below
+/// - `/missing.png`: 404
+/// - `/not-an-image`: plain text body with a 200 status
+/// - `/red.png.sha256`: SHA-256 sidecar of `/red.png`
+fn check_routes(red: &[u8]) -> Router {
+    let truncated = {
+        let mut noise = 0x2545_f491u32;
+        let img = image::RgbImage::from_fn(400, 240, |_, y| {
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            let [r, g, b, _] = noise.to_le_bytes();
+            image::Rgb(if y < 120 { [255, 0, 0] } else { [r, g, b] })
+        });
+        let mut bytes = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .expect("Failed to encode fixture PNG");
+        bytes.truncate(bytes.len() / 2);
+        bytes
+    };
+    let red_sha256 = format!("{}  red.png\n", sha256_hex(red));
+
+    Router::new()
+        .route(
+            "/huge.png",
+            fixture("image/png", oversized_png(20000, 20000)),
+        )
+        .route(
+            "/endless.png",
+            get(|| async {
+                let chunk = bytes::Bytes::from(vec![0u8; 64 * 1024]);
+                let chunks = futures_util::stream::repeat(Ok::<_, std::convert::Infallible>(chunk));
+                axum::body::Body::from_stream(futures_util::StreamExt::take(chunks, 64))
+            }),
+        )
+        .route("/truncated.png", fixture("image/png", truncated))
+        .route(
+            "/missing.png",
+            get(|| async { StatusCode::NOT_FOUND.into_response() }),
+        )
+        .route("/not-an-image", get(|| async { "definitely not a PNG" }))
+        .route(
+            "/red.png.sha256",
+            fixture("text/plain", red_sha256.into_bytes()),
+        )
+}
+
+/// Images behind a sign-in or a User-Agent check
+///
+/// - `/login`: HTML sign-in page with a 200 status
+/// - `/private/red.png`: `/red.png` with an `sid=s3cret` cookie, else a
+///   redirect to `/login`
+/// - `/private/login` (POST): sets the cookie for `user=pi&password=pi`,
+///   then redirects to `/login`; 403 for other credentials
+/// - `/picky.png`: `/red.png` for the `EpaperTest/1.0` User-Agent, else 403
+/// - `/picky.json`: `{"ok": true}` for the same User-Agent, else 403
+/// - `/api/camera_proxy/camera.door`: Home Assistant snapshot, `/red.png`
+///   for the `Bearer ha-token` authorization, else 401
+fn session_routes(red: &[u8]) -> Router {
+    let private = red.to_vec();
+    let picky = red.to_vec();
+    let snapshot = red.to_vec();
+
+    Router::new()
+        .route(
+            "/login",
+            get(|| async {
+                axum::response::Html(
+                    "<!DOCTYPE html>\n<html><head><title>Sign in</title></head>\
+                     <body><form method=\"post\"></form></body></html>",
+                )
+            }),
+        )
+        .route(
+            "/private/red.png",
+            get(move |headers: HeaderMap| {
+                let private = private.clone();
+                async move {
+                    let signed_in = headers
+                        .get(header::COOKIE)
+                        .and_then(|v| v.to_str().ok())
+                        .is_some_and(|v| v.split("; ").any(|c| c == "sid=s3cret"));
+                    if signed_in {
+                        ([(header::CONTENT_TYPE, "image/png")], private).into_response()
+                    } else {
+                        (StatusCode::FOUND, [(header::LOCATION, "/login")]).into_response()
+                    }
+                }
+            }),
+        )
+        .route(
+            "/private/login",
+            post(|Form(form): Form<HashMap<String, String>>| async move {
+                if form.get("user").map(String::as_str) != Some("pi")
+                    || form.get("password").map(String::as_str) != Some("pi")
+                {
+                    return StatusCode::FORBIDDEN.into_response();
+                }
+                (
+                    StatusCode::SEE_OTHER,
+                    [
+                        (header::SET_COOKIE, "sid=s3cret; Path=/private; HttpOnly"),
+                        (header::LOCATION, "/login"),
+                    ],
+                )
+                    .into_response()
+            }),
+        )
+        .route(
+            "/picky.png",
+            get(move |headers: HeaderMap| {
+                let picky = picky.clone();
+                async move {
+                    if headers
+                        .get(header::USER_AGENT)
+                        .is_some_and(|v| v == "EpaperTest/1.0")
+                    {
+                        ([(header::CONTENT_TYPE, "image/png")], picky).into_response()
+                    } else {
+                        StatusCode::FORBIDDEN.into_response()
+                    }
+                }
+            }),
+        )
+        .route(
+            "/picky.json",
+            get(|headers: HeaderMap| async move {
+                if headers
+                    .get(header::USER_AGENT)
+                    .is_some_and(|v| v == "EpaperTest/1.0")
+                {
+                    axum::Json(serde_json::json!({ "ok": true })).into_response()
+                } else {
+                    StatusCode::FORBIDDEN.into_response()
+                }
+            }),
+        )
+        .route(
+            "/api/camera_proxy/camera.door",
+            get(move |headers: HeaderMap| {
+                let snapshot = snapshot.clone();
+                async move {
+                    if headers
+                        .get(header::AUTHORIZATION)
+                        .is_some_and(|v| v == "Bearer ha-token")
+                    {
+                        ([(header::CONTENT_TYPE, "image/png")], snapshot).into_response()
+                    } else {
+                        StatusCode::UNAUTHORIZED.into_response()
+                    }
+                }
+            }),
+        )
+}
+
+/// Content with presentation details: bundles, playlists, expiry and EXIF
+///
+/// - `/bundle.zip`: `/red.png` with a caption and a dwell time of 15 minutes
+/// - `/expired.tar.gz`: `/red.png`, valid until 2020
+/// - `/expiring.png`: `/red.png`, expiring one second after the request
+///   (`X-Content-Expires`)
+/// - `/photo.jpg`: red JPEG described as "Harbour at dusk", taken on
+///   14 July 2023
+/// - `/playlist.json`: playlist whose only current slide is `red.png`,
+///   shown for 20 minutes
+fn content_routes(red: &[u8]) -> Router {
+    let bundle = {
+        let mut zip = crate::web::bundle::ZipWriter::default();
+        zip.add(
+            "slides/manifest.json",
+            br#"{"image": "red.png", "caption": "Lunch menu", "dwell_secs": 900}"#,
+        );
+        zip.add("slides/red.png", red);
+        zip.finish()
+    };
+    let expired = fixture_tar_gz(&[
+        ("red.png", red),
+        (
+            "manifest.json",
+            br#"{"valid_until": "2020-01-01T00:00:00Z"}"#,
+        ),
+    ]);
+    let photo = fixture_photo_jpeg([255, 0, 0], "Harbour at dusk", "2023:07:14 18:30:00");
+    let expiring = red.to_vec();
+
+    Router::new()
+        .route("/bundle.zip", fixture("application/zip", bundle))
+        .route("/expired.tar.gz", fixture("application/gzip", expired))
+        .route(
+            "/expiring.png",
+            get(move || {
+                let expiring = expiring.clone();
+                let expires = (chrono::Local::now() + chrono::TimeDelta::seconds(1)).to_rfc3339();
+                async move {
+                    (
+                        [
+                            ("content-type", "image/png"),
+                            ("x-content-expires", &expires),
+                        ],
+                        expiring,
+                    )
+                        .into_response()
+                }
+            }),
+        )
+        .route("/photo.jpg", fixture("image/jpeg", photo))
+        .route(
+            "/playlist.json",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, "application/json")],
+                    r#"{"slides": [
+                        {"url": "/white.png", "valid_until": "2020-01-01T00:00:00Z"},
+                        {"url": "red.png", "duration_secs": 1200},
+                        {"url": "white.png", "valid_from": "2999-01-01T00:00:00Z"}
+                    ]}"#,
+                )
+            }),
+        )
+}
+
+/// Notification services
+///
+/// - `/ntfy/sse`: ntfy event stream with one message, then closed
+fn notification_routes() -> Router {
+    Router::new().route(
+        "/ntfy/sse",
+        get(|| async {
+            (
+                [(header::CONTENT_TYPE, "text/event-stream")],
+                "event: open\n\
+                 data: {\"id\":\"a1\",\"event\":\"open\",\"topic\":\"door\"}\n\n\
+                 event: message\n\
+                 data: {\"id\":\"a2\",\"event\":\"message\",\"topic\":\"door\",\
+                 \"title\":\"Doorbell\",\"message\":\"Someone is at the door\"}\n\n",
+            )
+        }),
+    )
+}
+
+/// Scheduler and pipeline wired to the mock display and a fixture server
+pub struct Harness {
+    /// Kept alive for the lifetime of the harness
+    _server: FixtureServer,
+    pub config: Arc<RwLock<Config>>,
+    pub processor: Arc<ImageProcessor>,
+    pub scheduler: Scheduler,
+    pub status: Arc<StatusTracker>,
+    pub arbiter: Arc<ContentArbiter>,
+    pub display: MockHandle,
+}
+
+impl Harness {
+    /// Build a harness whose image URL points at `path` on the fixture server
+    pub async fn new(path: &str) -> Self {
+        Self::with_panel(path, PanelModel::default()).await
+    }
+
+    /// Like `new`, with the mock display and config set up for `panel`
+    pub async fn with_panel(path: &str, panel: PanelModel) -> Self {
+        let server = FixtureServer::start().await;
+
+        let config = Config {
+            image_url: server.url(path),
+            panel,
+            display_width: panel.width(),
+            display_height: panel.height(),
+            ..Config::default()
+        };
+        let config = Arc::new(RwLock::new(config));
+
+        let (controller, display) = DisplayController::mock(panel);
+        let status = Arc::new(StatusTracker::default());
+        let processor = Arc::new(ImageProcessor::new(controller).with_status(Arc::clone(&status)));
+        let arbiter = Arc::new(ContentArbiter::default().with_status(Arc::clone(&status)));
+        let scheduler = Scheduler::new(
+            Arc::clone(&config),
+            Arc::clone(&processor),
+            Arc::clone(&status),
+        )
+        .with_arbiter(Arc::clone(&arbiter));
+
+        Self {
+            _server: server,
+            config,
+            processor,
+            scheduler,
+            status,
+            arbiter,
+            display,
+        }
+    }
+
+    /// Number of frames the mock display has received
+    pub fn frames_displayed(&self) -> u64 {
+        self.display.lock().unwrap().frames_displayed
+    }
+
+    /// Copy of the last frame the mock display received
+    pub fn last_frame(&self) -> Option<Vec<u8>> {
+        self.display.lock().unwrap().last_frame.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::Color;
+    use crate::image_proc::DownloadError;
+
+    #[tokio::test]
+    async fn scheduled_refresh_displays_fixture() {
+        let harness = Harness::new("/red.png").await;
+
+        harness.scheduler.refresh_display().await;
+
+        assert_eq!(harness.frames_displayed(), 1);
+        let frame = harness.last_frame().unwrap();
+        assert_eq!(frame.len(), PanelModel::default().buffer_size());
+
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        assert!(frame.iter().all(|&b| b == red));
+    }
+
+    #[tokio::test]
+    async fn panel_is_initialized_again_every_few_refreshes() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.reinit_every = 2;
+
+        for _ in 0..5 {
+            harness.scheduler.refresh_display().await;
+        }
+
+        let state = harness.display.lock().unwrap();
+        assert_eq!(state.frames_displayed, 5);
+        // At startup, then before the 3rd and 5th refresh
+        assert_eq!(state.inits, 3);
+    }
+
+    #[tokio::test]
+    async fn portrait_panel_gets_full_size_frame() {
+        let harness = Harness::with_panel("/red.png", PanelModel::Epd4in0e).await;
+
+        harness.scheduler.refresh_display().await;
+
+        let frame = harness.last_frame().unwrap();
+        assert_eq!(frame.len(), 400 * 600 / 2);
+    }
+
+    #[tokio::test]
+    async fn three_color_panel_gets_black_and_red_planes() {
+        let panel = PanelModel::Epd4in2bV2;
+        let harness = Harness::with_panel("/red.png", panel).await;
+
+        harness.scheduler.refresh_display().await;
+
+        let frame = harness.last_frame().unwrap();
+        let allowed = [Color::Black as u8, Color::White as u8, Color::Red as u8];
+        assert!(
+            frame
+                .iter()
+                .all(|&b| allowed.contains(&(b >> 4)) && allowed.contains(&(b & 0x0F)))
+        );
+
+        let (black, red) =
+            crate::display::epd_bwr::to_planes(&frame, panel.width(), panel.height(), true);
+        assert_eq!(black.len(), 400 / 8 * 300);
+        // Center of the frame: red ink, no black ink
+        let center = 150 * 400 / 8 + 25;
+        assert_eq!(black[center], 0xFF);
+        assert_eq!(red[center], 0xFF);
+    }
+
+    #[tokio::test]
+    async fn greyscale_panel_gets_grey_levels() {
+        let harness = Harness::with_panel("/red.png", PanelModel::It8951Epd6in).await;
+
+        harness.scheduler.refresh_display().await;
+
+        let frame = harness.last_frame().unwrap();
+        assert_eq!(frame.len(), 800 * 600 / 2);
+        // Pure red has a luma of 76/255, between grey levels 4 and 5
+        let center = frame[300 * 400 + 200];
+        assert!([4, 5].contains(&(center >> 4)) && [4, 5].contains(&(center & 0x0F)));
+    }
+
+    #[cfg(feature = "renderers")]
+    #[tokio::test]
+    async fn layout_zones_dither_on_their_own() {
+        use crate::display::Color;
+        use crate::image_proc::dither::DitherMode;
+        use crate::sources::SourceConfig;
+        use crate::sources::layout::{LayoutSource, Zone};
+
+        let harness = Harness::new("/grey.png").await;
+        let url = harness.config.read().await.image_url.clone();
+        let zone = |x, dither| Zone {
+            x,
+            y: 0,
+            width: 400,
+            height: 240,
+            source: SourceConfig::Image { url: url.clone() },
+            border: false,
+            refresh_min: None,
+            dither: Some(dither),
+        };
+        harness.config.write().await.source = SourceConfig::Layout(LayoutSource {
+            zones: vec![
+                zone(0, DitherMode::None),
+                zone(400, DitherMode::FloydSteinberg),
+            ],
+        });
+        harness.scheduler.refresh_display().await;
+
+        // Grey is one flat panel color on the left, a pattern on the right
+        let frame = harness.last_frame().unwrap();
+        let zone_codes = |left: usize| {
+            let mut codes = std::collections::HashSet::new();
+            for row in frame.chunks_exact(400).take(240) {
+                for &b in &row[left / 2..left / 2 + 200] {
+                    codes.extend([b >> 4, b & 0x0F]);
+                }
+            }
+            codes
+        };
+        assert_eq!(zone_codes(0).len(), 1);
+        let dithered = zone_codes(400);
+        assert!(dithered.contains(&(Color::Black as u8)));
+        assert!(dithered.contains(&(Color::White as u8)));
+    }
+
+    #[tokio::test]
+    async fn packed_rotation_reorients_last_frame() {
+        let harness = Harness::new("/red.png").await;
+        {
+            let mut config = harness.config.write().await;
+            config.rotate_packed = true;
+            config.rotation = 180;
+        }
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // Turning by 90° needs a new render; mirroring reuses the frame
+        let mut config = harness.config.read().await.clone();
+        config.rotation = 90;
+        assert!(!harness.processor.reorient(&config).await.unwrap());
+        config.rotation = 0;
+        config.mirror_h = true;
+        assert!(harness.processor.reorient(&config).await.unwrap());
+        assert_eq!(harness.frames_displayed(), 2);
+
+        let red = Color::Red as u8;
+        let frame = harness.last_frame().unwrap();
+        assert_eq!(frame[240 * 400 + 200], (red << 4) | red);
+    }
+
+    #[tokio::test]
+    async fn rendered_preview_matches_the_panel() {
+        let harness = Harness::new("/red-dot.png").await;
+        let config = {
+            let mut config = harness.config.write().await;
+            config.rotate_packed = true;
+            config.rotation = 180;
+            config.clone()
+        };
+        harness.scheduler.refresh_display().await;
+
+        let bytes = reqwest::get(&config.image_url)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let img = image::load_from_memory(&bytes).unwrap();
+        let format = crate::display::PixelFormat::packed4(PanelModel::default().spec().palette);
+        let preview = crate::image_proc::render_preview(&config, img, format);
+
+        let panel = harness.processor.last_frame_png().unwrap();
+        assert_eq!(preview, image::load_from_memory(&panel).unwrap().to_rgb8());
+    }
+
+    #[tokio::test]
+    async fn small_change_gets_partial_refresh() {
+        let harness = Harness::new("/red.png").await;
+        harness.scheduler.refresh_display().await;
+
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("red", "red-dot");
+        harness.config.write().await.image_url = url;
+        harness.scheduler.refresh_display().await;
+
+        {
+            let state = harness.display.lock().unwrap();
+            assert_eq!((state.frames_displayed, state.partial_updates), (1, 1));
+            let region = state.last_region.unwrap();
+            assert_eq!((region.x, region.y), (0, 0));
+            assert!(region.width < 60 && region.height < 60);
+        }
+
+        // Above the threshold, the whole panel is refreshed
+        harness.config.write().await.auto_partial_max_percent = 0;
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("red-dot", "red");
+        harness.config.write().await.image_url = url;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+    }
+
+    #[tokio::test]
+    async fn similar_image_skips_scheduled_refresh() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.skip_similar_distance = Some(8);
+
+        harness.scheduler.refresh_display().await;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // A small detail changing is not worth a refresh
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("red.png", "red-dot.png");
+        harness.config.write().await.image_url = url;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // Same structure, different colors
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("red-dot.png", "white.png");
+        harness.config.write().await.image_url = url;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+
+        // Manual refreshes always reach the panel
+        let config = harness.config.read().await.clone();
+        harness
+            .processor
+            .process_and_display(&config)
+            .await
+            .unwrap();
+        assert_eq!(harness.frames_displayed(), 3);
+    }
+
+    #[tokio::test]
+    async fn status_reports_running_refresh() {
+        let harness = Harness::new("/slow.png").await;
+        assert!(!harness.status.snapshot().refreshing);
+        assert!(harness.status.snapshot().clock_synchronized);
+
+        let config = harness.config.read().await.clone();
+        let processor = Arc::clone(&harness.processor);
+        let refresh = tokio::spawn(async move { processor.process_and_display(&config).await });
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        let snapshot = harness.status.snapshot();
+        assert!(snapshot.refreshing);
+        assert_eq!(
+            snapshot.refresh_stage,
+            Some(crate::status::RefreshStage::Fetching)
+        );
+
+        refresh.await.unwrap().unwrap();
+        let snapshot = harness.status.snapshot();
+        assert!(!snapshot.refreshing);
+        assert_eq!(snapshot.refresh_stage, None);
+        assert_eq!(harness.frames_displayed(), 1);
+    }
+
+    #[tokio::test]
+    async fn network_return_triggers_refresh() {
+        let harness = Harness::new("/red.png").await;
+        let url = harness.config.read().await.image_url.clone();
+
+        assert!(!harness.scheduler.check_network().await);
+
+        harness.config.write().await.image_url = "http://host.invalid/red.png".to_string();
+        assert!(!harness.scheduler.check_network().await);
+
+        harness.config.write().await.image_url = url;
+        assert!(harness.scheduler.check_network().await);
+        assert!(!harness.scheduler.check_network().await);
+    }
+
+    #[tokio::test]
+    async fn startup_waits_for_network() {
+        let harness = Harness::new("/red.png").await;
+        let (_shutdown_tx, mut shutdown) = tokio::sync::broadcast::channel(1);
+        let url = harness.config.read().await.image_url.clone();
+
+        let started = std::time::Instant::now();
+        assert!(harness.scheduler.start_up(&mut shutdown).await);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        // Gives up after the timeout, then refreshes once the network is back
+        {
+            let mut config = harness.config.write().await;
+            config.image_url = "http://host.invalid/red.png".to_string();
+            config.startup.network_timeout_secs = 1;
+        }
+        let started = std::time::Instant::now();
+        assert!(harness.scheduler.start_up(&mut shutdown).await);
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+
+        harness.config.write().await.image_url = url;
+        assert!(harness.scheduler.check_network().await);
+    }
+
+    #[tokio::test]
+    async fn refresh_on_start_can_be_skipped() {
+        let harness = Harness::new("/red.png").await;
+        let run = || async {
+            let (_shutdown_tx, shutdown) = tokio::sync::broadcast::channel(1);
+            let _ = tokio::time::timeout(
+                std::time::Duration::from_millis(500),
+                harness.scheduler.run(shutdown),
+            )
+            .await;
+        };
+
+        harness.config.write().await.startup.refresh_on_start = false;
+        run().await;
+        assert_eq!(harness.frames_displayed(), 0);
+
+        harness.config.write().await.startup.refresh_on_start = true;
+        run().await;
+        assert_eq!(harness.frames_displayed(), 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_budget_skips_scheduled_refreshes() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.max_refreshes_per_day = 2;
+
+        for _ in 0..3 {
+            harness.scheduler.refresh_display().await;
+        }
+
+        assert_eq!(harness.frames_displayed(), 2);
+    }
+
+    #[tokio::test]
+    async fn every_full_refresh_counts_against_the_budget() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.max_refreshes_per_day = 2;
+        let config = harness.config.read().await.clone();
+
+        // Manual paths run past the budget, but are counted
+        harness.scheduler.refresh_display().await;
+        harness.processor.show_test_pattern(&config).await.unwrap();
+        harness.processor.clear_display(&config).await.unwrap();
+        assert_eq!(harness.status.refreshes_today(), 3);
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 3);
+        assert_eq!(harness.status.refreshes_today(), 3);
+    }
+
+    #[tokio::test]
+    async fn missing_image_leaves_display_untouched() {
+        let harness = Harness::new("/missing.png").await;
+
+        harness.scheduler.refresh_display().await;
+
+        assert_eq!(harness.frames_displayed(), 0);
+        let error = harness.status.snapshot().last_error.unwrap();
+        assert_eq!(error.stage, crate::status::RefreshStage::Fetching);
+        assert!(error.message.contains("404"), "{}", error.message);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn metrics_count_errors_by_category() {
+        let harness = Harness::new("/missing.png").await;
+        harness.scheduler.refresh_display().await;
+        harness.config.write().await.image_url = "http://host.invalid/red.png".to_string();
+        harness.scheduler.refresh_display().await;
+
+        let metrics = crate::web::metrics::render(&harness.status);
+        assert!(metrics.contains("epaper_errors_total{category=\"http_4xx\"} 1\n"));
+        assert!(metrics.contains("epaper_errors_total{category=\"dns\"} 1\n"));
+        assert!(metrics.contains("epaper_errors_total{category=\"spi\"} 0\n"));
+        assert!(metrics.contains("# TYPE epaper_errors_total counter\n"));
+
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+        let response = reqwest::get(format!("{}/metrics", base)).await.unwrap();
+        assert!(response.status().is_success());
+        assert!(
+            response.headers()[reqwest::header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain")
+        );
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn device_name_identifies_the_display() {
+        let config = Config {
+            device_name: "Kitchen \"north\"".to_string(),
+            ..Config::default()
+        };
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+        let get = |path: &'static str| {
+            let base = base.clone();
+            async move {
+                reqwest::get(format!("{}{}", base, path))
+                    .await
+                    .unwrap()
+                    .text()
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let status: serde_json::Value = serde_json::from_str(&get("/api/v1/status").await).unwrap();
+        assert_eq!(status["device_name"], "Kitchen \"north\"");
+        assert_eq!(status["hostname"], crate::network::hostname());
+        assert!(
+            get("/status")
+                .await
+                .contains("<h2>Kitchen &quot;north&quot;</h2>")
+        );
+        assert!(
+            get("/")
+                .await
+                .contains("<title>Kitchen &quot;north&quot; – ePaper Display</title>")
+        );
+        #[cfg(feature = "metrics")]
+        assert!(
+            get("/metrics")
+                .await
+                .contains(r#"device="Kitchen \"north\""}"#)
+        );
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn follower_shows_frames_pushed_by_the_leader() {
+        use crate::mirror;
+
+        let leader = Harness::new("/red.png").await;
+        leader.scheduler.refresh_display().await;
+        let frame = leader.last_frame().unwrap();
+
+        let (base, display, handle) = serve_follower("group secret").await;
+        let url = format!("{}{}", base, mirror::FRAME_PATH);
+        let model = crate::display::mock::spec(PanelModel::default()).model;
+
+        let (width, height) = (
+            PanelModel::default().width(),
+            PanelModel::default().height(),
+        );
+        let push_sized = |token: &str, (width, height): (u32, u32), body: Vec<u8>| {
+            reqwest::Client::new()
+                .post(&url)
+                .bearer_auth(token)
+                .header(mirror::HEADER_WIDTH, width)
+                .header(mirror::HEADER_HEIGHT, height)
+                .header(mirror::HEADER_MODEL, model)
+                .header(
+                    mirror::HEADER_SHOW_AT,
+                    chrono::Local::now().timestamp_millis(),
+                )
+                .body(body)
+                .send()
+        };
+        let push = |token: &str, body: Vec<u8>| push_sized(token, (width, height), body);
+
+        let wrong_token = push("guess", frame.clone()).await.unwrap();
+        assert_eq!(wrong_token.status(), reqwest::StatusCode::FORBIDDEN);
+        let truncated = push("group secret", frame[1..].to_vec()).await.unwrap();
+        assert_eq!(truncated.status(), reqwest::StatusCode::BAD_REQUEST);
+        // Same number of pixels, but not the panel's size
+        let transposed = push_sized("group secret", (height, width), frame.clone())
+            .await
+            .unwrap();
+        assert_eq!(transposed.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(display.lock().unwrap().frames_displayed, 0);
+
+        let accepted = push("group secret", frame.clone()).await.unwrap();
+        assert_eq!(accepted.status(), reqwest::StatusCode::ACCEPTED);
+        for _ in 0..50 {
+            if display.lock().unwrap().frames_displayed > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(display.lock().unwrap().last_frame.as_ref(), Some(&frame));
+        handle.abort();
+
+        // Displays outside a group do not take frames
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+        let response = reqwest::Client::new()
+            .post(format!("{}{}", base, mirror::FRAME_PATH))
+            .bearer_auth("group secret")
+            .body(frame)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn interrupted_frame_upload_resumes() {
+        use crate::image_proc::download::sha256_hex;
+        use crate::mirror::{self, CHUNK_SIZE, MirrorConfig, MirrorRole, UploadState};
+
+        let leader = Harness::new("/red.png").await;
+        leader.scheduler.refresh_display().await;
+        let frame = leader.last_frame().unwrap();
+        let sha256 = sha256_hex(&frame);
+
+        let (base, display, handle) = serve_follower("group secret").await;
+        let chunks = format!("{}{}/{}", base, mirror::CHUNKS_PATH, sha256);
+        let put_chunk = |index: usize, checksum: String| {
+            let chunk = frame[index * CHUNK_SIZE..(index + 1) * CHUNK_SIZE].to_vec();
+            reqwest::Client::new()
+                .put(format!("{}/{}", chunks, index))
+                .bearer_auth("group secret")
+                .header(mirror::HEADER_FRAME_SIZE, frame.len())
+                .header(mirror::HEADER_CHUNK_SHA256, checksum)
+                .body(chunk)
+                .send()
+        };
+
+        // Corrupted on the way: refused
+        let corrupted = put_chunk(1, sha256_hex(b"something else")).await.unwrap();
+        assert_eq!(corrupted.status(), reqwest::StatusCode::BAD_REQUEST);
+        // The link drops after two chunks
+        for index in [0, 2] {
+            let checksum = sha256_hex(&frame[index * CHUNK_SIZE..(index + 1) * CHUNK_SIZE]);
+            let stored = put_chunk(index, checksum).await.unwrap();
+            assert_eq!(stored.status(), reqwest::StatusCode::NO_CONTENT);
+        }
+        let state: UploadState = serde_json::from_str(
+            &reqwest::Client::new()
+                .get(&chunks)
+                .bearer_auth("group secret")
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(state.size, frame.len());
+        assert_eq!(state.received, [0, 2]);
+
+        // The leader's next push sends the rest and starts the refresh
+        let config = MirrorConfig {
+            role: MirrorRole::Leader,
+            followers: vec![base],
+            token: "group secret".to_string(),
+        };
+        let model = crate::display::mock::spec(PanelModel::default()).model;
+        let (width, height) = (
+            PanelModel::default().width(),
+            PanelModel::default().height(),
+        );
+        let user_agent = Config::default().user_agent();
+        let show_at = mirror::push(&config, &user_agent, model, width, height, &frame).await;
+        assert!(show_at > chrono::Local::now());
+        assert_eq!(display.lock().unwrap().frames_displayed, 0);
+
+        tokio::time::sleep(mirror::wait_until(show_at.timestamp_millis())).await;
+        for _ in 0..50 {
+            if display.lock().unwrap().frames_displayed > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(display.lock().unwrap().last_frame.as_ref(), Some(&frame));
+        handle.abort();
+    }
+
+    /// Serve the web UI of a mirror group follower on a loopback port
+    async fn serve_follower(token: &str) -> (String, MockHandle, JoinHandle<()>) {
+        use crate::mirror::{MirrorConfig, MirrorRole};
+
+        let config = Config {
+            mirror: MirrorConfig {
+                role: MirrorRole::Follower,
+                token: token.to_string(),
+                ..MirrorConfig::default()
+            },
+            ..Config::default()
+        };
+        let (controller, display) = DisplayController::mock(config.panel);
+        let server = crate::web::WebServer::new(
+            config,
+            controller,
+            String::new(),
+            Arc::new(StatusTracker::default()),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = server.build_router();
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        (base, display, handle)
+    }
+
+    #[tokio::test]
+    async fn undecodable_image_leaves_display_untouched() {
+        let harness = Harness::new("/not-an-image").await;
+
+        harness.scheduler.refresh_display().await;
+
+        assert_eq!(harness.frames_displayed(), 0);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_leaves_display_untouched() {
+        let harness = Harness::new("/red.png").await;
+        let red_url = harness.config.read().await.image_url.clone();
+
+        let white = fixture_png(400, 240, [255, 255, 255]);
+        harness.config.write().await.image_sha256 = sha256_hex(&white);
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 0);
+
+        // Sidecar describing a different image
+        {
+            let mut config = harness.config.write().await;
+            config.image_sha256.clear();
+            config.image_sha256_url = format!("{}.sha256", red_url);
+            config.image_url = red_url.replace("/red.png", "/white.png");
+        }
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 0);
+
+        harness.config.write().await.image_url = red_url;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+    }
+
+    #[tokio::test]
+    async fn bundle_manifest_controls_presentation() {
+        use crate::sources::{SourceConfig, bundle::BundleSource};
+
+        let harness = Harness::new("/bundle.zip").await;
+        let url = harness.config.read().await.image_url.clone();
+        harness.config.write().await.source = SourceConfig::Bundle(BundleSource { url });
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        assert_eq!(
+            harness.processor.presentation().dwell,
+            Some(std::time::Duration::from_secs(900))
+        );
+        // Red image above the caption bar
+        let frame = harness.last_frame().unwrap();
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        let row = PanelModel::default().width() as usize / 2;
+        assert_eq!(frame[row / 2], red);
+        assert_ne!(frame[frame.len() - row / 2], red);
+
+        // Expired content is not shown
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("/bundle.zip", "/expired.tar.gz");
+        harness.config.write().await.source = SourceConfig::Bundle(BundleSource { url });
+        let error = crate::sources::fetch_image(&*harness.config.read().await)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().starts_with("Bundle content expired at "),
+            "{}",
+            error
+        );
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+    }
+
+    #[tokio::test]
+    async fn playlist_shows_the_current_slide() {
+        use crate::sources::{SourceConfig, playlist::PlaylistSource};
+
+        let harness = Harness::new("/playlist.json").await;
+        let url = harness.config.read().await.image_url.clone();
+        harness.config.write().await.source = SourceConfig::Playlist(PlaylistSource { url });
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        let frame = harness.last_frame().unwrap();
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        assert_eq!(frame[frame.len() / 2], red);
+
+        // The next slide is due when the slide's turn ends
+        let presentation = harness.processor.presentation();
+        assert_eq!(
+            presentation.dwell,
+            Some(std::time::Duration::from_secs(1200))
+        );
+        let left = presentation.next_slide.unwrap() - chrono::Local::now();
+        assert!(left > chrono::TimeDelta::zero() && left <= chrono::TimeDelta::minutes(20));
+    }
+
+    #[tokio::test]
+    async fn slide_dwell_is_independent_of_refresh_interval() {
+        use crate::sources::{SourceConfig, bundle::BundleSource};
+        use std::time::Duration;
+
+        let harness = Harness::new("/bundle.zip").await;
+        let url = harness.config.read().await.image_url.clone();
+        {
+            let mut config = harness.config.write().await;
+            config.source = SourceConfig::Bundle(BundleSource { url });
+            config.allow_fast_refresh = true;
+        }
+        harness.scheduler.refresh_display().await;
+        let next_slide = harness.processor.presentation().next_slide.unwrap();
+
+        // Refreshing the same slide keeps its dwell timer running
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+        assert_eq!(
+            harness.processor.presentation().next_slide,
+            Some(next_slide)
+        );
+
+        // The schedule refreshes the slide meanwhile, and the slide
+        // advances before a longer interval ends
+        let config = harness.config.read().await.clone();
+        let short = Duration::from_secs(5 * 60);
+        assert_eq!(harness.scheduler.content_interval(&config, short), short);
+        let long = harness
+            .scheduler
+            .content_interval(&config, Duration::from_secs(60 * 60));
+        assert!(long <= Duration::from_secs(900) && long > Duration::from_secs(890));
+        // An interval ending just before the next slide waits for it
+        let close = harness
+            .scheduler
+            .content_interval(&config, Duration::from_secs(890));
+        assert!(close > Duration::from_secs(890));
+    }
+
+    #[tokio::test]
+    async fn expired_content_gives_way_to_the_fallback_image() {
+        use crate::sources::{SourceConfig, bundle::BundleSource};
+
+        let harness = Harness::new("/expiring.png").await;
+        let fallback =
+            std::env::temp_dir().join(format!("epaper-fallback-{}.png", std::process::id()));
+        std::fs::write(&fallback, fixture_png(400, 240, [0, 0, 0])).unwrap();
+        harness.config.write().await.fallback_image = fallback.display().to_string();
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        assert!(harness.processor.presentation().valid_until.is_some());
+
+        // Still current: a failed refresh leaves it on the panel
+        let expired = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("/expiring.png", "/expired.tar.gz");
+        harness.config.write().await.source = SourceConfig::Bundle(BundleSource { url: expired });
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // Expired, and the replacement has expired too
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let error = harness
+            .processor
+            .display_if_changed(&*harness.config.read().await)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("Bundle content expired at "),
+            "{}",
+            error
+        );
+        assert_eq!(harness.frames_displayed(), 2);
+        let frame = harness.last_frame().unwrap();
+        let black = (Color::Black as u8) << 4 | Color::Black as u8;
+        assert!(frame.iter().all(|&pair| pair == black));
+
+        // The fallback stays up without being drawn again
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+        std::fs::remove_file(fallback).unwrap();
+    }
+
+    #[tokio::test]
+    async fn photo_caption_comes_from_exif() {
+        use crate::image_proc::download::{DownloadConfig, download_image_with_config};
+        use crate::image_proc::photo::PhotoCaption;
+
+        let harness = Harness::new("/photo.jpg").await;
+        let url = harness.config.read().await.image_url.clone();
+        let config = DownloadConfig {
+            metadata: true,
+            ..DownloadConfig::default()
+        };
+        let info = download_image_with_config(&url, &config)
+            .await
+            .unwrap()
+            .info;
+        assert_eq!(info.description.as_deref(), Some("Harbour at dusk"));
+        assert_eq!(
+            PhotoCaption::Both.text(&info).as_deref(),
+            Some("Harbour at dusk · 14 July 2023")
+        );
+
+        // Full refreshes, so the mock keeps whole frames
+        harness.config.write().await.auto_partial_max_percent = 0;
+        harness.scheduler.refresh_display().await;
+        let plain = harness.last_frame().unwrap();
+        harness.config.write().await.photo_caption = PhotoCaption::Both;
+        harness.scheduler.refresh_display().await;
+        let captioned = harness.last_frame().unwrap();
+        assert_eq!(harness.frames_displayed(), 2);
+
+        // The caption sits in the bottom corner of the photo
+        let changed = plain.iter().zip(&captioned).position(|(a, b)| a != b);
+        assert!(
+            changed.is_some_and(|at| at > plain.len() / 2),
+            "{:?}",
+            changed
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_image_is_refused_before_decoding() {
+        use crate::image_proc::download::{DownloadConfig, download_bytes};
+
+        let harness = Harness::new("/huge.png").await;
+        harness.config.write().await.max_image_memory_mb = 16;
+
+        let error = crate::sources::fetch_image(&*harness.config.read().await)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error,
+                crate::sources::SourceError::Download(DownloadError::TooLarge {
+                    required_mb: 2289,
+                    limit_mb: 16
+                })
+            ),
+            "{}",
+            error
+        );
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 0);
+
+        // Bodies without a Content-Length stop at the limit too
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("/huge.png", "/endless.png");
+        let config = DownloadConfig {
+            max_memory: Some(1024 * 1024),
+            ..Default::default()
+        };
+        let error = download_bytes(&url, &config).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                DownloadError::TooLarge {
+                    required_mb: 2,
+                    limit_mb: 1
+                }
+            ),
+            "{}",
+            error
+        );
+
+        // Small images pass
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("/huge.png", "/red.png");
+        harness.config.write().await.image_url = url;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+    }
+
+    #[tokio::test]
+    async fn truncated_image_is_shown_with_warning() {
+        let harness = Harness::new("/truncated.png").await;
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // The red top half decoded; the missing bottom gets a black bar
+        let frame = harness.last_frame().unwrap();
+        assert_eq!(frame[0] >> 4, Color::Red as u8);
+        assert_eq!(frame[479 * 400] >> 4, Color::Black as u8);
+    }
+
+    #[tokio::test]
+    async fn html_page_is_reported_as_such() {
+        let harness = Harness::new("/login").await;
+
+        let error = crate::sources::fetch_image(&*harness.config.read().await)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                crate::sources::SourceError::Download(DownloadError::HtmlPage { title: Some(t) })
+                    if t == "Sign in"
+            ),
+            "{}",
+            error
+        );
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 0);
+        let health = harness.status.snapshot().source_health.unwrap();
+        assert!(health.error.unwrap().contains("HTML page"));
+
+        // Optionally shown on the panel, still reported as an error
+        harness.config.write().await.show_html_errors = true;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        assert!(!harness.status.snapshot().source_health.unwrap().healthy);
+    }
+
+    #[tokio::test]
+    async fn login_cookie_is_kept_across_refreshes() {
+        let harness = Harness::new("/private/red.png").await;
+        let cookie_file =
+            std::env::temp_dir().join(format!("epaper-cookies-{}.json", std::process::id()));
+        {
+            let mut config = harness.config.write().await;
+            let login_url = config.image_url.replace("/red.png", "/login");
+            config.session.login_url = login_url;
+            config.session.cookie_file = cookie_file.display().to_string();
+            config.session.login_form = [("user", "pi"), ("password", "pi")]
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .into();
+        }
+
+        // Redirected to the login page, logs in, then gets the image
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        let saved = std::fs::read_to_string(&cookie_file).unwrap();
+        assert!(saved.contains("s3cret"));
+
+        // The next refresh reuses the cookie; a wrong password would fail
+        harness.config.write().await.session.login_form.clear();
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+        std::fs::remove_file(&cookie_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn configured_user_agent_is_sent() {
+        let harness = Harness::new("/picky.png").await;
+        harness.config.write().await.user_agent = "EpaperTest/1.0".to_string();
+
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        // API sources send it too
+        #[cfg(feature = "renderers")]
+        {
+            use crate::image_proc::download::fetch_json;
+
+            let config = harness.config.read().await.clone();
+            let url = config.image_url.replace("/picky.png", "/picky.json");
+            let json: serde_json::Value =
+                fetch_json(&url, &HeaderMap::new(), &config.download_config())
+                    .await
+                    .unwrap();
+            assert_eq!(json["ok"], true);
+        }
+    }
+
+    #[tokio::test]
+    async fn home_assistant_snapshot_is_fetched_with_the_token() {
+        use crate::sources::SourceConfig;
+        use crate::sources::home_assistant::HomeAssistantSource;
+
+        let harness = Harness::new("/red.png").await;
+        let base_url = harness.config.read().await.image_url.replace("/red.png", "");
+        let camera = |token: &str| {
+            SourceConfig::HomeAssistant(HomeAssistantSource {
+                base_url: base_url.clone(),
+                token: token.to_string(),
+                entity_id: "camera.door".to_string(),
+            })
+        };
+
+        harness.config.write().await.source = camera("wrong-token");
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 0);
+        let error = harness.status.snapshot().last_error.unwrap();
+        assert!(error.message.contains("401"), "{}", error.message);
+
+        harness.config.write().await.source = camera("ha-token");
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == red));
+    }
+
+    #[tokio::test]
+    async fn power_save_sets_the_rtc_alarm_before_powering_off() {
+        use std::time::Duration;
+
+        let dir = std::env::temp_dir().join(format!("epaper-rtc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let alarm = dir.join("wakealarm");
+        let marker = dir.join("powered-off");
+
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.power_save = crate::power_save::PowerSaveConfig {
+            enabled: true,
+            rtc_path: dir.to_string_lossy().into_owned(),
+            boot_secs: 60,
+            min_off_min: 15,
+            power_off_command: vec!["touch".to_string(), marker.to_string_lossy().into_owned()],
+        };
+
+        // Too short to power off for
+        harness
+            .scheduler
+            .power_off(Duration::from_secs(10 * 60))
+            .await;
+        assert!(!alarm.exists());
+        assert!(!marker.exists());
+
+        let now = chrono::Local::now().timestamp();
+        harness
+            .scheduler
+            .power_off(Duration::from_secs(60 * 60))
+            .await;
+        let wake: i64 = std::fs::read_to_string(&alarm).unwrap().parse().unwrap();
+        assert!((now + 3600 - 60..=now + 3600 - 59).contains(&wake));
+        assert!(marker.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "renderers")]
+    #[tokio::test]
+    async fn clock_partial_refresh_updates_time_region() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.source =
+            crate::sources::SourceConfig::Clock(crate::sources::clock::ClockSource {
+                style: crate::sources::clock::ClockStyle::Digital,
+                hour24: true,
+                show_date: true,
+            });
+
+        harness.scheduler.refresh_display().await;
+        harness.scheduler.refresh_partial().await;
+
+        {
+            let state = harness.display.lock().unwrap();
+            assert_eq!(state.frames_displayed, 1);
+            assert_eq!(state.partial_updates, 1);
+            let region = state.last_region.unwrap();
+            assert_eq!(region.x % 2, 0);
+            assert_eq!(region.width % 2, 0);
+            assert!(region.height < PanelModel::default().height());
+        }
+
+        // A cleared panel stays blank until the regular schedule redraws it
+        harness.processor.clear_display(&Config::default()).await.unwrap();
+        harness.scheduler.refresh_partial().await;
+        let state = harness.display.lock().unwrap();
+        // The mock counts the clear as a frame too
+        assert_eq!(state.frames_displayed, 2);
+        assert_eq!(state.partial_updates, 1);
+    }
+
+    #[tokio::test]
+    async fn debug_bundle_redacts_secrets() {
+        let harness = Harness::new("/red.png").await;
+        harness.scheduler.refresh_display().await;
+        harness.config.write().await.source = crate::sources::SourceConfig::HomeAssistant(
+            crate::sources::home_assistant::HomeAssistantSource {
+                base_url: "http://ha.local:8123".to_string(),
+                token: "very-secret-token".to_string(),
+                entity_id: "camera.door".to_string(),
+            },
+        );
+        harness.config.write().await.session = crate::image_proc::session::SessionConfig {
+            login_url: "https://sso.local/login".to_string(),
+            login_form: [("user", "grandma"), ("pwd", "hunter2")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        let state = crate::web::routes::AppState {
+            config: Arc::clone(&harness.config),
+            processor: Arc::clone(&harness.processor),
+            config_path: String::new(),
+            status: Arc::new(StatusTracker::default()),
+            arbiter: Arc::default(),
+            uploads: Arc::default(),
+            outputs: Arc::default(),
+            debug_epd: false,
+        };
+        let zip = crate::web::bundle::build(&state).await;
+        let contains = |needle: &[u8]| zip.windows(needle.len()).any(|w| w == needle);
+
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        for name in ["config.json", "status.json", "logs.txt", "frame.png"] {
+            assert!(contains(name.as_bytes()), "missing {}", name);
+        }
+        assert!(contains(b"<redacted>"));
+        assert!(!contains(b"very-secret-token"));
+        assert!(!contains(b"hunter2"));
+        assert!(!contains(b"grandma"));
+    }
+
+    #[tokio::test]
+    async fn raw_panel_commands_need_the_debug_flag() {
+        use crate::web::api::{EpdCommandRequest, epd_command};
+        use crate::web::error::ApiError;
+        use axum::{Json, extract::State};
+
+        let harness = Harness::new("/red.png").await;
+        let mut state = crate::web::routes::AppState {
+            config: Arc::clone(&harness.config),
+            processor: Arc::clone(&harness.processor),
+            config_path: String::new(),
+            status: Arc::new(StatusTracker::default()),
+            arbiter: Arc::default(),
+            uploads: Arc::default(),
+            outputs: Arc::default(),
+            debug_epd: false,
+        };
+        let request = |command: &str, data: &str| {
+            Ok(Json(EpdCommandRequest {
+                command: command.to_string(),
+                data: data.to_string(),
+            }))
+        };
+
+        let refused = epd_command(State(state.clone()), request("0x12", "00")).await;
+        assert!(matches!(refused, Err(ApiError::Forbidden(_))));
+
+        state.debug_epd = true;
+        let invalid = epd_command(State(state.clone()), request("0x12", "0x0")).await;
+        assert!(matches!(invalid, Err(ApiError::BadRequest(_))));
+        let Json(sent) = epd_command(State(state), request("0x61", "0x03 0x20, 01E0"))
+            .await
+            .unwrap();
+        assert_eq!(sent.data_bytes, 4);
+        assert_eq!(
+            harness.display.lock().unwrap().raw_commands,
+            vec![(0x61, vec![0x03, 0x20, 0x01, 0xE0])]
+        );
+    }
+
+    #[tokio::test]
+    async fn image_url_secret_reference_is_resolved_and_masked() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let harness = Harness::new("/red.png").await;
+        let url = harness.config.read().await.image_url.clone();
+
+        let path = std::env::temp_dir().join(format!("epaper-secret-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", url)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        harness.config.write().await.image_url = format!("${{FILE:{}}}", path.display());
+
+        harness.scheduler.refresh_display().await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(harness.frames_displayed(), 1);
+        assert_eq!(crate::secrets::redact(&url), crate::secrets::REDACTED);
+    }
+
+    /// Serve the web UI on a loopback port
+    async fn serve_web(
+        config: Config,
+        config_path: String,
+    ) -> (String, crate::web::WebServer, JoinHandle<()>) {
+        let (controller, _display) = DisplayController::mock(config.panel);
+        let server = crate::web::WebServer::new(
+            config,
+            controller,
+            config_path,
+            Arc::new(StatusTracker::default()),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = server.build_router();
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+        (base, server, handle)
+    }
+
+    #[tokio::test]
+    async fn logs_are_streamed_as_server_sent_events() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        // Spawned tasks run on this thread and log to this subscriber
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(crate::logs::BufferLayer),
+        );
+        tracing::warn!("before following");
+
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+        let lines: serde_json::Value = serde_json::from_str(
+            &reqwest::get(format!("{}/api/v1/logs", base))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(
+            lines
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|line| line["message"] == "before following")
+        );
+
+        let mut response = reqwest::get(format!("{}/api/v1/logs?follow=true", base))
+            .await
+            .unwrap();
+        assert!(
+            response.headers()[reqwest::header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/event-stream")
+        );
+        tracing::warn!("while following");
+
+        let mut received = String::new();
+        let read = async {
+            while !received.contains("while following") {
+                let chunk = response.chunk().await.unwrap().unwrap();
+                received.push_str(&String::from_utf8_lossy(&chunk));
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), read)
+            .await
+            .unwrap();
+        assert!(received.contains("event: log\n"));
+        assert!(received.find("before following") < received.find("while following"));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn log_page_filters_by_level() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry().with(crate::logs::BufferLayer),
+        );
+        tracing::warn!("log page <warning>");
+        tracing::info!("log page notice");
+
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+        let page = |query: &'static str| {
+            let base = base.clone();
+            async move {
+                reqwest::get(format!("{}/logs{}", base, query))
+                    .await
+                    .unwrap()
+                    .text()
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let all = page("").await;
+        assert!(all.contains("log page &lt;warning&gt;"));
+        assert!(all.contains("log page notice"));
+
+        let warnings = page("?level=warn&lines=500").await;
+        assert!(warnings.contains("log page &lt;warning&gt;"));
+        assert!(!warnings.contains("log page notice"));
+        assert!(warnings.contains(r#"<option value="warn" selected>"#));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn log_filter_can_be_changed_at_runtime() {
+        use tracing_subscriber::{Layer, layer::SubscriberExt};
+
+        let _subscriber = tracing::subscriber::set_default(
+            tracing_subscriber::registry()
+                .with(crate::logs::BufferLayer.with_filter(crate::logs::runtime_filter("warn"))),
+        );
+        let recorded = |message: &str| {
+            crate::logs::recent()
+                .iter()
+                .any(|line| line.message == message)
+        };
+
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+        let client = reqwest::Client::new();
+        let set_filter = |directives: &str| {
+            client
+                .put(format!("{}/api/v1/logs/filter", base))
+                .header("Content-Type", "application/json")
+                .body(serde_json::json!({ "directives": directives }).to_string())
+                .send()
+        };
+
+        tracing::debug!("filter test: before");
+        assert!(!recorded("filter test: before"));
+
+        let response = set_filter(" testing=debug ").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let filter: serde_json::Value =
+            serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(filter["directives"], "testing=debug");
+        tracing::debug!("filter test: raised");
+        assert!(recorded("filter test: raised"));
+
+        let response = set_filter("testing=loud").await.unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(crate::logs::directives(), "testing=debug");
+
+        assert_eq!(set_filter("").await.unwrap().status(), 200);
+        tracing::debug!("filter test: reset");
+        assert!(!recorded("filter test: reset"));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn schedule_simulation_follows_plan_periods() {
+        use crate::config::{SchedulePeriod, SchedulePlan};
+
+        let config = Config {
+            schedule_plans: vec![SchedulePlan::new(
+                "Office",
+                vec![
+                    SchedulePeriod::new("06:00", "22:00", 60),
+                    SchedulePeriod::new("22:00", "06:00", 240),
+                ],
+            )],
+            day_assignments: crate::config::Weekday::all()
+                .iter()
+                .map(|day| (*day, "Office".to_string()))
+                .collect(),
+            ..Config::default()
+        };
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+
+        let simulate = |query: &'static str| {
+            let url = format!("{}/api/v1/schedule/simulate?{}", base, query);
+            async move { reqwest::get(url).await.unwrap() }
+        };
+        let response = simulate("from=2026-03-02T20:00&to=2026-03-03T08:00").await;
+        let body: serde_json::Value =
+            serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["refresh_count"], 7);
+        assert_eq!(body["refreshes"][2]["interval_min"], 240);
+
+        let response = simulate("from=2026-03-02&to=2026-03-01").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Same replay as a calendar feed
+        let response = reqwest::get(format!(
+            "{}/api/v1/schedule.ics?from=2026-03-02T20:00&to=2026-03-03T08:00",
+            base
+        ))
+        .await
+        .unwrap();
+        assert!(
+            response.headers()[reqwest::header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/calendar")
+        );
+        let ics = response.text().await.unwrap();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 7);
+        assert_eq!(ics.matches("SUMMARY:Refresh (Office)").count(), 7);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn alternate_weeks_switch_plans_every_other_week() {
+        use crate::config::{AlternateWeeks, SchedulePreset, Weekday};
+
+        let mut config = Config::default();
+        config
+            .schedule_plans
+            .push(SchedulePreset::PhotoFrame.plan("Visit"));
+        config.alternate_weeks = Some(AlternateWeeks {
+            b_weeks: "2026-03-04".to_string(),
+            day_assignments: [(Weekday::Saturday, "Visit".to_string())].into(),
+        });
+        config.validate().unwrap();
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+
+        let plan_on = |day: &'static str| {
+            let url = format!(
+                "{}/api/v1/schedule/simulate?from={}T12:00&to={}T12:00",
+                base, day, day
+            );
+            async move {
+                let body = reqwest::get(url).await.unwrap().text().await.unwrap();
+                let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+                body["refreshes"][0]["plan"].as_str().unwrap().to_string()
+            }
+        };
+        assert_eq!(plan_on("2026-03-07").await, "Visit");
+        assert_eq!(plan_on("2026-03-06").await, "Default");
+        assert_eq!(plan_on("2026-03-14").await, "Default");
+        assert_eq!(plan_on("2026-03-21").await, "Visit");
+        assert_eq!(plan_on("2026-02-21").await, "Visit");
+
+        let week_b = reqwest::get(format!("{}/api/v1/schedule/assignments?week=b", base))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(week_b, r#"{"saturday":"Visit"}"#);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn vacation_mode_swaps_plan_and_image() {
+        use crate::config::SchedulePreset;
+
+        let harness = Harness::new("/red.png").await;
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        let white = (Color::White as u8) << 4 | Color::White as u8;
+        {
+            let mut config = harness.config.write().await;
+            config
+                .schedule_plans
+                .push(SchedulePreset::BatterySaver.plan("Away"));
+            config.vacation.plan = "Away".to_string();
+            config.vacation.image_url = config.image_url.replace("/red.png", "/white.png");
+            config.vacation.enabled = true;
+            config.validate().unwrap();
+            assert_eq!(config.get_current_plan().unwrap().name, "Away");
+        }
+        harness.scheduler.refresh_display().await;
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == white));
+
+        harness.config.write().await.vacation.enabled = false;
+        harness.scheduler.refresh_display().await;
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == red));
+        assert_eq!(
+            harness.config.read().await.get_current_plan().unwrap().name,
+            "Default"
+        );
+
+        // Switched through the API, keeping the day assignments
+        let path =
+            std::env::temp_dir().join(format!("epaper-vacation-{}.json", std::process::id()));
+        let mut config = Config::default();
+        config
+            .schedule_plans
+            .push(SchedulePreset::BatterySaver.plan("Away"));
+        let (base, _server, handle) = serve_web(config, path.display().to_string()).await;
+        let client = reqwest::Client::new();
+        let switch = |body: &'static str| {
+            client
+                .put(format!("{}/api/v1/vacation", base))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+        };
+
+        let response = switch(r#"{"enabled": true, "plan": "Away"}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = reqwest::get(format!("{}/api/v1/vacation", base))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"enabled":true,"plan":"Away","image":false}"#);
+        let saved = Config::load(&path).unwrap();
+        assert!(saved.vacation.enabled);
+        assert!(saved.day_assignments.values().all(|plan| plan == "Default"));
+
+        let response = switch(r#"{"enabled": true, "plan": "Beach"}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        handle.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn plans_are_added_from_presets_and_copies() {
+        let path = std::env::temp_dir().join(format!("epaper-plans-{}.json", std::process::id()));
+        let (base, server, handle) = serve_web(Config::default(), path.display().to_string()).await;
+        let client = reqwest::Client::new();
+        let add = |body: &'static str| {
+            client
+                .post(format!("{}/api/v1/schedule/plans", base))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+        };
+
+        let response = add(r#"{"preset": "office_hours"}"#).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let plan: serde_json::Value =
+            serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(plan["name"], "Office Hours");
+        assert_eq!(plan["periods"][0]["interval_min"], 15);
+
+        add(r#"{"copy_of": "Office Hours"}"#).await.unwrap();
+        add(r#"{"copy_of": "Office Hours"}"#).await.unwrap();
+        let names: Vec<String> = server
+            .config()
+            .read()
+            .await
+            .schedule_plans
+            .iter()
+            .map(|plan| plan.name.clone())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Default",
+                "Office Hours",
+                "Office Hours copy",
+                "Office Hours copy 2"
+            ]
+        );
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.schedule_plans.len(), 4);
+
+        let response = add(r#"{"copy_of": "Weekend"}"#).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = add(r#"{"name": "Default", "preset": "photo_frame"}"#)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        handle.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn day_assignments_are_changed_in_bulk() {
+        use crate::config::{SchedulePreset, Weekday};
+        use crate::web::auth::{Account, Role};
+
+        let path = std::env::temp_dir().join(format!("epaper-days-{}.json", std::process::id()));
+        let mut config = Config::default();
+        config
+            .schedule_plans
+            .push(SchedulePreset::OfficeHours.plan("Office"));
+        config.auth.users = vec![
+            Account {
+                username: "admin".to_string(),
+                password: "admin-pass".to_string(),
+                role: Role::Admin,
+            },
+            Account {
+                username: "family".to_string(),
+                password: "family-pass".to_string(),
+                role: Role::Viewer,
+            },
+        ];
+        let (base, server, handle) = serve_web(config, path.display().to_string()).await;
+        let url = format!("{}/api/v1/schedule/assignments", base);
+        let client = reqwest::Client::new();
+        let send = |method: reqwest::Method, body: &'static str, user: (&str, &str)| {
+            client
+                .request(method, &url)
+                .basic_auth(user.0, Some(user.1))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+        };
+        let admin = ("admin", "admin-pass");
+        let viewer = ("family", "family-pass");
+
+        let response = send(
+            reqwest::Method::POST,
+            r#"{"days": "weekdays", "plan": "Office"}"#,
+            admin,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let week = response.text().await.unwrap();
+        assert!(week.starts_with(r#"{"monday":"Office","tuesday":"Office""#));
+        assert!(week.ends_with(r#""saturday":"Default","sunday":"Default"}"#));
+
+        let response = send(
+            reqwest::Method::PUT,
+            r#"{"sunday": "Office", "monday": "Default"}"#,
+            admin,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let config = server.config().read().await.clone();
+        assert_eq!(config.day_assignments[&Weekday::Monday], "Default");
+        assert_eq!(config.day_assignments[&Weekday::Friday], "Office");
+        assert_eq!(config.day_assignments[&Weekday::Sunday], "Office");
+        assert_eq!(
+            Config::load(&path).unwrap().day_assignments,
+            config.day_assignments
+        );
+
+        let response = send(
+            reqwest::Method::POST,
+            r#"{"days": ["friday"], "plan": "Weekend"}"#,
+            admin,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(reqwest::Method::GET, "", viewer).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(reqwest::Method::PUT, r#"{"friday": "Default"}"#, viewer)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        handle.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn message_overrides_schedule_until_it_expires() {
+        let harness = Harness::new("/red.png").await;
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+
+        harness.arbiter.set(crate::message::new(
+            "Back at 3pm",
+            std::time::Duration::from_millis(500),
+        ));
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+        assert!(!harness.last_frame().unwrap().iter().all(|&b| b == red));
+
+        // Already on the panel
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 1);
+
+        tokio::time::sleep(std::time::Duration::from_millis(600)).await;
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == red));
+
+        // Posted by the CLI through the API
+        let (base, server, handle) = serve_web(Config::default(), String::new()).await;
+        let port = base.rsplit(':').next().unwrap().parse().unwrap();
+        crate::cli::post_message(&Config::default(), port, "Back at 3pm", Some(5))
+            .await
+            .unwrap();
+        let message = server.arbiter().active().unwrap();
+        assert_eq!(message.priority, crate::arbiter::Priority::Manual);
+        assert!(message.remaining().unwrap() > std::time::Duration::from_secs(4 * 60));
+        assert!(
+            crate::cli::post_message(&Config::default(), port, " ", None)
+                .await
+                .is_err()
+        );
+
+        let status = || async {
+            let body = reqwest::get(format!("{}/api/v1/status", base))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()
+        };
+        let snapshot = status().await;
+        assert_eq!(snapshot["override_priority"], "manual");
+        assert!(snapshot["override_until"].is_string());
+        assert!(snapshot["override_remaining_secs"].as_u64().unwrap() > 4 * 60);
+
+        // Canceled early
+        crate::cli::cancel_message(&Config::default(), port)
+            .await
+            .unwrap();
+        assert!(server.arbiter().active().is_none());
+        assert!(status().await["override_until"].is_null());
+        assert!(
+            crate::cli::cancel_message(&Config::default(), port)
+                .await
+                .is_err()
+        );
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn pushed_image_is_shown_like_a_message() {
+        let (base, server, handle) = serve_web(Config::default(), String::new()).await;
+        let port = base.rsplit(':').next().unwrap().parse().unwrap();
+        let path = std::env::temp_dir().join(format!("epaper-push-{}.png", std::process::id()));
+        std::fs::write(&path, fixture_png(400, 240, [255, 0, 0])).unwrap();
+        let path = path.to_str().unwrap();
+
+        crate::cli::push_image(&Config::default(), "127.0.0.1", port, path, Some(5), None)
+            .await
+            .unwrap();
+        let pushed = server.arbiter().active().unwrap();
+        assert_eq!(pushed.priority, crate::arbiter::Priority::Manual);
+        assert!(pushed.label.starts_with("pushed image"));
+        let preview = server.processor().last_frame_png().unwrap();
+        let preview = image::load_from_memory(&preview).unwrap().to_rgb8();
+        assert_eq!(preview.get_pixel(400, 240).0, [255, 0, 0]);
+
+        // Not an image
+        std::fs::write(path, "hello").unwrap();
+        let error = crate::cli::push_image(&Config::default(), &base, 0, path, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("400"), "{}", error);
+        std::fs::remove_file(path).unwrap();
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn alert_preempts_message_until_cleared() {
+        use crate::arbiter::{Override, Priority};
+
+        let harness = Harness::new("/red.png").await;
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        let hour = std::time::Duration::from_secs(3600);
+
+        assert!(
+            harness
+                .arbiter
+                .set(crate::message::new("Back at 3pm", hour))
+        );
+        harness.scheduler.refresh_display().await;
+        let message_frame = harness.last_frame().unwrap();
+
+        let alert = Override {
+            priority: Priority::Alert,
+            label: "test alert".to_string(),
+            source: crate::sources::SourceConfig::Text(crate::sources::text::TextSource {
+                text: "Disk full".to_string(),
+                size: 0,
+                color: Some("red".to_string()),
+                background: None,
+                align: Default::default(),
+            }),
+            until: None,
+        };
+        assert!(harness.arbiter.set(alert));
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+        assert_ne!(harness.last_frame().unwrap(), message_frame);
+        assert_eq!(
+            harness.status.snapshot().override_priority,
+            Some(Priority::Alert)
+        );
+
+        // A new message waits behind the alert
+        assert!(
+            !harness
+                .arbiter
+                .set(crate::message::new("Back at 3pm", hour))
+        );
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.frames_displayed(), 2);
+
+        // Clearing the alert brings the message back, then the schedule
+        assert!(harness.arbiter.clear(Priority::Alert));
+        harness.scheduler.refresh_display().await;
+        assert_eq!(harness.last_frame().unwrap(), message_frame);
+        assert!(harness.arbiter.clear(Priority::Manual));
+        harness.scheduler.refresh_display().await;
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == red));
+        assert_eq!(harness.status.snapshot().override_priority, None);
+    }
+
+    #[tokio::test]
+    async fn demo_cycles_samples_and_restores_schedule() {
+        use crate::demo::Demo;
+        use std::time::Duration;
+
+        let harness = Harness::new("/red.png").await;
+        let red = (Color::Red as u8) << 4 | Color::Red as u8;
+        let demo = || {
+            Demo::new(
+                harness.config.clone(),
+                harness.processor.clone(),
+                harness.arbiter.clone(),
+            )
+        };
+
+        // Steps are counted, not timed: three frames, then the schedule
+        let shown = demo()
+            .run(
+                chrono::Local::now() + Duration::from_secs(60),
+                3,
+                Duration::ZERO,
+            )
+            .await;
+        assert_eq!(shown, 3);
+        assert_eq!(harness.frames_displayed(), 3);
+
+        // The override ends with the demo and the schedule takes over
+        assert!(harness.arbiter.active().is_none());
+        harness.scheduler.refresh_display().await;
+        assert!(harness.last_frame().unwrap().iter().all(|&b| b == red));
+
+        // A message ends a running demo
+        demo().start(Duration::from_secs(60), Duration::from_secs(30));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        harness
+            .arbiter
+            .set(crate::message::new("Back at 3pm", Duration::from_secs(60)));
+        assert!(Demo::stop(&harness.arbiter));
+        assert!(harness.arbiter.active().unwrap().label.starts_with("kiosk"));
+
+        demo().start(Duration::from_secs(60), Duration::from_secs(30));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(Demo::stop(&harness.arbiter));
+        assert!(harness.arbiter.active().is_none());
+        assert!(!Demo::stop(&harness.arbiter));
+    }
+
+    #[cfg(feature = "alerts")]
+    #[tokio::test]
+    async fn alertmanager_webhook_shows_firing_alerts() {
+        use crate::arbiter::Priority;
+
+        let (base, server, handle) = serve_web(Config::default(), String::new()).await;
+        let post = |alerts: serde_json::Value| {
+            let url = format!("{}/api/v1/alerts", base);
+            async move {
+                let body =
+                    serde_json::json!({ "version": "4", "status": "firing", "alerts": alerts });
+                let response = reqwest::Client::new()
+                    .post(url)
+                    .header("content-type", "application/json")
+                    .body(body.to_string())
+                    .send()
+                    .await
+                    .unwrap();
+                assert!(response.status().is_success());
+                serde_json::from_str::<serde_json::Value>(&response.text().await.unwrap()).unwrap()
+            }
+        };
+        let alert = |status: &str, name: &str, severity: &str| {
+            serde_json::json!({
+                "status": status,
+                "labels": { "alertname": name, "severity": severity },
+                "annotations": { "summary": format!("{} on nas", name) },
+                "startsAt": "2026-10-16T08:00:00Z",
+                "fingerprint": name,
+            })
+        };
+
+        let response = post(serde_json::json!([
+            alert("firing", "DiskFull", "critical"),
+            alert("firing", "HighLoad", "warning"),
+        ]))
+        .await;
+        assert_eq!(response["firing"], 2);
+        assert_eq!(response["shown"], true);
+        let shown = server.arbiter().active().unwrap();
+        assert_eq!(shown.priority, Priority::Alert);
+
+        // Rendered with the red header bar
+        let harness = Harness::new("/white.png").await;
+        harness.arbiter.set(shown);
+        harness.scheduler.refresh_display().await;
+        let red = Color::Red as u8;
+        assert!(
+            harness
+                .last_frame()
+                .unwrap()
+                .iter()
+                .any(|&b| b >> 4 == red || b & 0x0f == red)
+        );
+
+        let response = post(serde_json::json!([alert(
+            "resolved", "DiskFull", "critical"
+        )]))
+        .await;
+        assert_eq!(response["firing"], 1);
+        assert!(server.arbiter().active().is_some());
+
+        let response = post(serde_json::json!([alert(
+            "resolved", "HighLoad", "warning"
+        )]))
+        .await;
+        assert_eq!(response["firing"], 0);
+        assert_eq!(response["shown"], false);
+        assert!(server.arbiter().active().is_none());
+
+        handle.abort();
+    }
+
+    #[cfg(feature = "notifications")]
+    #[tokio::test]
+    async fn pushed_notifications_are_shown() {
+        use crate::notifications::{PushProvider, Subscriber};
+        use crate::sources::SourceConfig;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let harness = Harness::new("/red.png").await;
+        // Text of the override once its frame reached the panel
+        let shown_text = async |frames| {
+            for _ in 0..100 {
+                if harness.frames_displayed() == frames
+                    && let Some(content) = harness.arbiter.active()
+                    && let SourceConfig::Text(text) = content.source
+                {
+                    return text.text;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            panic!("No notification shown");
+        };
+        let subscribe = async |provider, url: String, token: &str| {
+            let mut config = harness.config.write().await;
+            config.notifications.provider = Some(provider);
+            config.notifications.url = url;
+            config.notifications.token = token.to_string();
+            config.notifications.duration_min = 5;
+            Subscriber::new(
+                Arc::clone(&harness.config),
+                Arc::clone(&harness.processor),
+                Arc::clone(&harness.arbiter),
+            )
+        };
+
+        // ntfy: server-sent events
+        let url = harness
+            .config
+            .read()
+            .await
+            .image_url
+            .replace("/red.png", "/ntfy");
+        let ntfy = tokio::spawn(subscribe(PushProvider::Ntfy, url, "").await.run());
+        assert_eq!(shown_text(1).await, "Doorbell\n\nSomeone is at the door");
+        let content = harness.arbiter.active().unwrap();
+        assert_eq!(content.priority, crate::arbiter::Priority::Manual);
+        assert!(content.remaining().unwrap() > std::time::Duration::from_secs(4 * 60));
+        ntfy.abort();
+        harness.arbiter.clear(crate::arbiter::Priority::Manual);
+
+        // Gotify: WebSocket stream, answering pings
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap().to_lowercase();
+            assert!(request.starts_with("get /stream "));
+            assert!(request.contains("x-gotify-key: client-token"));
+            socket
+                .write_all(
+                    b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                      Connection: Upgrade\r\nSec-WebSocket-Accept: x\r\n\r\n",
+                )
+                .await
+                .unwrap();
+
+            socket.write_all(&[0x89, 2, b'h', b'i']).await.unwrap();
+            let mut pong = [0u8; 8];
+            socket.read_exact(&mut pong).await.unwrap();
+            assert_eq!(pong[0], 0x8A);
+            let unmasked: Vec<u8> = pong[6..]
+                .iter()
+                .zip(&pong[2..6])
+                .map(|(b, m)| b ^ m)
+                .collect();
+            assert_eq!(unmasked, b"hi");
+
+            let message =
+                br#"{"id":7,"appid":1,"title":"Backup","message":"Backup finished","priority":5}"#;
+            socket
+                .write_all(&[0x81, message.len() as u8])
+                .await
+                .unwrap();
+            socket.write_all(message).await.unwrap();
+            socket
+        });
+        let gotify = tokio::spawn(
+            subscribe(PushProvider::Gotify, url, "client-token")
+                .await
+                .run(),
+        );
+        assert_eq!(shown_text(2).await, "Backup\n\nBackup finished");
+        gotify.abort();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn save_asks_to_confirm_changes() {
+        let path = std::env::temp_dir().join(format!("epaper-confirm-{}.json", std::process::id()));
+        let (base, server, handle) = serve_web(Config::default(), path.display().to_string()).await;
+
+        let plans = r#"{"plans":[{"name":"Default","periods":[{"start_time":"00:00","end_time":"00:00","interval_min":15}]}],"day_assignments":{}}"#;
+        let mut form = vec![
+            ("display_width", "800"),
+            ("display_height", "480"),
+            ("rotation", "180"),
+            ("rotate_first", "1"),
+            ("scale_to_fit", "on"),
+            ("plans_json", plans),
+        ];
+        let client = reqwest::Client::new();
+
+        let page = client
+            .post(format!("{}/save", base))
+            .form(&form)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(page.contains("rotation 0°→180°"));
+        assert!(page.contains("Default 00:00–00:00: interval 60→15 min"));
+        assert_eq!(server.config().read().await.rotation, 0);
+
+        form.push(("confirmed", "1"));
+        client
+            .post(format!("{}/save", base))
+            .form(&form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(server.config().read().await.rotation, 180);
+        assert_eq!(server.config().read().await.get_current_interval(), 15);
+
+        handle.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn fast_refresh_plans_are_refused_on_save() {
+        let (base, server, handle) = serve_web(Config::default(), String::new()).await;
+        let plans = r#"{"plans":[{"name":"Default","periods":[{"start_time":"00:00","end_time":"00:00","interval_min":1}]}],"day_assignments":{}}"#;
+
+        let page = reqwest::Client::new()
+            .post(format!("{}/save", base))
+            .form(&[("plans_json", plans), ("confirmed", "1")])
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(page.contains("below the minimum of 5 min"));
+        assert_eq!(server.config().read().await.get_current_interval(), 60);
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn display_api_reports_panel_specs() {
+        let (base, server, handle) = serve_web(Config::default(), String::new()).await;
+        server.processor().clear_display(&Config::default()).await.unwrap();
+
+        let body = reqwest::get(format!("{}/api/v1/display", base))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let info: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(info["width"], 800);
+        assert_eq!(info["height"], 480);
+        assert_eq!(info["palette"].as_array().unwrap().len(), 7);
+        assert_eq!(info["palette"][3]["name"], "Red");
+        assert!(info["last_refresh_ms"].as_u64().is_some());
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn api_errors_carry_codes() {
+        let fixtures = FixtureServer::start().await;
+        let config = Config {
+            image_url: fixtures.url("/not-an-image"),
+            ..Config::default()
+        };
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+
+        let client = reqwest::Client::new();
+        let get_json = |path: &str| {
+            let request = client
+                .get(format!("{}{}", base, path))
+                .header("accept", "application/json");
+            async move {
+                let response = request.send().await.unwrap();
+                let status = response.status().as_u16();
+                let body: serde_json::Value =
+                    serde_json::from_str(&response.text().await.unwrap()).unwrap();
+                (status, body)
+            }
+        };
+
+        let (status, body) = get_json("/action/show").await;
+        assert_eq!(status, 502);
+        assert_eq!(body["code"], "decode");
+        assert_eq!(body["retryable"], false);
+
+        let (status, body) = get_json("/action/dance").await;
+        assert_eq!(status, 404);
+        assert_eq!(body["code"], "not_found");
+
+        // Browsers still get the HTML page
+        let page = client
+            .get(format!("{}/action/dance", base))
+            .send()
+            .await
+            .unwrap();
+        assert!(page.text().await.unwrap().contains("Unknown action"));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn openapi_document_lists_endpoints() {
+        let (base, _server, handle) = serve_web(Config::default(), String::new()).await;
+
+        let body = reqwest::get(format!("{}/api/v1/openapi.json", base))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let spec: serde_json::Value = serde_json::from_str(&body).unwrap();
+        for path in ["/api/v1/status", "/api/v1/display", "/action/{action}"] {
+            assert!(spec["paths"][path].is_object(), "{} missing", path);
+        }
+        assert!(spec["components"]["schemas"]["ErrorBody"].is_object());
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn viewer_cannot_change_config() {
+        use crate::web::auth::{Account, Role};
+
+        let mut config = Config::default();
+        config.auth.users = vec![
+            Account {
+                username: "admin".to_string(),
+                password: "admin-pass".to_string(),
+                role: Role::Admin,
+            },
+            Account {
+                username: "family".to_string(),
+                password: "family-pass".to_string(),
+                role: Role::Viewer,
+            },
+        ];
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+
+        let client = reqwest::Client::new();
+        let get = |path: &str, user: Option<(&str, &str)>| {
+            let request = client.get(format!("{}{}", base, path));
+            let request = match user {
+                Some((name, password)) => request.basic_auth(name, Some(password)),
+                None => request,
+            };
+            async move { request.send().await.unwrap() }
+        };
+        let viewer = Some(("family", "family-pass"));
+        let admin = Some(("admin", "admin-pass"));
+
+        assert_eq!(get("/health", None).await.status(), 200);
+        assert_eq!(get("/", None).await.status(), 401);
+        assert_eq!(get("/", Some(("family", "wrong"))).await.status(), 401);
+
+        let page = get("/", viewer).await;
+        assert_eq!(page.status(), 200);
+        assert!(!page.text().await.unwrap().contains("Save &amp; Apply"));
+        assert_eq!(get("/api/v1/status", viewer).await.status(), 200);
+        assert_eq!(get("/action/clear", viewer).await.status(), 403);
+        let denied = get("/api/v1/debug/bundle", viewer).await;
+        assert_eq!(denied.status(), 403);
+        let body: serde_json::Value = serde_json::from_str(&denied.text().await.unwrap()).unwrap();
+        assert_eq!(body["code"], "forbidden");
+
+        let page = get("/", admin).await;
+        assert!(page.text().await.unwrap().contains("Save &amp; Apply"));
+        assert_eq!(get("/api/v1/debug/bundle", admin).await.status(), 200);
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn public_status_card_needs_no_login() {
+        use crate::web::auth::{Account, Role};
+        use crate::web::public::REQUESTS_PER_WINDOW;
+
+        let mut config = Config {
+            image_url: "https://example.com/image.png?token=secret".to_string(),
+            ..Config::default()
+        };
+        config.auth.users = vec![Account {
+            username: "admin".to_string(),
+            password: "admin-pass".to_string(),
+            role: Role::Admin,
+        }];
+        let (base, _server, handle) = serve_web(config, String::new()).await;
+
+        let client = reqwest::Client::new();
+        let page = client.get(format!("{}/status", base)).send().await.unwrap();
+        assert_eq!(page.status(), 200);
+        let page = page.text().await.unwrap();
+        assert!(page.contains("Next refresh"));
+        assert!(!page.contains("example.com"));
+        assert!(!page.contains("Save"));
+
+        let thumbnail = client
+            .get(format!("{}/status/thumbnail.png", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(thumbnail.status(), 404);
+
+        for _ in 2..REQUESTS_PER_WINDOW {
+            client.get(format!("{}/status", base)).send().await.unwrap();
+        }
+        let limited = client.get(format!("{}/status", base)).send().await.unwrap();
+        assert_eq!(limited.status(), 429);
+        assert!(limited.headers().contains_key("retry-after"));
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn history_keeps_last_frames() {
+        let fixtures = FixtureServer::start().await;
+        let dir = std::env::temp_dir().join(format!("epaper-history-{}", std::process::id()));
+        let config = Config {
+            image_url: fixtures.url("/red.png"),
+            history_frames: 2,
+            ..Config::default()
+        };
+        let config_path = dir.join("config.json").to_string_lossy().into_owned();
+        let (base, server, handle) = serve_web(config.clone(), config_path).await;
+
+        for _ in 0..3 {
+            server
+                .processor()
+                .process_and_display(&config)
+                .await
+                .unwrap();
+        }
+        let entries = server.processor().history().unwrap().list();
+        assert_eq!(entries.len(), 2);
+
+        let page = reqwest::get(format!("{}/history", base)).await.unwrap();
+        let page = page.text().await.unwrap();
+        assert!(page.contains(&entries[0].name));
+        assert!(page.contains(&entries[1].name));
+
+        let frame = reqwest::get(format!("{}/history/{}", base, entries[0].name))
+            .await
+            .unwrap();
+        assert_eq!(frame.status(), 200);
+        assert!(image::load_from_memory(&frame.bytes().await.unwrap()).is_ok());
+        let escape = reqwest::get(format!("{}/history/..%2Fconfig.json", base))
+            .await
+            .unwrap();
+        assert_eq!(escape.status(), 404);
+
+        #[cfg(feature = "timelapse")]
+        {
+            let gif = reqwest::get(format!("{}/api/v1/history/timelapse.gif?width=100", base))
+                .await
+                .unwrap();
+            assert_eq!(gif.status(), 200);
+            let gif = gif.bytes().await.unwrap();
+            assert!(gif.starts_with(b"GIF89a"));
+            assert_eq!(image::load_from_memory(&gif).unwrap().width(), 100);
+
+            let invalid =
+                reqwest::get(format!("{}/api/v1/history/timelapse.gif?from=monday", base))
+                    .await
+                    .unwrap();
+            assert_eq!(invalid.status(), 400);
+            let body: serde_json::Value =
+                serde_json::from_str(&invalid.text().await.unwrap()).unwrap();
+            assert_eq!(body["code"], "bad_request");
+        }
+
+        handle.abort();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn empty_url_skips_refresh() {
+        let harness = Harness::new("/red.png").await;
+        harness.config.write().await.image_url.clear();
+
+        harness.scheduler.refresh_display().await;
+
+        assert_eq!(harness.frames_displayed(), 0);
+    }
+}
@@ -0,0 +1,20 @@
+//! System clock sanity.
+//!
+//! The Pi Zero has no real-time clock: until NTP has synchronized, the
+//! system time is whatever was saved at the last shutdown, or 1970. The
+//! schedule picks intervals by weekday and time of day, so with such a
+//! clock it would pick nonsense ones.
+
+use chrono::Datelike;
+use std::time::Duration;
+
+/// Earliest year a synchronized clock can show
+pub const MIN_YEAR: i32 = 2024;
+
+/// Refresh interval used instead of the schedule while the clock is off
+pub const UNSYNCED_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Whether the system clock looks synchronized
+pub fn is_synchronized() -> bool {
+    chrono::Local::now().year() >= MIN_YEAR
+}
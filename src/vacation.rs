@@ -0,0 +1,51 @@
+//! Vacation mode.
+//!
+//! While nobody is home the panel does not need the usual schedule. One
+//! switch replaces the day assignments with a designated plan, usually
+//! one that refreshes rarely, and can show a static image instead of the
+//! configured source. Plans and day assignments are left as they are and
+//! apply again once the switch is turned off.
+
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
+
+/// Vacation mode settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VacationConfig {
+    /// Vacation mode is on
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Plan for every day while on (empty = keep the normal schedule)
+    #[serde(default)]
+    pub plan: String,
+
+    /// Image shown while on (empty = keep the configured source)
+    #[serde(default)]
+    pub image_url: String,
+}
+
+impl VacationConfig {
+    /// Validate settings; the plan name is checked with the plans
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = self.image_url.trim();
+        if !url.is_empty() && !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(ConfigError::ValidationError(
+                "vacation.image_url must start with http:// or https://".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Plan replacing the day assignments, while on
+    pub fn active_plan(&self) -> Option<&str> {
+        let plan = self.plan.trim();
+        (self.enabled && !plan.is_empty()).then_some(plan)
+    }
+
+    /// Image replacing the configured source, while on
+    pub fn active_image(&self) -> Option<&str> {
+        let url = self.image_url.trim();
+        (self.enabled && !url.is_empty()).then_some(url)
+    }
+}
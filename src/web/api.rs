@@ -0,0 +1,1450 @@
+//! JSON API handlers (`/api/v1/...`).
+
+use super::bundle;
+use super::error::ApiError;
+use super::routes::{AppState, Done};
+use crate::arbiter::Priority;
+use crate::config::{
+    Config, DayAssignments, SchedulePeriod, SchedulePlan, SchedulePreset, Weekday,
+};
+use crate::demo::{self, Demo};
+use crate::discovery::{self, Peer};
+use crate::display::Palette;
+use crate::image_proc::dither;
+use crate::message::{self, MAX_MESSAGE_CHARS};
+use crate::mirror;
+use crate::outputs::OutputStatus;
+use crate::schedule;
+#[cfg(feature = "alerts")]
+use crate::sources::SourceConfig;
+#[cfg(feature = "alerts")]
+use crate::sources::alerts::{AlertList, AlertmanagerWebhook};
+use crate::sources::pushed::PushedImage;
+use crate::status::StatusSnapshot;
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Path, Query, State, rejection::JsonRejection},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Panel specs and constraints (`GET /api/v1/display`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DisplayInfo {
+    /// Panel model of the active driver
+    pub model: &'static str,
+    /// Native resolution in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Colors or grey levels the panel can show
+    pub palette: Vec<PaletteColor>,
+    /// Accepted `rotation` values in degrees
+    pub rotations: [u16; 4],
+    /// Whether regions can be refreshed without a full refresh
+    pub partial_refresh: bool,
+    /// Typical full refresh duration from the datasheet
+    pub refresh_time_secs: u32,
+    /// Measured duration of the last full refresh, if any since start
+    pub last_refresh_ms: Option<u64>,
+}
+
+/// One palette entry
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaletteColor {
+    /// Index (or grey level) sent to the panel
+    pub index: u8,
+    pub name: String,
+    /// RGB value used when dithering
+    pub rgb: [u8; 3],
+}
+
+/// Kiosk message to show (`POST /api/v1/message`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MessageRequest {
+    /// Text to show (use "\n" for line breaks)
+    pub text: String,
+    /// Minutes until the schedule resumes (default `message_duration_min`)
+    #[serde(default)]
+    pub duration_min: Option<u32>,
+}
+
+/// Kiosk message on the panel
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageInfo {
+    pub text: String,
+    /// Local time the message ends (RFC 3339)
+    pub until: String,
+    /// Whether the panel shows the message now; false while an alert is
+    /// shown instead
+    pub shown: bool,
+}
+
+/// Parameters of `POST /api/v1/image`
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PushParams {
+    /// Minutes until the schedule resumes (default `message_duration_min`)
+    pub duration_min: Option<u32>,
+}
+
+/// Pushed image on the panel
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PushedInfo {
+    /// Size of the pushed image in pixels, before it is fitted to the panel
+    pub width: u32,
+    pub height: u32,
+    /// Local time the image ends (RFC 3339)
+    pub until: String,
+    /// Whether the panel shows the image now; false while an alert is
+    /// shown instead
+    pub shown: bool,
+}
+
+/// Alerts on the panel after a webhook
+#[cfg(feature = "alerts")]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AlertsInfo {
+    /// Alerts still firing
+    pub firing: usize,
+    /// Whether the panel shows them; false once all are resolved
+    pub shown: bool,
+}
+
+/// Demo to run (`POST /api/v1/demo`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DemoRequest {
+    /// Minutes until the schedule resumes
+    #[serde(default = "default_demo_duration_min")]
+    pub duration_min: u32,
+    /// Seconds each screen is shown
+    #[serde(default = "default_demo_step_secs")]
+    pub step_secs: u32,
+}
+
+fn default_demo_duration_min() -> u32 {
+    30
+}
+
+fn default_demo_step_secs() -> u32 {
+    60
+}
+
+/// Running demo
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DemoInfo {
+    /// Local time the demo ends (RFC 3339)
+    pub until: String,
+    /// Seconds each screen is shown
+    pub step_secs: u32,
+}
+
+/// Parameters of `GET /api/v1/schedule/simulate` and `/api/v1/schedule.ics`
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScheduleRange {
+    /// Start (RFC 3339, `YYYY-MM-DDTHH:MM` or `YYYY-MM-DD`); default: now
+    /// for the simulation, the next scheduled refresh for the feed
+    pub from: Option<String>,
+    /// End, in the same formats; default: one day (simulation) or one
+    /// week (feed) after `from`
+    pub to: Option<String>,
+}
+
+impl ScheduleRange {
+    /// Start and end as local times, `days` long unless `to` is given
+    fn resolve(
+        &self,
+        default_from: DateTime<Local>,
+        days: i64,
+    ) -> Result<(DateTime<Local>, DateTime<Local>), ApiError> {
+        let time = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| {
+                    schedule::parse_time(v)
+                        .ok_or_else(|| ApiError::BadRequest(format!("Invalid time '{}'", v)))
+                })
+                .transpose()
+        };
+        let from = time(&self.from)?.unwrap_or(default_from);
+        let to = time(&self.to)?.unwrap_or(from + chrono::Duration::days(days));
+        if to < from {
+            return Err(ApiError::BadRequest(
+                "to must not be before from".to_string(),
+            ));
+        }
+        if to - from > chrono::Duration::days(schedule::MAX_RANGE_DAYS) {
+            return Err(ApiError::BadRequest(format!(
+                "The range must not be longer than {} days",
+                schedule::MAX_RANGE_DAYS
+            )));
+        }
+        Ok((from, to))
+    }
+}
+
+/// Refreshes the schedule would run (`GET /api/v1/schedule/simulate`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleSimulation {
+    /// Start of the range (RFC 3339)
+    pub from: String,
+    /// End of the range (RFC 3339)
+    pub to: String,
+    /// Refreshes that would run, not counting skipped ones
+    pub refresh_count: usize,
+    /// Whether the list was cut at 5000 entries
+    pub truncated: bool,
+    pub refreshes: Vec<SimulatedRefresh>,
+}
+
+/// One refresh of a simulated schedule
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimulatedRefresh {
+    /// Local time (RFC 3339)
+    pub at: String,
+    /// Plan in effect (none for weekdays without a plan)
+    pub plan: Option<String>,
+    /// Minutes until the next refresh
+    pub interval_min: u32,
+    /// Left out because `max_refreshes_per_day` is used up that day
+    pub skipped: bool,
+}
+
+/// Ready-made plan (`GET /api/v1/schedule/presets`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PresetInfo {
+    /// Value for `preset` in `POST /api/v1/schedule/plans`
+    pub id: SchedulePreset,
+    /// Name a plan created from it gets
+    pub name: &'static str,
+    pub description: &'static str,
+    pub periods: Vec<SchedulePeriod>,
+}
+
+/// Plan to add (`POST /api/v1/schedule/plans`): a copy of an existing plan
+/// or a preset
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewPlan {
+    /// Name of the new plan (default: the preset name, or "<plan> copy")
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Plan to duplicate
+    #[serde(default)]
+    pub copy_of: Option<String>,
+    /// Preset to start from
+    #[serde(default)]
+    pub preset: Option<SchedulePreset>,
+}
+
+/// Days to assign a plan to: a group or a list of days
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum DaySelection {
+    Group(DayGroup),
+    Days(Vec<Weekday>),
+}
+
+/// Group of weekdays
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DayGroup {
+    /// Monday to Friday
+    Weekdays,
+    /// Saturday and Sunday
+    Weekend,
+    /// Every day
+    All,
+}
+
+impl DaySelection {
+    /// Selected days
+    fn days(&self) -> Vec<Weekday> {
+        match self {
+            DaySelection::Group(DayGroup::Weekdays) => Weekday::all()[..5].to_vec(),
+            DaySelection::Group(DayGroup::Weekend) => Weekday::all()[5..].to_vec(),
+            DaySelection::Group(DayGroup::All) => Weekday::all().to_vec(),
+            DaySelection::Days(days) => days.clone(),
+        }
+    }
+}
+
+/// Vacation mode (`GET /api/v1/vacation`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VacationInfo {
+    pub enabled: bool,
+    /// Plan used for every day while on (none = the normal schedule)
+    pub plan: Option<String>,
+    /// Whether a static image replaces the source while on
+    pub image: bool,
+}
+
+impl VacationInfo {
+    fn of(config: &Config) -> Self {
+        let vacation = &config.vacation;
+        Self {
+            enabled: vacation.enabled,
+            plan: Some(vacation.plan.trim().to_string()).filter(|plan| !plan.is_empty()),
+            image: !vacation.image_url.trim().is_empty(),
+        }
+    }
+}
+
+/// Turn vacation mode on or off (`PUT /api/v1/vacation`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VacationRequest {
+    pub enabled: bool,
+    /// Plan for every day while on (default: as configured; "" = the
+    /// normal schedule)
+    #[serde(default)]
+    pub plan: Option<String>,
+    /// Image shown while on (default: as configured; "" = the configured
+    /// source)
+    #[serde(default)]
+    pub image_url: Option<String>,
+}
+
+/// Raw panel controller command (`POST /api/v1/debug/epd`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EpdCommandRequest {
+    /// Command in hex, e.g. `"0x12"`: a byte, or a 16-bit word on IT8951
+    /// panels
+    pub command: String,
+    /// Data bytes in hex, e.g. `"00"` or `"0x3F 0x00"` (default: none)
+    #[serde(default)]
+    pub data: String,
+}
+
+/// Raw command sent to the panel controller
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EpdCommandResult {
+    pub command: String,
+    /// Data bytes sent after the command
+    pub data_bytes: usize,
+    /// Time until the controller was idle again
+    pub elapsed_ms: u64,
+}
+
+/// A GPIO output (`GET /api/v1/outputs`)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OutputInfo {
+    pub name: String,
+    /// GPIO number (BCM numbering)
+    pub pin: u8,
+    pub on: bool,
+    /// Switched through the API; holds until the schedule changes state
+    pub manual: bool,
+}
+
+impl From<OutputStatus> for OutputInfo {
+    fn from(status: OutputStatus) -> Self {
+        Self {
+            name: status.name,
+            pin: status.pin,
+            on: status.on,
+            manual: status.manual,
+        }
+    }
+}
+
+/// Switch a GPIO output (`PUT /api/v1/outputs/{name}`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OutputRequest {
+    pub on: bool,
+}
+
+/// Week of `/api/v1/schedule/assignments`
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WeekParam {
+    /// `a` (default) or `b`, the weeks `alternate_weeks` applies to
+    #[serde(default)]
+    pub week: Week,
+}
+
+/// A or B week of alternating day assignments
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Week {
+    #[default]
+    A,
+    B,
+}
+
+const NO_ALTERNATE_WEEKS: &str = "No alternate weeks are set up (alternate_weeks.b_weeks)";
+
+/// Plan for several days (`POST /api/v1/schedule/assignments`)
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssignDays {
+    /// `weekdays`, `weekend`, `all` or a list like `["monday", "friday"]`
+    pub days: DaySelection,
+    /// Name of the plan
+    pub plan: String,
+}
+
+/// GET /api/v1/status - Device status snapshot
+#[utoipa::path(
+    get,
+    path = "/api/v1/status",
+    tag = "status",
+    responses((status = 200, description = "Device status", body = StatusSnapshot)),
+)]
+pub async fn status(State(state): State<AppState>) -> Json<StatusSnapshot> {
+    Json(state.status.snapshot())
+}
+
+/// GET /api/v1/display - Panel specs and constraints of the active driver
+#[utoipa::path(
+    get,
+    path = "/api/v1/display",
+    tag = "display",
+    responses((status = 200, description = "Panel specs", body = DisplayInfo)),
+)]
+pub async fn display(State(state): State<AppState>) -> Json<DisplayInfo> {
+    let display = state.processor.display();
+    let spec = display.spec();
+
+    Json(DisplayInfo {
+        model: spec.model,
+        width: spec.width,
+        height: spec.height,
+        palette: palette_entries(spec.palette),
+        rotations: [0, 90, 180, 270],
+        partial_refresh: spec.partial_refresh,
+        refresh_time_secs: spec.refresh_secs,
+        last_refresh_ms: display.last_refresh_ms(),
+    })
+}
+
+/// GET /api/v1/peers - Other displays on the network
+///
+/// Asks the network over mDNS and collects answers for 1.5 seconds.
+#[utoipa::path(
+    get,
+    path = "/api/v1/peers",
+    tag = "status",
+    responses(
+        (status = 200, description = "Other displays that answered, by name", body = Vec<Peer>),
+        (status = 404, description = "Peer discovery is off", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn peers(State(state): State<AppState>) -> Result<Json<Vec<Peer>>, ApiError> {
+    if !state.config.read().await.peer_discovery {
+        return Err(ApiError::NotFound(
+            "Peer discovery is off (peer_discovery)".to_string(),
+        ));
+    }
+    Ok(Json(discovery::browse(&crate::network::hostname()).await))
+}
+
+/// GET /api/v1/schedule/simulate - Refresh times of the current schedule
+///
+/// Replays the plans and day assignments over a range (at most 31 days)
+/// without waiting for it. Each day starts with an unused refresh budget;
+/// failures, overrides and deferrals are not simulated.
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedule/simulate",
+    tag = "schedule",
+    params(ScheduleRange),
+    responses(
+        (status = 200, description = "Simulated refreshes", body = ScheduleSimulation),
+        (status = 400, description = "Invalid range", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn simulate_schedule(
+    State(state): State<AppState>,
+    Query(range): Query<ScheduleRange>,
+) -> Result<Json<ScheduleSimulation>, ApiError> {
+    let (from, to) = range.resolve(Local::now(), 1)?;
+    let (refreshes, truncated) = schedule::replay(&*state.config.read().await, from, to);
+
+    Ok(Json(ScheduleSimulation {
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        refresh_count: refreshes.iter().filter(|r| !r.skipped).count(),
+        truncated,
+        refreshes: refreshes
+            .into_iter()
+            .map(|r| SimulatedRefresh {
+                at: r.at.to_rfc3339(),
+                plan: r.plan,
+                interval_min: r.interval_min,
+                skipped: r.skipped,
+            })
+            .collect(),
+    }))
+}
+
+/// GET /api/v1/schedule.ics - Refresh schedule as an iCalendar feed
+///
+/// The replay of `/api/v1/schedule/simulate` with one event per refresh,
+/// to overlay on a calendar next to the update times of the dashboards.
+/// Starts at the next scheduled refresh and covers a week by default.
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedule.ics",
+    tag = "schedule",
+    params(ScheduleRange),
+    responses(
+        (status = 200, description = "iCalendar feed", content_type = "text/calendar", body = String),
+        (status = 400, description = "Invalid range", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn schedule_ics(
+    State(state): State<AppState>,
+    Query(range): Query<ScheduleRange>,
+) -> Result<impl IntoResponse, ApiError> {
+    let now = Local::now();
+    let next = state
+        .status
+        .snapshot()
+        .next_refresh_at
+        .as_deref()
+        .and_then(schedule::parse_time)
+        .filter(|next| *next > now);
+    let (from, to) = range.resolve(next.unwrap_or(now), 7)?;
+    let (refreshes, _) = schedule::replay(&*state.config.read().await, from, to);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"epaper-schedule.ics\"",
+            ),
+        ],
+        schedule::to_ics(&refreshes),
+    ))
+}
+
+/// GET /api/v1/schedule/presets - Ready-made plans
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedule/presets",
+    tag = "schedule",
+    responses((status = 200, description = "Presets", body = Vec<PresetInfo>)),
+)]
+pub async fn schedule_presets() -> Json<Vec<PresetInfo>> {
+    Json(
+        SchedulePreset::all()
+            .iter()
+            .map(|preset| PresetInfo {
+                id: *preset,
+                name: preset.title(),
+                description: preset.description(),
+                periods: preset.periods(),
+            })
+            .collect(),
+    )
+}
+
+/// POST /api/v1/schedule/plans - Add a plan copied from another or a preset
+///
+/// The plan is saved right away but not assigned to any day.
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedule/plans",
+    tag = "schedule",
+    request_body = NewPlan,
+    responses(
+        (status = 200, description = "Plan added", body = SchedulePlan),
+        (status = 400, description = "Invalid request or name already taken", body = super::error::ErrorBody),
+        (status = 404, description = "No plan with the `copy_of` name", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn add_plan(
+    State(state): State<AppState>,
+    request: Result<Json<NewPlan>, JsonRejection>,
+) -> Result<Json<SchedulePlan>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let mut config = state.config.write().await;
+
+    let (base, periods) = match (&request.copy_of, request.preset) {
+        (Some(source), None) => {
+            let plan = config
+                .get_plan(source)
+                .ok_or_else(|| ApiError::NotFound(format!("No plan named '{}'", source)))?;
+            (format!("{} copy", plan.name), plan.periods.clone())
+        }
+        (None, Some(preset)) => (preset.title().to_string(), preset.periods()),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Give either copy_of or preset".to_string(),
+            ));
+        }
+    };
+    let name = match request.name.as_deref().map(str::trim) {
+        Some(name) if config.get_plan(name).is_some() => {
+            return Err(ApiError::BadRequest(format!(
+                "A plan named '{}' already exists",
+                name
+            )));
+        }
+        Some(name) => name.to_string(),
+        None => config.unused_plan_name(&base),
+    };
+
+    let plan = SchedulePlan::new(&name, periods);
+    let mut updated = config.clone();
+    updated.schedule_plans.push(plan.clone());
+    save_config(&state, &mut config, updated)?;
+
+    tracing::info!("Added schedule plan '{}'", name);
+    Ok(Json(plan))
+}
+
+/// GET /api/v1/schedule/assignments - Plan of each weekday
+///
+/// For B weeks only the days that differ from A weeks are listed.
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedule/assignments",
+    tag = "schedule",
+    params(WeekParam),
+    responses(
+        (status = 200, description = "Plan names by weekday", body = BTreeMap<Weekday, String>),
+        (status = 404, description = "No alternate weeks set up", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn day_assignments(
+    State(state): State<AppState>,
+    Query(param): Query<WeekParam>,
+) -> Result<Json<BTreeMap<Weekday, String>>, ApiError> {
+    let config = state.config.read().await;
+    let assignments = match param.week {
+        Week::A => &config.day_assignments,
+        Week::B => config
+            .alternate_weeks
+            .as_ref()
+            .map(|weeks| &weeks.day_assignments)
+            .ok_or_else(|| ApiError::NotFound(NO_ALTERNATE_WEEKS.to_string()))?,
+    };
+    Ok(Json(sorted_assignments(assignments)))
+}
+
+/// PUT /api/v1/schedule/assignments - Set the plan of some or all weekdays
+///
+/// Takes the answer of `GET` as is, to copy a week from another display.
+/// Days left out keep their plan.
+#[utoipa::path(
+    put,
+    path = "/api/v1/schedule/assignments",
+    tag = "schedule",
+    params(WeekParam),
+    request_body = BTreeMap<Weekday, String>,
+    responses(
+        (status = 200, description = "Plan names by weekday after the change", body = BTreeMap<Weekday, String>),
+        (status = 400, description = "Unknown day or plan", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn set_day_assignments(
+    State(state): State<AppState>,
+    Query(param): Query<WeekParam>,
+    request: Result<Json<BTreeMap<Weekday, String>>, JsonRejection>,
+) -> Result<Json<BTreeMap<Weekday, String>>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    assign(&state, param.week, request).await
+}
+
+/// POST /api/v1/schedule/assignments - Assign one plan to a group of days
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedule/assignments",
+    tag = "schedule",
+    params(WeekParam),
+    request_body = AssignDays,
+    responses(
+        (status = 200, description = "Plan names by weekday after the change", body = BTreeMap<Weekday, String>),
+        (status = 400, description = "Unknown day or plan", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn assign_days(
+    State(state): State<AppState>,
+    Query(param): Query<WeekParam>,
+    request: Result<Json<AssignDays>, JsonRejection>,
+) -> Result<Json<BTreeMap<Weekday, String>>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let assignments = request
+        .days
+        .days()
+        .into_iter()
+        .map(|day| (day, request.plan.clone()))
+        .collect();
+    assign(&state, param.week, assignments).await
+}
+
+/// Assign plans to days of A or B weeks and save
+async fn assign(
+    state: &AppState,
+    week: Week,
+    assignments: BTreeMap<Weekday, String>,
+) -> Result<Json<BTreeMap<Weekday, String>>, ApiError> {
+    let mut config = state.config.write().await;
+    if let Some(plan) = assignments
+        .values()
+        .find(|plan| config.get_plan(plan).is_none())
+    {
+        return Err(ApiError::BadRequest(format!("No plan named '{}'", plan)));
+    }
+
+    let mut updated = config.clone();
+    let days = match week {
+        Week::A => &mut updated.day_assignments,
+        Week::B => updated
+            .alternate_weeks
+            .as_mut()
+            .map(|weeks| &mut weeks.day_assignments)
+            .ok_or_else(|| ApiError::BadRequest(NO_ALTERNATE_WEEKS.to_string()))?,
+    };
+    days.extend(assignments);
+    let days = sorted_assignments(days);
+    save_config(state, &mut config, updated)?;
+
+    tracing::info!("Updated day assignments of {:?} weeks", week);
+    Ok(Json(days))
+}
+
+/// Day assignments in weekday order
+fn sorted_assignments(assignments: &DayAssignments) -> BTreeMap<Weekday, String> {
+    assignments
+        .iter()
+        .map(|(day, plan)| (*day, plan.clone()))
+        .collect()
+}
+
+/// GET /api/v1/vacation - Vacation mode
+#[utoipa::path(
+    get,
+    path = "/api/v1/vacation",
+    tag = "schedule",
+    responses((status = 200, description = "Vacation mode", body = VacationInfo)),
+)]
+pub async fn vacation(State(state): State<AppState>) -> Json<VacationInfo> {
+    Json(VacationInfo::of(&*state.config.read().await))
+}
+
+/// PUT /api/v1/vacation - Turn vacation mode on or off
+///
+/// Plans and day assignments are kept and apply again once it is off. If
+/// the switch changes the image, the panel is redrawn in the background.
+#[utoipa::path(
+    put,
+    path = "/api/v1/vacation",
+    tag = "schedule",
+    request_body = VacationRequest,
+    responses(
+        (status = 200, description = "Vacation mode after the change", body = VacationInfo),
+        (status = 400, description = "Unknown plan or invalid image URL", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn set_vacation(
+    State(state): State<AppState>,
+    request: Result<Json<VacationRequest>, JsonRejection>,
+) -> Result<Json<VacationInfo>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let mut config = state.config.write().await;
+
+    let mut updated = config.clone();
+    updated.vacation.enabled = request.enabled;
+    if let Some(plan) = request.plan {
+        updated.vacation.plan = plan;
+    }
+    if let Some(image_url) = request.image_url {
+        updated.vacation.image_url = image_url;
+    }
+    let redraw = config.vacation.active_image() != updated.vacation.active_image();
+    save_config(&state, &mut config, updated)?;
+    tracing::info!(
+        "Vacation mode {}",
+        if config.vacation.enabled { "on" } else { "off" }
+    );
+    let info = VacationInfo::of(&config);
+    drop(config);
+
+    if redraw {
+        tokio::spawn(async move {
+            let config = state.config.read().await;
+            let content = state.arbiter.content_config(&config);
+            if state.arbiter.active().is_some() || !content.has_source() {
+                return;
+            }
+            if let Err(e) = state.processor.process_and_display(&content).await {
+                tracing::error!("Failed to display after vacation switch: {}", e);
+            }
+        });
+    }
+
+    Ok(Json(info))
+}
+
+/// GET /api/v1/outputs - Auxiliary GPIO outputs
+#[utoipa::path(
+    get,
+    path = "/api/v1/outputs",
+    tag = "outputs",
+    responses((status = 200, description = "Configured outputs and their state", body = Vec<OutputInfo>)),
+)]
+pub async fn outputs(State(state): State<AppState>) -> Json<Vec<OutputInfo>> {
+    let config = state.config.read().await;
+    Json(
+        state
+            .outputs
+            .statuses(&config.outputs)
+            .into_iter()
+            .map(OutputInfo::from)
+            .collect(),
+    )
+}
+
+/// PUT /api/v1/outputs/{name} - Switch a GPIO output on or off
+///
+/// The output stays as switched until its schedule changes state.
+#[utoipa::path(
+    put,
+    path = "/api/v1/outputs/{name}",
+    tag = "outputs",
+    params(("name" = String, Path, description = "Name of the output")),
+    request_body = OutputRequest,
+    responses(
+        (status = 200, description = "Output after the switch", body = OutputInfo),
+        (status = 400, description = "Invalid request body", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+        (status = 404, description = "No output with this name", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn set_output(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    request: Result<Json<OutputRequest>, JsonRejection>,
+) -> Result<Json<OutputInfo>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let config = state.config.read().await;
+    state
+        .outputs
+        .switch(&config.outputs, &name, request.on, Local::now())
+        .map(|status| Json(status.into()))
+        .ok_or_else(|| ApiError::NotFound(format!("No output named '{}'", name)))
+}
+
+/// Validate `updated`, save it and make it the shared configuration
+fn save_config(state: &AppState, config: &mut Config, updated: Config) -> Result<(), ApiError> {
+    updated
+        .validate()
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    updated
+        .save(&state.config_path)
+        .map_err(|e| ApiError::Config(e.to_string()))?;
+    state.status.set_device_name(updated.device_name());
+    *config = updated;
+    Ok(())
+}
+
+/// Palette entries as reported by the API
+fn palette_entries(palette: Palette) -> Vec<PaletteColor> {
+    match palette {
+        Palette::Colors(colors) => colors
+            .iter()
+            .map(|&color| PaletteColor {
+                index: color as u8,
+                name: dither::color_name(color).to_string(),
+                rgb: dither::color_rgb(color),
+            })
+            .collect(),
+        Palette::Grey(levels) => (0..levels.max(2))
+            .map(|i| {
+                let level = i * 15 / (levels.max(2) - 1);
+                PaletteColor {
+                    index: level,
+                    name: format!("Grey {}", level),
+                    rgb: [level * 17; 3],
+                }
+            })
+            .collect(),
+    }
+}
+
+/// GET /api/v1/debug/bundle - Zip of config, logs, last frame and status
+#[utoipa::path(
+    get,
+    path = "/api/v1/debug/bundle",
+    tag = "status",
+    responses(
+        (status = 200, description = "Support bundle", content_type = "application/zip", body = Vec<u8>),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn debug_bundle(State(state): State<AppState>) -> impl IntoResponse {
+    let filename = format!(
+        "epaper-debug-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        bundle::build(&state).await,
+    )
+}
+
+/// POST /api/v1/debug/epd - Send a raw command to the panel controller
+///
+/// For trying out controller registers without recompiling; only accepted
+/// when the server was started with `--debug-epd`. The command goes out
+/// as given, so it can leave the panel in any state.
+#[utoipa::path(
+    post,
+    path = "/api/v1/debug/epd",
+    tag = "display",
+    request_body = EpdCommandRequest,
+    responses(
+        (status = 200, description = "Command sent and the controller idle again", body = EpdCommandResult),
+        (status = 400, description = "Invalid hex", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required, or not started with --debug-epd", body = super::error::ErrorBody),
+        (status = 500, description = "The controller rejected the command or did not get idle", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn epd_command(
+    State(state): State<AppState>,
+    request: Result<Json<EpdCommandRequest>, JsonRejection>,
+) -> Result<Json<EpdCommandResult>, ApiError> {
+    if !state.debug_epd {
+        return Err(ApiError::Forbidden(
+            "Raw panel commands are off; start the server with --debug-epd".to_string(),
+        ));
+    }
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    let command = strip_hex_prefix(request.command.trim());
+    let command = u16::from_str_radix(command, 16)
+        .ok()
+        .filter(|_| !command.starts_with('+'))
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid command '{}'", request.command)))?;
+    let data = parse_hex(&request.data)
+        .ok_or_else(|| ApiError::BadRequest(format!("Invalid data '{}'", request.data)))?;
+
+    tracing::warn!(
+        "Sending raw panel command 0x{:02X} with {} data bytes",
+        command,
+        data.len()
+    );
+    let started = std::time::Instant::now();
+    state
+        .processor
+        .display()
+        .raw_command(command, &data)
+        .await?;
+    Ok(Json(EpdCommandResult {
+        command: format!("0x{:02X}", command),
+        data_bytes: data.len(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }))
+}
+
+/// Bytes of a hex string; groups may be separated by spaces or commas and
+/// start with `0x`
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for group in text.split([' ', ',']).filter(|group| !group.is_empty()) {
+        let digits = strip_hex_prefix(group);
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return None;
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            bytes.push(u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?);
+        }
+    }
+    Some(bytes)
+}
+
+fn strip_hex_prefix(text: &str) -> &str {
+    text.strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text)
+}
+
+/// POST /api/v1/message - Show a message instead of the scheduled content
+#[utoipa::path(
+    post,
+    path = "/api/v1/message",
+    tag = "display",
+    request_body = MessageRequest,
+    responses(
+        (status = 200, description = "Message shown or held back behind an alert", body = MessageInfo),
+        (status = 400, description = "Invalid message", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn message(
+    State(state): State<AppState>,
+    request: Result<Json<MessageRequest>, JsonRejection>,
+) -> Result<Json<MessageInfo>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+
+    let text = request.text.trim();
+    if text.is_empty() {
+        return Err(ApiError::BadRequest("The message is empty".to_string()));
+    }
+    if text.chars().count() > MAX_MESSAGE_CHARS {
+        return Err(ApiError::BadRequest(format!(
+            "The message is longer than {} characters",
+            MAX_MESSAGE_CHARS
+        )));
+    }
+
+    let config = state.config.read().await;
+    let duration_min = request.duration_min.unwrap_or(config.message_duration_min);
+    if !(1..=1440).contains(&duration_min) {
+        return Err(ApiError::BadRequest(
+            "duration_min must be between 1 and 1440".to_string(),
+        ));
+    }
+
+    let message = message::new(text, Duration::from_secs(duration_min as u64 * 60));
+    let until = message.until.unwrap_or_default().to_rfc3339();
+    let content = message.content_config(&config);
+    let shown = state.arbiter.set(message);
+    if shown {
+        state.processor.process_and_display(&content).await?;
+    }
+
+    Ok(Json(MessageInfo {
+        text: text.to_string(),
+        until,
+        shown,
+    }))
+}
+
+/// POST /api/v1/image - Show an image file instead of the scheduled content
+///
+/// Shown like a kiosk message: for `duration_min`, behind alerts, and
+/// ended early by `DELETE /api/v1/message`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/image",
+    tag = "display",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "Image file (PNG, JPEG, ...)"),
+    params(PushParams),
+    responses(
+        (status = 200, description = "Image shown or held back behind an alert", body = PushedInfo),
+        (status = 400, description = "Not an image, or too large", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn push_image(
+    State(state): State<AppState>,
+    Query(params): Query<PushParams>,
+    body: Bytes,
+) -> Result<Json<PushedInfo>, ApiError> {
+    let config = state.config.read().await;
+    let duration_min = params.duration_min.unwrap_or(config.message_duration_min);
+    if !(1..=1440).contains(&duration_min) {
+        return Err(ApiError::BadRequest(
+            "duration_min must be between 1 and 1440".to_string(),
+        ));
+    }
+    let image = PushedImage::new(body, &config)
+        .map_err(|e| ApiError::BadRequest(format!("Cannot show the image: {}", e)))?;
+    let (width, height) = (image.width, image.height);
+
+    let pushed = message::image(image, Duration::from_secs(duration_min as u64 * 60));
+    let until = pushed.until.unwrap_or_default().to_rfc3339();
+    let content = pushed.content_config(&config);
+    let shown = state.arbiter.set(pushed);
+    if shown {
+        state.processor.process_and_display(&content).await?;
+    }
+
+    Ok(Json(PushedInfo {
+        width,
+        height,
+        until,
+        shown,
+    }))
+}
+
+/// DELETE /api/v1/message - Remove the message and resume the schedule
+#[utoipa::path(
+    delete,
+    path = "/api/v1/message",
+    tag = "display",
+    responses(
+        (status = 200, description = "Scheduled content shown again", body = Done),
+        (status = 404, description = "No message is shown", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn cancel_message(State(state): State<AppState>) -> Result<Json<Done>, ApiError> {
+    if !state.arbiter.clear(Priority::Manual) {
+        return Err(ApiError::NotFound("No message is shown".to_string()));
+    }
+
+    // Under an alert the message was not on the panel
+    let shared = state.config.read().await;
+    let config = shared.scheduled_content();
+    if state.arbiter.active().is_none() && config.has_source() {
+        state.processor.process_and_display(&config).await?;
+    }
+
+    Ok(Json(Done {
+        message: "Message removed, schedule resumed".to_string(),
+    }))
+}
+
+/// POST /api/v1/demo - Cycle test patterns and sample images for a while
+///
+/// Replaces a running demo or a message. The config is left alone; the
+/// scheduled content returns once the demo ends.
+#[utoipa::path(
+    post,
+    path = "/api/v1/demo",
+    tag = "display",
+    request_body = DemoRequest,
+    responses(
+        (status = 200, description = "Demo started", body = DemoInfo),
+        (status = 400, description = "Invalid duration or step", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn start_demo(
+    State(state): State<AppState>,
+    request: Result<Json<DemoRequest>, JsonRejection>,
+) -> Result<Json<DemoInfo>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+
+    if !(1..=demo::MAX_DURATION_MIN).contains(&request.duration_min) {
+        return Err(ApiError::BadRequest(format!(
+            "duration_min must be between 1 and {}",
+            demo::MAX_DURATION_MIN
+        )));
+    }
+    if !(demo::MIN_STEP_SECS..=demo::MAX_STEP_SECS).contains(&request.step_secs) {
+        return Err(ApiError::BadRequest(format!(
+            "step_secs must be between {} and {}",
+            demo::MIN_STEP_SECS,
+            demo::MAX_STEP_SECS
+        )));
+    }
+
+    let until = Demo::new(
+        state.config.clone(),
+        state.processor.clone(),
+        state.arbiter.clone(),
+    )
+    .start(
+        Duration::from_secs(request.duration_min as u64 * 60),
+        Duration::from_secs(request.step_secs as u64),
+    );
+
+    Ok(Json(DemoInfo {
+        until: until.to_rfc3339(),
+        step_secs: request.step_secs,
+    }))
+}
+
+/// DELETE /api/v1/demo - End the demo and resume the schedule
+#[utoipa::path(
+    delete,
+    path = "/api/v1/demo",
+    tag = "display",
+    responses(
+        (status = 200, description = "Scheduled content shown again", body = Done),
+        (status = 404, description = "No demo is running", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn stop_demo(State(state): State<AppState>) -> Result<Json<Done>, ApiError> {
+    if !Demo::stop(&state.arbiter) {
+        return Err(ApiError::NotFound("No demo is running".to_string()));
+    }
+
+    // Under an alert the demo was not on the panel
+    let shared = state.config.read().await;
+    let config = shared.scheduled_content();
+    if state.arbiter.active().is_none() && config.has_source() {
+        state.processor.process_and_display(&config).await?;
+    }
+
+    Ok(Json(Done {
+        message: "Demo ended, schedule resumed".to_string(),
+    }))
+}
+
+/// POST /api/v1/alerts - Alertmanager webhook receiver
+///
+/// Firing alerts are shown until they are resolved. The panel is redrawn
+/// in the background so Alertmanager gets its answer right away.
+#[cfg(feature = "alerts")]
+#[utoipa::path(
+    post,
+    path = "/api/v1/alerts",
+    tag = "display",
+    request_body = AlertmanagerWebhook,
+    responses(
+        (status = 200, description = "Alerts updated", body = AlertsInfo),
+        (status = 400, description = "Invalid webhook payload", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn alerts(
+    State(state): State<AppState>,
+    request: Result<Json<AlertmanagerWebhook>, JsonRejection>,
+) -> Result<Json<AlertsInfo>, ApiError> {
+    let Json(webhook) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+
+    let mut firing = 0;
+    let mut had_alerts = false;
+    let shown = state.arbiter.modify(Priority::Alert, |current| {
+        let mut list = match current.map(|o| &o.source) {
+            Some(SourceConfig::Alerts(list)) => list.clone(),
+            _ => AlertList::default(),
+        };
+        had_alerts = !list.is_empty();
+        list.apply(&webhook);
+        firing = list.len();
+        (!list.is_empty()).then(|| list.into_override())
+    });
+
+    if shown || (had_alerts && firing == 0) {
+        tokio::spawn(async move {
+            let config = state.config.read().await;
+            let content = state.arbiter.content_config(&config);
+            let result = if shown {
+                // Alertmanager repeats unchanged alerts
+                state.processor.display_if_changed(&content).await
+            } else if content.has_source() {
+                state
+                    .processor
+                    .process_and_display(&content)
+                    .await
+                    .map(|_| true)
+            } else {
+                Ok(false)
+            };
+            if let Err(e) = result {
+                tracing::error!("Failed to display alerts: {}", e);
+            }
+        });
+    }
+
+    Ok(Json(AlertsInfo { firing, shown }))
+}
+
+/// POST /api/v1/mirror/frame - Frame from the leader of the mirror group
+///
+/// Only accepted with `mirror.role` set to `follower` and the group token
+/// as bearer token. The body is the packed frame in panel orientation, or
+/// empty with the frame uploaded in chunks before and named by
+/// `x-frame-sha256`; the panel refreshes at the start time, together with
+/// the others.
+#[utoipa::path(
+    post,
+    path = "/api/v1/mirror/frame",
+    tag = "display",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "Packed frame"),
+    params(
+        ("x-frame-width" = u32, Header, description = "Frame width in pixels"),
+        ("x-frame-height" = u32, Header, description = "Frame height in pixels"),
+        ("x-panel-model" = String, Header, description = "Panel model the frame is dithered for"),
+        ("x-show-at" = i64, Header, description = "Start of the refresh, Unix time in milliseconds"),
+        ("x-frame-sha256" = Option<String>, Header, description = "SHA-256 of a frame uploaded in chunks, instead of a body"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 202, description = "Frame accepted, shown at the start time"),
+        (status = 400, description = "Frame does not fit this panel, or its upload is incomplete", body = super::error::ErrorBody),
+        (status = 403, description = "Wrong group token", body = super::error::ErrorBody),
+        (status = 404, description = "Not a mirror group follower, or no such upload", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn mirror_frame(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, ApiError> {
+    let config = mirror_follower(&state, &headers).await?;
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ApiError::BadRequest(format!("Missing {} header", name)))
+    };
+    let number = |name: &str| {
+        header(name)?
+            .parse::<i64>()
+            .map_err(|_| ApiError::BadRequest(format!("Invalid {} header", name)))
+    };
+    let (width, height) = (
+        number(mirror::HEADER_WIDTH)?,
+        number(mirror::HEADER_HEIGHT)?,
+    );
+    let show_at = number(mirror::HEADER_SHOW_AT)?;
+    let model = header(mirror::HEADER_MODEL)?;
+
+    let display = state.processor.display();
+    let spec = display.spec();
+    if model != spec.model {
+        return Err(ApiError::BadRequest(format!(
+            "Frame is for a {} panel, this display has a {}",
+            model, spec.model
+        )));
+    }
+    // Frames come in panel orientation, so only the native size fits
+    if (width, height) != (i64::from(spec.width), i64::from(spec.height)) {
+        return Err(ApiError::BadRequest(format!(
+            "Frame of {}x{} does not fit this {}x{} panel",
+            width, height, spec.width, spec.height
+        )));
+    }
+    let (width, height) = (spec.width, spec.height);
+    let frame = match headers.get(mirror::HEADER_FRAME_SHA256) {
+        None => body.to_vec(),
+        Some(sha256) => {
+            let sha256 = sha256
+                .to_str()
+                .map_err(|_| ApiError::BadRequest("Invalid x-frame-sha256 header".to_string()))?;
+            match state.uploads.take(sha256, show_at).map_err(upload_error)? {
+                Some(frame) => frame,
+                // Repeated by the leader, already scheduled
+                None => return Ok(StatusCode::ACCEPTED),
+            }
+        }
+    };
+    let format = display.pixel_format().await?;
+    let expected = format.buffer_size(width, height);
+    if frame.len() != expected {
+        return Err(ApiError::BadRequest(format!(
+            "Frame of {}x{} needs {} bytes, got {}",
+            width,
+            height,
+            expected,
+            frame.len()
+        )));
+    }
+
+    let wait = mirror::wait_until(show_at);
+    tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        if let Err(e) = state
+            .processor
+            .show_mirrored_frame(&config, width, height, frame)
+            .await
+        {
+            tracing::error!("Failed to show the mirrored frame: {}", e);
+        }
+    });
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// GET /api/v1/mirror/chunks/{frame} - Chunks received of a frame upload
+///
+/// Tells the leader which chunks to send again after a broken upload.
+#[utoipa::path(
+    get,
+    path = "/api/v1/mirror/chunks/{frame}",
+    tag = "display",
+    params(("frame" = String, Path, description = "SHA-256 of the frame in hex")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Chunks received intact", body = mirror::UploadState),
+        (status = 403, description = "Wrong group token", body = super::error::ErrorBody),
+        (status = 404, description = "Not a mirror group follower, or no chunk of the frame received", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn mirror_upload(
+    State(state): State<AppState>,
+    Path(frame): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<mirror::UploadState>, ApiError> {
+    mirror_follower(&state, &headers).await?;
+    state
+        .uploads
+        .state(&frame.to_ascii_lowercase())
+        .map(Json)
+        .ok_or_else(|| upload_error(mirror::UploadError::Unknown(frame)))
+}
+
+/// PUT /api/v1/mirror/chunks/{frame}/{index} - Chunk of a frame upload
+///
+/// Chunks have `chunk_size` bytes (16 KiB), the last one the rest. A chunk
+/// that does not match `x-chunk-sha256` is refused and must be sent again.
+#[utoipa::path(
+    put,
+    path = "/api/v1/mirror/chunks/{frame}/{index}",
+    tag = "display",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream", description = "Chunk"),
+    params(
+        ("frame" = String, Path, description = "SHA-256 of the frame in hex"),
+        ("index" = usize, Path, description = "Chunk number, from 0"),
+        ("x-frame-size" = usize, Header, description = "Frame size in bytes"),
+        ("x-chunk-sha256" = String, Header, description = "SHA-256 of the chunk in hex"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Chunk stored"),
+        (status = 400, description = "Wrong size or checksum", body = super::error::ErrorBody),
+        (status = 403, description = "Wrong group token", body = super::error::ErrorBody),
+        (status = 404, description = "Not a mirror group follower", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn mirror_chunk(
+    State(state): State<AppState>,
+    Path((frame, index)): Path<(String, usize)>,
+    headers: HeaderMap,
+    chunk: Bytes,
+) -> Result<StatusCode, ApiError> {
+    mirror_follower(&state, &headers).await?;
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ApiError::BadRequest(format!("Missing {} header", name)))
+    };
+    let size = header(mirror::HEADER_FRAME_SIZE)?
+        .parse::<usize>()
+        .map_err(|_| ApiError::BadRequest("Invalid x-frame-size header".to_string()))?;
+    let checksum = header(mirror::HEADER_CHUNK_SHA256)?;
+    state
+        .uploads
+        .put(&frame, size, index, checksum, chunk)
+        .map_err(upload_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Config of a mirror group follower, if the request carries its token
+async fn mirror_follower(state: &AppState, headers: &HeaderMap) -> Result<Config, ApiError> {
+    let config = state.config.read().await.clone();
+    if !config.mirror.is_follower() {
+        return Err(ApiError::NotFound(
+            "This display is not a mirror group follower".to_string(),
+        ));
+    }
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !config.mirror.authorizes(authorization) {
+        return Err(ApiError::Forbidden("Wrong mirror group token".to_string()));
+    }
+    Ok(config)
+}
+
+fn upload_error(e: mirror::UploadError) -> ApiError {
+    match e {
+        mirror::UploadError::Unknown(_) => ApiError::NotFound(e.to_string()),
+        e => ApiError::BadRequest(e.to_string()),
+    }
+}
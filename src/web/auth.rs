@@ -0,0 +1,180 @@
+//! Web UI accounts and roles.
+//!
+//! Without accounts the UI is open, as before. Once `auth.users` lists at
+//! least one account, every page except `/health` and the public status
+//! card (`/status`) requires HTTP Basic authentication:
+//!
+//! - `viewer`: status page (read-only), live preview and status API
+//! - `admin`: additionally saving the config, display actions and the
+//!   debug bundle
+//!
+//! Passwords accept `${ENV:...}` / `${FILE:...}` references like other
+//! secrets.
+
+use super::error::{ApiError, wants_json};
+use super::routes::AppState;
+use super::templates;
+use crate::config::ConfigError;
+use crate::secrets;
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Realm shown in the browser login prompt
+const REALM: &str = "ePaper Display";
+
+/// Access level of an account, lowest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only status and preview
+    #[default]
+    Viewer,
+    /// Full access
+    Admin,
+}
+
+/// A UI account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Account {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// Web UI authentication settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuthConfig {
+    /// Accounts allowed to log in; empty leaves the UI open
+    #[serde(default)]
+    pub users: Vec<Account>,
+}
+
+impl AuthConfig {
+    /// Validate settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut names = HashSet::new();
+        for account in &self.users {
+            let name = account.username.trim();
+            if name.is_empty() || name.contains(':') {
+                return Err(ConfigError::ValidationError(format!(
+                    "Invalid username '{}'",
+                    account.username
+                )));
+            }
+            if !names.insert(name) {
+                return Err(ConfigError::ValidationError(format!(
+                    "Duplicate username '{}'",
+                    name
+                )));
+            }
+            if account.password.is_empty() {
+                return Err(ConfigError::ValidationError(format!(
+                    "Password for '{}' cannot be empty",
+                    name
+                )));
+            }
+        }
+
+        if !self.users.is_empty() && !self.users.iter().any(|a| a.role == Role::Admin) {
+            return Err(ConfigError::ValidationError(
+                "At least one account needs the admin role".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Role of the account matching an `Authorization` header value
+    fn authenticate(&self, authorization: Option<&str>) -> Option<Role> {
+        let encoded = authorization?.strip_prefix("Basic ")?;
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        self.users.iter().find_map(|account| {
+            let expected = secrets::resolve(&account.password)
+                .map_err(|e| {
+                    tracing::warn!("Password of '{}' unavailable: {}", account.username, e)
+                })
+                .ok()?;
+            (account.username.trim() == username
+                && secrets::constant_time_eq(expected.as_bytes(), password.as_bytes()))
+            .then_some(account.role)
+        })
+    }
+}
+
+/// Middleware for pages open to viewers and admins
+pub async fn require_viewer(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    authorize(&state, req, next, Role::Viewer).await
+}
+
+/// Middleware for pages that change configuration or the display
+pub async fn require_admin(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    authorize(&state, req, next, Role::Admin).await
+}
+
+/// Check credentials and make the caller's role available to handlers
+async fn authorize(state: &AppState, mut req: Request, next: Next, required: Role) -> Response {
+    let role = {
+        let config = state.config.read().await;
+        if config.auth.users.is_empty() {
+            Some(Role::Admin)
+        } else {
+            let authorization = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok());
+            config.auth.authenticate(authorization)
+        }
+    };
+
+    // API clients get machine-readable errors
+    let json = req.uri().path().starts_with("/api/") || wants_json(req.headers());
+
+    match role {
+        Some(role) if role >= required => {
+            req.extensions_mut().insert(role);
+            next.run(req).await
+        }
+        Some(_) if json => ApiError::Forbidden(
+            "This account can view the display status but not change it.".to_string(),
+        )
+        .into_response(),
+        Some(_) => (
+            StatusCode::FORBIDDEN,
+            Html(templates::render_message_page(
+                "Forbidden",
+                "This account can view the display status but not change it.",
+                true,
+            )),
+        )
+            .into_response(),
+        None => {
+            let challenge = [(
+                header::WWW_AUTHENTICATE,
+                format!("Basic realm=\"{}\", charset=\"UTF-8\"", REALM),
+            )];
+            if json {
+                (challenge, ApiError::Unauthorized).into_response()
+            } else {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    challenge,
+                    "Authentication required",
+                )
+                    .into_response()
+            }
+        }
+    }
+}
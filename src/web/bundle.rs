@@ -0,0 +1,132 @@
+//! Debug bundle for remote support.
+//!
+//! Packs everything needed to troubleshoot a device without shell access
+//! into one zip: the config (secrets redacted), recent log lines, the
+//! last frame sent to the panel and a status snapshot.
+
+use super::routes::AppState;
+use crate::logs;
+
+/// Build the debug bundle zip
+pub async fn build(state: &AppState) -> Vec<u8> {
+    let mut zip = ZipWriter::default();
+
+    let config = state
+        .config
+        .read()
+        .await
+        .to_redacted_json()
+        .unwrap_or_else(|e| format!("Failed to export config: {}", e));
+    zip.add("config.json", config.as_bytes());
+
+    let status = serde_json::to_string_pretty(&state.status.snapshot())
+        .unwrap_or_else(|e| format!("Failed to export status: {}", e));
+    zip.add("status.json", status.as_bytes());
+
+    let log: String = logs::recent()
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect();
+    zip.add("logs.txt", log.as_bytes());
+
+    if let Some(png) = state.processor.last_frame_png() {
+        zip.add("frame.png", &png);
+    }
+
+    zip.finish()
+}
+
+/// Minimal zip writer (stored entries, no compression)
+///
+/// The bundle is small and the frame is already a compressed PNG, so
+/// deflate would save little on the Pi Zero.
+#[derive(Default)]
+pub(crate) struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    /// Append a file
+    pub(crate) fn add(&mut self, name: &str, data: &[u8]) {
+        let offset = self.out.len() as u32;
+        let crc = crc32fast::hash(data);
+        let size = data.len() as u32;
+        let (time, date) = dos_timestamp(chrono::Local::now().naive_local());
+
+        // Local file header
+        put32(&mut self.out, 0x0403_4b50);
+        put16(&mut self.out, 20); // version needed
+        put16(&mut self.out, 0x0800); // UTF-8 names
+        put16(&mut self.out, 0); // stored
+        put16(&mut self.out, time);
+        put16(&mut self.out, date);
+        put32(&mut self.out, crc);
+        put32(&mut self.out, size);
+        put32(&mut self.out, size);
+        put16(&mut self.out, name.len() as u16);
+        put16(&mut self.out, 0); // extra length
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(data);
+
+        // Central directory entry
+        let c = &mut self.central;
+        put32(c, 0x0201_4b50);
+        put16(c, 20); // version made by
+        put16(c, 20); // version needed
+        put16(c, 0x0800);
+        put16(c, 0);
+        put16(c, time);
+        put16(c, date);
+        put32(c, crc);
+        put32(c, size);
+        put32(c, size);
+        put16(c, name.len() as u16);
+        put16(c, 0); // extra length
+        put16(c, 0); // comment length
+        put16(c, 0); // disk number
+        put16(c, 0); // internal attributes
+        put32(c, 0); // external attributes
+        put32(c, offset);
+        c.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+    }
+
+    /// Append the central directory and return the archive
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let offset = self.out.len() as u32;
+        let size = self.central.len() as u32;
+        self.out.append(&mut self.central);
+
+        // End of central directory
+        put32(&mut self.out, 0x0605_4b50);
+        put16(&mut self.out, 0); // disk number
+        put16(&mut self.out, 0); // disk with central directory
+        put16(&mut self.out, self.entries);
+        put16(&mut self.out, self.entries);
+        put32(&mut self.out, size);
+        put32(&mut self.out, offset);
+        put16(&mut self.out, 0); // comment length
+        self.out
+    }
+}
+
+fn put16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// MS-DOS time and date fields used by zip headers
+fn dos_timestamp(now: chrono::NaiveDateTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let year = now.year().clamp(1980, 2107) as u32 - 1980;
+    let date = ((year << 9) | (now.month() << 5) | now.day()) as u16;
+    (time, date)
+}
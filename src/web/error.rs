@@ -0,0 +1,182 @@
+//! Machine-readable errors for API clients.
+//!
+//! Failures are reported as `{"code": ..., "message": ..., "retryable": ...}`
+//! so automation can branch on the kind of failure. JSON API routes always
+//! use this form; the HTML pages use it when the request asks for JSON
+//! (`Accept: application/json`).
+
+use crate::display::DisplayError;
+use crate::image_proc::{DownloadError, ProcessingError};
+use crate::sources::SourceError;
+use axum::{
+    Json,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Failure of an API or web request
+#[derive(Error, Debug)]
+pub enum ApiError {
+    /// The image source could not be reached or answered with an error
+    #[error("{message}")]
+    Download { message: String, retryable: bool },
+
+    /// The source answered, but not with a usable image
+    #[error("{0}")]
+    Decode(String),
+
+    /// The panel is still busy with an earlier command
+    #[error("{0}")]
+    DisplayBusy(String),
+
+    /// Driving the panel failed
+    #[error("{0}")]
+    Display(String),
+
+    /// The configuration is invalid or incomplete
+    #[error("{0}")]
+    Config(String),
+
+    /// A request parameter is invalid
+    #[error("{0}")]
+    BadRequest(String),
+
+    /// The submitted changes must be resent with `confirmed` set
+    #[error("{0}")]
+    ConfirmationRequired(String),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    /// Unexpected failure on the device
+    #[cfg_attr(not(feature = "timelapse"), allow(dead_code))]
+    #[error("{0}")]
+    Internal(String),
+
+    #[error("Authentication required")]
+    Unauthorized,
+
+    #[error("{0}")]
+    Forbidden(String),
+}
+
+/// JSON body of an error response
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ErrorBody {
+    /// Stable identifier of the kind of failure
+    pub code: &'static str,
+    /// Human-readable description
+    pub message: String,
+    /// Whether the same request may succeed later
+    pub retryable: bool,
+}
+
+impl ApiError {
+    /// Stable identifier reported as `code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Download { .. } => "download",
+            ApiError::Decode(_) => "decode",
+            ApiError::DisplayBusy(_) => "display_busy",
+            ApiError::Display(_) => "display",
+            ApiError::Config(_) => "config",
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::ConfirmationRequired(_) => "confirmation_required",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Internal(_) => "internal",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+        }
+    }
+
+    /// Whether retrying the request later may succeed
+    pub fn retryable(&self) -> bool {
+        match self {
+            ApiError::Download { retryable, .. } => *retryable,
+            ApiError::DisplayBusy(_) => true,
+            _ => false,
+        }
+    }
+
+    /// HTTP status of the response
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Download { .. } | ApiError::Decode(_) => StatusCode::BAD_GATEWAY,
+            ApiError::DisplayBusy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Display(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Config(_) | ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::ConfirmationRequired(_) => StatusCode::CONFLICT,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+        }
+    }
+
+    /// Body reported to the client
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            retryable: self.retryable(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+impl From<DownloadError> for ApiError {
+    fn from(e: DownloadError) -> Self {
+        let retryable = match &e {
+            DownloadError::DecodeError(_)
+            | DownloadError::InvalidResponse(_)
+            | DownloadError::ChecksumMismatch { .. }
+            | DownloadError::TooLarge { .. }
+            | DownloadError::HtmlPage { .. } => {
+                return ApiError::Decode(e.to_string());
+            }
+            DownloadError::EmptyUrl => return ApiError::Config(e.to_string()),
+            DownloadError::HttpError { status } => *status == 429 || *status >= 500,
+            DownloadError::Login(_) => false,
+            DownloadError::RequestError(_) | DownloadError::Timeout => true,
+        };
+        ApiError::Download {
+            message: e.to_string(),
+            retryable,
+        }
+    }
+}
+
+impl From<DisplayError> for ApiError {
+    fn from(e: DisplayError) -> Self {
+        match e {
+            DisplayError::Timeout(_) => ApiError::DisplayBusy(e.to_string()),
+            e => ApiError::Display(e.to_string()),
+        }
+    }
+}
+
+impl From<ProcessingError> for ApiError {
+    fn from(e: ProcessingError) -> Self {
+        match e {
+            ProcessingError::Download(e) => e.into(),
+            ProcessingError::Display(e) => e.into(),
+            ProcessingError::NoImageUrl => ApiError::Config(e.to_string()),
+            ProcessingError::Source(SourceError::Download(e)) => e.into(),
+            ProcessingError::Source(e) => ApiError::Config(e.to_string()),
+        }
+    }
+}
+
+/// Whether the client asked for a JSON response
+pub fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"))
+}
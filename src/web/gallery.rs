@@ -0,0 +1,129 @@
+//! Gallery of previously displayed frames (`/history`).
+//!
+//! Lists the frames kept by [`FrameHistory`](crate::history::FrameHistory),
+//! newest first and grouped by day. With the `timelapse` feature the frames
+//! can also be downloaded as an animated GIF.
+
+#[cfg(feature = "timelapse")]
+use super::error::ApiError;
+#[cfg(feature = "timelapse")]
+use axum::extract::Query;
+use super::routes::AppState;
+use super::templates;
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+};
+
+/// GET /history - Gallery page
+pub async fn page(State(state): State<AppState>) -> Html<String> {
+    let keep = state.config.read().await.history_frames;
+    let entries = state
+        .processor
+        .history()
+        .map(|history| history.list())
+        .unwrap_or_default();
+    Html(templates::render_history_page(&entries, keep))
+}
+
+/// GET /history/:name - A stored frame
+pub async fn frame(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    match state
+        .processor
+        .history()
+        .and_then(|history| history.read(&name))
+    {
+        Some(png) => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                // Stored frames never change
+                (header::CACHE_CONTROL, "max-age=86400, immutable"),
+            ],
+            png,
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "No such frame").into_response(),
+    }
+}
+
+/// Parameters of `GET /api/v1/history/timelapse.gif`
+#[cfg(feature = "timelapse")]
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelapseQuery {
+    /// First day (`YYYY-MM-DD`), default: oldest stored frame
+    pub from: Option<String>,
+    /// Last day (`YYYY-MM-DD`), default: today
+    pub to: Option<String>,
+    /// Largest frame width in pixels (default 400)
+    pub width: Option<u32>,
+    /// Time each frame is shown in milliseconds (default 500)
+    pub delay_ms: Option<u32>,
+}
+
+/// GET /api/v1/history/timelapse.gif - Stored frames as an animated GIF
+#[cfg(feature = "timelapse")]
+#[utoipa::path(
+    get,
+    path = "/api/v1/history/timelapse.gif",
+    tag = "status",
+    params(TimelapseQuery),
+    responses(
+        (status = 200, description = "Animated GIF", content_type = "image/gif", body = Vec<u8>),
+        (status = 400, description = "Invalid parameter", body = super::error::ErrorBody),
+        (status = 404, description = "No frames in the range", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn timelapse(
+    State(state): State<AppState>,
+    Query(query): Query<TimelapseQuery>,
+) -> Result<Response, ApiError> {
+    let date = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(|v| {
+                chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                    .map_err(|_| ApiError::BadRequest(format!("Invalid date '{}'", v)))
+            })
+            .transpose()
+    };
+    let (from, to) = (date(&query.from)?, date(&query.to)?);
+    let width = query.width.unwrap_or(400);
+    let delay_ms = query.delay_ms.unwrap_or(500);
+    if !(16..=2000).contains(&width) {
+        return Err(ApiError::BadRequest(
+            "width must be between 16 and 2000".to_string(),
+        ));
+    }
+    if !(20..=60_000).contains(&delay_ms) {
+        return Err(ApiError::BadRequest(
+            "delay_ms must be between 20 and 60000".to_string(),
+        ));
+    }
+
+    let Some(history) = state.processor.history().cloned() else {
+        return Err(ApiError::NotFound("No frame history".to_string()));
+    };
+    // Decoding and encoding hundreds of frames takes a while on the Pi Zero
+    let gif = tokio::task::spawn_blocking(move || history.timelapse(from, to, width, delay_ms))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Timelapse task failed: {}", e)))?
+        .map_err(|e| ApiError::Internal(format!("Failed to encode timelapse: {}", e)))?
+        .ok_or_else(|| ApiError::NotFound("No frames in the requested range".to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/gif".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"epaper-timelapse-{}.gif\"",
+                    chrono::Local::now().format("%Y%m%d")
+                ),
+            ),
+        ],
+        gif,
+    )
+        .into_response())
+}
@@ -0,0 +1,168 @@
+//! Log access over HTTP (`/logs`, `/api/v1/logs`).
+//!
+//! Serves the lines kept by the [in-memory log buffer](crate::logs), and
+//! with `follow=true` keeps the connection open and streams new lines as
+//! server-sent events, so a device can be debugged from the browser
+//! (`new EventSource('/api/v1/logs?follow=true')`) or with
+//! `curl -N` without SSH access. `/logs` shows them as a page with a
+//! level filter, and `/api/v1/logs/filter` changes what is logged.
+
+use super::error::ApiError;
+use super::templates;
+use crate::logs::{self, LogLine};
+use axum::{
+    Json,
+    extract::{Query, rejection::JsonRejection},
+    response::{
+        Html, IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::stream::{self, StreamExt};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::Level;
+
+/// Lines shown by `/logs` unless asked otherwise
+const DEFAULT_PAGE_LINES: usize = 200;
+
+/// Parameters of `GET /api/v1/logs`
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogsQuery {
+    /// Keep the connection open and stream new lines as server-sent events
+    #[serde(default)]
+    pub follow: bool,
+}
+
+/// GET /api/v1/logs - Recent log lines, optionally followed live
+///
+/// With `follow=true` the recorded lines and then every new one are sent
+/// as `log` events, each with one line as JSON, until the client
+/// disconnects. A client that cannot keep up gets a comment saying how
+/// many lines it missed.
+#[utoipa::path(
+    get,
+    path = "/api/v1/logs",
+    tag = "status",
+    params(LogsQuery),
+    responses(
+        (status = 200, description = "Recorded lines, oldest first; an event stream (`text/event-stream`) with `follow=true`", body = Vec<LogLine>),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn logs(Query(query): Query<LogsQuery>) -> Response {
+    if !query.follow {
+        return Json(logs::recent()).into_response();
+    }
+
+    let (recent, live) = logs::follow();
+    let live = stream::unfold(live, |mut live| async move {
+        let event = match live.recv().await {
+            Ok(line) => log_event(&line),
+            Err(RecvError::Lagged(skipped)) => {
+                Event::default().comment(format!("{} lines skipped", skipped))
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok::<_, Infallible>(event), live))
+    });
+    let events = stream::iter(recent.iter().map(log_event).map(Ok).collect::<Vec<_>>()).chain(live);
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Parameters of `GET /logs`
+#[derive(Debug, serde::Deserialize)]
+pub struct LogsPageQuery {
+    /// Least severe level shown ("error" ... "trace"); empty shows all
+    pub level: Option<String>,
+    /// Number of newest lines shown
+    pub lines: Option<usize>,
+}
+
+/// GET /logs - Log viewer page
+pub async fn page(Query(query): Query<LogsPageQuery>) -> Html<String> {
+    let level = query
+        .level
+        .as_deref()
+        .and_then(|level| level.parse::<Level>().ok());
+    let limit = query
+        .lines
+        .unwrap_or(DEFAULT_PAGE_LINES)
+        .clamp(1, logs::CAPACITY);
+
+    // More verbose levels compare greater
+    let lines: Vec<LogLine> = logs::recent()
+        .into_iter()
+        .filter(|line| {
+            level.is_none_or(|level| line.level.parse::<Level>().is_ok_and(|l| l <= level))
+        })
+        .collect();
+    let lines = &lines[lines.len().saturating_sub(limit)..];
+    Html(templates::render_logs_page(lines, level, limit))
+}
+
+/// Filter directives added at runtime
+#[derive(Debug, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct LogFilter {
+    /// Comma-separated `target=level` directives as in `RUST_LOG`, e.g.
+    /// `display=debug`; bare module names refer to this service. Empty
+    /// for none
+    pub directives: String,
+}
+
+/// GET /api/v1/logs/filter - Filter directives added at runtime
+#[utoipa::path(
+    get,
+    path = "/api/v1/logs/filter",
+    tag = "status",
+    responses(
+        (status = 200, description = "Directives in effect besides the startup log level", body = LogFilter),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn filter() -> Json<LogFilter> {
+    Json(LogFilter {
+        directives: logs::directives(),
+    })
+}
+
+/// PUT /api/v1/logs/filter - Change what is logged without a restart
+///
+/// The directives are added to the startup log level of both the journal
+/// and the in-memory buffer, replacing the ones set before, and last until
+/// the service restarts.
+#[utoipa::path(
+    put,
+    path = "/api/v1/logs/filter",
+    tag = "status",
+    request_body = LogFilter,
+    responses(
+        (status = 200, description = "Directives now in effect", body = LogFilter),
+        (status = 400, description = "Invalid directive", body = super::error::ErrorBody),
+        (status = 403, description = "Admin role required", body = super::error::ErrorBody),
+    ),
+)]
+pub async fn set_filter(
+    request: Result<Json<LogFilter>, JsonRejection>,
+) -> Result<Json<LogFilter>, ApiError> {
+    let Json(request) = request.map_err(|e| ApiError::BadRequest(e.body_text()))?;
+    logs::set_directives(&request.directives).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let directives = logs::directives();
+    if directives.is_empty() {
+        tracing::info!("Log filter reset to the startup level");
+    } else {
+        tracing::info!("Log filter changed: {}", directives);
+    }
+    Ok(Json(LogFilter { directives }))
+}
+
+/// Server-sent event carrying `line`
+fn log_event(line: &LogLine) -> Event {
+    Event::default()
+        .event("log")
+        .data(serde_json::to_string(line).unwrap_or_default())
+}
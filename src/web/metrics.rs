@@ -0,0 +1,146 @@
+//! Prometheus metrics (`/metrics`).
+//!
+//! Device health in the Prometheus text format, for fleet dashboards.
+//! Failed refreshes are counted by cause, so network problems (DNS, TLS,
+//! HTTP) can be told apart from panel failures (SPI, busy timeouts)
+//! across many devices. Counters start at zero with every start of the
+//! service.
+
+use super::routes::AppState;
+use crate::status::StatusTracker;
+use axum::{extract::State, http::header, response::IntoResponse};
+use chrono::DateTime;
+use std::fmt::Write;
+
+/// Content type of the Prometheus text format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// GET /metrics - Device health in the Prometheus text format
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, CONTENT_TYPE)],
+        render(&state.status),
+    )
+}
+
+/// Metrics of `status` in the Prometheus text format
+pub fn render(status: &StatusTracker) -> String {
+    let snapshot = status.snapshot();
+    let mut out = String::new();
+
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, f64)]| {
+        let _ = writeln!(out, "# HELP epaper_{} {}", name, help);
+        let _ = writeln!(out, "# TYPE epaper_{} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(out, "epaper_{}{} {}", name, labels, value);
+        }
+    };
+    let single = |value: f64| [(String::new(), value)];
+
+    metric(
+        "build_info",
+        "gauge",
+        "Version and name of the running service",
+        &[(
+            format!(
+                "{{version=\"{}\",device=\"{}\"}}",
+                snapshot.version,
+                label_value(&snapshot.device_name)
+            ),
+            1.0,
+        )],
+    );
+    metric(
+        "uptime_seconds",
+        "gauge",
+        "Seconds since the service started",
+        &single(snapshot.uptime_secs as f64),
+    );
+    metric(
+        "restarts",
+        "gauge",
+        "Starts recorded in the state directory",
+        &single(snapshot.restart_count as f64),
+    );
+    metric(
+        "refreshes_today",
+        "gauge",
+        "Full panel refreshes since local midnight",
+        &single(snapshot.refreshes_today as f64),
+    );
+    if let Some(at) = timestamp(snapshot.last_refresh_at.as_deref()) {
+        metric(
+            "last_refresh_timestamp_seconds",
+            "gauge",
+            "Unix time of the last full panel refresh",
+            &single(at),
+        );
+    }
+    if let Some(at) = timestamp(snapshot.last_error.as_ref().map(|e| e.at.as_str())) {
+        metric(
+            "last_error_timestamp_seconds",
+            "gauge",
+            "Unix time of the last failed refresh",
+            &single(at),
+        );
+    }
+    if let Some(health) = &snapshot.source_health {
+        metric(
+            "source_healthy",
+            "gauge",
+            "Whether the last image source check succeeded",
+            &single(flag(health.healthy)),
+        );
+    }
+    metric(
+        "clock_synchronized",
+        "gauge",
+        "Whether the system clock looks synchronized",
+        &single(flag(snapshot.clock_synchronized)),
+    );
+    metric(
+        "refreshing",
+        "gauge",
+        "Whether the panel is being updated right now",
+        &single(flag(snapshot.refreshing)),
+    );
+
+    let errors: Vec<(String, f64)> = status
+        .error_counts()
+        .into_iter()
+        .map(|(category, count)| {
+            (
+                format!("{{category=\"{}\"}}", category.as_str()),
+                count as f64,
+            )
+        })
+        .collect();
+    metric(
+        "errors_total",
+        "counter",
+        "Failed refreshes since start, by cause",
+        &errors,
+    );
+
+    out
+}
+
+/// `value` escaped for a label of the text format
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Gauge value of a yes/no state
+fn flag(on: bool) -> f64 {
+    if on { 1.0 } else { 0.0 }
+}
+
+/// Unix time of a local RFC 3339 time
+fn timestamp(time: Option<&str>) -> Option<f64> {
+    DateTime::parse_from_rfc3339(time?)
+        .ok()
+        .map(|time| time.timestamp() as f64)
+}
@@ -2,13 +2,29 @@
 //!
 //! Provides an HTTP server using Axum for the configuration web interface.
 
+pub mod api;
+pub mod auth;
+pub mod bundle;
+pub mod gallery;
+pub mod error;
+pub mod logs;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod openapi;
+pub mod public;
 pub mod routes;
 pub mod templates;
 
-use crate::config::Config;
+use crate::config::{self, Config};
 use crate::display::DisplayController;
+use crate::history::FrameHistory;
 use crate::image_proc::ImageProcessor;
-use axum::{routing::get, Router};
+use crate::mirror;
+use crate::outputs::Outputs;
+use crate::sources::pushed;
+use crate::arbiter::ContentArbiter;
+use crate::status::StatusTracker;
+use axum::{extract::DefaultBodyLimit, middleware, routing::get, Router};
 use routes::AppState;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -31,18 +47,46 @@ pub struct WebServer {
     config: Arc<RwLock<Config>>,
     processor: Arc<ImageProcessor>,
     config_path: String,
+    status: Arc<StatusTracker>,
+    arbiter: Arc<ContentArbiter>,
+    uploads: Arc<mirror::Uploads>,
+    outputs: Arc<Outputs>,
+    /// Accept raw panel commands (`--debug-epd`)
+    debug_epd: bool,
 }
 
 impl WebServer {
     /// Create a new web server
-    pub fn new(config: Config, display: DisplayController, config_path: String) -> Self {
+    pub fn new(
+        config: Config,
+        display: DisplayController,
+        config_path: String,
+        status: Arc<StatusTracker>,
+    ) -> Self {
+        let history = FrameHistory::new(&config::state_dir(&config_path));
+        status.set_device_name(config.device_name());
         Self {
             config: Arc::new(RwLock::new(config)),
-            processor: Arc::new(ImageProcessor::new(display)),
+            processor: Arc::new(
+                ImageProcessor::new(display)
+                    .with_history(history)
+                    .with_status(Arc::clone(&status)),
+            ),
             config_path,
+            status: Arc::clone(&status),
+            arbiter: Arc::new(ContentArbiter::default().with_status(status)),
+            uploads: Arc::default(),
+            outputs: Arc::default(),
+            debug_epd: false,
         }
     }
 
+    /// Accept raw panel controller commands on `/api/v1/debug/epd`
+    pub fn with_debug_epd(mut self, enabled: bool) -> Self {
+        self.debug_epd = enabled;
+        self
+    }
+
     /// Get shared config reference for scheduler
     pub fn config(&self) -> Arc<RwLock<Config>> {
         Arc::clone(&self.config)
@@ -53,20 +97,122 @@ impl WebServer {
         Arc::clone(&self.processor)
     }
 
+    /// Get shared content arbiter reference for scheduler
+    pub fn arbiter(&self) -> Arc<ContentArbiter> {
+        Arc::clone(&self.arbiter)
+    }
+
+    /// Get shared GPIO outputs reference for scheduler
+    pub fn outputs(&self) -> Arc<Outputs> {
+        Arc::clone(&self.outputs)
+    }
+
     /// Build the router with all routes
-    fn build_router(&self) -> Router {
+    pub(crate) fn build_router(&self) -> Router {
         let state = AppState {
             config: Arc::clone(&self.config),
             processor: Arc::clone(&self.processor),
             config_path: self.config_path.clone(),
+            status: Arc::clone(&self.status),
+            arbiter: Arc::clone(&self.arbiter),
+            uploads: Arc::clone(&self.uploads),
+            outputs: Arc::clone(&self.outputs),
+            debug_epd: self.debug_epd,
         };
 
-        Router::new()
-            .route("/", get(routes::index))
+        let admin = Router::new()
             .route("/save", axum::routing::post(routes::save_config))
             .route("/apply", axum::routing::post(routes::save_and_apply))
             .route("/action/:action", get(routes::display_action))
+            .route("/api/v1/debug/bundle", get(api::debug_bundle))
+            .route("/api/v1/debug/epd", axum::routing::post(api::epd_command))
+            .route("/logs", get(logs::page))
+            .route("/api/v1/logs", get(logs::logs))
+            .route(
+                "/api/v1/logs/filter",
+                get(logs::filter).put(logs::set_filter),
+            )
+            .route(
+                "/api/v1/message",
+                axum::routing::post(api::message).delete(api::cancel_message),
+            )
+            .route(
+                "/api/v1/demo",
+                axum::routing::post(api::start_demo).delete(api::stop_demo),
+            )
+            .route(
+                "/api/v1/image",
+                axum::routing::post(api::push_image)
+                    .layer(DefaultBodyLimit::max(pushed::MAX_IMAGE_BYTES)),
+            )
+            .route("/api/v1/schedule/plans", axum::routing::post(api::add_plan))
+            .route(
+                "/api/v1/schedule/assignments",
+                axum::routing::put(api::set_day_assignments).post(api::assign_days),
+            )
+            .route("/api/v1/vacation", axum::routing::put(api::set_vacation))
+            .route("/api/v1/outputs/:name", axum::routing::put(api::set_output));
+        #[cfg(feature = "alerts")]
+        let admin = admin.route("/api/v1/alerts", axum::routing::post(api::alerts));
+        let admin = admin.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_admin,
+        ));
+
+        let viewer = Router::new()
+            .route("/", get(routes::index))
+            .route("/preview.png", get(routes::preview))
+            .route("/history", get(gallery::page))
+            .route("/history/:name", get(gallery::frame))
+            .route("/api/v1/status", get(api::status))
+            .route("/api/v1/display", get(api::display))
+            .route("/api/v1/peers", get(api::peers))
+            .route("/api/v1/schedule/simulate", get(api::simulate_schedule))
+            .route("/api/v1/schedule.ics", get(api::schedule_ics))
+            .route("/api/v1/schedule/presets", get(api::schedule_presets))
+            .route("/api/v1/schedule/assignments", get(api::day_assignments))
+            .route("/api/v1/vacation", get(api::vacation))
+            .route("/api/v1/outputs", get(api::outputs))
+            .route("/api/v1/openapi.json", get(openapi::spec))
+            .route("/api/docs", get(openapi::docs));
+        #[cfg(feature = "timelapse")]
+        let viewer = viewer.route("/api/v1/history/timelapse.gif", get(gallery::timelapse));
+        #[cfg(feature = "metrics")]
+        let viewer = viewer.route("/metrics", get(metrics::metrics));
+        let viewer = viewer
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::require_viewer,
+            ));
+
+        let public = Router::new()
+            .route("/status", get(public::status_page))
+            .route("/status/thumbnail.png", get(public::thumbnail))
+            .route_layer(middleware::from_fn_with_state(
+                Arc::new(public::RateLimiter::default()),
+                public::rate_limit,
+            ));
+
+        Router::new()
+            .merge(admin)
+            .merge(viewer)
+            .merge(public)
             .route("/health", get(routes::health))
+            // Authorized with the mirror group token instead of an account
+            .route(
+                mirror::FRAME_PATH,
+                axum::routing::post(api::mirror_frame)
+                    .layer(DefaultBodyLimit::max(mirror::MAX_FRAME_BYTES)),
+            )
+            .route(
+                &format!("{}/:frame", mirror::CHUNKS_PATH),
+                get(api::mirror_upload),
+            )
+            .route(
+                &format!("{}/:frame/:index", mirror::CHUNKS_PATH),
+                axum::routing::put(api::mirror_chunk)
+                    .layer(DefaultBodyLimit::max(mirror::CHUNK_SIZE)),
+            )
             .with_state(state)
     }
 
@@ -0,0 +1,126 @@
+//! OpenAPI description of the JSON API.
+//!
+//! The document is generated from the handler annotations and served at
+//! `/api/v1/openapi.json`. `/api/docs` is a Swagger UI page for it; the
+//! UI itself is loaded from a CDN, so the browser needs internet access.
+
+use super::error::ErrorBody;
+use super::{api, routes};
+use axum::{Json, response::Html};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// The API document
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ePaper Display API",
+        description = "Status and control of the e-paper display. When `auth.users` is configured, \
+                       every endpoint except `/health` needs HTTP Basic credentials."
+    ),
+    paths(
+        api::status,
+        api::display,
+        api::peers,
+        api::debug_bundle,
+        api::epd_command,
+        super::logs::logs,
+        super::logs::filter,
+        super::logs::set_filter,
+        api::message,
+        api::cancel_message,
+        api::push_image,
+        api::start_demo,
+        api::stop_demo,
+        api::mirror_frame,
+        api::mirror_upload,
+        api::mirror_chunk,
+        api::simulate_schedule,
+        api::schedule_ics,
+        api::schedule_presets,
+        api::add_plan,
+        api::day_assignments,
+        api::set_day_assignments,
+        api::assign_days,
+        api::vacation,
+        api::set_vacation,
+        api::outputs,
+        api::set_output,
+        routes::display_action,
+        routes::health
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&BasicAuth),
+    security(("basic" = [])),
+    tags(
+        (name = "status", description = "Device health and diagnostics"),
+        (name = "display", description = "Panel specs and actions"),
+        (name = "schedule", description = "Refresh schedule"),
+        (name = "outputs", description = "Auxiliary GPIO outputs")
+    )
+)]
+pub struct ApiDoc;
+
+/// Endpoints of optional features
+#[cfg(feature = "timelapse")]
+#[derive(OpenApi)]
+#[openapi(paths(super::gallery::timelapse))]
+struct TimelapseDoc;
+
+#[cfg(feature = "alerts")]
+#[derive(OpenApi)]
+#[openapi(paths(super::api::alerts))]
+struct AlertsDoc;
+
+/// Registers the HTTP Basic scheme of the web UI accounts, and the
+/// bearer token of mirror groups
+struct BasicAuth;
+
+impl Modify for BasicAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Swagger UI page pointing at the document
+const DOCS_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>ePaper Display API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        SwaggerUIBundle({ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>
+"##;
+
+/// GET /api/v1/openapi.json - OpenAPI document
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    #[allow(unused_mut)]
+    let mut doc = ApiDoc::openapi();
+    #[cfg(feature = "timelapse")]
+    doc.merge(TimelapseDoc::openapi());
+    #[cfg(feature = "alerts")]
+    doc.merge(AlertsDoc::openapi());
+    Json(doc)
+}
+
+/// GET /api/docs - Swagger UI
+pub async fn docs() -> Html<&'static str> {
+    Html(DOCS_PAGE)
+}
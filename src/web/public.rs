@@ -0,0 +1,110 @@
+//! Public status card (`/status`).
+//!
+//! A read-only page with the last and next refresh, a thumbnail of the
+//! current image and basic device health. It needs no login and shows
+//! nothing from the config (URLs, tokens, schedules), so it can be linked
+//! from a wiki or dashboard.
+//!
+//! Requests are rate limited across all clients, so a busy page or a
+//! misbehaving crawler cannot keep the Pi busy encoding thumbnails.
+
+use super::routes::AppState;
+use super::templates;
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Requests allowed per window, shared by all clients
+pub const REQUESTS_PER_WINDOW: u32 = 30;
+
+/// Rate limit window
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+/// Width of the thumbnail on the status card
+const THUMBNAIL_WIDTH: u32 = 400;
+
+/// Fixed-window request counter
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    /// Start of the current window and requests counted in it
+    state: Mutex<(Instant, u32)>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            state: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Count a request; returns how long to wait if the limit is reached
+    fn check(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (started, count) = &mut *state;
+
+        let elapsed = started.elapsed();
+        if elapsed >= self.window {
+            *started = Instant::now();
+            *count = 0;
+        } else if *count >= self.limit {
+            return Err(self.window - elapsed);
+        }
+
+        *count += 1;
+        Ok(())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(REQUESTS_PER_WINDOW, WINDOW)
+    }
+}
+
+/// Middleware answering 429 once the public pages hit the rate limit
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limiter.check() {
+        Ok(()) => next.run(request).await,
+        Err(wait) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, wait.as_secs().max(1).to_string())],
+            "Too many requests, try again later",
+        )
+            .into_response(),
+    }
+}
+
+/// GET /status - Public status card
+pub async fn status_page(State(state): State<AppState>) -> Html<String> {
+    let snapshot = state.status.snapshot();
+    let spec = state.processor.display().spec();
+    Html(templates::render_status_card(&snapshot, spec.model))
+}
+
+/// GET /status/thumbnail.png - Scaled-down copy of the current image
+pub async fn thumbnail(State(state): State<AppState>) -> Response {
+    match state.processor.last_frame_thumbnail(THUMBNAIL_WIDTH) {
+        Some(png) => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                (header::CACHE_CONTROL, "max-age=60"),
+            ],
+            png,
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "Nothing displayed yet").into_response(),
+    }
+}
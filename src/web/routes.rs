@@ -1,13 +1,24 @@
 //! HTTP route handlers for the web interface.
 
+use super::auth::Role;
+use super::error::{ApiError, ErrorBody, wants_json};
 use super::templates;
-use crate::config::{Config, DayAssignments, SchedulePeriod, SchedulePlan, Weekday};
+use crate::config::{
+    AlternateWeeks, Config, DayAssignments, SchedulePeriod, SchedulePlan, Weekday,
+};
 use crate::image_proc::ImageProcessor;
+use crate::mirror;
+use crate::outputs::Outputs;
+use crate::arbiter::ContentArbiter;
+use crate::status::StatusTracker;
+use crate::vacation::VacationConfig;
 use axum::{
-    extract::{Form, Path, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    Json,
+    extract::{Extension, Form, Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{Html, IntoResponse, Response},
 };
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -18,11 +29,34 @@ pub struct AppState {
     pub config: Arc<RwLock<Config>>,
     pub processor: Arc<ImageProcessor>,
     pub config_path: String,
+    pub status: Arc<StatusTracker>,
+    pub arbiter: Arc<ContentArbiter>,
+    /// Frames uploaded by the leader of the mirror group
+    pub uploads: Arc<mirror::Uploads>,
+    /// Auxiliary GPIO outputs
+    pub outputs: Arc<Outputs>,
+    /// Raw panel commands are accepted (`--debug-epd`)
+    pub debug_epd: bool,
 }
 
 /// Form data is captured as a HashMap to handle dynamic schedule fields
 type FormData = HashMap<String, String>;
 
+/// JSON body of a successful action or save
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct Done {
+    pub message: String,
+}
+
+impl Done {
+    fn json(message: impl Into<String>) -> Response {
+        Json(Self {
+            message: message.into(),
+        })
+        .into_response()
+    }
+}
+
 fn default_display_width() -> u32 {
     800
 }
@@ -36,6 +70,28 @@ fn default_display_height() -> u32 {
 struct PlansFormData {
     plans: Vec<PlanData>,
     day_assignments: HashMap<String, String>,
+    /// Missing if the client does not know alternate weeks; they are kept
+    #[serde(default)]
+    alternate_weeks: Option<AlternateWeeksData>,
+    /// Missing if the client does not know vacation mode; it is kept
+    #[serde(default)]
+    vacation: Option<VacationConfig>,
+}
+
+#[derive(serde::Deserialize)]
+struct AlternateWeeksData {
+    /// Empty to turn alternate weeks off
+    b_weeks: String,
+    day_assignments: HashMap<String, String>,
+}
+
+/// Schedule settings of the form
+struct PlansForm {
+    plans: Vec<SchedulePlan>,
+    day_assignments: DayAssignments,
+    /// `None` if not part of the form
+    alternate_weeks: Option<Option<AlternateWeeks>>,
+    vacation: Option<VacationConfig>,
 }
 
 #[derive(serde::Deserialize)]
@@ -52,7 +108,7 @@ struct PeriodData {
 }
 
 /// Parse schedule plans from form data
-fn parse_plans_from_form(form: &FormData) -> Result<(Vec<SchedulePlan>, DayAssignments), String> {
+fn parse_plans_from_form(form: &FormData) -> Result<PlansForm, String> {
     let plans_json = form
         .get("plans_json")
         .ok_or("Missing schedule plans data")?;
@@ -89,92 +145,224 @@ fn parse_plans_from_form(form: &FormData) -> Result<(Vec<SchedulePlan>, DayAssig
         day_assignments.insert(*day, plan_name);
     }
 
-    Ok((plans, day_assignments))
+    let alternate_weeks = data.alternate_weeks.map(|weeks| {
+        let b_weeks = weeks.b_weeks.trim();
+        (!b_weeks.is_empty()).then(|| AlternateWeeks {
+            b_weeks: b_weeks.to_string(),
+            day_assignments: Weekday::all()
+                .iter()
+                .filter_map(|day| {
+                    let plan = weeks.day_assignments.get(day.short_name())?;
+                    (!plan.is_empty()).then(|| (*day, plan.clone()))
+                })
+                .collect(),
+        })
+    });
+
+    Ok(PlansForm {
+        plans,
+        day_assignments,
+        alternate_weeks,
+        vacation: data.vacation,
+    })
 }
 
 /// GET / - Main configuration page
-pub async fn index(State(state): State<AppState>) -> Html<String> {
+pub async fn index(
+    State(state): State<AppState>,
+    Extension(role): Extension<Role>,
+) -> Html<String> {
     let config = state.config.read().await;
-    Html(templates::render_config_page(&config, None))
+    Html(templates::render_config_page(&config, None, role))
+}
+
+/// GET /preview.png - Last frame sent to the panel
+pub async fn preview(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    match state.processor.last_frame_png() {
+        Some(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        None if wants_json(&headers) => {
+            ApiError::NotFound("Nothing displayed yet".to_string()).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "Nothing displayed yet").into_response(),
+    }
 }
 
 /// POST /save - Save configuration
 pub async fn save_config(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Form(form): Form<FormData>,
-) -> impl IntoResponse {
+) -> Response {
+    let json = wants_json(&headers);
     match update_config(&state, &form).await {
-        Ok(_) => {
+        Ok(Update::Confirm(changes)) if json => confirmation_required(&changes),
+        Ok(Update::Confirm(changes)) => {
+            Html(templates::render_confirm_page(&changes, &form, "/save")).into_response()
+        }
+        Ok(Update::Saved { .. }) if json => Done::json("Configuration saved"),
+        Ok(Update::Saved { .. }) => {
             let config = state.config.read().await;
-            Html(templates::render_config_page(&config, Some("Configuration saved!")))
+            Html(templates::render_config_page(
+                &config,
+                Some("Configuration saved!"),
+                Role::Admin,
+            ))
+            .into_response()
         }
+        Err(e) if json => ApiError::Config(e).into_response(),
         Err(e) => {
             let config = state.config.read().await;
             Html(templates::render_config_page(
                 &config,
                 Some(&format!("Error: {}", e)),
+                Role::Admin,
             ))
+            .into_response()
         }
     }
 }
 
+/// Error telling API clients to resend the form with `confirmed` set
+fn confirmation_required(changes: &[String]) -> Response {
+    ApiError::ConfirmationRequired(format!(
+        "Resend with confirmed=1 to apply: {}",
+        changes.join(", ")
+    ))
+    .into_response()
+}
+
 /// POST /apply - Save configuration and refresh display
 pub async fn save_and_apply(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Form(form): Form<FormData>,
-) -> impl IntoResponse {
+) -> Response {
+    let json = wants_json(&headers);
+
     // Save config first
-    if let Err(e) = update_config(&state, &form).await {
-        let config = state.config.read().await;
-        return Html(templates::render_config_page(
-            &config,
-            Some(&format!("Error saving: {}", e)),
-        ));
-    }
+    let orientation_only = match update_config(&state, &form).await {
+        Ok(Update::Saved { orientation_only }) => orientation_only,
+        Ok(Update::Confirm(changes)) if json => return confirmation_required(&changes),
+        Ok(Update::Confirm(changes)) => {
+            return Html(templates::render_confirm_page(&changes, &form, "/apply"))
+                .into_response();
+        }
+        Err(e) if json => return ApiError::Config(e).into_response(),
+        Err(e) => {
+            let config = state.config.read().await;
+            return Html(templates::render_config_page(
+                &config,
+                Some(&format!("Error saving: {}", e)),
+                Role::Admin,
+            ))
+            .into_response();
+        }
+    };
 
-    // Apply to display
+    // Apply to display; a new orientation alone reuses the last frame
     let config = state.config.read().await;
-    match state.processor.process_and_display(&config).await {
-        Ok(_) => Html(templates::render_config_page(
-            &config,
-            Some("Configuration saved and applied!"),
-        )),
+    let reoriented = if orientation_only {
+        state.processor.reorient(&config).await
+    } else {
+        Ok(false)
+    };
+    let result = match reoriented {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            state
+                .processor
+                .process_and_display(&config.scheduled_content())
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => {
+            let message = match budget_warning(&state, &config) {
+                Some(warning) => format!("Configuration saved and applied! {}", warning),
+                None => "Configuration saved and applied!".to_string(),
+            };
+            if json {
+                return Done::json(message);
+            }
+            Html(templates::render_config_page(
+                &config,
+                Some(&message),
+                Role::Admin,
+            ))
+            .into_response()
+        }
+        Err(e) if json => ApiError::from(e).into_response(),
         Err(e) => Html(templates::render_config_page(
             &config,
             Some(&format!("Saved, but display error: {}", e)),
-        )),
+            Role::Admin,
+        ))
+        .into_response(),
     }
 }
 
 /// GET /action/:action - Display actions
+#[utoipa::path(
+    get,
+    path = "/action/{action}",
+    tag = "display",
+    params(
+        ("action" = String, Path, description = "`show` (fetch and display), `test` (test pattern) or `clear`"),
+        ("Accept" = String, Header, description = "`application/json` for JSON responses instead of HTML"),
+    ),
+    responses(
+        (status = 200, description = "Action completed", body = Done),
+        (status = 404, description = "Unknown action", body = ErrorBody),
+        (status = 502, description = "Image source failed (`download`, `decode`)", body = ErrorBody),
+        (status = 500, description = "Panel failed (`display`)", body = ErrorBody),
+        (status = 503, description = "Panel busy (`display_busy`)", body = ErrorBody),
+    ),
+)]
 pub async fn display_action(
     State(state): State<AppState>,
     Path(action): Path<String>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+) -> Response {
+    let json = wants_json(&headers);
+    let config = state.config.read().await;
     let result = match action.as_str() {
         "show" => {
-            let config = state.config.read().await;
-            state.processor.process_and_display(&config).await
+            state
+                .processor
+                .process_and_display(&config.scheduled_content())
+                .await
+        }
+        "test" => state.processor.show_test_pattern(&config).await,
+        "clear" => state.processor.clear_display(&config).await,
+        _ if json => {
+            return ApiError::NotFound(format!("Unknown action '{}'", action)).into_response();
         }
-        "test" => state.processor.show_test_pattern().await,
-        "clear" => state.processor.clear_display().await,
         _ => {
             return (
                 StatusCode::NOT_FOUND,
                 Html(templates::render_message_page("Not Found", "Unknown action", true)),
-            );
+            )
+                .into_response();
         }
     };
 
     match result {
-        Ok(_) => (
-            StatusCode::OK,
-            Html(templates::render_message_page(
-                "Success",
-                &format!("Action '{}' completed successfully!", action),
-                true,
-            )),
-        ),
+        Ok(_) => {
+            let mut message = format!("Action '{}' completed successfully!", action);
+            if let Some(warning) = budget_warning(&state, &config) {
+                message = format!("{} {}", message, warning);
+            }
+            if json {
+                return Done::json(message);
+            }
+            (
+                StatusCode::OK,
+                Html(templates::render_message_page("Success", &message, true)),
+            )
+                .into_response()
+        }
+        Err(e) if json => ApiError::from(e).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Html(templates::render_message_page(
@@ -182,11 +370,32 @@ pub async fn display_action(
                 &format!("Action failed: {}", e),
                 true,
             )),
-        ),
+        )
+            .into_response(),
     }
 }
 
+/// Warning for the page after a manual refresh went over the daily budget
+///
+/// Manual refreshes always run; the processor counts them.
+fn budget_warning(state: &AppState, config: &Config) -> Option<String> {
+    let refreshes = state.status.refreshes_today();
+    config.refresh_budget_exceeded(refreshes).then(|| {
+        format!(
+            "Warning: daily refresh budget exceeded ({}/{}), scheduled refreshes are paused until midnight.",
+            refreshes, config.max_refreshes_per_day
+        )
+    })
+}
+
 /// Health check endpoint
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "status",
+    security(()),
+    responses((status = 200, description = "Server is up", body = String, example = "OK")),
+)]
 pub async fn health() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
@@ -203,11 +412,46 @@ fn parse_form_field<T: std::str::FromStr>(form: &FormData, key: &str, default: T
         .unwrap_or(default)
 }
 
+/// Outcome of a submitted configuration form
+enum Update {
+    /// Validated and saved; `orientation_only` if only the rotation or
+    /// mirroring of the content changed
+    Saved { orientation_only: bool },
+    /// Valid, but the listed changes need confirming first
+    Confirm(Vec<String>),
+}
+
 /// Update configuration from form data
-async fn update_config(state: &AppState, form: &FormData) -> Result<(), String> {
+///
+/// Changes are only saved once the form comes back with `confirmed` set,
+/// after the user has seen what they change.
+async fn update_config(state: &AppState, form: &FormData) -> Result<Update, String> {
     let mut config = state.config.write().await;
+    let mut updated = config.clone();
+    apply_form(&mut updated, form)?;
+
+    // Validate
+    updated.validate().map_err(|e| e.to_string())?;
+
+    let changes = describe_changes(&config, &updated);
+    if !changes.is_empty() && !form.contains_key("confirmed") {
+        return Ok(Update::Confirm(changes));
+    }
+
+    // Save to file
+    updated.save(&state.config_path).map_err(|e| e.to_string())?;
+    state.status.set_device_name(updated.device_name());
+    let orientation_only = orientation_only_change(&config, &updated);
+    *config = updated;
 
+    tracing::info!("Configuration saved to {}", state.config_path);
+    Ok(Update::Saved { orientation_only })
+}
+
+/// Copy the form fields into `config`
+fn apply_form(config: &mut Config, form: &FormData) -> Result<(), String> {
     // Parse basic fields
+    config.device_name = get_form_field(form, "device_name", "").trim().to_string();
     config.image_url = get_form_field(form, "image_url", "").to_string();
     config.display_width = parse_form_field(form, "display_width", default_display_width());
     config.display_height = parse_form_field(form, "display_height", default_display_height());
@@ -220,18 +464,192 @@ async fn update_config(state: &AppState, form: &FormData) -> Result<(), String>
     config.mirror_h = form.contains_key("mirror_h");
     config.mirror_v = form.contains_key("mirror_v");
     config.scale_to_fit = form.contains_key("scale_to_fit");
+    config.smart_crop = form.contains_key("smart_crop");
+    config.rotate_packed = form.contains_key("rotate_packed");
 
     // Parse schedule plans and day assignments
-    let (plans, day_assignments) = parse_plans_from_form(form)?;
-    config.schedule_plans = plans;
-    config.day_assignments = day_assignments;
+    let schedule = parse_plans_from_form(form)?;
+    config.schedule_plans = schedule.plans;
+    config.day_assignments = schedule.day_assignments;
+    if let Some(alternate_weeks) = schedule.alternate_weeks {
+        config.alternate_weeks = alternate_weeks;
+    }
+    if let Some(vacation) = schedule.vacation {
+        config.vacation = vacation;
+    }
+    Ok(())
+}
 
-    // Validate
-    config.validate().map_err(|e| e.to_string())?;
+/// Whether `new` only turns or mirrors the content shown with `old`
+fn orientation_only_change(old: &Config, new: &Config) -> bool {
+    let reoriented = old.rotation != new.rotation
+        || old.rotate_first != new.rotate_first
+        || old.mirror_h != new.mirror_h
+        || old.mirror_v != new.mirror_v;
+    let same_content = old.image_url == new.image_url
+        && old.source == new.source
+        && old.scale_to_fit == new.scale_to_fit
+        && old.smart_crop == new.smart_crop
+        && old.color_profile == new.color_profile
+        && old.snap_threshold == new.snap_threshold
+        && old.rotate_packed == new.rotate_packed
+        && (old.display_width, old.display_height) == (new.display_width, new.display_height);
+    reoriented && same_content
+}
 
-    // Save to file
-    config.save(&state.config_path).map_err(|e| e.to_string())?;
+/// Human-readable list of what `new` changes compared to `old`
+///
+/// The image URL is not shown as it may contain an API key.
+fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let on_off = |b: bool| if b { "on" } else { "off" };
+    let order = |rotate_first: bool| {
+        if rotate_first {
+            "rotate then mirror"
+        } else {
+            "mirror then rotate"
+        }
+    };
+    let mut changes = Vec::new();
 
-    tracing::info!("Configuration saved to {}", state.config_path);
-    Ok(())
+    if old.device_name != new.device_name {
+        changes.push(format!("device name {}→{}", old.device_name(), new.device_name()));
+    }
+    if old.image_url != new.image_url {
+        changes.push("image URL".to_string());
+    }
+    if old.display_width != new.display_width {
+        changes.push(format!("width {}→{}", old.display_width, new.display_width));
+    }
+    if old.display_height != new.display_height {
+        changes.push(format!("height {}→{}", old.display_height, new.display_height));
+    }
+    if old.rotation != new.rotation {
+        changes.push(format!("rotation {}°→{}°", old.rotation, new.rotation));
+    }
+    if old.rotate_first != new.rotate_first {
+        changes.push(format!(
+            "transform order {}→{}",
+            order(old.rotate_first),
+            order(new.rotate_first)
+        ));
+    }
+    for (name, was, is) in [
+        ("mirror H", old.mirror_h, new.mirror_h),
+        ("mirror V", old.mirror_v, new.mirror_v),
+        ("scale to fit", old.scale_to_fit, new.scale_to_fit),
+        ("smart crop", old.smart_crop, new.smart_crop),
+        ("rotate after dithering", old.rotate_packed, new.rotate_packed),
+    ] {
+        if was != is {
+            changes.push(format!("{} {}→{}", name, on_off(was), on_off(is)));
+        }
+    }
+
+    for plan in &new.schedule_plans {
+        match old.get_plan(&plan.name) {
+            None => changes.push(format!("new plan {}: {}", plan.name, periods(plan))),
+            Some(before) if before.periods == plan.periods => {}
+            Some(before) if same_times(before, plan) => {
+                for (was, is) in before.periods.iter().zip(&plan.periods) {
+                    if was.interval_min != is.interval_min {
+                        changes.push(format!(
+                            "{} {}–{}: interval {}→{} min",
+                            plan.name,
+                            is.start_time,
+                            is.end_time,
+                            was.interval_min,
+                            is.interval_min
+                        ));
+                    }
+                }
+            }
+            Some(before) => changes.push(format!(
+                "{} periods: {} → {}",
+                plan.name,
+                periods(before),
+                periods(plan)
+            )),
+        }
+    }
+    for plan in &old.schedule_plans {
+        if new.get_plan(&plan.name).is_none() {
+            changes.push(format!("removed plan {}", plan.name));
+        }
+    }
+
+    for day in Weekday::all() {
+        let was = old.day_assignments.get(day);
+        let is = new.day_assignments.get(day);
+        if was != is {
+            changes.push(format!(
+                "{}: {}→{}",
+                day.short_name(),
+                was.map(String::as_str).unwrap_or("-"),
+                is.map(String::as_str).unwrap_or("-")
+            ));
+        }
+    }
+
+    let b_weeks = |config: &Config| {
+        config
+            .alternate_weeks
+            .as_ref()
+            .map_or("off".to_string(), |weeks| weeks.b_weeks.clone())
+    };
+    if b_weeks(old) != b_weeks(new) {
+        changes.push(format!("week B: {}→{}", b_weeks(old), b_weeks(new)));
+    }
+    let b_day = |config: &Config, day: &Weekday| {
+        config
+            .alternate_weeks
+            .as_ref()
+            .and_then(|weeks| weeks.day_assignments.get(day))
+            .map_or("as week A".to_string(), String::clone)
+    };
+    for day in Weekday::all() {
+        let (was, is) = (b_day(old, day), b_day(new, day));
+        if was != is {
+            changes.push(format!("{} (week B): {}→{}", day.short_name(), was, is));
+        }
+    }
+
+    let (was, is) = (&old.vacation, &new.vacation);
+    if was.enabled != is.enabled {
+        changes.push(format!(
+            "vacation mode {}→{}",
+            on_off(was.enabled),
+            on_off(is.enabled)
+        ));
+    }
+    if was.plan != is.plan {
+        let plan = |plan: &str| if plan.is_empty() { "-" } else { plan }.to_string();
+        changes.push(format!(
+            "vacation plan {}→{}",
+            plan(&was.plan),
+            plan(&is.plan)
+        ));
+    }
+    if was.image_url != is.image_url {
+        changes.push("vacation image URL".to_string());
+    }
+
+    changes
+}
+
+/// Whether both plans have the same period boundaries
+fn same_times(a: &SchedulePlan, b: &SchedulePlan) -> bool {
+    a.periods.len() == b.periods.len()
+        && a.periods
+            .iter()
+            .zip(&b.periods)
+            .all(|(x, y)| x.start_time == y.start_time && x.end_time == y.end_time)
+}
+
+/// Periods of a plan as "08:00–18:00 every 15 min, ..."
+fn periods(plan: &SchedulePlan) -> String {
+    plan.periods
+        .iter()
+        .map(|p| format!("{}–{} every {} min", p.start_time, p.end_time, p.interval_min))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
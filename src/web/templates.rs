@@ -2,13 +2,28 @@
 //!
 //! Embedded HTML templates for the configuration UI.
 
-use crate::config::{Config, SchedulePlan, Weekday};
+use super::auth::Role;
+use crate::arbiter::Priority;
+use crate::config::{Config, SchedulePlan, SchedulePreset, Weekday};
+use crate::history::HistoryEntry;
+use crate::logs::LogLine;
+use crate::status::StatusSnapshot;
+use std::collections::HashMap;
 
 /// Generate HTML for schedule plans data (as JSON for JavaScript)
 fn render_schedule_plans_json(plans: &[SchedulePlan]) -> String {
     serde_json::to_string(plans).unwrap_or_else(|_| "[]".to_string())
 }
 
+/// Preset plans offered for new plans (as JSON for JavaScript)
+fn render_presets_json() -> String {
+    let presets: Vec<SchedulePlan> = SchedulePreset::all()
+        .iter()
+        .map(|preset| preset.plan(preset.title()))
+        .collect();
+    render_schedule_plans_json(&presets)
+}
+
 /// Generate HTML for day assignments data (as JSON for JavaScript)
 fn render_day_assignments_json(config: &Config) -> String {
     let assignments: Vec<(&str, &str)> = Weekday::all()
@@ -25,6 +40,31 @@ fn render_day_assignments_json(config: &Config) -> String {
     serde_json::to_string(&assignments).unwrap_or_else(|_| "[]".to_string())
 }
 
+/// Alternate week settings with short day names (as JSON for JavaScript)
+fn render_alternate_weeks_json(config: &Config) -> String {
+    let (b_weeks, days) = match &config.alternate_weeks {
+        Some(weeks) => (weeks.b_weeks.as_str(), &weeks.day_assignments),
+        None => ("", &HashMap::new()),
+    };
+    let day_assignments: HashMap<&str, &str> = days
+        .iter()
+        .map(|(day, plan)| (day.short_name(), plan.as_str()))
+        .collect();
+    serde_json::json!({ "b_weeks": b_weeks, "day_assignments": day_assignments }).to_string()
+}
+
+/// Vacation mode settings (as JSON for JavaScript); the image URL is
+/// left out for viewers as it may carry an API key
+fn render_vacation_json(config: &Config, read_only: bool) -> String {
+    let vacation = &config.vacation;
+    serde_json::json!({
+        "enabled": vacation.enabled,
+        "plan": vacation.plan,
+        "image_url": if read_only { "" } else { vacation.image_url.as_str() },
+    })
+    .to_string()
+}
+
 /// Get the current active period info for display
 fn get_active_period_info(config: &Config) -> String {
     let weekday = Config::get_current_weekday();
@@ -32,31 +72,93 @@ fn get_active_period_info(config: &Config) -> String {
         .get_current_plan()
         .map(|p| p.name.as_str())
         .unwrap_or("None");
+    let weekday = if config.vacation.enabled {
+        format!("{} (vacation)", weekday.display_name())
+    } else if config.is_b_week(chrono::Local::now()) {
+        format!("{} (week B)", weekday.display_name())
+    } else {
+        weekday.display_name().to_string()
+    };
 
     if let Some(period) = config.get_current_period() {
         format!(
             "{} → '{}': {} - {} (every {} min)",
-            weekday.display_name(),
+            weekday,
             plan_name,
             period.start_time,
             period.end_time,
             period.interval_min
         )
     } else {
-        format!("{} → No active schedule", weekday.display_name())
+        format!("{} → No active schedule", weekday)
     }
 }
 
 /// Render the main configuration page
-pub fn render_config_page(config: &Config, status_message: Option<&str>) -> String {
+///
+/// Viewers get the same page read-only: controls disabled, no save or
+/// action buttons and no image URL (it may carry an API key).
+pub fn render_config_page(config: &Config, status_message: Option<&str>, role: Role) -> String {
     let status_html = status_message
         .map(|msg| format!(r#"<div class="alert">{}</div>"#, msg))
         .unwrap_or_default();
 
+    let read_only = role < Role::Admin;
+    let url_link = if read_only {
+        "<em>hidden</em>".to_string()
+    } else {
+        format!(
+            r#"<a href="{}" target="_blank" style="color: #1565c0;">{}</a>"#,
+            html_escape(&config.image_url),
+            truncate_url(&config.image_url, 60)
+        )
+    };
+    let url = if read_only {
+        String::new()
+    } else {
+        html_escape(&config.image_url)
+    };
+    let (save_buttons, actions_html) = if read_only {
+        ("", "")
+    } else {
+        (
+            r#"<div class="buttons">
+                <button type="submit" class="btn-primary">Save</button>
+                <button type="submit" formaction="/apply" class="btn-blue">Save &amp; Apply</button>
+            </div>"#,
+            r#"<hr>
+        <h3>Actions</h3>
+        <div class="actions">
+            <a href="/action/show"><button type="button" class="btn-orange">Refresh Now</button></a>
+            <a href="/action/test"><button type="button" class="btn-blue">Test Pattern</button></a>
+            <a href="/action/clear"><button type="button" class="btn-red">Clear Display</button></a>
+            <span id="busy" class="busy" hidden><span class="spinner"></span><span id="busyStage">Refreshing…</span></span>
+        </div>
+        <div id="override" class="override" hidden>
+            <span id="overrideText"></span>
+            <button type="button" class="btn-blue" id="resumeSchedule">Resume Now</button>
+        </div>
+        <p><a href="/logs">Service log</a></p>"#,
+        )
+    };
+
+    let peers_html = if config.peer_discovery {
+        r#"<details id="peers">
+            <summary>🖥️ Other displays on this network</summary>
+            <ul id="peerList"></ul>
+        </details>"#
+    } else {
+        ""
+    };
+
     let active_period = get_active_period_info(config);
     let current_interval = config.get_current_interval();
     let schedule_plans_json = render_schedule_plans_json(&config.schedule_plans);
     let day_assignments_json = render_day_assignments_json(config);
+    let alternate_weeks_json = render_alternate_weeks_json(config);
+    let vacation_json = render_vacation_json(config, read_only);
+    let is_b_week = config.is_b_week(chrono::Local::now());
+    let presets_json = render_presets_json();
 
     format!(
         r##"<!DOCTYPE html>
@@ -64,7 +166,7 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1">
-    <title>Pi Zero W ePaper Display</title>
+    <title>{device_name} – ePaper Display</title>
     <style>
         * {{ box-sizing: border-box; }}
         body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 0; padding: 20px; background: #f5f5f5; }}
@@ -72,6 +174,11 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         h1 {{ color: #333; margin-top: 0; }}
         h3 {{ color: #444; margin-top: 24px; margin-bottom: 12px; }}
         .status {{ background: #e3f2fd; padding: 16px; border-radius: 8px; margin-bottom: 20px; font-size: 14px; word-break: break-word; }}
+        .last-error {{ background: #ffebee; color: #c62828; padding: 8px 12px; border-radius: 8px; margin-top: 10px; }}
+        .last-error.resolved {{ background: #f5f5f5; color: #666; }}
+        .last-error[hidden] {{ display: none; }}
+        .restart-required {{ background: #fff3e0; padding: 8px 12px; border-radius: 8px; margin-top: 10px; }}
+        .restart-required[hidden] {{ display: none; }}
         .alert {{ background: #c8e6c9; padding: 12px; border-radius: 8px; margin-bottom: 16px; color: #2e7d32; }}
         label {{ display: block; margin-top: 16px; font-weight: 600; color: #555; }}
         input, select {{ width: 100%; padding: 10px; margin-top: 6px; border: 1px solid #ddd; border-radius: 8px; font-size: 15px; }}
@@ -89,7 +196,14 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         button:hover {{ opacity: 0.9; }}
         hr {{ border: none; border-top: 1px solid #eee; margin: 24px 0; }}
         .actions {{ display: flex; gap: 10px; flex-wrap: wrap; }}
+        .override {{ background: #fff3e0; padding: 12px; border-radius: 8px; margin-top: 12px; }}
+        .override[hidden] {{ display: none; }}
         .actions a {{ text-decoration: none; }}
+        button:disabled {{ opacity: 0.5; cursor: not-allowed; }}
+        .busy {{ display: inline-flex; align-items: center; gap: 8px; color: #555; font-size: 14px; }}
+        .busy[hidden] {{ display: none; }}
+        .spinner {{ width: 16px; height: 16px; border: 3px solid #ddd; border-top-color: #2196F3; border-radius: 50%; animation: spin 1s linear infinite; }}
+        @keyframes spin {{ to {{ transform: rotate(360deg); }} }}
         .help-text {{ color: #666; font-size: 13px; margin-top: 4px; }}
         textarea.url-input {{ width: 100%; padding: 10px; border: 1px solid #ddd; border-radius: 6px; box-sizing: border-box; font-family: inherit; font-size: 14px; resize: vertical; min-height: 80px; }}
         .row {{ display: flex; gap: 10px; }}
@@ -99,6 +213,7 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         .tab {{ padding: 8px 16px; cursor: pointer; border-radius: 8px 8px 0 0; background: #f0f0f0; font-weight: 500; font-size: 14px; }}
         .tab.active {{ background: #2196F3; color: white; }}
         .tab-add {{ background: #e8f5e9; color: #2e7d32; }}
+        select.tab-add {{ width: auto; margin: 0; padding: 8px 12px; border: none; }}
         .tab-content {{ display: none; padding: 16px; border: 1px solid #e0e0e0; border-top: none; border-radius: 0 0 8px 8px; }}
         .tab-content.active {{ display: block; }}
         /* Schedule table */
@@ -115,9 +230,14 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         .day-card.today {{ border-color: #4CAF50; background: #e8f5e9; }}
         .day-card .day-name {{ font-weight: 600; font-size: 13px; color: #333; margin-bottom: 6px; }}
         .day-card select {{ width: 100%; padding: 4px; font-size: 12px; border-radius: 4px; }}
+        .bulk-days {{ display: flex; gap: 8px; align-items: center; flex-wrap: wrap; margin-top: 8px; font-size: 13px; }}
+        .bulk-days select, .bulk-days input {{ width: auto; margin: 0; padding: 4px 8px; font-size: 13px; }}
+        .bulk-days label {{ display: flex; align-items: center; gap: 6px; margin: 0; }}
         .plan-name-input {{ margin-bottom: 12px; }}
         details {{ margin-top: 16px; }}
         details summary {{ cursor: pointer; font-weight: 600; color: #555; padding: 8px 0; }}
+        fieldset {{ border: none; padding: 0; margin: 0; min-width: 0; }}
+        .preview {{ max-width: 100%; border: 1px solid #ddd; border-radius: 8px; }}
         /* Footer */
         .footer {{ margin-top: 24px; padding-top: 16px; border-top: 1px solid #eee; text-align: center; font-size: 13px; color: #888; }}
         .footer a {{ color: #666; text-decoration: none; }}
@@ -129,11 +249,25 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         <h1>🖼️ Pi Zero W ePaper Display</h1>
         {status_html}
         <div class="status">
-            <strong>URL:</strong> <a href="{url}" target="_blank" style="color: #1565c0;">{url_display}</a><br>
+            <strong>Device:</strong> {device_name} ({hostname})<br>
+            <strong>Source:</strong> {source_kind} &nbsp;|&nbsp; <strong>URL:</strong> {url_link}<br>
             <strong>Active:</strong> {active_period} &nbsp;|&nbsp; <strong>Interval:</strong> {current_interval} min<br>
             <strong>Size:</strong> {display_width}×{display_height} &nbsp;|&nbsp; <strong>Rotation:</strong> {rotation}°
+            <div id="lastError" class="last-error" hidden></div>
+            <div id="restartRequired" class="restart-required" hidden></div>
         </div>
+        <details>
+            <summary>🖼️ Preview</summary>
+            <img src="/preview.png" class="preview" alt="Nothing displayed yet">
+            <p><a href="/history">Earlier frames</a></p>
+        </details>
+        {peers_html}
         <form method="POST" action="/save" id="configForm">
+            <fieldset {disabled}>
+            <label>Device name:</label>
+            <input type="text" name="device_name" value="{device_name_value}" maxlength="40" placeholder="{hostname}">
+            <div class="help-text">Tells displays apart in page titles, the status card and metrics. Empty uses the host name.</div>
+
             <label>Image URL:</label>
             <textarea name="image_url" class="url-input" rows="3" placeholder="https://example.com/image.png">{url}</textarea>
             <div class="help-text">Enter the full URL to the image. Long URLs (e.g., Grafana render URLs) are supported.</div>
@@ -142,6 +276,10 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
             <div class="help-text">Create named schedule plans and assign them to different days of the week.</div>
 
             <div class="day-grid" id="dayAssignments"></div>
+            <div class="bulk-days" id="bulkDays"></div>
+            <div class="bulk-days" id="weekB"></div>
+            <div class="day-grid" id="dayAssignmentsB"></div>
+            <div class="bulk-days" id="vacation"></div>
 
             <div class="tabs" id="planTabs"></div>
             <div id="planContents"></div>
@@ -172,25 +310,19 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
                 <label><input type="checkbox" name="mirror_h" {mirror_h}> Mirror H</label>
                 <label><input type="checkbox" name="mirror_v" {mirror_v}> Mirror V</label>
                 <label><input type="checkbox" name="scale_to_fit" {scale_to_fit}> Scale to Fit</label>
+                <label><input type="checkbox" name="smart_crop" {smart_crop}> Smart Crop</label>
+                <label><input type="checkbox" name="rotate_packed" {rotate_packed}> Rotate after Dithering</label>
             </div>
 
-            <div class="buttons">
-                <button type="submit" class="btn-primary">Save</button>
-                <button type="submit" formaction="/apply" class="btn-blue">Save &amp; Apply</button>
-            </div>
+            </fieldset>
+            {save_buttons}
         </form>
-        <hr>
-        <h3>Actions</h3>
-        <div class="actions">
-            <a href="/action/show"><button type="button" class="btn-orange">Refresh Now</button></a>
-            <a href="/action/test"><button type="button" class="btn-blue">Test Pattern</button></a>
-            <a href="/action/clear"><button type="button" class="btn-red">Clear Display</button></a>
-        </div>
+        {actions_html}
 
         <details>
             <summary>ℹ️ Help</summary>
             <div style="background:#fafafa;padding:16px;border-radius:8px;margin-top:8px;font-size:13px;">
-                <p><strong>Schedule Plans:</strong> Create named schedules (e.g., "Weekday", "Weekend") with different time periods. Assign plans to days of the week.</p>
+                <p><strong>Schedule Plans:</strong> Create named schedules (e.g., "Weekday", "Weekend") with different time periods. Assign plans to days of the week. Start a plan from a preset, or duplicate one to change it.</p>
                 <p><strong>Time Periods:</strong> Each plan must cover all 24 hours. Use 00:00-00:00 for a single all-day period.</p>
                 <p><strong>Display:</strong> Waveshare 7.3" E-Paper, 800×480, 6-color (Black, White, Red, Yellow, Blue, Green).</p>
             </div>
@@ -201,7 +333,12 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
     const DAY_NAMES = ['Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday', 'Sunday'];
     let plans = {schedule_plans_json};
     let dayAssignments = Object.fromEntries({day_assignments_json});
+    let weekB = {alternate_weeks_json};
+    const IS_B_WEEK = {is_b_week};
+    let vacation = {vacation_json};
+    const PRESETS = {presets_json};
     let activePlanIdx = 0;
+    let bulkPlan = plans[0].name;
 
     function renderAll() {{
         renderDayAssignments();
@@ -214,20 +351,97 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         const today = new Date().getDay();
         const todayIdx = today === 0 ? 6 : today - 1;
         container.innerHTML = DAYS.map((day, i) => `
-            <div class="day-card ${{i === todayIdx ? 'today' : ''}}">
+            <div class="day-card ${{i === todayIdx && !IS_B_WEEK ? 'today' : ''}}">
                 <div class="day-name">${{day}}</div>
-                <select name="day_${{day}}" onchange="dayAssignments['${{day}}']=this.value">
+                <select name="day_${{day}}" onchange="dayAssignments['${{day}}']=this.value;syncHiddenFields()">
                     ${{plans.map(p => `<option value="${{p.name}}" ${{dayAssignments[day]===p.name?'selected':''}}>${{p.name}}</option>`).join('')}}
                 </select>
             </div>
         `).join('');
+        const anchor = ['', 'odd', 'even'].includes(weekB.b_weeks) ? '' : weekB.b_weeks;
+        document.getElementById('weekB').innerHTML = `
+            Alternate weeks: <select onchange="weekB.b_weeks=this.value;renderDayAssignments();syncHiddenFields()">
+                <option value="" ${{weekB.b_weeks===''?'selected':''}}>Off (every week the same)</option>
+                <option value="odd" ${{weekB.b_weeks==='odd'?'selected':''}}>Week B in odd ISO weeks</option>
+                <option value="even" ${{weekB.b_weeks==='even'?'selected':''}}>Week B in even ISO weeks</option>
+                ${{anchor ? `<option value="${{anchor}}" selected>Week B every other week from ${{anchor}}</option>` : ''}}
+            </select>`;
+        document.getElementById('dayAssignmentsB').innerHTML = !weekB.b_weeks ? '' : DAYS.map((day, i) => `
+            <div class="day-card ${{i === todayIdx && IS_B_WEEK ? 'today' : ''}}">
+                <div class="day-name">${{day}} (B)</div>
+                <select onchange="setWeekBDay('${{day}}', this.value)">
+                    <option value="">As week A</option>
+                    ${{plans.map(p => `<option value="${{p.name}}" ${{weekB.day_assignments[day]===p.name?'selected':''}}>${{p.name}}</option>`).join('')}}
+                </select>
+            </div>
+        `).join('');
+        renderVacation();
+        if (!plans.find(p => p.name === bulkPlan)) bulkPlan = plans[0].name;
+        document.getElementById('bulkDays').innerHTML = `
+            Set <select onchange="bulkPlan=this.value">
+                ${{plans.map(p => `<option value="${{p.name}}" ${{p.name===bulkPlan?'selected':''}}>${{p.name}}</option>`).join('')}}
+            </select> for
+            <button type="button" class="btn-small preset-btn" onclick="assignDays(DAYS.slice(0, 5))">Mon–Fri</button>
+            <button type="button" class="btn-small preset-btn" onclick="assignDays(DAYS.slice(5))">Sat–Sun</button>
+            <button type="button" class="btn-small preset-btn" onclick="assignDays(DAYS)">All days</button>
+            <button type="button" class="btn-small preset-btn" onclick="copyWeek()">Copy week</button>
+            <button type="button" class="btn-small preset-btn" onclick="pasteWeek()">Paste week</button>`;
+    }}
+
+    function renderVacation() {{
+        document.getElementById('vacation').innerHTML = `
+            <label><input type="checkbox" ${{vacation.enabled?'checked':''}} onchange="vacation.enabled=this.checked;syncHiddenFields()"> Vacation mode:</label>
+            <select onchange="vacation.plan=this.value;syncHiddenFields()">
+                <option value="">Normal schedule</option>
+                ${{plans.map(p => `<option value="${{p.name}}" ${{vacation.plan===p.name?'selected':''}}>${{p.name}}</option>`).join('')}}
+            </select>
+            <input type="url" id="vacationImage" placeholder="Static image URL (optional)" onchange="vacation.image_url=this.value;syncHiddenFields()">`;
+        document.getElementById('vacationImage').value = vacation.image_url;
+    }}
+
+    function setWeekBDay(day, plan) {{
+        if (plan) weekB.day_assignments[day] = plan;
+        else delete weekB.day_assignments[day];
+        syncHiddenFields();
+    }}
+
+    function assignDays(days) {{
+        days.forEach(d => dayAssignments[d] = bulkPlan);
+        renderAll();
+    }}
+
+    // Same format as day_assignments in the config file and the API
+    function copyWeek() {{
+        const week = JSON.stringify(Object.fromEntries(DAYS.map((d, i) => [DAY_NAMES[i].toLowerCase(), dayAssignments[d]])));
+        if (navigator.clipboard && window.isSecureContext) {{
+            navigator.clipboard.writeText(week).then(() => alert('Day assignments copied.'));
+        }} else {{
+            prompt('Copy the day assignments:', week);
+        }}
+    }}
+
+    function pasteWeek() {{
+        const text = prompt('Paste day assignments copied from another display:');
+        if (!text) return;
+        let week;
+        try {{ week = JSON.parse(text); }} catch (e) {{ alert('Not valid day assignments.'); return; }}
+        const unknown = [];
+        DAYS.forEach((d, i) => {{
+            const plan = week[DAY_NAMES[i].toLowerCase()];
+            if (plan === undefined) return;
+            if (plans.find(p => p.name === plan)) dayAssignments[d] = plan;
+            else if (!unknown.includes(plan)) unknown.push(plan);
+        }});
+        renderAll();
+        if (unknown.length) alert('No plan named ' + unknown.join(', ') + ' here; those days were left as they were.');
     }}
 
     function renderTabs() {{
         const container = document.getElementById('planTabs');
         container.innerHTML = plans.map((p, i) =>
             `<div class="tab ${{i===activePlanIdx?'active':''}}" onclick="selectPlan(${{i}})">${{p.name}}</div>`
-        ).join('') + `<div class="tab tab-add" onclick="addPlan()">+ New Plan</div>`;
+        ).join('') + `<div class="tab tab-add" onclick="addPlan()">+ New Plan</div>`
+          + `<select class="tab tab-add" onchange="addPresetPlan(this.value)"><option value="">+ From Preset…</option>${{PRESETS.map((p, i) => `<option value="${{i}}">${{p.name}}</option>`).join('')}}</select>`;
     }}
 
     function renderPlanContent() {{
@@ -239,6 +453,7 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
                     <label style="display:inline;margin:0;">Plan Name:</label>
                     <input type="text" value="${{plan.name}}" style="width:200px;display:inline;margin-left:8px;"
                            onchange="renamePlan(${{pi}}, this.value)" ${{plans.length===1?'readonly':''}}>
+                    <button type="button" class="btn-small btn-blue" style="margin-left:8px;" onclick="duplicatePlan(${{pi}})">Duplicate</button>
                     ${{plans.length > 1 ? `<button type="button" class="btn-small btn-red" style="margin-left:8px;" onclick="deletePlan(${{pi}})">Delete Plan</button>` : ''}}
                 </div>
                 <table class="schedule-table">
@@ -278,12 +493,35 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         }} else if (name) {{ alert('Plan name already exists.'); }}
     }}
 
+    function unusedPlanName(base) {{
+        let name = base;
+        for (let n = 2; plans.find(p => p.name === name); n++) name = base + ' ' + n;
+        return name;
+    }}
+
+    function duplicatePlan(idx) {{
+        const plan = plans[idx];
+        plans.push({{ name: unusedPlanName(plan.name + ' copy'), periods: plan.periods.map(p => ({{ ...p }})) }});
+        activePlanIdx = plans.length - 1;
+        renderAll();
+    }}
+
+    function addPresetPlan(idx) {{
+        const preset = PRESETS[idx];
+        if (!preset) return;
+        plans.push({{ name: unusedPlanName(preset.name), periods: preset.periods.map(p => ({{ ...p }})) }});
+        activePlanIdx = plans.length - 1;
+        renderAll();
+    }}
+
     function renamePlan(idx, newName) {{
         if (!newName.trim()) return;
         const oldName = plans[idx].name;
         if (plans.find((p,i) => i !== idx && p.name === newName)) {{ alert('Name exists.'); return; }}
         plans[idx].name = newName;
         Object.keys(dayAssignments).forEach(d => {{ if (dayAssignments[d] === oldName) dayAssignments[d] = newName; }});
+        Object.keys(weekB.day_assignments).forEach(d => {{ if (weekB.day_assignments[d] === oldName) weekB.day_assignments[d] = newName; }});
+        if (vacation.plan === oldName) vacation.plan = newName;
         renderAll();
     }}
 
@@ -292,6 +530,8 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         const name = plans[idx].name;
         const fallback = plans.find((p,i) => i !== idx).name;
         Object.keys(dayAssignments).forEach(d => {{ if (dayAssignments[d] === name) dayAssignments[d] = fallback; }});
+        Object.keys(weekB.day_assignments).forEach(d => {{ if (weekB.day_assignments[d] === name) delete weekB.day_assignments[d]; }});
+        if (vacation.plan === name) vacation.plan = '';
         plans.splice(idx, 1);
         activePlanIdx = Math.min(activePlanIdx, plans.length - 1);
         renderAll();
@@ -324,11 +564,126 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         if (existing) existing.remove();
         const input = document.createElement('input');
         input.type = 'hidden'; input.name = 'plans_json'; input.id = 'plansData';
-        input.value = JSON.stringify({{ plans: plans, day_assignments: dayAssignments }});
+        input.value = JSON.stringify({{ plans: plans, day_assignments: dayAssignments, alternate_weeks: weekB, vacation: vacation }});
         document.getElementById('configForm').appendChild(input);
     }}
 
     renderAll();
+
+    // Disable refresh-triggering buttons while the panel is being updated
+    const STAGES = {{ fetching: 'Fetching image…', processing: 'Processing…', displaying: 'Updating panel…' }};
+    let pending = false;
+    let busy = false;
+
+    function setBusy(on, stage) {{
+        busy = on;
+        document.querySelectorAll('.actions button, #resumeSchedule, button[formaction="/apply"]').forEach(b => b.disabled = on);
+        const indicator = document.getElementById('busy');
+        if (!indicator) return;
+        indicator.hidden = !on;
+        document.getElementById('busyStage').textContent = STAGES[stage] || 'Refreshing…';
+    }}
+
+    document.querySelectorAll('.actions a').forEach(a => a.addEventListener('click', e => {{
+        if (busy) {{ e.preventDefault(); return; }}
+        pending = true;
+        setBusy(true, null);
+    }}));
+    document.getElementById('configForm').addEventListener('submit', e => {{
+        if (!e.submitter || e.submitter.getAttribute('formaction') !== '/apply') return;
+        if (busy) {{ e.preventDefault(); return; }}
+        pending = true;
+        // Disable after the submitter has been added to the form data
+        setTimeout(() => setBusy(true, null));
+    }});
+
+    // What overrides the schedule, and the countdown until a message ends
+    const override = document.getElementById('override');
+    function showOverride(priority, secs) {{
+        if (!override) return;
+        override.hidden = !priority;
+        if (!priority) return;
+        const alert = priority === 'alert';
+        const min = Math.ceil((secs || 0) / 60);
+        const left = min >= 60 ? Math.floor(min / 60) + ' h ' + (min % 60) + ' min' : min + ' min';
+        document.getElementById('overrideText').textContent = alert
+            ? 'An alert is on the panel until it is resolved.'
+            : 'A message is on the panel; the schedule resumes in ' + left + '.';
+        document.getElementById('resumeSchedule').hidden = alert;
+    }}
+    if (override) document.getElementById('resumeSchedule').addEventListener('click', async () => {{
+        if (busy) return;
+        pending = true;
+        setBusy(true, null);
+        try {{ await fetch('/api/v1/message', {{ method: 'DELETE' }}); }} catch (e) {{}}
+        pending = false;
+        pollStatus();
+    }});
+
+    // Last failed update, in red until a later refresh succeeds
+    const FAILED_STAGES = {{ fetching: 'fetching the image', processing: 'processing', displaying: 'updating the panel' }};
+    function showLastError(error, lastRefresh) {{
+        const box = document.getElementById('lastError');
+        box.hidden = !error;
+        if (!error) return;
+        const at = new Date(error.at);
+        const resolved = !!lastRefresh && new Date(lastRefresh) > at;
+        box.classList.toggle('resolved', resolved);
+        box.textContent = (resolved ? 'Last error' : '⚠️ Last refresh failed') + ' at ' + at.toLocaleString()
+            + ' while ' + (FAILED_STAGES[error.stage] || error.stage) + ': ' + error.message;
+    }}
+
+    function showRestartRequired(settings) {{
+        const box = document.getElementById('restartRequired');
+        box.hidden = !settings || !settings.length;
+        if (box.hidden) return;
+        box.textContent = '🔄 Restart required: the config file changes ' + settings.join(', ')
+            + '; the running values stay in effect until then';
+    }}
+
+    // Other displays, searched for each time the list is opened
+    function peerItem(text) {{
+        const item = document.createElement('li');
+        item.textContent = text;
+        return item;
+    }}
+    const peers = document.getElementById('peers');
+    if (peers) {{
+        peers.addEventListener('toggle', async () => {{
+            if (!peers.open) return;
+            const list = document.getElementById('peerList');
+            list.replaceChildren(peerItem('Searching…'));
+            try {{
+                const response = await fetch('/api/v1/peers');
+                const found = response.ok ? await response.json() : [];
+                const items = found.map(peer => {{
+                    const item = peerItem(' (' + peer.host + ', version ' + peer.version + ')');
+                    const link = document.createElement('a');
+                    link.href = peer.url;
+                    link.textContent = peer.name;
+                    item.prepend(link);
+                    return item;
+                }});
+                list.replaceChildren(...(items.length ? items : [peerItem('No other displays found')]));
+            }} catch (e) {{
+                list.replaceChildren(peerItem('Search failed'));
+            }}
+        }});
+    }}
+
+    async function pollStatus() {{
+        try {{
+            const response = await fetch('/api/v1/status');
+            if (!response.ok) return;
+            const status = await response.json();
+            setBusy(pending || status.refreshing, status.refresh_stage);
+            showOverride(status.override_priority, status.override_remaining_secs);
+            showLastError(status.last_error, status.last_refresh_at);
+            showRestartRequired(status.restart_required);
+        }} catch (e) {{}}
+    }}
+    pollStatus();
+    setInterval(pollStatus, 3000);
     </script>
     <div class="footer">
         <a href="https://github.com/bolausson/RPiZeroW-ePaper-Display" target="_blank">🔗 GitHub Repository</a>
@@ -336,10 +691,22 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
 </body>
 </html>"##,
         status_html = status_html,
-        url = html_escape(&config.image_url),
-        url_display = truncate_url(&config.image_url, 60),
+        peers_html = peers_html,
+        device_name = html_escape(&config.device_name()),
+        device_name_value = html_escape(&config.device_name),
+        hostname = html_escape(&crate::network::hostname()),
+        source_kind = config.source.kind_name(),
+        url = url,
+        url_link = url_link,
+        disabled = if read_only { "disabled" } else { "" },
+        save_buttons = save_buttons,
+        actions_html = actions_html,
         schedule_plans_json = schedule_plans_json,
         day_assignments_json = day_assignments_json,
+        alternate_weeks_json = alternate_weeks_json,
+        is_b_week = is_b_week,
+        vacation_json = vacation_json,
+        presets_json = presets_json,
         active_period = active_period,
         current_interval = current_interval,
         display_width = config.display_width,
@@ -354,9 +721,313 @@ pub fn render_config_page(config: &Config, status_message: Option<&str>) -> Stri
         mirror_h = checked_if(config.mirror_h),
         mirror_v = checked_if(config.mirror_v),
         scale_to_fit = checked_if(config.scale_to_fit),
+        smart_crop = checked_if(config.smart_crop),
+        rotate_packed = checked_if(config.rotate_packed),
+    )
+}
+
+/// Render the confirmation page listing what a submitted form changes
+///
+/// The submitted fields are carried over as hidden inputs, so confirming
+/// re-posts exactly what was reviewed.
+pub fn render_confirm_page(
+    changes: &[String],
+    form: &HashMap<String, String>,
+    action: &str,
+) -> String {
+    let changes_html: String = changes
+        .iter()
+        .map(|c| format!("<li>{}</li>", html_escape(c)))
+        .collect();
+    let hidden_html: String = form
+        .iter()
+        .filter(|(name, _)| name.as_str() != "confirmed")
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                html_escape(name),
+                html_escape(value)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="UTF-8"><title>Confirm changes</title>
+<style>body{{font-family:sans-serif;padding:20px;}}
+.msg{{background:#fff3e0;padding:20px;border-radius:8px;max-width:500px;}}
+button{{padding:10px 20px;border:none;border-radius:8px;font-size:15px;cursor:pointer;font-weight:600;background:#4CAF50;color:white;}}
+a{{color:#2196F3;margin-left:16px;}}</style></head>
+<body><div class="msg"><h2>Confirm changes</h2><p>You are changing:</p><ul>{changes_html}</ul>
+<form method="POST" action="{action}">{hidden_html}<input type="hidden" name="confirmed" value="1">
+<button type="submit">Confirm</button><a href="/">Cancel</a></form></div></body></html>"#,
+        changes_html = changes_html,
+        action = action,
+        hidden_html = hidden_html,
+    )
+}
+
+/// Render the public status card
+///
+/// Shows only status information, never config values. Reloads itself
+/// every minute.
+pub fn render_status_card(status: &StatusSnapshot, model: &str) -> String {
+    let source_html = match &status.source_health {
+        Some(health) if health.healthy => "Reachable".to_string(),
+        Some(health) => match health.http_status {
+            Some(code) => format!("Unreachable (HTTP {})", code),
+            None => "Unreachable".to_string(),
+        },
+        None => "Not checked yet".to_string(),
+    };
+    let override_html = match (status.override_priority, status.override_until.as_deref()) {
+        (Some(Priority::Alert), _) => "<tr><td>Showing</td><td>Alert</td></tr>\n".to_string(),
+        (Some(Priority::Manual), until) => format!(
+            "<tr><td>Message shown until</td><td>{}</td></tr>\n",
+            format_time(until)
+        ),
+        (None, _) => String::new(),
+    };
+    let clock_html = if status.clock_synchronized {
+        ""
+    } else {
+        r#"<div class="warn">System clock not synchronized; refresh times may be wrong and the schedule is paused.</div>"#
+    };
+    let mock_html = if status.mock_display {
+        r#"<div class="warn">Mock display: frames are rendered for the preview only, the panel is not driven.</div>"#
+    } else {
+        ""
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="UTF-8"><meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="60"><title>{device_name} – ePaper Display Status</title>
+<style>body{{font-family:sans-serif;padding:20px;background:#f5f5f5;}}
+.card{{background:white;padding:20px;border-radius:8px;max-width:440px;box-shadow:0 2px 4px rgba(0,0,0,0.1);}}
+.card img{{max-width:100%;border:1px solid #ddd;border-radius:4px;}}
+table{{border-collapse:collapse;width:100%;margin-top:12px;}}
+td{{padding:4px 0;}}td:first-child{{color:#666;width:45%;}}
+.warn{{background:#fff3e0;padding:8px 12px;border-radius:8px;margin-bottom:12px;}}</style></head>
+<body><div class="card"><h2>{device_name}</h2>{mock_html}{clock_html}
+<img src="/status/thumbnail.png" alt="No image displayed yet">
+<table>
+<tr><td>Last refresh</td><td>{last_refresh}</td></tr>
+<tr><td>Next refresh</td><td>{next_refresh}</td></tr>
+{override_html}<tr><td>Refreshes today</td><td>{refreshes_today}</td></tr>
+<tr><td>Image source</td><td>{source_html}</td></tr>
+<tr><td>Panel</td><td>{model}</td></tr>
+<tr><td>Uptime</td><td>{uptime}</td></tr>
+<tr><td>Version</td><td>{version}</td></tr>
+</table></div></body></html>"#,
+        device_name = html_escape(&status.device_name),
+        last_refresh = format_time(status.last_refresh_at.as_deref()),
+        next_refresh = format_time(status.next_refresh_at.as_deref()),
+        override_html = override_html,
+        refreshes_today = status.refreshes_today,
+        source_html = source_html,
+        clock_html = clock_html,
+        mock_html = mock_html,
+        model = html_escape(model),
+        uptime = format_uptime(status.uptime_secs),
+        version = status.version,
+    )
+}
+
+/// Render the gallery of previously displayed frames
+///
+/// `keep` is the configured `history_frames`; 0 means no history is kept.
+pub fn render_history_page(entries: &[HistoryEntry], keep: u32) -> String {
+    let mut body = String::new();
+    let mut day = None;
+    for entry in entries {
+        let date = entry.displayed_at.date();
+        if day != Some(date) {
+            if day.is_some() {
+                body.push_str("</div>");
+            }
+            body.push_str(&format!(
+                r#"<h3>{}</h3><div class="day">"#,
+                date.format("%A, %Y-%m-%d")
+            ));
+            day = Some(date);
+        }
+        body.push_str(&format!(
+            r#"<figure><a href="/history/{name}" target="_blank"><img src="/history/{name}" loading="lazy" alt=""></a><figcaption>{time}</figcaption></figure>"#,
+            name = html_escape(&entry.name),
+            time = entry.displayed_at.format("%H:%M:%S"),
+        ));
+    }
+    if day.is_some() {
+        body.push_str("</div>");
+    }
+
+    let summary = match (keep, entries.len()) {
+        (0, 0) => "History is off. Set <code>history_frames</code> in the config to keep displayed frames.".to_string(),
+        (0, n) => format!("History is off; {} frames from earlier are still stored.", n),
+        (_, 0) => "No frames stored yet.".to_string(),
+        (keep, n) => format!("{} of up to {} frames, newest first.", n, keep),
+    };
+    let timelapse_html = if cfg!(feature = "timelapse") && !entries.is_empty() {
+        r#"<p><a href="/api/v1/history/timelapse.gif">Download as timelapse GIF</a></p>"#
+    } else {
+        ""
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="UTF-8"><meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Display History</title>
+<style>body{{font-family:sans-serif;padding:20px;background:#f5f5f5;}}
+.day{{display:flex;flex-wrap:wrap;gap:12px;}}
+figure{{margin:0;background:white;padding:8px;border-radius:8px;box-shadow:0 2px 4px rgba(0,0,0,0.1);}}
+figure img{{width:240px;display:block;border:1px solid #ddd;}}
+figcaption{{color:#666;font-size:13px;margin-top:4px;}}
+a{{color:#2196F3;}}</style></head>
+<body><h2>Display History</h2><p>{summary}</p>{timelapse_html}{body}
+<p><a href="/">← Back to configuration</a></p></body></html>"#,
+        summary = summary,
+        timelapse_html = timelapse_html,
+        body = body,
     )
 }
 
+/// Levels offered by the log viewer, most severe first
+const LOG_LEVELS: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+
+/// Render the log viewer
+///
+/// `lines` are already filtered to `level` (None = all) and cut to the
+/// newest `limit`. "Follow" streams new lines from `/api/v1/logs` and
+/// applies the same filter in the browser.
+pub fn render_logs_page(lines: &[LogLine], level: Option<tracing::Level>, limit: usize) -> String {
+    let mut rows = String::new();
+    for line in lines {
+        rows.push_str(&format!(
+            r#"<tr class="{class}"><td>{time}</td><td>{level}</td><td>{message}</td></tr>"#,
+            class = line.level.to_lowercase(),
+            time = format_log_time(&line.timestamp),
+            level = html_escape(&line.level),
+            message = html_escape(&line.message),
+        ));
+    }
+    let summary = if lines.is_empty() {
+        "No matching lines recorded.".to_string()
+    } else {
+        format!("Last {} lines, oldest first.", lines.len())
+    };
+
+    let selected = level.map(|l| l.as_str());
+    let mut level_options = format!(
+        r#"<option value="" {}>All</option>"#,
+        selected_if(selected.is_none())
+    );
+    for name in LOG_LEVELS {
+        level_options.push_str(&format!(
+            r#"<option value="{value}" {selected}>{name}</option>"#,
+            value = name.to_lowercase(),
+            selected = selected_if(selected == Some(name)),
+            name = name,
+        ));
+    }
+    // Index of the least severe level shown, for the filter in the browser
+    let max_level = selected
+        .and_then(|s| LOG_LEVELS.iter().position(|l| *l == s))
+        .unwrap_or(LOG_LEVELS.len() - 1);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html><head><meta charset="UTF-8"><meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Service Log</title>
+<style>body{{font-family:sans-serif;padding:20px;background:#f5f5f5;}}
+form{{margin-bottom:12px;}}
+table{{border-collapse:collapse;background:white;width:100%;font-family:monospace;font-size:13px;}}
+td{{padding:2px 8px;border-bottom:1px solid #eee;vertical-align:top;white-space:pre-wrap;}}
+td:first-child{{white-space:nowrap;color:#666;}}
+tr.error td:nth-child(2){{color:#c62828;font-weight:bold;}}
+tr.warn td:nth-child(2){{color:#ef6c00;font-weight:bold;}}
+tr.debug td,tr.trace td{{color:#888;}}
+a{{color:#2196F3;}}</style></head>
+<body><h2>Service Log</h2>
+<form method="GET" action="/logs">
+<label>Level: <select name="level">{level_options}</select></label>
+<label>Lines: <input type="number" name="lines" value="{limit}" min="1" max="{max_lines}" style="width:5em"></label>
+<button type="submit">Show</button>
+<label><input type="checkbox" id="follow"> Follow</label>
+</form>
+<p id="summary">{summary}</p>
+<table><tbody id="lines">{rows}</tbody></table>
+<p><a href="/">← Back to configuration</a></p>
+<script>
+const LEVELS = {levels_json};
+const MAX_LEVEL = {max_level};
+const LIMIT = {limit};
+let source = null;
+
+function formatTime(timestamp) {{
+    return timestamp.slice(5, 10) + ' ' + timestamp.slice(11, 19);
+}}
+
+document.getElementById('follow').addEventListener('change', e => {{
+    if (source) {{
+        source.close();
+        source = null;
+    }}
+    if (!e.target.checked) return;
+    // The stream starts with the recorded lines, so start over
+    const body = document.getElementById('lines');
+    body.textContent = '';
+    document.getElementById('summary').textContent = 'Following new lines…';
+    source = new EventSource('/api/v1/logs?follow=true');
+    source.addEventListener('log', ev => {{
+        const line = JSON.parse(ev.data);
+        const index = LEVELS.indexOf(line.level);
+        if (index < 0 || index > MAX_LEVEL) return;
+        const row = body.insertRow();
+        row.className = line.level.toLowerCase();
+        for (const text of [formatTime(line.timestamp), line.level, line.message]) {{
+            row.insertCell().textContent = text;
+        }}
+        while (body.rows.length > LIMIT) body.deleteRow(0);
+        window.scrollTo(0, document.body.scrollHeight);
+    }});
+}});
+</script></body></html>"#,
+        level_options = level_options,
+        limit = limit,
+        max_lines = crate::logs::CAPACITY,
+        summary = summary,
+        rows = rows,
+        levels_json = serde_json::to_string(&LOG_LEVELS).unwrap_or_default(),
+        max_level = max_level,
+    )
+}
+
+/// Time of a log line as shown in the log viewer (`MM-DD HH:MM:SS`)
+fn format_log_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.format("%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| html_escape(timestamp))
+}
+
+/// Local RFC 3339 time as shown on the status card
+fn format_time(time: Option<&str>) -> String {
+    time.and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "—".to_string())
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
 /// Render a simple message page
 pub fn render_message_page(title: &str, message: &str, back_link: bool) -> String {
     let back_html = if back_link {
@@ -401,3 +1072,19 @@ fn truncate_url(url: &str, max_len: usize) -> String {
         format!("{}…", &escaped[..max_len])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::StatusTracker;
+
+    #[test]
+    fn status_card_flags_the_mock_display() {
+        let status = StatusTracker::default();
+        let card = |status: &StatusTracker| render_status_card(&status.snapshot(), "mock");
+        assert!(!card(&status).contains("Mock display"));
+        status.set_mock_display(true);
+        assert!(status.snapshot().mock_display);
+        assert!(card(&status).contains("Mock display"));
+    }
+}